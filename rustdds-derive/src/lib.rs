@@ -0,0 +1,691 @@
+//! Derive macros for RustDDS. These are re-exported from the `rustdds` crate
+//! and should be used from there.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+  ext::IdentExt, parse::ParseStream, parse_macro_input, parse_quote, punctuated::Punctuated,
+  spanned::Spanned, Attribute, Data, DeriveInput, Error, Expr, Field, Fields, Ident, Index, Lit,
+  LitInt, Meta, MetaNameValue, NestedMeta, Result, Token, Type,
+};
+
+/// Derives `rustdds::dds::xtypes::TypeSupport`. See the trait for the
+/// supported `#[xtypes(..)]` attributes. `#[key]` is the same as
+/// `#[xtypes(key)]`.
+#[proc_macro_derive(TypeSupport, attributes(xtypes, key))]
+pub fn derive_type_support(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match type_support(input) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+/// Derives `rustdds::dds::traits::Keyed` for a structure, with the fields
+/// marked `#[key]` (or `#[xtypes(key)]`) as the key. Also derives
+/// `rustdds::dds::traits::KeyMember`, so that the structure can be a key
+/// member of another structure.
+#[proc_macro_derive(Keyed, attributes(key))]
+pub fn derive_keyed(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match keyed(input) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+/// Derives `rustdds::dds::traits::Key` and `rustdds::dds::traits::KeyMember`
+/// for a structure or enum whose values are keys as a whole.
+#[proc_macro_derive(Key)]
+pub fn derive_key(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match key(input) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+/// Derives `rustdds::serialization::PlCdrType` for a structure with named
+/// fields, each marked with its ParameterId, e.g. `#[parameter_id = 0x0005]`.
+#[proc_macro_derive(PlCdrType, attributes(parameter_id))]
+pub fn derive_pl_cdr_type(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match pl_cdr_type(input) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+/// Derives `serde::Serialize` and `serde::Deserialize` for an enum that is an
+/// IDL union. The discriminator type is given with `#[discriminator(T)]`,
+/// and the labels of each variant with `#[case(..)]`.
+#[proc_macro_derive(IdlUnion, attributes(discriminator, case))]
+pub fn derive_idl_union(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  match idl_union(input) {
+    Ok(tokens) => tokens.into(),
+    Err(e) => e.to_compile_error().into(),
+  }
+}
+
+// Contents of one #[xtypes(..)] attribute item
+enum XTypesArg {
+  Flag(Ident),
+  Id(Ident, LitInt),
+}
+
+fn parse_xtypes_arg(input: ParseStream) -> Result<XTypesArg> {
+  // "final" is a keyword, so plain Ident parsing would reject it.
+  let name = input.call(Ident::parse_any)?;
+  if input.peek(Token![=]) {
+    input.parse::<Token![=]>()?;
+    Ok(XTypesArg::Id(name, input.parse()?))
+  } else {
+    Ok(XTypesArg::Flag(name))
+  }
+}
+
+fn xtypes_args(attrs: &[Attribute]) -> Result<Vec<XTypesArg>> {
+  let mut args = Vec::new();
+  for attr in attrs.iter().filter(|a| a.path.is_ident("xtypes")) {
+    args.extend(attr.parse_args_with(|input: ParseStream| {
+      Punctuated::<XTypesArg, Token![,]>::parse_terminated_with(input, parse_xtypes_arg)
+    })?);
+  }
+  Ok(args)
+}
+
+fn extensibility(attrs: &[Attribute]) -> Result<TokenStream2> {
+  let mut extensibility = quote!(Appendable);
+  for arg in xtypes_args(attrs)? {
+    match arg {
+      XTypesArg::Flag(f) if f == "final" => extensibility = quote!(Final),
+      XTypesArg::Flag(f) if f == "appendable" => extensibility = quote!(Appendable),
+      XTypesArg::Flag(f) if f == "mutable" => extensibility = quote!(Mutable),
+      XTypesArg::Flag(f) | XTypesArg::Id(f, _) => {
+        return Err(Error::new(
+          f.span(),
+          "expected `final`, `appendable` or `mutable`",
+        ))
+      }
+    }
+  }
+  Ok(quote!(::rustdds::dds::xtypes::Extensibility::#extensibility))
+}
+
+fn type_support(mut input: DeriveInput) -> Result<TokenStream2> {
+  let xtypes = quote!(::rustdds::dds::xtypes);
+  let extensibility = extensibility(&input.attrs)?;
+
+  let body = match &input.data {
+    Data::Struct(data) => match &data.fields {
+      Fields::Named(fields) => {
+        let mut next_id: u32 = 0;
+        let mut members = Vec::new();
+        for field in &fields.named {
+          let mut key = has_key_attribute(field)?;
+          for arg in xtypes_args(&field.attrs)? {
+            match arg {
+              XTypesArg::Flag(f) if f == "key" => key = true,
+              XTypesArg::Id(f, id) if f == "id" => next_id = id.base10_parse()?,
+              XTypesArg::Flag(f) | XTypesArg::Id(f, _) => {
+                return Err(Error::new(f.span(), "expected `key` or `id = ..`"))
+              }
+            }
+          }
+          let member_id = next_id;
+          next_id += 1;
+          let name = field.ident.as_ref().unwrap().unraw().to_string();
+          let ty = &field.ty;
+          members.push(quote_spanned! {field.span()=>
+            #xtypes::StructMember {
+              member_id: #member_id,
+              member_type: <#ty as #xtypes::TypeSupport>::type_identifier(type_objects),
+              name_hash: #xtypes::name_hash(#name),
+              optional: <#ty as #xtypes::TypeSupport>::is_optional(),
+              must_understand: #key,
+              key: #key,
+            }
+          });
+        }
+        quote! {
+          let members = vec![ #(#members),* ];
+          type_objects.add(#xtypes::TypeObject::Struct(#xtypes::StructType {
+            extensibility: #extensibility,
+            base_type: None,
+            members,
+          }))
+        }
+      }
+      Fields::Unit => quote! {
+        type_objects.add(#xtypes::TypeObject::Struct(#xtypes::StructType {
+          extensibility: #extensibility,
+          base_type: None,
+          members: Vec::new(),
+        }))
+      },
+      Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+        let ty = &fields.unnamed[0].ty;
+        quote! {
+          let related_type = <#ty as #xtypes::TypeSupport>::type_identifier(type_objects);
+          type_objects.add(#xtypes::TypeObject::Alias { related_type })
+        }
+      }
+      Fields::Unnamed(fields) => {
+        return Err(Error::new(
+          fields.span(),
+          "TypeSupport cannot be derived for tuple structs with more than one field",
+        ))
+      }
+    },
+    Data::Enum(data) => {
+      let mut literals = Vec::new();
+      // Serde encodes enum values as variant indices.
+      for (index, variant) in data.variants.iter().enumerate() {
+        if !variant.fields.is_empty() {
+          return Err(Error::new(
+            variant.span(),
+            "TypeSupport can be derived only for enums without fields",
+          ));
+        }
+        let name = variant.ident.unraw().to_string();
+        let value = index as i32;
+        let default = index == 0;
+        literals.push(quote! {
+          #xtypes::EnumLiteral {
+            value: #value,
+            name_hash: #xtypes::name_hash(#name),
+            default: #default,
+          }
+        });
+      }
+      quote! {
+        type_objects.add(#xtypes::TypeObject::Enum(#xtypes::EnumType {
+          extensibility: #extensibility,
+          bit_bound: 32,
+          literals: vec![ #(#literals),* ],
+        }))
+      }
+    }
+    Data::Union(data) => {
+      return Err(Error::new(
+        data.union_token.span(),
+        "TypeSupport cannot be derived for Rust unions",
+      ))
+    }
+  };
+
+  let type_params: Vec<Ident> = input
+    .generics
+    .type_params()
+    .map(|tp| tp.ident.clone())
+    .collect();
+  let where_clause = input.generics.make_where_clause();
+  for tp in type_params {
+    where_clause
+      .predicates
+      .push(parse_quote!(#tp: #xtypes::TypeSupport));
+  }
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  Ok(quote! {
+    impl #impl_generics #xtypes::TypeSupport for #name #ty_generics #where_clause {
+      fn type_identifier(type_objects: &mut #xtypes::TypeObjects) -> #xtypes::TypeIdentifier {
+        #body
+      }
+    }
+  })
+}
+
+fn has_key_attribute(field: &Field) -> Result<bool> {
+  let mut key = false;
+  for attr in field.attrs.iter().filter(|a| a.path.is_ident("key")) {
+    if !attr.tokens.is_empty() {
+      return Err(Error::new(attr.tokens.span(), "expected `#[key]`"));
+    }
+    key = true;
+  }
+  Ok(key)
+}
+
+// Rust implements comparison and hashing traits for tuples of up to 12
+// elements.
+const MAX_KEY_FIELDS: usize = 12;
+
+fn keyed(mut input: DeriveInput) -> Result<TokenStream2> {
+  let traits = quote!(::rustdds::dds::traits);
+  let fields = match &input.data {
+    Data::Struct(data) => &data.fields,
+    _ => {
+      return Err(Error::new(
+        input.ident.span(),
+        "Keyed can be derived only for structs",
+      ))
+    }
+  };
+
+  let mut field_types = Vec::new();
+  let mut key_types = Vec::new();
+  let mut keys = Vec::new();
+  for (index, field) in fields.iter().enumerate() {
+    let xtypes_key = xtypes_args(&field.attrs)?
+      .iter()
+      .any(|arg| matches!(arg, XTypesArg::Flag(f) if f == "key"));
+    if !has_key_attribute(field)? && !xtypes_key {
+      continue;
+    }
+    let member = match &field.ident {
+      Some(ident) => quote!(#ident),
+      None => {
+        let index = Index::from(index);
+        quote!(#index)
+      }
+    };
+    let ty = &field.ty;
+    field_types.push(ty.clone());
+    key_types.push(quote_spanned!(field.span()=> <#ty as #traits::KeyMember>::Key));
+    keys.push(quote_spanned!(field.span()=> #traits::KeyMember::key(&self.#member)));
+  }
+
+  // A single key member is the key as it is, several are a tuple.
+  let (key_type, key) = match keys.len() {
+    0 => {
+      return Err(Error::new(
+        input.ident.span(),
+        "no #[key] fields. Data types without a key are used with no_key topics.",
+      ))
+    }
+    1 => (key_types.pop().unwrap(), keys.pop().unwrap()),
+    n if n > MAX_KEY_FIELDS => {
+      return Err(Error::new(
+        input.ident.span(),
+        format!("at most {} fields can be #[key] fields", MAX_KEY_FIELDS),
+      ))
+    }
+    _ => (quote!(( #(#key_types),* )), quote!(( #(#keys),* ))),
+  };
+
+  // Bounds are needed only for generic key fields. Adding them for others
+  // would also put private types in the public interface.
+  if input.generics.type_params().next().is_some() {
+    let where_clause = input.generics.make_where_clause();
+    for ty in field_types {
+      where_clause
+        .predicates
+        .push(parse_quote!(#ty: #traits::KeyMember));
+    }
+  }
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  Ok(quote! {
+    impl #impl_generics #traits::Keyed for #name #ty_generics #where_clause {
+      type K = #key_type;
+      fn get_key(&self) -> Self::K {
+        #key
+      }
+    }
+
+    impl #impl_generics #traits::KeyMember for #name #ty_generics #where_clause {
+      type Key = #key_type;
+      fn key(&self) -> Self::Key {
+        #key
+      }
+    }
+  })
+}
+
+fn key(mut input: DeriveInput) -> Result<TokenStream2> {
+  let traits = quote!(::rustdds::dds::traits);
+  // The key hash can be the key itself only if its size is fixed.
+  let fixed_size = match &input.data {
+    Data::Struct(data) => {
+      let types = data.fields.iter().map(|f| &f.ty);
+      quote!(true #(&& <#types as #traits::Key>::is_fixed_size())*)
+    }
+    // Serde encodes fieldless enums as variant indices.
+    Data::Enum(data) if data.variants.iter().all(|v| v.fields.is_empty()) => quote!(true),
+    Data::Enum(_) => quote!(false),
+    Data::Union(data) => {
+      return Err(Error::new(
+        data.union_token.span(),
+        "Key cannot be derived for Rust unions",
+      ))
+    }
+  };
+
+  let type_params: Vec<Ident> = input
+    .generics
+    .type_params()
+    .map(|tp| tp.ident.clone())
+    .collect();
+  let where_clause = input.generics.make_where_clause();
+  for tp in type_params {
+    where_clause
+      .predicates
+      .push(parse_quote!(#tp: #traits::Key));
+  }
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+  Ok(quote! {
+    impl #impl_generics #traits::Key for #name #ty_generics #where_clause {
+      fn is_fixed_size() -> bool {
+        #fixed_size
+      }
+    }
+
+    impl #impl_generics #traits::KeyMember for #name #ty_generics #where_clause {
+      type Key = Self;
+      fn key(&self) -> Self {
+        ::std::clone::Clone::clone(self)
+      }
+    }
+  })
+}
+
+// The name serde uses for a field, which is given to the PlCdrType
+// implementation by the parameter list (de)serializer.
+fn serde_name(field: &Field) -> Result<String> {
+  let mut name = field.ident.as_ref().unwrap().unraw().to_string();
+  for attr in field.attrs.iter().filter(|a| a.path.is_ident("serde")) {
+    if let Meta::List(list) = attr.parse_meta()? {
+      for nested in list.nested {
+        match nested {
+          NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => match nv.lit {
+            Lit::Str(s) => name = s.value(),
+            lit => return Err(Error::new(lit.span(), "expected a string")),
+          },
+          NestedMeta::Meta(Meta::List(l)) if l.path.is_ident("rename") => {
+            return Err(Error::new(
+              l.span(),
+              "PlCdrType needs the same field name for serialization and deserialization",
+            ))
+          }
+          _ => (),
+        }
+      }
+    }
+  }
+  Ok(name)
+}
+
+fn pl_cdr_type(input: DeriveInput) -> Result<TokenStream2> {
+  let fields = match &input.data {
+    Data::Struct(data) => match &data.fields {
+      Fields::Named(fields) => &fields.named,
+      _ => {
+        return Err(Error::new(
+          input.ident.span(),
+          "PlCdrType can be derived only for structs with named fields",
+        ))
+      }
+    },
+    _ => {
+      return Err(Error::new(
+        input.ident.span(),
+        "PlCdrType can be derived only for structs",
+      ))
+    }
+  };
+  for attr in input.attrs.iter().filter(|a| a.path.is_ident("serde")) {
+    if attr.tokens.to_string().contains("rename_all") {
+      return Err(Error::new(
+        attr.span(),
+        "PlCdrType does not support #[serde(rename_all)]",
+      ));
+    }
+  }
+
+  let mut names = Vec::new();
+  let mut ids = Vec::new();
+  for field in fields {
+    let mut id = None;
+    for attr in field
+      .attrs
+      .iter()
+      .filter(|a| a.path.is_ident("parameter_id"))
+    {
+      match attr.parse_meta()? {
+        Meta::NameValue(MetaNameValue {
+          lit: Lit::Int(lit), ..
+        }) => id = Some(lit.base10_parse::<u16>()?),
+        meta => return Err(Error::new(meta.span(), "expected `#[parameter_id = ..]`")),
+      }
+    }
+    match id {
+      // PID_PAD and PID_SENTINEL structure the parameter list.
+      Some(0) | Some(1) => {
+        return Err(Error::new(
+          field.span(),
+          "ParameterIds 0x0000 and 0x0001 are reserved",
+        ))
+      }
+      Some(id) => {
+        names.push(serde_name(field)?);
+        ids.push(id);
+      }
+      None => return Err(Error::new(field.span(), "missing #[parameter_id = ..]")),
+    }
+  }
+
+  let name = &input.ident;
+  let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+  Ok(quote! {
+    impl #impl_generics ::rustdds::serialization::PlCdrType for #name #ty_generics #where_clause {
+      fn parameter_id(field: &str) -> Option<u16> {
+        match field {
+          #( #names => Some(#ids), )*
+          _ => None,
+        }
+      }
+    }
+  })
+}
+
+// One variant of an IDL union, with its case labels
+struct UnionCase {
+  variant: Ident,
+  labels: Vec<Expr>,
+  default: bool,
+  has_value: bool,
+}
+
+fn union_case(variant: &syn::Variant) -> Result<UnionCase> {
+  let mut labels = Vec::new();
+  let mut default = false;
+  for attr in variant.attrs.iter().filter(|a| a.path.is_ident("case")) {
+    let args = attr.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)?;
+    for arg in args {
+      match &arg {
+        Expr::Path(p) if p.path.is_ident("default") => default = true,
+        _ => labels.push(arg),
+      }
+    }
+  }
+  if labels.is_empty() && !default {
+    return Err(Error::new(
+      variant.span(),
+      "expected `#[case(..)]` with labels or `default`",
+    ));
+  }
+  // The default case also carries the discriminator value.
+  let fields = match &variant.fields {
+    Fields::Unit => 0,
+    Fields::Unnamed(fields) => fields.unnamed.len(),
+    Fields::Named(_) => usize::MAX,
+  };
+  let has_value = match (default, fields) {
+    (false, 0) | (true, 1) => false,
+    (false, 1) | (true, 2) => true,
+    (false, _) => {
+      return Err(Error::new(
+        variant.fields.span(),
+        "a case must have one unnamed field, or none",
+      ))
+    }
+    (true, _) => {
+      return Err(Error::new(
+        variant.fields.span(),
+        "the default case must have the discriminator and one other unnamed field, or only the discriminator",
+      ))
+    }
+  };
+  Ok(UnionCase {
+    variant: variant.ident.clone(),
+    labels,
+    default,
+    has_value,
+  })
+}
+
+fn idl_union(input: DeriveInput) -> Result<TokenStream2> {
+  let variants = match &input.data {
+    Data::Enum(data) => &data.variants,
+    _ => {
+      return Err(Error::new(
+        input.ident.span(),
+        "IdlUnion can be derived only for enums",
+      ))
+    }
+  };
+  if input.generics.params.iter().next().is_some() {
+    return Err(Error::new(
+      input.generics.span(),
+      "IdlUnion cannot be derived for generic enums",
+    ));
+  }
+  let mut discriminator: Option<Type> = None;
+  for attr in input
+    .attrs
+    .iter()
+    .filter(|a| a.path.is_ident("discriminator"))
+  {
+    discriminator = Some(attr.parse_args()?);
+  }
+  let discriminator = match discriminator {
+    Some(d) => d,
+    None => {
+      return Err(Error::new(
+        input.ident.span(),
+        "missing `#[discriminator(..)]` with the discriminator type",
+      ))
+    }
+  };
+  let cases = variants
+    .iter()
+    .map(union_case)
+    .collect::<Result<Vec<_>>>()?;
+  if cases.iter().filter(|c| c.default).count() > 1 {
+    return Err(Error::new(
+      input.ident.span(),
+      "only one case can be the default",
+    ));
+  }
+
+  let name = &input.ident;
+  let expecting = format!("union {}", name);
+
+  // Serialized as the discriminator, followed by the value of the case, if any.
+  let serialize_arms = cases.iter().map(|c| {
+    let variant = &c.variant;
+    let (pattern, discriminator_value) = match (c.default, c.has_value) {
+      (false, false) => (quote!(#name::#variant), None),
+      (false, true) => (quote!(#name::#variant(value)), None),
+      (true, false) => (
+        quote!(#name::#variant(discriminator)),
+        Some(quote!(::std::clone::Clone::clone(discriminator))),
+      ),
+      (true, true) => (
+        quote!(#name::#variant(discriminator, value)),
+        Some(quote!(::std::clone::Clone::clone(discriminator))),
+      ),
+    };
+    let discriminator_value = discriminator_value.unwrap_or_else(|| {
+      let label = &c.labels[0];
+      quote!(#label)
+    });
+    let len: usize = if c.has_value { 2 } else { 1 };
+    let value = if c.has_value {
+      quote!(tuple.serialize_element(value)?;)
+    } else {
+      quote!()
+    };
+    quote! {
+      #pattern => {
+        let discriminator: #discriminator = #discriminator_value;
+        let mut tuple = serializer.serialize_tuple(#len)?;
+        tuple.serialize_element(&discriminator)?;
+        #value
+        tuple.end()
+      }
+    }
+  });
+
+  let value_of = |c: &UnionCase| {
+    let variant = &c.variant;
+    match (c.default, c.has_value) {
+      (false, false) => quote!(Ok(#name::#variant)),
+      (false, true) => quote! {
+        seq.next_element()?.map(#name::#variant)
+          .ok_or_else(|| ::serde::de::Error::invalid_length(1, &self))
+      },
+      (true, false) => quote!(Ok(#name::#variant(discriminator))),
+      (true, true) => quote! {
+        seq.next_element()?.map(|value| #name::#variant(discriminator, value))
+          .ok_or_else(|| ::serde::de::Error::invalid_length(1, &self))
+      },
+    }
+  };
+  let mut deserialize_arms = Vec::new();
+  for c in cases.iter().filter(|c| !c.labels.is_empty()) {
+    let labels = &c.labels;
+    let value = value_of(c);
+    deserialize_arms.push(quote!( #(#labels)|* => #value, ));
+  }
+  match cases.iter().find(|c| c.default) {
+    Some(c) => {
+      let value = value_of(c);
+      deserialize_arms.push(quote!( _ => #value, ));
+    }
+    None => deserialize_arms.push(quote! {
+      _ => Err(::serde::de::Error::custom(concat!("unknown discriminator of ", #expecting))),
+    }),
+  }
+
+  Ok(quote! {
+    impl ::serde::Serialize for #name {
+      fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        use ::serde::ser::SerializeTuple;
+        match self {
+          #(#serialize_arms)*
+        }
+      }
+    }
+
+    impl<'de> ::serde::Deserialize<'de> for #name {
+      fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+        struct UnionVisitor;
+        impl<'de> ::serde::de::Visitor<'de> for UnionVisitor {
+          type Value = #name;
+          fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            formatter.write_str(#expecting)
+          }
+          // A default case or labels covering all discriminator values make the
+          // last arm unreachable.
+          #[allow(unreachable_patterns)]
+          fn visit_seq<A: ::serde::de::SeqAccess<'de>>(self, mut seq: A) -> ::std::result::Result<#name, A::Error> {
+            let discriminator: #discriminator = seq.next_element()?
+              .ok_or_else(|| ::serde::de::Error::invalid_length(0, &self))?;
+            match discriminator {
+              #(#deserialize_arms)*
+            }
+          }
+        }
+        deserializer.deserialize_tuple(2, UnionVisitor)
+      }
+    }
+  })
+}
@@ -0,0 +1,858 @@
+// Rust code generation from the parsed IDL
+//
+// IDL modules become Rust modules, and references between them are relative
+// (super::), so that the generated code can be included anywhere in a crate.
+
+use std::collections::HashSet;
+
+use crate::{
+  parser::{
+    resolve, ConstValue, Definition, EnumDef, Extensibility, Member, Path, Specification,
+    StructDef, Symbol, TypeSpec, UnionDef,
+  },
+  Error,
+};
+
+// Serde implements its traits only for arrays of up to this length.
+pub(crate) const SERDE_MAX_ARRAY: u32 = 32;
+
+const SERIALIZE: &str = "::serde::Serialize";
+const DESERIALIZE: &str = "::serde::Deserialize";
+const TYPE_SUPPORT: &str = "::rustdds::dds::xtypes::TypeSupport";
+const KEY: &str = "::rustdds::dds::traits::Key";
+const KEYED: &str = "::rustdds::dds::traits::Keyed";
+
+// Keys are tuples of the key members, and tuples implement Key up to this
+// length.
+const MAX_KEY_MEMBERS: usize = 12;
+
+// IDL naming conventions differ from Rust ones.
+pub(crate) const ALLOW_LINTS: &str =
+  "#[allow(clippy::all, non_camel_case_types, non_snake_case, non_upper_case_globals)]";
+
+const RUST_KEYWORDS: &[&str] = &[
+  "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+  "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+  "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+  "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof",
+  "unsized", "virtual", "yield",
+];
+
+pub(crate) fn generate(specification: &Specification) -> Result<String, Error> {
+  let mut generator = Generator {
+    specification,
+    out: String::new(),
+    indent: 0,
+    scope: Vec::new(),
+  };
+  generator.line("// Generated by rustdds-idlgen. Do not edit.");
+  for definition in &merge_modules(&specification.definitions) {
+    generator.line("");
+    generator.line(ALLOW_LINTS);
+    generator.definition(definition)?;
+  }
+  Ok(generator.out)
+}
+
+// IDL modules can be reopened, but Rust modules cannot. Rust items can be in
+// any order, so the contents of a reopened module are appended to the first
+// definition.
+fn merge_modules(definitions: &[Definition]) -> Vec<Definition> {
+  let mut merged: Vec<Definition> = Vec::new();
+  for definition in definitions {
+    if let Definition::Module { name, definitions } = definition {
+      let existing = merged.iter_mut().find_map(|d| match d {
+        Definition::Module {
+          name: n,
+          definitions: ds,
+        } if n == name => Some(ds),
+        _ => None,
+      });
+      if let Some(existing) = existing {
+        existing.extend(definitions.iter().cloned());
+        continue;
+      }
+    }
+    merged.push(definition.clone());
+  }
+  merged
+}
+
+// Rust identifier for an IDL identifier
+pub(crate) fn identifier(name: &str) -> String {
+  match name {
+    "self" | "Self" | "super" | "crate" => format!("{}_", name),
+    _ if RUST_KEYWORDS.contains(&name) => format!("r#{}", name),
+    _ => name.to_string(),
+  }
+}
+
+// Rust enum variant name for a union member name
+fn variant_name(name: &str) -> String {
+  let mut variant = String::new();
+  let mut upper = true;
+  for c in name.chars() {
+    if c == '_' {
+      upper = true;
+    } else if upper {
+      variant.extend(c.to_uppercase());
+      upper = false;
+    } else {
+      variant.push(c);
+    }
+  }
+  if variant.is_empty() || variant.starts_with(|c: char| c.is_ascii_digit()) {
+    variant.insert(0, 'V');
+  }
+  identifier(&variant)
+}
+
+// Type properties, which decide the derives
+#[derive(Clone, Copy, PartialEq)]
+enum Property {
+  // Contains floating point numbers, so no Eq, Ord or Hash
+  Float,
+  // Contains a union, so no TypeSupport
+  Union,
+}
+
+struct Generator<'a> {
+  specification: &'a Specification,
+  out: String,
+  indent: usize,
+  // Module of the definition being generated
+  scope: Path,
+}
+
+impl<'a> Generator<'a> {
+  fn line(&mut self, line: &str) {
+    if !line.is_empty() {
+      for _ in 0..self.indent {
+        self.out.push_str("  ");
+      }
+    }
+    self.out.push_str(line);
+    self.out.push('\n');
+  }
+
+  fn symbol(&self, path: &[String]) -> &'a Symbol {
+    // The parser has checked that all names are defined.
+    &self.specification.symbols[path]
+  }
+
+  fn resolve(&self, type_spec: &TypeSpec) -> TypeSpec {
+    resolve(&self.specification.symbols, type_spec)
+  }
+
+  fn error<T>(&self, name: &str, message: &str) -> Result<T, Error> {
+    let mut path = self.scope.clone();
+    path.push(name.to_string());
+    Err(Error::new(format!("{}: {}", path.join("::"), message)))
+  }
+
+  // Path to a definition, relative to the current module
+  fn path_to(&self, path: &[String]) -> String {
+    let common = self
+      .scope
+      .iter()
+      .zip(path)
+      .take_while(|(a, b)| a == b)
+      .count();
+    let mut parts: Vec<String> = vec!["super".to_string(); self.scope.len() - common];
+    parts.extend(path[common..].iter().map(|p| identifier(p)));
+    parts.join("::")
+  }
+
+  fn rust_type(&self, type_spec: &TypeSpec) -> String {
+    match type_spec {
+      TypeSpec::Boolean => "bool".to_string(),
+      TypeSpec::Octet | TypeSpec::Char | TypeSpec::UInt8 => "u8".to_string(),
+      TypeSpec::Int8 => "i8".to_string(),
+      TypeSpec::Int16 => "i16".to_string(),
+      TypeSpec::Int32 => "i32".to_string(),
+      TypeSpec::Int64 => "i64".to_string(),
+      TypeSpec::UInt16 => "u16".to_string(),
+      TypeSpec::UInt32 => "u32".to_string(),
+      TypeSpec::UInt64 => "u64".to_string(),
+      TypeSpec::Float => "f32".to_string(),
+      TypeSpec::Double => "f64".to_string(),
+      TypeSpec::String { .. } => "String".to_string(),
+      TypeSpec::Sequence { element, .. } => format!("Vec<{}>", self.rust_type(element)),
+      TypeSpec::Array {
+        element,
+        dimensions,
+      } => dimensions
+        .iter()
+        .rev()
+        .fold(self.rust_type(element), |t, d| format!("[{}; {}]", t, d)),
+      TypeSpec::Named(path) => self.path_to(path),
+    }
+  }
+
+  // IDL spelling of a type, for documenting bounds
+  fn idl_type(&self, type_spec: &TypeSpec) -> String {
+    match type_spec {
+      TypeSpec::String { bound: Some(b) } => format!("string<{}>", b),
+      TypeSpec::Sequence { element, bound } => match bound {
+        Some(b) => format!("sequence<{}, {}>", self.idl_type(element), b),
+        None => format!("sequence<{}>", self.idl_type(element)),
+      },
+      TypeSpec::Array {
+        element,
+        dimensions,
+      } => {
+        let dimensions: Vec<String> = dimensions.iter().map(|d| format!("[{}]", d)).collect();
+        format!("{}{}", self.idl_type(element), dimensions.concat())
+      }
+      TypeSpec::Named(path) => path.join("::"),
+      TypeSpec::Boolean => "boolean".to_string(),
+      TypeSpec::Octet => "octet".to_string(),
+      TypeSpec::Char => "char".to_string(),
+      TypeSpec::Int8 => "int8".to_string(),
+      TypeSpec::Int16 => "short".to_string(),
+      TypeSpec::Int32 => "long".to_string(),
+      TypeSpec::Int64 => "long long".to_string(),
+      TypeSpec::UInt8 => "uint8".to_string(),
+      TypeSpec::UInt16 => "unsigned short".to_string(),
+      TypeSpec::UInt32 => "unsigned long".to_string(),
+      TypeSpec::UInt64 => "unsigned long long".to_string(),
+      TypeSpec::Float => "float".to_string(),
+      TypeSpec::Double => "double".to_string(),
+      TypeSpec::String { bound: None } => "string".to_string(),
+    }
+  }
+
+  fn doc_bounds(&mut self, type_spec: &TypeSpec) {
+    fn bounded(t: &TypeSpec) -> bool {
+      match t {
+        TypeSpec::String { bound } => bound.is_some(),
+        TypeSpec::Sequence { element, bound } => bound.is_some() || bounded(element),
+        TypeSpec::Array { element, .. } => bounded(element),
+        _ => false,
+      }
+    }
+    if bounded(type_spec) {
+      let line = format!("/// IDL type `{}`", self.idl_type(type_spec));
+      self.line(&line);
+    }
+  }
+
+  fn has_property(&self, type_spec: &TypeSpec, property: Property) -> bool {
+    self.has_property_inner(type_spec, property, &mut HashSet::new())
+  }
+
+  // Types can be recursive through sequences, hence the visited set.
+  fn has_property_inner(
+    &self,
+    type_spec: &TypeSpec,
+    property: Property,
+    visited: &mut HashSet<Path>,
+  ) -> bool {
+    match type_spec {
+      TypeSpec::Float | TypeSpec::Double => property == Property::Float,
+      TypeSpec::Sequence { element, .. } | TypeSpec::Array { element, .. } => {
+        self.has_property_inner(element, property, visited)
+      }
+      TypeSpec::Named(path) => {
+        if !visited.insert(path.clone()) {
+          return false;
+        }
+        match self.symbol(path) {
+          Symbol::Typedef(t) => self.has_property_inner(t, property, visited),
+          Symbol::Struct(Some(s)) => s
+            .members
+            .iter()
+            .any(|m| self.has_property_inner(&m.type_spec, property, visited)),
+          Symbol::Union(Some(u)) => {
+            property == Property::Union
+              || u
+                .cases
+                .iter()
+                .any(|c| self.has_property_inner(&c.type_spec, property, visited))
+          }
+          _ => false,
+        }
+      }
+      _ => false,
+    }
+  }
+
+  // Comparisons and hashing are derived when the types allow.
+  fn derives(&self, types: &[&TypeSpec], extra: &[&str]) -> String {
+    let mut derives = vec!["Clone", "Debug", "PartialEq"];
+    if !types.iter().any(|t| self.has_property(t, Property::Float)) {
+      derives.extend(&["Eq", "PartialOrd", "Ord", "Hash"]);
+    }
+    derives.extend(extra);
+    format!("#[derive({})]", derives.join(", "))
+  }
+
+  fn extensibility_attribute(&mut self, extensibility: Extensibility) {
+    self.line(match extensibility {
+      Extensibility::Final => "#[xtypes(final)]",
+      Extensibility::Appendable => "#[xtypes(appendable)]",
+      Extensibility::Mutable => "#[xtypes(mutable)]",
+    });
+  }
+
+  fn definition(&mut self, definition: &Definition) -> Result<(), Error> {
+    match definition {
+      Definition::Module { name, definitions } => {
+        self.line(&format!("pub mod {} {{", identifier(name)));
+        self.indent += 1;
+        self.scope.push(name.clone());
+        for (i, d) in merge_modules(definitions).iter().enumerate() {
+          if i > 0 {
+            self.line("");
+          }
+          self.definition(d)?;
+        }
+        self.scope.pop();
+        self.indent -= 1;
+        self.line("}");
+      }
+      Definition::Struct(s) => self.struct_definition(s)?,
+      Definition::Union(u) => self.union_definition(u)?,
+      Definition::Enum(e) => self.enum_definition(e),
+      Definition::Typedef { name, type_spec } => {
+        self.doc_bounds(type_spec);
+        let line = format!(
+          "pub type {} = {};",
+          identifier(name),
+          self.rust_type(type_spec)
+        );
+        self.line(&line);
+      }
+      Definition::Const {
+        name,
+        const_type,
+        value,
+      } => {
+        let rust_type = match self.resolve(const_type) {
+          TypeSpec::String { .. } => "&str".to_string(),
+          _ => self.rust_type(const_type),
+        };
+        let value = match value {
+          ConstValue::Integer(v) => v.to_string(),
+          ConstValue::Float(v) if v.is_finite() => format!("{:?}", v),
+          ConstValue::Float(_) => return self.error(name, "constant is not finite"),
+          ConstValue::Boolean(b) => b.to_string(),
+          ConstValue::Char(c) => c.to_string(),
+          ConstValue::String(s) => format!("{:?}", s),
+          ConstValue::Enumerator {
+            enum_path, name, ..
+          } => format!("{}::{}", self.path_to(enum_path), identifier(name)),
+        };
+        let line = format!("pub const {}: {} = {};", identifier(name), rust_type, value);
+        self.line(&line);
+      }
+    }
+    Ok(())
+  }
+
+  // Member declaration, with the serde attribute needed by long arrays
+  fn member(
+    &mut self,
+    name: &str,
+    member: &Member,
+    xtypes_attributes: &[String],
+  ) -> Result<(), Error> {
+    self.doc_bounds(&member.type_spec);
+    for attribute in xtypes_attributes {
+      self.line(attribute);
+    }
+    let mut rust_type = self.rust_type(&member.type_spec);
+    if let TypeSpec::Array {
+      element,
+      dimensions,
+    } = self.resolve(&member.type_spec)
+    {
+      if dimensions[1..].iter().any(|d| *d > SERDE_MAX_ARRAY)
+        || self.has_long_array(&element)
+        || (dimensions[0] > SERDE_MAX_ARRAY && member.optional)
+      {
+        return self.error(
+          name,
+          &format!(
+            "only member arrays can have more than {} elements",
+            SERDE_MAX_ARRAY
+          ),
+        );
+      }
+      if dimensions[0] > SERDE_MAX_ARRAY {
+        self.line("#[serde(with = \"::rustdds::serialization::large_array\")]");
+      }
+    } else if self.has_long_array(&member.type_spec) {
+      return self.error(
+        name,
+        &format!(
+          "only member arrays can have more than {} elements",
+          SERDE_MAX_ARRAY
+        ),
+      );
+    }
+    // Only the outermost bound is checked. Nested ones are documented only.
+    if let TypeSpec::String { bound: Some(bound) }
+    | TypeSpec::Sequence {
+      bound: Some(bound), ..
+    } = self.resolve(&member.type_spec)
+    {
+      if !member.optional {
+        let line = format!(
+          "#[serde(with = \"::rustdds::serialization::Bound::<{}>\")]",
+          bound
+        );
+        self.line(&line);
+      }
+    }
+    if member.optional {
+      rust_type = format!("Option<{}>", rust_type);
+    }
+    let line = format!("pub {}: {},", identifier(&member.name), rust_type);
+    self.line(&line);
+    Ok(())
+  }
+
+  // Is there an array longer than serde supports, other than through
+  // a named struct or union, which are checked at their definition
+  fn has_long_array(&self, type_spec: &TypeSpec) -> bool {
+    match self.resolve(type_spec) {
+      TypeSpec::Array {
+        element,
+        dimensions,
+      } => dimensions.iter().any(|d| *d > SERDE_MAX_ARRAY) || self.has_long_array(&element),
+      TypeSpec::Sequence { element, .. } => self.has_long_array(&element),
+      _ => false,
+    }
+  }
+
+  fn struct_definition(&mut self, s: &StructDef) -> Result<(), Error> {
+    let member_types: Vec<&TypeSpec> = s.members.iter().map(|m| &m.type_spec).collect();
+    let type_support = !member_types
+      .iter()
+      .any(|t| self.has_property(t, Property::Union));
+    let keyed = s.members.iter().any(|m| m.key);
+    if keyed {
+      self.check_keys(s)?;
+    }
+    let name = identifier(&s.name);
+    let mut extra = vec![SERIALIZE, DESERIALIZE];
+    if type_support {
+      extra.push(TYPE_SUPPORT);
+    }
+    if keyed {
+      extra.push(KEYED);
+    } else {
+      let mut path = self.scope.clone();
+      path.push(s.name.clone());
+      if self.is_key(&TypeSpec::Named(path)) {
+        extra.push(KEY);
+      }
+    }
+    self.line(&self.derives(&member_types, &extra));
+    if type_support {
+      self.extensibility_attribute(s.extensibility);
+    }
+    self.line(&format!("pub struct {} {{", name));
+    self.indent += 1;
+    let mut next_id = 0;
+    for member in &s.members {
+      let mut attributes = Vec::new();
+      if member.key {
+        attributes.push("#[key]".to_string());
+      }
+      if type_support && member.id != next_id {
+        attributes.push(format!("#[xtypes(id = {})]", member.id));
+      }
+      next_id = member.id + 1;
+      self.member(&s.name, member, &attributes)?;
+    }
+    self.indent -= 1;
+    self.line("}");
+    Ok(())
+  }
+
+  // The key of a structure is the tuple of the keys of its key members, see
+  // derive(Keyed) in rustdds.
+  fn check_keys(&self, s: &StructDef) -> Result<(), Error> {
+    for key in s.members.iter().filter(|m| m.key) {
+      if key.optional || !self.is_key_member(&key.type_spec) {
+        let reason = if self.has_property(&key.type_spec, Property::Float) {
+          "contains floating point numbers"
+        } else {
+          "cannot be used as a key"
+        };
+        return self.error(&s.name, &format!("key member {} {}", key.name, reason));
+      }
+    }
+    if s.members.iter().filter(|m| m.key).count() > MAX_KEY_MEMBERS {
+      return self.error(
+        &s.name,
+        &format!("more than {} key members", MAX_KEY_MEMBERS),
+      );
+    }
+    Ok(())
+  }
+
+  // Can the type be a key member: either a Key itself, or a structure with
+  // key members, or a collection of those.
+  fn is_key_member(&self, type_spec: &TypeSpec) -> bool {
+    match self.resolve(type_spec) {
+      TypeSpec::Named(path) => match self.symbol(&path) {
+        Symbol::Struct(Some(s)) if s.members.iter().any(|m| m.key) => true,
+        _ => self.is_key(type_spec),
+      },
+      TypeSpec::Sequence { element, .. } => self.is_key_member(&element),
+      TypeSpec::Array {
+        element,
+        dimensions,
+      } => dimensions.iter().all(|d| *d <= SERDE_MAX_ARRAY) && self.is_key_member(&element),
+      _ => self.is_key(type_spec),
+    }
+  }
+
+  // Does the generated type implement Key
+  fn is_key(&self, type_spec: &TypeSpec) -> bool {
+    self.is_key_inner(type_spec, &mut HashSet::new())
+  }
+
+  fn is_key_inner(&self, type_spec: &TypeSpec, visited: &mut HashSet<Path>) -> bool {
+    match type_spec {
+      TypeSpec::Float | TypeSpec::Double => false,
+      TypeSpec::Sequence { element, .. } => self.is_key_inner(element, visited),
+      TypeSpec::Array {
+        element,
+        dimensions,
+      } => dimensions.iter().all(|d| *d <= SERDE_MAX_ARRAY) && self.is_key_inner(element, visited),
+      TypeSpec::Named(path) => {
+        if !visited.insert(path.clone()) {
+          return true;
+        }
+        match self.symbol(path) {
+          Symbol::Typedef(t) => self.is_key_inner(t, visited),
+          Symbol::Enum(_) => true,
+          Symbol::Struct(Some(s)) => s
+            .members
+            .iter()
+            .all(|m| !m.key && !m.optional && self.is_key_inner(&m.type_spec, visited)),
+          Symbol::Union(Some(u)) => u
+            .cases
+            .iter()
+            .all(|c| self.is_key_inner(&c.type_spec, visited)),
+          _ => false,
+        }
+      }
+      _ => true,
+    }
+  }
+
+  fn enum_definition(&mut self, e: &EnumDef) {
+    self.line(
+      "#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, ::serde::Serialize, \
+       ::serde::Deserialize, ::rustdds::dds::xtypes::TypeSupport, ::rustdds::dds::traits::Key)]",
+    );
+    self.extensibility_attribute(e.extensibility);
+    self.line(&format!("pub enum {} {{", identifier(&e.name)));
+    self.indent += 1;
+    for enumerator in &e.enumerators {
+      self.line(&format!("{},", identifier(enumerator)));
+    }
+    self.indent -= 1;
+    self.line("}");
+  }
+
+  // Discriminator value as a Rust expression or pattern
+  fn label(&self, discriminator: &TypeSpec, value: &ConstValue) -> String {
+    match value {
+      ConstValue::Integer(v) => format!("{}{}", v, self.rust_type(&self.resolve(discriminator))),
+      ConstValue::Boolean(b) => b.to_string(),
+      ConstValue::Char(c) => format!("{}u8", c),
+      ConstValue::Enumerator {
+        enum_path, name, ..
+      } => format!("{}::{}", self.path_to(enum_path), identifier(name)),
+      // The parser accepts only the above for discriminators.
+      ConstValue::Float(_) | ConstValue::String(_) => unreachable!(),
+    }
+  }
+
+  // Unions are Rust enums with a variant for each case. The default case also
+  // carries the discriminator value. Serialized as the discriminator followed
+  // by the value of the selected case.
+  fn union_definition(&mut self, u: &UnionDef) -> Result<(), Error> {
+    let name = identifier(&u.name);
+    let discriminator = self.rust_type(&u.discriminator);
+    let mut variants: Vec<String> = Vec::new();
+    for case in &u.cases {
+      let variant = variant_name(&case.name);
+      if variants.contains(&variant) {
+        return self.error(&u.name, &format!("two members map to variant {}", variant));
+      }
+      if self.has_long_array(&case.type_spec) {
+        return self.error(
+          &u.name,
+          &format!(
+            "only member arrays can have more than {} elements",
+            SERDE_MAX_ARRAY
+          ),
+        );
+      }
+      variants.push(variant);
+    }
+    let mut types: Vec<&TypeSpec> = u.cases.iter().map(|c| &c.type_spec).collect();
+    types.push(&u.discriminator);
+    let mut path = self.scope.clone();
+    path.push(u.name.clone());
+    if self.is_key(&TypeSpec::Named(path)) {
+      self.line(&self.derives(&types, &[KEY]));
+    } else {
+      self.line(&self.derives(&types, &[]));
+    }
+    self.line(&format!("pub enum {} {{", name));
+    self.indent += 1;
+    for (case, variant) in u.cases.iter().zip(&variants) {
+      self.doc_bounds(&case.type_spec);
+      let case_type = self.rust_type(&case.type_spec);
+      if case.default {
+        self.line(&format!("{}({}, {}),", variant, discriminator, case_type));
+      } else {
+        self.line(&format!("{}({}),", variant, case_type));
+      }
+    }
+    self.indent -= 1;
+    self.line("}");
+
+    self.line("");
+    self.line(&format!("impl ::serde::Serialize for {} {{", name));
+    self.indent += 1;
+    self.line("fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {");
+    self.indent += 1;
+    self.line("use ::serde::ser::SerializeTuple;");
+    self.line("let mut tuple = serializer.serialize_tuple(2)?;");
+    self.line("match self {");
+    self.indent += 1;
+    for (case, variant) in u.cases.iter().zip(&variants) {
+      if case.default {
+        self.line(&format!(
+          "{}::{}(discriminator, value) => {{",
+          name, variant
+        ));
+        self.line("  tuple.serialize_element(discriminator)?;");
+      } else {
+        self.line(&format!("{}::{}(value) => {{", name, variant));
+        let label = self.label(&u.discriminator, &case.labels[0]);
+        self.line(&format!("  tuple.serialize_element(&{})?;", label));
+      }
+      self.line("  tuple.serialize_element(value)?;");
+      self.line("}");
+    }
+    self.indent -= 1;
+    self.line("}");
+    self.line("tuple.end()");
+    self.indent -= 1;
+    self.line("}");
+    self.indent -= 1;
+    self.line("}");
+
+    self.line("");
+    self.line(&format!(
+      "impl<'de> ::serde::Deserialize<'de> for {} {{",
+      name
+    ));
+    self.indent += 1;
+    self.line(&format!(
+      "fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> ::std::result::Result<{}, D::Error> {{",
+      name
+    ));
+    self.indent += 1;
+    self.line("struct UnionVisitor;");
+    self.line("impl<'de> ::serde::de::Visitor<'de> for UnionVisitor {");
+    self.indent += 1;
+    self.line(&format!("type Value = {};", name));
+    self.line("fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {");
+    self.line(&format!("  formatter.write_str(\"union {}\")", u.name));
+    self.line("}");
+    // A default case or labels covering all discriminator values make the
+    // last arm unreachable.
+    self.line("#[allow(unreachable_patterns)]");
+    self.line("fn visit_seq<A: ::serde::de::SeqAccess<'de>>(self, mut seq: A) -> ::std::result::Result<Self::Value, A::Error> {");
+    self.indent += 1;
+    self.line(&format!(
+      "let discriminator: {} = seq.next_element()?.ok_or_else(|| ::serde::de::Error::invalid_length(0, &self))?;",
+      discriminator
+    ));
+    self.line("let value = match discriminator {");
+    self.indent += 1;
+    let mut default = None;
+    for (case, variant) in u.cases.iter().zip(&variants) {
+      if case.default {
+        default = Some(variant);
+        if case.labels.is_empty() {
+          continue;
+        }
+      }
+      let labels: Vec<String> = case
+        .labels
+        .iter()
+        .map(|l| self.label(&u.discriminator, l))
+        .collect();
+      let value = if case.default {
+        format!(
+          "seq.next_element()?.map(|value| {}::{}(discriminator, value))",
+          name, variant
+        )
+      } else {
+        format!("seq.next_element()?.map({}::{})", name, variant)
+      };
+      self.line(&format!("{} => {},", labels.join(" | "), value));
+    }
+    match default {
+      Some(variant) => self.line(&format!(
+        "_ => seq.next_element()?.map(|value| {}::{}(discriminator, value)),",
+        name, variant
+      )),
+      None => self.line(
+        "_ => return Err(::serde::de::Error::custom(format_args!(\"unknown discriminator {:?}\", discriminator))),",
+      ),
+    }
+    self.indent -= 1;
+    self.line("};");
+    self.line("value.ok_or_else(|| ::serde::de::Error::invalid_length(1, &self))");
+    self.indent -= 1;
+    self.line("}");
+    self.indent -= 1;
+    self.line("}");
+    self.line("deserializer.deserialize_tuple(2, UnionVisitor)");
+    self.indent -= 1;
+    self.line("}");
+    self.indent -= 1;
+    self.line("}");
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::generate;
+
+  #[test]
+  fn generate_struct_with_key() {
+    let code = generate(
+      r#"
+      module shapes {
+        @final struct Shape {
+          @key string<32> color;
+          long x;
+          @id(5) long y;
+          @optional double size;
+        };
+      };
+    "#,
+    )
+    .unwrap();
+    let expected = "
+#[allow(clippy::all, non_camel_case_types, non_snake_case, non_upper_case_globals)]
+pub mod shapes {
+  #[derive(Clone, Debug, PartialEq, ::serde::Serialize, ::serde::Deserialize, ::rustdds::dds::xtypes::TypeSupport, ::rustdds::dds::traits::Keyed)]
+  #[xtypes(final)]
+  pub struct Shape {
+    /// IDL type `string<32>`
+    #[key]
+    #[serde(with = \"::rustdds::serialization::Bound::<32>\")]
+    pub color: String,
+    pub x: i32,
+    #[xtypes(id = 5)]
+    pub y: i32,
+    pub size: Option<f64>,
+  }
+}
+";
+    assert!(code.ends_with(expected), "{}", code);
+  }
+
+  #[test]
+  fn generate_references_and_composite_key() {
+    let code = generate(
+      r#"
+      module a {
+        enum Kind { ONE, TWO };
+        module b {
+          typedef octet Block[64];
+          struct Item { @key a::Kind kind; @key long id; Block data; sequence<long, 3> type; };
+        };
+      };
+    "#,
+    )
+    .unwrap();
+    for expected in &[
+      "      #[key]\n      pub kind: super::Kind,\n",
+      "::rustdds::dds::traits::Keyed)]\n    #[xtypes(appendable)]\n    pub struct Item {\n",
+      "      #[serde(with = \"::rustdds::serialization::large_array\")]\n      pub data: Block,\n",
+      "      /// IDL type `sequence<long, 3>`\n      #[serde(with = \"::rustdds::serialization::Bound::<3>\")]\n      pub r#type: Vec<i32>,\n",
+      "::rustdds::dds::traits::Key)]\n  #[xtypes(appendable)]\n  pub enum Kind {\n",
+    ] {
+      assert!(code.contains(expected), "{}\n---\n{}", expected, code);
+    }
+  }
+
+  #[test]
+  fn generate_union() {
+    let code = generate(
+      r#"
+      enum Color { RED, GREEN };
+      union Value switch (Color) {
+        case RED: case GREEN: long number;
+        default: string text_value;
+      };
+      const Color C = GREEN;
+      const string S = "x";
+    "#,
+    )
+    .unwrap();
+    for expected in &[
+      "Hash, ::rustdds::dds::traits::Key)]\npub enum Value {\n  Number(i32),\n  TextValue(Color, String),\n}\n",
+      "Value::Number(value) => {\n        tuple.serialize_element(&Color::RED)?;",
+      "Color::RED | Color::GREEN => seq.next_element()?.map(Value::Number),",
+      "_ => seq.next_element()?.map(|value| Value::TextValue(discriminator, value)),",
+      "pub const C: Color = Color::GREEN;",
+      "pub const S: &str = \"x\";",
+    ] {
+      assert!(code.contains(expected), "{}\n---\n{}", expected, code);
+    }
+  }
+
+  #[test]
+  fn generate_reopened_module() {
+    let code =
+      generate("module m { struct A { long x; }; }; module m { struct B { A a; }; };").unwrap();
+    assert_eq!(code.matches("pub mod m {").count(), 1, "{}", code);
+    assert!(code.contains("pub a: A,"), "{}", code);
+  }
+
+  #[test]
+  fn generate_nested_keys() {
+    let code = generate(
+      r#"
+      struct Id { @key long id; float weight; };
+      struct Name { string first; string last; };
+      struct Item { @key Id id; @key Name name; sequence<Id> others; };
+    "#,
+    )
+    .unwrap();
+    for expected in &[
+      "::rustdds::dds::traits::Keyed)]\n#[xtypes(appendable)]\npub struct Id {\n",
+      "::rustdds::dds::traits::Key)]\n#[xtypes(appendable)]\npub struct Name {\n",
+      "::rustdds::dds::traits::Keyed)]\n#[xtypes(appendable)]\npub struct Item {\n",
+    ] {
+      assert!(code.contains(expected), "{}\n---\n{}", expected, code);
+    }
+  }
+
+  #[test]
+  fn generate_errors() {
+    assert!(generate("struct A { @key double d; };").is_err());
+    assert!(generate("struct A { @key octet d[40]; };").is_err());
+    assert!(generate("struct A { double d; }; struct B { @key A a; };").is_err());
+    assert!(generate("struct A { sequence<octet, 4> s[40][40]; };").is_err());
+    assert!(generate("union U switch (long) { case 1: long a_b; case 2: long aB; };").is_err());
+  }
+}
@@ -0,0 +1,218 @@
+//! Generates Rust types for RustDDS from OMG IDL 4 type definitions.
+//!
+//! The generated code has
+//! * a `struct` for each IDL structure, with an inherited base structure
+//!   flattened into it,
+//! * an `enum` for each IDL enumeration and union,
+//! * a type alias for each typedef, a `const` for each constant and a
+//!   `mod` for each module.
+//!
+//! All types implement the serde traits, so that they can be used with the
+//! CDR serializer adapters of RustDDS. Structures with `@key` members
+//! derive [`Keyed`](../rustdds/dds/traits/trait.Keyed.html), with the key
+//! members marked `#[key]`. Enumerations, and other types that can be keys,
+//! derive [`Key`](../rustdds/dds/traits/trait.Key.html).
+//!
+//! Extensibility annotations (`@final`, `@appendable`, `@mutable`) and member
+//! ids are carried over to the derived `TypeSupport` implementation, which
+//! describes the type to remote participants. The serializer adapter
+//! must be chosen to match, e.g. `CDR2SerializerAdapter` for final types and
+//! `DelimitedCDR2SerializerAdapter` for appendable ones.
+//!
+//! IDL types map to Rust types as follows:
+//!
+//! | IDL | Rust |
+//! |-----|------|
+//! | `boolean` | `bool` |
+//! | `octet`, `char`, `uint8` | `u8` |
+//! | `int8`, `short`, `long`, `long long` | `i8`, `i16`, `i32`, `i64` |
+//! | `unsigned short`, `unsigned long`, `unsigned long long` | `u16`, `u32`, `u64` |
+//! | `float`, `double` | `f32`, `f64` |
+//! | `string`, `string<N>` | `String` |
+//! | `sequence<T>`, `sequence<T, N>` | `Vec<T>` |
+//! | `T x[N][M]` | `[[T; M]; N]` |
+//! | `@optional T` | `Option<T>` |
+//!
+//! The bound of a string or sequence member is checked with
+//! [`Bound`](../rustdds/serialization/struct.Bound.html) when the member is
+//! serialized and deserialized. Bounds of nested and optional types are only
+//! documented on the generated members. Arrays of more than 32 elements are supported as members
+//! only. `wchar`, `wstring`, `long double`, `fixed`, `any`, `map`, bitsets and
+//! bitmasks are not supported.
+//!
+//! # Usage
+//!
+//! The generated code uses `serde` (with the `derive` feature) and `rustdds`,
+//! so the crate must depend on both. Code is usually generated in the build
+//! script:
+//!
+//! ```no_run
+//! // in build.rs main()
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! rustdds_idlgen::Builder::new()
+//!   .input("idl/shapes.idl")
+//!   .include_dir("idl/common")
+//!   .write_to(std::path::Path::new(&out_dir).join("shapes.rs"))
+//!   .unwrap();
+//! ```
+//!
+//! and included in the crate with
+//! `include!(concat!(env!("OUT_DIR"), "/shapes.rs"));`.
+//!
+//! ROS 2 `.msg`, `.srv` and `.action` files are handled by
+//! [`ros2::Builder`](ros2/struct.Builder.html).
+
+use std::{
+  collections::HashSet,
+  fmt, fs, io,
+  path::{Path, PathBuf},
+};
+
+mod codegen;
+mod parser;
+pub mod ros2;
+
+use parser::{Located, Token};
+
+/// IDL syntax error, unsupported IDL feature or I/O error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+  message: String,
+}
+
+impl Error {
+  pub(crate) fn new(message: String) -> Error {
+    Error { message }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&self.message)
+  }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+  fn from(e: io::Error) -> Error {
+    Error::new(e.to_string())
+  }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Generates Rust code from IDL given as a string. `#include` directives
+/// are not supported here, see [`Builder`](struct.Builder.html).
+///
+/// ```
+/// let code = rustdds_idlgen::generate("struct Point { @key long id; double x; };").unwrap();
+/// assert!(code.contains("pub struct Point"));
+/// ```
+pub fn generate(idl: &str) -> Result<String> {
+  let file_names = vec!["IDL".to_string()];
+  let tokens = parser::tokenize(idl, 0, &file_names[0])?;
+  let specification = parser::parse(tokens, &file_names)?;
+  codegen::generate(&specification)
+}
+
+/// Generates Rust code from IDL files
+///
+/// `#include` directives are expanded, looking for the file first relative to
+/// the including file and then in the include directories. Each file is
+/// included only once. Other preprocessor directives are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+  inputs: Vec<PathBuf>,
+  include_dirs: Vec<PathBuf>,
+}
+
+impl Builder {
+  pub fn new() -> Builder {
+    Builder::default()
+  }
+
+  /// Adds an IDL file. The files are processed in the order they were added.
+  pub fn input<P: AsRef<Path>>(mut self, path: P) -> Builder {
+    self.inputs.push(path.as_ref().to_path_buf());
+    self
+  }
+
+  /// Adds a directory to search for included files
+  pub fn include_dir<P: AsRef<Path>>(mut self, path: P) -> Builder {
+    self.include_dirs.push(path.as_ref().to_path_buf());
+    self
+  }
+
+  /// Generates the code and returns it
+  pub fn generate(&self) -> Result<String> {
+    self.generate_with_files().map(|(code, _)| code)
+  }
+
+  /// Generates the code into a file. Also tells Cargo to run the build
+  /// script again if any of the read IDL files changes, so this is meant to be
+  /// called from a build script.
+  pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    let (code, files) = self.generate_with_files()?;
+    for file in files {
+      println!("cargo:rerun-if-changed={}", file.display());
+    }
+    fs::write(path, code)?;
+    Ok(())
+  }
+
+  fn generate_with_files(&self) -> Result<(String, Vec<PathBuf>)> {
+    let mut files = Vec::new();
+    let mut tokens = Vec::new();
+    let mut seen = HashSet::new();
+    for input in &self.inputs {
+      self.read_file(input, &mut files, &mut seen, &mut tokens)?;
+    }
+    let file_names: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+    let specification = parser::parse(tokens, &file_names)?;
+    Ok((codegen::generate(&specification)?, files))
+  }
+
+  // Appends the tokens of the file, with included files expanded in place.
+  fn read_file(
+    &self,
+    path: &Path,
+    files: &mut Vec<PathBuf>,
+    seen: &mut HashSet<PathBuf>,
+    tokens: &mut Vec<Located>,
+  ) -> Result<()> {
+    let canonical =
+      fs::canonicalize(path).map_err(|e| Error::new(format!("{}: {}", path.display(), e)))?;
+    if !seen.insert(canonical) {
+      return Ok(());
+    }
+    let idl =
+      fs::read_to_string(path).map_err(|e| Error::new(format!("{}: {}", path.display(), e)))?;
+    let index = files.len();
+    files.push(path.to_path_buf());
+    for located in parser::tokenize(&idl, index, &path.display().to_string())? {
+      match &located.token {
+        Token::Include(name) => {
+          let candidates = path
+            .parent()
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name));
+          match candidates.into_iter().find(|p| p.is_file()) {
+            Some(included) => self.read_file(&included, files, seen, tokens)?,
+            None => {
+              return Err(Error::new(format!(
+                "{}:{}: cannot find included file {}",
+                path.display(),
+                located.line,
+                name
+              )))
+            }
+          }
+        }
+        _ => tokens.push(located),
+      }
+    }
+    Ok(())
+  }
+}
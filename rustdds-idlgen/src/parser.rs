@@ -0,0 +1,1233 @@
+// Parser for the data type subset of OMG IDL 4: modules, structs, unions,
+// enums, typedefs and constants.
+//
+// IDL requires declaration before use, so names are resolved to absolute
+// scoped names while parsing.
+
+use std::{collections::HashMap, convert::TryFrom};
+
+use crate::Error;
+
+pub(crate) type Path = Vec<String>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Extensibility {
+  Final,
+  Appendable,
+  Mutable,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TypeSpec {
+  Boolean,
+  Octet,
+  Char,
+  Int8,
+  Int16,
+  Int32,
+  Int64,
+  UInt8,
+  UInt16,
+  UInt32,
+  UInt64,
+  Float,
+  Double,
+  String {
+    bound: Option<u32>,
+  },
+  Sequence {
+    element: Box<TypeSpec>,
+    bound: Option<u32>,
+  },
+  Array {
+    element: Box<TypeSpec>,
+    dimensions: Vec<u32>,
+  },
+  // Absolute scoped name of a struct, union, enum or typedef
+  Named(Path),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ConstValue {
+  Integer(i64),
+  Float(f64),
+  Boolean(bool),
+  Char(u8),
+  String(String),
+  Enumerator {
+    enum_path: Path,
+    name: String,
+    index: i64,
+  },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Member {
+  pub name: String,
+  pub type_spec: TypeSpec,
+  pub id: u32,
+  pub key: bool,
+  pub optional: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct StructDef {
+  pub name: String,
+  pub extensibility: Extensibility,
+  // Members of the base type come first.
+  pub members: Vec<Member>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct UnionCase {
+  pub labels: Vec<ConstValue>,
+  pub default: bool,
+  pub name: String,
+  pub type_spec: TypeSpec,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct UnionDef {
+  pub name: String,
+  pub extensibility: Extensibility,
+  pub discriminator: TypeSpec,
+  pub cases: Vec<UnionCase>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct EnumDef {
+  pub name: String,
+  pub extensibility: Extensibility,
+  pub enumerators: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Definition {
+  Module {
+    name: String,
+    definitions: Vec<Definition>,
+  },
+  Struct(StructDef),
+  Union(UnionDef),
+  Enum(EnumDef),
+  Typedef {
+    name: String,
+    type_spec: TypeSpec,
+  },
+  Const {
+    name: String,
+    const_type: TypeSpec,
+    value: ConstValue,
+  },
+}
+
+// What a scoped name refers to
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Symbol {
+  Module,
+  // None until the definition is complete, i.e. forward declared
+  Struct(Option<StructDef>),
+  Union(Option<UnionDef>),
+  Enum(EnumDef),
+  Typedef(TypeSpec),
+  Const(ConstValue),
+}
+
+pub(crate) struct Specification {
+  pub definitions: Vec<Definition>,
+  // Indexed by absolute scoped name
+  pub symbols: HashMap<Path, Symbol>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+  Ident(String),
+  Number(String),
+  Str(String),
+  Char(String),
+  Punct(&'static str),
+  // #include directive, expanded by the caller
+  Include(String),
+}
+
+// Token with its source location for error messages
+#[derive(Clone, Debug)]
+pub(crate) struct Located {
+  pub token: Token,
+  pub file: usize,
+  pub line: usize,
+}
+
+// "<<" and ">>" are not tokens, so that nested templates like
+// sequence<sequence<long>> work. Shift expressions look for two tokens.
+const PUNCTUATION: &[&str] = &[
+  "::", "{", "}", "(", ")", "<", ">", "[", "]", ";", ",", ":", "=", "@", "+", "-", "*", "/", "%",
+  "|", "&", "^", "~",
+];
+
+pub(crate) fn tokenize(idl: &str, file: usize, file_name: &str) -> Result<Vec<Located>, Error> {
+  let mut tokens = Vec::new();
+  let chars: Vec<char> = idl.chars().collect();
+  let mut i = 0;
+  let mut line = 1;
+  let mut line_start = true;
+  let error =
+    |line: usize, message: String| Error::new(format!("{}:{}: {}", file_name, line, message));
+  while i < chars.len() {
+    let c = chars[i];
+    if c == '\n' {
+      line += 1;
+      line_start = true;
+      i += 1;
+    } else if c.is_whitespace() {
+      i += 1;
+    } else if c == '#' && line_start {
+      // Preprocessor directive. Only #include is understood.
+      let start = i;
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+      let directive: String = chars[start + 1..i].iter().collect();
+      let directive = directive.trim();
+      if let Some(rest) = directive.strip_prefix("include") {
+        let rest = rest.trim();
+        let path = rest
+          .strip_prefix('"')
+          .and_then(|r| r.strip_suffix('"'))
+          .or_else(|| rest.strip_prefix('<').and_then(|r| r.strip_suffix('>')));
+        match path {
+          Some(path) => tokens.push(Located {
+            token: Token::Include(path.to_string()),
+            file,
+            line,
+          }),
+          None => return Err(error(line, format!("bad #include {}", rest))),
+        }
+      }
+    } else if chars[i..].starts_with(&['/', '/']) {
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+    } else if chars[i..].starts_with(&['/', '*']) {
+      i += 2;
+      while i < chars.len() && !chars[i..].starts_with(&['*', '/']) {
+        if chars[i] == '\n' {
+          line += 1;
+        }
+        i += 1;
+      }
+      i += 2;
+    } else {
+      line_start = false;
+      let start = i;
+      let token = if c.is_alphabetic() || c == '_' {
+        while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+          i += 1;
+        }
+        Token::Ident(chars[start..i].iter().collect())
+      } else if c.is_ascii_digit()
+        || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit())
+      {
+        while i < chars.len()
+          && (chars[i].is_alphanumeric()
+            || chars[i] == '.'
+            || ((chars[i] == '+' || chars[i] == '-') && matches!(chars[i - 1], 'e' | 'E')))
+        {
+          i += 1;
+        }
+        Token::Number(chars[start..i].iter().collect())
+      } else if c == '"' || c == '\'' {
+        i += 1;
+        while i < chars.len() && chars[i] != c {
+          if chars[i] == '\\' {
+            i += 1;
+          }
+          i += 1;
+        }
+        if i >= chars.len() {
+          return Err(error(line, "unterminated literal".to_string()));
+        }
+        i += 1;
+        let content = unescape(&chars[start + 1..i - 1])
+          .ok_or_else(|| error(line, "bad escape sequence".to_string()))?;
+        if c == '"' {
+          Token::Str(content)
+        } else {
+          Token::Char(content)
+        }
+      } else {
+        let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+        match PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+          Some(p) => {
+            i += p.len();
+            Token::Punct(p)
+          }
+          None => return Err(error(line, format!("unexpected character {:?}", c))),
+        }
+      };
+      tokens.push(Located { token, file, line });
+    }
+  }
+  Ok(tokens)
+}
+
+fn unescape(chars: &[char]) -> Option<String> {
+  let mut s = String::new();
+  let mut i = 0;
+  while i < chars.len() {
+    if chars[i] != '\\' {
+      s.push(chars[i]);
+      i += 1;
+      continue;
+    }
+    i += 1;
+    let c = match chars.get(i)? {
+      'n' => '\n',
+      't' => '\t',
+      'r' => '\r',
+      '0' => '\0',
+      'x' => {
+        let hex: String = chars[i + 1..]
+          .iter()
+          .take(2)
+          .take_while(|c| c.is_ascii_hexdigit())
+          .collect();
+        i += hex.len();
+        char::from(u8::from_str_radix(&hex, 16).ok()?)
+      }
+      c => *c, // \\ \' \" \?
+    };
+    s.push(c);
+    i += 1;
+  }
+  Some(s)
+}
+
+// Annotations that are relevant to code generation
+#[derive(Default)]
+struct Annotations {
+  key: bool,
+  optional: bool,
+  id: Option<u32>,
+  extensibility: Option<Extensibility>,
+}
+
+pub(crate) struct Parser<'a> {
+  tokens: Vec<Located>,
+  file_names: &'a [String],
+  pos: usize,
+  scope: Path,
+  symbols: HashMap<Path, Symbol>,
+}
+
+pub(crate) fn parse(tokens: Vec<Located>, file_names: &[String]) -> Result<Specification, Error> {
+  let mut parser = Parser {
+    tokens,
+    file_names,
+    pos: 0,
+    scope: Vec::new(),
+    symbols: HashMap::new(),
+  };
+  let mut definitions = Vec::new();
+  while !parser.at_end() {
+    definitions.extend(parser.definition()?);
+  }
+  Ok(Specification {
+    definitions,
+    symbols: parser.symbols,
+  })
+}
+
+impl<'a> Parser<'a> {
+  fn at_end(&self) -> bool {
+    self.pos >= self.tokens.len()
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos).map(|t| &t.token)
+  }
+
+  fn peek_at(&self, offset: usize) -> Option<&Token> {
+    self.tokens.get(self.pos + offset).map(|t| &t.token)
+  }
+
+  fn error<T>(&self, message: &str) -> Result<T, Error> {
+    let location = match self.tokens.get(self.pos).or_else(|| self.tokens.last()) {
+      Some(t) => format!("{}:{}", self.file_names[t.file], t.line),
+      None => "IDL".to_string(),
+    };
+    Err(Error::new(format!("{}: {}", location, message)))
+  }
+
+  fn next(&mut self) -> Result<Token, Error> {
+    match self.tokens.get(self.pos) {
+      Some(t) => {
+        self.pos += 1;
+        Ok(t.token.clone())
+      }
+      None => self.error("unexpected end of input"),
+    }
+  }
+
+  fn is_punct(&self, p: &str) -> bool {
+    matches!(self.peek(), Some(Token::Punct(q)) if *q == p)
+  }
+
+  fn is_keyword(&self, k: &str) -> bool {
+    matches!(self.peek(), Some(Token::Ident(i)) if i == k)
+  }
+
+  fn accept_punct(&mut self, p: &str) -> bool {
+    let found = self.is_punct(p);
+    if found {
+      self.pos += 1;
+    }
+    found
+  }
+
+  fn accept_keyword(&mut self, k: &str) -> bool {
+    let found = self.is_keyword(k);
+    if found {
+      self.pos += 1;
+    }
+    found
+  }
+
+  fn expect_punct(&mut self, p: &str) -> Result<(), Error> {
+    if self.accept_punct(p) {
+      Ok(())
+    } else {
+      self.error(&format!("expected \"{}\"", p))
+    }
+  }
+
+  fn identifier(&mut self) -> Result<String, Error> {
+    match self.next()? {
+      // Leading underscore escapes IDL keywords.
+      Token::Ident(i) => Ok(i.strip_prefix('_').map(String::from).unwrap_or(i)),
+      other => {
+        self.pos -= 1;
+        self.error(&format!("expected identifier, found {:?}", other))
+      }
+    }
+  }
+
+  // Returns the name as written, with an empty first element if it is
+  // absolute.
+  fn scoped_name(&mut self) -> Result<Path, Error> {
+    let mut name = Vec::new();
+    if self.accept_punct("::") {
+      name.push(String::new());
+    }
+    name.push(self.identifier()?);
+    while self.accept_punct("::") {
+      name.push(self.identifier()?);
+    }
+    Ok(name)
+  }
+
+  fn qualified(&self, name: &str) -> Path {
+    let mut path = self.scope.clone();
+    path.push(name.to_string());
+    path
+  }
+
+  // Relative names are looked up from the innermost scope outwards.
+  fn lookup(&self, name: &[String]) -> Option<(Path, &Symbol)> {
+    if name[0].is_empty() {
+      let path = name[1..].to_vec();
+      return self.symbols.get(&path).map(|s| (path, s));
+    }
+    (0..=self.scope.len()).rev().find_map(|depth| {
+      let mut path = self.scope[..depth].to_vec();
+      path.extend_from_slice(name);
+      self.symbols.get(&path).map(|s| (path, s))
+    })
+  }
+
+  fn define(&mut self, name: &str, symbol: Symbol) -> Result<Path, Error> {
+    let path = self.qualified(name);
+    match (self.symbols.get(&path), &symbol) {
+      (None, _)
+      | (Some(Symbol::Module), Symbol::Module)
+      | (Some(Symbol::Struct(None)), Symbol::Struct(_))
+      | (Some(Symbol::Union(None)), Symbol::Union(_)) => {
+        self.symbols.insert(path.clone(), symbol);
+        Ok(path)
+      }
+      _ => self.error(&format!("{} is defined twice", name)),
+    }
+  }
+
+  // Forward declarations produce no definitions, and a typedef with several
+  // declarators produces one for each.
+  fn definition(&mut self) -> Result<Vec<Definition>, Error> {
+    if let Some(Token::Include(_)) = self.peek() {
+      return self.error("#include is supported only when reading files");
+    }
+    let annotations = self.annotations()?;
+    let definition = if self.accept_keyword("module") {
+      let name = self.identifier()?;
+      self.define(&name, Symbol::Module)?;
+      self.expect_punct("{")?;
+      self.scope.push(name.clone());
+      let mut definitions = Vec::new();
+      while !self.accept_punct("}") {
+        definitions.extend(self.definition()?);
+      }
+      self.scope.pop();
+      Some(Definition::Module { name, definitions })
+    } else if self.accept_keyword("struct") {
+      self.struct_definition(annotations)?.map(Definition::Struct)
+    } else if self.accept_keyword("union") {
+      self.union_definition(annotations)?.map(Definition::Union)
+    } else if self.accept_keyword("enum") {
+      Some(Definition::Enum(self.enum_definition(annotations)?))
+    } else if self.accept_keyword("typedef") {
+      let base = self.type_spec()?;
+      let mut typedefs = Vec::new();
+      for (name, type_spec) in self.declarators(&base)? {
+        self.define(&name, Symbol::Typedef(type_spec.clone()))?;
+        typedefs.push(Definition::Typedef { name, type_spec });
+      }
+      self.expect_punct(";")?;
+      return Ok(typedefs);
+    } else if self.accept_keyword("const") {
+      let const_type = self.type_spec()?;
+      let name = self.identifier()?;
+      self.expect_punct("=")?;
+      let value = self.const_expr()?;
+      let value = self.convert_const(&const_type, value)?;
+      self.define(&name, Symbol::Const(value.clone()))?;
+      Some(Definition::Const {
+        name,
+        const_type,
+        value,
+      })
+    } else if self.is_keyword("bitmask") || self.is_keyword("bitset") {
+      return self.error("bitmasks and bitsets are not supported");
+    } else if self.is_keyword("interface") || self.is_keyword("valuetype") {
+      return self.error("interfaces and valuetypes are not supported");
+    } else {
+      return self.error("expected a definition");
+    };
+    self.expect_punct(";")?;
+    Ok(definition.into_iter().collect())
+  }
+
+  fn annotations(&mut self) -> Result<Annotations, Error> {
+    let mut a = Annotations::default();
+    while self.accept_punct("@") {
+      let name = self.scoped_name()?;
+      let mut args = Vec::new();
+      if self.accept_punct("(") {
+        let mut depth = 0;
+        loop {
+          let t = self.next()?;
+          match &t {
+            Token::Punct("(") => depth += 1,
+            Token::Punct(")") if depth == 0 => break,
+            Token::Punct(")") => depth -= 1,
+            _ => (),
+          }
+          args.push(t);
+        }
+      }
+      let arg_ident = match args.as_slice() {
+        [Token::Ident(i)] => Some(i.to_uppercase()),
+        _ => None,
+      };
+      let flag = arg_ident.as_deref() != Some("FALSE");
+      match name.last().map(String::as_str) {
+        Some("key") => a.key = flag,
+        Some("optional") => a.optional = flag,
+        Some("final") => a.extensibility = Some(Extensibility::Final),
+        Some("appendable") => a.extensibility = Some(Extensibility::Appendable),
+        Some("mutable") => a.extensibility = Some(Extensibility::Mutable),
+        Some("extensibility") => {
+          a.extensibility = match arg_ident.as_deref() {
+            Some("FINAL") => Some(Extensibility::Final),
+            Some("APPENDABLE") => Some(Extensibility::Appendable),
+            Some("MUTABLE") => Some(Extensibility::Mutable),
+            _ => return self.error("bad @extensibility"),
+          }
+        }
+        Some("id") => match args.as_slice() {
+          [Token::Number(n)] => match parse_integer(n) {
+            Some(id) if (0..=0x0FFF_FFFF).contains(&id) => a.id = Some(id as u32),
+            _ => return self.error("bad @id"),
+          },
+          _ => return self.error("bad @id"),
+        },
+        Some("autoid") if arg_ident.as_deref() == Some("HASH") => {
+          return self.error("@autoid(HASH) is not supported")
+        }
+        Some("value") => {
+          return self.error("@value is not supported, enumerators are numbered from 0")
+        }
+        _ => (), // e.g. @topic, @nested, @verbatim, @default_literal
+      }
+    }
+    Ok(a)
+  }
+
+  fn struct_definition(&mut self, annotations: Annotations) -> Result<Option<StructDef>, Error> {
+    let name = self.identifier()?;
+    if self.is_punct(";") {
+      self.define(&name, Symbol::Struct(None))?;
+      return Ok(None);
+    }
+    let mut members = Vec::new();
+    if self.accept_punct(":") {
+      let base = self.scoped_name()?;
+      match self.lookup(&base) {
+        Some((_, Symbol::Struct(Some(s)))) => members = s.members.clone(),
+        _ => return self.error(&format!("{} is not a defined structure", base.join("::"))),
+      }
+    }
+    // Forward declaration, so that the struct can refer to itself
+    self.define(&name, Symbol::Struct(None))?;
+    // Member ids continue after those of the base type.
+    let mut next_id = members.iter().map(|m| m.id + 1).max().unwrap_or(0);
+    self.expect_punct("{")?;
+    while !self.accept_punct("}") {
+      let member_annotations = self.annotations()?;
+      let type_spec = self.type_spec()?;
+      for (member_name, type_spec) in self.declarators(&type_spec)? {
+        let id = member_annotations.id.unwrap_or(next_id);
+        if members.iter().any(|m: &Member| m.id == id) {
+          return self.error(&format!("duplicate member id {}", id));
+        }
+        if members.iter().any(|m: &Member| m.name == member_name) {
+          return self.error(&format!("duplicate member {}", member_name));
+        }
+        if member_annotations.key && member_annotations.optional {
+          return self.error("key members cannot be optional");
+        }
+        next_id = id + 1;
+        members.push(Member {
+          name: member_name,
+          type_spec,
+          id,
+          key: member_annotations.key,
+          optional: member_annotations.optional,
+        });
+      }
+      self.expect_punct(";")?;
+    }
+    let definition = StructDef {
+      name: name.clone(),
+      extensibility: annotations
+        .extensibility
+        .unwrap_or(Extensibility::Appendable),
+      members,
+    };
+    self.define(&name, Symbol::Struct(Some(definition.clone())))?;
+    Ok(Some(definition))
+  }
+
+  fn union_definition(&mut self, annotations: Annotations) -> Result<Option<UnionDef>, Error> {
+    let name = self.identifier()?;
+    if self.is_punct(";") {
+      self.define(&name, Symbol::Union(None))?;
+      return Ok(None);
+    }
+    self.define(&name, Symbol::Union(None))?;
+    if !self.accept_keyword("switch") {
+      return self.error("expected switch");
+    }
+    self.expect_punct("(")?;
+    let discriminator = self.type_spec()?;
+    match self.resolve(&discriminator) {
+      TypeSpec::Boolean
+      | TypeSpec::Octet
+      | TypeSpec::Char
+      | TypeSpec::Int8
+      | TypeSpec::Int16
+      | TypeSpec::Int32
+      | TypeSpec::Int64
+      | TypeSpec::UInt8
+      | TypeSpec::UInt16
+      | TypeSpec::UInt32
+      | TypeSpec::UInt64 => (),
+      TypeSpec::Named(path) if matches!(self.symbols.get(&path), Some(Symbol::Enum(_))) => (),
+      _ => return self.error("union discriminator must be an integer, char, boolean or enum"),
+    }
+    self.expect_punct(")")?;
+    self.expect_punct("{")?;
+    let mut cases: Vec<UnionCase> = Vec::new();
+    while !self.accept_punct("}") {
+      let mut labels = Vec::new();
+      let mut default = false;
+      loop {
+        if self.accept_keyword("case") {
+          let label = self.const_expr()?;
+          labels.push(self.convert_const(&discriminator, label)?);
+        } else if self.accept_keyword("default") {
+          if default || cases.iter().any(|c| c.default) {
+            return self.error("more than one default case");
+          }
+          default = true;
+        } else {
+          break;
+        }
+        self.expect_punct(":")?;
+      }
+      if labels.is_empty() && !default {
+        return self.error("expected case or default");
+      }
+      if labels
+        .iter()
+        .any(|l| cases.iter().any(|c| c.labels.contains(l)))
+      {
+        return self.error("duplicate case label");
+      }
+      let member_annotations = self.annotations()?;
+      if member_annotations.key || member_annotations.optional {
+        return self.error("union members cannot be keys or optional");
+      }
+      let type_spec = self.type_spec()?;
+      let mut declarators = self.declarators(&type_spec)?;
+      if declarators.len() != 1 {
+        return self.error("expected one union member per case");
+      }
+      let (member_name, type_spec) = declarators.pop().unwrap();
+      self.expect_punct(";")?;
+      cases.push(UnionCase {
+        labels,
+        default,
+        name: member_name,
+        type_spec,
+      });
+    }
+    let definition = UnionDef {
+      name: name.clone(),
+      extensibility: annotations
+        .extensibility
+        .unwrap_or(Extensibility::Appendable),
+      discriminator,
+      cases,
+    };
+    self.define(&name, Symbol::Union(Some(definition.clone())))?;
+    Ok(Some(definition))
+  }
+
+  fn enum_definition(&mut self, annotations: Annotations) -> Result<EnumDef, Error> {
+    let name = self.identifier()?;
+    self.expect_punct("{")?;
+    let enum_path = self.qualified(&name);
+    let mut enumerators = Vec::new();
+    loop {
+      self.annotations()?;
+      let enumerator = self.identifier()?;
+      // Enumerators are constants in the enclosing scope.
+      let value = ConstValue::Enumerator {
+        enum_path: enum_path.clone(),
+        name: enumerator.clone(),
+        index: enumerators.len() as i64,
+      };
+      self.define(&enumerator, Symbol::Const(value))?;
+      enumerators.push(enumerator);
+      if !self.accept_punct(",") || self.is_punct("}") {
+        break;
+      }
+    }
+    self.expect_punct("}")?;
+    let definition = EnumDef {
+      name: name.clone(),
+      extensibility: annotations
+        .extensibility
+        .unwrap_or(Extensibility::Appendable),
+      enumerators,
+    };
+    self.define(&name, Symbol::Enum(definition.clone()))?;
+    Ok(definition)
+  }
+
+  // One or more comma-separated names, each possibly with array dimensions
+  fn declarators(&mut self, base: &TypeSpec) -> Result<Vec<(String, TypeSpec)>, Error> {
+    let mut declarators = Vec::new();
+    loop {
+      let name = self.identifier()?;
+      let mut dimensions = Vec::new();
+      while self.accept_punct("[") {
+        dimensions.push(self.positive_int()?);
+        self.expect_punct("]")?;
+      }
+      let type_spec = if dimensions.is_empty() {
+        base.clone()
+      } else {
+        TypeSpec::Array {
+          element: Box::new(base.clone()),
+          dimensions,
+        }
+      };
+      declarators.push((name, type_spec));
+      if !self.accept_punct(",") {
+        return Ok(declarators);
+      }
+    }
+  }
+
+  fn type_spec(&mut self) -> Result<TypeSpec, Error> {
+    let first = match self.peek() {
+      Some(Token::Ident(i)) => i.clone(),
+      Some(Token::Punct("::")) => return self.named_type(),
+      _ => return self.error("expected a type"),
+    };
+    let t = match first.as_str() {
+      "boolean" => TypeSpec::Boolean,
+      "octet" => TypeSpec::Octet,
+      "char" => TypeSpec::Char,
+      "int8" => TypeSpec::Int8,
+      "uint8" => TypeSpec::UInt8,
+      "short" | "int16" => TypeSpec::Int16,
+      "int32" => TypeSpec::Int32,
+      "int64" => TypeSpec::Int64,
+      "uint16" => TypeSpec::UInt16,
+      "uint32" => TypeSpec::UInt32,
+      "uint64" => TypeSpec::UInt64,
+      "float" => TypeSpec::Float,
+      "double" => TypeSpec::Double,
+      "long" => {
+        self.pos += 1;
+        if self.is_keyword("double") {
+          return self.error("long double is not supported");
+        }
+        return Ok(if self.accept_keyword("long") {
+          TypeSpec::Int64
+        } else {
+          TypeSpec::Int32
+        });
+      }
+      "unsigned" => {
+        self.pos += 1;
+        return if self.accept_keyword("short") {
+          Ok(TypeSpec::UInt16)
+        } else if self.accept_keyword("long") {
+          Ok(if self.accept_keyword("long") {
+            TypeSpec::UInt64
+          } else {
+            TypeSpec::UInt32
+          })
+        } else {
+          self.error("expected short or long")
+        };
+      }
+      "string" => {
+        self.pos += 1;
+        let bound = if self.accept_punct("<") {
+          let b = self.positive_int()?;
+          self.expect_punct(">")?;
+          Some(b)
+        } else {
+          None
+        };
+        return Ok(TypeSpec::String { bound });
+      }
+      "sequence" => {
+        self.pos += 1;
+        self.expect_punct("<")?;
+        let element = self.type_spec()?;
+        let bound = if self.accept_punct(",") {
+          Some(self.positive_int()?)
+        } else {
+          None
+        };
+        self.expect_punct(">")?;
+        return Ok(TypeSpec::Sequence {
+          element: Box::new(element),
+          bound,
+        });
+      }
+      "wchar" | "wstring" | "any" | "fixed" | "map" | "Object" | "ValueBase" => {
+        return self.error(&format!("{} is not supported", first))
+      }
+      _ => return self.named_type(),
+    };
+    self.pos += 1;
+    Ok(t)
+  }
+
+  fn named_type(&mut self) -> Result<TypeSpec, Error> {
+    let name = self.scoped_name()?;
+    match self.lookup(&name) {
+      Some((path, Symbol::Struct(_)))
+      | Some((path, Symbol::Union(_)))
+      | Some((path, Symbol::Enum(_)))
+      | Some((path, Symbol::Typedef(_))) => Ok(TypeSpec::Named(path)),
+      _ => self.error(&format!("unknown type {}", name.join("::"))),
+    }
+  }
+
+  // Follows typedefs
+  pub(crate) fn resolve(&self, type_spec: &TypeSpec) -> TypeSpec {
+    resolve(&self.symbols, type_spec)
+  }
+
+  fn positive_int(&mut self) -> Result<u32, Error> {
+    match self.const_expr()? {
+      ConstValue::Integer(v) if v > 0 && v <= i64::from(u32::MAX) => Ok(v as u32),
+      v => self.error(&format!("expected a positive integer, got {:?}", v)),
+    }
+  }
+
+  // Checks that the value fits the declared constant type.
+  fn convert_const(&self, const_type: &TypeSpec, value: ConstValue) -> Result<ConstValue, Error> {
+    let range = |min: i64, max: i64| match value {
+      ConstValue::Integer(v) if v >= min && v <= max => Ok(value.clone()),
+      _ => self.error(&format!("{:?} is not of type {:?}", value, const_type)),
+    };
+    match self.resolve(const_type) {
+      TypeSpec::Int8 => range(i8::MIN.into(), i8::MAX.into()),
+      TypeSpec::Int16 => range(i16::MIN.into(), i16::MAX.into()),
+      TypeSpec::Int32 => range(i32::MIN.into(), i32::MAX.into()),
+      TypeSpec::Int64 => range(i64::MIN, i64::MAX),
+      TypeSpec::UInt64 => range(0, i64::MAX),
+      TypeSpec::UInt8 | TypeSpec::Octet => range(0, u8::MAX.into()),
+      TypeSpec::UInt16 => range(0, u16::MAX.into()),
+      TypeSpec::UInt32 => range(0, u32::MAX.into()),
+      TypeSpec::Float | TypeSpec::Double => match value {
+        ConstValue::Float(_) => Ok(value),
+        ConstValue::Integer(v) => Ok(ConstValue::Float(v as f64)),
+        _ => self.error(&format!("{:?} is not a floating point number", value)),
+      },
+      TypeSpec::Boolean => match value {
+        ConstValue::Boolean(_) => Ok(value),
+        _ => self.error(&format!("{:?} is not a boolean", value)),
+      },
+      TypeSpec::Char => match value {
+        ConstValue::Char(_) => Ok(value),
+        _ => self.error(&format!("{:?} is not a char", value)),
+      },
+      TypeSpec::String { bound } => match &value {
+        ConstValue::String(s) if !matches!(bound, Some(b) if s.len() > b as usize) => Ok(value),
+        _ => self.error(&format!("{:?} is not a string of the declared type", value)),
+      },
+      TypeSpec::Named(path) => match &value {
+        ConstValue::Enumerator { enum_path, .. } if *enum_path == path => Ok(value),
+        _ => self.error(&format!(
+          "{:?} is not an enumerator of {}",
+          value,
+          path.join("::")
+        )),
+      },
+      _ => self.error("constants must be of a primitive, string or enum type"),
+    }
+  }
+
+  // Constant expressions, with C operator precedence. Arithmetic is defined
+  // for integers, and for floating point numbers except the bit operations.
+  fn const_expr(&mut self) -> Result<ConstValue, Error> {
+    let mut value = self.xor_expr()?;
+    while self.accept_punct("|") {
+      let rhs = self.xor_expr()?;
+      value = self.integer_op(value, rhs, |a, b| Some(a | b))?;
+    }
+    Ok(value)
+  }
+
+  fn xor_expr(&mut self) -> Result<ConstValue, Error> {
+    let mut value = self.and_expr()?;
+    while self.accept_punct("^") {
+      let rhs = self.and_expr()?;
+      value = self.integer_op(value, rhs, |a, b| Some(a ^ b))?;
+    }
+    Ok(value)
+  }
+
+  fn and_expr(&mut self) -> Result<ConstValue, Error> {
+    let mut value = self.shift_expr()?;
+    while self.accept_punct("&") {
+      let rhs = self.shift_expr()?;
+      value = self.integer_op(value, rhs, |a, b| Some(a & b))?;
+    }
+    Ok(value)
+  }
+
+  fn shift_expr(&mut self) -> Result<ConstValue, Error> {
+    let mut value = self.add_expr()?;
+    loop {
+      let shift = match (self.peek(), self.peek_at(1)) {
+        (Some(Token::Punct("<")), Some(Token::Punct("<"))) => "<<",
+        (Some(Token::Punct(">")), Some(Token::Punct(">"))) => ">>",
+        _ => return Ok(value),
+      };
+      self.pos += 2;
+      let rhs = self.add_expr()?;
+      value = self.integer_op(value, rhs, |a, b| {
+        let b = u32::try_from(b).ok().filter(|b| *b < 64)?;
+        Some(if shift == "<<" { a << b } else { a >> b })
+      })?;
+    }
+  }
+
+  fn add_expr(&mut self) -> Result<ConstValue, Error> {
+    let mut value = self.mul_expr()?;
+    loop {
+      if self.accept_punct("+") {
+        let rhs = self.mul_expr()?;
+        value = self.arithmetic_op(value, rhs, i64::checked_add, |a, b| a + b)?;
+      } else if self.accept_punct("-") {
+        let rhs = self.mul_expr()?;
+        value = self.arithmetic_op(value, rhs, i64::checked_sub, |a, b| a - b)?;
+      } else {
+        return Ok(value);
+      }
+    }
+  }
+
+  fn mul_expr(&mut self) -> Result<ConstValue, Error> {
+    let mut value = self.unary_expr()?;
+    loop {
+      if self.accept_punct("*") {
+        let rhs = self.unary_expr()?;
+        value = self.arithmetic_op(value, rhs, i64::checked_mul, |a, b| a * b)?;
+      } else if self.accept_punct("/") {
+        let rhs = self.unary_expr()?;
+        value = self.arithmetic_op(value, rhs, i64::checked_div, |a, b| a / b)?;
+      } else if self.accept_punct("%") {
+        let rhs = self.unary_expr()?;
+        value = self.integer_op(value, rhs, i64::checked_rem)?;
+      } else {
+        return Ok(value);
+      }
+    }
+  }
+
+  fn unary_expr(&mut self) -> Result<ConstValue, Error> {
+    if self.accept_punct("-") {
+      return match self.unary_expr()? {
+        ConstValue::Integer(v) => Ok(ConstValue::Integer(-v)),
+        ConstValue::Float(v) => Ok(ConstValue::Float(-v)),
+        v => self.error(&format!("cannot negate {:?}", v)),
+      };
+    }
+    if self.accept_punct("+") {
+      return self.unary_expr();
+    }
+    if self.accept_punct("~") {
+      return match self.unary_expr()? {
+        ConstValue::Integer(v) => Ok(ConstValue::Integer(!v)),
+        v => self.error(&format!("cannot complement {:?}", v)),
+      };
+    }
+    self.primary_expr()
+  }
+
+  fn primary_expr(&mut self) -> Result<ConstValue, Error> {
+    if self.accept_punct("(") {
+      let value = self.const_expr()?;
+      self.expect_punct(")")?;
+      return Ok(value);
+    }
+    match self.peek().cloned() {
+      Some(Token::Number(n)) => {
+        self.pos += 1;
+        match parse_number(&n) {
+          Some(v) => Ok(v),
+          None => self.error(&format!("bad number {}", n)),
+        }
+      }
+      Some(Token::Str(s)) => {
+        self.pos += 1;
+        // Adjacent string literals are concatenated.
+        let mut s = s;
+        while let Some(Token::Str(more)) = self.peek().cloned() {
+          self.pos += 1;
+          s.push_str(&more);
+        }
+        Ok(ConstValue::String(s))
+      }
+      Some(Token::Char(c)) => {
+        self.pos += 1;
+        match c.chars().collect::<Vec<char>>().as_slice() {
+          [c] if (*c as u32) <= 0xFF => Ok(ConstValue::Char(*c as u32 as u8)),
+          _ => self.error("bad char literal"),
+        }
+      }
+      Some(Token::Ident(i)) if i == "TRUE" || i == "FALSE" => {
+        self.pos += 1;
+        Ok(ConstValue::Boolean(i == "TRUE"))
+      }
+      Some(Token::Ident(_)) | Some(Token::Punct("::")) => {
+        let name = self.scoped_name()?;
+        match self.lookup(&name) {
+          Some((_, Symbol::Const(v))) => Ok(v.clone()),
+          _ => self.error(&format!("unknown constant {}", name.join("::"))),
+        }
+      }
+      _ => self.error("expected a constant"),
+    }
+  }
+
+  fn integer_op(
+    &self,
+    a: ConstValue,
+    b: ConstValue,
+    op: impl Fn(i64, i64) -> Option<i64>,
+  ) -> Result<ConstValue, Error> {
+    match (&a, &b) {
+      (ConstValue::Integer(x), ConstValue::Integer(y)) => match op(*x, *y) {
+        Some(v) => Ok(ConstValue::Integer(v)),
+        None => self.error("integer overflow or division by zero"),
+      },
+      _ => self.error(&format!("expected integers, got {:?} and {:?}", a, b)),
+    }
+  }
+
+  fn arithmetic_op(
+    &self,
+    a: ConstValue,
+    b: ConstValue,
+    integer_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+  ) -> Result<ConstValue, Error> {
+    match (&a, &b) {
+      (ConstValue::Float(x), ConstValue::Float(y)) => Ok(ConstValue::Float(float_op(*x, *y))),
+      (ConstValue::Float(x), ConstValue::Integer(y)) => {
+        Ok(ConstValue::Float(float_op(*x, *y as f64)))
+      }
+      (ConstValue::Integer(x), ConstValue::Float(y)) => {
+        Ok(ConstValue::Float(float_op(*x as f64, *y)))
+      }
+      _ => self.integer_op(a, b, integer_op),
+    }
+  }
+}
+
+pub(crate) fn resolve(symbols: &HashMap<Path, Symbol>, type_spec: &TypeSpec) -> TypeSpec {
+  let mut t = type_spec.clone();
+  // Typedefs cannot be cyclic, since names are declared before use.
+  while let TypeSpec::Named(path) = &t {
+    match symbols.get(path) {
+      Some(Symbol::Typedef(aliased)) => t = aliased.clone(),
+      _ => break,
+    }
+  }
+  t
+}
+
+fn parse_number(s: &str) -> Option<ConstValue> {
+  let is_float = !s.starts_with("0x")
+    && !s.starts_with("0X")
+    && (s.contains('.') || s.contains('e') || s.contains('E'));
+  if is_float {
+    let s = s.trim_end_matches(['d', 'D']);
+    s.parse().ok().map(ConstValue::Float)
+  } else {
+    parse_integer(s).map(ConstValue::Integer)
+  }
+}
+
+fn parse_integer(s: &str) -> Option<i64> {
+  if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+    i64::from_str_radix(hex, 16).ok()
+  } else if s.len() > 1 && s.starts_with('0') {
+    i64::from_str_radix(&s[1..], 8).ok()
+  } else {
+    s.parse().ok()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_str(idl: &str) -> Result<Specification, Error> {
+    let names = vec!["test.idl".to_string()];
+    parse(tokenize(idl, 0, "test.idl")?, &names)
+  }
+
+  #[test]
+  fn parse_definitions() {
+    let spec = parse_str(
+      r#"
+      module shapes {
+        const long MAX = 1 << 4 | 1;
+        const string NAME = "sha" "pes";
+        const double HALF = 1 / 2.0;
+        enum Color { RED, GREEN, BLUE, };
+        const Color FAVORITE = GREEN;
+        @final struct Base { @key long id; };
+        struct Shape : Base {
+          @id(10) string<MAX> color;
+          unsigned long long x, y;
+          @optional sequence<Base, MAX - 1> parts[2];
+        };
+        union Value switch (Color) {
+          case RED: case GREEN: long number;
+          default: string text;
+        };
+        typedef sequence<sequence<octet>> Blobs, Matrix[3][4];
+      };
+    "#,
+    )
+    .unwrap();
+    let symbols = &spec.symbols;
+    let path = |name: &str| name.split("::").map(String::from).collect::<Path>();
+    assert_eq!(
+      symbols[&path("shapes::MAX")],
+      Symbol::Const(ConstValue::Integer(17))
+    );
+    assert_eq!(
+      symbols[&path("shapes::NAME")],
+      Symbol::Const(ConstValue::String("shapes".to_string()))
+    );
+    assert_eq!(
+      symbols[&path("shapes::HALF")],
+      Symbol::Const(ConstValue::Float(0.5))
+    );
+    match &symbols[&path("shapes::FAVORITE")] {
+      Symbol::Const(ConstValue::Enumerator { index: 1, .. }) => (),
+      other => panic!("{:?}", other),
+    }
+    let shape = match &symbols[&path("shapes::Shape")] {
+      Symbol::Struct(Some(s)) => s.clone(),
+      other => panic!("{:?}", other),
+    };
+    assert_eq!(shape.extensibility, Extensibility::Appendable);
+    let ids: Vec<u32> = shape.members.iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec![0, 10, 11, 12, 13]);
+    assert!(shape.members[0].key);
+    assert_eq!(
+      shape.members[1].type_spec,
+      TypeSpec::String { bound: Some(17) }
+    );
+    assert_eq!(
+      shape.members[4].type_spec,
+      TypeSpec::Array {
+        element: Box::new(TypeSpec::Sequence {
+          element: Box::new(TypeSpec::Named(path("shapes::Base"))),
+          bound: Some(16)
+        }),
+        dimensions: vec![2],
+      }
+    );
+    assert!(shape.members[4].optional);
+    match &symbols[&path("shapes::Value")] {
+      Symbol::Union(Some(u)) => {
+        assert_eq!(u.cases.len(), 2);
+        assert_eq!(u.cases[0].labels.len(), 2);
+        assert!(u.cases[1].default);
+      }
+      other => panic!("{:?}", other),
+    }
+    assert!(symbols.contains_key(&path("shapes::Matrix")));
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert!(parse_str("struct A { Unknown x; };").is_err());
+    assert!(parse_str("struct A { long x; }").is_err());
+    assert!(parse_str("struct A { long x[0]; };").is_err());
+    assert!(parse_str("struct A { @id(1) long x; @id(1) long y; };").is_err());
+    assert!(parse_str("struct A { long x; }; struct A { long y; };").is_err());
+    assert!(parse_str("const short S = 100000;").is_err());
+    assert!(parse_str("union U switch (double) { case 1: long x; };").is_err());
+    assert!(parse_str("union U switch (long) { case 1: long x; case 1: long y; };").is_err());
+    assert!(parse_str("struct A { wstring s; };").is_err());
+    let error = parse_str("\n\nstruct A { long double d; };").err().unwrap();
+    assert!(error.to_string().starts_with("test.idl:3:"), "{}", error);
+  }
+}
@@ -0,0 +1,533 @@
+// Rust code generation from parsed ROS 2 interfaces
+//
+// Each package becomes a module with `msg`, `srv` and `action` submodules, as
+// in the ROS 2 type names. References between packages are relative
+// (super::), so all packages must be generated into the same module.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::parser::{ArrayKind, BaseType, Constant, Field, MessageDef, Value};
+use crate::{
+  codegen::{identifier, ALLOW_LINTS, SERDE_MAX_ARRAY},
+  Error,
+};
+
+// rosidl adds this member to empty structures, which IDL does not allow.
+const EMPTY_STRUCTURE_MEMBER: &str = "structure_needs_at_least_one_member";
+
+const DEFAULT: &str = "::std::default::Default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum InterfaceKind {
+  Message,
+  Service,
+  Action,
+}
+
+impl InterfaceKind {
+  // Module of the interface, also the directory and file extension
+  pub(crate) fn module(self) -> &'static str {
+    match self {
+      InterfaceKind::Message => "msg",
+      InterfaceKind::Service => "srv",
+      InterfaceKind::Action => "action",
+    }
+  }
+
+  // Names of the structures of the sections of an interface file
+  pub(crate) fn section_names(self, name: &str) -> Vec<String> {
+    let suffixes: &[&str] = match self {
+      InterfaceKind::Message => &[""],
+      InterfaceKind::Service => &["_Request", "_Response"],
+      InterfaceKind::Action => &["_Goal", "_Result", "_Feedback"],
+    };
+    suffixes.iter().map(|s| format!("{}{}", name, s)).collect()
+  }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Interface {
+  pub package: String,
+  pub kind: InterfaceKind,
+  pub name: String,
+  pub sections: Vec<MessageDef>,
+}
+
+pub(crate) fn generate(interfaces: &[Interface]) -> Result<String, Error> {
+  let messages: BTreeSet<(&str, &str)> = interfaces
+    .iter()
+    .filter(|i| i.kind == InterfaceKind::Message)
+    .map(|i| (i.package.as_str(), i.name.as_str()))
+    .collect();
+  let mut packages: BTreeMap<&str, BTreeMap<InterfaceKind, Vec<&Interface>>> = BTreeMap::new();
+  for interface in interfaces {
+    packages
+      .entry(&interface.package)
+      .or_default()
+      .entry(interface.kind)
+      .or_default()
+      .push(interface);
+  }
+
+  let mut generator = Generator {
+    messages,
+    out: String::new(),
+    indent: 0,
+    scope: Vec::new(),
+  };
+  generator.line("// Generated by rustdds-idlgen from ROS 2 interfaces. Do not edit.");
+  for (package, kinds) in packages {
+    generator.line("");
+    generator.line(ALLOW_LINTS);
+    generator.line(&format!("pub mod {} {{", identifier(package)));
+    generator.indent += 1;
+    for (i, (kind, interfaces)) in kinds.into_iter().enumerate() {
+      if i > 0 {
+        generator.line("");
+      }
+      generator.line(&format!("pub mod {} {{", kind.module()));
+      generator.indent += 1;
+      generator.scope = vec![package.to_string(), kind.module().to_string()];
+      let mut interfaces = interfaces;
+      interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+      for (j, interface) in interfaces.into_iter().enumerate() {
+        if j > 0 {
+          generator.line("");
+        }
+        generator.interface(interface)?;
+      }
+      generator.indent -= 1;
+      generator.line("}");
+    }
+    generator.indent -= 1;
+    generator.line("}");
+  }
+  Ok(generator.out)
+}
+
+// DDS type name, e.g. `std_msgs::msg::dds_::String_`
+fn dds_type_name(package: &str, kind: InterfaceKind, name: &str) -> String {
+  format!("{}::{}::dds_::{}_", package, kind.module(), name)
+}
+
+fn rust_primitive(base: &BaseType) -> Option<&'static str> {
+  match base {
+    BaseType::Bool => Some("bool"),
+    BaseType::Byte | BaseType::Char | BaseType::UInt8 => Some("u8"),
+    BaseType::Float32 => Some("f32"),
+    BaseType::Float64 => Some("f64"),
+    BaseType::Int8 => Some("i8"),
+    BaseType::Int16 => Some("i16"),
+    BaseType::UInt16 => Some("u16"),
+    BaseType::Int32 => Some("i32"),
+    BaseType::UInt32 => Some("u32"),
+    BaseType::Int64 => Some("i64"),
+    BaseType::UInt64 => Some("u64"),
+    _ => None,
+  }
+}
+
+// ROS spelling of a type, for documenting bounds
+fn ros_type(field: &Field) -> String {
+  let base = match &field.field_type.base {
+    BaseType::String { bound: Some(b) } => format!("string<={}", b),
+    BaseType::WString { bound: Some(b) } => format!("wstring<={}", b),
+    other => format!("{:?}", other).to_lowercase(),
+  };
+  match field.field_type.array {
+    ArrayKind::Single => base,
+    ArrayKind::Fixed(n) => format!("{}[{}]", base, n),
+    ArrayKind::Unbounded => format!("{}[]", base),
+    ArrayKind::Bounded(n) => format!("{}[<={}]", base, n),
+  }
+}
+
+fn literal(value: &Value, base: &BaseType) -> String {
+  match (value, base) {
+    (Value::String(s), BaseType::WString { .. }) => {
+      format!("::rustdds::serialization::WString::from({:?})", s)
+    }
+    (Value::String(s), _) => format!("{:?}.to_string()", s),
+    (Value::Bool(b), _) => b.to_string(),
+    (Value::Integer(i), _) => i.to_string(),
+    (Value::Float(f), _) => format!("{:?}", f),
+  }
+}
+
+struct Generator<'a> {
+  // Package and name of all messages being generated
+  messages: BTreeSet<(&'a str, &'a str)>,
+  out: String,
+  indent: usize,
+  // Package and module of the interface being generated
+  scope: Vec<String>,
+}
+
+impl<'a> Generator<'a> {
+  fn line(&mut self, line: &str) {
+    if !line.is_empty() {
+      for _ in 0..self.indent {
+        self.out.push_str("  ");
+      }
+    }
+    self.out.push_str(line);
+    self.out.push('\n');
+  }
+
+  fn error<T>(&self, name: &str, message: &str) -> Result<T, Error> {
+    Err(Error::new(format!(
+      "{}/{}/{}: {}",
+      self.scope[0], self.scope[1], name, message
+    )))
+  }
+
+  // Path to a message, relative to the current module
+  fn path_to(&self, package: &str, name: &str) -> String {
+    let path = [package, "msg", name];
+    let common = self
+      .scope
+      .iter()
+      .zip(&path)
+      .take_while(|(a, b)| a == *b)
+      .count();
+    let mut parts: Vec<String> = vec!["super".to_string(); self.scope.len() - common];
+    parts.extend(path[common..].iter().map(|p| identifier(p)));
+    parts.join("::")
+  }
+
+  fn rust_base_type(&self, base: &BaseType) -> String {
+    match base {
+      BaseType::String { .. } => "String".to_string(),
+      BaseType::WString { .. } => "::rustdds::serialization::WString".to_string(),
+      BaseType::Named { package, name } => self.path_to(package, name),
+      primitive => rust_primitive(primitive).unwrap_or_default().to_string(),
+    }
+  }
+
+  fn interface(&mut self, interface: &Interface) -> Result<(), Error> {
+    for (i, section) in interface.sections.iter().enumerate() {
+      if i > 0 {
+        self.line("");
+      }
+      self.message(interface, section)?;
+    }
+    let (ros_trait, associated_types): (&str, &[&str]) = match interface.kind {
+      InterfaceKind::Message => return Ok(()),
+      InterfaceKind::Service => ("::rustdds::ros2::Service", &["Request", "Response"]),
+      InterfaceKind::Action => ("::rustdds::ros2::Action", &["Goal", "Result", "Feedback"]),
+    };
+    let name = identifier(&interface.name);
+    self.line("");
+    self.line(&format!("pub struct {};", name));
+    self.line("");
+    self.line(&format!("impl {} for {} {{", ros_trait, name));
+    self.indent += 1;
+    for (associated_type, section) in associated_types.iter().zip(&interface.sections) {
+      self.line(&format!(
+        "type {} = {};",
+        associated_type,
+        identifier(&section.name)
+      ));
+    }
+    self.line("fn type_name() -> String {");
+    self.line(&format!(
+      "  {:?}.to_string()",
+      dds_type_name(&interface.package, interface.kind, &interface.name)
+    ));
+    self.line("}");
+    self.indent -= 1;
+    self.line("}");
+    Ok(())
+  }
+
+  fn message(&mut self, interface: &Interface, message: &MessageDef) -> Result<(), Error> {
+    let name = identifier(&message.name);
+    for field in &message.fields {
+      if let BaseType::Named { package, name } = &field.field_type.base {
+        if !self.messages.contains(&(package.as_str(), name.as_str())) {
+          return self.error(
+            &message.name,
+            &format!("unknown type {}/{}", package, name),
+          );
+        }
+      }
+    }
+
+    self.line("#[derive(Clone, Debug, PartialEq, ::serde::Serialize, ::serde::Deserialize)]");
+    self.line(&format!("pub struct {} {{", name));
+    self.indent += 1;
+    for field in &message.fields {
+      self.field(&message.name, field)?;
+    }
+    if message.fields.is_empty() {
+      self.line(&format!("pub {}: u8,", EMPTY_STRUCTURE_MEMBER));
+    }
+    self.indent -= 1;
+    self.line("}");
+
+    self.line("");
+    self.line(&format!("impl {} {{", name));
+    self.indent += 1;
+    self.line(&format!(
+      "pub const TYPE_NAME: &'static str = {:?};",
+      dds_type_name(&interface.package, interface.kind, &message.name)
+    ));
+    for constant in &message.constants {
+      self.constant(&message.name, constant)?;
+    }
+    self.indent -= 1;
+    self.line("}");
+
+    self.line("");
+    self.line(&format!("impl {} for {} {{", DEFAULT, name));
+    self.indent += 1;
+    self.line(&format!("fn default() -> {} {{", name));
+    self.indent += 1;
+    self.line(&format!("{} {{", name));
+    self.indent += 1;
+    for field in &message.fields {
+      let value = self.default_value(field);
+      self.line(&format!("{}: {},", identifier(&field.name), value));
+    }
+    if message.fields.is_empty() {
+      self.line(&format!("{}: 0,", EMPTY_STRUCTURE_MEMBER));
+    }
+    self.indent -= 1;
+    self.line("}");
+    self.indent -= 1;
+    self.line("}");
+    self.indent -= 1;
+    self.line("}");
+    Ok(())
+  }
+
+  fn field(&mut self, message_name: &str, field: &Field) -> Result<(), Error> {
+    let field_type = &field.field_type;
+    let base_bound = match field_type.base {
+      BaseType::String { bound } | BaseType::WString { bound } => bound,
+      _ => None,
+    };
+    if base_bound.is_some() || matches!(field_type.array, ArrayKind::Bounded(_)) {
+      self.line(&format!("/// ROS type `{}`", ros_type(field)));
+    }
+    // Only the outermost bound is checked. Bounds of array elements are
+    // documented only.
+    let bound = match field_type.array {
+      ArrayKind::Single => base_bound,
+      ArrayKind::Bounded(n) => Some(n),
+      _ => None,
+    };
+    if let Some(bound) = bound {
+      self.line(&format!(
+        "#[serde(with = \"::rustdds::serialization::Bound::<{}>\")]",
+        bound
+      ));
+    }
+    let element = self.rust_base_type(&field_type.base);
+    let rust_type = match field_type.array {
+      ArrayKind::Single => element,
+      ArrayKind::Fixed(n) => {
+        if n > SERDE_MAX_ARRAY as usize {
+          if !field_type.base.is_primitive() {
+            return self.error(
+              message_name,
+              &format!(
+                "only arrays of primitive types can have more than {} elements",
+                SERDE_MAX_ARRAY
+              ),
+            );
+          }
+          self.line("#[serde(with = \"::rustdds::serialization::large_array\")]");
+        }
+        format!("[{}; {}]", element, n)
+      }
+      ArrayKind::Unbounded | ArrayKind::Bounded(_) => format!("Vec<{}>", element),
+    };
+    self.line(&format!("pub {}: {},", identifier(&field.name), rust_type));
+    Ok(())
+  }
+
+  fn constant(&mut self, message_name: &str, constant: &Constant) -> Result<(), Error> {
+    if constant.name == "TYPE_NAME" {
+      return self.error(message_name, "constant TYPE_NAME is reserved");
+    }
+    let (rust_type, value) = match (&constant.base, &constant.value) {
+      (BaseType::String { .. }, Value::String(s)) => ("&'static str", format!("{:?}", s)),
+      (base, value) => (
+        rust_primitive(base).unwrap_or_default(),
+        literal(value, base),
+      ),
+    };
+    self.line(&format!(
+      "pub const {}: {} = {};",
+      identifier(&constant.name),
+      rust_type,
+      value
+    ));
+    Ok(())
+  }
+
+  fn default_value(&self, field: &Field) -> String {
+    let base = &field.field_type.base;
+    match (&field.default, field.field_type.array) {
+      (Some(values), ArrayKind::Single) => literal(&values[0], base),
+      (Some(values), ArrayKind::Fixed(_)) => {
+        let values: Vec<String> = values.iter().map(|v| literal(v, base)).collect();
+        format!("[{}]", values.join(", "))
+      }
+      (Some(values), _) => {
+        let values: Vec<String> = values.iter().map(|v| literal(v, base)).collect();
+        format!("vec![{}]", values.join(", "))
+      }
+      // Default is implemented only for arrays of up to 32 elements.
+      (None, ArrayKind::Fixed(n)) if n > SERDE_MAX_ARRAY as usize => {
+        let zero = match base {
+          BaseType::Bool => "false",
+          BaseType::Float32 | BaseType::Float64 => "0.0",
+          _ => "0",
+        };
+        format!("[{}; {}]", zero, n)
+      }
+      (None, _) => format!("{}::default()", DEFAULT),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ros2::parser::parse;
+
+  fn interface(package: &str, kind: InterfaceKind, name: &str, text: &str) -> Interface {
+    Interface {
+      package: package.to_string(),
+      kind,
+      name: name.to_string(),
+      sections: parse(text, package, &kind.section_names(name), name).unwrap(),
+    }
+  }
+
+  #[test]
+  fn generate_message() {
+    let code = generate(&[
+      interface(
+        "geometry_msgs",
+        InterfaceKind::Message,
+        "Vector3",
+        "float64 x\nfloat64 y\nfloat64 z\n",
+      ),
+      interface(
+        "demo_msgs",
+        InterfaceKind::Message,
+        "Sample",
+        r#"
+        uint8 MODE_FAST=1
+        string NAME="demo"
+        geometry_msgs/Vector3 velocity
+        string<=8 label "none"
+        int32[<=4] counts [1, 2]
+        float32[2] weights [0.5, 1]
+        uint8[64] data
+        wstring text
+        bool type
+      "#,
+      ),
+      interface("demo_msgs", InterfaceKind::Message, "Empty", ""),
+    ])
+    .unwrap();
+    for expected in &[
+      "#[allow(clippy::all, non_camel_case_types, non_snake_case, non_upper_case_globals)]\npub mod demo_msgs {\n  pub mod msg {\n",
+      "    pub struct Empty {\n      pub structure_needs_at_least_one_member: u8,\n    }\n",
+      "    pub struct Sample {
+      pub velocity: super::super::geometry_msgs::msg::Vector3,
+      /// ROS type `string<=8`
+      #[serde(with = \"::rustdds::serialization::Bound::<8>\")]
+      pub label: String,
+      /// ROS type `int32[<=4]`
+      #[serde(with = \"::rustdds::serialization::Bound::<4>\")]
+      pub counts: Vec<i32>,
+      pub weights: [f32; 2],
+      #[serde(with = \"::rustdds::serialization::large_array\")]
+      pub data: [u8; 64],
+      pub text: ::rustdds::serialization::WString,
+      pub r#type: bool,
+    }
+
+    impl Sample {
+      pub const TYPE_NAME: &'static str = \"demo_msgs::msg::dds_::Sample_\";
+      pub const MODE_FAST: u8 = 1;
+      pub const NAME: &'static str = \"demo\";
+    }
+
+    impl ::std::default::Default for Sample {
+      fn default() -> Sample {
+        Sample {
+          velocity: ::std::default::Default::default(),
+          label: \"none\".to_string(),
+          counts: vec![1, 2],
+          weights: [0.5, 1.0],
+          data: [0; 64],
+          text: ::std::default::Default::default(),
+          r#type: ::std::default::Default::default(),
+        }
+      }
+    }
+",
+      "pub const TYPE_NAME: &'static str = \"geometry_msgs::msg::dds_::Vector3_\";",
+    ] {
+      assert!(code.contains(expected), "{}\n---\n{}", expected, code);
+    }
+  }
+
+  #[test]
+  fn generate_service_and_action() {
+    let code = generate(&[
+      interface(
+        "example_interfaces",
+        InterfaceKind::Service,
+        "AddTwoInts",
+        "int64 a\nint64 b\n---\nint64 sum\n",
+      ),
+      interface(
+        "example_interfaces",
+        InterfaceKind::Action,
+        "Fibonacci",
+        "int32 order\n---\nint32[] sequence\n---\nint32[] partial_sequence\n",
+      ),
+    ])
+    .unwrap();
+    for expected in &[
+      "  pub mod srv {\n    #[derive(",
+      "    pub struct AddTwoInts_Response {\n      pub sum: i64,\n    }\n",
+      "\"example_interfaces::srv::dds_::AddTwoInts_Request_\"",
+      "    pub struct AddTwoInts;
+
+    impl ::rustdds::ros2::Service for AddTwoInts {
+      type Request = AddTwoInts_Request;
+      type Response = AddTwoInts_Response;
+      fn type_name() -> String {
+        \"example_interfaces::srv::dds_::AddTwoInts_\".to_string()
+      }
+    }
+",
+      "      type Result = Fibonacci_Result;\n",
+      "\"example_interfaces::action::dds_::Fibonacci_Feedback_\"",
+    ] {
+      assert!(code.contains(expected), "{}\n---\n{}", expected, code);
+    }
+  }
+
+  #[test]
+  fn generate_errors() {
+    let unknown = interface("a", InterfaceKind::Message, "A", "b/B x\n");
+    assert_eq!(
+      generate(&[unknown]).unwrap_err().to_string(),
+      "a/msg/A: unknown type b/B"
+    );
+    let b = interface("b", InterfaceKind::Message, "B", "");
+    let long = interface("a", InterfaceKind::Message, "A", "b/B[40] x\n");
+    assert!(generate(&[b, long]).is_err());
+    let reserved = interface("a", InterfaceKind::Message, "A", "int32 TYPE_NAME=1\n");
+    assert!(generate(&[reserved]).is_err());
+  }
+}
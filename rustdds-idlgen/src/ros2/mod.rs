@@ -0,0 +1,259 @@
+//! Generates Rust types from ROS 2 interface definitions (`.msg`, `.srv` and
+//! `.action` files).
+//!
+//! Each package becomes a module with `msg`, `srv` and `action` submodules.
+//! The generated code has
+//! * a `struct` for each message and for each section of a service or action,
+//!   e.g. `AddTwoInts_Request`, with the CDR layout used by ROS 2,
+//! * the constants of each message in an `impl` block, together with
+//!   `TYPE_NAME`, the DDS type name of the message, e.g.
+//!   `"std_msgs::msg::dds_::String_"`,
+//! * a `Default` implementation using the default values of the fields,
+//! * a unit `struct` for each service and action, implementing
+//!   [`Service`](../../rustdds/ros2/trait.Service.html) or
+//!   [`Action`](../../rustdds/ros2/trait.Action.html).
+//!
+//! ROS types map to Rust types as in the IDL generator, with `byte` and
+//! `char` as `u8` and `wstring` as
+//! [`WString`](../../rustdds/serialization/struct.WString.html). Bounds of
+//! strings and sequences are checked as in the IDL generator.
+//!
+//! Messages referenced from other packages are generated as well, so they
+//! must be found in the search directories. All packages are generated
+//! into the same file, and must be included into the same module.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! // in build.rs main()
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! rustdds_idlgen::ros2::Builder::new()
+//!   .ament_prefix_path()
+//!   .search_dir("interfaces")
+//!   .package("example_interfaces")
+//!   .write_to(std::path::Path::new(&out_dir).join("interfaces.rs"))
+//!   .unwrap();
+//! ```
+
+use std::{
+  collections::BTreeMap,
+  env, fs,
+  path::{Path, PathBuf},
+};
+
+use crate::{Error, Result};
+use codegen::{Interface, InterfaceKind};
+use parser::BaseType;
+
+mod codegen;
+mod parser;
+
+const AMENT_PREFIX_PATH: &str = "AMENT_PREFIX_PATH";
+
+/// Generates Rust code from ROS 2 interface files
+///
+/// Interfaces are looked up as `<dir>/<package>/msg/<Name>.msg`,
+/// `<dir>/<package>/srv/<Name>.srv` and
+/// `<dir>/<package>/action/<Name>.action`, trying the search directories in
+/// the order they were added. This is the layout of the `share` directory of
+/// an ament install tree, and of the source tree of interface packages.
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+  search_dirs: Vec<PathBuf>,
+  packages: Vec<String>,
+  uses_ament_prefix_path: bool,
+}
+
+impl Builder {
+  pub fn new() -> Builder {
+    Builder::default()
+  }
+
+  /// Adds a directory containing interface packages, e.g. a vendored copy of
+  /// the interfaces.
+  pub fn search_dir<P: AsRef<Path>>(mut self, path: P) -> Builder {
+    self.search_dirs.push(path.as_ref().to_path_buf());
+    self
+  }
+
+  /// Adds the `share` directory of each install prefix in the
+  /// `AMENT_PREFIX_PATH` environment variable, which is set by sourcing a
+  /// ROS 2 installation.
+  pub fn ament_prefix_path(mut self) -> Builder {
+    if let Some(paths) = env::var_os(AMENT_PREFIX_PATH) {
+      self
+        .search_dirs
+        .extend(env::split_paths(&paths).map(|prefix| prefix.join("share")));
+    }
+    self.uses_ament_prefix_path = true;
+    self
+  }
+
+  /// Adds a package. All messages, services and actions of the package are
+  /// generated.
+  pub fn package(mut self, name: &str) -> Builder {
+    self.packages.push(name.to_string());
+    self
+  }
+
+  /// Generates the code and returns it
+  pub fn generate(&self) -> Result<String> {
+    self.generate_with_files().map(|(code, _)| code)
+  }
+
+  /// Generates the code into a file. Also tells Cargo to run the build
+  /// script again if any of the read interface files changes, so this is
+  /// meant to be called from a build script.
+  pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+    let (code, files) = self.generate_with_files()?;
+    if self.uses_ament_prefix_path {
+      println!("cargo:rerun-if-env-changed={}", AMENT_PREFIX_PATH);
+    }
+    for file in files {
+      println!("cargo:rerun-if-changed={}", file.display());
+    }
+    fs::write(path, code)?;
+    Ok(())
+  }
+
+  fn generate_with_files(&self) -> Result<(String, Vec<PathBuf>)> {
+    let mut interfaces = BTreeMap::new();
+    let mut files = Vec::new();
+    for package in &self.packages {
+      if !parser::is_package_name(package) {
+        return Err(Error::new(format!("invalid package name {}", package)));
+      }
+      let dir = self
+        .search_dirs
+        .iter()
+        .map(|dir| dir.join(package))
+        .find(|dir| dir.is_dir())
+        .ok_or_else(|| Error::new(format!("cannot find package {}", package)))?;
+      for kind in &[
+        InterfaceKind::Message,
+        InterfaceKind::Service,
+        InterfaceKind::Action,
+      ] {
+        for name in interface_names(&dir.join(kind.module()), kind.module())? {
+          self.load(package, *kind, &name, &mut interfaces, &mut files)?;
+        }
+      }
+    }
+    let interfaces: Vec<Interface> = interfaces.into_values().collect();
+    Ok((codegen::generate(&interfaces)?, files))
+  }
+
+  // Loads the interface, and the messages it references
+  fn load(
+    &self,
+    package: &str,
+    kind: InterfaceKind,
+    name: &str,
+    interfaces: &mut BTreeMap<(String, InterfaceKind, String), Interface>,
+    files: &mut Vec<PathBuf>,
+  ) -> Result<()> {
+    let key = (package.to_string(), kind, name.to_string());
+    if interfaces.contains_key(&key) {
+      return Ok(());
+    }
+    let relative = Path::new(package)
+      .join(kind.module())
+      .join(format!("{}.{}", name, kind.module()));
+    let path = self
+      .search_dirs
+      .iter()
+      .map(|dir| dir.join(&relative))
+      .find(|path| path.is_file())
+      .ok_or_else(|| Error::new(format!("cannot find {}", relative.display())))?;
+    let text =
+      fs::read_to_string(&path).map_err(|e| Error::new(format!("{}: {}", path.display(), e)))?;
+    let sections = parser::parse(
+      &text,
+      package,
+      &kind.section_names(name),
+      &path.display().to_string(),
+    )?;
+    files.push(path);
+    let referenced: Vec<(String, String)> = sections
+      .iter()
+      .flat_map(|s| &s.fields)
+      .filter_map(|f| match &f.field_type.base {
+        BaseType::Named { package, name } => Some((package.clone(), name.clone())),
+        _ => None,
+      })
+      .collect();
+    interfaces.insert(
+      key,
+      Interface {
+        package: package.to_string(),
+        kind,
+        name: name.to_string(),
+        sections,
+      },
+    );
+    for (package, name) in referenced {
+      self.load(&package, InterfaceKind::Message, &name, interfaces, files)?;
+    }
+    Ok(())
+  }
+}
+
+// Names of the interfaces in the directory, in alphabetical order
+fn interface_names(dir: &Path, extension: &str) -> Result<Vec<String>> {
+  if !dir.is_dir() {
+    return Ok(Vec::new());
+  }
+  let mut names = Vec::new();
+  for entry in fs::read_dir(dir).map_err(|e| Error::new(format!("{}: {}", dir.display(), e)))? {
+    let path = entry?.path();
+    if path.extension().is_some_and(|e| e == extension) {
+      if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+        if parser::is_type_name(name) {
+          names.push(name.to_string());
+        }
+      }
+    }
+  }
+  names.sort();
+  Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn builder_loads_referenced_packages() {
+    let dir = env::temp_dir().join(format!("rustdds-idlgen-ros2-{}", std::process::id()));
+    let write = |path: &str, text: &str| {
+      let path = dir.join(path);
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, text).unwrap();
+    };
+    write("builtin_interfaces/msg/Time.msg", "int32 sec\nuint32 nanosec\n");
+    write("builtin_interfaces/msg/Duration.msg", "int32 sec\nuint32 nanosec\n");
+    write(
+      "demo/msg/Stamped.msg",
+      "builtin_interfaces/Time stamp\nPoint point\n",
+    );
+    write("demo/msg/Point.msg", "float64 x\nfloat64 y\n");
+    write("demo/srv/Reset.srv", "---\nbool success\n");
+    write("demo/action/Wait.action", "builtin_interfaces/Time until\n---\n---\n");
+
+    let result = Builder::new().search_dir(&dir).package("demo").generate();
+    let missing = Builder::new().search_dir(&dir).package("nonexistent").generate();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let code = result.unwrap();
+    assert!(code.contains("pub mod builtin_interfaces {"));
+    assert!(code.contains("pub struct Time {"));
+    // Only referenced messages of other packages are generated.
+    assert!(!code.contains("pub struct Duration {"));
+    assert!(code.contains("pub stamp: super::super::builtin_interfaces::msg::Time,"));
+    assert!(code.contains("pub point: Point,"));
+    assert!(code.contains("impl ::rustdds::ros2::Service for Reset {"));
+    assert!(code.contains("impl ::rustdds::ros2::Action for Wait {"));
+    assert!(code.contains("pub until: super::super::builtin_interfaces::msg::Time,"));
+    assert!(missing.is_err());
+  }
+}
@@ -0,0 +1,563 @@
+// Parser of ROS 2 interface definitions: .msg files, and the sections of
+// .srv and .action files separated by `---` lines.
+//
+// Each line is a field `type name [default]`, a constant `TYPE NAME=value` or
+// empty. Comments start with `#` outside of quotes.
+
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BaseType {
+  Bool,
+  Byte,
+  Char,
+  Float32,
+  Float64,
+  Int8,
+  UInt8,
+  Int16,
+  UInt16,
+  Int32,
+  UInt32,
+  Int64,
+  UInt64,
+  String { bound: Option<usize> },
+  WString { bound: Option<usize> },
+  // Message of a package, which is the package of the definition if not
+  // given
+  Named { package: String, name: String },
+}
+
+impl BaseType {
+  fn parse(type_name: &str, package: &str) -> Result<BaseType, String> {
+    let primitive = match type_name {
+      "bool" => Some(BaseType::Bool),
+      "byte" => Some(BaseType::Byte),
+      "char" => Some(BaseType::Char),
+      "float32" => Some(BaseType::Float32),
+      "float64" => Some(BaseType::Float64),
+      "int8" => Some(BaseType::Int8),
+      "uint8" => Some(BaseType::UInt8),
+      "int16" => Some(BaseType::Int16),
+      "uint16" => Some(BaseType::UInt16),
+      "int32" => Some(BaseType::Int32),
+      "uint32" => Some(BaseType::UInt32),
+      "int64" => Some(BaseType::Int64),
+      "uint64" => Some(BaseType::UInt64),
+      "string" => Some(BaseType::String { bound: None }),
+      "wstring" => Some(BaseType::WString { bound: None }),
+      _ => None,
+    };
+    if let Some(primitive) = primitive {
+      return Ok(primitive);
+    }
+    if let Some(bound) = type_name.strip_prefix("string<=") {
+      return Ok(BaseType::String {
+        bound: Some(parse_bound(bound)?),
+      });
+    }
+    if let Some(bound) = type_name.strip_prefix("wstring<=") {
+      return Ok(BaseType::WString {
+        bound: Some(parse_bound(bound)?),
+      });
+    }
+    let (package, name) = match type_name.find('/') {
+      Some(i) => (&type_name[..i], &type_name[i + 1..]),
+      None => (package, type_name),
+    };
+    if !is_package_name(package) || !is_type_name(name) {
+      return Err(format!("invalid type {}", type_name));
+    }
+    Ok(BaseType::Named {
+      package: package.to_string(),
+      name: name.to_string(),
+    })
+  }
+
+  pub(crate) fn is_primitive(&self) -> bool {
+    !matches!(
+      self,
+      BaseType::String { .. } | BaseType::WString { .. } | BaseType::Named { .. }
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ArrayKind {
+  // `T`
+  Single,
+  // `T[N]`
+  Fixed(usize),
+  // `T[]`
+  Unbounded,
+  // `T[<=N]`
+  Bounded(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FieldType {
+  pub base: BaseType,
+  pub array: ArrayKind,
+}
+
+impl FieldType {
+  fn parse(type_name: &str, package: &str) -> Result<FieldType, String> {
+    let (base, array) = match type_name.find('[') {
+      Some(i) => {
+        let array = type_name[i + 1..]
+          .strip_suffix(']')
+          .ok_or_else(|| format!("invalid array type {}", type_name))?;
+        let array = if array.is_empty() {
+          ArrayKind::Unbounded
+        } else if let Some(bound) = array.strip_prefix("<=") {
+          ArrayKind::Bounded(parse_bound(bound)?)
+        } else {
+          ArrayKind::Fixed(parse_bound(array)?)
+        };
+        (&type_name[..i], array)
+      }
+      None => (type_name, ArrayKind::Single),
+    };
+    Ok(FieldType {
+      base: BaseType::parse(base, package)?,
+      array,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+  Bool(bool),
+  Integer(i128),
+  Float(f64),
+  String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Field {
+  pub name: String,
+  pub field_type: FieldType,
+  // One value for single fields, the elements for arrays
+  pub default: Option<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Constant {
+  pub name: String,
+  pub base: BaseType,
+  pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MessageDef {
+  pub name: String,
+  pub fields: Vec<Field>,
+  pub constants: Vec<Constant>,
+}
+
+fn parse_bound(bound: &str) -> Result<usize, String> {
+  match bound.parse::<usize>() {
+    Ok(n) if n > 0 => Ok(n),
+    _ => Err(format!("invalid size {}", bound)),
+  }
+}
+
+pub(crate) fn is_package_name(name: &str) -> bool {
+  name.starts_with(|c: char| c.is_ascii_lowercase())
+    && name
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    && !name.contains("__")
+    && !name.ends_with('_')
+}
+
+pub(crate) fn is_type_name(name: &str) -> bool {
+  name.starts_with(|c: char| c.is_ascii_uppercase()) && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_field_name(name: &str) -> bool {
+  is_package_name(name)
+}
+
+fn is_constant_name(name: &str) -> bool {
+  name.starts_with(|c: char| c.is_ascii_uppercase())
+    && name
+      .chars()
+      .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    && !name.contains("__")
+    && !name.ends_with('_')
+}
+
+// The line without its comment
+fn strip_comment(line: &str) -> &str {
+  let mut quote = None;
+  let mut escaped = false;
+  for (i, c) in line.char_indices() {
+    match (quote, c) {
+      _ if escaped => escaped = false,
+      (Some(_), '\\') => escaped = true,
+      (Some(q), c) if c == q => quote = None,
+      (None, '"') | (None, '\'') => quote = Some(c),
+      (None, '#') => return &line[..i],
+      _ => (),
+    }
+  }
+  line
+}
+
+fn parse_string(text: &str) -> Result<String, String> {
+  let quote = match text.chars().next() {
+    Some(q @ '"') | Some(q @ '\'') => q,
+    // Unquoted strings are taken as they are.
+    _ => return Ok(text.to_string()),
+  };
+  let mut value = String::new();
+  let mut chars = text[1..].chars();
+  loop {
+    match chars.next() {
+      Some('\\') => match chars.next() {
+        Some('n') => value.push('\n'),
+        Some('t') => value.push('\t'),
+        Some(c) => value.push(c),
+        None => break,
+      },
+      Some(c) if c == quote => {
+        return if chars.as_str().trim().is_empty() {
+          Ok(value)
+        } else {
+          Err(format!("unexpected text after string {}", text))
+        }
+      }
+      Some(c) => value.push(c),
+      None => break,
+    }
+  }
+  Err(format!("unterminated string {}", text))
+}
+
+fn integer_range(base: &BaseType) -> Option<(i128, i128)> {
+  match base {
+    BaseType::Byte | BaseType::Char | BaseType::UInt8 => Some((0, u8::MAX.into())),
+    BaseType::Int8 => Some((i8::MIN.into(), i8::MAX.into())),
+    BaseType::Int16 => Some((i16::MIN.into(), i16::MAX.into())),
+    BaseType::UInt16 => Some((0, u16::MAX.into())),
+    BaseType::Int32 => Some((i32::MIN.into(), i32::MAX.into())),
+    BaseType::UInt32 => Some((0, u32::MAX.into())),
+    BaseType::Int64 => Some((i64::MIN.into(), i64::MAX.into())),
+    BaseType::UInt64 => Some((0, u64::MAX.into())),
+    _ => None,
+  }
+}
+
+fn parse_value(text: &str, base: &BaseType) -> Result<Value, String> {
+  let invalid = || format!("invalid value {} for type {:?}", text, base);
+  if let Some((min, max)) = integer_range(base) {
+    let value = match text.strip_prefix("0x") {
+      Some(hex) => i128::from_str_radix(hex, 16),
+      None => text.parse::<i128>(),
+    };
+    return match value {
+      Ok(v) if min <= v && v <= max => Ok(Value::Integer(v)),
+      _ => Err(invalid()),
+    };
+  }
+  match base {
+    BaseType::Bool => match text.to_ascii_lowercase().as_str() {
+      "true" | "1" => Ok(Value::Bool(true)),
+      "false" | "0" => Ok(Value::Bool(false)),
+      _ => Err(invalid()),
+    },
+    BaseType::Float32 | BaseType::Float64 => match text.parse::<f64>() {
+      Ok(v) if v.is_finite() => Ok(Value::Float(v)),
+      _ => Err(invalid()),
+    },
+    BaseType::String { bound } | BaseType::WString { bound } => {
+      let value = parse_string(text)?;
+      match bound {
+        Some(bound) if value.chars().count() > *bound => {
+          Err(format!("string {} exceeds bound {}", text, bound))
+        }
+        _ => Ok(Value::String(value)),
+      }
+    }
+    _ => Err("only fields of primitive types and strings can have values".to_string()),
+  }
+}
+
+// Splits `[a, "b, c"]` into its elements
+fn parse_array(text: &str) -> Result<Vec<&str>, String> {
+  let inner = text
+    .strip_prefix('[')
+    .and_then(|t| t.strip_suffix(']'))
+    .ok_or_else(|| format!("array value {} must be in brackets", text))?;
+  if inner.trim().is_empty() {
+    return Ok(Vec::new());
+  }
+  let mut elements = Vec::new();
+  let mut start = 0;
+  let mut quote = None;
+  let mut escaped = false;
+  for (i, c) in inner.char_indices() {
+    match (quote, c) {
+      _ if escaped => escaped = false,
+      (Some(_), '\\') => escaped = true,
+      (Some(q), c) if c == q => quote = None,
+      (None, '"') | (None, '\'') => quote = Some(c),
+      (None, ',') => {
+        elements.push(inner[start..i].trim());
+        start = i + 1;
+      }
+      _ => (),
+    }
+  }
+  elements.push(inner[start..].trim());
+  Ok(elements)
+}
+
+fn parse_default(text: &str, field_type: &FieldType) -> Result<Vec<Value>, String> {
+  if field_type.array == ArrayKind::Single {
+    return Ok(vec![parse_value(text, &field_type.base)?]);
+  }
+  let values = parse_array(text)?
+    .into_iter()
+    .map(|element| parse_value(element, &field_type.base))
+    .collect::<Result<Vec<Value>, String>>()?;
+  match field_type.array {
+    ArrayKind::Fixed(n) if values.len() != n => {
+      Err(format!("array default must have {} elements", n))
+    }
+    ArrayKind::Bounded(n) if values.len() > n => {
+      Err(format!("array default has more than {} elements", n))
+    }
+    _ => Ok(values),
+  }
+}
+
+// Adds the field or constant of the line, and returns its name
+fn parse_line(line: &str, package: &str, message: &mut MessageDef) -> Result<String, String> {
+  let line = line.trim();
+  let type_end = line.find(char::is_whitespace).unwrap_or(line.len());
+  let (type_name, rest) = (&line[..type_end], line[type_end..].trim_start());
+  let name_end = rest
+    .find(|c: char| c.is_whitespace() || c == '=')
+    .unwrap_or(rest.len());
+  let (name, rest) = (&rest[..name_end], rest[name_end..].trim_start());
+  if name.is_empty() {
+    return Err(format!("missing name after type {}", type_name));
+  }
+  let field_type = FieldType::parse(type_name, package)?;
+
+  if let Some(value) = rest.strip_prefix('=') {
+    if !is_constant_name(name) {
+      return Err(format!("invalid constant name {}", name));
+    }
+    if field_type.array != ArrayKind::Single {
+      return Err(format!("constant {} must not be an array", name));
+    }
+    if let BaseType::WString { .. } | BaseType::Named { .. } = field_type.base {
+      return Err(format!("constant {} must be of a primitive type or string", name));
+    }
+    // String constants are the rest of the line, unless quoted.
+    let value = parse_value(value.trim(), &field_type.base)?;
+    message.constants.push(Constant {
+      name: name.to_string(),
+      base: field_type.base,
+      value,
+    });
+  } else {
+    if !is_field_name(name) {
+      return Err(format!("invalid field name {}", name));
+    }
+    let default = if rest.is_empty() {
+      None
+    } else {
+      Some(parse_default(rest, &field_type)?)
+    };
+    message.fields.push(Field {
+      name: name.to_string(),
+      field_type,
+      default,
+    });
+  }
+  Ok(name.to_string())
+}
+
+/// Parses an interface file into its sections. A .msg file has one section,
+/// a .srv file two and an .action file three. `names` are the names of the
+/// sections, e.g. `AddTwoInts_Request`.
+pub(crate) fn parse(
+  text: &str,
+  package: &str,
+  names: &[String],
+  file: &str,
+) -> Result<Vec<MessageDef>, Error> {
+  let mut sections = vec![MessageDef {
+    name: names[0].clone(),
+    fields: Vec::new(),
+    constants: Vec::new(),
+  }];
+  for (index, line) in text.lines().enumerate() {
+    let located = |message: String| Error::new(format!("{}:{}: {}", file, index + 1, message));
+    let line = strip_comment(line);
+    if line.trim() == "---" {
+      if sections.len() == names.len() {
+        return Err(located("too many sections".to_string()));
+      }
+      sections.push(MessageDef {
+        name: names[sections.len()].clone(),
+        fields: Vec::new(),
+        constants: Vec::new(),
+      });
+    } else if !line.trim().is_empty() {
+      let section = sections.last_mut().unwrap();
+      let name = parse_line(line, package, section).map_err(located)?;
+      let count = section.fields.iter().filter(|f| f.name == name).count()
+        + section.constants.iter().filter(|c| c.name == name).count();
+      if count > 1 {
+        return Err(located(format!("duplicate name {}", name)));
+      }
+    }
+  }
+  if sections.len() != names.len() {
+    return Err(Error::new(format!(
+      "{}: expected {} sections separated by ---, found {}",
+      file,
+      names.len(),
+      sections.len()
+    )));
+  }
+  Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_one(text: &str) -> Result<MessageDef, Error> {
+    parse(text, "pkg", &["Msg".to_string()], "Msg.msg").map(|mut s| s.remove(0))
+  }
+
+  #[test]
+  fn fields_and_constants() {
+    let message = parse_one(
+      r#"
+      # comment
+      int32 X_MAX=10  # comment
+      string GREETING="hi # there"
+      int32 x
+      float64[3] position [1, 2.5, -3]
+      string<=8[<=2] names ["a", 'b,c']
+      geometry_msgs/Point point
+      Other other
+      bool flag true
+      byte b 0xff
+    "#,
+    )
+    .unwrap();
+    assert_eq!(
+      message.constants,
+      vec![
+        Constant {
+          name: "X_MAX".to_string(),
+          base: BaseType::Int32,
+          value: Value::Integer(10),
+        },
+        Constant {
+          name: "GREETING".to_string(),
+          base: BaseType::String { bound: None },
+          value: Value::String("hi # there".to_string()),
+        },
+      ]
+    );
+    let types: Vec<&FieldType> = message.fields.iter().map(|f| &f.field_type).collect();
+    assert_eq!(
+      types,
+      vec![
+        &FieldType {
+          base: BaseType::Int32,
+          array: ArrayKind::Single
+        },
+        &FieldType {
+          base: BaseType::Float64,
+          array: ArrayKind::Fixed(3)
+        },
+        &FieldType {
+          base: BaseType::String { bound: Some(8) },
+          array: ArrayKind::Bounded(2)
+        },
+        &FieldType {
+          base: BaseType::Named {
+            package: "geometry_msgs".to_string(),
+            name: "Point".to_string()
+          },
+          array: ArrayKind::Single
+        },
+        &FieldType {
+          base: BaseType::Named {
+            package: "pkg".to_string(),
+            name: "Other".to_string()
+          },
+          array: ArrayKind::Single
+        },
+        &FieldType {
+          base: BaseType::Bool,
+          array: ArrayKind::Single
+        },
+        &FieldType {
+          base: BaseType::Byte,
+          array: ArrayKind::Single
+        },
+      ]
+    );
+    assert_eq!(
+      message.fields[1].default,
+      Some(vec![Value::Float(1.0), Value::Float(2.5), Value::Float(-3.0)])
+    );
+    assert_eq!(
+      message.fields[2].default,
+      Some(vec![
+        Value::String("a".to_string()),
+        Value::String("b,c".to_string())
+      ])
+    );
+    assert_eq!(message.fields[6].default, Some(vec![Value::Integer(255)]));
+  }
+
+  #[test]
+  fn sections() {
+    let names = vec!["A_Request".to_string(), "A_Response".to_string()];
+    let sections = parse("int64 a\n---\nint64 sum\n", "pkg", &names, "A.srv").unwrap();
+    assert_eq!(sections.len(), 2);
+    assert_eq!(sections[1].name, "A_Response");
+    assert_eq!(sections[1].fields[0].name, "sum");
+    assert!(parse("int64 a\n", "pkg", &names, "A.srv").is_err());
+    assert!(parse("---\n---\n", "pkg", &names, "A.srv").is_err());
+  }
+
+  #[test]
+  fn errors() {
+    for invalid in &[
+      "int32 Bad_name",
+      "int32 x_ 1",
+      "uint8 x 256",
+      "int8 x -129",
+      "float32[2] x [1.0]",
+      "int32[<=1] x [1, 2]",
+      "string<=2 s \"abc\"",
+      "int32 lower=1",
+      "int32[2] X=1",
+      "pkg/lower x",
+      "int32 x\nint32 x",
+      "int32 X=1\nint32 X=2",
+      "string s \"unterminated",
+      "Point p 1",
+      "int32[0] x",
+    ] {
+      assert!(parse_one(invalid).is_err(), "{}", invalid);
+    }
+    assert_eq!(
+      parse_one("int32 x\nbad_type y").unwrap_err().to_string(),
+      "Msg.msg:2: invalid type bad_type"
+    );
+  }
+}
@@ -1,6 +1,6 @@
 //use log::debug;
 
-use crate::structure::{time::Timestamp, guid::GUID};
+use crate::structure::{time::Timestamp, inline_qos::SampleIdentity};
 
 use crate::{
   dds::traits::key::{Key, Keyed},
@@ -54,8 +54,9 @@ struct SampleWithMetaData<D: Keyed> {
   // a snapshot of the instance-wide counts
   // at the time this sample was received.
   generation_counts: NotAliveGenerationCounts,
-  // who wrote this
-  writer_guid: GUID,
+  // who wrote this, and which sample it relates to
+  sample_identity: SampleIdentity,
+  related_sample_identity: Option<SampleIdentity>,
   // timestamps
   source_timestamp: Option<Timestamp>, // as stamped by sender
   sample_has_been_read: bool,          // sample_state
@@ -94,7 +95,8 @@ where
   pub fn add_sample(
     &mut self,
     new_sample: Result<D, D::K>,
-    writer_guid: GUID,
+    sample_identity: SampleIdentity,
+    related_sample_identity: Option<SampleIdentity>,
     receive_timestamp: Timestamp,
     source_timestamp: Option<Timestamp>,
   ) {
@@ -165,7 +167,8 @@ where
         receive_timestamp,
         SampleWithMetaData {
           generation_counts: instance_metadata.latest_generation_available,
-          writer_guid,
+          sample_identity,
+          related_sample_identity,
           source_timestamp,
           sample_has_been_read: false,
           sample: new_sample,
@@ -315,7 +318,9 @@ where
       generation_rank: mrsic_generations - dswm.generation_counts.total(),
       absolute_generation_rank: mrs_generations - dswm.generation_counts.total(),
       source_timestamp: dswm.source_timestamp.clone(),
-      publication_handle: dswm.writer_guid,
+      publication_handle: dswm.sample_identity.writer_guid,
+      sample_identity: dswm.sample_identity,
+      related_sample_identity: dswm.related_sample_identity,
     }
   }
 
@@ -567,7 +572,13 @@ mod tests {
     let org_ddsdata = DDSData::from(&data, Some(timestamp));
 
     let key = data.get_key().clone();
-    datasample_cache.add_sample(Ok(data.clone()), GUID::GUID_UNKNOWN, timestamp, None);
+    datasample_cache.add_sample(
+      Ok(data.clone()),
+      SampleIdentity::UNKNOWN,
+      None,
+      timestamp,
+      None,
+    );
     //datasample_cache.add_datasample(datasample).unwrap();
 
     let samples = datasample_cache.read_by_keys(&[(timestamp, key)]);
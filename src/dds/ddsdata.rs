@@ -4,7 +4,7 @@ use bytes::Bytes;
 use crate::{
   dds::traits::key::Keyed,
   structure::{
-    inline_qos::{KeyHash, StatusInfo},
+    inline_qos::{KeyHash, SampleIdentity, StatusInfo},
   },
 };
 use crate::messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier;
@@ -26,6 +26,8 @@ pub struct DDSData {
   value: Option<SerializedPayload>,
   // needed to identify what instance type (unique key) this change is for 9.6.3.8
   pub value_key_hash: u128,
+  // sent in inline QoS as PID_RELATED_SAMPLE_IDENTITY, e.g. by service replies
+  pub related_sample_identity: Option<SampleIdentity>,
 }
 
 impl DDSData {
@@ -37,6 +39,7 @@ impl DDSData {
       writer_id: EntityId::ENTITYID_UNKNOWN,
       value: Some(payload),
       value_key_hash: 0,
+      related_sample_identity: None,
     }
   }
 
@@ -59,6 +62,7 @@ impl DDSData {
       writer_id: EntityId::ENTITYID_UNKNOWN,
       value: None,
       value_key_hash: value_key_hash.value(),
+      related_sample_identity: None,
     }
   }
 
@@ -83,6 +87,19 @@ impl DDSData {
       writer_id: EntityId::ENTITYID_UNKNOWN,
       value: Some(serialized_payload),
       value_key_hash: 0,
+      related_sample_identity: None,
+    }
+  }
+
+  pub fn from_serialized(payload: SerializedPayload, source_timestamp: Option<Timestamp>) -> DDSData {
+    DDSData {
+      source_timestamp: source_timestamp.unwrap_or_else(Timestamp::now),
+      change_kind: ChangeKind::ALIVE,
+      reader_id: EntityId::ENTITYID_UNKNOWN,
+      writer_id: EntityId::ENTITYID_UNKNOWN,
+      value: Some(payload),
+      value_key_hash: 0,
+      related_sample_identity: None,
     }
   }
 
@@ -105,6 +122,7 @@ impl DDSData {
       value: None, // TODO: Here we should place the serialized _key_, so that RTPS writer can send the
       // the DATA message indicating dispose
       value_key_hash: 0,
+      related_sample_identity: None,
     }
   }
 
@@ -17,11 +17,13 @@ use crate::{
   dds::{message_receiver::MessageReceiver, reader::Reader, writer::Writer},
   network::util::get_local_multicast_locators,
   structure::builtin_endpoint::{BuiltinEndpointSet, },
-  dds::qos::policy,
+  dds::qos::{policy, HasQoSPolicy},
 };
 use crate::network::udp_listener::UDPListener;
+use crate::network::tcp_transport::TCPTransport;
 use crate::network::constant::*;
 use crate::structure::guid::{GuidPrefix, GUID, EntityId, EntityKind};
+use crate::structure::locator::LocatorList;
 use crate::structure::entity::RTPSEntity;
 
 use crate::{
@@ -75,6 +77,8 @@ pub struct DPEventLoop {
   writers: HashMap<GUID, Writer>,
 
   discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
+
+  tcp_transport: Option<TCPTransport>,
 }
 
 impl DPEventLoop {
@@ -91,6 +95,7 @@ impl DPEventLoop {
     remove_writer_receiver: TokenReceiverPair<GUID>,
     stop_poll_receiver: mio_channel::Receiver<()>,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
+    tcp_transport: Option<TCPTransport>,
   ) -> DPEventLoop {
     let poll = Poll::new().expect("Unable to create new poll.");
     let (acknack_sender, acknack_reciever) =
@@ -169,6 +174,12 @@ impl DPEventLoop {
       )
       .expect("Failed to register reader update notification.");
 
+    let mut tcp_transport = tcp_transport;
+    if let Some(tcp) = tcp_transport.as_mut() {
+      tcp.register(&poll)
+        .expect("Failed to register TCP transport.");
+    }
+
     DPEventLoop {
       domain_info,
       poll,
@@ -187,6 +198,7 @@ impl DPEventLoop {
       writers: HashMap::new(),
       ack_nack_reciever: acknack_reciever,
       discovery_update_notification_receiver,
+      tcp_transport,
     }
   }
 
@@ -210,6 +222,8 @@ impl DPEventLoop {
           return
         } else if DPEventLoop::is_udp_traffic(&event) {
           ev_wrapper.handle_udp_traffic(&event);
+        } else if ev_wrapper.tcp_transport.is_some() && TCPTransport::is_tcp_token(event.token()) {
+          ev_wrapper.handle_tcp_traffic(&event);
         } else if DPEventLoop::is_reader_action(&event) {
           ev_wrapper.handle_reader_action(&event);
         } else if ev_wrapper.is_reader_timed_event_action(&event) {
@@ -331,6 +345,17 @@ impl DPEventLoop {
     }
   }
 
+  pub fn handle_tcp_traffic(&mut self, event: &Event) {
+    let tcp_messages = match self.tcp_transport.as_mut() {
+      Some(tcp) => tcp.handle_event(&self.poll, event),
+      None => return,
+    };
+    // TCP connections carry both discovery and user traffic.
+    for data in tcp_messages.into_iter() {
+      self.message_receiver.handle_user_msg(data);
+    }
+  }
+
   pub fn handle_reader_action(&mut self, event: &Event) {
     match event.token() {
       ADD_READER_TOKEN => {
@@ -369,6 +394,15 @@ impl DPEventLoop {
             new_reader.get_guid(),
           );
           new_reader.set_requested_deadline_check_timer();
+          if let Some(tcp) = &self.tcp_transport {
+            new_reader.set_tcp_sender(tcp.sender());
+          }
+          // Writers in this participant are matched directly, without discovery.
+          let topic_name = new_reader.topic_name().clone();
+          for writer in self.writers.values()
+              .filter(|w| w.topic_name() == &topic_name) {
+            new_reader.update_local_writer(writer.get_guid(), writer.get_qos());
+          }
           trace!("Add reader: {:?}", new_reader);
           self.message_receiver.add_reader(new_reader);
         }
@@ -408,6 +442,13 @@ impl DPEventLoop {
             new_writer.get_timed_event_entity_token(),
             timed_action_receiver,
           );
+          if let Some(tcp) = &self.tcp_transport {
+            new_writer.set_tcp_sender(tcp.sender());
+          }
+          for reader in self.message_receiver.available_readers.iter_mut()
+              .filter(|r| r.topic_name() == new_writer.topic_name()) {
+            reader.update_local_writer(new_writer.get_guid(), new_writer.get_qos());
+          }
           self.writers.insert(new_writer.get_guid(), new_writer);
         }
       }
@@ -417,15 +458,25 @@ impl DPEventLoop {
           if let Some(w) = writer {
             &self.poll.deregister(&w.writer_command_receiver);
           };
+          for reader in self.message_receiver.available_readers.iter_mut() {
+            reader.remove_writer_proxy(*writer_guid);
+          }
         }
       }
 
       writer_token => {
-        self
+        let written = self
           .writers
           .iter_mut()
           .find(|p| p.1.get_entity_token() == writer_token)
-          .map( |(_guid, writer)| writer.process_writer_command() );
+          .map( |(guid, writer)| { writer.process_writer_command(); *guid } );
+        // Local readers read the new samples directly from DDSCache,
+        // they only need to be woken up.
+        if let Some(writer_guid) = written {
+          for reader in self.message_receiver.available_readers.iter() {
+            reader.notify_local_write(writer_guid);
+          }
+        }
       }
     }
   }
@@ -500,8 +551,11 @@ impl DPEventLoop {
       for (_writer_guid, writer) in self.writers.iter_mut() {
         match writer.get_entity_id() {
           EntityId::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER => {
+            let tcp_peers = self.tcp_transport.as_ref()
+              .map(|t| t.config().initial_peer_locators())
+              .unwrap_or_default();
             DPEventLoop::update_spdp_participant_readers(writer, &db,
-              self.domain_info.domain_id );
+              self.domain_info.domain_id, tcp_peers );
           }
           EntityId::ENTITYID_SEDP_BUILTIN_SUBSCRIPTIONS_WRITER => {
             DPEventLoop::update_discovery_writer( writer, discovered_participant,
@@ -607,8 +661,10 @@ impl DPEventLoop {
 
   fn remote_reader_discovered(&mut self, drd: DiscoveredReaderData, 
       rtps_reader_proxy: RtpsReaderProxy , _needs_new_cache_change: bool) {
-    for (_writer_guid, writer) in self.writers.iter_mut() {
-      if drd.subscription_topic_data.topic_name() == writer.topic_name() {
+    let db = self.discovery_db.read().unwrap();
+    for (writer_guid, writer) in self.writers.iter_mut() {
+      if drd.subscription_topic_data.topic_name() == writer.topic_name() 
+          && db.is_type_consistent_with_local_writer(*writer_guid, &drd) {
         writer.update_reader_proxy(rtps_reader_proxy.clone(), 
           drd.subscription_topic_data.generate_qos());
       }
@@ -626,6 +682,7 @@ impl DPEventLoop {
     writer: &mut Writer,
     db: &RwLockReadGuard<DiscoveryDB>,
     domain_id: u16,
+    tcp_initial_peers: LocatorList,
   ) {
     let guid_prefix = writer.get_guid_prefix();
 
@@ -658,6 +715,8 @@ impl DPEventLoop {
     let mut multicast_reader = RtpsReaderProxy::new(multicast_guid);
     multicast_reader.multicast_locator_list =
       get_local_multicast_locators(get_spdp_well_known_multicast_port(domain_id));
+    // TCP servers we connect to are announced to in the same way as multicast.
+    multicast_reader.unicast_locator_list = tcp_initial_peers;
 
     writer.update_reader_proxy(multicast_reader, Discovery::create_spdp_patricipant_qos());
     debug!("SPDP Participant readers updated.");
@@ -700,8 +759,10 @@ impl DPEventLoop {
   }
 
   pub fn remote_writer_discovered(&mut self, dwd: DiscoveredWriterData) {
+    let db = self.discovery_db.read().unwrap();
     for reader in self.message_receiver.available_readers.iter_mut() {
-      if &dwd.publication_topic_data.topic_name == reader.topic_name() {
+      if &dwd.publication_topic_data.topic_name == reader.topic_name() 
+          && db.is_type_consistent_with_local_reader(reader.get_guid(), &dwd) {
         reader.update_writer_proxy( 
           RtpsWriterProxy::from_discovered_writer_data(&dwd),
           dwd.publication_topic_data.qos(), 
@@ -812,6 +873,7 @@ mod tests {
       },
       stop_poll_receiver,
       discovery_update_notification_receiver,
+      None,
     );
 
     let (sender_stop, receiver_stop) = mio_channel::channel::<i32>();
@@ -879,6 +941,8 @@ mod tests {
       history: None,
       resource_limits: None,
       lifespan: None,
+      data_representation: None,
+      type_consistency: None,
     };
     let dp = DomainParticipant::new(0);
     let sub = dp.create_subscriber(&somePolicies).unwrap();
@@ -938,6 +1002,7 @@ mod tests {
       },
       stop_poll_receiver,
       discovery_update_notification_receiver,
+      None,
     );
 
     let (sender_stop, receiver_stop) = mio_channel::channel::<i32>();
@@ -991,6 +1056,8 @@ mod tests {
         history: None,
         resource_limits: None,
         lifespan: None,
+        data_representation: None,
+        type_consistency: None,
       };
 
       let mut datareader = sub
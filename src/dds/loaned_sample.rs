@@ -0,0 +1,231 @@
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, DeserializeOwned};
+
+use crate::{
+  dds::{
+    sampleinfo::*,
+    traits::serde_adapters::{BorrowingDeserializerAdapter, DeserializerAdapter},
+    values::result::*,
+  },
+  messages::submessages::submessage_elements::serialized_payload::SerializedPayload,
+  structure::{
+    cache_change::{CacheChange, ChangeKind},
+    inline_qos::SampleIdentity,
+  },
+};
+
+/// A received sample, loaned from a DataReader without deserializing it.
+///
+/// The serialized data stays in the reference-counted receive buffer, so taking
+/// a loan does not copy it. The sample is deserialized only on request: to the
+/// DataReader's data type with [`value`](#method.value), or to a type that
+/// borrows from the buffer with [`view`](#method.view).
+///
+/// Loaned samples are taken directly from the received changes, so the
+/// instance-related fields of the [`SampleInfo`] are not tracked. The
+/// `instance_state` tells if the change was a write, dispose or unregister,
+/// and the instance is identified by [`key_hash`](#method.key_hash).
+///
+/// [`SampleInfo`]: struct.SampleInfo.html
+pub struct LoanedSample<D, DA> {
+  sample_info: SampleInfo,
+  key_hash: u128,
+  payload: Option<SerializedPayload>,
+  phantom: PhantomData<(D, DA)>,
+}
+
+impl<D, DA> LoanedSample<D, DA> {
+  pub(crate) fn from_cache_change(cache_change: &CacheChange) -> Self {
+    let instance_state = match cache_change.kind {
+      ChangeKind::ALIVE => InstanceState::Alive,
+      ChangeKind::NOT_ALIVE_DISPOSED => InstanceState::NotAlive_Disposed,
+      ChangeKind::NOT_ALIVE_UNREGISTERED => InstanceState::NotAlive_NoWriters,
+    };
+    LoanedSample {
+      sample_info: SampleInfo {
+        instance_state,
+        publication_handle: cache_change.writer_guid,
+        sample_identity: SampleIdentity {
+          writer_guid: cache_change.writer_guid,
+          sequence_number: cache_change.sequence_number,
+        },
+        related_sample_identity: cache_change.related_sample_identity,
+        ..SampleInfo::new_deprecated()
+      },
+      key_hash: cache_change.key,
+      // Bytes is reference-counted, so this does not copy the data.
+      payload: cache_change.data_value.clone(),
+      phantom: PhantomData,
+    }
+  }
+
+  // The no_key DataReader loans from its wrapped keyed DataReader.
+  pub(crate) fn cast<D2, DA2>(self) -> LoanedSample<D2, DA2> {
+    LoanedSample {
+      sample_info: self.sample_info,
+      key_hash: self.key_hash,
+      payload: self.payload,
+      phantom: PhantomData,
+    }
+  }
+
+  pub fn sample_info(&self) -> &SampleInfo {
+    &self.sample_info
+  }
+
+  /// Key hash of the instance, as sent by the DataWriter.
+  pub fn key_hash(&self) -> u128 {
+    self.key_hash
+  }
+
+  /// The serialized data. Dispose and unregister samples may have no data.
+  pub fn payload(&self) -> Option<&SerializedPayload> {
+    self.payload.as_ref()
+  }
+
+  fn payload_or_error(&self) -> Result<&SerializedPayload> {
+    match &self.payload {
+      Some(payload) => Ok(payload),
+      None => Error::precondition_not_met("Loaned sample has no data."),
+    }
+  }
+
+  /// Deserializes the sample to a type that may borrow from the loaned buffer,
+  /// e.g. a struct with `#[serde(borrow)]` fields of type `&'a str` or `&'a [u8]`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::Deserialize;
+  /// # use rustdds::dds::data_types::LoanedSample;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #[derive(Deserialize)]
+  /// struct BlobView<'a> {
+  ///   name: &'a str,
+  ///   #[serde(borrow)]
+  ///   data: &'a [u8],
+  /// }
+  ///
+  /// fn blob_size<D>(sample: &LoanedSample<D, CDRDeserializerAdapter<D>>) -> Option<usize> {
+  ///   sample.view::<BlobView>().ok().map(|blob| blob.data.len())
+  /// }
+  /// ```
+  pub fn view<'a, T>(&'a self) -> Result<T>
+  where
+    T: Deserialize<'a>,
+    DA: BorrowingDeserializerAdapter,
+  {
+    let payload = self.payload_or_error()?;
+    Ok(DA::from_bytes_borrowed(
+      &payload.value,
+      payload.representation_identifier,
+    )?)
+  }
+}
+
+impl<D, DA> LoanedSample<D, DA>
+where
+  D: DeserializeOwned,
+  DA: DeserializerAdapter<D>,
+{
+  /// Deserializes the sample to the data type of the DataReader.
+  pub fn value(&self) -> Result<D> {
+    let payload = self.payload_or_error()?;
+    if !DA::accepts_encoding(payload.representation_identifier) {
+      return Err(Error::Serialization {
+        reason: format!(
+          "Unknown representation id {:?}.",
+          payload.representation_identifier
+        ),
+      });
+    }
+    Ok(DA::from_bytes(
+      &payload.value,
+      payload.representation_identifier,
+    )?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use bytes::Bytes;
+  use byteorder::LittleEndian;
+  use serde::{Deserialize, Serialize};
+
+  use crate::{
+    messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier,
+    serialization::{cdr_serializer::to_bytes, CDRDeserializerAdapter},
+    structure::{guid::GUID, sequence_number::SequenceNumber},
+  };
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Blob {
+    name: String,
+    data: Vec<u8>,
+  }
+
+  #[derive(Deserialize)]
+  struct BlobView<'a> {
+    name: &'a str,
+    #[serde(borrow)]
+    data: &'a [u8],
+  }
+
+  fn loan(change: &CacheChange) -> LoanedSample<Blob, CDRDeserializerAdapter<Blob>> {
+    LoanedSample::from_cache_change(change)
+  }
+
+  #[test]
+  fn loaned_sample_views_payload() {
+    let blob = Blob {
+      name: "blob".to_string(),
+      data: vec![1, 2, 3, 4, 5],
+    };
+    let value = Bytes::from(to_bytes::<Blob, LittleEndian>(&blob).unwrap());
+    let change = CacheChange {
+      kind: ChangeKind::ALIVE,
+      writer_guid: GUID::GUID_UNKNOWN,
+      sequence_number: SequenceNumber::from(1),
+      data_value: Some(SerializedPayload {
+        representation_identifier: RepresentationIdentifier::CDR_LE,
+        representation_options: [0, 0],
+        value: value.clone(),
+      }),
+      key: 42,
+      related_sample_identity: None,
+    };
+    let sample = loan(&change);
+    assert_eq!(sample.key_hash(), 42);
+    assert_eq!(sample.sample_info().instance_state, InstanceState::Alive);
+
+    let view: BlobView = sample.view().unwrap();
+    assert_eq!(view.name, "blob");
+    assert_eq!(view.data, &[1, 2, 3, 4, 5]);
+    // The view points into the received buffer.
+    let range = value.as_ptr_range();
+    assert!(range.contains(&view.data.as_ptr()));
+    assert!(range.contains(&view.name.as_ptr()));
+
+    assert_eq!(sample.value().unwrap(), blob);
+  }
+
+  #[test]
+  fn loaned_sample_without_data() {
+    let change = CacheChange {
+      kind: ChangeKind::NOT_ALIVE_DISPOSED,
+      data_value: None,
+      ..CacheChange::default()
+    };
+    let sample = loan(&change);
+    assert_eq!(
+      sample.sample_info().instance_state,
+      InstanceState::NotAlive_Disposed
+    );
+    assert!(sample.payload().is_none());
+    assert!(sample.value().is_err());
+    assert!(sample.view::<BlobView>().is_err());
+  }
+}
@@ -232,7 +232,8 @@ impl MessageReceiver {
 
     let mr_state = self.give_message_receiver_info();
     match submessage {
-      EntitySubmessage::Data(data, _) => {
+      EntitySubmessage::Data(data, flags) => {
+        let endianness = endianness_flag(flags.bits());
         // If reader_id == ENTITYID_UNKNOWN, message should be sent to all matched readers
         if data.reader_id == EntityId::ENTITYID_UNKNOWN {
           trace!("send_submessage DATA from unknown. writer_id = {:?}", &data.writer_id);
@@ -250,11 +251,11 @@ impl MessageReceiver {
                       )
           {
             trace!("send_submessage DATA from unknown handling in {:?}",&reader);
-            reader.handle_data_msg(data.clone(), mr_state.clone());
+            reader.handle_data_msg(data.clone(), endianness, mr_state.clone());
           }
         } else {
           if let Some(target_reader) = self.get_reader(data.reader_id) {
-            target_reader.handle_data_msg(data, mr_state);
+            target_reader.handle_data_msg(data, endianness, mr_state);
           }
         }
       }
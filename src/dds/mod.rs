@@ -23,7 +23,10 @@
 //! * If you are using CDR serialization (DDS default), then use [`CDRSerializerAdapter`] and [`CDRDeserializerAdapter`]
 //!   when such adapters are required. If you need to use another serialization format, then you should find or write
 //!   a [Serde data format](https://serde.rs/data-format.html) implementation and wrap it as a (De)SerializerAdaper.
-//! 
+//! * For XTypes XCDR2 encoding use `CDR2SerializerAdapter`, `DelimitedCDR2SerializerAdapter` or
+//!   `ParameterizedCDR2SerializerAdapter` together with `CDR2DeserializerAdapter`. The DataRepresentation QoS
+//!   of the writer and reader is filled in from the adapter.
+//!
 //! [`DomainParticipant`]: struct.DomainParticipant.html
 //! [`Topic`]: struct.Topic.html
 //! [`Publisher`]: struct.Publisher.html
@@ -109,8 +112,10 @@
 
 mod datasample_cache;
 pub(crate) mod ddsdata;
+pub(crate) mod loaned_sample;
 mod dp_event_loop;
 mod message_receiver;
+mod raw_endpoints;
 mod sampleinfo;
 
 /// Participating in NoKey topics.
@@ -138,6 +143,8 @@ pub mod qos;
 
 pub mod statusevents;
 
+pub mod xtypes;
+
 /// Datatypes needed for overall operability with this crate
 pub mod data_types {
   pub use crate::discovery::data_types::topic_data::{
@@ -154,6 +161,12 @@ pub mod data_types {
   // TODO: move typedesc module somewhere better
   pub use crate::dds::typedesc::TypeDesc;
   pub use crate::dds::sampleinfo::SampleInfo;
+  pub use crate::structure::inline_qos::SampleIdentity;
+  pub use crate::structure::sequence_number::SequenceNumber;
+  pub use crate::dds::loaned_sample::LoanedSample;
+  pub use crate::messages::submessages::submessage_elements::serialized_payload::{
+    SerializedPayload, RepresentationIdentifier,
+  };
   #[doc(inline)]
   pub use crate::structure::topic_kind::TopicKind; // AKA dds::topic::TopicKind
   pub use super::traits::key::BuiltInTopicKey;
@@ -168,6 +181,11 @@ pub use participant::DomainParticipant;
 pub use topic::Topic;
 pub use pubsub::Subscriber;
 pub use pubsub::Publisher;
+pub use crate::network::tcp_transport::{TCPTransportConfig, TCPRole};
+pub use crate::network::transport_config::TransportConfig;
+pub use crate::network::flow_controller::FlowControllerConfig;
+pub use writer::BatchingConfig;
+pub use raw_endpoints::{RawDataReader, RawDataWriter, RawSample};
 
 #[doc(inline)]
 pub use with_key::datawriter::DataWriter as With_Key_DataWriter;
@@ -388,6 +388,50 @@ where
         .map(|ds| ds.value),
     )
   }
+
+  /// Takes up to `max_samples` received samples as loans, without deserializing them.
+  /// See [`With_Key_DataReader::take_loaned`](../struct.With_Key_DataReader.html#method.take_loaned).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::dds::DomainParticipant;
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// # use rustdds::dds::data_types::TopicKind;
+  /// # use rustdds::dds::No_Key_DataReader as DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// # let domain_participant = DomainParticipant::new(0).unwrap();
+  /// # let qos = QosPolicyBuilder::new().build();
+  /// # let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  /// #
+  /// # // NoKey is important
+  /// # let topic = domain_participant.create_topic("some_topic", "SomeType", &qos, TopicKind::NoKey).unwrap();
+  /// #
+  /// # #[derive(Serialize, Deserialize)]
+  /// # struct SomeType { name: String }
+  /// #
+  /// #[derive(Deserialize)]
+  /// struct SomeView<'a> { name: &'a str }
+  ///
+  /// let mut data_reader = subscriber.create_datareader_no_key::<SomeType, CDRDeserializerAdapter<_>>(topic, None, None).unwrap();
+  /// for loan in data_reader.take_loaned(10).unwrap() {
+  ///   if let Ok(view) = loan.view::<SomeView>() {
+  ///     // Do something with view.name
+  ///   }
+  /// }
+  /// ```
+  pub fn take_loaned(&mut self, max_samples: usize) -> Result<Vec<LoanedSample<D, DA>>> {
+    Ok(
+      self
+        .keyed_datareader
+        .take_loaned(max_samples)?
+        .into_iter()
+        .map(|loan| loan.cast())
+        .collect(),
+    )
+  }
   /*
   /// Gets latest RequestedDeadlineMissed status
   ///
@@ -1,5 +1,6 @@
 use crate::structure::guid::GUID;
 use crate::structure::time::Timestamp;
+use crate::structure::inline_qos::SampleIdentity;
 
 use crate::dds::sampleinfo::*;
 
@@ -43,6 +44,11 @@ impl<D> DataSample<D> {
         absolute_generation_rank,
         source_timestamp: Some(source_timestamp),
         publication_handle: writer_guid,
+        sample_identity: SampleIdentity {
+          writer_guid,
+          ..SampleIdentity::UNKNOWN
+        },
+        related_sample_identity: None,
       },
       value: payload,
     }
@@ -20,6 +20,7 @@ use crate::dds::traits::dds_entity::DDSEntity;
 use crate::dds::traits::serde_adapters::SerializerAdapter;
 
 use crate::dds::qos::{HasQoSPolicy, QosPolicies};
+use crate::dds::writer::BatchingConfig;
 
 use crate::{
   discovery::data_types::topic_data::SubscriptionBuiltinTopicData,
@@ -100,6 +101,22 @@ where
       .write(NoKeyWrapper::<D> { d: data }, source_timestamp)
   }
 
+  /// Turns writer-side batching on or off. See `With_Key_DataWriter::set_batching`.
+  pub fn set_batching(&self, config: Option<BatchingConfig>) -> Result<()> {
+    self.keyed_datawriter.set_batching(config)
+  }
+
+  /// Sends out immediately all samples waiting in the current batch.
+  pub fn flush(&self) -> Result<()> {
+    self.keyed_datawriter.flush()
+  }
+
+  /// Attaches this DataWriter to a named flow controller, or detaches it with `None`.
+  /// See `With_Key_DataWriter::set_flow_controller`.
+  pub fn set_flow_controller(&self, name: Option<&str>) -> Result<()> {
+    self.keyed_datawriter.set_flow_controller(name)
+  }
+
   /// Waits for all acknowledgements to finish
   ///
   /// # Examples
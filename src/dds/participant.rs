@@ -17,6 +17,9 @@ use crate::{
   discovery::data_types::topic_data::DiscoveredTopicData,
   discovery::discovery::DiscoveryCommand,
   network::{udp_listener::UDPListener, constant::*},
+  network::tcp_transport::{TCPTransport, TCPTransportConfig},
+  network::transport_config::TransportConfig,
+  network::flow_controller::FlowController,
 };
 
 use crate::dds::{
@@ -30,6 +33,7 @@ use crate::{
     entity::{RTPSEntity},
     guid::GUID,
     dds_cache::DDSCache,
+    locator::LocatorList,
   },
 };
 
@@ -50,9 +54,37 @@ impl DomainParticipant {
   /// let domain_participant = DomainParticipant::new(0);
   /// ```
   pub fn new(domain_id: u16) -> Result<DomainParticipant> {
+    DomainParticipant::construct(domain_id, TransportConfig::default())
+  }
+
+  /// Creates a DomainParticipant that also communicates using RTPS over TCPv4.
+  /// UDP communication is still available as usual.
+  ///
+  /// # Examples
+  /// ```
+  /// # use rustdds::dds::{DomainParticipant, TCPTransportConfig};
+  /// let tcp_config = TCPTransportConfig::client(vec!["127.0.0.1:7650".parse().unwrap()]);
+  /// let domain_participant = DomainParticipant::new_with_tcp(0, tcp_config);
+  /// ```
+  pub fn new_with_tcp(domain_id: u16, tcp_config: TCPTransportConfig) 
+    -> Result<DomainParticipant> 
+  {
+    DomainParticipant::construct(domain_id, TransportConfig::new().tcp(tcp_config))
+  }
+
+  /// Creates a DomainParticipant with the given [transport settings](struct.TransportConfig.html).
+  pub fn new_with_transport(domain_id: u16, transport_config: TransportConfig) 
+    -> Result<DomainParticipant> 
+  {
+    DomainParticipant::construct(domain_id, transport_config)
+  }
+
+  fn construct(domain_id: u16, transport_config: TransportConfig) 
+    -> Result<DomainParticipant> 
+  {
     trace!("DomainParticipant construct start");
     let (djh_sender, djh_receiver) = mio_channel::channel();
-    let mut dpd = DomainParticipant_Disc::new(domain_id, djh_receiver)?;
+    let mut dpd = DomainParticipant_Disc::new(domain_id, djh_receiver, transport_config)?;
 
     let discovery_updated_sender = match dpd.discovery_updated_sender.take() {
       Some(dus) => dus,
@@ -165,6 +197,40 @@ impl DomainParticipant {
     type_desc: &str,
     qos: &QosPolicies,
     topic_kind: TopicKind,
+  ) -> Result<Topic> {
+    self.create_topic_with_type(name, TypeDesc::new(type_desc), qos, topic_kind)
+  }
+
+  /// Create DDS Topic with a full type description.
+  ///
+  /// If the type description carries XTypes type information, it is advertised
+  /// in discovery, and remote readers and writers are matched only if their
+  /// types are consistent with it.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::dds::DomainParticipant;
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// use rustdds::dds::data_types::{TopicKind, TypeDesc};
+  /// use rustdds::dds::xtypes::TypeSupport;
+  ///
+  /// #[derive(TypeSupport)]
+  /// struct SomeType {
+  ///   a: i32,
+  /// }
+  ///
+  /// let domain_participant = DomainParticipant::new(0);
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let topic = domain_participant.create_topic_with_type(
+  ///   "some_topic", TypeDesc::of::<SomeType>("SomeType"), &qos, TopicKind::NoKey);
+  /// ```
+  pub fn create_topic_with_type(
+    &self,
+    name: &str,
+    type_desc: TypeDesc,
+    qos: &QosPolicies,
+    topic_kind: TopicKind,
   ) -> Result<Topic> {
     //println!("Create topic outer");
     let w = self.weak_clone();
@@ -223,6 +289,27 @@ impl DomainParticipant {
   pub(crate) fn discovery_db(&self) -> Arc<RwLock<DiscoveryDB>> {
     self.dpi.lock().unwrap().dpi.lock().unwrap().discovery_db.clone()
   }
+
+  // TCP locators to be announced in SPDP. Empty, if TCP is not in use.
+  pub(crate) fn tcp_locators(&self) -> LocatorList {
+    match &self.dpi.lock().unwrap().dpi.lock().unwrap().tcp_config {
+      Some(config) => config.announced_locators(),
+      None => vec![],
+    }
+  }
+
+  // TCP servers to send our SPDP announcements to.
+  pub(crate) fn tcp_initial_peer_locators(&self) -> LocatorList {
+    match &self.dpi.lock().unwrap().dpi.lock().unwrap().tcp_config {
+      Some(config) => config.initial_peer_locators(),
+      None => vec![],
+    }
+  }
+
+  // Named flow controller from TransportConfig.
+  pub(crate) fn flow_controller(&self, name: &str) -> Option<FlowController> {
+    self.dpi.lock().unwrap().dpi.lock().unwrap().flow_controllers.get(name).cloned()
+  }
 }
 
 impl PartialEq for DomainParticipant {
@@ -271,7 +358,7 @@ impl DomainParticipantWeak {
     topic_kind: TopicKind,
   ) -> Result<Topic> {
     match self.dpi.upgrade() {
-      Some(dpi) => dpi.lock().unwrap().create_topic(&self, name, type_desc, qos, topic_kind),
+      Some(dpi) => dpi.lock().unwrap().create_topic(&self, name, TypeDesc::new(type_desc), qos, topic_kind),
       None => Err(Error::LockPoisoned),
     }
   }
@@ -297,6 +384,16 @@ impl DomainParticipantWeak {
     }
   }
 
+  pub fn tcp_initial_peer_locators(&self) -> LocatorList {
+    match self.dpi.upgrade() {
+      Some(dpi) => match &dpi.lock().unwrap().dpi.lock().unwrap().tcp_config {
+        Some(config) => config.initial_peer_locators(),
+        None => vec![],
+      },
+      None => vec![],
+    }
+  }
+
   pub fn upgrade(self) -> Option<DomainParticipant> {
     match self.dpi.upgrade() {
       Some(d) => Some(DomainParticipant { dpi: d }),
@@ -327,11 +424,13 @@ impl DomainParticipant_Disc {
   pub fn new(
     domain_id: u16,
     discovery_join_handle: mio_channel::Receiver<JoinHandle<()>>,
+    transport_config: TransportConfig,
   ) -> Result<DomainParticipant_Disc> {
     let (discovery_update_notification_sender, discovery_update_notification_receiver) =
       mio_channel::sync_channel::<DiscoveryNotificationType>(100);
 
-    let dpi = DomainParticipant_Inner::new(domain_id, discovery_update_notification_receiver)?;
+    let dpi = DomainParticipant_Inner::new(domain_id, discovery_update_notification_receiver,
+      transport_config)?;
 
     let (discovery_command_sender, discovery_command_receiver) =
       mio_channel::sync_channel::<DiscoveryCommand>(10);
@@ -369,7 +468,7 @@ impl DomainParticipant_Disc {
     &self,
     dp: &DomainParticipantWeak,
     name: &str,
-    type_desc: &str,
+    type_desc: TypeDesc,
     qos: &QosPolicies,
     topic_kind: TopicKind,
   ) -> Result<Topic> {
@@ -447,6 +546,9 @@ pub(crate) struct DomainParticipant_Inner {
 
   dds_cache: Arc<RwLock<DDSCache>>,
   discovery_db: Arc<RwLock<DiscoveryDB>>,
+
+  tcp_config: Option<TCPTransportConfig>,
+  flow_controllers: HashMap<String, FlowController>,
 }
 
 impl Drop for DomainParticipant_Inner {
@@ -473,8 +575,21 @@ impl DomainParticipant_Inner {
   fn new(
     domain_id: u16,
     discovery_update_notification_receiver: mio_channel::Receiver<DiscoveryNotificationType>,
+    transport_config: TransportConfig,
   ) -> Result<DomainParticipant_Inner> {
     let mut listeners = HashMap::new();
+    let tcp_config = transport_config.tcp;
+    let flow_controllers = transport_config.flow_controllers.into_iter()
+      .map(|config| (config.name.clone(), FlowController::new(config)))
+      .collect();
+
+    let tcp_transport = match tcp_config.clone() {
+      Some(config) => match TCPTransport::new(config) {
+        Ok(t) => Some(t),
+        Err(e) => return log_and_err_internal!("Cannot start TCP transport: {:?}", e),
+      },
+      None => None,
+    };
 
     // Creating UDP listeners for participantId 0 (change this if necessary)
     let discovery_multicast_listener = UDPListener::try_bind(
@@ -549,6 +664,13 @@ impl DomainParticipant_Inner {
 
     listeners.insert(USER_TRAFFIC_LISTENER_TOKEN, user_traffic_listener);
 
+    if let Some(size) = transport_config.udp_receive_buffer_size {
+      for listener in listeners.values() {
+        listener.set_receive_buffer_size(size)
+          .unwrap_or_else(|e| warn!("Cannot set UDP receive buffer size to {}: {:?}", size, e));
+      }
+    }
+
     // Adding readers
     let (sender_add_reader, receiver_add_reader) = mio_channel::sync_channel::<Reader>(100);
     let (sender_remove_reader, receiver_remove_reader) = mio_channel::sync_channel::<GUID>(10);
@@ -594,6 +716,7 @@ impl DomainParticipant_Inner {
       },
       stop_poll_receiver,
       discovery_update_notification_receiver,
+      tcp_transport,
     );
     // Launch the background thread for DomainParticipant
     let ev_loop_handle = thread::Builder::new()
@@ -618,6 +741,8 @@ impl DomainParticipant_Inner {
       remove_writer_sender,
       dds_cache: Arc::new(RwLock::new(DDSCache::new())),
       discovery_db,
+      tcp_config,
+      flow_controllers,
     })
   }
 
@@ -679,14 +804,14 @@ impl DomainParticipant_Inner {
     &self,
     domain_participant_weak: &DomainParticipantWeak,
     name: &str,
-    type_desc: &str,
+    type_desc: TypeDesc,
     qos: &QosPolicies,
     topic_kind: TopicKind,
   ) -> Result<Topic> {
     let topic = Topic::new(
       domain_participant_weak,
       name.to_string(),
-      TypeDesc::new(type_desc),
+      type_desc,
       &qos,
       topic_kind,
     );
@@ -31,6 +31,11 @@ use crate::dds::{
   no_key::datareader::DataReader as NoKeyDataReader,
   traits::key::{Keyed, Key},
   traits::serde_adapters::*,
+  xtypes::{
+    DynamicDataReader, DynamicDataWriter, DynamicType, EncodedSample, EncodedSampleAdapter,
+    XcdrVersion,
+  },
+  raw_endpoints::{RawDataReader, RawDataWriter, RawPayload, RawPayloadAdapter},
 };
 use crate::dds::statusevents::*;
 
@@ -140,6 +145,71 @@ impl Publisher {
   }
 
   /// Shorthand for crate_datawriter with Commaon Data Representation Little Endian
+  /// Creates a [DynamicDataWriter](xtypes/struct.DynamicDataWriter.html) for
+  /// a type that is known only at run time. The type must be a structure.
+  ///
+  /// The DataRepresentation QoS selects between XCDR2 (the default) and XCDR1.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::dds::DomainParticipant;
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// # use rustdds::dds::data_types::TopicKind;
+  /// use rustdds::dds::xtypes::{DynamicData, DynamicType, DynamicValue};
+  ///
+  /// let domain_participant = DomainParticipant::new(0);
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// let point_type = DynamicType::from_idl("struct Point { long x; long y; };", "Point").unwrap();
+  /// let topic = domain_participant.create_topic("points", "Point", &qos, TopicKind::NoKey).unwrap();
+  /// let data_writer = publisher.create_dynamic_datawriter(topic, &point_type, None).unwrap();
+  ///
+  /// let mut point = DynamicData::new(&point_type).unwrap();
+  /// point.set("x", DynamicValue::Int32(1)).unwrap();
+  /// data_writer.write(&point, None).unwrap();
+  /// ```
+  pub fn create_dynamic_datawriter(
+    &self,
+    topic: Topic,
+    dynamic_type: &DynamicType,
+    qos: Option<QosPolicies>,
+  ) -> Result<DynamicDataWriter> {
+    self.inner.create_dynamic_datawriter(self, topic, dynamic_type, qos)
+  }
+
+  /// Creates a [RawDataWriter](struct.RawDataWriter.html), which writes
+  /// samples that are serialized already, e.g. when forwarding samples
+  /// received by a [RawDataReader](struct.RawDataReader.html).
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::dds::DomainParticipant;
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// # use rustdds::dds::data_types::{RepresentationIdentifier, SerializedPayload, TopicKind};
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// let topic = domain_participant.create_topic("points", "Point", &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_raw_datawriter(topic, None).unwrap();
+  ///
+  /// // struct Point { @key long x; long y; } with x = 1, y = 2 in little-endian CDR
+  /// let payload = SerializedPayload::new(RepresentationIdentifier::CDR_LE, vec![1, 0, 0, 0, 2, 0, 0, 0]);
+  /// data_writer.write(payload, 1, None).unwrap();
+  /// data_writer.dispose(1, None).unwrap();
+  /// ```
+  pub fn create_raw_datawriter(
+    &self,
+    topic: Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<RawDataWriter> {
+    self.inner.create_raw_datawriter(self, topic, qos)
+  }
+
   pub fn create_datawriter_CDR<D>(&self, entity_id: Option<EntityId>, 
       topic: Topic, qos: Option<QosPolicies>) 
     -> Result<WithKeyDataWriter<D, CDRSerializerAdapter<D,LittleEndian>>>
@@ -358,18 +428,34 @@ impl InnerPublisher {
     <D as Keyed>::K: Key,
     SA: SerializerAdapter<D>,
   {
-    let (dwcc_upload, hccc_download) = mio_channel::sync_channel::<WriterCommand>(100);
-    let (message_status_sender, message_status_receiver) = mio_channel::sync_channel(100);
-
-   
     // DDS Spec 2.2.2.4.1.5 create_datawriter:
     // If no QoS is specified, we should take the Publisher default
     // QoS, modify it to match any QoS settings (that are set) in the
     // Topic QoS and use that.
     let writer_qos = optional_qos.unwrap_or_else(
         || self.default_datawriter_qos.modify_by(&topic.get_qos()) );
+    let writer_qos = writer_data_representation::<D,SA>(writer_qos)?;
 
     let entity_id = unwrap_or_random_EntityId(entity_id_opt, EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    self.create_datawriter_internal(outer, entity_id, topic, writer_qos)
+  }
+
+  // Creates the DataWriter with its final QoS.
+  fn create_datawriter_internal<D, SA>(
+    &self,
+    outer: &Publisher,
+    entity_id: EntityId,
+    topic: Topic,
+    writer_qos: QosPolicies,
+  ) -> Result<WithKeyDataWriter<D, SA>>
+  where
+    D: Keyed + Serialize,
+    <D as Keyed>::K: Key,
+    SA: SerializerAdapter<D>,
+  {
+    let (dwcc_upload, hccc_download) = mio_channel::sync_channel::<WriterCommand>(100);
+    let (message_status_sender, message_status_receiver) = mio_channel::sync_channel(100);
+
     let dp = self.get_participant()
               .ok_or("upgrade fail")
               .or_else (|e| log_and_err_internal!("Where is my DomainParticipant? {}",e))?;
@@ -423,6 +509,60 @@ impl InnerPublisher {
     Ok(NoKeyDataWriter::<D, SA>::from_keyed(d))
   }
 
+  pub fn create_dynamic_datawriter(
+    &self,
+    outer: &Publisher,
+    topic: Topic,
+    dynamic_type: &DynamicType,
+    optional_qos: Option<QosPolicies>,
+  ) -> Result<DynamicDataWriter> {
+    use policy::{DataRepresentation, DataRepresentationId};
+    let descriptor = match dynamic_type.as_struct() {
+      Some(s) => s.clone(),
+      None => return Error::bad_parameter("DynamicDataWriter type must be a structure."),
+    };
+    let mut writer_qos = optional_qos.unwrap_or_else(
+        || self.default_datawriter_qos.modify_by(&topic.get_qos()) );
+    // The representation cannot come from a SerializerAdapter here, so it is
+    // chosen by QoS instead.
+    let version = match writer_qos.data_representation.map(|dr| dr.offered()) {
+      Some(DataRepresentationId::XCDR) => XcdrVersion::Xcdr1,
+      Some(DataRepresentationId::XCDR2) => XcdrVersion::Xcdr2,
+      Some(DataRepresentationId::XML) =>
+        return Error::bad_parameter("DynamicDataWriter does not support XML representation."),
+      None => {
+        writer_qos.data_representation = Some(DataRepresentation::new(&[DataRepresentationId::XCDR2]));
+        XcdrVersion::Xcdr2
+      }
+    };
+    let entity_kind = match topic.kind() {
+      TopicKind::WithKey => EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      TopicKind::NoKey => EntityKind::WRITER_NO_KEY_USER_DEFINED,
+    };
+    let entity_id = unwrap_or_random_EntityId(None, entity_kind);
+    let writer = self.create_datawriter_internal::<EncodedSample, EncodedSampleAdapter>(
+      outer, entity_id, topic, writer_qos)?;
+    Ok(DynamicDataWriter::new(writer, dynamic_type.clone(), descriptor, version))
+  }
+
+  pub fn create_raw_datawriter(
+    &self,
+    outer: &Publisher,
+    topic: Topic,
+    optional_qos: Option<QosPolicies>,
+  ) -> Result<RawDataWriter> {
+    let writer_qos = optional_qos.unwrap_or_else(
+        || self.default_datawriter_qos.modify_by(&topic.get_qos()) );
+    let entity_kind = match topic.kind() {
+      TopicKind::WithKey => EntityKind::WRITER_WITH_KEY_USER_DEFINED,
+      TopicKind::NoKey => EntityKind::WRITER_NO_KEY_USER_DEFINED,
+    };
+    let entity_id = unwrap_or_random_EntityId(None, entity_kind);
+    let writer = self.create_datawriter_internal::<RawPayload, RawPayloadAdapter>(
+      outer, entity_id, topic, writer_qos)?;
+    Ok(RawDataWriter::new(writer))
+  }
+
   fn add_writer(&self, writer: Writer) -> Result<()> {
     match self.add_writer_sender.send(writer) {
       Ok(_) => Ok(()),
@@ -650,6 +790,69 @@ impl Subscriber {
   }
 
 
+  /// Creates a [DynamicDataReader](xtypes/struct.DynamicDataReader.html) for
+  /// a type that is known only at run time. The type must be a structure.
+  ///
+  /// The Topic may be either WITH_KEY or NO_KEY.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::dds::DomainParticipant;
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// # use rustdds::dds::data_types::TopicKind;
+  /// use rustdds::dds::xtypes::DynamicType;
+  ///
+  /// let domain_participant = DomainParticipant::new(0);
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// let point_type = DynamicType::from_idl("struct Point { long x; long y; };", "Point").unwrap();
+  /// let topic = domain_participant.create_topic("points", "Point", &qos, TopicKind::NoKey).unwrap();
+  /// let mut data_reader = subscriber.create_dynamic_datareader(topic, &point_type, None).unwrap();
+  /// while let Ok(Some(sample)) = data_reader.take_next_sample() {
+  ///   println!("x = {:?}", sample.value().get("x"));
+  /// }
+  /// ```
+  pub fn create_dynamic_datareader(
+    &self,
+    topic: Topic,
+    dynamic_type: &DynamicType,
+    qos: Option<QosPolicies>,
+  ) -> Result<DynamicDataReader> {
+    self.inner.create_dynamic_datareader(self, topic, dynamic_type, qos)
+  }
+
+  /// Creates a [RawDataReader](struct.RawDataReader.html), which receives
+  /// samples of any type without deserializing them.
+  ///
+  /// The Topic may be either WITH_KEY or NO_KEY.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use rustdds::dds::DomainParticipant;
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// # use rustdds::dds::data_types::TopicKind;
+  ///
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// let topic = domain_participant.create_topic("points", "Point", &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_raw_datareader(topic, None).unwrap();
+  /// while let Ok(Some(sample)) = data_reader.take_next_sample() {
+  ///   println!("instance {:x}: {:?}", sample.key_hash(), sample.payload());
+  /// }
+  /// ```
+  pub fn create_raw_datareader(
+    &self,
+    topic: Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<RawDataReader> {
+    self.inner.create_raw_datareader(self, topic, qos)
+  }
+
   // Retrieves a previously created DataReader belonging to the Subscriber.
   // TODO: Is this even possible. Whould probably need to return reference and store references on creation
   /*
@@ -739,6 +942,7 @@ impl InnerSubscriber {
 
 
     let qos = optional_qos.unwrap_or_else(|| topic.get_qos().clone());
+    let qos = reader_data_representation::<D,SA>(qos);
 
     let entity_id = unwrap_or_random_EntityId(entity_id_opt, EntityKind::READER_WITH_KEY_USER_DEFINED);
 
@@ -756,7 +960,7 @@ impl InnerSubscriber {
     let new_reader = Reader::new(
       reader_guid,
       send,
-      status_sender,
+      status_sender.clone(),
       dp.get_dds_cache(),
       topic.get_name().to_string(),
       qos.clone(),
@@ -772,7 +976,9 @@ impl InnerSubscriber {
       dp.get_dds_cache(),
       self.discovery_command.clone(),
       status_receiver,
+      status_sender,
       reader_command_sender,
+      new_reader.local_matched_writers(),
     )?;
 
     {
@@ -846,6 +1052,43 @@ impl InnerSubscriber {
     Ok(NoKeyDataReader::<D, SA>::from_keyed(d))
   }
 
+  pub fn create_dynamic_datareader(
+    &self,
+    outer: &Subscriber,
+    topic: Topic,
+    dynamic_type: &DynamicType,
+    qos: Option<QosPolicies>,
+  ) -> Result<DynamicDataReader> {
+    let descriptor = match dynamic_type.as_struct() {
+      Some(s) => s.clone(),
+      None => return Error::bad_parameter("DynamicDataReader type must be a structure."),
+    };
+    let entity_kind = match topic.kind() {
+      TopicKind::WithKey => EntityKind::READER_WITH_KEY_USER_DEFINED,
+      TopicKind::NoKey => EntityKind::READER_NO_KEY_USER_DEFINED,
+    };
+    let entity_id = unwrap_or_random_EntityId(None, entity_kind);
+    let reader = self.create_datareader_internal::<EncodedSample, EncodedSampleAdapter>(
+      outer, Some(entity_id), topic, qos)?;
+    Ok(DynamicDataReader::new(reader, dynamic_type.clone(), descriptor))
+  }
+
+  pub fn create_raw_datareader(
+    &self,
+    outer: &Subscriber,
+    topic: Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<RawDataReader> {
+    let entity_kind = match topic.kind() {
+      TopicKind::WithKey => EntityKind::READER_WITH_KEY_USER_DEFINED,
+      TopicKind::NoKey => EntityKind::READER_NO_KEY_USER_DEFINED,
+    };
+    let entity_id = unwrap_or_random_EntityId(None, entity_kind);
+    let reader = self.create_datareader_internal::<RawPayload, RawPayloadAdapter>(
+      outer, Some(entity_id), topic, qos)?;
+    Ok(RawDataReader::new(reader))
+  }
+
   pub fn get_participant(&self) -> Option<DomainParticipant> {
     self.domain_participant.clone().upgrade()
   }
@@ -863,3 +1106,45 @@ fn unwrap_or_random_EntityId(entity_id_opt: Option<EntityId>, entityKind: Entity
 
 #[cfg(test)]
 mod tests {}
+
+// XTypes 7.6.3.1: DataWriter offers the data representation that its SerializerAdapter
+// produces. An explicitly given representation must agree with the adapter.
+fn writer_data_representation<D, SA>(mut qos: QosPolicies) -> Result<QosPolicies>
+where
+  D: Serialize,
+  SA: SerializerAdapter<D>,
+{
+  use policy::{DataRepresentation, DataRepresentationId};
+  let produced = match DataRepresentationId::from_representation_identifier(SA::output_encoding()) {
+    Some(id) => id,
+    None => return Ok(qos), // Some custom encoding. Cannot check.
+  };
+  match qos.data_representation {
+    Some(dr) if dr.offered() != produced =>
+      Error::bad_parameter("DataRepresentation QoS does not match the SerializerAdapter."),
+    Some(_) => Ok(qos),
+    None => {
+      qos.data_representation = Some(DataRepresentation::new(&[produced]));
+      Ok(qos)
+    }
+  }
+}
+
+// DataReader accepts the data representations that its DeserializerAdapter can decode,
+// unless QoS narrows them down.
+fn reader_data_representation<D, DA>(mut qos: QosPolicies) -> QosPolicies
+where
+  D: DeserializeOwned,
+  DA: DeserializerAdapter<D>,
+{
+  use policy::{DataRepresentation, DataRepresentationId};
+  if qos.data_representation.is_none() {
+    let ids: Vec<DataRepresentationId> = DA::supported_encodings().iter()
+      .filter_map(|e| DataRepresentationId::from_representation_identifier(*e))
+      .collect();
+    if ! ids.is_empty() {
+      qos.data_representation = Some(DataRepresentation::new(&ids));
+    }
+  }
+  qos
+}
@@ -1,4 +1,5 @@
 use log::{trace,};
+use speedy::Readable;
 
 use crate::{
   structure::inline_qos::KeyHash,
@@ -6,7 +7,10 @@ use crate::{
   messages::submessages::submessage_elements::{
     parameter_list::ParameterList, RepresentationIdentifier,
   },
-  structure::{parameter_id::ParameterId, inline_qos::StatusInfo},
+  structure::{
+    parameter_id::ParameterId,
+    inline_qos::{SampleIdentity, StatusInfo},
+  },
 };
 
 // This is to be implemented by all DomanParticipant, Publisher, Subscriber, DataWriter, DataReader, Topic
@@ -47,6 +51,7 @@ pub enum QosPolicyId {
   //TransportPriority, // 20
   Lifespan,
   //DurabilityService, // 22
+  DataRepresentation, // XTypes 23
 }
 
 /// Utility for building [QosPolicies](struct.QosPolicies.html)
@@ -63,6 +68,8 @@ pub struct QosPolicyBuilder {
   history: Option<policy::History>,
   resource_limits: Option<policy::ResourceLimits>,
   lifespan: Option<policy::Lifespan>,
+  data_representation: Option<policy::DataRepresentation>,
+  type_consistency: Option<policy::TypeConsistencyEnforcement>,
 }
 
 impl QosPolicyBuilder {
@@ -80,6 +87,8 @@ impl QosPolicyBuilder {
       history: None,
       resource_limits: None,
       lifespan: None,
+      data_representation: None,
+      type_consistency: None,
     }
   }
 
@@ -152,6 +161,22 @@ impl QosPolicyBuilder {
     self
   }
 
+  pub const fn data_representation(
+    mut self,
+    data_representation: policy::DataRepresentation,
+  ) -> QosPolicyBuilder {
+    self.data_representation = Some(data_representation);
+    self
+  }
+
+  pub const fn type_consistency(
+    mut self,
+    type_consistency: policy::TypeConsistencyEnforcement,
+  ) -> QosPolicyBuilder {
+    self.type_consistency = Some(type_consistency);
+    self
+  }
+
   pub const fn build(self) -> QosPolicies {
     QosPolicies {
       durability: self.durability,
@@ -166,6 +191,8 @@ impl QosPolicyBuilder {
       history: self.history,
       resource_limits: self.resource_limits,
       lifespan: self.lifespan,
+      data_representation: self.data_representation,
+      type_consistency: self.type_consistency,
     }
   }
 }
@@ -186,6 +213,8 @@ pub struct QosPolicies {
   pub(crate) history: Option<policy::History>,
   pub(crate) resource_limits: Option<policy::ResourceLimits>,
   pub(crate) lifespan: Option<policy::Lifespan>,
+  pub(crate) data_representation: Option<policy::DataRepresentation>,
+  pub(crate) type_consistency: Option<policy::TypeConsistencyEnforcement>,
 }
 
 impl QosPolicies {
@@ -204,6 +233,8 @@ impl QosPolicies {
       history: None,
       resource_limits: None,
       lifespan: None,
+      data_representation: None,
+      type_consistency: None,
     }
   }
 
@@ -259,6 +290,14 @@ impl QosPolicies {
     self.lifespan
   }
 
+  pub const fn data_representation(&self) -> Option<policy::DataRepresentation> {
+    self.data_representation
+  }
+
+  pub const fn type_consistency(&self) -> Option<policy::TypeConsistencyEnforcement> {
+    self.type_consistency
+  }
+
   pub fn modify_by(&self,other: &QosPolicies) -> QosPolicies {
     QosPolicies {
       durability: other.durability.or(self.durability),
@@ -273,6 +312,8 @@ impl QosPolicies {
       history: other.history.or(self.history),
       resource_limits: other.resource_limits.or(self.resource_limits),
       lifespan: other.lifespan.or(self.lifespan),      
+      data_representation: other.data_representation.or(self.data_representation),
+      type_consistency: other.type_consistency.or(self.type_consistency),
     }
   }
 
@@ -374,6 +415,15 @@ impl QosPolicies {
       }
     }
 
+    // check Data Representation (XTypes 7.6.3.1.2)
+    // The representation offered by the writer must be one of those the reader accepts.
+    // Missing policy means XCDR only.
+    let offered = self.data_representation.unwrap_or_default().offered();
+    let accepted = other.data_representation.unwrap_or_default();
+    if ! accepted.accepts(offered) {
+      return Some(QosPolicyId::DataRepresentation)
+    }
+
     // default value. no incompatibility detected.
     None
   }
@@ -383,6 +433,7 @@ impl QosPolicies {
 /// Contains all available QoSPolicies
 pub mod policy {
   use crate::structure::{parameter_id::ParameterId, duration::Duration};
+  use crate::messages::submessages::submessage_elements::RepresentationIdentifier;
   use serde::{Serialize, Deserialize};
   use std::cmp::Ordering;
 
@@ -559,6 +610,138 @@ pub mod policy {
     pub max_samples_per_instance: i32,
   }
 
+  /// Data representation identifiers of XTypes 7.6.3.1.1
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+  pub enum DataRepresentationId {
+    XCDR,
+    XML,
+    XCDR2,
+  }
+
+  impl DataRepresentationId {
+    pub fn from_i16(id: i16) -> Option<DataRepresentationId> {
+      match id {
+        0 => Some(DataRepresentationId::XCDR),
+        1 => Some(DataRepresentationId::XML),
+        2 => Some(DataRepresentationId::XCDR2),
+        _ => None,
+      }
+    }
+
+    pub fn to_i16(self) -> i16 {
+      match self {
+        DataRepresentationId::XCDR => 0,
+        DataRepresentationId::XML => 1,
+        DataRepresentationId::XCDR2 => 2,
+      }
+    }
+
+    /// Which data representation a serialized payload encoding belongs to.
+    pub fn from_representation_identifier(
+      encoding: RepresentationIdentifier,
+    ) -> Option<DataRepresentationId> {
+      match encoding {
+        RepresentationIdentifier::CDR_BE
+        | RepresentationIdentifier::CDR_LE
+        | RepresentationIdentifier::PL_CDR_BE
+        | RepresentationIdentifier::PL_CDR_LE => Some(DataRepresentationId::XCDR),
+        RepresentationIdentifier::CDR2_BE
+        | RepresentationIdentifier::CDR2_LE
+        | RepresentationIdentifier::D_CDR_BE
+        | RepresentationIdentifier::D_CDR_LE
+        | RepresentationIdentifier::PL_CDR2_BE
+        | RepresentationIdentifier::PL_CDR2_LE => Some(DataRepresentationId::XCDR2),
+        RepresentationIdentifier::XML => Some(DataRepresentationId::XML),
+        _ => None,
+      }
+    }
+  }
+
+  /// XTypes 7.6.3.1.1 DATA_REPRESENTATION
+  ///
+  /// A DataWriter offers the first representation in the list.
+  /// A DataReader accepts all of the listed representations.
+  /// An empty list means XCDR.
+  ///
+  /// If not set, DataWriters and DataReaders fill this in from their serializer adapters.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+  pub struct DataRepresentation {
+    // There are only three representations, so a list without duplicates fits here.
+    ids: [DataRepresentationId; 3],
+    len: usize,
+  }
+
+  impl DataRepresentation {
+    pub fn new(ids: &[DataRepresentationId]) -> DataRepresentation {
+      let mut dr = DataRepresentation { ids: [DataRepresentationId::XCDR; 3], len: 0 };
+      for id in ids {
+        if ! dr.ids().contains(id) {
+          dr.ids[dr.len] = *id;
+          dr.len += 1;
+        }
+      }
+      dr
+    }
+
+    pub fn ids(&self) -> &[DataRepresentationId] {
+      &self.ids[..self.len]
+    }
+
+    pub fn offered(&self) -> DataRepresentationId {
+      self.ids().first().copied().unwrap_or(DataRepresentationId::XCDR)
+    }
+
+    pub fn accepts(&self, id: DataRepresentationId) -> bool {
+      if self.len == 0 {
+        id == DataRepresentationId::XCDR
+      } else {
+        self.ids().contains(&id)
+      }
+    }
+  }
+
+  impl Default for DataRepresentation {
+    fn default() -> DataRepresentation {
+      DataRepresentation::new(&[DataRepresentationId::XCDR])
+    }
+  }
+
+  /// XTypes 7.6.3.4.1 TypeConsistencyKind
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+  pub enum TypeConsistencyKind {
+    DisallowTypeCoercion,
+    AllowTypeCoercion,
+  }
+
+  /// XTypes 7.6.3.4 TYPE_CONSISTENCY_ENFORCEMENT
+  ///
+  /// Decides which writer types a DataReader accepts. This is used only
+  /// when both endpoints advertise their types, see the
+  /// [xtypes](../../xtypes/index.html) module.
+  #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+  pub struct TypeConsistencyEnforcement {
+    pub kind: TypeConsistencyKind,
+    pub ignore_sequence_bounds: bool,
+    pub ignore_string_bounds: bool,
+    pub ignore_member_names: bool,
+    pub prevent_type_widening: bool,
+    /// Do not match endpoints whose types are not known.
+    pub force_type_validation: bool,
+  }
+
+  impl Default for TypeConsistencyEnforcement {
+    fn default() -> TypeConsistencyEnforcement {
+      TypeConsistencyEnforcement {
+        kind: TypeConsistencyKind::AllowTypeCoercion,
+        ignore_sequence_bounds: true,
+        ignore_string_bounds: true,
+        ignore_member_names: false,
+        prevent_type_widening: false,
+        force_type_validation: false,
+      }
+    }
+  }
+
   #[derive(Serialize, Deserialize)]
   pub(crate) struct QosData<D>
   where
@@ -654,6 +837,26 @@ impl InlineQos {
 
     Ok(key_hash)
   }
+
+  // Fast DDS sends the identity with both the standard and its older
+  // vendor-specific parameter id. Either is accepted.
+  pub fn related_sample_identity(
+    params: &ParameterList,
+    endianness: speedy::Endianness,
+  ) -> std::result::Result<Option<SampleIdentity>, speedy::Error> {
+    params
+      .parameters
+      .iter()
+      .find(|p| p.parameter_id == ParameterId::PID_RELATED_SAMPLE_IDENTITY)
+      .or_else(|| {
+        params
+          .parameters
+          .iter()
+          .find(|p| p.parameter_id == ParameterId::PID_CUSTOM_RELATED_SAMPLE_IDENTITY)
+      })
+      .map(|p| SampleIdentity::read_from_buffer_with_ctx(endianness, &p.value))
+      .transpose()
+  }
 }
 
 
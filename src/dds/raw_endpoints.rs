@@ -0,0 +1,542 @@
+use std::io;
+
+use mio::{Evented, Poll, PollOpt, Ready, Token};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+  dds::{
+    data_types::{ReadCondition, SelectByKey},
+    qos::{HasQoSPolicy, QosPolicies},
+    sampleinfo::SampleInfo,
+    statusevents::{DataReaderStatus, StatusEvented},
+    topic::Topic,
+    traits::{
+      key::{Key, Keyed},
+      serde_adapters::{DeserializerAdapter, SerializerAdapter},
+    },
+    values::result::Result,
+    with_key::{datareader::DataReader, datasample::DataSample, datawriter::DataWriter},
+  },
+  messages::submessages::submessage_elements::serialized_payload::{
+    RepresentationIdentifier, SerializedPayload,
+  },
+  serialization,
+  structure::{entity::RTPSEntity, guid::GUID, inline_qos::SampleIdentity, time::Timestamp},
+};
+
+// The key of a raw sample is the key hash that the remote DataWriter sent in
+// inline QoS. It cannot be computed from the payload, so all samples from
+// writers that do not send PID_KEY_HASH belong to the instance of key hash 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub(crate) struct RawKey(u128);
+
+impl Key for RawKey {
+  fn is_fixed_size() -> bool {
+    true
+  }
+
+  fn into_hash_key(&self) -> u128 {
+    self.0
+  }
+}
+
+// Received sample as it is, together with its key hash. The serde traits are
+// implemented only to satisfy the bounds of the typed DataReader and
+// DataWriter, which the raw endpoints wrap. RawPayloadAdapter bypasses them.
+pub(crate) struct RawPayload {
+  payload: SerializedPayload,
+  key: RawKey,
+}
+
+impl Keyed for RawPayload {
+  type K = RawKey;
+  fn get_key(&self) -> RawKey {
+    self.key
+  }
+}
+
+impl Serialize for RawPayload {
+  fn serialize<S: Serializer>(&self, _serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    Err(ser::Error::custom(
+      "RawPayload is not serialized with serde.",
+    ))
+  }
+}
+
+impl<'de> Deserialize<'de> for RawPayload {
+  fn deserialize<D: Deserializer<'de>>(_deserializer: D) -> std::result::Result<Self, D::Error> {
+    Err(de::Error::custom(
+      "RawPayload is not deserialized with serde.",
+    ))
+  }
+}
+
+pub(crate) struct RawPayloadAdapter {}
+
+const repr_ids: [RepresentationIdentifier; 14] = [
+  RepresentationIdentifier::CDR_BE,
+  RepresentationIdentifier::CDR_LE,
+  RepresentationIdentifier::PL_CDR_BE,
+  RepresentationIdentifier::PL_CDR_LE,
+  RepresentationIdentifier::CDR2_BE,
+  RepresentationIdentifier::CDR2_LE,
+  RepresentationIdentifier::PL_CDR2_BE,
+  RepresentationIdentifier::PL_CDR2_LE,
+  RepresentationIdentifier::D_CDR_BE,
+  RepresentationIdentifier::D_CDR_LE,
+  RepresentationIdentifier::XML,
+  RepresentationIdentifier::CBOR,
+  RepresentationIdentifier::JSON,
+  RepresentationIdentifier::PROTOBUF,
+];
+
+impl DeserializerAdapter<RawPayload> for RawPayloadAdapter {
+  // Advertised in the DataRepresentation QoS of the RawDataReader
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &repr_ids
+  }
+
+  // The payload is not decoded, so vendor-specific representations are
+  // received too.
+  fn accepts_encoding(_encoding: RepresentationIdentifier) -> bool {
+    true
+  }
+
+  // Not called by DataReader, which uses from_payload. There is no key hash here.
+  fn from_bytes(
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> serialization::error::Result<RawPayload> {
+    Ok(RawPayload {
+      payload: SerializedPayload::new(encoding, input_bytes.to_vec()),
+      key: RawKey(0),
+    })
+  }
+
+  fn from_payload(
+    payload: &SerializedPayload,
+    key_hash: u128,
+  ) -> serialization::error::Result<RawPayload> {
+    Ok(RawPayload {
+      // Bytes is reference-counted, so this does not copy the data.
+      payload: payload.clone(),
+      key: RawKey(key_hash),
+    })
+  }
+}
+
+impl SerializerAdapter<RawPayload> for RawPayloadAdapter {
+  // Not used, because RawDataWriter writes each payload with its own
+  // representation identifier.
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::CDR_LE
+  }
+
+  fn to_writer<W: io::Write>(
+    mut writer: W,
+    value: &RawPayload,
+  ) -> serialization::error::Result<()> {
+    writer.write_all(&value.payload.value)?;
+    Ok(())
+  }
+}
+
+/// Sample received by a [`RawDataReader`](struct.RawDataReader.html)
+///
+/// Samples of disposed instances carry only the key hash and the
+/// [`SampleInfo`](data_types/struct.SampleInfo.html), and no payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawSample {
+  sample_info: SampleInfo,
+  key_hash: u128,
+  payload: Option<SerializedPayload>,
+}
+
+impl RawSample {
+  fn from_data_sample(
+    sample_info: SampleInfo,
+    value: std::result::Result<&RawPayload, RawKey>,
+  ) -> Self {
+    match value {
+      Ok(raw) => RawSample {
+        sample_info,
+        key_hash: raw.key.0,
+        payload: Some(raw.payload.clone()),
+      },
+      Err(key) => RawSample {
+        sample_info,
+        key_hash: key.0,
+        payload: None,
+      },
+    }
+  }
+
+  pub fn sample_info(&self) -> &SampleInfo {
+    &self.sample_info
+  }
+
+  /// Key hash of the instance, as sent by the DataWriter. It is zero on NO_KEY
+  /// topics and if the DataWriter did not send it.
+  pub fn key_hash(&self) -> u128 {
+    self.key_hash
+  }
+
+  /// The serialized data, or `None` if the instance was disposed.
+  pub fn payload(&self) -> Option<&SerializedPayload> {
+    self.payload.as_ref()
+  }
+
+  pub fn into_payload(self) -> Option<SerializedPayload> {
+    self.payload
+  }
+}
+
+fn from_borrowed(samples: Vec<DataSample<&RawPayload>>) -> Vec<RawSample> {
+  samples
+    .into_iter()
+    .map(|s| RawSample::from_data_sample(s.sample_info, s.value))
+    .collect()
+}
+
+fn from_owned(samples: Vec<DataSample<RawPayload>>) -> Vec<RawSample> {
+  samples
+    .into_iter()
+    .map(|s| match s.value {
+      Ok(raw) => RawSample {
+        sample_info: s.sample_info,
+        key_hash: raw.key.0,
+        payload: Some(raw.payload),
+      },
+      Err(key) => RawSample::from_data_sample(s.sample_info, Err(key)),
+    })
+    .collect()
+}
+
+/// DataReader for samples of any type, without deserializing them
+///
+/// The samples are received in their serialized form, together with their
+/// representation identifier and key hash, e.g. to forward them in a bridge.
+/// Samples in any representation are accepted.
+///
+/// Instances are identified by key hash, so reading by instance, instance
+/// states and disposes work as in the typed
+/// [`DataReader`](struct.With_Key_DataReader.html).
+///
+/// Created with
+/// [`Subscriber::create_raw_datareader`](struct.Subscriber.html#method.create_raw_datareader).
+pub struct RawDataReader {
+  reader: DataReader<RawPayload, RawPayloadAdapter>,
+}
+
+impl RawDataReader {
+  pub(crate) fn new(reader: DataReader<RawPayload, RawPayloadAdapter>) -> RawDataReader {
+    RawDataReader { reader }
+  }
+
+  /// Reads samples like
+  /// [`DataReader::read`](struct.With_Key_DataReader.html#method.read)
+  pub fn read(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+  ) -> Result<Vec<RawSample>> {
+    Ok(from_borrowed(
+      self.reader.read(max_samples, read_condition)?,
+    ))
+  }
+
+  /// Takes samples like
+  /// [`DataReader::take`](struct.With_Key_DataReader.html#method.take)
+  pub fn take(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+  ) -> Result<Vec<RawSample>> {
+    Ok(from_owned(self.reader.take(max_samples, read_condition)?))
+  }
+
+  pub fn read_next_sample(&mut self) -> Result<Option<RawSample>> {
+    let mut ds = self.read(1, ReadCondition::not_read())?;
+    Ok(ds.pop())
+  }
+
+  pub fn take_next_sample(&mut self) -> Result<Option<RawSample>> {
+    let mut ds = self.take(1, ReadCondition::not_read())?;
+    Ok(ds.pop())
+  }
+
+  /// Reads samples of one instance like
+  /// [`DataReader::read_instance`](struct.With_Key_DataReader.html#method.read_instance).
+  /// Instances are ordered by key hash.
+  pub fn read_instance(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    key_hash: Option<u128>,
+    this_or_next: SelectByKey,
+  ) -> Result<Vec<RawSample>> {
+    Ok(from_borrowed(self.reader.read_instance(
+      max_samples,
+      read_condition,
+      key_hash.map(RawKey),
+      this_or_next,
+    )?))
+  }
+
+  /// Takes samples of one instance like
+  /// [`DataReader::take_instance`](struct.With_Key_DataReader.html#method.take_instance).
+  /// Instances are ordered by key hash.
+  pub fn take_instance(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+    key_hash: Option<u128>,
+    this_or_next: SelectByKey,
+  ) -> Result<Vec<RawSample>> {
+    Ok(from_owned(self.reader.take_instance(
+      max_samples,
+      read_condition,
+      key_hash.map(RawKey),
+      this_or_next,
+    )?))
+  }
+}
+
+// Polling delegates to the wrapped DataReader, like in no_key::DataReader.
+impl Evented for RawDataReader {
+  fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+    self
+      .reader
+      .notification_receiver
+      .register(poll, token, interest, opts)
+  }
+
+  fn reregister(
+    &self,
+    poll: &Poll,
+    token: Token,
+    interest: Ready,
+    opts: PollOpt,
+  ) -> io::Result<()> {
+    self
+      .reader
+      .notification_receiver
+      .reregister(poll, token, interest, opts)
+  }
+
+  fn deregister(&self, poll: &Poll) -> io::Result<()> {
+    self.reader.notification_receiver.deregister(poll)
+  }
+}
+
+impl StatusEvented<DataReaderStatus> for RawDataReader {
+  fn as_status_evented(&mut self) -> &dyn Evented {
+    self.reader.as_status_evented()
+  }
+
+  fn try_recv_status(&self) -> Option<DataReaderStatus> {
+    self.reader.try_recv_status()
+  }
+}
+
+impl HasQoSPolicy for RawDataReader {
+  fn get_qos(&self) -> QosPolicies {
+    self.reader.get_qos()
+  }
+}
+
+impl RTPSEntity for RawDataReader {
+  fn get_guid(&self) -> GUID {
+    self.reader.get_guid()
+  }
+}
+
+/// DataWriter for samples that are serialized already
+///
+/// Each sample is sent with its own representation identifier and key hash,
+/// e.g. as they were received by a [`RawDataReader`](struct.RawDataReader.html).
+/// The DataRepresentation QoS is not derived from the samples, so set it if
+/// the samples are not in XCDR1.
+///
+/// Created with
+/// [`Publisher::create_raw_datawriter`](struct.Publisher.html#method.create_raw_datawriter).
+pub struct RawDataWriter {
+  writer: DataWriter<RawPayload, RawPayloadAdapter>,
+}
+
+impl RawDataWriter {
+  pub(crate) fn new(writer: DataWriter<RawPayload, RawPayloadAdapter>) -> RawDataWriter {
+    RawDataWriter { writer }
+  }
+
+  /// Writes a serialized sample of the instance identified by `key_hash`. Use
+  /// zero on NO_KEY topics.
+  pub fn write(
+    &self,
+    payload: SerializedPayload,
+    key_hash: u128,
+    source_timestamp: Option<Timestamp>,
+  ) -> Result<()> {
+    self
+      .writer
+      .write_serialized(payload, key_hash, None, source_timestamp)
+  }
+
+  /// Writes a serialized sample like [`write`](#method.write), and relates it
+  /// to another sample, e.g. a service reply to its request. The identity of
+  /// the other sample is sent in inline QoS, and readers get it in
+  /// [`SampleInfo::related_sample_identity`](data_types/struct.SampleInfo.html#structfield.related_sample_identity).
+  ///
+  /// Each write and dispose takes the next sequence number of the DataWriter,
+  /// starting from 1. It is the sequence number in the identity of the
+  /// sample.
+  pub fn write_related(
+    &self,
+    payload: SerializedPayload,
+    key_hash: u128,
+    related_sample_identity: SampleIdentity,
+    source_timestamp: Option<Timestamp>,
+  ) -> Result<()> {
+    self.writer.write_serialized(
+      payload,
+      key_hash,
+      Some(related_sample_identity),
+      source_timestamp,
+    )
+  }
+
+  /// Disposes the instance identified by `key_hash`
+  pub fn dispose(&self, key_hash: u128, source_timestamp: Option<Timestamp>) -> Result<()> {
+    self.writer.dispose(RawKey(key_hash), source_timestamp)
+  }
+
+  pub fn get_topic(&self) -> &Topic {
+    self.writer.get_topic()
+  }
+}
+
+impl HasQoSPolicy for RawDataWriter {
+  fn get_qos(&self) -> QosPolicies {
+    self.writer.get_qos()
+  }
+}
+
+impl RTPSEntity for RawDataWriter {
+  fn get_guid(&self) -> GUID {
+    self.writer.get_guid()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{thread, time::Duration};
+
+  use super::*;
+  use crate::dds::{
+    participant::DomainParticipant,
+    qos::{policy, QosPolicyBuilder},
+    topic::TopicKind,
+  };
+
+  // Reads until `count` samples have arrived, or gives up after a few seconds.
+  fn read_samples(reader: &mut RawDataReader, count: usize) -> Vec<RawSample> {
+    for _ in 0..50 {
+      let samples = reader.read(usize::MAX, ReadCondition::any()).unwrap();
+      if samples.len() >= count {
+        return samples;
+      }
+      thread::sleep(Duration::from_millis(100));
+    }
+    reader.read(usize::MAX, ReadCondition::any()).unwrap()
+  }
+
+  #[test]
+  fn raw_payload_keeps_key_hash() {
+    let payload = SerializedPayload::new(RepresentationIdentifier::CDR2_LE, vec![1, 2, 3, 4]);
+    let raw = RawPayloadAdapter::from_payload(&payload, 0x1234).unwrap();
+    assert_eq!(raw.get_key(), RawKey(0x1234));
+    assert_eq!(raw.get_key().into_hash_key(), 0x1234);
+    assert_eq!(raw.payload, payload);
+
+    // The wrapped DataReader would otherwise drop samples in vendor
+    // representations.
+    for id in &[
+      RepresentationIdentifier::PL_CDR2_BE,
+      RepresentationIdentifier::JSON,
+      RepresentationIdentifier { bytes: [0x81, 0x7f] },
+    ] {
+      assert!(RawPayloadAdapter::accepts_encoding(*id));
+    }
+  }
+
+  #[test]
+  fn raw_instances_round_trip() {
+    let participant = DomainParticipant::new(0).unwrap();
+    let qos = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: crate::structure::duration::Duration::DURATION_ZERO,
+      })
+      .history(policy::History::KeepAll)
+      .build();
+    let topic = participant
+      .create_topic("raw_instances", "RawInstances", &qos, TopicKind::WithKey)
+      .unwrap();
+    let writer = participant
+      .create_publisher(&qos)
+      .unwrap()
+      .create_raw_datawriter(topic.clone(), None)
+      .unwrap();
+    let mut reader = participant
+      .create_subscriber(&qos)
+      .unwrap()
+      .create_raw_datareader(topic, None)
+      .unwrap();
+
+    // A representation that no DeserializerAdapter of this crate supports
+    let vendor_repr = RepresentationIdentifier { bytes: [0x81, 0x7f] };
+    let (key_a, key_b) = (0x0a, 0x0b);
+    for _ in 0..20 {
+      // Wait for the endpoints to match, then write.
+      if reader.read(1, ReadCondition::any()).unwrap().is_empty() {
+        writer
+          .write(SerializedPayload::new(vendor_repr, vec![1, 2, 3, 4]), key_a, None)
+          .unwrap();
+        thread::sleep(Duration::from_millis(100));
+      }
+    }
+    writer
+      .write(SerializedPayload::new(RepresentationIdentifier::CDR_LE, vec![5, 6, 7, 8]), key_b, None)
+      .unwrap();
+    let samples = read_samples(&mut reader, 2);
+    let keys: Vec<u128> = samples.iter().map(|s| s.key_hash()).collect();
+    assert!(keys.contains(&key_a) && keys.contains(&key_b), "got key hashes {:x?}", keys);
+    let a = samples.iter().find(|s| s.key_hash() == key_a).unwrap();
+    assert_eq!(a.payload().unwrap().representation_identifier, vendor_repr);
+
+    let only_b = reader
+      .read_instance(usize::MAX, ReadCondition::any(), Some(key_b), SelectByKey::This)
+      .unwrap();
+    assert!(!only_b.is_empty());
+    assert!(only_b.iter().all(|s| s.key_hash() == key_b));
+
+    writer.dispose(key_a, None).unwrap();
+    let mut disposed = None;
+    for _ in 0..50 {
+      disposed = reader
+        .take_instance(usize::MAX, ReadCondition::any(), Some(key_a), SelectByKey::This)
+        .unwrap()
+        .into_iter()
+        .find(|s| s.payload().is_none());
+      if disposed.is_some() {
+        break;
+      }
+      thread::sleep(Duration::from_millis(100));
+    }
+    let disposed = disposed.expect("dispose did not arrive");
+    assert_eq!(disposed.key_hash(), key_a);
+    assert_eq!(
+      disposed.sample_info().instance_state,
+      crate::dds::sampleinfo::InstanceState::NotAlive_Disposed
+    );
+  }
+}
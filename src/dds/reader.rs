@@ -37,6 +37,7 @@ use crate::structure::cache_change::CacheChange;
 use crate::dds::message_receiver::MessageReceiverState;
 use crate::dds::qos::{QosPolicies, HasQoSPolicy, policy};
 use crate::network::udp_sender::UDPSender;
+use crate::network::tcp_transport::TCPSender;
 
 use crate::serialization::message::Message;
 use crate::messages::header::Header;
@@ -80,6 +81,12 @@ pub(crate) struct Reader {
 
   timed_event_handler: Option<TimedEventHandler>,
   pub(crate) data_reader_command_receiver: mio_channel::Receiver<ReaderCommand>,
+  // Set by dp_event_loop, if TCP transport is in use.
+  tcp_sender: Option<TCPSender>,
+  // Matched writers in our own participant. Their samples are delivered
+  // through the shared DDSCache, so the DataReader needs to know which
+  // local writers it is allowed to see.
+  local_matched_writers: Arc<RwLock<BTreeSet<GUID>>>,
 } // placeholder
 
 impl Reader {
@@ -113,8 +120,15 @@ impl Reader {
       offered_incompatible_qos_count: 0,
       timed_event_handler: None,
       data_reader_command_receiver,
+      tcp_sender: None,
+      local_matched_writers: Arc::new(RwLock::new(BTreeSet::new())),
     }
   }
+
+  // Called by dp_event_loop
+  pub fn set_tcp_sender(&mut self, tcp_sender: TCPSender) {
+    self.tcp_sender = Some(tcp_sender);
+  }
   // TODO: check if it's necessary to implement different handlers for discovery
   // and user messages
 
@@ -304,6 +318,36 @@ impl Reader {
     }
   }
 
+  // Matches a Writer living in the same participant. No locators are needed,
+  // as samples are picked up directly from the DDSCache.
+  pub fn update_local_writer(&mut self, writer_guid: GUID, offered_qos: QosPolicies) {
+    self.update_writer_proxy(
+      RtpsWriterProxy::new(writer_guid, vec![], vec![], EntityId::ENTITYID_UNKNOWN),
+      offered_qos,
+    );
+    if self.matched_writers.contains_key(&writer_guid) {
+      match self.local_matched_writers.write() {
+        Ok(mut lw) => { lw.insert(writer_guid); }
+        Err(e) => panic!("Local matched writers set is poisoned. {:?}", e),
+      }
+    }
+  }
+
+  pub(crate) fn local_matched_writers(&self) -> Arc<RwLock<BTreeSet<GUID>>> {
+    self.local_matched_writers.clone()
+  }
+
+  // Called by dp_event_loop after a local Writer has processed new samples.
+  pub fn notify_local_write(&self, writer_guid: GUID) {
+    let matched = match self.local_matched_writers.read() {
+      Ok(lw) => lw.contains(&writer_guid),
+      Err(e) => panic!("Local matched writers set is poisoned. {:?}", e),
+    };
+    if matched {
+      self.notify_cache_change();
+    }
+  }
+
   // return value counts how many new proxies were added
   fn matched_writer_update(&mut self, proxy: RtpsWriterProxy) -> i32 {
     match self.matched_writer_lookup(proxy.remote_writer_guid) {
@@ -319,6 +363,9 @@ impl Reader {
   }
 
   pub fn remove_writer_proxy(&mut self, writer_guid:GUID) {
+    if let Ok(mut lw) = self.local_matched_writers.write() {
+      lw.remove(&writer_guid);
+    }
     if self.matched_writers.contains_key(&writer_guid) {
       self.matched_writers.remove(&writer_guid);
       self.send_status_change(DataReaderStatus::SubscriptionMatched { 
@@ -370,7 +417,12 @@ impl Reader {
   }
 
   // handles regular data message and updates history cache
-  pub fn handle_data_msg(&mut self, data: Data, mr_state: MessageReceiverState) {
+  pub fn handle_data_msg(
+    &mut self,
+    data: Data,
+    endianness: Endianness,
+    mr_state: MessageReceiverState,
+  ) {
     trace!("handle_data_msg entry");
     let duration = match mr_state.timestamp {
       Some(ts) => Timestamp::now().duration_since(ts),
@@ -414,7 +466,7 @@ impl Reader {
       }
     }
 
-    self.make_cache_change(data, instant, writer_guid, no_writers);
+    self.make_cache_change(data, endianness, instant, writer_guid, no_writers);
     // Add to own track-keeping datastructure
     self.seqnum_instant_map.insert(seq_num, instant);
 
@@ -586,6 +638,7 @@ impl Reader {
   fn make_cache_change(
     &mut self,
     data: Data,
+    endianness: Endianness,
     instant: Timestamp,
     writer_guid: GUID,
     no_writers: bool,
@@ -600,8 +653,19 @@ impl Reader {
       None => None,
     };
 
+    // KeyHash is an octet array, which data_msg writes as the little-endian
+    // bytes of the key hash, whatever the representation of the payload.
     let key_hash = match &data.inline_qos {
-      Some(iqos) => InlineQos::key_hash(iqos, representation_identifier).ok(),
+      Some(iqos) => InlineQos::key_hash(iqos, RepresentationIdentifier::CDR_LE).ok(),
+      None => None,
+    };
+
+    // Parameters are in the byte order of the submessage, unlike the payload.
+    let related_sample_identity = match &data.inline_qos {
+      Some(iqos) => InlineQos::related_sample_identity(iqos, endianness).unwrap_or_else(|e| {
+        warn!("Cannot read related sample identity: {:?}", e);
+        None
+      }),
       None => None,
     };
 
@@ -627,11 +691,18 @@ impl Reader {
       DDSData::new_disposed(status_info, key_hash)
     } else {
       match data.serialized_payload {
-        Some(pl) => DDSData::new(pl),
+        Some(pl) => {
+          let mut ddsdata = DDSData::new(pl);
+          if let Some(kh) = key_hash {
+            ddsdata.value_key_hash = kh.value();
+          }
+          ddsdata
+        }
         None => return,
       }
     };
 
+    ddsdata.related_sample_identity = related_sample_identity;
     ddsdata.set_reader_id(data.reader_id);
     ddsdata.set_writer_id(data.writer_id);
     let cache_change = CacheChange::new(change_kind, writer_guid, data.writer_sn, Some(ddsdata));
@@ -695,7 +766,13 @@ impl Reader {
     let bytes = message
       .write_to_vec_with_ctx(Endianness::LittleEndian)
       .unwrap();
-    sender.send_to_locator_list(&bytes, &mr_state.unicast_reply_locator_list);
+    // If the writer is reachable over TCP, reply there instead.
+    let sent_over_tcp = self.tcp_sender.as_ref()
+      .map(|tcp| tcp.send_to_participant(mr_state.source_guid_prefix, &bytes))
+      .unwrap_or(false);
+    if !sent_over_tcp {
+      sender.send_to_locator_list(&bytes, &mr_state.unicast_reply_locator_list);
+    }
   }
 
   pub fn send_preemptive_acknacks(&mut self) {
@@ -713,6 +790,8 @@ impl Reader {
       .matched_writers
       .iter()
       .filter(|(_, p)| p.no_changes() )
+      // local writers do not need acknacks
+      .filter(|(g, _)| g.guidPrefix != self.my_guid.guidPrefix )
     {
       let mut message = Message::new(Header {
         protocol_id: ProtocolId::default(),
@@ -745,7 +824,12 @@ impl Reader {
       let bytes = message
         .write_to_vec_with_ctx(Endianness::LittleEndian)
         .unwrap();
-      sender.send_to_locator_list(&bytes, &writer_proxy.unicast_locator_list);
+      let sent_over_tcp = self.tcp_sender.as_ref()
+        .map(|tcp| tcp.send_to_participant(writer_proxy.remote_writer_guid.guidPrefix, &bytes))
+        .unwrap_or(false);
+      if !sent_over_tcp {
+        sender.send_to_locator_list(&bytes, &writer_proxy.unicast_locator_list);
+      }
     }
   }
 
@@ -817,6 +901,10 @@ mod tests {
   use crate::structure::guid::GuidPrefix;
   use crate::structure::topic_kind::TopicKind;
   use crate::dds::typedesc::TypeDesc;
+  use crate::messages::submessages::submessage_elements::{
+    parameter::Parameter, parameter_list::ParameterList,
+  };
+  use crate::structure::parameter_id::ParameterId;
 
   #[test]
   fn rtpsreader_notification() {
@@ -862,7 +950,7 @@ mod tests {
     data.reader_id = EntityId::createCustomEntityID([1, 2, 3], 111);
     data.writer_id = writer_guid.entityId;
 
-    reader.handle_data_msg(data, mr_state);
+    reader.handle_data_msg(data, Endianness::LittleEndian, mr_state);
 
     assert!(rec.try_recv().is_ok());
   }
@@ -908,8 +996,14 @@ mod tests {
 
     let mut d = Data::default();
     d.writer_id = writer_guid.entityId;
+    let mut inline_qos = ParameterList::new();
+    inline_qos.parameters.push(Parameter {
+      parameter_id: ParameterId::PID_KEY_HASH,
+      value: 0x0102_0304u128.to_le_bytes().to_vec(),
+    });
+    d.inline_qos = Some(inline_qos);
     let d_seqnum = d.writer_sn;
-    new_reader.handle_data_msg(d.clone(), mr_state);
+    new_reader.handle_data_msg(d.clone(), Endianness::LittleEndian, mr_state);
 
     assert!(rec.try_recv().is_ok());
 
@@ -923,6 +1017,8 @@ mod tests {
     let cc_built_here = CacheChange::new(ChangeKind::ALIVE, writer_guid, d_seqnum, Some(ddsdata));
 
     assert_eq!(cc_from_chache.unwrap(), &cc_built_here);
+    // Readers that do not deserialize the data identify the instance by this.
+    assert_eq!(cc_from_chache.unwrap().key, 0x0102_0304);
   }
 
   #[test]
@@ -1105,7 +1201,7 @@ mod tests {
 
     for i in 0..n {
       d.writer_sn = SequenceNumber::from(i);
-      reader.handle_data_msg(d.clone(), mr_state.clone());
+      reader.handle_data_msg(d.clone(), Endianness::LittleEndian, mr_state.clone());
       changes.push(
         reader
           .get_history_cache_change(d.writer_sn)
@@ -1170,4 +1266,52 @@ mod tests {
       Some(changes[9].clone())
     );
   }
+
+  #[test]
+  fn rtpsreader_local_writer_match() {
+    use crate::dds::qos::QosPolicyBuilder;
+    use crate::structure::guid::EntityKind;
+
+    let reader_guid = GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED);
+    let (send, rec) = mio_channel::sync_channel::<()>(100);
+    let (status_sender, _status_receiver) = mio_channel::sync_channel::<DataReaderStatus>(100);
+    let (_reader_command_sender, reader_command_receiver) =
+      mio_channel::sync_channel::<ReaderCommand>(10);
+
+    let reliable = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::Reliable { max_blocking_time: Duration::DURATION_ZERO })
+      .build();
+    let best_effort = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::BestEffort)
+      .build();
+
+    let mut reader = Reader::new(
+      reader_guid,
+      send,
+      status_sender,
+      Arc::new(RwLock::new(DDSCache::new())),
+      "test".to_string(),
+      reliable.clone(),
+      reader_command_receiver,
+    );
+    let local_writers = reader.local_matched_writers();
+
+    let good_writer = GUID::new_with_prefix_and_id(reader_guid.guidPrefix,
+      EntityId::createCustomEntityID([1; 3], EntityKind::WRITER_NO_KEY_USER_DEFINED));
+    let bad_writer = GUID::new_with_prefix_and_id(reader_guid.guidPrefix,
+      EntityId::createCustomEntityID([2; 3], EntityKind::WRITER_NO_KEY_USER_DEFINED));
+
+    reader.update_local_writer(good_writer, reliable);
+    reader.update_local_writer(bad_writer, best_effort);
+    assert!(local_writers.read().unwrap().contains(&good_writer));
+    assert!(!local_writers.read().unwrap().contains(&bad_writer));
+
+    reader.notify_local_write(bad_writer);
+    assert!(rec.try_recv().is_err());
+    reader.notify_local_write(good_writer);
+    assert!(rec.try_recv().is_ok());
+
+    reader.remove_writer_proxy(good_writer);
+    assert!(local_writers.read().unwrap().is_empty());
+  }
 }
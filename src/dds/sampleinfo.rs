@@ -1,6 +1,7 @@
 use enumflags2::BitFlags;
 
 use crate::{structure::guid::GUID};
+use crate::structure::inline_qos::SampleIdentity;
 use crate::structure::time::Timestamp;
 
 //use std::num::Zero; // unstable
@@ -128,6 +129,11 @@ pub struct SampleInfo {
   // the publication_handle that identifies locally the DataWriter that modified
   // the instance (wrote the sample)
   pub publication_handle: GUID,
+
+  // DDS-RPC 7.8.2 extensions: the identity of this sample, and the identity
+  // that the DataWriter related it to, if any, e.g. the request of a reply.
+  pub sample_identity: SampleIdentity,
+  pub related_sample_identity: Option<SampleIdentity>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -143,6 +149,8 @@ impl SampleInfo {
       absolute_generation_rank: 0,
       source_timestamp: None,
       publication_handle: GUID::GUID_UNKNOWN,
+      sample_identity: SampleIdentity::UNKNOWN,
+      related_sample_identity: None,
     }
   }
 
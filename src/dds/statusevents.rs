@@ -8,6 +8,7 @@
 // in DDS Specification v1.4
 
 use crate::dds::qos::QosPolicyId;
+use crate::messages::submessages::submessage_elements::RepresentationIdentifier;
 use mio::{Evented};
 use mio_extras::channel as mio_channel;
 
@@ -69,7 +70,8 @@ pub enum TopicStatus {
 
 #[derive(Debug, Clone)]
 pub enum DataReaderStatus {
-	/// Sample was rejected, because resource limits would have been exeeded.
+	/// Sample was rejected, because resource limits would have been exeeded,
+	/// or because its data representation is not supported.
 	SampleRejected { 
 		count: CountWithChange,
 		last_reason: SampleRejectedStatusKind,
@@ -175,6 +177,9 @@ pub enum SampleRejectedStatusKind {
 	ByInstancesLimit,
 	BySamplesLimit,
 	BySamplesPerInstanceLimit,
+	/// The DataReader cannot deserialize this data representation.
+	/// Not in the DDS spec.
+	ByUnsupportedRepresentation(RepresentationIdentifier),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
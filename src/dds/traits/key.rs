@@ -2,11 +2,11 @@
 // See e.g. Figure 2.3 in "2.2.1.2.2 Overall Conceptual Model"
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use byteorder::{LittleEndian};
+use byteorder::BigEndian;
 use rand::Rng;
-use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde::{Serialize, Serializer, Deserialize, de::DeserializeOwned, ser::SerializeTupleStruct};
 
-use crate::serialization::cdr_serializer::to_bytes;
+use crate::serialization::{cdr2_serializer::to_bytes, Extensibility};
 
 /// A sample data type may be `Keyed` : It allows a Key to be extracted from the sample.
 /// In its simplest form, the key may be just a part of the sample data, but it can be anything
@@ -50,38 +50,83 @@ pub trait Keyed {
 /// and Serde traits
 /// * [Serialize](https://docs.serde.rs/serde/trait.Serialize.html) and
 /// * [DeserializeOwned](https://docs.serde.rs/serde/de/trait.DeserializeOwned.html) .
+///
+/// The serialized form of the key should be the key members of the data type
+/// in declaration order, as this is what the key hash is computed from.
+/// Structures can implement `Key` with `#[derive(Key)]`, which also implements
+/// [`KeyMember`](trait.KeyMember.html).
+///
+/// Tuples and arrays of keys are also keys, so a composite key can be a tuple
+/// of its members.
 
 pub trait Key:
   Eq + PartialEq + PartialOrd + Ord + Hash + Clone + Serialize + DeserializeOwned
 {
-  // no methods required
+  /// True if all values of the type serialize to the same number of bytes,
+  /// like primitive types and structures of them do. `#[derive(Key)]`
+  /// computes this from the member types.
+  ///
+  /// Unless the type has a fixed size of at most 16 bytes, its key hash is
+  /// always an MD5 digest. The default, false, always digests the key. That
+  /// keeps instances apart for any key type, but for keys with a fixed size
+  /// of at most 16 bytes other DDS implementations use the padded key
+  /// instead, so such keys should return true.
+  fn is_fixed_size() -> bool {
+    false
+  }
+
+  /// Key hash as specified in RTPS 9.6.3.8 and XTypes 7.6.8: the key in
+  /// big-endian XCDR2, padded with zeros to 16 bytes if the type has a fixed
+  /// size of at most 16 bytes, and otherwise digested with MD5. The bytes are
+  /// in little-endian order in the returned integer.
+  ///
+  /// Earlier versions hashed the key in little-endian CDR, and digested it
+  /// only if it was longer than 16 bytes. Hashes of keys of more than one
+  /// byte differ from those versions.
   fn into_hash_key(&self) -> u128 {
-    let cdr_bytes = match to_bytes::<Self, LittleEndian>(&self) {
-      Ok(b) => b,
-      _ => Vec::new(),
-    };
+    hash_key_of(self, Self::is_fixed_size())
+  }
+}
 
-    let digest = if cdr_bytes.len() > 16 {
-      md5::compute(&cdr_bytes).to_vec()
-    } else {
-      cdr_bytes
-    };
+fn hash_key_of<T: Serialize>(key: &T, is_fixed_size: bool) -> u128 {
+  let cdr_bytes = to_bytes::<T, BigEndian>(key, Extensibility::Final).unwrap_or_default();
 
-    let mut digarr: [u8; 16] = [0; 16];
-    for i in 0..digest.len() {
-      digarr[i] = digest[i];
-    }
+  let digest = if is_fixed_size && cdr_bytes.len() <= 16 {
+    cdr_bytes
+  } else {
+    md5::compute(&cdr_bytes).to_vec()
+  };
 
-    u128::from_le_bytes(digarr)
-  }
+  let mut digarr: [u8; 16] = [0; 16];
+  digarr[..digest.len()].copy_from_slice(&digest);
+
+  u128::from_le_bytes(digarr)
 }
 
 impl Key for () {
+  fn is_fixed_size() -> bool {
+    true
+  }
+
   fn into_hash_key(&self) -> u128 {
     0
   }
 }
 
+/// Type of a `#[key]` member of a structure that derives
+/// [`Keyed`](trait.Keyed.html)
+///
+/// A member contributes its `Key` to the key of the structure. For primitive
+/// types and strings this is the value itself. For structures that derive
+/// `Keyed` it is their key, so that the key members of nested structures are
+/// part of the key. Structures that derive [`Key`](trait.Key.html) contribute
+/// all their members.
+pub trait KeyMember {
+  type Key: Key;
+
+  fn key(&self) -> Self::Key;
+}
+
 /// Key for a reference type `&D` is the same as for the value type `D`.
 /// This is required internally for the implementation of NoKey topics.
 impl<D: Keyed> Keyed for &D {
@@ -91,23 +136,118 @@ impl<D: Keyed> Keyed for &D {
   }
 }
 
-// TODO: might want to implement this for each primitive?
-impl Key for bool {}
-impl Key for char {}
-impl Key for i8 {}
-impl Key for i16 {}
-impl Key for i32 {}
-impl Key for i64 {}
-impl Key for i128 {}
-impl Key for isize {}
-impl Key for u8 {}
-impl Key for u16 {}
-impl Key for u32 {}
-impl Key for u64 {}
-impl Key for u128 {}
-impl Key for usize {}
+macro_rules! primitive_key {
+  ($($t:ty),*) => {
+    $(
+      impl Key for $t {
+        fn is_fixed_size() -> bool {
+          true
+        }
+      }
+
+      impl KeyMember for $t {
+        type Key = $t;
+
+        fn key(&self) -> $t {
+          *self
+        }
+      }
+    )*
+  };
+}
 
-impl Key for String {}
+primitive_key!(bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl Key for String {
+  fn is_fixed_size() -> bool {
+    false
+  }
+}
+
+impl KeyMember for String {
+  type Key = String;
+
+  fn key(&self) -> String {
+    self.clone()
+  }
+}
+
+impl<T: Key> Key for Vec<T> {
+  fn is_fixed_size() -> bool {
+    false
+  }
+}
+
+impl<T: KeyMember> KeyMember for Vec<T> {
+  type Key = Vec<T::Key>;
+
+  fn key(&self) -> Vec<T::Key> {
+    self.iter().map(KeyMember::key).collect()
+  }
+}
+
+// Serde implements its traits for arrays of up to 32 elements.
+impl<T: Key, const N: usize> Key for [T; N]
+where
+  [T; N]: Serialize + DeserializeOwned,
+{
+  fn is_fixed_size() -> bool {
+    T::is_fixed_size()
+  }
+}
+
+impl<T: KeyMember, const N: usize> KeyMember for [T; N]
+where
+  [T::Key; N]: Key,
+{
+  type Key = [T::Key; N];
+
+  fn key(&self) -> [T::Key; N] {
+    std::array::from_fn(|i| self[i].key())
+  }
+}
+
+// The members of a tuple key, serialized like the members of a structure.
+// Serde serializes tuples like arrays, which XCDR2 may give a DHEADER.
+struct TupleKeyMembers<'a, T>(&'a T);
+
+macro_rules! tuple_key {
+  ($($t:ident),*) => {
+    impl<$($t: Key),*> Key for ($($t,)*) {
+      fn is_fixed_size() -> bool {
+        $($t::is_fixed_size())&&*
+      }
+
+      fn into_hash_key(&self) -> u128 {
+        hash_key_of(&TupleKeyMembers(self), Self::is_fixed_size())
+      }
+    }
+
+    impl<'a, $($t: Serialize),*> Serialize for TupleKeyMembers<'a, ($($t,)*)> {
+      #[allow(non_snake_case)]
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ($($t,)*) = self.0;
+        let mut members =
+          serializer.serialize_tuple_struct("TupleKeyMembers", [$(stringify!($t)),*].len())?;
+        $(members.serialize_field($t)?;)*
+        members.end()
+      }
+    }
+  };
+}
+
+tuple_key!(A);
+tuple_key!(A, B);
+tuple_key!(A, B, C);
+tuple_key!(A, B, C, D);
+tuple_key!(A, B, C, D, E);
+tuple_key!(A, B, C, D, E, F);
+tuple_key!(A, B, C, D, E, F, G);
+tuple_key!(A, B, C, D, E, F, G, H);
+tuple_key!(A, B, C, D, E, F, G, H, I);
+tuple_key!(A, B, C, D, E, F, G, H, I, J);
+tuple_key!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_key!(A, B, C, D, E, F, G, H, I, J, K, L);
 
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 /// Key type to identicy data instances in builtin topics
@@ -128,3 +268,121 @@ impl BuiltInTopicKey {
     BuiltInTopicKey { value: [0, 0, 0] }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use byteorder::LittleEndian;
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+  use crate::{
+    dds::traits::{Key, Keyed},
+    serialization::cdr_serializer::to_bytes as to_cdr_bytes,
+  };
+
+  #[derive(Serialize, Keyed)]
+  struct Point {
+    #[key]
+    id: u32,
+    x: f64,
+  }
+
+  #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Key)]
+  enum Color {
+    Red,
+    Green,
+  }
+
+  #[derive(Serialize, Keyed)]
+  struct Shape {
+    #[key]
+    name: String,
+    size: i32,
+    #[key]
+    color: Color,
+    #[key]
+    origin: Point,
+  }
+
+  // Key-only serialization of Shape
+  #[derive(Serialize)]
+  struct ShapeKeyHolder {
+    name: String,
+    color: Color,
+    origin_id: u32,
+  }
+
+  fn hash_of_bytes(bytes: &[u8]) -> u128 {
+    let mut padded = [0; 16];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    u128::from_le_bytes(padded)
+  }
+
+  #[test]
+  fn primitive_key_hash() {
+    assert_eq!(0x0102_i16.into_hash_key(), hash_of_bytes(&[1, 2]));
+    // XCDR2 aligns 8-byte values to 4.
+    assert_eq!(
+      (1_u8, 2_u64).into_hash_key(),
+      hash_of_bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2])
+    );
+    // Not of fixed size, so digested even though short
+    let digest = md5::compute([0, 0, 0, 2, b'a', 0]);
+    assert_eq!(
+      "a".to_string().into_hash_key(),
+      u128::from_le_bytes(digest.0)
+    );
+  }
+
+  #[test]
+  fn derived_keys() {
+    let shape = Shape {
+      name: "square".to_string(),
+      size: 3,
+      color: Color::Green,
+      origin: Point { id: 7, x: 1.5 },
+    };
+    assert_eq!(shape.origin.get_key(), 7);
+    assert_eq!(shape.get_key(), ("square".to_string(), Color::Green, 7));
+    assert!(Color::is_fixed_size());
+    assert!(!<Shape as Keyed>::K::is_fixed_size());
+
+    let key_holder = ShapeKeyHolder {
+      name: "square".to_string(),
+      color: Color::Green,
+      origin_id: 7,
+    };
+    assert_eq!(
+      to_cdr_bytes::<_, LittleEndian>(&shape.get_key()).unwrap(),
+      to_cdr_bytes::<_, LittleEndian>(&key_holder).unwrap()
+    );
+    let key_bytes = to_bytes::<_, BigEndian>(&key_holder, Extensibility::Final).unwrap();
+    assert_eq!(
+      shape.get_key().into_hash_key(),
+      u128::from_le_bytes(md5::compute(&key_bytes).0)
+    );
+  }
+
+  #[test]
+  fn hand_written_key_hash() {
+    use crate::structure::guid::{EntityId, GuidPrefix, GUID};
+    // GUID implements Key by hand. RTPS uses the GUID itself as the key hash of
+    // the builtin topics, so this must never change.
+    let guid = GUID {
+      guidPrefix: GuidPrefix::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]),
+      entityId: EntityId::ENTITYID_SPDP_BUILTIN_PARTICIPANT_WRITER,
+    };
+    assert_eq!(guid.into_hash_key(), 0xc200_0100_0c0b_0a09_0807_0605_0403_0201);
+  }
+
+  #[test]
+  fn default_key_is_digested() {
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+    struct Id(u32);
+    impl Key for Id {}
+
+    assert!(!Id::is_fixed_size());
+    let digest = md5::compute([0, 0, 0, 5]);
+    assert_eq!(Id(5).into_hash_key(), u128::from_le_bytes(digest.0));
+  }
+}
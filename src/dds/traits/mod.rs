@@ -7,6 +7,7 @@ pub mod serde_adapters;
 pub use dds_entity::DDSEntity;
 pub use crate::structure::entity::RTPSEntity;
 
-pub use key::{Key, Keyed};
+pub use key::{Key, KeyMember, Keyed};
+pub use rustdds_derive::{Key, Keyed};
 
 pub use super::topic::TopicDescription;
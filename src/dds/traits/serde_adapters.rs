@@ -1,11 +1,13 @@
 use std::io;
 
-use serde::de::DeserializeOwned;
+use serde::de::{Deserialize, DeserializeOwned};
 use serde::ser::Serialize;
 
 use crate::serialization::error::Result;
 
-use crate::messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier;
+use crate::messages::submessages::submessage_elements::serialized_payload::{
+  RepresentationIdentifier, SerializedPayload,
+};
 
 /// DeserializerAdapter is used to fit serde Deserializer implementations and DataReader together.
 /// DataReader cannot assume a specific serialization format, so it needs to be given as a parameter.
@@ -15,6 +17,32 @@ where
 {
   fn supported_encodings() -> &'static [RepresentationIdentifier]; // Which data encodings can this deserializer read?
   fn from_bytes<'de>(input_bytes: &'de [u8], encoding: RepresentationIdentifier) -> Result<D>;
+
+  // DataReader deserializes received samples with this. Only the adapter of
+  // RawDataReader needs more than the bytes.
+  #[doc(hidden)]
+  fn from_payload(payload: &SerializedPayload, _key_hash: u128) -> Result<D> {
+    Self::from_bytes(&payload.value, payload.representation_identifier)
+  }
+
+  // DataReader rejects samples in other representations. Only the adapter of
+  // RawDataReader accepts all of them.
+  #[doc(hidden)]
+  fn accepts_encoding(encoding: RepresentationIdentifier) -> bool {
+    Self::supported_encodings().contains(&encoding)
+  }
+}
+
+/// BorrowingDeserializerAdapter deserializes values that borrow from the input bytes, e.g.
+/// `&'de str` and `&'de [u8]` fields marked with `#[serde(borrow)]`. It is used to view
+/// [`LoanedSample`](../../data_types/struct.LoanedSample.html)s without copying the data.
+pub trait BorrowingDeserializerAdapter {
+  fn from_bytes_borrowed<'de, T>(
+    input_bytes: &'de [u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<T>
+  where
+    T: Deserialize<'de>;
 }
 
 pub trait SerializerAdapter<D>
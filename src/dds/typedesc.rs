@@ -1,14 +1,31 @@
+use crate::dds::xtypes::{TypeInformation, TypeSupport};
+
 /// Description of the type of a [Topic](../struct.Topic.html)
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct TypeDesc {
   my_name: String, // this is a rather minimal implementation
+  type_information: Option<TypeInformation>,
 } // placeholders
 
 impl TypeDesc {
   pub fn new(my_name: &str) -> TypeDesc {
-    TypeDesc { my_name: my_name.to_string() }
+    TypeDesc { my_name: my_name.to_string(), type_information: None }
+  }
+
+  /// Type named `my_name`, with XTypes type information of `T`.
+  /// This is advertised in discovery and checked when matching remote endpoints.
+  pub fn of<T: TypeSupport + ?Sized>(my_name: &str) -> TypeDesc {
+    TypeDesc {
+      my_name: my_name.to_string(),
+      type_information: Some(TypeInformation::of::<T>()),
+    }
   }
+
   pub fn name(&self) -> &str {
     &self.my_name
   }
+
+  pub fn type_information(&self) -> Option<&TypeInformation> {
+    self.type_information.as_ref()
+  }
 }
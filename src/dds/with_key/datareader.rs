@@ -1,5 +1,6 @@
 use std::{io};
 use std::sync::{Arc, RwLock};
+use std::collections::BTreeSet;
 use std::marker::PhantomData;
 
 use itertools::Itertools;
@@ -18,6 +19,7 @@ use crate::{
     time::Timestamp,
     dds_cache::DDSCache,
     cache_change::{CacheChange, ChangeKind},
+    inline_qos::SampleIdentity,
   },
 };
 use crate::log_and_err_precondition_not_met;
@@ -28,6 +30,7 @@ use crate::dds::{
   qos::*,
   with_key::datasample::*,
   datasample_cache::DataSampleCache,
+  loaned_sample::LoanedSample,
   pubsub::Subscriber,
   topic::Topic,
   readcondition::*,
@@ -120,7 +123,13 @@ pub struct DataReader< D: Keyed + DeserializeOwned,  DA: DeserializerAdapter<D>
 
   discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
   status_receiver: StatusReceiver<DataReaderStatus>,
+  // Samples are rejected here, not in the RTPS Reader, if they cannot be
+  // deserialized.
+  status_sender: mio_channel::SyncSender<DataReaderStatus>,
+  sample_rejected_count: i32,
   reader_command: mio_channel::SyncSender<ReaderCommand>,
+  // Writers of our own participant, which the Reader has matched.
+  local_matched_writers: Arc<RwLock<BTreeSet<GUID>>>,
 }
 
 impl<D, DA> Drop for DataReader<D, DA>
@@ -159,7 +168,9 @@ where
     dds_cache: Arc<RwLock<DDSCache>>,
     discovery_command: mio_channel::SyncSender<DiscoveryCommand>,
     status_channel_rec: mio_channel::Receiver<DataReaderStatus>,
+    status_sender: mio_channel::SyncSender<DataReaderStatus>,
     reader_command: mio_channel::SyncSender<ReaderCommand>,
+    local_matched_writers: Arc<RwLock<BTreeSet<GUID>>>,
   ) -> Result<Self> {
     let dp = match subscriber.get_participant() {
       Some(dp) => dp,
@@ -184,8 +195,11 @@ where
       deserializer_type: PhantomData,
       discovery_command,
       status_receiver: StatusReceiver::new(status_channel_rec) ,
+      status_sender,
+      sample_rejected_count: 0,
       //current_status: CurrentStatusChanges::new(),
       reader_command,
+      local_matched_writers,
     })
   }
 
@@ -606,6 +620,104 @@ where
     )
   }
 
+  /// Takes up to `max_samples` received samples as loans, without deserializing them.
+  ///
+  /// A [`LoanedSample`](../data_types/struct.LoanedSample.html) shares the receive
+  /// buffer instead of copying the data, and deserializes on request, optionally to a
+  /// type that borrows from the buffer. This avoids copying large samples.
+  ///
+  /// Loaned samples bypass the sample cache of the DataReader: they are not
+  /// available to `read` or `take` afterwards, and samples already in the cache
+  /// are not loaned. Instance states are therefore not tracked for loans.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::dds::DomainParticipant;
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// # use rustdds::dds::data_types::TopicKind;
+  /// # use rustdds::dds::traits::Keyed;
+  /// # use rustdds::dds::With_Key_DataReader as DataReader;
+  /// # use rustdds::serialization::CDRDeserializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let subscriber = domain_participant.create_subscriber(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize)]
+  /// struct Blob { id: i32, data: Vec<u8> }
+  /// # impl Keyed for Blob {
+  /// #   type K = i32;
+  /// #
+  /// #   fn get_key(&self) -> Self::K {
+  /// #     self.id
+  /// #   }
+  /// # }
+  ///
+  /// // A view that borrows the data from the received message
+  /// #[derive(Deserialize)]
+  /// struct BlobView<'a> { id: i32, #[serde(borrow)] data: &'a [u8] }
+  ///
+  /// // WithKey is important
+  /// let topic = domain_participant.create_topic("some_topic", "Blob", &qos, TopicKind::WithKey).unwrap();
+  /// let mut data_reader = subscriber.create_datareader::<Blob, CDRDeserializerAdapter<_>>(topic, None, None).unwrap();
+  ///
+  /// // Wait for data to arrive...
+  ///
+  /// for loan in data_reader.take_loaned(10).unwrap() {
+  ///   if let Ok(blob) = loan.view::<BlobView>() {
+  ///     // do something with blob.data
+  ///   }
+  /// }
+  /// ```
+  pub fn take_loaned(&mut self, max_samples: usize) -> Result<Vec<LoanedSample<D, DA>>> {
+    let dds_cache = self.dds_cache.read()?;
+    let mut cache_changes = self.get_unseen_cache_changes(&dds_cache);
+    cache_changes.truncate(max_samples);
+
+    if let Some((last_instant, _)) = cache_changes.last() {
+      self.latest_instant = **last_instant;
+    }
+    let result = cache_changes
+      .into_iter()
+      .map(|(_, cc)| LoanedSample::from_cache_change(cc))
+      .collect();
+
+    // clearing receiver buffer
+    while let Ok(_) = self.notification_receiver.try_recv() {}
+
+    Ok(result)
+  }
+
+  // Gets the cache_changes from the TopicCache that have not been seen yet,
+  // in receive order.
+  fn get_unseen_cache_changes<'a>(
+    &self,
+    dds_cache: &'a DDSCache,
+  ) -> Vec<(&'a Timestamp, &'a CacheChange)> {
+    let cache_changes = dds_cache.from_topic_get_changes_in_range(
+      &self.my_topic.get_name().to_string(),
+      &self.latest_instant,
+      &Timestamp::now(),
+    );
+
+    // Changes from writers of our own participant are in the same topic cache.
+    // Those are delivered only if the Reader has matched the writer.
+    let local_matched_writers = match self.local_matched_writers.read() {
+      Ok(lw) => lw,
+      Err(e) => panic!("Local matched writers set is poisoned. Error: {}", e),
+    };
+    let my_prefix = self.get_guid_prefix();
+
+    cache_changes
+      .into_iter()
+      .sorted_by(|(a, _), (b, _)| Ord::cmp(a, b))
+      .filter(|(_, cc)| cc.writer_guid.guidPrefix != my_prefix
+                        || local_matched_writers.contains(&cc.writer_guid))
+      .collect()
+  }
+
   // Gets all unseen cache_changes from the TopicCache. Deserializes
   // the serialized payload and stores the DataSamples (the actual data and the
   // samplestate) to local container, datasample_cache.
@@ -619,17 +731,7 @@ where
       ),
     };
 
-    let cache_changes = dds_cache.from_topic_get_changes_in_range(
-      &self.my_topic.get_name().to_string(),
-      &self.latest_instant,
-      &Timestamp::now(),
-    );
-
-    let cache_changes: Vec<(&Timestamp, &CacheChange)> = cache_changes
-      .into_iter()
-      .sorted_by(|(a, _), (b, _)| Ord::cmp(a, b))
-      .filter(|(_, cc)| cc.writer_guid.guidPrefix != self.get_guid_prefix())
-      .collect();
+    let cache_changes = self.get_unseen_cache_changes(&dds_cache);
 
     match cache_changes.last() {
       Some((last_instant, _)) => self.latest_instant = **last_instant,
@@ -642,12 +744,17 @@ where
       CacheChange {
         kind,
         writer_guid,
-        sequence_number: _,
+        sequence_number,
         data_value: payload_opt,
         key: key_hash,
+        related_sample_identity,
       },
     ) in cache_changes
     {
+      let sample_identity = SampleIdentity {
+        writer_guid: *writer_guid,
+        sequence_number: *sequence_number,
+      };
       match kind {
         ChangeKind::NOT_ALIVE_UNREGISTERED => (), // presumably causes no local cache update?
 
@@ -656,7 +763,13 @@ where
           match self.datasample_cache.get_key_by_hash(*key_hash) {
             Some(key) => self
               .datasample_cache
-              .add_sample(Err(key), *writer_guid, *instant, None),
+              .add_sample(
+                Err(key),
+                sample_identity,
+                *related_sample_identity,
+                *instant,
+                None,
+              ),
             /* TODO: How to get source timestamps other then None ?? */
             None => warn!("Tried to dispose with unkonwn key hash: {:x?}", key_hash),
           }
@@ -666,15 +779,16 @@ where
             None => error!("Got CacheChange kind=ALIVE , but no serialized payload!"),
             Some(serialized_payload) => {
               // what is our data serialization format (representation identifier) ?
-              if let Some(recognized_rep_id) = 
-                  DA::supported_encodings().iter()
-                    .find(|r| **r == serialized_payload.representation_identifier)
-              {
-                match DA::from_bytes(&serialized_payload.value, *recognized_rep_id) {
+              if DA::accepts_encoding(serialized_payload.representation_identifier) {
+                match DA::from_payload(serialized_payload, *key_hash) {
                   Ok(payload) => {
-                    self
-                    .datasample_cache
-                    .add_sample(Ok(payload), *writer_guid, *instant, None)
+                    self.datasample_cache.add_sample(
+                      Ok(payload),
+                      sample_identity,
+                      *related_sample_identity,
+                      *instant,
+                      None,
+                    )
                   }
                   Err(e) => {
                     error!("Failed to deserialize bytes: {}, Topic = {}, Type = {:?}", 
@@ -684,9 +798,18 @@ where
                   }
                 }
               } else {
-                  warn!("Unknown representation id {:?}.", serialized_payload.representation_identifier);
-                  debug!("Serialized payload was {:?}", &serialized_payload);
-                  continue // skip this sample, as we cannot decode it                
+                debug!("Rejected sample with unsupported representation id {:?}. Serialized payload was {:?}",
+                        serialized_payload.representation_identifier, &serialized_payload);
+                self.sample_rejected_count += 1;
+                let status = DataReaderStatus::SampleRejected {
+                  count: CountWithChange::start_from(self.sample_rejected_count, 1),
+                  last_reason: SampleRejectedStatusKind::ByUnsupportedRepresentation(
+                    serialized_payload.representation_identifier),
+                };
+                if let Err(e) = self.status_sender.try_send(status) {
+                  debug!("Cannot send SampleRejected status: {:?}", e);
+                }
+                continue // skip this sample, as we cannot decode it
               }
             }
           } // match payload_opt
@@ -1141,6 +1264,7 @@ mod tests {
   use crate::structure::sequence_number::SequenceNumber;
   use crate::serialization::{cdr_deserializer::CDRDeserializerAdapter, cdr_serializer::to_bytes};
   use byteorder::LittleEndian;
+  use speedy::Endianness;
   use crate::messages::submessages::submessage_elements::serialized_payload::SerializedPayload;
   use std::{
     thread,
@@ -1214,7 +1338,7 @@ mod tests {
       representation_options: [0, 0],
       value: to_bytes::<RandomData, LittleEndian>(&random_data).unwrap(),
     });
-    new_reader.handle_data_msg(data, mr_state.clone());
+    new_reader.handle_data_msg(data, Endianness::LittleEndian, mr_state.clone());
 
     matching_datareader.fill_local_datasample_cache();
     let deserialized_random_data = matching_datareader.read(1, ReadCondition::any()).unwrap()[0]
@@ -1255,8 +1379,8 @@ mod tests {
       value: to_bytes::<RandomData, LittleEndian>(&random_data3).unwrap(),
     });
 
-    new_reader.handle_data_msg(data2, mr_state.clone());
-    new_reader.handle_data_msg(data3, mr_state);
+    new_reader.handle_data_msg(data2, Endianness::LittleEndian, mr_state.clone());
+    new_reader.handle_data_msg(data3, Endianness::LittleEndian, mr_state);
 
     matching_datareader.fill_local_datasample_cache();
     let random_data_vec = matching_datareader
@@ -1347,8 +1471,8 @@ mod tests {
       representation_options: [0, 0],
       value: to_bytes::<RandomData, LittleEndian>(&test_data2).unwrap(),
     });
-    reader.handle_data_msg(data_msg, mr_state.clone());
-    reader.handle_data_msg(data_msg2, mr_state.clone());
+    reader.handle_data_msg(data_msg, Endianness::LittleEndian, mr_state.clone());
+    reader.handle_data_msg(data_msg2, Endianness::LittleEndian, mr_state.clone());
 
     // Read the same sample two times.
     {
@@ -1447,10 +1571,10 @@ mod tests {
       representation_options: [0, 0],
       value: to_bytes::<RandomData, LittleEndian>(&data_key2_3).unwrap(),
     });
-    reader.handle_data_msg(data_msg, mr_state.clone());
-    reader.handle_data_msg(data_msg2, mr_state.clone());
-    reader.handle_data_msg(data_msg3, mr_state.clone());
-    reader.handle_data_msg(data_msg4, mr_state.clone());
+    reader.handle_data_msg(data_msg, Endianness::LittleEndian, mr_state.clone());
+    reader.handle_data_msg(data_msg2, Endianness::LittleEndian, mr_state.clone());
+    reader.handle_data_msg(data_msg3, Endianness::LittleEndian, mr_state.clone());
+    reader.handle_data_msg(data_msg4, Endianness::LittleEndian, mr_state.clone());
 
     info!("calling read with key 1 and this");
     let results =
@@ -1590,13 +1714,13 @@ mod tests {
     });
 
     let handle = std::thread::spawn(move || {
-      reader.handle_data_msg(data_msg, mr_state.clone());
+      reader.handle_data_msg(data_msg, Endianness::LittleEndian, mr_state.clone());
       thread::sleep(time::Duration::from_millis(100));
       info!("I'll send the second now..");
-      reader.handle_data_msg(data_msg2, mr_state.clone());
+      reader.handle_data_msg(data_msg2, Endianness::LittleEndian, mr_state.clone());
       thread::sleep(time::Duration::from_millis(100));
       info!("I'll send the third now..");
-      reader.handle_data_msg(data_msg3, mr_state.clone());
+      reader.handle_data_msg(data_msg3, Endianness::LittleEndian, mr_state.clone());
     });
 
     let poll = Poll::new().unwrap();
@@ -1,5 +1,6 @@
 use crate::{dds::traits::key::*, structure::guid::GUID};
 use crate::structure::time::Timestamp;
+use crate::structure::inline_qos::SampleIdentity;
 use crate::dds::sampleinfo::*;
 
 //use super::{interfaces::{IDataSample, IDataSampleConvert, IKeyedDataSample, IKeyedDataSampleConvert}, no_key::wrappers::NoKeyWrapper};
@@ -55,6 +56,11 @@ where
         absolute_generation_rank,
         source_timestamp: Some(source_timestamp),
         publication_handle: writer_guid,
+        sample_identity: SampleIdentity {
+          writer_guid,
+          ..SampleIdentity::UNKNOWN
+        },
+        related_sample_identity: None,
       },
       value: Ok(payload),
     }
@@ -88,6 +94,11 @@ where
         absolute_generation_rank,
         source_timestamp: Some(source_timestamp),
         publication_handle: writer_guid,
+        sample_identity: SampleIdentity {
+          writer_guid,
+          ..SampleIdentity::UNKNOWN
+        },
+        related_sample_identity: None,
       },
       value: Err(key),
     }
@@ -18,6 +18,7 @@ use crate::structure::entity::{RTPSEntity};
 use crate::structure::{
   dds_cache::DDSCache,
   guid::{GUID, EntityId},
+  inline_qos::SampleIdentity,
   topic_kind::TopicKind,
 };
 
@@ -37,7 +38,8 @@ use crate::dds::qos::{
 use crate::dds::traits::serde_adapters::SerializerAdapter;
 use crate::dds::with_key::datasample::DataSample;
 use crate::{discovery::data_types::topic_data::SubscriptionBuiltinTopicData, dds::ddsdata::DDSData};
-use super::super::{datasample_cache::DataSampleCache, writer::WriterCommand, };
+use crate::messages::submessages::submessage_elements::serialized_payload::SerializedPayload;
+use super::super::{datasample_cache::DataSampleCache, writer::{WriterCommand, BatchingConfig}, };
 
 /// DDS DataWriter for keyed topics
 ///
@@ -266,19 +268,26 @@ where
   /// data_writer.write(some_data, None).unwrap();
   /// ```
   pub fn write(&self, data: D, source_timestamp: Option<Timestamp>) -> Result<()> {
-    let mut ddsdata = DDSData::from(&data, source_timestamp);
+    let mut buffer = Vec::with_capacity(64);
+    SA::to_writer(&mut buffer, &data)?;
+    let payload = SerializedPayload::new(SA::output_encoding(), buffer);
     // TODO key value should be unique always. This is not always unique.
     // If sample with same values is given then hash is same for both samples.
     // TODO FIX THIS
-    ddsdata.value_key_hash = data.get_key().into_hash_key();
-
-    let _data_sample = match source_timestamp {
-      // TODO: fix this to use something else than new_deprecated.
-      // We cannot generate DataSample at the writer side, because most of the
-      // SampleInfo fields cannot be known.
-      Some(t) => DataSample::new_deprecated(t, data, self.get_guid()),
-      None => DataSample::new_deprecated(Timestamp::now(), data, self.get_guid()),
-    };
+    self.write_serialized(payload, data.get_key().into_hash_key(), None, source_timestamp)
+  }
+
+  // Writes a sample that has been serialized already, e.g. by a DynamicDataWriter.
+  pub(crate) fn write_serialized(
+    &self,
+    payload: SerializedPayload,
+    key_hash: u128,
+    related_sample_identity: Option<SampleIdentity>,
+    source_timestamp: Option<Timestamp>,
+  ) -> Result<()> {
+    let mut ddsdata = DDSData::from_serialized(payload, source_timestamp);
+    ddsdata.value_key_hash = key_hash;
+    ddsdata.related_sample_identity = related_sample_identity;
 
     match self
       .cc_upload
@@ -295,6 +304,91 @@ where
     }
   }
 
+  /// Turns writer-side batching on or off. With batching, samples written with
+  /// [`write`](#method.write) are collected and sent together in one RTPS message, which
+  /// saves headers and system calls for high-rate small samples.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # use serde::{Serialize, Deserialize};
+  /// # use rustdds::dds::{DomainParticipant, BatchingConfig};
+  /// # use rustdds::dds::qos::QosPolicyBuilder;
+  /// # use rustdds::dds::data_types::TopicKind;
+  /// # use rustdds::dds::traits::Keyed;
+  /// # use rustdds::serialization::CDRSerializerAdapter;
+  /// #
+  /// let domain_participant = DomainParticipant::new(0).unwrap();
+  /// let qos = QosPolicyBuilder::new().build();
+  /// let publisher = domain_participant.create_publisher(&qos).unwrap();
+  ///
+  /// #[derive(Serialize, Deserialize)]
+  /// struct SomeType { a: i32 }
+  /// impl Keyed for SomeType {
+  ///   type K = i32;
+  ///
+  ///   fn get_key(&self) -> Self::K {
+  ///     self.a
+  ///   }
+  /// }
+  ///
+  /// let topic = domain_participant.create_topic("some_topic", "SomeType", &qos, TopicKind::WithKey).unwrap();
+  /// let data_writer = publisher.create_datawriter::<SomeType, CDRSerializerAdapter<_>>(None, topic, None).unwrap();
+  ///
+  /// let batching = BatchingConfig::new(4096, std::time::Duration::from_millis(5));
+  /// data_writer.set_batching(Some(batching)).unwrap();
+  /// for a in 0..10 {
+  ///   data_writer.write(SomeType { a }, None).unwrap();
+  /// }
+  /// data_writer.flush().unwrap();
+  /// ```
+  pub fn set_batching(&self, config: Option<BatchingConfig>) -> Result<()> {
+    self.cc_upload.try_send(WriterCommand::SetBatching { config })
+      .or_else(|e| {
+        warn!("Failed to set batching. {:?}", e);
+        Err(Error::OutOfResources)
+      })
+  }
+
+  /// Sends out immediately all samples waiting in the current batch.
+  /// Does nothing, if batching is not enabled.
+  pub fn flush(&self) -> Result<()> {
+    self.cc_upload.try_send(WriterCommand::Flush)
+      .or_else(|e| {
+        warn!("Failed to flush. {:?}", e);
+        Err(Error::OutOfResources)
+      })
+  }
+
+  /// Attaches this DataWriter to a named flow controller, or detaches it with `None`.
+  /// The flow controller must have been defined in the
+  /// `TransportConfig` of the DomainParticipant.
+  /// UDP transmissions, including repairs to reliable readers, then wait for the
+  /// controller instead of going out immediately.
+  pub fn set_flow_controller(&self, name: Option<&str>) -> Result<()> {
+    let controller = match name {
+      None => None,
+      Some(name) => {
+        let dp = match self.my_publisher.get_participant() {
+          Some(dp) => dp,
+          None => return 
+            log_and_err_precondition_not_met!("DomainParticipant doesn't exist anymore."),
+        };
+        match dp.flow_controller(name) {
+          Some(fc) => Some(fc),
+          None => return Err(Error::BadParameter { 
+            reason: format!("No flow controller named {:?}", name) 
+          }),
+        }
+      }
+    };
+    self.cc_upload.try_send(WriterCommand::SetFlowController { controller })
+      .or_else(|e| {
+        warn!("Failed to set flow controller. {:?}", e);
+        Err(Error::OutOfResources)
+      })
+  }
+
   /// Waits for all acknowledgements to finish
   ///
   /// # Examples
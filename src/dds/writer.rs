@@ -4,7 +4,7 @@ use chrono::Duration as chronoDuration;
 #[allow(unused_imports)]
 use log::{debug, error, warn, info, trace};
 
-use speedy::{Writable, Endianness};
+use speedy::Endianness;
 use mio_extras::channel::{self as mio_channel, SyncSender};
 use mio::Token;
 use std::{
@@ -12,6 +12,7 @@ use std::{
   collections::{HashSet, HashMap, BTreeMap, BTreeSet, hash_map::DefaultHasher},
   iter::FromIterator,
   cmp::max,
+  cell::RefCell,
 };
 use std::hash::Hasher;
 
@@ -35,7 +36,8 @@ use crate::{
 
 use crate::dds::{ddsdata::DDSData, qos::HasQoSPolicy};
 use crate::{
-  network::{constant::TimerMessageType, udp_sender::UDPSender},
+  network::{constant::TimerMessageType, udp_sender::UDPSender, tcp_transport::TCPSender},
+  network::flow_controller::{FlowController, FlowControlQueue},
   structure::{
     entity::RTPSEntity,
     endpoint::{EndpointAttributes, Endpoint},
@@ -51,6 +53,74 @@ use super::{
 };
 use policy::{History, Reliability};
 
+/// Writer-side batching settings.
+///
+/// When batching is enabled, DATA submessages of consecutive samples are packed
+/// into the same RTPS message, until either `max_bytes` of payload is collected,
+/// `max_delay` has passed since the first sample of the batch, or the application
+/// calls `flush()` on the DataWriter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchingConfig {
+  pub max_bytes: usize,
+  pub max_delay: std::time::Duration,
+}
+
+impl BatchingConfig {
+  // Leave room for RTPS and IP/UDP headers within a single datagram.
+  const MAX_BATCH_BYTES: usize = 60 * 1024;
+
+  pub fn new(max_bytes: usize, max_delay: std::time::Duration) -> BatchingConfig {
+    BatchingConfig { max_bytes: max_bytes.min(BatchingConfig::MAX_BATCH_BYTES), max_delay }
+  }
+}
+
+impl Default for BatchingConfig {
+  fn default() -> BatchingConfig {
+    BatchingConfig::new(8 * 1024, std::time::Duration::from_millis(1))
+  }
+}
+
+// Upper bound of what a DATA submessage adds to its payload: submessage header,
+// fixed DATA fields, inline QoS (key hash and status info), encapsulation header
+// and padding.
+const DATA_SUBMESSAGE_OVERHEAD: usize = 64;
+
+// Samples in DDSCache, which are waiting to be sent in the next batch.
+#[derive(Default)]
+struct Batch {
+  samples: Vec<Timestamp>,
+  bytes: usize,
+}
+
+impl Batch {
+  fn sample_bytes(payload_len: usize) -> usize {
+    payload_len + DATA_SUBMESSAGE_OVERHEAD
+  }
+
+  // A sample that does not fit even into an empty batch is sent alone.
+  fn fits(&self, payload_len: usize, max_bytes: usize) -> bool {
+    self.samples.is_empty() || self.bytes + Batch::sample_bytes(payload_len) <= max_bytes
+  }
+
+  fn push(&mut self, timestamp: Timestamp, payload_len: usize) {
+    self.samples.push(timestamp);
+    self.bytes += Batch::sample_bytes(payload_len);
+  }
+
+  fn is_full(&self, max_bytes: usize) -> bool {
+    self.bytes >= max_bytes
+  }
+
+  fn is_empty(&self) -> bool {
+    self.samples.is_empty()
+  }
+
+  fn take(&mut self) -> Vec<Timestamp> {
+    self.bytes = 0;
+    std::mem::take(&mut self.samples)
+  }
+}
+
 #[derive(PartialEq,Eq,Clone,Copy)]
 pub enum DeliveryMode {
   Unicast,
@@ -113,6 +183,8 @@ pub(crate) struct Writer {
   requested_incompatible_qos_count: i32, // how many times a Reader requested incompatible QoS
   message: Option<Message>,
   udp_sender: UDPSender,
+  // Set by dp_event_loop, if TCP transport is in use.
+  tcp_sender: Option<TCPSender>,
   // This writer can read/write to only one of this DDSCache topic caches identified with my_topic_name
   dds_cache: Arc<RwLock<DDSCache>>,
   /// Writer can only read/write to this topic DDSHistoryCache.
@@ -146,10 +218,23 @@ pub(crate) struct Writer {
   // Used for sending status info about messages sent
   status_sender: SyncSender<DataWriterStatus>,
   //offered_deadline_status: OfferedDeadlineMissedStatus,
+
+  // Batching is off, if this is None.
+  batching: Option<BatchingConfig>,
+  batch: Batch,
+  batch_timer_set: bool,
+
+  // If a flow controller is attached, UDP datagrams are queued here until the
+  // controller allows sending them. RefCell, because sending happens through &self.
+  flow_control: Option<RefCell<FlowControlQueue>>,
 }
 
 pub(crate) enum WriterCommand {
   DDSData { data: DDSData },
+  // Send out any samples waiting in the current batch.
+  Flush,
+  SetBatching { config: Option<BatchingConfig> },
+  SetFlowController { controller: Option<FlowController> },
   //ResetOfferedDeadlineMissedStatus { writer_guid: GUID },
 }
 
@@ -207,6 +292,7 @@ impl Writer {
       message: None,
       endpoint_attributes: EndpointAttributes::default(),
       udp_sender: UDPSender::new_with_random_port(),
+      tcp_sender: None,
       dds_cache,
       my_topic_name: topic_name,
       sequence_number_to_instant: BTreeMap::new(),
@@ -216,6 +302,10 @@ impl Writer {
       qos_policies,
       status_sender,
       //offered_deadline_status: OfferedDeadlineMissedStatus::new(),
+      batching: None,
+      batch: Batch::default(),
+      batch_timer_set: false,
+      flow_control: None,
     }
   }
 
@@ -261,6 +351,11 @@ impl Writer {
   }
 
 
+  // Called by dp_wrapper
+  pub fn set_tcp_sender(&mut self, tcp_sender: TCPSender) {
+    self.tcp_sender = Some(tcp_sender);
+  }
+
   // --------------------------------------------------------------
   // --------------------------------------------------------------
   // --------------------------------------------------------------
@@ -273,6 +368,16 @@ impl Writer {
         self.handle_cache_cleaning(),
       TimerMessageType::WriterSendRepairData{ to_reader: r } =>
         self.handle_repair_data_send(r),
+      TimerMessageType::WriterFlushBatch => {
+        self.batch_timer_set = false;
+        self.send_batch()
+      }
+      TimerMessageType::WriterFlowControl => {
+        if let Some(fc) = &self.flow_control {
+          fc.borrow_mut().timer_set = false;
+        }
+        self.send_flow_controlled()
+      }
       other_msg => 
         error!("handle_timed_event - unexpected message: {:?}", other_msg),
     }
@@ -325,6 +430,17 @@ impl Writer {
 
           self.increase_heartbeat_counter();
 
+          // Readers in our own participant get the sample from DDSCache. If there
+          // is nobody remote to send to, there is no need to build a message.
+          if self.readers.is_empty() {
+            continue
+          }
+
+          if let Some(batching) = self.batching {
+            self.add_to_batch(timestamp, batching);
+            continue
+          }
+
           let partial_message = MessageBuilder::new()
             .ts_msg(self.endianness, Some(Timestamp::now()) );
           let data_hb_message_builder = 
@@ -332,11 +448,10 @@ impl Writer {
               if let Some(cache_change) = 
                   self.dds_cache.read().unwrap()
                     .from_topic_get_change(&self.my_topic_name, &timestamp) { 
-                partial_message.data_msg( cache_change.clone(), // TODO: We should not clone, too much copying
+                partial_message.data_msg( cache_change,
                                           EntityId::ENTITYID_UNKNOWN, // reader
                                           self.my_guid.entityId, // writer
                                           self.endianness ) 
-                // TODO: Here we are cloning the entire payload. We need to rewrite the transmit path to avoid copying.
               } else { partial_message }
             } else { partial_message };
           let final_flag = false;
@@ -346,6 +461,22 @@ impl Writer {
                .add_header_and_build(self.my_guid.guidPrefix);
           self.send_message_to_readers(DeliveryMode::Multicast, 
             &data_hb_message, &mut self.readers.values() );
+          self.send_flow_controlled();
+        }
+
+        WriterCommand::Flush => self.send_batch(),
+
+        WriterCommand::SetBatching { config } => {
+          self.send_batch(); // do not leave anything behind, if batching is turned off
+          self.batching = config;
+        }
+
+        WriterCommand::SetFlowController { controller } => {
+          // Anything queued behind the previous controller is sent out now.
+          if let Some(fc) = self.flow_control.take() {
+            fc.borrow_mut().send_all(&self.udp_sender);
+          }
+          self.flow_control = controller.map(|c| RefCell::new(FlowControlQueue::new(c)));
         }
 
         // WriterCommand::ResetOfferedDeadlineMissedStatus { writer_guid: _, } => {
@@ -355,6 +486,63 @@ impl Writer {
     }
   }
 
+  fn add_to_batch(&mut self, timestamp: Timestamp, batching: BatchingConfig) {
+    let payload_len = self.dds_cache.read().unwrap()
+      .from_topic_get_change(&self.my_topic_name, &timestamp)
+      .and_then(|cc| cc.data_value.as_ref().map(|p| p.value.len()))
+      .unwrap_or(0);
+    // Never let a batch grow past max_bytes, which keeps it within a datagram.
+    if !self.batch.fits(payload_len, batching.max_bytes) {
+      self.send_batch();
+    }
+    self.batch.push(timestamp, payload_len);
+
+    if self.batch.is_full(batching.max_bytes) {
+      self.send_batch();
+    } else if !self.batch_timer_set {
+      self.timed_event_handler.as_mut().unwrap().set_timeout(
+        &chronoDuration::from_std(batching.max_delay).unwrap_or_else(|_| chronoDuration::zero()),
+        TimerMessageType::WriterFlushBatch,
+      );
+      self.batch_timer_set = true;
+    }
+  }
+
+  // Sends all batched samples as DATA submessages of a single message, followed
+  // by one HEARTBEAT.
+  fn send_batch(&mut self) {
+    if self.batch.is_empty() {
+      return
+    }
+    let batch = self.batch.take();
+    let batch_message = self.batch_message(&batch);
+    self.send_message_to_readers(DeliveryMode::Multicast, 
+      &batch_message, &mut self.readers.values() );
+    self.send_flow_controlled();
+  }
+
+  // DATA submessages of the samples in the order they were written
+  fn batch_message(&self, batch: &[Timestamp]) -> Message {
+    let mut message_builder = MessageBuilder::new()
+      .ts_msg(self.endianness, Some(Timestamp::now()) );
+    if self.push_mode {
+      let dds_cache = self.dds_cache.read().unwrap();
+      for timestamp in batch.iter() {
+        if let Some(cache_change) = dds_cache.from_topic_get_change(&self.my_topic_name, timestamp) {
+          message_builder = message_builder.data_msg( cache_change,
+                                                      EntityId::ENTITYID_UNKNOWN, // reader
+                                                      self.my_guid.entityId, // writer
+                                                      self.endianness );
+        }
+      }
+    }
+    let final_flag = false;
+    let liveliness_flag = false;
+    message_builder
+      .heartbeat_msg(self, EntityId::ENTITYID_UNKNOWN, final_flag, liveliness_flag)
+      .add_header_and_build(self.my_guid.guidPrefix)
+  }
+
   fn insert_to_history_cache(&mut self, data: DDSData) -> Timestamp {
     // first increasing last SequenceNumber
     let new_sequence_number = self.last_change_sequence_number + SequenceNumber::from(1);
@@ -413,6 +601,9 @@ impl Writer {
   
   /// This is called periodically.
   pub fn handle_heartbeat_tick(&mut self, is_manual_assertion: bool ) {
+    // Samples waiting in a batch would be announced by the heartbeat, but not yet sent.
+    self.send_batch();
+
     // Reliable Stateless Writer will set the final flag.
     // Reliable Stateful Writer (that tracks Readers by ReaderProxy) will not set the final flag.
     let final_flag = false;
@@ -432,7 +623,8 @@ impl Writer {
         .heartbeat_msg(self, EntityId::ENTITYID_UNKNOWN, final_flag, liveliness_flag)
         .add_header_and_build(self.my_guid.guidPrefix);      
       self.send_message_to_readers(DeliveryMode::Multicast, &hb_message, 
-                                    &mut self.readers.values())
+                                    &mut self.readers.values());
+      self.send_flow_controlled();
     }
 
     self.set_heartbeat_timer(); // keep the heart beating
@@ -528,11 +720,10 @@ impl Writer {
             if let Some(cache_change) = self.dds_cache.read().unwrap()
                 .from_topic_get_change(&self.my_topic_name, &timestamp) {
               partial_message = partial_message
-                  .data_msg(cache_change.clone(), // TODO: We should not clone, too much copying
+                  .data_msg(cache_change,
                             reader_guid.entityId, // reader
                             self.my_guid.entityId, // writer
                             self.endianness); 
-              // TODO: Here we are cloning the entire payload. We need to rewrite the transmit path to avoid copying.
             } else {
               no_longer_relevant.push(unsent_sn);
             }
@@ -555,6 +746,7 @@ impl Writer {
 
       self.send_message_to_readers(DeliveryMode::Unicast, &data_gap_msg,
                 &mut std::iter::once(&reader_proxy));
+      self.send_flow_controlled();
 
       if found_data { // prime repair timer again, if data was found
         // Try to send repait messages at 5x rate compared to usual deadline rate
@@ -608,26 +800,79 @@ impl Writer {
     self.heartbeat_message_counter = self.heartbeat_message_counter + 1;
   }
 
+  // Sends what the flow controller allows from the queue, and sets a timer
+  // to continue later, if something is left. TCP is not flow controlled, as it
+  // has congestion control of its own.
+  fn send_flow_controlled(&mut self) {
+    let wait = match &self.flow_control {
+      Some(fc) => {
+        let mut queue = fc.borrow_mut();
+        match queue.send_available(&self.udp_sender) {
+          Some(wait) if !queue.timer_set => {
+            queue.timer_set = true;
+            Some(wait)
+          }
+          _ => None,
+        }
+      }
+      None => None,
+    };
+    if let Some(wait) = wait {
+      self.timed_event_handler.as_mut().unwrap().set_timeout(
+        &chronoDuration::from_std(wait).unwrap_or_else(|_| chronoDuration::milliseconds(1)),
+        TimerMessageType::WriterFlowControl,
+      );
+    }
+  }
+
   fn send_message_to_readers(&self, preferred_mode: DeliveryMode, message: &Message, 
         readers: &mut dyn Iterator<Item = &RtpsReaderProxy>) {
     // TODO: This is a stupid transmit algorithm. We should compute a preferred
     // unicast and multicast locators for each reader only on every reader update, and
     // not find it dynamically on every message.
-    let buffer = message.write_to_vec_with_ctx(self.endianness).unwrap();
+    // Payloads are not copied here. Segments share the Bytes of the CacheChanges.
+    let segments = message.write_to_segments(self.endianness).unwrap();
+    // TCP connections queue the frame anyway, so they get a contiguous buffer.
+    let buffer = if self.tcp_sender.is_some() { segments.concat() } else { Vec::new() };
     let mut already_sent_to = BTreeSet::new();
+    let mut already_sent_to_tcp = BTreeSet::new();
 
     macro_rules! send_unless_sent_and_mark {
       ($loc:expr) => {
         if already_sent_to.contains($loc) {
           trace!("Already sent to {:?}", $loc);
         } else {
-          self.udp_sender.send_to_locator(&buffer, $loc);
+          match &self.flow_control {
+            Some(fc) => fc.borrow_mut().enqueue(segments.clone(), $loc.clone()),
+            None => self.udp_sender.send_segments_to_locator(&segments, $loc),
+          }
           already_sent_to.insert($loc.clone());
         }
-      }
+      };
+      ($loc:expr, $tcp:expr) => {
+        if already_sent_to.contains($loc) {
+          trace!("Already sent to {:?}", $loc);
+        } else {
+          $tcp.send_to_locator(&buffer, $loc);
+          already_sent_to.insert($loc.clone());
+        }
+      };
     }
 
     for reader in readers {
+      if let Some(tcp) = &self.tcp_sender {
+        // A TCP connection to the reader's participant is always preferred.
+        let prefix = reader.remote_reader_guid.guidPrefix;
+        if already_sent_to_tcp.contains(&prefix) { continue }
+        if tcp.send_to_participant(prefix, &buffer) {
+          already_sent_to_tcp.insert(prefix);
+          continue
+        }
+        // No connection to it yet. Try the TCP locators, e.g. well-known servers.
+        for loc in reader.unicast_locator_list.iter().filter(|l| l.isTCP()) {
+          send_unless_sent_and_mark!(loc, tcp)
+        }
+      }
       match ( preferred_mode, 
               reader.unicast_locator_list.iter().find(|l| Locator::isUDP(l) ), 
               reader.multicast_locator_list.iter().find(|l| Locator::isUDP(l) ) ) {
@@ -816,22 +1061,37 @@ mod tests {
       topic::TopicKind,
     },
   };
-  use std::thread;
+  use std::{
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration as StdDuration, Instant},
+  };
   use crate::test::random_data::*;
   use crate::serialization::cdr_serializer::CDRSerializerAdapter;
+  use crate::dds::{ddsdata::DDSData, qos::policy::History, typedesc::TypeDesc};
+  use crate::common::timed_event_handler::TimedEventHandler;
+  use crate::network::constant::TimerMessageType;
+  use crate::messages::submessages::{
+    submessage::EntitySubmessage,
+    submessage_elements::serialized_payload::{RepresentationIdentifier, SerializedPayload},
+  };
+  use crate::serialization::submessage::SubmessageBody;
+  use crate::structure::{dds_cache::DDSCache, guid::GUID, time::Timestamp};
   use byteorder::LittleEndian;
+  use mio_extras::channel as mio_channel;
   use log::info;
+  use super::{Batch, BatchingConfig, Writer, DATA_SUBMESSAGE_OVERHEAD};
 
   #[test]
   fn test_writer_recieves_datawriter_cache_change_notifications() {
-    let domain_participant = DomainParticipant::new(0);
+    let domain_participant = DomainParticipant::new(0).unwrap();
     let qos = QosPolicies::qos_none();
     let _default_dw_qos = QosPolicies::qos_none();
 
-    let publisher = domain_participant.unwrap()
+    let publisher = domain_participant
       .create_publisher(&qos)
       .expect("Failed to create publisher");
-    let topic = domain_participant.unwrap()
+    let topic = domain_participant
       .create_topic("Aasii", "Huh?", &qos, TopicKind::WithKey)
       .expect("Failed to create topic");
     let data_writer: DataWriter<RandomData, CDRSerializerAdapter<RandomData, LittleEndian>> =
@@ -869,4 +1129,130 @@ mod tests {
     thread::sleep(std::time::Duration::from_millis(100));
     info!("writerResult:  {:?}", writeResult);
   }
+
+  #[test]
+  fn batching_config_fits_in_datagram() {
+    let delay = std::time::Duration::from_millis(1);
+    assert_eq!(BatchingConfig::new(1000, delay).max_bytes, 1000);
+    assert!(BatchingConfig::new(1_000_000, delay).max_bytes < 64 * 1024);
+  }
+
+  #[test]
+  fn batch_never_exceeds_max_bytes() {
+    let max_bytes = 1000;
+    let payload = 400;
+    let mut batch = Batch::default();
+    assert!(batch.fits(payload, max_bytes));
+    batch.push(Timestamp::now(), payload);
+    assert!(batch.fits(payload, max_bytes));
+    batch.push(Timestamp::now(), payload);
+    assert_eq!(batch.bytes, 2 * (payload + DATA_SUBMESSAGE_OVERHEAD));
+    assert!(!batch.is_full(max_bytes));
+    // A third sample would go over the limit, so the batch must be sent first.
+    assert!(!batch.fits(payload, max_bytes));
+    assert_eq!(batch.take().len(), 2);
+    assert!(batch.is_empty());
+    assert_eq!(batch.bytes, 0);
+
+    // An oversized sample only fits into an empty batch, which is then full.
+    batch.push(Timestamp::now(), payload);
+    assert!(!batch.fits(2 * max_bytes, max_bytes));
+    batch.take();
+    assert!(batch.fits(2 * max_bytes, max_bytes));
+    batch.push(Timestamp::now(), 2 * max_bytes);
+    assert!(batch.is_full(max_bytes));
+  }
+
+  // Writer with a timer, but without any network connections
+  fn test_writer(
+    topic_name: &str,
+  ) -> (Writer, mio_channel::Receiver<TimerMessageType>) {
+    let dds_cache = Arc::new(RwLock::new(DDSCache::new()));
+    dds_cache
+      .write()
+      .unwrap()
+      .add_new_topic(&topic_name.to_string(), TopicKind::NoKey, TypeDesc::new("Bytes"));
+    let (_command_sender, command_receiver) = mio_channel::channel();
+    let (status_sender, _status_receiver) = mio_channel::sync_channel(10);
+    let (timer_sender, timer_receiver) = mio_channel::sync_channel(10);
+    let mut writer = Writer::new(
+      GUID::dummy_test_guid(crate::structure::guid::EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      command_receiver,
+      dds_cache,
+      topic_name.to_string(),
+      QosPolicies::builder().history(History::KeepAll).build(),
+      status_sender,
+    );
+    writer.add_timed_event_handler(TimedEventHandler::new(timer_sender));
+    (writer, timer_receiver)
+  }
+
+  fn insert_sample(writer: &mut Writer, len: usize) -> Timestamp {
+    let payload = SerializedPayload::new(RepresentationIdentifier::CDR_LE, vec![0; len]);
+    writer.insert_to_history_cache(DDSData::new(payload))
+  }
+
+  #[test]
+  fn batch_is_sent_before_it_exceeds_max_bytes() {
+    let config = BatchingConfig::new(1000, StdDuration::from_secs(3600));
+    let (mut writer, _timer_receiver) = test_writer("batch_size_test");
+    let samples: Vec<Timestamp> = (0..3).map(|_| insert_sample(&mut writer, 400)).collect();
+
+    // Two samples fit into the batch, the third one flushes them.
+    writer.add_to_batch(samples[0], config);
+    writer.add_to_batch(samples[1], config);
+    assert_eq!(writer.batch.samples, samples[0..2]);
+    writer.add_to_batch(samples[2], config);
+    assert_eq!(writer.batch.samples, samples[2..3]);
+
+    // An oversized sample flushes the waiting one, and is sent alone.
+    let oversized = insert_sample(&mut writer, 2000);
+    writer.add_to_batch(oversized, config);
+    assert!(writer.batch.is_empty());
+  }
+
+  #[test]
+  fn batch_is_sent_after_max_delay() {
+    let config = BatchingConfig::new(60_000, StdDuration::from_millis(50));
+    let (mut writer, timer_receiver) = test_writer("batch_delay_test");
+
+    for _ in 0..5 {
+      let timestamp = insert_sample(&mut writer, 10);
+      writer.add_to_batch(timestamp, config);
+    }
+    assert_eq!(writer.batch.samples.len(), 5);
+
+    let deadline = Instant::now() + StdDuration::from_secs(5);
+    let timer_message = loop {
+      match timer_receiver.try_recv() {
+        Ok(TimerMessageType::WriterFlushBatch) => break TimerMessageType::WriterFlushBatch,
+        Ok(_) => {}
+        Err(_) => {
+          assert!(Instant::now() < deadline, "batch flush timer did not expire");
+          thread::sleep(StdDuration::from_millis(10));
+        }
+      }
+    };
+    writer.handle_timed_event(timer_message);
+    assert!(writer.batch.is_empty());
+    assert!(!writer.batch_timer_set);
+  }
+
+  #[test]
+  fn batch_message_keeps_sequence_number_order() {
+    let (mut writer, _timer_receiver) = test_writer("batch_order_test");
+    let batch: Vec<Timestamp> = (0..5).map(|_| insert_sample(&mut writer, 4)).collect();
+    let message = writer.batch_message(&batch);
+    let sequence_numbers: Vec<i64> = message
+      .submessages
+      .iter()
+      .filter_map(|submessage| match &submessage.body {
+        SubmessageBody::Entity(EntitySubmessage::Data(data, _)) => {
+          Some(i64::from(data.writer_sn))
+        }
+        _ => None,
+      })
+      .collect();
+    assert_eq!(sequence_numbers, vec![1, 2, 3, 4, 5]);
+  }
 }
@@ -0,0 +1,404 @@
+use crate::{
+  dds::qos::policy::{TypeConsistencyEnforcement, TypeConsistencyKind},
+  serialization::cdr2_serializer::Extensibility,
+};
+
+use super::type_object::{EnumType, StructMember, StructType, TypeIdentifier, TypeInformation, TypeObject};
+
+// Type graphs received from the network may be cyclic or absurdly deep.
+const MAX_DEPTH: usize = 64;
+
+impl TypeInformation {
+  /// XTypes 7.2.4: can data of the `writer` type be read as this type.
+  ///
+  /// Returns None if this cannot be decided, because some of the needed
+  /// TypeObjects are not known.
+  pub fn is_assignable_from(
+    &self,
+    writer: &TypeInformation,
+    type_consistency: &TypeConsistencyEnforcement,
+  ) -> Option<bool> {
+    Assignability { reader: self, writer, tce: type_consistency }.assignable(
+      self.type_identifier(),
+      writer.type_identifier(),
+      0,
+    )
+  }
+}
+
+// XTypes 7.6.3.4.2: Decides if a reader and a writer with these types may be matched.
+// Endpoints that did not tell their type, or whose types cannot be resolved,
+// are matched by name as before, unless the reader forces type validation.
+pub(crate) fn types_consistent(
+  reader: Option<&TypeInformation>,
+  writer: Option<&TypeInformation>,
+  type_consistency: &TypeConsistencyEnforcement,
+) -> bool {
+  match (reader, writer) {
+    (Some(r), Some(w)) => r
+      .is_assignable_from(w, type_consistency)
+      .unwrap_or(!type_consistency.force_type_validation),
+    _ => !type_consistency.force_type_validation,
+  }
+}
+
+// Conjunction, where a definite "false" wins over "unknown".
+fn all(results: impl IntoIterator<Item = Option<bool>>) -> Option<bool> {
+  let mut result = Some(true);
+  for r in results {
+    match r {
+      Some(false) => return Some(false),
+      None => result = None,
+      Some(true) => (),
+    }
+  }
+  result
+}
+
+struct Assignability<'a> {
+  reader: &'a TypeInformation,
+  writer: &'a TypeInformation,
+  tce: &'a TypeConsistencyEnforcement,
+}
+
+impl<'a> Assignability<'a> {
+  // With DISALLOW_TYPE_COERCION the types must be equal, apart from what the
+  // ignore_* settings allow.
+  fn strict(&self) -> bool {
+    self.tce.kind == TypeConsistencyKind::DisallowTypeCoercion
+  }
+
+  // Follows aliases. Returns the TypeObject for structures and enumerations,
+  // None for fully descriptive types, and Err if a hash is unknown.
+  fn resolve<'b>(
+    info: &'b TypeInformation,
+    mut ti: &'b TypeIdentifier,
+  ) -> Result<(&'b TypeIdentifier, Option<&'b TypeObject>), ()> {
+    for _ in 0..MAX_DEPTH {
+      match ti {
+        TypeIdentifier::EquivalenceHashMinimal(_) => match info.type_object(ti) {
+          Some(TypeObject::Alias { related_type }) => ti = related_type,
+          Some(to) => return Ok((ti, Some(to))),
+          None => return Err(()),
+        },
+        _ => return Ok((ti, None)),
+      }
+    }
+    Err(())
+  }
+
+  fn assignable(&self, r: &TypeIdentifier, w: &TypeIdentifier, depth: usize) -> Option<bool> {
+    if r == w {
+      // Equal hashes imply equal TypeObjects
+      return Some(true)
+    }
+    if depth > MAX_DEPTH {
+      return Some(false)
+    }
+    let (r, r_object) = Self::resolve(self.reader, r).ok()?;
+    let (w, w_object) = Self::resolve(self.writer, w).ok()?;
+    match (r_object, w_object) {
+      (None, None) => self.plain_assignable(r, w, depth),
+      (Some(TypeObject::Struct(rs)), Some(TypeObject::Struct(ws))) => {
+        self.struct_assignable(rs, ws, depth)
+      }
+      (Some(TypeObject::Enum(re)), Some(TypeObject::Enum(we))) => Some(self.enum_assignable(re, we)),
+      _ => Some(false),
+    }
+  }
+
+  fn bounds_ok(&self, reader_bound: u32, writer_bound: u32, ignore: bool) -> bool {
+    if ignore {
+      true
+    } else if self.strict() {
+      reader_bound == writer_bound
+    } else {
+      // zero is unbounded
+      reader_bound == 0 || (writer_bound != 0 && writer_bound <= reader_bound)
+    }
+  }
+
+  fn plain_assignable(&self, r: &TypeIdentifier, w: &TypeIdentifier, depth: usize) -> Option<bool> {
+    match (r, w) {
+      (TypeIdentifier::String8 { bound: rb }, TypeIdentifier::String8 { bound: wb })
+      | (TypeIdentifier::String16 { bound: rb }, TypeIdentifier::String16 { bound: wb }) => {
+        Some(self.bounds_ok(*rb, *wb, self.tce.ignore_string_bounds))
+      }
+      (
+        TypeIdentifier::Sequence { element: re, bound: rb },
+        TypeIdentifier::Sequence { element: we, bound: wb },
+      ) => {
+        if self.bounds_ok(*rb, *wb, self.tce.ignore_sequence_bounds) {
+          self.assignable(re, we, depth + 1)
+        } else {
+          Some(false)
+        }
+      }
+      (
+        TypeIdentifier::Array { element: re, dimensions: rd },
+        TypeIdentifier::Array { element: we, dimensions: wd },
+      ) => {
+        if rd == wd {
+          self.assignable(re, we, depth + 1)
+        } else {
+          Some(false)
+        }
+      }
+      // Primitives. XTypes 1.3 does not allow widening of primitive types.
+      _ => Some(r == w),
+    }
+  }
+
+  // Members of a structure, including those inherited from its base types
+  fn members<'b>(
+    info: &'b TypeInformation,
+    st: &'b StructType,
+    depth: usize,
+  ) -> Option<Vec<&'b StructMember>> {
+    let mut members = Vec::new();
+    if let Some(base) = &st.base_type {
+      match Self::resolve(info, base).ok()? {
+        (_, Some(TypeObject::Struct(base))) if depth < MAX_DEPTH => {
+          members.extend(Self::members(info, base, depth + 1)?)
+        }
+        _ => return None,
+      }
+    }
+    members.extend(st.members.iter());
+    Some(members)
+  }
+
+  fn member_assignable(
+    &self,
+    r: &StructMember,
+    w: &StructMember,
+    allow_optional_mismatch: bool,
+    depth: usize,
+  ) -> Option<bool> {
+    if r.member_id != w.member_id
+      || (r.name_hash != w.name_hash && !self.tce.ignore_member_names)
+      || r.key != w.key
+      || (r.optional != w.optional && !allow_optional_mismatch)
+    {
+      return Some(false)
+    }
+    self.assignable(&r.member_type, &w.member_type, depth + 1)
+  }
+
+  fn struct_assignable(&self, rs: &StructType, ws: &StructType, depth: usize) -> Option<bool> {
+    if rs.extensibility != ws.extensibility {
+      return Some(false)
+    }
+    let rm = Self::members(self.reader, rs, depth)?;
+    let wm = Self::members(self.writer, ws, depth)?;
+    let both_empty = rm.is_empty() && wm.is_empty();
+
+    let extensibility = if self.strict() { Extensibility::Final } else { rs.extensibility };
+    match extensibility {
+      Extensibility::Final => {
+        if rm.len() != wm.len() {
+          return Some(false)
+        }
+        all(rm.iter().zip(wm.iter()).map(|(r, w)| self.member_assignable(r, w, false, depth)))
+      }
+      Extensibility::Appendable => {
+        // One type must be a prefix of the other.
+        let common = rm.len().min(wm.len());
+        if rm[common..].iter().chain(wm[common..].iter()).any(|m| m.key)
+          || (self.tce.prevent_type_widening && wm.len() > rm.len())
+          || (common == 0 && !both_empty)
+        {
+          return Some(false)
+        }
+        all(rm.iter().zip(wm.iter()).map(|(r, w)| self.member_assignable(r, w, false, depth)))
+      }
+      Extensibility::Mutable => {
+        // Members are matched by id. Name must not move to another id.
+        let mut results = Vec::new();
+        for r in &rm {
+          if !self.tce.ignore_member_names
+            && wm.iter().any(|w| w.name_hash == r.name_hash && w.member_id != r.member_id)
+          {
+            return Some(false)
+          }
+          match wm.iter().find(|w| w.member_id == r.member_id) {
+            Some(w) => results.push(self.member_assignable(r, w, true, depth)),
+            None if r.key => return Some(false),
+            None => (),
+          }
+        }
+        for w in &wm {
+          if !rm.iter().any(|r| r.member_id == w.member_id)
+            && (w.key || self.tce.prevent_type_widening)
+          {
+            return Some(false)
+          }
+        }
+        if results.is_empty() && !both_empty {
+          return Some(false)
+        }
+        all(results)
+      }
+    }
+  }
+
+  fn enum_assignable(&self, re: &EnumType, we: &EnumType) -> bool {
+    if re.extensibility != we.extensibility || re.bit_bound != we.bit_bound {
+      return false
+    }
+    let same_literals = |a: &EnumType, b: &EnumType| {
+      a.literals.iter().all(|l| {
+        b.literals.iter().any(|m| m.name_hash == l.name_hash && m.value == l.value)
+      })
+    };
+    if self.strict() || re.extensibility == Extensibility::Final {
+      return re.literals.len() == we.literals.len() && same_literals(re, we)
+    }
+    // A literal must keep its value and a value must keep its literal.
+    let conflicting = we.literals.iter().any(|w| {
+      re.literals.iter().any(|r| (r.name_hash == w.name_hash) != (r.value == w.value))
+    });
+    !conflicting && (!self.tce.prevent_type_widening || same_literals(we, re))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dds::xtypes::type_object::{name_hash, EnumLiteral};
+
+  fn member(id: u32, name: &str, member_type: TypeIdentifier) -> StructMember {
+    StructMember {
+      member_id: id,
+      member_type,
+      name_hash: name_hash(name),
+      optional: false,
+      must_understand: false,
+      key: false,
+    }
+  }
+
+  fn struct_info(extensibility: Extensibility, members: Vec<StructMember>) -> TypeInformation {
+    let to = TypeObject::Struct(StructType { extensibility, base_type: None, members });
+    TypeInformation::new(to.type_identifier(), vec![to])
+  }
+
+  fn v1(ext: Extensibility) -> TypeInformation {
+    struct_info(ext, vec![
+      member(0, "id", TypeIdentifier::Int32),
+      member(1, "name", TypeIdentifier::String8 { bound: 0 }),
+    ])
+  }
+
+  fn v2(ext: Extensibility) -> TypeInformation {
+    struct_info(ext, vec![
+      member(0, "id", TypeIdentifier::Int32),
+      member(1, "name", TypeIdentifier::String8 { bound: 0 }),
+      member(2, "score", TypeIdentifier::Float64),
+    ])
+  }
+
+  #[test]
+  fn appendable_types_evolve() {
+    let tce = TypeConsistencyEnforcement::default();
+    let (old, new) = (v1(Extensibility::Appendable), v2(Extensibility::Appendable));
+    assert_eq!(old.is_assignable_from(&new, &tce), Some(true));
+    assert_eq!(new.is_assignable_from(&old, &tce), Some(true));
+
+    let prevent = TypeConsistencyEnforcement { prevent_type_widening: true, ..tce };
+    assert_eq!(old.is_assignable_from(&new, &prevent), Some(false));
+    assert_eq!(new.is_assignable_from(&old, &prevent), Some(true));
+
+    let disallow = TypeConsistencyEnforcement {
+      kind: TypeConsistencyKind::DisallowTypeCoercion,
+      ..tce
+    };
+    assert_eq!(old.is_assignable_from(&new, &disallow), Some(false));
+  }
+
+  #[test]
+  fn final_types_must_match() {
+    let tce = TypeConsistencyEnforcement::default();
+    let (old, new) = (v1(Extensibility::Final), v2(Extensibility::Final));
+    assert_eq!(old.is_assignable_from(&new, &tce), Some(false));
+    assert_eq!(old.is_assignable_from(&v1(Extensibility::Final), &tce), Some(true));
+    assert_eq!(old.is_assignable_from(&v1(Extensibility::Appendable), &tce), Some(false));
+
+    let renamed = struct_info(Extensibility::Final, vec![
+      member(0, "id", TypeIdentifier::Int32),
+      member(1, "label", TypeIdentifier::String8 { bound: 0 }),
+    ]);
+    assert_eq!(old.is_assignable_from(&renamed, &tce), Some(false));
+    let ignore_names = TypeConsistencyEnforcement { ignore_member_names: true, ..tce };
+    assert_eq!(old.is_assignable_from(&renamed, &ignore_names), Some(true));
+
+    let retyped = struct_info(Extensibility::Final, vec![
+      member(0, "id", TypeIdentifier::Int64),
+      member(1, "name", TypeIdentifier::String8 { bound: 0 }),
+    ]);
+    assert_eq!(old.is_assignable_from(&retyped, &tce), Some(false));
+  }
+
+  #[test]
+  fn mutable_types_match_by_id() {
+    let tce = TypeConsistencyEnforcement::default();
+    let reordered = struct_info(Extensibility::Mutable, vec![
+      member(2, "score", TypeIdentifier::Float64),
+      member(0, "id", TypeIdentifier::Int32),
+    ]);
+    assert_eq!(v1(Extensibility::Mutable).is_assignable_from(&reordered, &tce), Some(true));
+
+    let keyed = struct_info(Extensibility::Mutable, vec![
+      member(0, "id", TypeIdentifier::Int32),
+      StructMember { key: true, ..member(1, "name", TypeIdentifier::String8 { bound: 0 }) },
+    ]);
+    // the key member "name" is missing from the writer type
+    assert_eq!(keyed.is_assignable_from(&reordered, &tce), Some(false));
+  }
+
+  #[test]
+  fn bounds_and_enums() {
+    let tce = TypeConsistencyEnforcement {
+      ignore_string_bounds: false,
+      ..TypeConsistencyEnforcement::default()
+    };
+    let bounded = |bound| struct_info(Extensibility::Final, vec![
+      member(0, "s", TypeIdentifier::String8 { bound }),
+    ]);
+    assert_eq!(bounded(0).is_assignable_from(&bounded(10), &tce), Some(true));
+    assert_eq!(bounded(10).is_assignable_from(&bounded(0), &tce), Some(false));
+    assert_eq!(bounded(10).is_assignable_from(&bounded(0), &TypeConsistencyEnforcement::default()), Some(true));
+
+    let color = |literals: &[(&str, i32)]| {
+      let to = TypeObject::Enum(EnumType {
+        extensibility: Extensibility::Appendable,
+        bit_bound: 32,
+        literals: literals
+          .iter()
+          .map(|(n, v)| EnumLiteral { value: *v, name_hash: name_hash(n), default: false })
+          .collect(),
+      });
+      TypeInformation::new(to.type_identifier(), vec![to])
+    };
+    let rg = color(&[("RED", 0), ("GREEN", 1)]);
+    let rgb = color(&[("RED", 0), ("GREEN", 1), ("BLUE", 2)]);
+    let gr = color(&[("GREEN", 0), ("RED", 1)]);
+    assert_eq!(rgb.is_assignable_from(&rg, &tce), Some(true));
+    assert_eq!(rg.is_assignable_from(&rgb, &tce), Some(true));
+    assert_eq!(rg.is_assignable_from(&gr, &tce), Some(false));
+  }
+
+  #[test]
+  fn unresolved_types() {
+    let tce = TypeConsistencyEnforcement::default();
+    let full = v1(Extensibility::Appendable);
+    let ids_only = TypeInformation::new(v2(Extensibility::Appendable).type_identifier().clone(), vec![]);
+    assert_eq!(full.is_assignable_from(&ids_only, &tce), None);
+    assert!(types_consistent(Some(&full), Some(&ids_only), &tce));
+    assert!(types_consistent(None, Some(&full), &tce));
+
+    let force = TypeConsistencyEnforcement { force_type_validation: true, ..tce };
+    assert!(!types_consistent(Some(&full), Some(&ids_only), &force));
+    assert!(!types_consistent(None, Some(&full), &force));
+  }
+}
@@ -0,0 +1,973 @@
+// XCDR1 and XCDR2 encoding of DynamicData, XTypes 7.4.
+//
+// Unlike the serde-based (de)serializers, this walks the type description, so
+// each structure is encoded according to its own extensibility.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::{
+  messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier,
+  serialization::{
+    cdr2_serializer::{Extensibility, EMHEADER_LC_SHIFT, EMHEADER_MEMBER_ID_MASK, LC_NEXTINT},
+    error::{Error, Result},
+  },
+};
+
+use super::{
+  dynamic_data::{DynamicData, DynamicValue},
+  dynamic_type::{DynamicEnum, DynamicStruct, DynamicType},
+};
+
+const EMHEADER_M_FLAG: u32 = 1 << 31;
+
+// XCDR1 parameter headers, XTypes 7.4.1.2.1
+const PID_EXTENDED: u16 = 0x3F01;
+const PID_LIST_END: u16 = 0x3F02;
+const PID_MUST_UNDERSTAND: u16 = 0x4000;
+const PID_ID_MASK: u16 = 0x3FFF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum XcdrVersion {
+  Xcdr1,
+  Xcdr2,
+}
+
+/// Representations that DynamicData can be decoded from
+pub(crate) const SUPPORTED_ENCODINGS: &[RepresentationIdentifier] = &[
+  RepresentationIdentifier::CDR_LE,
+  RepresentationIdentifier::CDR_BE,
+  RepresentationIdentifier::PL_CDR_LE,
+  RepresentationIdentifier::PL_CDR_BE,
+  RepresentationIdentifier::CDR2_LE,
+  RepresentationIdentifier::CDR2_BE,
+  RepresentationIdentifier::D_CDR_LE,
+  RepresentationIdentifier::D_CDR_BE,
+  RepresentationIdentifier::PL_CDR2_LE,
+  RepresentationIdentifier::PL_CDR2_BE,
+];
+
+// XCDR version and big-endianness of a representation
+fn version_of(encoding: RepresentationIdentifier) -> Option<(XcdrVersion, bool)> {
+  match encoding {
+    RepresentationIdentifier::CDR_LE | RepresentationIdentifier::PL_CDR_LE => {
+      Some((XcdrVersion::Xcdr1, false))
+    }
+    RepresentationIdentifier::CDR_BE | RepresentationIdentifier::PL_CDR_BE => {
+      Some((XcdrVersion::Xcdr1, true))
+    }
+    RepresentationIdentifier::CDR2_LE
+    | RepresentationIdentifier::D_CDR_LE
+    | RepresentationIdentifier::PL_CDR2_LE => Some((XcdrVersion::Xcdr2, false)),
+    RepresentationIdentifier::CDR2_BE
+    | RepresentationIdentifier::D_CDR_BE
+    | RepresentationIdentifier::PL_CDR2_BE => Some((XcdrVersion::Xcdr2, true)),
+    _ => None,
+  }
+}
+
+/// The representation identifier for a top-level type, XTypes 7.6.3.1.2
+pub(crate) fn representation_identifier(
+  extensibility: Extensibility,
+  version: XcdrVersion,
+  big_endian: bool,
+) -> RepresentationIdentifier {
+  match (version, extensibility, big_endian) {
+    (XcdrVersion::Xcdr1, Extensibility::Mutable, false) => RepresentationIdentifier::PL_CDR_LE,
+    (XcdrVersion::Xcdr1, Extensibility::Mutable, true) => RepresentationIdentifier::PL_CDR_BE,
+    (XcdrVersion::Xcdr1, _, false) => RepresentationIdentifier::CDR_LE,
+    (XcdrVersion::Xcdr1, _, true) => RepresentationIdentifier::CDR_BE,
+    (XcdrVersion::Xcdr2, Extensibility::Final, false) => RepresentationIdentifier::CDR2_LE,
+    (XcdrVersion::Xcdr2, Extensibility::Final, true) => RepresentationIdentifier::CDR2_BE,
+    (XcdrVersion::Xcdr2, Extensibility::Appendable, false) => RepresentationIdentifier::D_CDR_LE,
+    (XcdrVersion::Xcdr2, Extensibility::Appendable, true) => RepresentationIdentifier::D_CDR_BE,
+    (XcdrVersion::Xcdr2, Extensibility::Mutable, false) => RepresentationIdentifier::PL_CDR2_LE,
+    (XcdrVersion::Xcdr2, Extensibility::Mutable, true) => RepresentationIdentifier::PL_CDR2_BE,
+  }
+}
+
+pub(crate) fn decode(
+  descriptor: &Arc<DynamicStruct>,
+  input: &[u8],
+  encoding: RepresentationIdentifier,
+) -> Result<DynamicData> {
+  match version_of(encoding) {
+    Some((version, false)) => Decoder::<LittleEndian>::new(input, version).decode_struct(descriptor),
+    Some((version, true)) => Decoder::<BigEndian>::new(input, version).decode_struct(descriptor),
+    None => Err(Error::Message(format!("Unsupported encoding {:?}", encoding))),
+  }
+}
+
+pub(crate) fn encode(data: &DynamicData, version: XcdrVersion, big_endian: bool) -> Result<Vec<u8>> {
+  if big_endian {
+    let mut encoder = Encoder::<BigEndian>::new(version, false);
+    encoder.encode_struct(data)?;
+    Ok(encoder.buffer)
+  } else {
+    let mut encoder = Encoder::<LittleEndian>::new(version, false);
+    encoder.encode_struct(data)?;
+    Ok(encoder.buffer)
+  }
+}
+
+/// Instance key hash, computed like
+/// [`Key::into_hash_key`](../traits/trait.Key.html#method.into_hash_key): key
+/// members in big-endian XCDR2, padded to 16 bytes if the key has a fixed
+/// size of at most 16 bytes, and otherwise digested with MD5. A structure
+/// without key members has key hash zero.
+pub(crate) fn key_hash(data: &DynamicData) -> Result<u128> {
+  if !data.members().any(|(m, _)| m.key) {
+    return Ok(0)
+  }
+  let mut encoder = Encoder::<BigEndian>::new(XcdrVersion::Xcdr2, true);
+  encoder.encode_struct(data)?;
+  let digest = if key_has_fixed_size(data.dynamic_struct()) && encoder.buffer.len() <= 16 {
+    encoder.buffer
+  } else {
+    md5::compute(&encoder.buffer).to_vec()
+  };
+  let mut bytes = [0; 16];
+  bytes[..digest.len()].copy_from_slice(&digest);
+  Ok(u128::from_le_bytes(bytes))
+}
+
+// Key members are all members of a nested structure without key members.
+fn key_has_fixed_size(dynamic_struct: &DynamicStruct) -> bool {
+  let has_key = dynamic_struct.members.iter().any(|m| m.key);
+  dynamic_struct
+    .members
+    .iter()
+    .filter(|m| m.key || !has_key)
+    .all(|m| !m.optional && has_fixed_size(&m.member_type))
+}
+
+fn has_fixed_size(dynamic_type: &DynamicType) -> bool {
+  match dynamic_type {
+    DynamicType::String { .. } | DynamicType::Sequence { .. } => false,
+    DynamicType::Array { element, .. } => has_fixed_size(element),
+    DynamicType::Struct(s) => key_has_fixed_size(s),
+    _ => true,
+  }
+}
+
+// Collections of these have no DHEADER in XCDR2.
+fn is_primitive(dynamic_type: &DynamicType) -> bool {
+  !matches!(
+    dynamic_type,
+    DynamicType::String { .. }
+      | DynamicType::Sequence { .. }
+      | DynamicType::Array { .. }
+      | DynamicType::Struct(_)
+  )
+}
+
+// Size of a primitive value, which is also its alignment
+fn primitive_size(dynamic_type: &DynamicType) -> Option<usize> {
+  match dynamic_type {
+    DynamicType::Boolean
+    | DynamicType::Byte
+    | DynamicType::Int8
+    | DynamicType::UInt8
+    | DynamicType::Char8 => Some(1),
+    DynamicType::Int16 | DynamicType::UInt16 => Some(2),
+    DynamicType::Int32 | DynamicType::UInt32 | DynamicType::Float32 => Some(4),
+    DynamicType::Int64 | DynamicType::UInt64 | DynamicType::Float64 => Some(8),
+    DynamicType::Enum(e) => Some(enum_size(e)),
+    _ => None,
+  }
+}
+
+fn enum_size(e: &DynamicEnum) -> usize {
+  match e.bit_bound {
+    0..=8 => 1,
+    9..=16 => 2,
+    _ => 4,
+  }
+}
+
+fn element_count(dimensions: &[u32]) -> usize {
+  dimensions.iter().map(|d| *d as usize).product()
+}
+
+fn missing_member(descriptor: &DynamicStruct, index: usize) -> Option<DynamicValue> {
+  let member = &descriptor.members[index];
+  if member.optional {
+    None
+  } else {
+    Some(DynamicValue::default_of(&member.member_type))
+  }
+}
+
+struct Decoder<'a, BO> {
+  input: &'a [u8],
+  position: usize,
+  version: XcdrVersion,
+  phantom: PhantomData<BO>,
+}
+
+impl<'a, BO: ByteOrder> Decoder<'a, BO> {
+  fn new(input: &'a [u8], version: XcdrVersion) -> Decoder<'a, BO> {
+    Decoder {
+      input,
+      position: 0,
+      version,
+      phantom: PhantomData,
+    }
+  }
+
+  fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+    if count > self.input.len() - self.position {
+      return Err(Error::Eof)
+    }
+    let bytes = &self.input[self.position..self.position + count];
+    self.position += count;
+    Ok(bytes)
+  }
+
+  // XCDR1 aligns to at most 8 bytes and XCDR2 to at most 4.
+  fn align(&mut self, alignment: usize) -> Result<()> {
+    let alignment = match self.version {
+      XcdrVersion::Xcdr1 => alignment.min(8),
+      XcdrVersion::Xcdr2 => alignment.min(4),
+    };
+    let padding = (alignment - self.position % alignment) % alignment;
+    self.bytes(padding).map(|_| ())
+  }
+
+  fn read<T>(&mut self, size: usize, f: fn(&[u8]) -> T) -> Result<T> {
+    self.align(size)?;
+    Ok(f(self.bytes(size)?))
+  }
+
+  fn u8(&mut self) -> Result<u8> {
+    Ok(self.bytes(1)?[0])
+  }
+
+  fn u16(&mut self) -> Result<u16> {
+    self.read(2, BO::read_u16)
+  }
+
+  fn u32(&mut self) -> Result<u32> {
+    self.read(4, BO::read_u32)
+  }
+
+  // Reads a DHEADER and returns the position where the delimited data ends.
+  fn dheader(&mut self) -> Result<usize> {
+    let len = self.u32()? as usize;
+    self.end_position(len)
+  }
+
+  fn end_position(&self, len: usize) -> Result<usize> {
+    if len > self.input.len() - self.position {
+      return Err(Error::Eof)
+    }
+    Ok(self.position + len)
+  }
+
+  fn skip_to(&mut self, end: usize) -> Result<()> {
+    if self.position > end {
+      return Err(Error::Message("Value overruns its length header".to_string()))
+    }
+    self.position = end;
+    Ok(())
+  }
+
+  fn decode_value(&mut self, dynamic_type: &DynamicType) -> Result<DynamicValue> {
+    Ok(match dynamic_type {
+      DynamicType::Boolean => match self.u8()? {
+        0 => DynamicValue::Boolean(false),
+        1 => DynamicValue::Boolean(true),
+        b => return Err(Error::BadBoolean(b)),
+      },
+      DynamicType::Byte => DynamicValue::Byte(self.u8()?),
+      DynamicType::Int8 => DynamicValue::Int8(self.u8()? as i8),
+      DynamicType::UInt8 => DynamicValue::UInt8(self.u8()?),
+      DynamicType::Int16 => DynamicValue::Int16(self.read(2, BO::read_i16)?),
+      DynamicType::UInt16 => DynamicValue::UInt16(self.u16()?),
+      DynamicType::Int32 => DynamicValue::Int32(self.read(4, BO::read_i32)?),
+      DynamicType::UInt32 => DynamicValue::UInt32(self.u32()?),
+      DynamicType::Int64 => DynamicValue::Int64(self.read(8, BO::read_i64)?),
+      DynamicType::UInt64 => DynamicValue::UInt64(self.read(8, BO::read_u64)?),
+      DynamicType::Float32 => DynamicValue::Float32(self.read(4, BO::read_f32)?),
+      DynamicType::Float64 => DynamicValue::Float64(self.read(8, BO::read_f64)?),
+      DynamicType::Char8 => DynamicValue::Char8(char::from(self.u8()?)),
+      DynamicType::String { bound } => {
+        let len = self.u32()? as usize;
+        let bytes = self.bytes(len)?;
+        // Length includes the terminating null, but some implementations send
+        // empty strings as length zero.
+        let bytes = match bytes.split_last() {
+          Some((0, s)) => s,
+          Some(_) => return Err(Error::Message("String is not null-terminated".to_string())),
+          None => bytes,
+        };
+        if *bound != 0 && bytes.len() > *bound as usize {
+          return Err(Error::Message(format!("String exceeds bound {}", bound)))
+        }
+        match std::str::from_utf8(bytes) {
+          Ok(s) => DynamicValue::String(s.to_string()),
+          Err(e) => return Err(Error::BadString(e)),
+        }
+      }
+      DynamicType::Sequence { element, bound } => {
+        let end = self.collection_dheader(element)?;
+        // The serde XCDR2 serializer does not know the element type of an
+        // empty sequence, so it writes only the zero length, which is read
+        // here as a zero DHEADER.
+        let len = if end == Some(self.position) { 0 } else { self.u32()? as usize };
+        if *bound != 0 && len > *bound as usize {
+          return Err(Error::Message(format!("Sequence exceeds bound {}", bound)))
+        }
+        let elements = self.decode_elements(element, len)?;
+        if let Some(end) = end {
+          self.skip_to(end)?;
+        }
+        DynamicValue::Sequence(elements)
+      }
+      DynamicType::Array {
+        element,
+        dimensions,
+      } => {
+        let end = self.collection_dheader(element)?;
+        let elements = self.decode_elements(element, element_count(dimensions))?;
+        if let Some(end) = end {
+          self.skip_to(end)?;
+        }
+        DynamicValue::Array(elements)
+      }
+      DynamicType::Enum(e) => DynamicValue::Enum(match enum_size(e) {
+        1 => i32::from(self.u8()? as i8),
+        2 => i32::from(self.read(2, BO::read_i16)?),
+        _ => self.read(4, BO::read_i32)?,
+      }),
+      DynamicType::Struct(s) => DynamicValue::Struct(self.decode_struct(s)?),
+    })
+  }
+
+  fn collection_dheader(&mut self, element: &DynamicType) -> Result<Option<usize>> {
+    if self.version == XcdrVersion::Xcdr2 && !is_primitive(element) {
+      Ok(Some(self.dheader()?))
+    } else {
+      Ok(None)
+    }
+  }
+
+  fn decode_elements(&mut self, element: &DynamicType, count: usize) -> Result<Vec<DynamicValue>> {
+    // Guards against huge allocations on garbage input. Elements of zero
+    // size are not possible in practice.
+    if count > self.input.len() - self.position {
+      return Err(Error::Eof)
+    }
+    let mut elements = Vec::with_capacity(count);
+    for _ in 0..count {
+      elements.push(self.decode_value(element)?);
+    }
+    Ok(elements)
+  }
+
+  fn decode_struct(&mut self, descriptor: &Arc<DynamicStruct>) -> Result<DynamicData> {
+    let values = match (self.version, descriptor.extensibility) {
+      (XcdrVersion::Xcdr1, Extensibility::Mutable) => self.decode_parameter_list(descriptor)?,
+      (XcdrVersion::Xcdr2, Extensibility::Mutable) => self.decode_mutable(descriptor)?,
+      (XcdrVersion::Xcdr2, Extensibility::Appendable) => {
+        let end = self.dheader()?;
+        let values = self.decode_members(descriptor, Some(end))?;
+        self.skip_to(end)?;
+        values
+      }
+      _ => self.decode_members(descriptor, None)?,
+    };
+    Ok(DynamicData::from_values(descriptor.clone(), values))
+  }
+
+  // Members in declaration order. If the writer had an older version of an
+  // appendable type, the missing members at the end get default values.
+  fn decode_members(
+    &mut self,
+    descriptor: &DynamicStruct,
+    end: Option<usize>,
+  ) -> Result<Vec<Option<DynamicValue>>> {
+    let mut values = Vec::with_capacity(descriptor.members.len());
+    for (index, member) in descriptor.members.iter().enumerate() {
+      if end.is_some_and(|end| self.position >= end) {
+        values.push(missing_member(descriptor, index));
+        continue
+      }
+      let value = if !member.optional {
+        Some(self.decode_value(&member.member_type)?)
+      } else {
+        match self.version {
+          XcdrVersion::Xcdr2 => match self.u8()? {
+            0 => None,
+            1 => Some(self.decode_value(&member.member_type)?),
+            b => return Err(Error::BadBoolean(b)),
+          },
+          XcdrVersion::Xcdr1 => {
+            let (_, _, member_end) = self.parameter_header()?.unwrap_or((0, false, self.position));
+            if member_end == self.position {
+              None
+            } else {
+              let value = self.decode_value(&member.member_type)?;
+              self.skip_to(member_end)?;
+              Some(value)
+            }
+          }
+        }
+      };
+      values.push(value);
+    }
+    Ok(values)
+  }
+
+  // XCDR2 mutable structure: DHEADER, then members with EMHEADERs
+  fn decode_mutable(&mut self, descriptor: &DynamicStruct) -> Result<Vec<Option<DynamicValue>>> {
+    let end = self.dheader()?;
+    let mut values: Vec<Option<Option<DynamicValue>>> = vec![None; descriptor.members.len()];
+    while self.position < end {
+      let emheader = self.u32()?;
+      let member_id = emheader & EMHEADER_MEMBER_ID_MASK;
+      let length_code = (emheader >> EMHEADER_LC_SHIFT) & 0x7;
+      let length = match length_code {
+        0..=3 => 1 << length_code,
+        LC_NEXTINT => self.u32()? as usize,
+        _ => {
+          // NEXTINT is also the beginning of the member value.
+          let next_int = BO::read_u32(self.input.get(self.position..self.position + 4).ok_or(Error::Eof)?) as usize;
+          let unit = [1, 4, 8][(length_code - 5) as usize];
+          next_int.checked_mul(unit).and_then(|l| l.checked_add(4)).ok_or(Error::Eof)?
+        }
+      };
+      let member_end = self.end_position(length)?;
+      if member_end > end {
+        return Err(Error::Message("Member overruns its structure".to_string()))
+      }
+      self.decode_member(descriptor, &mut values, member_id, emheader & EMHEADER_M_FLAG != 0, member_end)?;
+    }
+    self.skip_to(end)?;
+    Ok(self.finish_members(descriptor, values))
+  }
+
+  // XCDR1 mutable structure: members with parameter headers, terminated by
+  // PID_LIST_END
+  fn decode_parameter_list(
+    &mut self,
+    descriptor: &DynamicStruct,
+  ) -> Result<Vec<Option<DynamicValue>>> {
+    let mut values: Vec<Option<Option<DynamicValue>>> = vec![None; descriptor.members.len()];
+    while let Some((member_id, must_understand, member_end)) = self.parameter_header()? {
+      self.decode_member(descriptor, &mut values, member_id, must_understand, member_end)?;
+    }
+    Ok(self.finish_members(descriptor, values))
+  }
+
+  // Returns member id, must understand flag and end position of the member,
+  // or None at the end of the parameter list.
+  fn parameter_header(&mut self) -> Result<Option<(u32, bool, usize)>> {
+    self.align(4)?;
+    let pid = self.u16()?;
+    let length = self.u16()? as usize;
+    let must_understand = pid & PID_MUST_UNDERSTAND != 0;
+    match pid & PID_ID_MASK {
+      PID_LIST_END => Ok(None),
+      PID_EXTENDED => {
+        let member_id = self.u32()? & EMHEADER_MEMBER_ID_MASK;
+        let length = self.u32()? as usize;
+        Ok(Some((member_id, must_understand, self.end_position(length)?)))
+      }
+      member_id => Ok(Some((u32::from(member_id), must_understand, self.end_position(length)?))),
+    }
+  }
+
+  fn decode_member(
+    &mut self,
+    descriptor: &DynamicStruct,
+    values: &mut [Option<Option<DynamicValue>>],
+    member_id: u32,
+    must_understand: bool,
+    member_end: usize,
+  ) -> Result<()> {
+    match descriptor.member_by_id(member_id) {
+      Some((index, member)) => {
+        values[index] = Some(Some(self.decode_value(&member.member_type)?));
+      }
+      None if must_understand => {
+        return Err(Error::Message(format!("Unknown must-understand member {}", member_id)))
+      }
+      None => (), // added by a newer version of the type
+    }
+    self.skip_to(member_end)
+  }
+
+  fn finish_members(
+    &self,
+    descriptor: &DynamicStruct,
+    values: Vec<Option<Option<DynamicValue>>>,
+  ) -> Vec<Option<DynamicValue>> {
+    values
+      .into_iter()
+      .enumerate()
+      .map(|(index, v)| v.unwrap_or_else(|| missing_member(descriptor, index)))
+      .collect()
+  }
+}
+
+struct Encoder<BO> {
+  buffer: Vec<u8>,
+  version: XcdrVersion,
+  // Encodes only the key members of structures, or all members of a nested
+  // structure that has no key members.
+  key_only: bool,
+  phantom: PhantomData<BO>,
+}
+
+impl<BO: ByteOrder> Encoder<BO> {
+  fn new(version: XcdrVersion, key_only: bool) -> Encoder<BO> {
+    Encoder {
+      buffer: Vec::with_capacity(64),
+      version,
+      key_only,
+      phantom: PhantomData,
+    }
+  }
+
+  fn align(&mut self, alignment: usize) {
+    let alignment = match self.version {
+      XcdrVersion::Xcdr1 => alignment.min(8),
+      XcdrVersion::Xcdr2 => alignment.min(4),
+    };
+    while !self.buffer.len().is_multiple_of(alignment) {
+      self.buffer.push(0);
+    }
+  }
+
+  fn write(&mut self, size: usize, f: impl FnOnce(&mut [u8])) {
+    self.align(size);
+    let start = self.buffer.len();
+    self.buffer.resize(start + size, 0);
+    f(&mut self.buffer[start..]);
+  }
+
+  fn u16(&mut self, v: u16) {
+    self.write(2, |b| BO::write_u16(b, v))
+  }
+
+  fn u32(&mut self, v: u32) {
+    self.write(4, |b| BO::write_u32(b, v))
+  }
+
+  // Reserves room for a length and returns its position.
+  fn begin_length(&mut self) -> usize {
+    self.u32(0);
+    self.buffer.len() - 4
+  }
+
+  fn end_length(&mut self, position: usize) {
+    let len = (self.buffer.len() - position - 4) as u32;
+    BO::write_u32(&mut self.buffer[position..position + 4], len);
+  }
+
+  fn encode_value(&mut self, value: &DynamicValue, dynamic_type: &DynamicType) -> Result<()> {
+    match (value, dynamic_type) {
+      (DynamicValue::Boolean(v), DynamicType::Boolean) => self.buffer.push(*v as u8),
+      (DynamicValue::Byte(v), DynamicType::Byte) | (DynamicValue::UInt8(v), DynamicType::UInt8) => {
+        self.buffer.push(*v)
+      }
+      (DynamicValue::Int8(v), DynamicType::Int8) => self.buffer.push(*v as u8),
+      (DynamicValue::Int16(v), DynamicType::Int16) => self.write(2, |b| BO::write_i16(b, *v)),
+      (DynamicValue::UInt16(v), DynamicType::UInt16) => self.u16(*v),
+      (DynamicValue::Int32(v), DynamicType::Int32) => self.write(4, |b| BO::write_i32(b, *v)),
+      (DynamicValue::UInt32(v), DynamicType::UInt32) => self.u32(*v),
+      (DynamicValue::Int64(v), DynamicType::Int64) => self.write(8, |b| BO::write_i64(b, *v)),
+      (DynamicValue::UInt64(v), DynamicType::UInt64) => self.write(8, |b| BO::write_u64(b, *v)),
+      (DynamicValue::Float32(v), DynamicType::Float32) => self.write(4, |b| BO::write_f32(b, *v)),
+      (DynamicValue::Float64(v), DynamicType::Float64) => self.write(8, |b| BO::write_f64(b, *v)),
+      (DynamicValue::Char8(c), DynamicType::Char8) if (*c as u32) <= 0xFF => {
+        self.buffer.push(*c as u32 as u8)
+      }
+      (DynamicValue::String(s), DynamicType::String { .. }) => {
+        self.u32(s.len() as u32 + 1);
+        self.buffer.extend_from_slice(s.as_bytes());
+        self.buffer.push(0);
+      }
+      (DynamicValue::Sequence(elements), DynamicType::Sequence { element, .. }) => {
+        let dheader = self.collection_dheader(element);
+        self.u32(elements.len() as u32);
+        for e in elements {
+          self.encode_value(e, element)?;
+        }
+        if let Some(position) = dheader {
+          self.end_length(position);
+        }
+      }
+      (DynamicValue::Array(elements), DynamicType::Array { element, dimensions })
+        if elements.len() == element_count(dimensions) =>
+      {
+        let dheader = self.collection_dheader(element);
+        for e in elements {
+          self.encode_value(e, element)?;
+        }
+        if let Some(position) = dheader {
+          self.end_length(position);
+        }
+      }
+      (DynamicValue::Enum(v), DynamicType::Enum(e)) => match enum_size(e) {
+        1 => self.buffer.push(*v as i8 as u8),
+        2 => self.write(2, |b| BO::write_i16(b, *v as i16)),
+        _ => self.write(4, |b| BO::write_i32(b, *v)),
+      },
+      (DynamicValue::Struct(d), DynamicType::Struct(_)) => self.encode_struct(d)?,
+      (value, dynamic_type) => {
+        return Err(Error::Message(format!(
+          "Value {:?} does not conform to type {:?}",
+          value, dynamic_type
+        )))
+      }
+    }
+    Ok(())
+  }
+
+  fn collection_dheader(&mut self, element: &DynamicType) -> Option<usize> {
+    if self.version == XcdrVersion::Xcdr2 && !is_primitive(element) {
+      Some(self.begin_length())
+    } else {
+      None
+    }
+  }
+
+  fn encode_struct(&mut self, data: &DynamicData) -> Result<()> {
+    if self.key_only {
+      let has_key = data.members().any(|(m, _)| m.key);
+      for (member, value) in data.members() {
+        match value {
+          Some(value) if member.key || !has_key => self.encode_value(value, &member.member_type)?,
+          _ => (),
+        }
+      }
+      return Ok(())
+    }
+    let extensibility = data.dynamic_struct().extensibility;
+    match (self.version, extensibility) {
+      (XcdrVersion::Xcdr1, Extensibility::Mutable) => {
+        for (member, value) in data.members() {
+          if let Some(value) = value {
+            self.encode_parameter(member.id, member.key, true, |e| {
+              e.encode_value(value, &member.member_type)
+            })?;
+          }
+        }
+        self.align(4);
+        self.u16(PID_LIST_END);
+        self.u16(0);
+      }
+      (XcdrVersion::Xcdr2, Extensibility::Mutable) => {
+        let dheader = self.begin_length();
+        for (member, value) in data.members() {
+          if let Some(value) = value {
+            self.encode_emheader_member(member.id, member.key, value, &member.member_type)?;
+          }
+        }
+        self.end_length(dheader);
+      }
+      (version, _) => {
+        let dheader = match (version, extensibility) {
+          (XcdrVersion::Xcdr2, Extensibility::Appendable) => Some(self.begin_length()),
+          _ => None,
+        };
+        for (member, value) in data.members() {
+          match (member.optional, version, value) {
+            (false, _, Some(value)) => self.encode_value(value, &member.member_type)?,
+            (false, _, None) => {
+              return Err(Error::Message("Non-optional member has no value".to_string()))
+            }
+            (true, XcdrVersion::Xcdr2, None) => self.buffer.push(0),
+            (true, XcdrVersion::Xcdr2, Some(value)) => {
+              self.buffer.push(1);
+              self.encode_value(value, &member.member_type)?;
+            }
+            (true, XcdrVersion::Xcdr1, value) => {
+              self.encode_parameter(member.id, false, false, |e| match value {
+                Some(value) => e.encode_value(value, &member.member_type),
+                None => Ok(()),
+              })?;
+            }
+          }
+        }
+        if let Some(position) = dheader {
+          self.end_length(position);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  // XCDR1 parameter with a short header, or an extended one if the member id
+  // or the length does not fit.
+  fn encode_parameter(
+    &mut self,
+    member_id: u32,
+    must_understand: bool,
+    pad: bool,
+    encode: impl FnOnce(&mut Self) -> Result<()>,
+  ) -> Result<()> {
+    self.align(4);
+    let header = self.buffer.len();
+    let flags = if must_understand { PID_MUST_UNDERSTAND } else { 0 };
+    self.u16(0);
+    self.u16(0);
+    encode(self)?;
+    if pad {
+      self.align(4);
+    }
+    let length = self.buffer.len() - header - 4;
+    if member_id < u32::from(PID_EXTENDED) && length <= 0xFFFF {
+      BO::write_u16(&mut self.buffer[header..], member_id as u16 | flags);
+      BO::write_u16(&mut self.buffer[header + 2..], length as u16);
+    } else {
+      // Inserting 8 bytes keeps the value aligned.
+      BO::write_u16(&mut self.buffer[header..], PID_EXTENDED | flags);
+      BO::write_u16(&mut self.buffer[header + 2..], 8);
+      let mut extension = [0; 8];
+      BO::write_u32(&mut extension[0..4], member_id);
+      BO::write_u32(&mut extension[4..8], length as u32);
+      self.buffer.splice(header + 4..header + 4, extension.iter().cloned());
+    }
+    Ok(())
+  }
+
+  // XCDR2 member of a mutable structure. Primitives use the length codes
+  // for fixed sizes, and other members have an explicit NEXTINT length.
+  fn encode_emheader_member(
+    &mut self,
+    member_id: u32,
+    must_understand: bool,
+    value: &DynamicValue,
+    member_type: &DynamicType,
+  ) -> Result<()> {
+    let flag = if must_understand { EMHEADER_M_FLAG } else { 0 };
+    match primitive_size(member_type) {
+      Some(size) => {
+        let length_code = size.trailing_zeros();
+        self.u32(flag | (length_code << EMHEADER_LC_SHIFT) | member_id);
+        self.encode_value(value, member_type)
+      }
+      None => {
+        self.u32(flag | (LC_NEXTINT << EMHEADER_LC_SHIFT) | member_id);
+        let next_int = self.begin_length();
+        self.encode_value(value, member_type)?;
+        self.end_length(next_int);
+        Ok(())
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const IDL: &str = r#"
+    enum Color { RED, GREEN, BLUE };
+    @final struct Point { short x; long long y; };
+    @appendable struct Item { string<8> name; @optional double weight; };
+    @mutable struct Shape {
+      @key long id;
+      Color color;
+      sequence<Point> points;
+      Item items[2];
+      @optional string label;
+      @optional char tag;
+      sequence<octet, 4> raw;
+    };
+  "#;
+
+  fn shape_type() -> Arc<DynamicStruct> {
+    DynamicType::from_idl(IDL, "Shape").unwrap().as_struct().unwrap().clone()
+  }
+
+  fn sample(shape: &Arc<DynamicStruct>) -> DynamicData {
+    let point = |x, y| {
+      let mut p = DynamicData::new(&shape.members[2].member_type.sequence_element()).unwrap();
+      p.set("x", DynamicValue::Int16(x)).unwrap();
+      p.set("y", DynamicValue::Int64(y)).unwrap();
+      DynamicValue::Struct(p)
+    };
+    let mut shape_data = DynamicData::from_struct(shape.clone());
+    shape_data.set("id", DynamicValue::Int32(-7)).unwrap();
+    shape_data.set("color", DynamicValue::Enum(2)).unwrap();
+    shape_data
+      .set("points", DynamicValue::Sequence(vec![point(1, 2), point(-3, 1 << 40)]))
+      .unwrap();
+    let mut items = match shape_data.get("items") {
+      Some(DynamicValue::Array(items)) => items.clone(),
+      other => panic!("{:?}", other),
+    };
+    if let DynamicValue::Struct(item) = &mut items[1] {
+      item.set("name", DynamicValue::String("box".to_string())).unwrap();
+      item.set("weight", DynamicValue::Float64(1.5)).unwrap();
+    }
+    shape_data.set("items", DynamicValue::Array(items)).unwrap();
+    shape_data.set("tag", DynamicValue::Char8('é')).unwrap();
+    shape_data.set("raw", DynamicValue::Sequence(vec![DynamicValue::Byte(9)])).unwrap();
+    shape_data
+  }
+
+  impl DynamicType {
+    fn sequence_element(&self) -> DynamicType {
+      match self {
+        DynamicType::Sequence { element, .. } => (**element).clone(),
+        _ => panic!(),
+      }
+    }
+  }
+
+  #[test]
+  fn roundtrip_all_encodings() {
+    let shape = shape_type();
+    let data = sample(&shape);
+    for version in &[XcdrVersion::Xcdr1, XcdrVersion::Xcdr2] {
+      for big_endian in &[false, true] {
+        let bytes = encode(&data, *version, *big_endian).unwrap();
+        let encoding = representation_identifier(shape.extensibility, *version, *big_endian);
+        assert_eq!(decode(&shape, &bytes, encoding).unwrap(), data, "{:?}", encoding);
+      }
+    }
+  }
+
+  #[test]
+  fn xcdr2_final_layout() {
+    let point = DynamicType::from_idl(IDL, "Point").unwrap();
+    let mut p = DynamicData::new(&point).unwrap();
+    p.set("x", DynamicValue::Int16(1)).unwrap();
+    p.set("y", DynamicValue::Int64(2)).unwrap();
+    // XCDR2 aligns 8-byte values only to 4, XCDR1 to 8.
+    assert_eq!(
+      encode(&p, XcdrVersion::Xcdr2, false).unwrap(),
+      vec![1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(encode(&p, XcdrVersion::Xcdr1, false).unwrap().len(), 16);
+  }
+
+  #[test]
+  fn type_evolution() {
+    // A reader with a shorter appendable type skips the extra member, and a
+    // mutable type skips unknown members.
+    let old_item = DynamicType::from_idl("struct Item { string<8> name; };", "Item").unwrap();
+    let new_item = DynamicType::from_idl(IDL, "Item").unwrap();
+    let mut item = DynamicData::new(&new_item).unwrap();
+    item.set("name", DynamicValue::String("cup".to_string())).unwrap();
+    item.set("weight", DynamicValue::Float64(0.25)).unwrap();
+    let bytes = encode(&item, XcdrVersion::Xcdr2, false).unwrap();
+    let old = decode(old_item.as_struct().unwrap(), &bytes, RepresentationIdentifier::D_CDR_LE).unwrap();
+    assert_eq!(old.get("name").and_then(|v| v.as_str()), Some("cup"));
+
+    let old_shape = DynamicType::from_idl(
+      "enum Color { RED, GREEN, BLUE }; @mutable struct Shape { @id(1) Color color; @key @id(0) long id; };",
+      "Shape",
+    )
+    .unwrap();
+    let shape = shape_type();
+    let bytes = encode(&sample(&shape), XcdrVersion::Xcdr2, true).unwrap();
+    let old = decode(old_shape.as_struct().unwrap(), &bytes, RepresentationIdentifier::PL_CDR2_BE).unwrap();
+    assert_eq!(old.get("id"), Some(&DynamicValue::Int32(-7)));
+    assert_eq!(old.get("color"), Some(&DynamicValue::Enum(2)));
+  }
+
+  #[test]
+  fn key_hashes() {
+    // The key -7 in big-endian, padded to 16 bytes
+    let shape = shape_type();
+    let mut expected = [0; 16];
+    expected[..4].copy_from_slice(&(-7i32).to_be_bytes());
+    assert_eq!(key_hash(&sample(&shape)).unwrap(), u128::from_le_bytes(expected));
+    // Strings have no fixed size, so the key is always digested.
+    let named = DynamicType::from_idl("struct S { @key string name; long x; };", "S").unwrap();
+    let mut data = DynamicData::new(&named).unwrap();
+    data.set("name", DynamicValue::String("a".to_string())).unwrap();
+    let digest = md5::compute([0, 0, 0, 2, b'a', 0]);
+    assert_eq!(key_hash(&data).unwrap(), u128::from_le_bytes(digest.0));
+  }
+
+  #[test]
+  fn serde_codec_agrees() {
+    use serde::{Deserialize, Serialize};
+    use crate::serialization::{cdr2_deserializer::from_bytes, cdr2_serializer::to_bytes};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct Point {
+      x: i16,
+      y: i64,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    enum Color {
+      Red,
+      Green,
+      Blue,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct Polygon {
+      name: String,
+      points: Vec<Point>,
+      corners: [Point; 2],
+      ids: Vec<i32>,
+      tags: Vec<String>,
+      nested: Vec<Vec<Point>>,
+      color: Option<Color>,
+    }
+    let point = |x, y| Point { x, y };
+    let polygon = Polygon {
+      name: "square".to_string(),
+      points: vec![point(1, 2), point(-3, 1 << 40)],
+      corners: [point(0, 0), point(5, 5)],
+      ids: vec![7, 8, 9],
+      tags: vec!["a".to_string(), "bc".to_string()],
+      nested: vec![vec![point(1, 1)], vec![point(2, 2), point(3, 3)]],
+      color: Some(Color::Blue),
+    };
+
+    for (annotation, extensibility, encoding) in &[
+      ("@appendable", Extensibility::Appendable, RepresentationIdentifier::D_CDR_LE),
+      ("@mutable", Extensibility::Mutable, RepresentationIdentifier::PL_CDR2_LE),
+    ] {
+      let idl = format!(
+        "enum Color {{ RED, GREEN, BLUE }};
+         {0} struct Point {{ short x; long long y; }};
+         {0} struct Polygon {{
+           string name; sequence<Point> points; Point corners[2]; sequence<long> ids;
+           sequence<string> tags; sequence<sequence<Point> > nested; @optional Color color;
+         }};",
+        annotation
+      );
+      let polygon_type = DynamicType::from_idl(&idl, "Polygon").unwrap();
+      let polygon_type = polygon_type.as_struct().unwrap();
+
+      let bytes = to_bytes::<_, LittleEndian>(&polygon, *extensibility).unwrap();
+      let data = decode(polygon_type, &bytes, *encoding).unwrap();
+      assert_eq!(data.get("name").and_then(|v| v.as_str()), Some("square"));
+      assert_eq!(data.get("points").and_then(|v| v.as_slice()).map(|p| p.len()), Some(2));
+      assert_eq!(data.get("color"), Some(&DynamicValue::Enum(2)));
+      assert_eq!(encode(&data, XcdrVersion::Xcdr2, false).unwrap(), bytes, "{:?}", encoding);
+      assert_eq!(from_bytes::<Polygon, LittleEndian>(&bytes, *extensibility).unwrap(), polygon);
+
+      // An empty sequence of structs has a DHEADER only when the type is known.
+      let empty = Polygon { points: vec![], nested: vec![vec![]], ..polygon.clone() };
+      let bytes = to_bytes::<_, LittleEndian>(&empty, *extensibility).unwrap();
+      let data = decode(polygon_type, &bytes, *encoding).unwrap();
+      assert_eq!(data.get("points").and_then(|v| v.as_slice()).map(|p| p.len()), Some(0));
+      let bytes = encode(&data, XcdrVersion::Xcdr2, false).unwrap();
+      assert_eq!(from_bytes::<Polygon, LittleEndian>(&bytes, *extensibility).unwrap(), empty);
+    }
+  }
+
+  #[test]
+  fn bad_input() {
+    let shape = shape_type();
+    let bytes = encode(&sample(&shape), XcdrVersion::Xcdr2, false).unwrap();
+    for len in 0..bytes.len() {
+      assert!(decode(&shape, &bytes[..len], RepresentationIdentifier::PL_CDR2_LE).is_err());
+    }
+    let raw_bound_exceeded = DynamicType::from_idl("struct S { sequence<octet, 1> raw; };", "S").unwrap();
+    let bytes = [8, 0, 0, 0, 2, 0, 0, 0, 1, 2];
+    assert!(decode(raw_bound_exceeded.as_struct().unwrap(), &bytes, RepresentationIdentifier::D_CDR_LE).is_err());
+  }
+}
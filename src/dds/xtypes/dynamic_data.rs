@@ -0,0 +1,281 @@
+use std::sync::Arc;
+
+use crate::dds::values::result::{Error, Result};
+
+use super::dynamic_type::{DynamicMember, DynamicStruct, DynamicType};
+
+/// Value of a [DynamicType](enum.DynamicType.html)
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynamicValue {
+  Boolean(bool),
+  Byte(u8),
+  Int8(i8),
+  Int16(i16),
+  Int32(i32),
+  Int64(i64),
+  UInt8(u8),
+  UInt16(u16),
+  UInt32(u32),
+  UInt64(u64),
+  Float32(f32),
+  Float64(f64),
+  /// IDL `char` is a single octet, so only characters up to U+00FF fit.
+  Char8(char),
+  String(String),
+  /// Value of an enumerated type
+  Enum(i32),
+  Sequence(Vec<DynamicValue>),
+  /// All elements of an array, also a multidimensional one, in row-major order
+  Array(Vec<DynamicValue>),
+  Struct(DynamicData),
+}
+
+impl DynamicValue {
+  /// Default value of a type: zero, empty, the default enum literal or a
+  /// structure of default values.
+  pub fn default_of(dynamic_type: &DynamicType) -> DynamicValue {
+    match dynamic_type {
+      DynamicType::Boolean => DynamicValue::Boolean(false),
+      DynamicType::Byte => DynamicValue::Byte(0),
+      DynamicType::Int8 => DynamicValue::Int8(0),
+      DynamicType::Int16 => DynamicValue::Int16(0),
+      DynamicType::Int32 => DynamicValue::Int32(0),
+      DynamicType::Int64 => DynamicValue::Int64(0),
+      DynamicType::UInt8 => DynamicValue::UInt8(0),
+      DynamicType::UInt16 => DynamicValue::UInt16(0),
+      DynamicType::UInt32 => DynamicValue::UInt32(0),
+      DynamicType::UInt64 => DynamicValue::UInt64(0),
+      DynamicType::Float32 => DynamicValue::Float32(0.0),
+      DynamicType::Float64 => DynamicValue::Float64(0.0),
+      DynamicType::Char8 => DynamicValue::Char8('\0'),
+      DynamicType::String { .. } => DynamicValue::String(String::new()),
+      DynamicType::Sequence { .. } => DynamicValue::Sequence(Vec::new()),
+      DynamicType::Array {
+        element,
+        dimensions,
+      } => {
+        let count = dimensions.iter().product::<u32>() as usize;
+        DynamicValue::Array(vec![DynamicValue::default_of(element); count])
+      }
+      DynamicType::Enum(e) => DynamicValue::Enum(e.default_value()),
+      DynamicType::Struct(s) => DynamicValue::Struct(DynamicData::from_struct(s.clone())),
+    }
+  }
+
+  /// Checks that this value is of the given type, including string and
+  /// sequence bounds, array lengths and enum literals.
+  pub fn conforms_to(&self, dynamic_type: &DynamicType) -> bool {
+    match (self, dynamic_type) {
+      (DynamicValue::Boolean(_), DynamicType::Boolean)
+      | (DynamicValue::Byte(_), DynamicType::Byte)
+      | (DynamicValue::Int8(_), DynamicType::Int8)
+      | (DynamicValue::Int16(_), DynamicType::Int16)
+      | (DynamicValue::Int32(_), DynamicType::Int32)
+      | (DynamicValue::Int64(_), DynamicType::Int64)
+      | (DynamicValue::UInt8(_), DynamicType::UInt8)
+      | (DynamicValue::UInt16(_), DynamicType::UInt16)
+      | (DynamicValue::UInt32(_), DynamicType::UInt32)
+      | (DynamicValue::UInt64(_), DynamicType::UInt64)
+      | (DynamicValue::Float32(_), DynamicType::Float32)
+      | (DynamicValue::Float64(_), DynamicType::Float64) => true,
+      (DynamicValue::Char8(c), DynamicType::Char8) => (*c as u32) <= 0xFF,
+      (DynamicValue::String(s), DynamicType::String { bound }) => {
+        *bound == 0 || s.len() <= *bound as usize
+      }
+      (DynamicValue::Sequence(v), DynamicType::Sequence { element, bound }) => {
+        (*bound == 0 || v.len() <= *bound as usize) && v.iter().all(|e| e.conforms_to(element))
+      }
+      (DynamicValue::Array(v), DynamicType::Array { element, dimensions }) => {
+        v.len() == dimensions.iter().product::<u32>() as usize
+          && v.iter().all(|e| e.conforms_to(element))
+      }
+      (DynamicValue::Enum(value), DynamicType::Enum(e)) => e.literal_by_value(*value).is_some(),
+      (DynamicValue::Struct(d), DynamicType::Struct(s)) => {
+        (Arc::ptr_eq(&d.descriptor, s) || *d.descriptor == **s) && d.is_valid()
+      }
+      _ => false,
+    }
+  }
+
+  pub fn as_bool(&self) -> Option<bool> {
+    match self {
+      DynamicValue::Boolean(b) => Some(*b),
+      _ => None,
+    }
+  }
+
+  /// Any signed or unsigned integer, or enum value, that fits in `i64`
+  pub fn as_i64(&self) -> Option<i64> {
+    match *self {
+      DynamicValue::Byte(v) | DynamicValue::UInt8(v) => Some(i64::from(v)),
+      DynamicValue::Int8(v) => Some(i64::from(v)),
+      DynamicValue::Int16(v) => Some(i64::from(v)),
+      DynamicValue::Int32(v) | DynamicValue::Enum(v) => Some(i64::from(v)),
+      DynamicValue::Int64(v) => Some(v),
+      DynamicValue::UInt16(v) => Some(i64::from(v)),
+      DynamicValue::UInt32(v) => Some(i64::from(v)),
+      DynamicValue::UInt64(v) if v <= i64::MAX as u64 => Some(v as i64),
+      _ => None,
+    }
+  }
+
+  /// Any number, converted to `f64`
+  pub fn as_f64(&self) -> Option<f64> {
+    match *self {
+      DynamicValue::Float32(v) => Some(f64::from(v)),
+      DynamicValue::Float64(v) => Some(v),
+      DynamicValue::UInt64(v) => Some(v as f64),
+      _ => self.as_i64().map(|v| v as f64),
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      DynamicValue::String(s) => Some(s),
+      _ => None,
+    }
+  }
+
+  /// Elements of a sequence or an array
+  pub fn as_slice(&self) -> Option<&[DynamicValue]> {
+    match self {
+      DynamicValue::Sequence(v) | DynamicValue::Array(v) => Some(v),
+      _ => None,
+    }
+  }
+
+  pub fn as_struct(&self) -> Option<&DynamicData> {
+    match self {
+      DynamicValue::Struct(d) => Some(d),
+      _ => None,
+    }
+  }
+}
+
+/// Sample of a structure type that is known only at run time. Members are
+/// accessed by name or by member id.
+///
+/// Absent optional members have no value.
+///
+/// # Examples
+///
+/// ```
+/// use rustdds::dds::xtypes::{DynamicData, DynamicType, DynamicValue};
+///
+/// let point_type = DynamicType::from_idl("struct Point { long x; long y; };", "Point").unwrap();
+/// let mut point = DynamicData::new(&point_type).unwrap();
+/// point.set("x", DynamicValue::Int32(3)).unwrap();
+/// assert_eq!(point.get("x").and_then(|v| v.as_i64()), Some(3));
+/// assert!(point.set("x", DynamicValue::Float64(3.0)).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicData {
+  descriptor: Arc<DynamicStruct>,
+  values: Vec<Option<DynamicValue>>, // same order as descriptor.members
+}
+
+impl DynamicData {
+  /// New sample with default values. The type must be a structure.
+  pub fn new(dynamic_type: &DynamicType) -> Result<DynamicData> {
+    match dynamic_type {
+      DynamicType::Struct(s) => Ok(DynamicData::from_struct(s.clone())),
+      _ => Error::bad_parameter("DynamicData must be of a structure type."),
+    }
+  }
+
+  pub(crate) fn from_struct(descriptor: Arc<DynamicStruct>) -> DynamicData {
+    let values = descriptor
+      .members
+      .iter()
+      .map(|m| {
+        if m.optional {
+          None
+        } else {
+          Some(DynamicValue::default_of(&m.member_type))
+        }
+      })
+      .collect();
+    DynamicData { descriptor, values }
+  }
+
+  // Values must be in member order, and must conform to member types.
+  pub(crate) fn from_values(
+    descriptor: Arc<DynamicStruct>,
+    values: Vec<Option<DynamicValue>>,
+  ) -> DynamicData {
+    DynamicData { descriptor, values }
+  }
+
+  pub fn dynamic_struct(&self) -> &Arc<DynamicStruct> {
+    &self.descriptor
+  }
+
+  /// Value of the named member. None if there is no such member, or if it
+  /// is an absent optional member.
+  pub fn get(&self, name: &str) -> Option<&DynamicValue> {
+    let (index, _) = self.descriptor.member(name)?;
+    self.values[index].as_ref()
+  }
+
+  pub fn get_by_id(&self, id: u32) -> Option<&DynamicValue> {
+    let (index, _) = self.descriptor.member_by_id(id)?;
+    self.values[index].as_ref()
+  }
+
+  /// Sets the named member. The value must be of the member type.
+  pub fn set(&mut self, name: &str, value: DynamicValue) -> Result<()> {
+    match self.descriptor.member(name) {
+      Some((index, _)) => self.set_index(index, Some(value)),
+      None => Error::bad_parameter(&format!("No member named {}.", name)),
+    }
+  }
+
+  pub fn set_by_id(&mut self, id: u32, value: DynamicValue) -> Result<()> {
+    match self.descriptor.member_by_id(id) {
+      Some((index, _)) => self.set_index(index, Some(value)),
+      None => Error::bad_parameter(&format!("No member with id {}.", id)),
+    }
+  }
+
+  /// Makes an optional member absent.
+  pub fn clear(&mut self, name: &str) -> Result<()> {
+    match self.descriptor.member(name) {
+      Some((index, _)) => self.set_index(index, None),
+      None => Error::bad_parameter(&format!("No member named {}.", name)),
+    }
+  }
+
+  fn set_index(&mut self, index: usize, value: Option<DynamicValue>) -> Result<()> {
+    let member = &self.descriptor.members[index];
+    match &value {
+      None if !member.optional => return Error::bad_parameter("Member is not optional."),
+      Some(v) if !v.conforms_to(&member.member_type) => {
+        return Error::bad_parameter(&format!(
+          "Value {:?} does not conform to type {:?}.",
+          v, member.member_type
+        ))
+      }
+      _ => (),
+    }
+    self.values[index] = value;
+    Ok(())
+  }
+
+  /// Members and their values, in declaration order
+  pub fn members(&self) -> impl Iterator<Item = (&DynamicMember, Option<&DynamicValue>)> {
+    self
+      .descriptor
+      .members
+      .iter()
+      .zip(self.values.iter().map(|v| v.as_ref()))
+  }
+
+  pub(crate) fn is_valid(&self) -> bool {
+    self
+      .members()
+      .all(|(m, v)| match v {
+        None => m.optional,
+        Some(v) => v.conforms_to(&m.member_type),
+      })
+  }
+}
@@ -0,0 +1,290 @@
+use std::{io, sync::Arc};
+
+use log::warn;
+use mio::{Evented, Poll, PollOpt, Ready, Token};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  dds::{
+    data_types::ReadCondition,
+    no_key::datasample::DataSample,
+    qos::{HasQoSPolicy, QosPolicies},
+    sampleinfo::SampleInfo,
+    topic::Topic,
+    traits::{
+      key::Keyed,
+      serde_adapters::{DeserializerAdapter, SerializerAdapter},
+    },
+    values::result::{Error, Result},
+    with_key::{datareader::DataReader, datawriter::DataWriter},
+  },
+  messages::submessages::submessage_elements::serialized_payload::{
+    RepresentationIdentifier, SerializedPayload,
+  },
+  serialization,
+  structure::{entity::RTPSEntity, guid::GUID, time::Timestamp},
+};
+
+use super::{
+  dynamic_cdr::{self, XcdrVersion},
+  dynamic_data::DynamicData,
+  dynamic_type::{DynamicStruct, DynamicType},
+};
+
+// Serialized sample as it was received. The serde traits are implemented only
+// to satisfy the bounds of the typed DataReader and DataWriter, which the
+// dynamic endpoints wrap. Decoding happens in the dynamic endpoints.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EncodedSample {
+  representation: [u8; 2],
+  bytes: Vec<u8>,
+}
+
+// The dynamic endpoints do not track instances.
+impl Keyed for EncodedSample {
+  type K = ();
+  fn get_key(&self) {}
+}
+
+pub(crate) struct EncodedSampleAdapter {}
+
+impl DeserializerAdapter<EncodedSample> for EncodedSampleAdapter {
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    dynamic_cdr::SUPPORTED_ENCODINGS
+  }
+
+  fn from_bytes(
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> serialization::error::Result<EncodedSample> {
+    Ok(EncodedSample {
+      representation: encoding.bytes,
+      bytes: input_bytes.to_vec(),
+    })
+  }
+}
+
+impl SerializerAdapter<EncodedSample> for EncodedSampleAdapter {
+  // The actual representation depends on the type and on the DataRepresentation
+  // QoS. DynamicDataWriter sets the QoS itself.
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::CDR2_LE
+  }
+
+  fn to_writer<W: io::Write>(mut writer: W, value: &EncodedSample) -> serialization::error::Result<()> {
+    writer.write_all(&value.bytes)?;
+    Ok(())
+  }
+}
+
+/// DataReader for samples of a type that is known only at run time
+///
+/// Samples can be written in any of the XCDR1 and XCDR2 representations. The
+/// writer type does not have to be the same as the reader type, as long as the
+/// data can be decoded as the reader type: members of mutable types are matched
+/// by member id and missing members get default values.
+///
+/// Instances are not tracked, so samples of all instances are in the same
+/// history, and disposed or unregistered instances are not reported. Samples
+/// that cannot be decoded are skipped with a warning.
+///
+/// Created with
+/// [`Subscriber::create_dynamic_datareader`](../struct.Subscriber.html#method.create_dynamic_datareader).
+pub struct DynamicDataReader {
+  reader: DataReader<EncodedSample, EncodedSampleAdapter>,
+  dynamic_type: DynamicType,
+  descriptor: Arc<DynamicStruct>,
+}
+
+impl DynamicDataReader {
+  pub(crate) fn new(
+    reader: DataReader<EncodedSample, EncodedSampleAdapter>,
+    dynamic_type: DynamicType,
+    descriptor: Arc<DynamicStruct>,
+  ) -> DynamicDataReader {
+    DynamicDataReader {
+      reader,
+      dynamic_type,
+      descriptor,
+    }
+  }
+
+  pub fn dynamic_type(&self) -> &DynamicType {
+    &self.dynamic_type
+  }
+
+  /// Reads samples like
+  /// [`DataReader::read`](../struct.With_Key_DataReader.html#method.read). The
+  /// samples are decoded again on each read.
+  pub fn read(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+  ) -> Result<Vec<DataSample<DynamicData>>> {
+    let samples = self.reader.read(max_samples, read_condition)?;
+    let descriptor = &self.descriptor;
+    Ok(
+      samples
+        .into_iter()
+        .filter_map(|s| match s.value {
+          Ok(encoded) => decode_sample(descriptor, s.sample_info, encoded),
+          Err(()) => None,
+        })
+        .collect(),
+    )
+  }
+
+  /// Takes samples like
+  /// [`DataReader::take`](../struct.With_Key_DataReader.html#method.take)
+  pub fn take(
+    &mut self,
+    max_samples: usize,
+    read_condition: ReadCondition,
+  ) -> Result<Vec<DataSample<DynamicData>>> {
+    let samples = self.reader.take(max_samples, read_condition)?;
+    let descriptor = &self.descriptor;
+    Ok(
+      samples
+        .into_iter()
+        .filter_map(|s| match s.value {
+          Ok(encoded) => decode_sample(descriptor, s.sample_info, &encoded),
+          Err(()) => None,
+        })
+        .collect(),
+    )
+  }
+
+  pub fn read_next_sample(&mut self) -> Result<Option<DataSample<DynamicData>>> {
+    let mut ds = self.read(1, ReadCondition::not_read())?;
+    Ok(ds.pop())
+  }
+
+  pub fn take_next_sample(&mut self) -> Result<Option<DataSample<DynamicData>>> {
+    let mut ds = self.take(1, ReadCondition::not_read())?;
+    Ok(ds.pop())
+  }
+}
+
+fn decode_sample(
+  descriptor: &Arc<DynamicStruct>,
+  sample_info: SampleInfo,
+  encoded: &EncodedSample,
+) -> Option<DataSample<DynamicData>> {
+  let encoding = RepresentationIdentifier {
+    bytes: encoded.representation,
+  };
+  match dynamic_cdr::decode(descriptor, &encoded.bytes, encoding) {
+    Ok(value) => Some(DataSample { sample_info, value }),
+    Err(e) => {
+      warn!("Cannot decode sample as {:?}: {:?}", descriptor.name, e);
+      None
+    }
+  }
+}
+
+// Polling delegates to the wrapped DataReader, like in no_key::DataReader.
+impl Evented for DynamicDataReader {
+  fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+    self
+      .reader
+      .notification_receiver
+      .register(poll, token, interest, opts)
+  }
+
+  fn reregister(
+    &self,
+    poll: &Poll,
+    token: Token,
+    interest: Ready,
+    opts: PollOpt,
+  ) -> io::Result<()> {
+    self
+      .reader
+      .notification_receiver
+      .reregister(poll, token, interest, opts)
+  }
+
+  fn deregister(&self, poll: &Poll) -> io::Result<()> {
+    self.reader.notification_receiver.deregister(poll)
+  }
+}
+
+impl HasQoSPolicy for DynamicDataReader {
+  fn get_qos(&self) -> QosPolicies {
+    self.reader.get_qos()
+  }
+}
+
+impl RTPSEntity for DynamicDataReader {
+  fn get_guid(&self) -> GUID {
+    self.reader.get_guid()
+  }
+}
+
+/// DataWriter for samples of a type that is known only at run time
+///
+/// Samples are written in XCDR2, or in XCDR1 if that is the first
+/// representation in the DataRepresentation QoS. The exact representation
+/// follows the extensibility of the type.
+///
+/// Created with
+/// [`Publisher::create_dynamic_datawriter`](../struct.Publisher.html#method.create_dynamic_datawriter).
+pub struct DynamicDataWriter {
+  writer: DataWriter<EncodedSample, EncodedSampleAdapter>,
+  dynamic_type: DynamicType,
+  descriptor: Arc<DynamicStruct>,
+  version: XcdrVersion,
+}
+
+impl DynamicDataWriter {
+  pub(crate) fn new(
+    writer: DataWriter<EncodedSample, EncodedSampleAdapter>,
+    dynamic_type: DynamicType,
+    descriptor: Arc<DynamicStruct>,
+    version: XcdrVersion,
+  ) -> DynamicDataWriter {
+    DynamicDataWriter {
+      writer,
+      dynamic_type,
+      descriptor,
+      version,
+    }
+  }
+
+  pub fn dynamic_type(&self) -> &DynamicType {
+    &self.dynamic_type
+  }
+
+  /// Writes a sample. It must be of the type of this DataWriter.
+  pub fn write(&self, data: &DynamicData, source_timestamp: Option<Timestamp>) -> Result<()> {
+    if **data.dynamic_struct() != *self.descriptor || !data.is_valid() {
+      return Error::bad_parameter("DynamicData is not of the type of the DataWriter.")
+    }
+    let bytes = dynamic_cdr::encode(data, self.version, false)?;
+    let representation =
+      dynamic_cdr::representation_identifier(self.descriptor.extensibility, self.version, false);
+    let key_hash = dynamic_cdr::key_hash(data)?;
+    self.writer.write_serialized(
+      SerializedPayload::new(representation, bytes),
+      key_hash,
+      None,
+      source_timestamp,
+    )
+  }
+
+  pub fn get_topic(&self) -> &Topic {
+    self.writer.get_topic()
+  }
+}
+
+impl HasQoSPolicy for DynamicDataWriter {
+  fn get_qos(&self) -> QosPolicies {
+    self.writer.get_qos()
+  }
+}
+
+impl RTPSEntity for DynamicDataWriter {
+  fn get_guid(&self) -> GUID {
+    self.writer.get_guid()
+  }
+}
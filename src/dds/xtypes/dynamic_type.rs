@@ -0,0 +1,387 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+  dds::values::result::{Error, Result},
+  serialization::cdr2_serializer::Extensibility,
+};
+
+use super::{
+  idl,
+  type_object::{
+    name_hash, EnumLiteral, EnumType, EquivalenceHash, NameHash, StructMember, StructType,
+    TypeIdentifier, TypeInformation, TypeObject,
+  },
+  type_support::TypeObjects,
+};
+
+// Type graphs received from the network may be cyclic or absurdly deep.
+const MAX_DEPTH: usize = 64;
+
+/// Type of [DynamicData](struct.DynamicData.html), known only at run time.
+///
+/// A DynamicType is built from an IDL definition with
+/// [`from_idl`](#method.from_idl), or from the
+/// [TypeInformation](struct.TypeInformation.html) that a remote endpoint
+/// advertises in discovery with
+/// [`from_type_information`](#method.from_type_information).
+/// Aliases are resolved when the type is built.
+///
+/// String and sequence bounds of zero mean unbounded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynamicType {
+  Boolean,
+  Byte,
+  Int8,
+  Int16,
+  Int32,
+  Int64,
+  UInt8,
+  UInt16,
+  UInt32,
+  UInt64,
+  Float32,
+  Float64,
+  Char8,
+  String { bound: u32 },
+  Sequence { element: Box<DynamicType>, bound: u32 },
+  /// Multidimensional arrays have several dimensions. Their elements are
+  /// stored in row-major order in a single `DynamicValue::Array`.
+  Array { element: Box<DynamicType>, dimensions: Vec<u32> },
+  Enum(Arc<DynamicEnum>),
+  Struct(Arc<DynamicStruct>),
+}
+
+/// Member of a [DynamicStruct](struct.DynamicStruct.html)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicMember {
+  /// Minimal TypeObjects carry only the hash of the name, so the name is
+  /// not known for types that come from discovery.
+  pub name: Option<String>,
+  pub name_hash: NameHash,
+  pub id: u32,
+  pub member_type: DynamicType,
+  pub key: bool,
+  pub optional: bool,
+}
+
+/// Structure type. Members inherited from a base type come first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicStruct {
+  pub name: Option<String>,
+  pub extensibility: Extensibility,
+  pub members: Vec<DynamicMember>,
+}
+
+/// Literal of a [DynamicEnum](struct.DynamicEnum.html)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicEnumLiteral {
+  pub name: Option<String>,
+  pub name_hash: NameHash,
+  pub value: i32,
+  pub default: bool,
+}
+
+/// Enumerated type
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicEnum {
+  pub name: Option<String>,
+  pub extensibility: Extensibility,
+  pub bit_bound: u16,
+  pub literals: Vec<DynamicEnumLiteral>,
+}
+
+// Names are compared directly if known, and otherwise by hash.
+fn name_matches(name: &Option<String>, hash: &NameHash, wanted: &str) -> bool {
+  match name {
+    Some(n) => n == wanted,
+    None => *hash == name_hash(wanted),
+  }
+}
+
+impl DynamicStruct {
+  /// Finds a member by name. Returns the member and its index.
+  pub fn member(&self, name: &str) -> Option<(usize, &DynamicMember)> {
+    self
+      .members
+      .iter()
+      .enumerate()
+      .find(|(_, m)| name_matches(&m.name, &m.name_hash, name))
+  }
+
+  /// Finds a member by member id. Returns the member and its index.
+  pub fn member_by_id(&self, id: u32) -> Option<(usize, &DynamicMember)> {
+    self.members.iter().enumerate().find(|(_, m)| m.id == id)
+  }
+}
+
+impl DynamicEnum {
+  pub fn literal(&self, name: &str) -> Option<&DynamicEnumLiteral> {
+    self
+      .literals
+      .iter()
+      .find(|l| name_matches(&l.name, &l.name_hash, name))
+  }
+
+  pub fn literal_by_value(&self, value: i32) -> Option<&DynamicEnumLiteral> {
+    self.literals.iter().find(|l| l.value == value)
+  }
+
+  /// Value of the default literal, which is the first one unless marked
+  /// otherwise.
+  pub fn default_value(&self) -> i32 {
+    self
+      .literals
+      .iter()
+      .find(|l| l.default)
+      .or_else(|| self.literals.first())
+      .map(|l| l.value)
+      .unwrap_or(0)
+  }
+}
+
+impl DynamicType {
+  /// Type `type_name` defined in the given IDL. The name may be scoped, as
+  /// in `"geometry::Point"`.
+  ///
+  /// Structures, enumerations, typedefs, modules and constants are
+  /// supported, as well as the annotations `@key`, `@optional`, `@id`,
+  /// `@autoid`, `@final`, `@appendable`, `@mutable`, `@extensibility`,
+  /// `@bit_bound`, `@value` and `@default_literal`. Other annotations and
+  /// preprocessor directives are ignored.
+  pub fn from_idl(idl: &str, type_name: &str) -> Result<DynamicType> {
+    let types = idl::parse(idl)?;
+    let type_name = type_name.trim_start_matches("::");
+    match types.get(type_name) {
+      Some(t) => Ok(t.clone()),
+      None => Error::bad_parameter(&format!("Type {} is not defined in the IDL.", type_name)),
+    }
+  }
+
+  /// Type described by XTypes type information, e.g. that of a remote
+  /// DataWriter. All referenced TypeObjects must be included.
+  pub fn from_type_information(type_information: &TypeInformation) -> Result<DynamicType> {
+    Resolver {
+      type_information,
+      resolved: HashMap::new(),
+    }
+    .resolve(type_information.type_identifier(), 0)
+  }
+
+  /// XTypes type information describing this type, for advertising it in
+  /// discovery, see [TypeDesc](../data_types/struct.TypeDesc.html).
+  pub fn type_information(&self) -> TypeInformation {
+    let mut type_objects = TypeObjects::new();
+    let type_identifier = self.type_identifier(&mut type_objects);
+    type_objects.into_type_information(type_identifier)
+  }
+
+  /// The structure descriptor, if this is a structure type.
+  pub fn as_struct(&self) -> Option<&Arc<DynamicStruct>> {
+    match self {
+      DynamicType::Struct(s) => Some(s),
+      _ => None,
+    }
+  }
+
+  fn type_identifier(&self, type_objects: &mut TypeObjects) -> TypeIdentifier {
+    match self {
+      DynamicType::Boolean => TypeIdentifier::Boolean,
+      DynamicType::Byte => TypeIdentifier::Byte,
+      DynamicType::Int8 => TypeIdentifier::Int8,
+      DynamicType::Int16 => TypeIdentifier::Int16,
+      DynamicType::Int32 => TypeIdentifier::Int32,
+      DynamicType::Int64 => TypeIdentifier::Int64,
+      DynamicType::UInt8 => TypeIdentifier::UInt8,
+      DynamicType::UInt16 => TypeIdentifier::UInt16,
+      DynamicType::UInt32 => TypeIdentifier::UInt32,
+      DynamicType::UInt64 => TypeIdentifier::UInt64,
+      DynamicType::Float32 => TypeIdentifier::Float32,
+      DynamicType::Float64 => TypeIdentifier::Float64,
+      DynamicType::Char8 => TypeIdentifier::Char8,
+      DynamicType::String { bound } => TypeIdentifier::String8 { bound: *bound },
+      DynamicType::Sequence { element, bound } => TypeIdentifier::Sequence {
+        element: Box::new(element.type_identifier(type_objects)),
+        bound: *bound,
+      },
+      DynamicType::Array {
+        element,
+        dimensions,
+      } => TypeIdentifier::Array {
+        element: Box::new(element.type_identifier(type_objects)),
+        dimensions: dimensions.clone(),
+      },
+      DynamicType::Enum(e) => type_objects.add(TypeObject::Enum(EnumType {
+        extensibility: e.extensibility,
+        bit_bound: e.bit_bound,
+        literals: e
+          .literals
+          .iter()
+          .map(|l| EnumLiteral {
+            value: l.value,
+            name_hash: l.name_hash,
+            default: l.default,
+          })
+          .collect(),
+      })),
+      DynamicType::Struct(s) => {
+        let members = s
+          .members
+          .iter()
+          .map(|m| StructMember {
+            member_id: m.id,
+            member_type: m.member_type.type_identifier(type_objects),
+            name_hash: m.name_hash,
+            optional: m.optional,
+            must_understand: m.key,
+            key: m.key,
+          })
+          .collect();
+        type_objects.add(TypeObject::Struct(StructType {
+          extensibility: s.extensibility,
+          base_type: None,
+          members,
+        }))
+      }
+    }
+  }
+}
+
+struct Resolver<'a> {
+  type_information: &'a TypeInformation,
+  // Shared subtypes are converted only once.
+  resolved: HashMap<EquivalenceHash, DynamicType>,
+}
+
+impl<'a> Resolver<'a> {
+  fn resolve(&mut self, type_identifier: &TypeIdentifier, depth: usize) -> Result<DynamicType> {
+    if depth > MAX_DEPTH {
+      return Error::bad_parameter("Type is nested too deeply or recursive.")
+    }
+    Ok(match type_identifier {
+      TypeIdentifier::Boolean => DynamicType::Boolean,
+      TypeIdentifier::Byte => DynamicType::Byte,
+      TypeIdentifier::Int8 => DynamicType::Int8,
+      TypeIdentifier::Int16 => DynamicType::Int16,
+      TypeIdentifier::Int32 => DynamicType::Int32,
+      TypeIdentifier::Int64 => DynamicType::Int64,
+      TypeIdentifier::UInt8 => DynamicType::UInt8,
+      TypeIdentifier::UInt16 => DynamicType::UInt16,
+      TypeIdentifier::UInt32 => DynamicType::UInt32,
+      TypeIdentifier::UInt64 => DynamicType::UInt64,
+      TypeIdentifier::Float32 => DynamicType::Float32,
+      TypeIdentifier::Float64 => DynamicType::Float64,
+      TypeIdentifier::Char8 => DynamicType::Char8,
+      TypeIdentifier::String8 { bound } => DynamicType::String { bound: *bound },
+      TypeIdentifier::Sequence { element, bound } => DynamicType::Sequence {
+        element: Box::new(self.resolve(element, depth + 1)?),
+        bound: *bound,
+      },
+      TypeIdentifier::Array {
+        element,
+        dimensions,
+      } => DynamicType::Array {
+        element: Box::new(self.resolve(element, depth + 1)?),
+        dimensions: dimensions.clone(),
+      },
+      TypeIdentifier::Float128 | TypeIdentifier::Char16 | TypeIdentifier::String16 { .. } => {
+        return Error::bad_parameter(&format!(
+          "{:?} is not supported by DynamicType.",
+          type_identifier
+        ))
+      }
+      TypeIdentifier::EquivalenceHashMinimal(hash) => {
+        if let Some(t) = self.resolved.get(hash) {
+          return Ok(t.clone())
+        }
+        let resolved = match self.type_information.type_object(type_identifier) {
+          None => {
+            return Error::bad_parameter(&format!("TypeObject {:02x?} is missing.", hash))
+          }
+          Some(TypeObject::Alias { related_type }) => self.resolve(related_type, depth + 1)?,
+          Some(TypeObject::Enum(e)) => DynamicType::Enum(Arc::new(DynamicEnum {
+            name: None,
+            extensibility: e.extensibility,
+            bit_bound: e.bit_bound,
+            literals: e
+              .literals
+              .iter()
+              .map(|l| DynamicEnumLiteral {
+                name: None,
+                name_hash: l.name_hash,
+                value: l.value,
+                default: l.default,
+              })
+              .collect(),
+          })),
+          Some(TypeObject::Struct(s)) => {
+            let mut members = match &s.base_type {
+              None => Vec::new(),
+              Some(base) => match self.resolve(base, depth + 1)? {
+                DynamicType::Struct(b) => b.members.clone(),
+                _ => return Error::bad_parameter("Base type of a structure must be a structure."),
+              },
+            };
+            for m in &s.members {
+              members.push(DynamicMember {
+                name: None,
+                name_hash: m.name_hash,
+                id: m.member_id,
+                member_type: self.resolve(&m.member_type, depth + 1)?,
+                key: m.key,
+                optional: m.optional,
+              });
+            }
+            DynamicType::Struct(Arc::new(DynamicStruct {
+              name: None,
+              extensibility: s.extensibility,
+              members,
+            }))
+          }
+        };
+        self.resolved.insert(*hash, resolved.clone());
+        resolved
+      }
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn type_information_roundtrip() {
+    let idl = r#"
+      module geometry {
+        enum Color { RED, @value(5) GREEN };
+        @final struct Point { long x; long y; };
+        typedef sequence<Point, 10> Polyline;
+        struct Shape {
+          @key string<32> name;
+          Color color;
+          Polyline points;
+          @optional double size[2][3];
+        };
+      };
+    "#;
+    let t = DynamicType::from_idl(idl, "::geometry::Shape").unwrap();
+    let shape = t.as_struct().unwrap();
+    assert_eq!(shape.name.as_deref(), Some("geometry::Shape"));
+    assert_eq!(shape.members.len(), 4);
+    assert!(shape.members[0].key);
+    assert_eq!(shape.members[3].id, 3);
+
+    // Names are lost, but can still be used for lookup through the hash.
+    let from_discovery = DynamicType::from_type_information(&t.type_information()).unwrap();
+    let shape2 = from_discovery.as_struct().unwrap();
+    assert_eq!(shape2.name, None);
+    let (index, points) = shape2.member("points").unwrap();
+    assert_eq!(index, 2);
+    assert!(matches!(points.member_type, DynamicType::Sequence { bound: 10, .. }));
+    assert!(shape2.member("no_such_member").is_none());
+    match &shape2.members[1].member_type {
+      DynamicType::Enum(e) => assert_eq!(e.literal("GREEN").unwrap().value, 5),
+      other => panic!("{:?}", other),
+    }
+  }
+}
@@ -0,0 +1,698 @@
+// Parser for the subset of OMG IDL 4 that describes data types. It produces
+// DynamicTypes for all structures, enumerations and typedefs, indexed by their
+// fully scoped names, e.g. "geometry::Point".
+//
+// IDL requires declaration before use, so names are resolved while parsing.
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+  dds::values::result::{Error, Result},
+  serialization::cdr2_serializer::Extensibility,
+};
+
+use super::{
+  dynamic_type::{DynamicEnum, DynamicEnumLiteral, DynamicMember, DynamicStruct, DynamicType},
+  type_object::name_hash,
+};
+
+pub(crate) fn parse(idl: &str) -> Result<HashMap<String, DynamicType>> {
+  let mut parser = Parser {
+    tokens: tokenize(idl)?,
+    pos: 0,
+    scope: Vec::new(),
+    types: HashMap::new(),
+    constants: HashMap::new(),
+  };
+  while !parser.at_end() {
+    parser.definition()?;
+  }
+  Ok(parser.types)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+  Ident(String),
+  Number(String),
+  Literal(String), // string or character literal
+  Punct(&'static str),
+}
+
+const PUNCTUATION: &[&str] = &[
+  "::", "{", "}", "(", ")", "<", ">", "[", "]", ";", ",", ":", "=", "@", "+", "-",
+  "*", "/", "%", "|", "&", "^", "~",
+];
+
+fn tokenize(idl: &str) -> Result<Vec<(Token, usize)>> {
+  let mut tokens = Vec::new();
+  let chars: Vec<char> = idl.chars().collect();
+  let mut i = 0;
+  let mut line = 1;
+  let mut line_start = true;
+  while i < chars.len() {
+    let c = chars[i];
+    if c == '\n' {
+      line += 1;
+      line_start = true;
+      i += 1;
+    } else if c.is_whitespace() {
+      i += 1;
+    } else if c == '#' && line_start {
+      // Preprocessor directive, e.g. #include or #pragma
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+    } else if chars[i..].starts_with(&['/', '/']) {
+      while i < chars.len() && chars[i] != '\n' {
+        i += 1;
+      }
+    } else if chars[i..].starts_with(&['/', '*']) {
+      i += 2;
+      while i < chars.len() && !chars[i..].starts_with(&['*', '/']) {
+        if chars[i] == '\n' {
+          line += 1;
+        }
+        i += 1;
+      }
+      i += 2;
+    } else {
+      line_start = false;
+      let start = i;
+      if c.is_alphabetic() || c == '_' {
+        while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+          i += 1;
+        }
+        tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+      } else if c.is_ascii_digit() || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit()) {
+        while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+          i += 1;
+        }
+        tokens.push((Token::Number(chars[start..i].iter().collect()), line));
+      } else if c == '"' || c == '\'' {
+        i += 1;
+        while i < chars.len() && chars[i] != c {
+          if chars[i] == '\\' {
+            i += 1;
+          }
+          i += 1;
+        }
+        if i >= chars.len() {
+          return Error::bad_parameter(&format!("IDL line {}: unterminated literal", line))
+        }
+        i += 1;
+        tokens.push((Token::Literal(chars[start + 1..i - 1].iter().collect()), line));
+      } else {
+        let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+        match PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+          Some(p) => {
+            i += p.len();
+            tokens.push((Token::Punct(*p), line));
+          }
+          None => {
+            return Error::bad_parameter(&format!("IDL line {}: unexpected character {:?}", line, c))
+          }
+        }
+      }
+    }
+  }
+  Ok(tokens)
+}
+
+// Annotations that affect the type representation
+#[derive(Default)]
+struct Annotations {
+  key: bool,
+  optional: bool,
+  id: Option<u32>,
+  hash_id: Option<bool>,
+  extensibility: Option<Extensibility>,
+  bit_bound: Option<u16>,
+  value: Option<i32>,
+  default_literal: bool,
+}
+
+struct Parser {
+  tokens: Vec<(Token, usize)>,
+  pos: usize,
+  scope: Vec<String>,
+  types: HashMap<String, DynamicType>,
+  constants: HashMap<String, i64>,
+}
+
+impl Parser {
+  fn at_end(&self) -> bool {
+    self.pos >= self.tokens.len()
+  }
+
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos).map(|(t, _)| t)
+  }
+
+  fn error<T>(&self, message: &str) -> Result<T> {
+    let line = self
+      .tokens
+      .get(self.pos)
+      .or_else(|| self.tokens.last())
+      .map(|(_, l)| *l)
+      .unwrap_or(0);
+    Error::bad_parameter(&format!("IDL line {}: {}", line, message))
+  }
+
+  fn next(&mut self) -> Result<Token> {
+    match self.tokens.get(self.pos) {
+      Some((t, _)) => {
+        self.pos += 1;
+        Ok(t.clone())
+      }
+      None => self.error("unexpected end of input"),
+    }
+  }
+
+  fn is_punct(&self, p: &str) -> bool {
+    matches!(self.peek(), Some(Token::Punct(q)) if *q == p)
+  }
+
+  fn is_keyword(&self, k: &str) -> bool {
+    matches!(self.peek(), Some(Token::Ident(i)) if i == k)
+  }
+
+  fn accept_punct(&mut self, p: &str) -> bool {
+    let found = self.is_punct(p);
+    if found {
+      self.pos += 1;
+    }
+    found
+  }
+
+  fn accept_keyword(&mut self, k: &str) -> bool {
+    let found = self.is_keyword(k);
+    if found {
+      self.pos += 1;
+    }
+    found
+  }
+
+  fn expect_punct(&mut self, p: &str) -> Result<()> {
+    if self.accept_punct(p) {
+      Ok(())
+    } else {
+      self.error(&format!("expected \"{}\"", p))
+    }
+  }
+
+  fn identifier(&mut self) -> Result<String> {
+    match self.next()? {
+      // Leading underscore escapes IDL keywords.
+      Token::Ident(i) => Ok(i.strip_prefix('_').map(String::from).unwrap_or(i)),
+      other => {
+        self.pos -= 1;
+        self.error(&format!("expected identifier, found {:?}", other))
+      }
+    }
+  }
+
+  fn scoped_name(&mut self) -> Result<String> {
+    let mut name = String::new();
+    if self.accept_punct("::") {
+      name.push_str("::");
+    }
+    name.push_str(&self.identifier()?);
+    while self.accept_punct("::") {
+      name.push_str("::");
+      name.push_str(&self.identifier()?);
+    }
+    Ok(name)
+  }
+
+  fn qualified(&self, name: &str) -> String {
+    let mut scoped = self.scope.join("::");
+    if !scoped.is_empty() {
+      scoped.push_str("::");
+    }
+    scoped.push_str(name);
+    scoped
+  }
+
+  // Relative names are looked up from the innermost scope outwards.
+  fn lookup<'a, T>(&self, table: &'a HashMap<String, T>, name: &str) -> Option<&'a T> {
+    if let Some(absolute) = name.strip_prefix("::") {
+      return table.get(absolute)
+    }
+    (0..=self.scope.len()).rev().find_map(|depth| {
+      let mut scoped = self.scope[..depth].join("::");
+      if !scoped.is_empty() {
+        scoped.push_str("::");
+      }
+      scoped.push_str(name);
+      table.get(&scoped)
+    })
+  }
+
+  fn define(&mut self, name: &str, dynamic_type: DynamicType) -> Result<()> {
+    let scoped = self.qualified(name);
+    if self.types.insert(scoped, dynamic_type).is_some() {
+      return self.error(&format!("{} is defined twice", name))
+    }
+    Ok(())
+  }
+
+  fn definition(&mut self) -> Result<()> {
+    let annotations = self.annotations()?;
+    if self.accept_keyword("module") {
+      let name = self.identifier()?;
+      self.expect_punct("{")?;
+      self.scope.push(name);
+      while !self.accept_punct("}") {
+        self.definition()?;
+      }
+      self.scope.pop();
+    } else if self.accept_keyword("struct") {
+      self.struct_definition(annotations)?;
+    } else if self.accept_keyword("enum") {
+      self.enum_definition(annotations)?;
+    } else if self.accept_keyword("typedef") {
+      let base = self.type_spec()?;
+      for (name, dynamic_type) in self.declarators(&base)? {
+        self.define(&name, dynamic_type)?;
+      }
+    } else if self.accept_keyword("const") {
+      let const_type = self.type_spec()?;
+      let name = self.identifier()?;
+      self.expect_punct("=")?;
+      match const_type {
+        DynamicType::Boolean
+        | DynamicType::Char8
+        | DynamicType::Float32
+        | DynamicType::Float64
+        | DynamicType::String { .. } => {
+          // Only integer constants can be used in type definitions.
+          while !self.is_punct(";") {
+            self.next()?;
+          }
+        }
+        _ => {
+          let value = self.const_expr()?;
+          let scoped = self.qualified(&name);
+          self.constants.insert(scoped, value);
+        }
+      }
+    } else if self.is_keyword("union") || self.is_keyword("bitmask") || self.is_keyword("bitset") {
+      return self.error("unions, bitmasks and bitsets are not supported")
+    } else {
+      return self.error("expected a definition")
+    }
+    self.expect_punct(";")
+  }
+
+  fn annotations(&mut self) -> Result<Annotations> {
+    let mut a = Annotations::default();
+    while self.accept_punct("@") {
+      let name = self.scoped_name()?;
+      let mut args = Vec::new();
+      if self.accept_punct("(") {
+        let mut depth = 0;
+        loop {
+          let t = self.next()?;
+          match &t {
+            Token::Punct("(") => depth += 1,
+            Token::Punct(")") if depth == 0 => break,
+            Token::Punct(")") => depth -= 1,
+            _ => (),
+          }
+          args.push(t);
+        }
+      }
+      let arg_number = |args: &[Token]| -> Option<i64> {
+        match args {
+          [Token::Number(n)] => parse_integer(n),
+          [Token::Punct("-"), Token::Number(n)] => parse_integer(n).map(|v| -v),
+          _ => None,
+        }
+      };
+      let arg_ident = |args: &[Token]| -> Option<String> {
+        match args {
+          [Token::Ident(i)] => Some(i.to_uppercase()),
+          _ => None,
+        }
+      };
+      let flag = |args: &[Token]| !matches!(arg_ident(args).as_deref(), Some("FALSE"));
+      match name.trim_start_matches("::") {
+        "key" => a.key = flag(&args),
+        "optional" => a.optional = flag(&args),
+        "default_literal" => a.default_literal = true,
+        "final" => a.extensibility = Some(Extensibility::Final),
+        "appendable" => a.extensibility = Some(Extensibility::Appendable),
+        "mutable" => a.extensibility = Some(Extensibility::Mutable),
+        "extensibility" => {
+          a.extensibility = match arg_ident(&args).as_deref() {
+            Some("FINAL") => Some(Extensibility::Final),
+            Some("APPENDABLE") => Some(Extensibility::Appendable),
+            Some("MUTABLE") => Some(Extensibility::Mutable),
+            _ => return self.error("bad @extensibility"),
+          }
+        }
+        "id" => match arg_number(&args) {
+          Some(id) if (0..=0x0FFF_FFFF).contains(&id) => a.id = Some(id as u32),
+          _ => return self.error("bad @id"),
+        },
+        "autoid" => a.hash_id = Some(arg_ident(&args).as_deref() == Some("HASH")),
+        "bit_bound" => match arg_number(&args) {
+          Some(b) if (1..=32).contains(&b) => a.bit_bound = Some(b as u16),
+          _ => return self.error("bad @bit_bound"),
+        },
+        "value" => match arg_number(&args) {
+          Some(v) => a.value = Some(v as i32),
+          _ => return self.error("bad @value"),
+        },
+        _ => (), // e.g. @topic, @nested, @verbatim
+      }
+    }
+    Ok(a)
+  }
+
+  fn struct_definition(&mut self, annotations: Annotations) -> Result<()> {
+    let name = self.identifier()?;
+    if self.is_punct(";") {
+      return Ok(()) // forward declaration
+    }
+    let mut members = Vec::new();
+    if self.accept_punct(":") {
+      let base = self.scoped_name()?;
+      match self.lookup(&self.types, &base) {
+        Some(DynamicType::Struct(s)) => members = s.members.clone(),
+        _ => return self.error(&format!("{} is not a structure", base)),
+      }
+    }
+    // Member ids continue after those of the base type.
+    let mut next_id = members.iter().map(|m| m.id + 1).max().unwrap_or(0);
+    self.expect_punct("{")?;
+    while !self.accept_punct("}") {
+      let member_annotations = self.annotations()?;
+      let member_type = self.type_spec()?;
+      for (member_name, member_type) in self.declarators(&member_type)? {
+        let name_hash = name_hash(&member_name);
+        let id = match (member_annotations.id, annotations.hash_id) {
+          (Some(id), _) => id,
+          (None, Some(true)) => u32::from_le_bytes(name_hash) & 0x0FFF_FFFF,
+          _ => next_id,
+        };
+        if members.iter().any(|m: &DynamicMember| m.id == id) {
+          return self.error(&format!("duplicate member id {}", id))
+        }
+        next_id = id + 1;
+        members.push(DynamicMember {
+          name: Some(member_name),
+          name_hash,
+          id,
+          member_type,
+          key: member_annotations.key,
+          optional: member_annotations.optional,
+        });
+      }
+      self.expect_punct(";")?;
+    }
+    let dynamic_type = DynamicType::Struct(Arc::new(DynamicStruct {
+      name: Some(self.qualified(&name)),
+      extensibility: annotations.extensibility.unwrap_or(Extensibility::Appendable),
+      members,
+    }));
+    self.define(&name, dynamic_type)
+  }
+
+  fn enum_definition(&mut self, annotations: Annotations) -> Result<()> {
+    let name = self.identifier()?;
+    self.expect_punct("{")?;
+    let mut literals: Vec<DynamicEnumLiteral> = Vec::new();
+    let mut next_value = 0;
+    loop {
+      let literal_annotations = self.annotations()?;
+      let literal_name = self.identifier()?;
+      let value = literal_annotations.value.unwrap_or(next_value);
+      next_value = value + 1;
+      // Enumerators are constants in the enclosing scope.
+      let scoped = self.qualified(&literal_name);
+      self.constants.insert(scoped, i64::from(value));
+      literals.push(DynamicEnumLiteral {
+        name_hash: name_hash(&literal_name),
+        name: Some(literal_name),
+        value,
+        default: literal_annotations.default_literal,
+      });
+      if !self.accept_punct(",") {
+        break
+      }
+    }
+    self.expect_punct("}")?;
+    let dynamic_type = DynamicType::Enum(Arc::new(DynamicEnum {
+      name: Some(self.qualified(&name)),
+      extensibility: annotations.extensibility.unwrap_or(Extensibility::Appendable),
+      bit_bound: annotations.bit_bound.unwrap_or(32),
+      literals,
+    }));
+    self.define(&name, dynamic_type)
+  }
+
+  // One or more comma-separated names, each possibly with array dimensions
+  fn declarators(&mut self, base: &DynamicType) -> Result<Vec<(String, DynamicType)>> {
+    let mut declarators = Vec::new();
+    loop {
+      let name = self.identifier()?;
+      let mut dimensions = Vec::new();
+      while self.accept_punct("[") {
+        dimensions.push(self.positive_int()?);
+        self.expect_punct("]")?;
+      }
+      let dynamic_type = if dimensions.is_empty() {
+        base.clone()
+      } else {
+        DynamicType::Array {
+          element: Box::new(base.clone()),
+          dimensions,
+        }
+      };
+      declarators.push((name, dynamic_type));
+      if !self.accept_punct(",") {
+        return Ok(declarators)
+      }
+    }
+  }
+
+  fn type_spec(&mut self) -> Result<DynamicType> {
+    let first = match self.peek() {
+      Some(Token::Ident(i)) => i.clone(),
+      Some(Token::Punct("::")) => return self.named_type(),
+      _ => return self.error("expected a type"),
+    };
+    let t = match first.as_str() {
+      "boolean" => DynamicType::Boolean,
+      "octet" => DynamicType::Byte,
+      "char" => DynamicType::Char8,
+      "int8" => DynamicType::Int8,
+      "uint8" => DynamicType::UInt8,
+      "short" | "int16" => DynamicType::Int16,
+      "int32" => DynamicType::Int32,
+      "int64" => DynamicType::Int64,
+      "uint16" => DynamicType::UInt16,
+      "uint32" => DynamicType::UInt32,
+      "uint64" => DynamicType::UInt64,
+      "float" => DynamicType::Float32,
+      "double" => DynamicType::Float64,
+      "long" => {
+        self.pos += 1;
+        if self.is_keyword("double") {
+          return self.error("long double is not supported")
+        }
+        return Ok(if self.accept_keyword("long") {
+          DynamicType::Int64
+        } else {
+          DynamicType::Int32
+        })
+      }
+      "unsigned" => {
+        self.pos += 1;
+        return if self.accept_keyword("short") {
+          Ok(DynamicType::UInt16)
+        } else if self.accept_keyword("long") {
+          Ok(if self.accept_keyword("long") {
+            DynamicType::UInt64
+          } else {
+            DynamicType::UInt32
+          })
+        } else {
+          self.error("expected short or long")
+        }
+      }
+      "string" => {
+        self.pos += 1;
+        let bound = if self.accept_punct("<") {
+          let b = self.positive_int()?;
+          self.expect_punct(">")?;
+          b
+        } else {
+          0
+        };
+        return Ok(DynamicType::String { bound })
+      }
+      "sequence" => {
+        self.pos += 1;
+        self.expect_punct("<")?;
+        let element = self.type_spec()?;
+        let bound = if self.accept_punct(",") {
+          self.positive_int()?
+        } else {
+          0
+        };
+        self.expect_punct(">")?;
+        return Ok(DynamicType::Sequence {
+          element: Box::new(element),
+          bound,
+        })
+      }
+      "wchar" | "wstring" | "any" | "fixed" | "map" => {
+        return self.error(&format!("{} is not supported", first))
+      }
+      _ => return self.named_type(),
+    };
+    self.pos += 1;
+    Ok(t)
+  }
+
+  fn named_type(&mut self) -> Result<DynamicType> {
+    let name = self.scoped_name()?;
+    match self.lookup(&self.types, &name) {
+      Some(t) => Ok(t.clone()),
+      None => self.error(&format!("unknown type {}", name)),
+    }
+  }
+
+  fn positive_int(&mut self) -> Result<u32> {
+    match self.const_expr()? {
+      v if v > 0 && v <= i64::from(u32::MAX) => Ok(v as u32),
+      v => self.error(&format!("expected a positive integer, got {}", v)),
+    }
+  }
+
+  // Integer constant expressions: literals, constants and + - * / ( )
+  fn const_expr(&mut self) -> Result<i64> {
+    let mut value = self.const_term()?;
+    loop {
+      if self.accept_punct("+") {
+        value = value.wrapping_add(self.const_term()?);
+      } else if self.accept_punct("-") {
+        value = value.wrapping_sub(self.const_term()?);
+      } else {
+        return Ok(value)
+      }
+    }
+  }
+
+  fn const_term(&mut self) -> Result<i64> {
+    let mut value = self.const_factor()?;
+    loop {
+      if self.accept_punct("*") {
+        value = value.wrapping_mul(self.const_factor()?);
+      } else if self.accept_punct("/") {
+        match self.const_factor()? {
+          0 => return self.error("division by zero"),
+          d => value /= d,
+        }
+      } else {
+        return Ok(value)
+      }
+    }
+  }
+
+  fn const_factor(&mut self) -> Result<i64> {
+    if self.accept_punct("-") {
+      return Ok(-self.const_factor()?)
+    }
+    if self.accept_punct("(") {
+      let value = self.const_expr()?;
+      self.expect_punct(")")?;
+      return Ok(value)
+    }
+    match self.peek().cloned() {
+      Some(Token::Number(n)) => {
+        self.pos += 1;
+        match parse_integer(&n) {
+          Some(v) => Ok(v),
+          None => self.error(&format!("bad integer {}", n)),
+        }
+      }
+      Some(Token::Ident(_)) | Some(Token::Punct("::")) => {
+        let name = self.scoped_name()?;
+        match self.lookup(&self.constants, &name) {
+          Some(v) => Ok(*v),
+          None => self.error(&format!("unknown constant {}", name)),
+        }
+      }
+      _ => self.error("expected an integer"),
+    }
+  }
+}
+
+fn parse_integer(s: &str) -> Option<i64> {
+  if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+    i64::from_str_radix(hex, 16).ok()
+  } else if s.len() > 1 && s.starts_with('0') {
+    i64::from_str_radix(&s[1..], 8).ok()
+  } else {
+    s.parse().ok()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_idl() {
+    let idl = r#"
+      #include "other.idl"
+      /* Shapes */
+      module shapes {
+        const long MAX_POINTS = 2 * 8;
+        @appendable struct Base { @key long id; };
+        @mutable
+        struct Shape : Base {
+          @id(10) string<MAX_POINTS> color; // comment
+          unsigned long long x, y;
+          sequence<shapes::Base, MAX_POINTS - 1> parts;
+          @optional short grid[2][3];
+        };
+        module inner { typedef ::shapes::Shape Alias; };
+      };
+    "#;
+    let types = parse(idl).unwrap();
+    assert_eq!(types.len(), 3);
+    let shape = types["shapes::Shape"].as_struct().unwrap().clone();
+    assert_eq!(types["shapes::inner::Alias"], DynamicType::Struct(shape.clone()));
+    assert_eq!(shape.extensibility, Extensibility::Mutable);
+    let ids: Vec<u32> = shape.members.iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec![0, 10, 11, 12, 13, 14]);
+    assert!(shape.members[0].key);
+    assert_eq!(shape.members[1].member_type, DynamicType::String { bound: 16 });
+    assert_eq!(shape.members[3].member_type, DynamicType::UInt64);
+    match &shape.members[4].member_type {
+      DynamicType::Sequence { bound: 15, .. } => (),
+      other => panic!("{:?}", other),
+    }
+    assert_eq!(
+      shape.members[5].member_type,
+      DynamicType::Array {
+        element: Box::new(DynamicType::Int16),
+        dimensions: vec![2, 3]
+      }
+    );
+    assert!(shape.members[5].optional);
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert!(parse("struct A { Unknown x; };").is_err());
+    assert!(parse("struct A { long x; }").is_err());
+    assert!(parse("union U switch (long) { case 1: long x; };").is_err());
+    assert!(parse("struct A { long x[0]; };").is_err());
+    assert!(parse("struct A { @id(1) long x; @id(1) long y; };").is_err());
+  }
+}
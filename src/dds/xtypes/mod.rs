@@ -0,0 +1,43 @@
+//! XTypes 1.3 type representation and type-consistency checking.
+//!
+//! Topic types are described by [TypeIdentifier](enum.TypeIdentifier.html)s
+//! and minimal [TypeObject](enum.TypeObject.html)s. These are advertised in
+//! discovery, and a remote reader or writer is matched only if the reader type
+//! is assignable from the writer type according to the
+//! [TypeConsistencyEnforcement](../qos/policy/struct.TypeConsistencyEnforcement.html)
+//! QoS of the reader.
+//!
+//! To use this, implement [TypeSupport](trait.TypeSupport.html) for the topic
+//! data type, usually with `#[derive(TypeSupport)]`, and create the Topic with
+//! [`TypeDesc::of`](../data_types/struct.TypeDesc.html#method.of).
+//!
+//! Types that are known only at run time are described by
+//! [DynamicType](enum.DynamicType.html), parsed from IDL or built from the
+//! TypeInformation of a discovered endpoint. Their samples are
+//! [DynamicData](struct.DynamicData.html), read and written with
+//! [DynamicDataReader](struct.DynamicDataReader.html) and
+//! [DynamicDataWriter](struct.DynamicDataWriter.html).
+
+mod assignability;
+mod dynamic_cdr;
+mod dynamic_data;
+mod dynamic_endpoints;
+mod dynamic_type;
+mod idl;
+mod type_object;
+mod type_support;
+
+pub(crate) use assignability::types_consistent;
+pub(crate) use dynamic_cdr::XcdrVersion;
+pub(crate) use dynamic_endpoints::{EncodedSample, EncodedSampleAdapter};
+pub use dynamic_data::{DynamicData, DynamicValue};
+pub use dynamic_endpoints::{DynamicDataReader, DynamicDataWriter};
+pub use dynamic_type::{DynamicEnum, DynamicEnumLiteral, DynamicMember, DynamicStruct, DynamicType};
+pub use type_object::{
+  name_hash, EnumLiteral, EnumType, EquivalenceHash, NameHash, StructMember, StructType,
+  TypeIdentifier, TypeInformation, TypeObject,
+};
+pub use type_support::{TypeObjects, TypeSupport};
+pub use rustdds_derive::TypeSupport;
+
+pub use crate::serialization::Extensibility;
@@ -0,0 +1,869 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::serialization::{cdr2_serializer::Extensibility, error::{Error, Result}};
+
+// Type kinds of XTypes 7.3.4.9, used as discriminators of TypeIdentifier and TypeObject
+const TK_NONE: u8 = 0x00;
+const TK_BOOLEAN: u8 = 0x01;
+const TK_BYTE: u8 = 0x02;
+const TK_INT16: u8 = 0x03;
+const TK_INT32: u8 = 0x04;
+const TK_INT64: u8 = 0x05;
+const TK_UINT16: u8 = 0x06;
+const TK_UINT32: u8 = 0x07;
+const TK_UINT64: u8 = 0x08;
+const TK_FLOAT32: u8 = 0x09;
+const TK_FLOAT64: u8 = 0x0A;
+const TK_FLOAT128: u8 = 0x0B;
+const TK_INT8: u8 = 0x0C;
+const TK_UINT8: u8 = 0x0D;
+const TK_CHAR8: u8 = 0x10;
+const TK_CHAR16: u8 = 0x11;
+const TK_ALIAS: u8 = 0x30;
+const TK_ENUM: u8 = 0x40;
+const TK_STRUCTURE: u8 = 0x51;
+
+const TI_STRING8_SMALL: u8 = 0x70;
+const TI_STRING8_LARGE: u8 = 0x71;
+const TI_STRING16_SMALL: u8 = 0x72;
+const TI_STRING16_LARGE: u8 = 0x73;
+const TI_PLAIN_SEQUENCE_SMALL: u8 = 0x80;
+const TI_PLAIN_SEQUENCE_LARGE: u8 = 0x81;
+const TI_PLAIN_ARRAY_SMALL: u8 = 0x90;
+const TI_PLAIN_ARRAY_LARGE: u8 = 0x91;
+
+const EK_MINIMAL: u8 = 0xF1;
+const EK_BOTH: u8 = 0xF3;
+
+// TypeFlags of XTypes 7.3.4.5
+const IS_FINAL: u16 = 1 << 0;
+const IS_APPENDABLE: u16 = 1 << 1;
+const IS_MUTABLE: u16 = 1 << 2;
+
+// MemberFlags of XTypes 7.3.4.5
+const IS_OPTIONAL: u16 = 1 << 3;
+const IS_MUST_UNDERSTAND: u16 = 1 << 4;
+const IS_KEY: u16 = 1 << 5;
+const IS_DEFAULT: u16 = 1 << 6;
+
+/// Hash of the minimal TypeObject of a type, XTypes 7.3.4.2
+pub type EquivalenceHash = [u8; 14];
+
+/// Hash of a member name, XTypes 7.3.4.6.3
+pub type NameHash = [u8; 4];
+
+/// First four bytes of the MD5 of the member name.
+pub fn name_hash(name: &str) -> NameHash {
+  let digest = md5::compute(name.as_bytes());
+  [digest[0], digest[1], digest[2], digest[3]]
+}
+
+/// XTypes 7.3.4.2 TypeIdentifier
+///
+/// Primitive, string and plain collection types are described fully by
+/// their identifier. Structures, enumerations and aliases are identified by
+/// the hash of their minimal [TypeObject](enum.TypeObject.html).
+///
+/// String and sequence bounds of zero mean unbounded.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypeIdentifier {
+  Boolean,
+  Byte,
+  Int8,
+  Int16,
+  Int32,
+  Int64,
+  UInt8,
+  UInt16,
+  UInt32,
+  UInt64,
+  Float32,
+  Float64,
+  Float128,
+  Char8,
+  Char16,
+  String8 { bound: u32 },
+  String16 { bound: u32 },
+  Sequence { element: Box<TypeIdentifier>, bound: u32 },
+  Array { element: Box<TypeIdentifier>, dimensions: Vec<u32> },
+  EquivalenceHashMinimal(EquivalenceHash),
+}
+
+/// Member of a structure type, XTypes 7.3.4.6.3
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StructMember {
+  pub member_id: u32,
+  pub member_type: TypeIdentifier,
+  pub name_hash: NameHash,
+  pub optional: bool,
+  pub must_understand: bool,
+  pub key: bool,
+}
+
+/// XTypes 7.3.4.6.4 MinimalStructType
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StructType {
+  pub extensibility: Extensibility,
+  pub base_type: Option<TypeIdentifier>,
+  pub members: Vec<StructMember>,
+}
+
+/// Literal of an enumerated type, XTypes 7.3.4.8.1
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnumLiteral {
+  pub value: i32,
+  pub name_hash: NameHash,
+  pub default: bool,
+}
+
+/// XTypes 7.3.4.8.2 MinimalEnumeratedType
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnumType {
+  pub extensibility: Extensibility,
+  pub bit_bound: u16,
+  pub literals: Vec<EnumLiteral>,
+}
+
+/// XTypes 7.3.4.3 TypeObject, minimal representation only.
+///
+/// Unions, bitmasks, bitsets and annotations are not supported.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TypeObject {
+  Alias { related_type: TypeIdentifier },
+  Struct(StructType),
+  Enum(EnumType),
+}
+
+impl TypeObject {
+  /// XTypes 7.3.4.2: first 14 bytes of the MD5 of the little-endian XCDR2
+  /// serialization of the TypeObject.
+  pub fn equivalence_hash(&self) -> EquivalenceHash {
+    let digest = md5::compute(self.to_bytes());
+    let mut hash = [0; 14];
+    hash.copy_from_slice(&digest[..14]);
+    hash
+  }
+
+  pub fn type_identifier(&self) -> TypeIdentifier {
+    TypeIdentifier::EquivalenceHashMinimal(self.equivalence_hash())
+  }
+
+  /// Little-endian XCDR2 serialization of the TypeObject
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut w = XcdrWriter::<LittleEndian>::new();
+    w.type_object(self);
+    w.buffer
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<TypeObject> {
+    XcdrReader::<LittleEndian>::new(bytes).type_object()
+  }
+}
+
+/// Type of a Topic, as advertised in discovery.
+///
+/// Contains the identifier of the type and the TypeObjects of it and all the
+/// types it depends on, so that remote types can be checked for
+/// assignability without the TypeLookup service.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TypeInformation {
+  type_identifier: TypeIdentifier,
+  type_objects: BTreeMap<EquivalenceHash, TypeObject>,
+}
+
+impl TypeInformation {
+  pub fn new(
+    type_identifier: TypeIdentifier,
+    type_objects: Vec<TypeObject>,
+  ) -> TypeInformation {
+    TypeInformation {
+      type_identifier,
+      type_objects: type_objects
+        .into_iter()
+        .map(|to| (to.equivalence_hash(), to))
+        .collect(),
+    }
+  }
+
+  pub fn type_identifier(&self) -> &TypeIdentifier {
+    &self.type_identifier
+  }
+
+  pub fn type_objects(&self) -> impl Iterator<Item = &TypeObject> {
+    self.type_objects.values()
+  }
+
+  /// Finds the TypeObject a hashed identifier refers to.
+  /// Returns None for fully descriptive identifiers and unknown hashes.
+  pub fn type_object(&self, type_identifier: &TypeIdentifier) -> Option<&TypeObject> {
+    match type_identifier {
+      TypeIdentifier::EquivalenceHashMinimal(hash) => self.type_objects.get(hash),
+      _ => None,
+    }
+  }
+
+  // Serialized body of PID_TYPE_INFORMATION, XTypes 7.6.3.2.2.
+  // Only the minimal TypeIdentifiers are filled in.
+  pub(crate) fn to_pid_bytes<BO: ByteOrder>(&self) -> Vec<u8> {
+    let mut w = XcdrWriter::<BO>::new();
+    let dheader = w.begin_dheader();
+
+    // @id(0x1001) minimal
+    let member = w.begin_member(0x1001);
+    let deps: Vec<(TypeIdentifier, u32)> = self
+      .type_objects
+      .iter()
+      .map(|(hash, to)| (TypeIdentifier::EquivalenceHashMinimal(*hash), to.to_bytes().len() as u32))
+      .filter(|(ti, _)| *ti != self.type_identifier)
+      .collect();
+    let top_size = self
+      .type_object(&self.type_identifier)
+      .map(|to| to.to_bytes().len() as u32)
+      .unwrap_or(0);
+    w.type_identifier_with_dependencies(Some(&self.type_identifier), top_size, &deps);
+    w.end_member(member);
+
+    // @id(0x1002) complete. We do not generate complete TypeObjects.
+    let member = w.begin_member(0x1002);
+    w.type_identifier_with_dependencies(None, 0, &[]);
+    w.end_member(member);
+
+    w.end_dheader(dheader);
+    w.buffer
+  }
+
+  // Serialized sequence<TypeObject>. This travels in a vendor specific parameter
+  // next to PID_TYPE_INFORMATION.
+  pub(crate) fn type_objects_to_pid_bytes<BO: ByteOrder>(&self) -> Vec<u8> {
+    let mut w = XcdrWriter::<BO>::new();
+    let dheader = w.begin_dheader();
+    w.u32(self.type_objects.len() as u32);
+    for to in self.type_objects.values() {
+      w.type_object(to);
+    }
+    w.end_dheader(dheader);
+    w.buffer
+  }
+
+  pub(crate) fn from_pid_bytes(
+    type_information: &[u8],
+    type_objects: Option<&[u8]>,
+    big_endian: bool,
+  ) -> Result<TypeInformation> {
+    let type_identifier = if big_endian {
+      XcdrReader::<BigEndian>::new(type_information).type_information()?
+    } else {
+      XcdrReader::<LittleEndian>::new(type_information).type_information()?
+    };
+    let type_objects = match type_objects {
+      Some(bytes) if big_endian => XcdrReader::<BigEndian>::new(bytes).type_object_seq()?,
+      Some(bytes) => XcdrReader::<LittleEndian>::new(bytes).type_object_seq()?,
+      None => Vec::new(),
+    };
+    Ok(TypeInformation::new(type_identifier, type_objects))
+  }
+}
+
+fn extensibility_flags(extensibility: Extensibility) -> u16 {
+  match extensibility {
+    Extensibility::Final => IS_FINAL,
+    Extensibility::Appendable => IS_APPENDABLE,
+    Extensibility::Mutable => IS_MUTABLE,
+  }
+}
+
+fn extensibility_from_flags(flags: u16) -> Extensibility {
+  if flags & IS_MUTABLE != 0 {
+    Extensibility::Mutable
+  } else if flags & IS_FINAL != 0 {
+    Extensibility::Final
+  } else {
+    Extensibility::Appendable
+  }
+}
+
+// Minimal XCDR2 encoder for the type representation. Alignment is relative to
+// the start of the buffer and capped at 4, as in XCDR2.
+struct XcdrWriter<BO> {
+  buffer: Vec<u8>,
+  phantom: PhantomData<BO>,
+}
+
+impl<BO: ByteOrder> XcdrWriter<BO> {
+  fn new() -> XcdrWriter<BO> {
+    XcdrWriter { buffer: Vec::new(), phantom: PhantomData }
+  }
+
+  fn align(&mut self, alignment: usize) {
+    while self.buffer.len() % alignment != 0 {
+      self.buffer.push(0);
+    }
+  }
+
+  fn u8(&mut self, v: u8) {
+    self.buffer.push(v);
+  }
+
+  fn u16(&mut self, v: u16) {
+    self.align(2);
+    let mut b = [0; 2];
+    BO::write_u16(&mut b, v);
+    self.buffer.extend_from_slice(&b);
+  }
+
+  fn u32(&mut self, v: u32) {
+    self.align(4);
+    let mut b = [0; 4];
+    BO::write_u32(&mut b, v);
+    self.buffer.extend_from_slice(&b);
+  }
+
+  fn i32(&mut self, v: i32) {
+    self.u32(v as u32)
+  }
+
+  fn begin_dheader(&mut self) -> usize {
+    self.u32(0);
+    self.buffer.len()
+  }
+
+  fn end_dheader(&mut self, start: usize) {
+    let len = (self.buffer.len() - start) as u32;
+    BO::write_u32(&mut self.buffer[start - 4..start], len);
+  }
+
+  // EMHEADER with length code 4 followed by NEXTINT
+  fn begin_member(&mut self, member_id: u32) -> usize {
+    self.u32((4 << 28) | member_id);
+    self.begin_dheader()
+  }
+
+  fn end_member(&mut self, start: usize) {
+    self.end_dheader(start)
+  }
+
+  fn type_identifier(&mut self, ti: &TypeIdentifier) {
+    match ti {
+      TypeIdentifier::Boolean => self.u8(TK_BOOLEAN),
+      TypeIdentifier::Byte => self.u8(TK_BYTE),
+      TypeIdentifier::Int8 => self.u8(TK_INT8),
+      TypeIdentifier::Int16 => self.u8(TK_INT16),
+      TypeIdentifier::Int32 => self.u8(TK_INT32),
+      TypeIdentifier::Int64 => self.u8(TK_INT64),
+      TypeIdentifier::UInt8 => self.u8(TK_UINT8),
+      TypeIdentifier::UInt16 => self.u8(TK_UINT16),
+      TypeIdentifier::UInt32 => self.u8(TK_UINT32),
+      TypeIdentifier::UInt64 => self.u8(TK_UINT64),
+      TypeIdentifier::Float32 => self.u8(TK_FLOAT32),
+      TypeIdentifier::Float64 => self.u8(TK_FLOAT64),
+      TypeIdentifier::Float128 => self.u8(TK_FLOAT128),
+      TypeIdentifier::Char8 => self.u8(TK_CHAR8),
+      TypeIdentifier::Char16 => self.u8(TK_CHAR16),
+      TypeIdentifier::String8 { bound } => self.string(TI_STRING8_SMALL, TI_STRING8_LARGE, *bound),
+      TypeIdentifier::String16 { bound } => self.string(TI_STRING16_SMALL, TI_STRING16_LARGE, *bound),
+      TypeIdentifier::Sequence { element, bound } => {
+        if *bound < 256 {
+          self.u8(TI_PLAIN_SEQUENCE_SMALL);
+          self.collection_header(element);
+          self.u8(*bound as u8);
+        } else {
+          self.u8(TI_PLAIN_SEQUENCE_LARGE);
+          self.collection_header(element);
+          self.u32(*bound);
+        }
+        self.type_identifier(element);
+      }
+      TypeIdentifier::Array { element, dimensions } => {
+        if dimensions.iter().all(|d| *d < 256) {
+          self.u8(TI_PLAIN_ARRAY_SMALL);
+          self.collection_header(element);
+          self.u32(dimensions.len() as u32);
+          for d in dimensions {
+            self.u8(*d as u8);
+          }
+        } else {
+          self.u8(TI_PLAIN_ARRAY_LARGE);
+          self.collection_header(element);
+          self.u32(dimensions.len() as u32);
+          for d in dimensions {
+            self.u32(*d);
+          }
+        }
+        self.type_identifier(element);
+      }
+      TypeIdentifier::EquivalenceHashMinimal(hash) => {
+        self.u8(EK_MINIMAL);
+        self.buffer.extend_from_slice(hash);
+      }
+    }
+  }
+
+  fn string(&mut self, small: u8, large: u8, bound: u32) {
+    if bound < 256 {
+      self.u8(small);
+      self.u8(bound as u8);
+    } else {
+      self.u8(large);
+      self.u32(bound);
+    }
+  }
+
+  // PlainCollectionHeader: equivalence kind and element flags
+  fn collection_header(&mut self, element: &TypeIdentifier) {
+    match element {
+      TypeIdentifier::EquivalenceHashMinimal(_) => self.u8(EK_MINIMAL),
+      _ => self.u8(EK_BOTH),
+    }
+    self.u16(0);
+  }
+
+  fn optional_type_identifier(&mut self, ti: Option<&TypeIdentifier>) {
+    match ti {
+      Some(ti) => self.type_identifier(ti),
+      None => self.u8(TK_NONE),
+    }
+  }
+
+  fn type_object(&mut self, to: &TypeObject) {
+    // TypeObject and MinimalTypeObject are both appendable unions
+    let outer = self.begin_dheader();
+    self.u8(EK_MINIMAL);
+    let inner = self.begin_dheader();
+    match to {
+      TypeObject::Alias { related_type } => {
+        self.u8(TK_ALIAS);
+        self.u16(0); // alias_flags
+        let header = self.begin_dheader();
+        self.end_dheader(header);
+        let body = self.begin_dheader();
+        self.u16(0); // related_flags
+        self.type_identifier(related_type);
+        self.end_dheader(body);
+      }
+      TypeObject::Struct(st) => {
+        self.u8(TK_STRUCTURE);
+        self.u16(extensibility_flags(st.extensibility));
+        let header = self.begin_dheader();
+        self.optional_type_identifier(st.base_type.as_ref());
+        self.end_dheader(header);
+        let seq = self.begin_dheader();
+        self.u32(st.members.len() as u32);
+        for m in &st.members {
+          let member = self.begin_dheader();
+          self.u32(m.member_id);
+          let mut flags = 0;
+          if m.optional {
+            flags |= IS_OPTIONAL;
+          }
+          if m.must_understand {
+            flags |= IS_MUST_UNDERSTAND;
+          }
+          if m.key {
+            flags |= IS_KEY;
+          }
+          self.u16(flags);
+          self.type_identifier(&m.member_type);
+          self.buffer.extend_from_slice(&m.name_hash);
+          self.end_dheader(member);
+        }
+        self.end_dheader(seq);
+      }
+      TypeObject::Enum(et) => {
+        self.u8(TK_ENUM);
+        self.u16(extensibility_flags(et.extensibility));
+        let header = self.begin_dheader();
+        self.u16(et.bit_bound);
+        self.end_dheader(header);
+        let seq = self.begin_dheader();
+        self.u32(et.literals.len() as u32);
+        for l in &et.literals {
+          let literal = self.begin_dheader();
+          self.i32(l.value);
+          self.u16(if l.default { IS_DEFAULT } else { 0 });
+          self.buffer.extend_from_slice(&l.name_hash);
+          self.end_dheader(literal);
+        }
+        self.end_dheader(seq);
+      }
+    }
+    self.end_dheader(inner);
+    self.end_dheader(outer);
+  }
+
+  // TypeIdentifierWithDependencies of XTypes 7.6.3.2.2
+  fn type_identifier_with_dependencies(
+    &mut self,
+    ti: Option<&TypeIdentifier>,
+    size: u32,
+    dependencies: &[(TypeIdentifier, u32)],
+  ) {
+    let outer = self.begin_dheader();
+    self.type_identifier_with_size(ti, size);
+    self.i32(dependencies.len() as i32);
+    let seq = self.begin_dheader();
+    self.u32(dependencies.len() as u32);
+    for (dep, size) in dependencies {
+      self.type_identifier_with_size(Some(dep), *size);
+    }
+    self.end_dheader(seq);
+    self.end_dheader(outer);
+  }
+
+  fn type_identifier_with_size(&mut self, ti: Option<&TypeIdentifier>, size: u32) {
+    let dheader = self.begin_dheader();
+    self.optional_type_identifier(ti);
+    self.u32(size);
+    self.end_dheader(dheader);
+  }
+}
+
+struct XcdrReader<'a, BO> {
+  input: &'a [u8],
+  position: usize,
+  phantom: PhantomData<BO>,
+}
+
+impl<'a, BO: ByteOrder> XcdrReader<'a, BO> {
+  fn new(input: &'a [u8]) -> XcdrReader<'a, BO> {
+    XcdrReader { input, position: 0, phantom: PhantomData }
+  }
+
+  fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+    if self.position + count > self.input.len() {
+      return Err(Error::Eof)
+    }
+    let bytes = &self.input[self.position..self.position + count];
+    self.position += count;
+    Ok(bytes)
+  }
+
+  fn align(&mut self, alignment: usize) -> Result<()> {
+    let padding = (alignment - self.position % alignment) % alignment;
+    self.bytes(padding).map(|_| ())
+  }
+
+  fn u8(&mut self) -> Result<u8> {
+    Ok(self.bytes(1)?[0])
+  }
+
+  fn u16(&mut self) -> Result<u16> {
+    self.align(2)?;
+    Ok(BO::read_u16(self.bytes(2)?))
+  }
+
+  fn u32(&mut self) -> Result<u32> {
+    self.align(4)?;
+    Ok(BO::read_u32(self.bytes(4)?))
+  }
+
+  fn i32(&mut self) -> Result<i32> {
+    Ok(self.u32()? as i32)
+  }
+
+  // Reads a DHEADER and returns the position where the delimited data ends.
+  fn dheader(&mut self) -> Result<usize> {
+    let len = self.u32()? as usize;
+    let end = self.position + len;
+    if end > self.input.len() {
+      return Err(Error::Eof)
+    }
+    Ok(end)
+  }
+
+  // Skips members appended by newer versions of an appendable type
+  fn skip_to(&mut self, end: usize) -> Result<()> {
+    if self.position > end {
+      return Err(Error::Message("Delimited type overruns its DHEADER".to_string()))
+    }
+    self.position = end;
+    Ok(())
+  }
+
+  fn seq_len(&mut self) -> Result<usize> {
+    let len = self.u32()? as usize;
+    // Each element takes at least one byte. This avoids huge allocations on garbage input.
+    if len > self.input.len() - self.position {
+      return Err(Error::Eof)
+    }
+    Ok(len)
+  }
+
+  fn name_hash(&mut self) -> Result<NameHash> {
+    let mut hash = [0; 4];
+    hash.copy_from_slice(self.bytes(4)?);
+    Ok(hash)
+  }
+
+  fn type_identifier(&mut self) -> Result<TypeIdentifier> {
+    self.optional_type_identifier()?
+      .ok_or_else(|| Error::Message("Missing TypeIdentifier".to_string()))
+  }
+
+  fn optional_type_identifier(&mut self) -> Result<Option<TypeIdentifier>> {
+    let ti = match self.u8()? {
+      TK_NONE => return Ok(None),
+      TK_BOOLEAN => TypeIdentifier::Boolean,
+      TK_BYTE => TypeIdentifier::Byte,
+      TK_INT8 => TypeIdentifier::Int8,
+      TK_INT16 => TypeIdentifier::Int16,
+      TK_INT32 => TypeIdentifier::Int32,
+      TK_INT64 => TypeIdentifier::Int64,
+      TK_UINT8 => TypeIdentifier::UInt8,
+      TK_UINT16 => TypeIdentifier::UInt16,
+      TK_UINT32 => TypeIdentifier::UInt32,
+      TK_UINT64 => TypeIdentifier::UInt64,
+      TK_FLOAT32 => TypeIdentifier::Float32,
+      TK_FLOAT64 => TypeIdentifier::Float64,
+      TK_FLOAT128 => TypeIdentifier::Float128,
+      TK_CHAR8 => TypeIdentifier::Char8,
+      TK_CHAR16 => TypeIdentifier::Char16,
+      TI_STRING8_SMALL => TypeIdentifier::String8 { bound: self.u8()? as u32 },
+      TI_STRING8_LARGE => TypeIdentifier::String8 { bound: self.u32()? },
+      TI_STRING16_SMALL => TypeIdentifier::String16 { bound: self.u8()? as u32 },
+      TI_STRING16_LARGE => TypeIdentifier::String16 { bound: self.u32()? },
+      TI_PLAIN_SEQUENCE_SMALL => {
+        self.collection_header()?;
+        let bound = self.u8()? as u32;
+        TypeIdentifier::Sequence { element: Box::new(self.type_identifier()?), bound }
+      }
+      TI_PLAIN_SEQUENCE_LARGE => {
+        self.collection_header()?;
+        let bound = self.u32()?;
+        TypeIdentifier::Sequence { element: Box::new(self.type_identifier()?), bound }
+      }
+      TI_PLAIN_ARRAY_SMALL => {
+        self.collection_header()?;
+        let len = self.seq_len()?;
+        let dimensions = self.bytes(len)?.iter().map(|d| *d as u32).collect();
+        TypeIdentifier::Array { element: Box::new(self.type_identifier()?), dimensions }
+      }
+      TI_PLAIN_ARRAY_LARGE => {
+        self.collection_header()?;
+        let len = self.seq_len()?;
+        let dimensions = (0..len).map(|_| self.u32()).collect::<Result<Vec<u32>>>()?;
+        TypeIdentifier::Array { element: Box::new(self.type_identifier()?), dimensions }
+      }
+      EK_MINIMAL => {
+        let mut hash = [0; 14];
+        hash.copy_from_slice(self.bytes(14)?);
+        TypeIdentifier::EquivalenceHashMinimal(hash)
+      }
+      other => {
+        return Err(Error::Message(format!("Unsupported TypeIdentifier kind {:#x}", other)))
+      }
+    };
+    Ok(Some(ti))
+  }
+
+  fn collection_header(&mut self) -> Result<()> {
+    let _equivalence_kind = self.u8()?;
+    let _element_flags = self.u16()?;
+    Ok(())
+  }
+
+  fn type_object(&mut self) -> Result<TypeObject> {
+    let outer = self.dheader()?;
+    if self.u8()? != EK_MINIMAL {
+      return Err(Error::Message("Only minimal TypeObjects are supported".to_string()))
+    }
+    let inner = self.dheader()?;
+    let to = match self.u8()? {
+      TK_ALIAS => {
+        let _alias_flags = self.u16()?;
+        let header = self.dheader()?;
+        self.skip_to(header)?;
+        let body = self.dheader()?;
+        let _related_flags = self.u16()?;
+        let related_type = self.type_identifier()?;
+        self.skip_to(body)?;
+        TypeObject::Alias { related_type }
+      }
+      TK_STRUCTURE => {
+        let extensibility = extensibility_from_flags(self.u16()?);
+        let header = self.dheader()?;
+        let base_type = self.optional_type_identifier()?;
+        self.skip_to(header)?;
+        let seq = self.dheader()?;
+        let len = self.seq_len()?;
+        let mut members = Vec::with_capacity(len);
+        for _ in 0..len {
+          let member = self.dheader()?;
+          let member_id = self.u32()?;
+          let flags = self.u16()?;
+          let member_type = self.type_identifier()?;
+          let name_hash = self.name_hash()?;
+          self.skip_to(member)?;
+          members.push(StructMember {
+            member_id,
+            member_type,
+            name_hash,
+            optional: flags & IS_OPTIONAL != 0,
+            must_understand: flags & IS_MUST_UNDERSTAND != 0,
+            key: flags & IS_KEY != 0,
+          });
+        }
+        self.skip_to(seq)?;
+        TypeObject::Struct(StructType { extensibility, base_type, members })
+      }
+      TK_ENUM => {
+        let extensibility = extensibility_from_flags(self.u16()?);
+        let header = self.dheader()?;
+        let bit_bound = self.u16()?;
+        self.skip_to(header)?;
+        let seq = self.dheader()?;
+        let len = self.seq_len()?;
+        let mut literals = Vec::with_capacity(len);
+        for _ in 0..len {
+          let literal = self.dheader()?;
+          let value = self.i32()?;
+          let flags = self.u16()?;
+          let name_hash = self.name_hash()?;
+          self.skip_to(literal)?;
+          literals.push(EnumLiteral { value, name_hash, default: flags & IS_DEFAULT != 0 });
+        }
+        self.skip_to(seq)?;
+        TypeObject::Enum(EnumType { extensibility, bit_bound, literals })
+      }
+      other => return Err(Error::Message(format!("Unsupported TypeObject kind {:#x}", other))),
+    };
+    self.skip_to(inner)?;
+    self.skip_to(outer)?;
+    Ok(to)
+  }
+
+  fn type_object_seq(&mut self) -> Result<Vec<TypeObject>> {
+    let end = self.dheader()?;
+    let len = self.seq_len()?;
+    let type_objects = (0..len).map(|_| self.type_object()).collect::<Result<Vec<_>>>()?;
+    self.skip_to(end)?;
+    Ok(type_objects)
+  }
+
+  // Returns the minimal TypeIdentifier from a TypeInformation.
+  // Dependencies are not needed, because TypeObjects are sent separately.
+  fn type_information(&mut self) -> Result<TypeIdentifier> {
+    let end = self.dheader()?;
+    let mut minimal = None;
+    while self.position < end {
+      self.align(4)?;
+      let emheader = self.u32()?;
+      let member_id = emheader & 0x0FFF_FFFF;
+      let member_end = match emheader >> 28 & 0x7 {
+        lc @ 0..=3 => self.position + (1 << lc),
+        4 => self.dheader()?,
+        // NEXTINT is also the first word of the member
+        lc => {
+          let nextint = self.input.get(self.position..self.position + 4)
+            .map(BO::read_u32)
+            .ok_or(Error::Eof)? as usize;
+          self.position + 4 + nextint * [1, 4, 8][lc as usize - 5]
+        }
+      };
+      if member_id == 0x1001 {
+        let deps = self.dheader()?;
+        let with_size = self.dheader()?;
+        minimal = self.optional_type_identifier()?;
+        self.skip_to(with_size)?;
+        self.skip_to(deps)?;
+      }
+      self.skip_to(member_end)?;
+    }
+    minimal.ok_or_else(|| Error::Message("TypeInformation has no minimal TypeIdentifier".to_string()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn point_type() -> TypeObject {
+    TypeObject::Struct(StructType {
+      extensibility: Extensibility::Appendable,
+      base_type: None,
+      members: vec![
+        StructMember {
+          member_id: 0,
+          member_type: TypeIdentifier::Int32,
+          name_hash: name_hash("x"),
+          optional: false,
+          must_understand: false,
+          key: true,
+        },
+        StructMember {
+          member_id: 1,
+          member_type: TypeIdentifier::Sequence {
+            element: Box::new(TypeIdentifier::String8 { bound: 300 }),
+            bound: 0,
+          },
+          name_hash: name_hash("names"),
+          optional: true,
+          must_understand: false,
+          key: false,
+        },
+      ],
+    })
+  }
+
+  #[test]
+  fn type_object_roundtrip() {
+    let color = TypeObject::Enum(EnumType {
+      extensibility: Extensibility::Final,
+      bit_bound: 32,
+      literals: vec![
+        EnumLiteral { value: 0, name_hash: name_hash("RED"), default: true },
+        EnumLiteral { value: 1, name_hash: name_hash("GREEN"), default: false },
+      ],
+    });
+    let alias = TypeObject::Alias {
+      related_type: TypeIdentifier::Array {
+        element: Box::new(color.type_identifier()),
+        dimensions: vec![3, 1000],
+      },
+    };
+    for to in &[point_type(), color, alias] {
+      let bytes = to.to_bytes();
+      assert_eq!(&TypeObject::from_bytes(&bytes).unwrap(), to);
+    }
+  }
+
+  #[test]
+  fn equivalence_hash_depends_on_contents() {
+    let a = point_type();
+    let mut b = point_type();
+    assert_eq!(a.equivalence_hash(), b.equivalence_hash());
+    if let TypeObject::Struct(st) = &mut b {
+      st.extensibility = Extensibility::Mutable;
+    }
+    assert_ne!(a.equivalence_hash(), b.equivalence_hash());
+  }
+
+  #[test]
+  fn type_information_pid_roundtrip() {
+    let point = point_type();
+    let info = TypeInformation::new(point.type_identifier(), vec![point]);
+
+    let le = TypeInformation::from_pid_bytes(
+      &info.to_pid_bytes::<LittleEndian>(),
+      Some(&info.type_objects_to_pid_bytes::<LittleEndian>()),
+      false,
+    )
+    .unwrap();
+    assert_eq!(le, info);
+
+    let be = TypeInformation::from_pid_bytes(
+      &info.to_pid_bytes::<BigEndian>(),
+      Some(&info.type_objects_to_pid_bytes::<BigEndian>()),
+      true,
+    )
+    .unwrap();
+    assert_eq!(be, info);
+
+    // Without the TypeObjects only the identifier is known.
+    let ids_only =
+      TypeInformation::from_pid_bytes(&info.to_pid_bytes::<LittleEndian>(), None, false).unwrap();
+    assert_eq!(ids_only.type_identifier(), info.type_identifier());
+    assert!(ids_only.type_object(ids_only.type_identifier()).is_none());
+  }
+}
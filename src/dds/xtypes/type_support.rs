@@ -0,0 +1,152 @@
+use super::type_object::{TypeIdentifier, TypeInformation, TypeObject};
+
+/// Rust types that have an XTypes type representation.
+///
+/// Implementations for structs and fieldless enums can be derived with
+/// `#[derive(TypeSupport)]`. The derived representation follows the Serde
+/// data model, so that it describes what the CDR serializer adapters produce.
+///
+/// Struct types are appendable by default, like in XTypes. This can be changed
+/// with `#[xtypes(final)]` or `#[xtypes(mutable)]` on the type. Fields can be
+/// marked `#[xtypes(key)]` and given an explicit member id with
+/// `#[xtypes(id = 5)]`. `Option` fields are optional members.
+/// A single-field tuple struct becomes an alias of the field type.
+pub trait TypeSupport {
+  /// Identifier of this type. TypeObjects of the type and of the types it
+  /// refers to are added to `type_objects`.
+  fn type_identifier(type_objects: &mut TypeObjects) -> TypeIdentifier;
+
+  /// Struct members of this type are optional. This is true only for `Option`.
+  fn is_optional() -> bool {
+    false
+  }
+}
+
+/// TypeObjects collected while generating a TypeIdentifier.
+#[derive(Default)]
+pub struct TypeObjects {
+  type_objects: Vec<TypeObject>,
+}
+
+impl TypeObjects {
+  pub fn new() -> TypeObjects {
+    TypeObjects::default()
+  }
+
+  /// Adds a TypeObject and returns the identifier that refers to it.
+  pub fn add(&mut self, type_object: TypeObject) -> TypeIdentifier {
+    let type_identifier = type_object.type_identifier();
+    if !self.type_objects.contains(&type_object) {
+      self.type_objects.push(type_object);
+    }
+    type_identifier
+  }
+
+  pub(crate) fn into_type_information(self, type_identifier: TypeIdentifier) -> TypeInformation {
+    TypeInformation::new(type_identifier, self.type_objects)
+  }
+}
+
+impl TypeInformation {
+  /// Type information of a Rust type.
+  pub fn of<T: TypeSupport + ?Sized>() -> TypeInformation {
+    let mut type_objects = TypeObjects::new();
+    let type_identifier = T::type_identifier(&mut type_objects);
+    type_objects.into_type_information(type_identifier)
+  }
+}
+
+macro_rules! primitive_type_support {
+  ($($t:ty => $ti:expr),* $(,)?) => {
+    $(
+      impl TypeSupport for $t {
+        fn type_identifier(_type_objects: &mut TypeObjects) -> TypeIdentifier {
+          $ti
+        }
+      }
+    )*
+  };
+}
+
+primitive_type_support!(
+  bool => TypeIdentifier::Boolean,
+  i8 => TypeIdentifier::Int8,
+  u8 => TypeIdentifier::UInt8,
+  i16 => TypeIdentifier::Int16,
+  u16 => TypeIdentifier::UInt16,
+  i32 => TypeIdentifier::Int32,
+  u32 => TypeIdentifier::UInt32,
+  i64 => TypeIdentifier::Int64,
+  u64 => TypeIdentifier::UInt64,
+  f32 => TypeIdentifier::Float32,
+  f64 => TypeIdentifier::Float64,
+  String => TypeIdentifier::String8 { bound: 0 },
+  str => TypeIdentifier::String8 { bound: 0 },
+);
+
+impl<T: TypeSupport> TypeSupport for Vec<T> {
+  fn type_identifier(type_objects: &mut TypeObjects) -> TypeIdentifier {
+    TypeIdentifier::Sequence {
+      element: Box::new(T::type_identifier(type_objects)),
+      bound: 0,
+    }
+  }
+}
+
+impl<T: TypeSupport> TypeSupport for [T] {
+  fn type_identifier(type_objects: &mut TypeObjects) -> TypeIdentifier {
+    Vec::<T>::type_identifier(type_objects)
+  }
+}
+
+impl<T: TypeSupport, const N: usize> TypeSupport for [T; N] {
+  fn type_identifier(type_objects: &mut TypeObjects) -> TypeIdentifier {
+    // Nested arrays are a single multi-dimensional array in XTypes.
+    match T::type_identifier(type_objects) {
+      TypeIdentifier::Array { element, mut dimensions } => {
+        dimensions.insert(0, N as u32);
+        TypeIdentifier::Array { element, dimensions }
+      }
+      element => TypeIdentifier::Array { element: Box::new(element), dimensions: vec![N as u32] },
+    }
+  }
+}
+
+impl<T: TypeSupport + ?Sized> TypeSupport for Box<T> {
+  fn type_identifier(type_objects: &mut TypeObjects) -> TypeIdentifier {
+    T::type_identifier(type_objects)
+  }
+}
+
+impl<T: TypeSupport> TypeSupport for Option<T> {
+  fn type_identifier(type_objects: &mut TypeObjects) -> TypeIdentifier {
+    T::type_identifier(type_objects)
+  }
+
+  fn is_optional() -> bool {
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn collection_type_identifiers() {
+    let mut type_objects = TypeObjects::new();
+    assert_eq!(
+      <[[u8; 3]; 2]>::type_identifier(&mut type_objects),
+      TypeIdentifier::Array { element: Box::new(TypeIdentifier::UInt8), dimensions: vec![2, 3] }
+    );
+    assert_eq!(
+      Vec::<Option<String>>::type_identifier(&mut type_objects),
+      TypeIdentifier::Sequence {
+        element: Box::new(TypeIdentifier::String8 { bound: 0 }),
+        bound: 0
+      }
+    );
+    assert!(Option::<i32>::is_optional());
+    assert!(!i32::is_optional());
+  }
+}
@@ -131,7 +131,13 @@ impl SPDPDiscoveredParticipantData {
 
     let unicast_port =
       get_user_traffic_unicast_port(participant.domain_id(), participant.participant_id());
-    let default_unicast_locators = get_local_unicast_socket_address(unicast_port);
+    let mut default_unicast_locators = get_local_unicast_socket_address(unicast_port);
+
+    // TCP connections carry both metatraffic and user traffic.
+    let tcp_locators = participant.tcp_locators();
+    let mut metatraffic_unicast_locators = metatraffic_unicast_locators;
+    metatraffic_unicast_locators.extend_from_slice(&tcp_locators);
+    default_unicast_locators.extend_from_slice(&tcp_locators);
 
     let builtin_endpoints = BuiltinEndpointSet::DISC_BUILTIN_ENDPOINT_PARTICIPANT_ANNOUNCER
       | BuiltinEndpointSet::DISC_BUILTIN_ENDPOINT_PARTICIPANT_DETECTOR
@@ -199,7 +205,7 @@ mod tests {
   use crate::messages::submessages::submessages::EntitySubmessage;
   use crate::serialization::message::Message;
   use crate::serialization::submessage::*;
-  use crate::serialization::pl_cdr_deserializer::PlCdrDeserializerAdapter;
+  use crate::serialization::builtin_data_deserializer::BuiltinPlCdrDeserializerAdapter;
   use crate::serialization::cdr_serializer::{to_bytes};
   use byteorder::LittleEndian;
   use crate::{
@@ -220,7 +226,7 @@ mod tests {
         SubmessageBody::Entity(v) => match v {
           EntitySubmessage::Data(d, _) => {
             let participant_data: SPDPDiscoveredParticipantData =
-              PlCdrDeserializerAdapter::from_bytes(
+              BuiltinPlCdrDeserializerAdapter::from_bytes(
                 &d.serialized_payload.as_ref().unwrap().value,
                 RepresentationIdentifier::PL_CDR_LE,
               )
@@ -234,7 +240,7 @@ mod tests {
             );
 
             let participant_data_2: SPDPDiscoveredParticipantData =
-              PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE)
+              BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE)
                 .unwrap();
             let sdata_2 =
               to_bytes::<SPDPDiscoveredParticipantData, LittleEndian>(&participant_data_2)
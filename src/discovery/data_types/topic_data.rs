@@ -9,8 +9,11 @@ use crate::{
   dds::{
     qos::policy::{
       Deadline, Durability, LatencyBudget, Reliability, Ownership, DestinationOrder, Liveliness,
-      TimeBasedFilter, Presentation, Lifespan, History, ResourceLimits,
+      TimeBasedFilter, Presentation, Lifespan, History, ResourceLimits, DataRepresentation,
+      DataRepresentationId, TypeConsistencyEnforcement,
     },
+    typedesc::TypeDesc,
+    xtypes::TypeInformation,
     traits::key::Keyed,
     traits::serde_adapters::SerializerAdapter,
     rtps_reader_proxy::RtpsReaderProxy,
@@ -116,6 +119,9 @@ pub struct SubscriptionBuiltinTopicData {
   // pub group_data: Option<GroupData>,
   // pub durability_service: Option<DurabilityService>,
   lifespan: Option<Lifespan>,
+  data_representation: Option<DataRepresentation>,
+  type_consistency: Option<TypeConsistencyEnforcement>,
+  type_information: Option<TypeInformation>,
 }
 
 impl SubscriptionBuiltinTopicData {
@@ -140,6 +146,9 @@ impl SubscriptionBuiltinTopicData {
       time_based_filter: None,
       presentation: None,
       lifespan: None,
+      data_representation: None,
+      type_consistency: None,
+      type_information: None,
     };
 
     sbtd.set_qos(qos);
@@ -218,6 +227,22 @@ impl SubscriptionBuiltinTopicData {
     &self.lifespan
   }
 
+  pub fn data_representation(&self) -> &Option<DataRepresentation> {
+    &self.data_representation
+  }
+
+  pub fn type_consistency(&self) -> &Option<TypeConsistencyEnforcement> {
+    &self.type_consistency
+  }
+
+  pub fn type_information(&self) -> &Option<TypeInformation> {
+    &self.type_information
+  }
+
+  pub fn set_type_information(&mut self, type_information: Option<TypeInformation>) {
+    self.type_information = type_information;
+  }
+
   pub fn set_qos(&mut self, qos: &QosPolicies) {
     self.durability = qos.durability.clone();
     self.deadline = qos.deadline.clone();
@@ -229,6 +254,20 @@ impl SubscriptionBuiltinTopicData {
     self.time_based_filter = qos.time_based_filter.clone();
     self.presentation = qos.presentation.clone();
     self.lifespan = qos.lifespan.clone();
+    self.data_representation = qos.data_representation.clone();
+    self.type_consistency = qos.type_consistency.clone();
+  }
+
+  // DataReader QoS may be more specific than the Topic QoS, and the type comes from the Topic.
+  pub(crate) fn set_reader_details(&mut self, reader_qos: &QosPolicies, type_desc: &TypeDesc) {
+    // Accepted representations depend on the DeserializerAdapter of the DataReader.
+    if let Some(dr) = reader_qos.data_representation {
+      self.data_representation = Some(dr);
+    }
+    if let Some(tc) = reader_qos.type_consistency {
+      self.type_consistency = Some(tc);
+    }
+    self.type_information = type_desc.type_information().cloned();
   }
 
   pub fn generate_qos(&self) -> QosPolicies {
@@ -245,6 +284,8 @@ impl SubscriptionBuiltinTopicData {
       history: None, // TODO: Check that this really does not exist in source
       resource_limits: None, // TODO: Check that this really does not exist in source
       lifespan: self.lifespan, 
+      data_representation: self.data_representation,
+      type_consistency: self.type_consistency,
     }
   }
 }
@@ -298,6 +339,7 @@ impl DiscoveredReaderData {
       &topic.get_qos(),
     );
     subscription_topic_data.set_participant_key(dp.get_guid());
+    subscription_topic_data.set_reader_details(&reader.get_qos(), &topic.get_type());
 
     DiscoveredReaderData {
       reader_proxy,
@@ -435,6 +477,8 @@ pub struct PublicationBuiltinTopicData {
   pub ownership: Option<Ownership>,
   pub destination_order: Option<DestinationOrder>,
   pub presentation: Option<Presentation>,
+  pub data_representation: Option<DataRepresentation>,
+  pub type_information: Option<TypeInformation>,
 }
 
 impl PublicationBuiltinTopicData {
@@ -459,6 +503,8 @@ impl PublicationBuiltinTopicData {
       ownership: None,
       destination_order: None,
       presentation: None,
+      data_representation: None,
+      type_information: None,
     }
   }
 
@@ -473,6 +519,7 @@ impl PublicationBuiltinTopicData {
     self.ownership = qos.ownership;
     self.destination_order = qos.destination_order;
     self.presentation = qos.presentation;
+    self.data_representation = qos.data_representation;
   }
 
   pub fn qos(&self) -> QosPolicies {
@@ -489,6 +536,8 @@ impl PublicationBuiltinTopicData {
       history: None, // TODO: ???
       resource_limits: None, // TODO: ???
       lifespan: self.lifespan,
+      data_representation: self.data_representation,
+      type_consistency: None, // only readers have this
     }
   }
 }
@@ -557,6 +606,11 @@ impl DiscoveredWriterData {
     );
 
     publication_topic_data.read_qos(&topic.get_qos());
+    // The DataWriter offers the representation its SerializerAdapter produces.
+    if let Some(id) = DataRepresentationId::from_representation_identifier(SA::output_encoding()) {
+      publication_topic_data.data_representation = Some(DataRepresentation::new(&[id]));
+    }
+    publication_topic_data.type_information = topic.get_type().type_information().cloned();
 
     DiscoveredWriterData {
       last_updated: Instant::now(),
@@ -748,7 +802,11 @@ impl Keyed for ParticipantMessageData {
   }
 }
 
-impl Key for (GuidPrefix, ParticipantMessageDataKind) {}
+impl Key for ParticipantMessageDataKind {
+  fn is_fixed_size() -> bool {
+    true
+  }
+}
 
 // =======================================================================
 // =======================================================================
@@ -765,7 +823,7 @@ mod tests {
   };
   use byteorder::LittleEndian;
   use log::info;
-  use crate::serialization::pl_cdr_deserializer::PlCdrDeserializerAdapter;
+  use crate::serialization::builtin_data_deserializer::BuiltinPlCdrDeserializerAdapter;
 
   use crate::{
     test::test_data::{
@@ -784,7 +842,7 @@ mod tests {
 
     let sdata = to_bytes::<ReaderProxy, LittleEndian>(&reader_proxy).unwrap();
     let reader_proxy2: ReaderProxy =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     assert_eq!(reader_proxy, reader_proxy2);
     let sdata2 = to_bytes::<ReaderProxy, LittleEndian>(&reader_proxy2).unwrap();
     assert_eq!(sdata, sdata2);
@@ -796,7 +854,7 @@ mod tests {
 
     let sdata = to_bytes::<WriterProxy, LittleEndian>(&writer_proxy).unwrap();
     let writer_proxy2: WriterProxy =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     assert_eq!(writer_proxy, writer_proxy2);
     let sdata2 = to_bytes::<WriterProxy, LittleEndian>(&writer_proxy2).unwrap();
     assert_eq!(sdata, sdata2);
@@ -808,7 +866,7 @@ mod tests {
 
     let sdata = to_bytes::<SubscriptionBuiltinTopicData, LittleEndian>(&sub_topic_data).unwrap();
     let sub_topic_data2: SubscriptionBuiltinTopicData =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     assert_eq!(sub_topic_data, sub_topic_data2);
     let sdata2 = to_bytes::<SubscriptionBuiltinTopicData, LittleEndian>(&sub_topic_data2).unwrap();
     assert_eq!(sdata, sdata2);
@@ -820,7 +878,7 @@ mod tests {
 
     let sdata = to_bytes::<PublicationBuiltinTopicData, LittleEndian>(&pub_topic_data).unwrap();
     let pub_topic_data2: PublicationBuiltinTopicData =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     assert_eq!(pub_topic_data, pub_topic_data2);
     let sdata2 = to_bytes::<PublicationBuiltinTopicData, LittleEndian>(&pub_topic_data2).unwrap();
     assert_eq!(sdata, sdata2);
@@ -841,7 +899,7 @@ mod tests {
 
     let sdata = to_bytes::<DiscoveredReaderData, LittleEndian>(&drd).unwrap();
     let drd2: DiscoveredReaderData =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     assert_eq!(drd, drd2);
     let sdata2 = to_bytes::<DiscoveredReaderData, LittleEndian>(&drd2).unwrap();
     assert_eq!(sdata, sdata2);
@@ -885,7 +943,7 @@ mod tests {
 
     let sdata = to_bytes::<DiscoveredWriterData, LittleEndian>(&dwd).unwrap();
     let mut dwd2: DiscoveredWriterData =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     // last updated is not serialized thus copying value for correct result
     dwd2.last_updated = dwd.last_updated;
 
@@ -900,7 +958,7 @@ mod tests {
 
     let sdata = to_bytes::<TopicBuiltinTopicData, LittleEndian>(&topic_data).unwrap();
     let topic_data2: TopicBuiltinTopicData =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     assert_eq!(topic_data, topic_data2);
     let sdata2 = to_bytes::<TopicBuiltinTopicData, LittleEndian>(&topic_data2).unwrap();
     assert_eq!(sdata, sdata2);
@@ -914,7 +972,7 @@ mod tests {
 
     let sdata = to_bytes::<DiscoveredTopicData, LittleEndian>(&dtd).unwrap();
     let dtd2: DiscoveredTopicData =
-      PlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
+      BuiltinPlCdrDeserializerAdapter::from_bytes(&sdata, RepresentationIdentifier::PL_CDR_LE).unwrap();
     assert_eq!(dtd.topic_data, dtd2.topic_data);
     let sdata2 = to_bytes::<DiscoveredTopicData, LittleEndian>(&dtd2).unwrap();
     assert_eq!(sdata, sdata2);
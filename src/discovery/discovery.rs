@@ -43,9 +43,9 @@ use crate::discovery::{
   discovery_db::DiscoveryDB,
 };
 
-use crate::structure::{duration::Duration, guid::EntityId, time::Timestamp};
+use crate::structure::{duration::Duration, guid::EntityId, time::Timestamp, locator::LocatorList};
 
-use crate::serialization::{CDRSerializerAdapter, pl_cdr_deserializer::PlCdrDeserializerAdapter};
+use crate::serialization::{CDRSerializerAdapter, builtin_data_deserializer::BuiltinPlCdrDeserializerAdapter};
 
 use crate::network::constant::*;
 use super::data_types::topic_data::{
@@ -109,6 +109,8 @@ impl Discovery {
     history: Some(History::KeepLast { depth: 1 }),
     resource_limits: None,
     lifespan: None,
+    data_representation: None,
+    type_consistency: None,
   };
 
   pub fn new(
@@ -187,7 +189,7 @@ impl Discovery {
       "Unable to create DCPSParticipant topic. {:?}");
   
     let mut dcps_participant_reader = try_construct!( discovery_subscriber
-      .create_datareader::<SPDPDiscoveredParticipantData,PlCdrDeserializerAdapter<SPDPDiscoveredParticipantData>>(
+      .create_datareader::<SPDPDiscoveredParticipantData,BuiltinPlCdrDeserializerAdapter<SPDPDiscoveredParticipantData>>(
         dcps_participant_topic.clone(),
         Some(EntityId::ENTITYID_SPDP_BUILTIN_PARTICIPANT_READER),
         None,
@@ -239,7 +241,7 @@ impl Discovery {
     ) ,"Unable to create DCPSSubscription topic. {:?}");
 
     let mut dcps_subscription_reader = try_construct!( discovery_subscriber
-      .create_datareader::<DiscoveredReaderData, PlCdrDeserializerAdapter<DiscoveredReaderData>>(
+      .create_datareader::<DiscoveredReaderData, BuiltinPlCdrDeserializerAdapter<DiscoveredReaderData>>(
         dcps_subscription_topic.clone(),
         Some(EntityId::ENTITYID_SEDP_BUILTIN_SUBSCRIPTIONS_READER),
         None,
@@ -278,7 +280,7 @@ impl Discovery {
     ) ,"Unable to create DCPSPublication topic. {:?}");
 
     let mut dcps_publication_reader = try_construct!( discovery_subscriber
-      .create_datareader::<DiscoveredWriterData, PlCdrDeserializerAdapter<DiscoveredWriterData>>(
+      .create_datareader::<DiscoveredWriterData, BuiltinPlCdrDeserializerAdapter<DiscoveredWriterData>>(
         dcps_publication_topic.clone(),
         Some(EntityId::ENTITYID_SEDP_BUILTIN_PUBLICATIONS_READER),
         None,
@@ -327,7 +329,7 @@ impl Discovery {
     ) ,"Unable to register topic cleanup timer. {:?}");
 
     let mut dcps_reader = try_construct!( discovery_subscriber
-      .create_datareader::<DiscoveredTopicData, PlCdrDeserializerAdapter<DiscoveredTopicData>>(
+      .create_datareader::<DiscoveredTopicData, BuiltinPlCdrDeserializerAdapter<DiscoveredTopicData>>(
         dcps_topic.clone(),
         Some(EntityId::ENTITYID_SEDP_BUILTIN_TOPIC_READER),
         None,
@@ -563,15 +565,17 @@ impl Discovery {
         guid_prefix: dp.get_guid().guidPrefix
     });
     // insert reader proxy as multicast address, so discovery notifications are sent somewhere
-    self.initialize_participant_reader_proxy(port);
+    self.initialize_participant_reader_proxy(port, dp.tcp_initial_peer_locators());
   }
 
-  pub fn initialize_participant_reader_proxy(&self, port: u16) {
+  pub fn initialize_participant_reader_proxy(&self, port: u16, tcp_initial_peers: LocatorList) {
     let guid = GUID::new_with_prefix_and_id(
       GuidPrefix::GUIDPREFIX_UNKNOWN, EntityId::ENTITYID_SPDP_BUILTIN_PARTICIPANT_READER);
 
     let mut reader_proxy = ReaderProxy::new(guid);
     reader_proxy.multicast_locator_list = get_local_multicast_locators(port);
+    // If we are a TCP client, the servers are announced to like the multicast group.
+    reader_proxy.unicast_locator_list = tcp_initial_peers;
 
     let sub_topic_data = SubscriptionBuiltinTopicData::new(
       guid,
@@ -595,7 +599,7 @@ impl Discovery {
 
   pub fn handle_participant_reader(&self,
     reader: &mut DataReader<SPDPDiscoveredParticipantData,
-      PlCdrDeserializerAdapter<SPDPDiscoveredParticipantData>>) 
+      BuiltinPlCdrDeserializerAdapter<SPDPDiscoveredParticipantData>>) 
   {
     loop {
       let s = reader.take_next_sample();
@@ -625,7 +629,7 @@ impl Discovery {
 
   pub fn handle_subscription_reader(
     &self,
-    reader: &mut DataReader<DiscoveredReaderData, PlCdrDeserializerAdapter<DiscoveredReaderData>>,
+    reader: &mut DataReader<DiscoveredReaderData, BuiltinPlCdrDeserializerAdapter<DiscoveredReaderData>>,
   ) {
     match reader.take(100, ReadCondition::not_read()) {
       Ok(d) => {
@@ -663,7 +667,7 @@ impl Discovery {
 
   pub fn handle_publication_reader(
     &self,
-    reader: &mut DataReader<DiscoveredWriterData, PlCdrDeserializerAdapter<DiscoveredWriterData>>,
+    reader: &mut DataReader<DiscoveredWriterData, BuiltinPlCdrDeserializerAdapter<DiscoveredWriterData>>,
   ) {
     match reader.take(100, ReadCondition::not_read()) {
       Ok(d) => {
@@ -694,7 +698,7 @@ impl Discovery {
 
   pub fn handle_topic_reader(
     &self,
-    reader: &mut DataReader<DiscoveredTopicData, PlCdrDeserializerAdapter<DiscoveredTopicData>>,
+    reader: &mut DataReader<DiscoveredTopicData, BuiltinPlCdrDeserializerAdapter<DiscoveredTopicData>>,
   ) {
     let topic_data_vec: Option<Vec<DiscoveredTopicData>> =
       match reader.take(100, ReadCondition::any()) {
@@ -1175,7 +1179,7 @@ mod tests {
       match &mut submsg.body {
         SubmessageBody::Entity(v) => match v {
           EntitySubmessage::Data(d, _) => {
-            let mut drd: DiscoveredReaderData = PlCdrDeserializerAdapter::from_bytes(
+            let mut drd: DiscoveredReaderData = BuiltinPlCdrDeserializerAdapter::from_bytes(
               &d.serialized_payload.as_ref().unwrap().value,
               RepresentationIdentifier::PL_CDR_LE,
             )
@@ -18,7 +18,7 @@ use crate::structure::{guid::GUID, guid::EntityId,
 use crate::{
   dds::{
     rtps_reader_proxy::RtpsReaderProxy, reader::Reader, participant::DomainParticipant,
-    topic::Topic, traits::TopicDescription,
+    topic::Topic, traits::TopicDescription, xtypes::types_consistent,
   },
 };
 
@@ -28,6 +28,7 @@ use super::{
     topic_data::{
       DiscoveredReaderData, DiscoveredTopicData, DiscoveredWriterData, ParticipantMessageData,
       ReaderProxy, SubscriptionBuiltinTopicData, TopicBuiltinTopicData,
+      PublicationBuiltinTopicData,
     },
   },
 };
@@ -304,6 +305,40 @@ impl DiscoveryDB {
     
   }
 
+  // XTypes 7.6.3.4.2: A remote reader is matched with a local writer only if
+  // the reader type is assignable from the writer type.
+  // Local endpoints not (yet) in the DB are not checked.
+  pub fn is_type_consistent_with_local_writer(&self, writer_guid: GUID,
+      drd: &DiscoveredReaderData) -> bool {
+    match self.local_topic_writers.get(&writer_guid) {
+      Some(dwd) => 
+        Self::types_consistent(&drd.subscription_topic_data, &dwd.publication_topic_data),
+      None => true,
+    }
+  }
+
+  pub fn is_type_consistent_with_local_reader(&self, reader_guid: GUID,
+      dwd: &DiscoveredWriterData) -> bool {
+    match self.local_topic_readers.get(&reader_guid) {
+      Some(drd) => 
+        Self::types_consistent(&drd.subscription_topic_data, &dwd.publication_topic_data),
+      None => true,
+    }
+  }
+
+  fn types_consistent(reader: &SubscriptionBuiltinTopicData, 
+      writer: &PublicationBuiltinTopicData) -> bool {
+    let consistent = types_consistent(
+      reader.type_information().as_ref(), 
+      writer.type_information.as_ref(),
+      &reader.type_consistency().unwrap_or_default());
+    if ! consistent {
+      warn!("Not matching reader {:?} of type {:?} with writer {:?} of type {:?} on topic {:?}: inconsistent types",
+        reader.key(), reader.type_name(), writer.key, writer.type_name, writer.topic_name);
+    }
+    consistent
+  }
+
   pub fn update_topic_data_drd(&mut self, drd: &DiscoveredReaderData) {
     let topic_data = DiscoveredTopicData::new(TopicBuiltinTopicData {
       key: None,
@@ -404,6 +439,7 @@ impl DiscoveryDB {
       &topic.get_qos(),
     );
     subscription_data.set_participant_key(domain_participant.get_guid());
+    subscription_data.set_reader_details(&reader.get_qos(), &topic.get_type());
 
     // TODO: possibly change content filter to dynamic value
     let content_filter = None;
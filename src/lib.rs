@@ -27,6 +27,7 @@ extern crate num_traits;
 extern crate speedy;
 extern crate tokio_util;
 extern crate uuid;
+extern crate self as rustdds;
 
 #[macro_use]
 mod serialization_test;
@@ -105,8 +105,12 @@ impl Data {
   }
 }
 
-impl<C: Context> Writable<C> for Data {
-  fn write_to<'a, T: ?Sized + Writer<C>>(&'a self, writer: &mut T) -> Result<(), C::Error> {
+impl Data {
+  // Writes everything except the payload value bytes. Those are appended by the caller,
+  // either by write_to below or by pointing an iovec to the shared payload buffer.
+  fn write_headers_to<C: Context, T: ?Sized + Writer<C>>(&self, writer: &mut T)
+    -> Result<(), C::Error> 
+  {
     //This version of the protocol (2.3) should set all the bits in the extraFlags to zero
     writer.write_u16(0)?;
     //The octetsToInlineQos field contains the number of octets starting from the first octet immediately following
@@ -123,9 +127,38 @@ impl<C: Context> Writable<C> for Data {
     }
 
     if let Some(serialized_payload) = self.serialized_payload.as_ref() {
-      writer.write_value(serialized_payload)?;
+      serialized_payload.write_header_to(writer)?;
     }
+    Ok(())
+  }
+
+  /// Serializes the submessage body without the payload value.
+  /// Concatenating the result and the payload value gives the same bytes as `write_to`.
+  pub fn headers_to_vec(&self, endianness: speedy::Endianness) -> Result<Vec<u8>, Error> {
+    DataHeaders(self).write_to_vec_with_ctx(endianness)
+  }
+
+  /// Serialized length of the submessage body, computed without copying the payload.
+  pub fn len_serialized(&self, endianness: speedy::Endianness) -> Result<usize, Error> {
+    let payload_len = self.serialized_payload.as_ref().map(|p| p.value.len()).unwrap_or(0);
+    Ok(self.headers_to_vec(endianness)?.len() + payload_len)
+  }
+}
+
+struct DataHeaders<'a>(&'a Data);
+
+impl<'a, C: Context> Writable<C> for DataHeaders<'a> {
+  fn write_to<'b, T: ?Sized + Writer<C>>(&'b self, writer: &mut T) -> Result<(), C::Error> {
+    self.0.write_headers_to(writer)
+  }
+}
 
+impl<C: Context> Writable<C> for Data {
+  fn write_to<'a, T: ?Sized + Writer<C>>(&'a self, writer: &mut T) -> Result<(), C::Error> {
+    self.write_headers_to(writer)?;
+    if let Some(serialized_payload) = self.serialized_payload.as_ref() {
+      writer.write_bytes(&serialized_payload.value)?;
+    }
     Ok(())
   }
 }
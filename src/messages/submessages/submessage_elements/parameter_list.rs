@@ -25,7 +25,6 @@ impl ParameterList {
 /// that follows and its length can be anything (as long as it is a multiple of
 /// 4)
 pub const PID_PAD: u16 = 0x00;
-const SENTINEL: u32 = 0x00000001;
 
 impl<C: Context> Writable<C> for ParameterList {
   #[inline]
@@ -34,7 +33,9 @@ impl<C: Context> Writable<C> for ParameterList {
       writer.write_value(param)?;
     }
 
-    writer.write_u32(SENTINEL)?;
+    // PID_SENTINEL with length 0, both in the byte order of the writer
+    writer.write_value(&ParameterId::PID_SENTINEL)?;
+    writer.write_u16(0)?;
 
     Ok(())
   }
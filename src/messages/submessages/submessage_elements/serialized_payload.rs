@@ -39,6 +39,15 @@ impl RepresentationIdentifier {
   pub const XML: RepresentationIdentifier 
     = RepresentationIdentifier { bytes: [0x00, 0x04]};
 
+  // Vendor-specific representations, with the most significant bit set.
+  // These are used by the optional serializer adapters of RustDDS.
+  pub const CBOR: RepresentationIdentifier 
+    = RepresentationIdentifier { bytes: [0x80, 0x01]};
+  pub const JSON: RepresentationIdentifier 
+    = RepresentationIdentifier { bytes: [0x80, 0x02]};
+  pub const PROTOBUF: RepresentationIdentifier 
+    = RepresentationIdentifier { bytes: [0x80, 0x03]};
+
   pub fn from_bytes(bytes: &[u8]) -> io::Result<RepresentationIdentifier> {
     let mut reader = io::Cursor::new(bytes);
     Ok( RepresentationIdentifier { 
@@ -111,18 +120,23 @@ impl SerializedPayload {
   pub fn representation_identifier(&self) -> RepresentationIdentifier {
     self.representation_identifier
   }
-}
 
-impl<C: Context> Writable<C> for SerializedPayload {
-  fn write_to<'a, T: ?Sized + Writer<C>>(&'a self, writer: &mut T) -> Result<(), C::Error> {
+  // Encapsulation header only: representation identifier and options.
+  pub(crate) fn write_header_to<C: Context, T: ?Sized + Writer<C>>(&self, writer: &mut T)
+    -> Result<(), C::Error> 
+  {
     writer.write_u8(self.representation_identifier.bytes[0])?;
     writer.write_u8(self.representation_identifier.bytes[1])?;
     writer.write_u8(self.representation_options[0])?;
     writer.write_u8(self.representation_options[1])?;
-    // TODO: This looks like a slowish way to copy bytes.
-    for element in &*self.value {
-      writer.write_u8(*element)?;
-    }
+    Ok(())
+  }
+}
+
+impl<C: Context> Writable<C> for SerializedPayload {
+  fn write_to<'a, T: ?Sized + Writer<C>>(&'a self, writer: &mut T) -> Result<(), C::Error> {
+    self.write_header_to(writer)?;
+    writer.write_bytes(&self.value)?;
     Ok(())
   }
 }
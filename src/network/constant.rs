@@ -56,6 +56,12 @@ pub const DISCOVERY_PARTICIPANT_MESSAGE_TIMER_TOKEN: Token = Token(41);
 
 pub const DPEV_ACKNACK_TIMER_TOKEN: Token = Token(50);
 
+pub const TCP_LISTENER_TOKEN: Token = Token(60);
+pub const TCP_RECONNECT_TIMER_TOKEN: Token = Token(61);
+// Each TCP connection gets a token from this range.
+pub const TCP_CONNECTION_TOKEN_BASE: usize = 1000;
+pub const TCP_CONNECTION_TOKEN_LIMIT: usize = TCP_CONNECTION_TOKEN_BASE + 65536;
+
 pub struct TokenReceiverPair<T> {
   pub token: Token,
   pub receiver: mio_channel::Receiver<T>,
@@ -91,6 +97,8 @@ pub enum TimerMessageType {
   WriterHeartbeat,
   WriterCacheCleaning,
   WriterSendRepairData { to_reader: GUID },
+  WriterFlushBatch,
+  WriterFlowControl,
   ReaderDeadlineMissedCheck,
 }
 
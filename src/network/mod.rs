@@ -1,4 +1,7 @@
 pub mod constant;
+pub mod flow_controller;
+pub mod tcp_transport;
+pub mod transport_config;
 pub mod udp_listener;
 pub mod udp_sender;
 pub mod util;
@@ -0,0 +1,678 @@
+use std::net::SocketAddr;
+use std::io::{self, Read, Write};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mio::{Poll, Event, Token, Ready, PollOpt};
+use mio::net::{TcpListener, TcpStream};
+use mio_extras::timer::Timer;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, warn, trace};
+
+use bytes::{Bytes, BytesMut, BufMut};
+
+use crate::structure::guid::GuidPrefix;
+use crate::structure::locator::{Locator, LocatorList};
+use crate::network::constant::*;
+use crate::network::util::get_local_unicast_socket_address;
+
+// RTPS over TCP framing.
+//
+// Each RTPS message is preceded by a 14-byte TCP header, laid out like the one
+// of the DDS-TCP PSM and its implementations:
+//
+// 0...2...........8...............16
+// +---------------+---------------+
+// |  'R' 'T' 'C' 'P'              |
+// +---------------+---------------+
+// |  length (u32, includes header)|
+// +---------------+---------------+
+// |  crc (u32)                    |
+// +---------------+---------------+
+// |  logical port |
+// +---------------+
+//
+// All integers are little endian. The crc is an additive checksum over the
+// RTPS message bytes. Logical ports are not used for multiplexing here,
+// because one connection carries all traffic between two participants. The
+// control protocol of the PSM (BIND, OPEN_LOGICAL_PORT etc.) is not
+// implemented, so only RustDDS peers understand this transport.
+pub(crate) const TCP_HEADER_SIZE: usize = 14;
+const TCP_HEADER_MAGIC: &[u8; 4] = b"RTCP";
+
+// RTPS messages larger than this are not sent over TCP. Same limit as UDP.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+// If a peer does not read, we stop queuing data to it beyond this.
+const MAX_SEND_QUEUE_SIZE: usize = 16 * 1024 * 1024;
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+const DEFAULT_RECONNECT_PERIOD: Duration = Duration::from_secs(2);
+
+/// Role of this DomainParticipant in RTPS over TCP communication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TCPRole {
+  /// Accept connections from clients at the given address.
+  /// Use e.g. `0.0.0.0:7650` to listen on all interfaces.
+  Server { listen_address: SocketAddr },
+  /// Connect out to the given server participants. This works also behind
+  /// NAT, because the servers reply over the same connection.
+  Client { server_addresses: Vec<SocketAddr> },
+}
+
+/// Configuration of the TCPv4 transport.
+///
+/// This transport only works between RustDDS participants. It frames RTPS
+/// messages like the DDSI-RTPS TCP PSM, but does not implement its control
+/// protocol (BIND, OPEN_LOGICAL_PORT etc.) and ignores logical ports, so
+/// other DDS implementations cannot connect to it or be connected to.
+///
+/// # Examples
+///
+/// ```
+/// # use rustdds::dds::TCPTransportConfig;
+/// let server = TCPTransportConfig::server("0.0.0.0:7650".parse().unwrap());
+/// let client = TCPTransportConfig::client(vec!["192.0.2.10:7650".parse().unwrap()]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TCPTransportConfig {
+  pub role: TCPRole,
+  /// How long a client waits before trying to re-establish a lost or
+  /// failed connection.
+  pub reconnect_period: Duration,
+}
+
+impl TCPTransportConfig {
+  pub fn server(listen_address: SocketAddr) -> TCPTransportConfig {
+    TCPTransportConfig {
+      role: TCPRole::Server { listen_address },
+      reconnect_period: DEFAULT_RECONNECT_PERIOD,
+    }
+  }
+
+  pub fn client(server_addresses: Vec<SocketAddr>) -> TCPTransportConfig {
+    TCPTransportConfig {
+      role: TCPRole::Client { server_addresses },
+      reconnect_period: DEFAULT_RECONNECT_PERIOD,
+    }
+  }
+
+  pub fn reconnect_period(mut self, reconnect_period: Duration) -> TCPTransportConfig {
+    self.reconnect_period = reconnect_period;
+    self
+  }
+
+  /// Locators to be announced in SPDP.
+  /// A server announces its listening address. A client announces its local
+  /// addresses with port zero, meaning that it can only be reached over the
+  /// connection it has opened itself.
+  pub(crate) fn announced_locators(&self) -> LocatorList {
+    match &self.role {
+      TCPRole::Server { listen_address } => {
+        if listen_address.ip().is_unspecified() {
+          get_local_unicast_socket_address(listen_address.port())
+            .into_iter()
+            .map(|l| Locator::from_tcp_socket_address(l.to_socket_address()))
+            .collect()
+        } else {
+          vec![Locator::from_tcp_socket_address(*listen_address)]
+        }
+      }
+      TCPRole::Client { .. } => get_local_unicast_socket_address(0)
+        .into_iter()
+        .map(|l| Locator::from_tcp_socket_address(l.to_socket_address()))
+        .collect(),
+    }
+  }
+
+  /// Locators of the well-known server participants. Used to send the
+  /// initial SPDP announcements before anything has been discovered.
+  pub(crate) fn initial_peer_locators(&self) -> LocatorList {
+    match &self.role {
+      TCPRole::Server { .. } => vec![],
+      TCPRole::Client { server_addresses } => server_addresses
+        .iter()
+        .map(|sa| Locator::from_tcp_socket_address(*sa))
+        .collect(),
+    }
+  }
+}
+
+fn tcp_checksum(message: &[u8]) -> u32 {
+  message
+    .iter()
+    .fold(0u32, |crc, b| crc.wrapping_add(u32::from(*b)))
+}
+
+/// Wraps an RTPS message into a TCP frame.
+pub(crate) fn encode_frame(message: &[u8]) -> Bytes {
+  let mut frame = BytesMut::with_capacity(TCP_HEADER_SIZE + message.len());
+  frame.put_slice(TCP_HEADER_MAGIC);
+  frame.put_u32_le((TCP_HEADER_SIZE + message.len()) as u32);
+  frame.put_u32_le(tcp_checksum(message));
+  frame.put_u16_le(0); // logical port
+  frame.put_slice(message);
+  frame.freeze()
+}
+
+/// Extracts all complete RTPS messages from the beginning of `buffer`.
+/// Incomplete data is left in the buffer to wait for more bytes.
+/// An error means that the stream is out of sync and the connection
+/// must be dropped.
+pub(crate) fn decode_frames(buffer: &mut BytesMut) -> io::Result<Vec<Bytes>> {
+  let mut messages = Vec::new();
+  while buffer.len() >= TCP_HEADER_SIZE {
+    if buffer[0..4] != TCP_HEADER_MAGIC[..] {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "Bad RTPS TCP header magic"))
+    }
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&buffer[4..8]);
+    let length = u32::from_le_bytes(word) as usize;
+    word.copy_from_slice(&buffer[8..12]);
+    let crc = u32::from_le_bytes(word);
+
+    if !(TCP_HEADER_SIZE..=TCP_HEADER_SIZE + MAX_MESSAGE_SIZE).contains(&length) {
+      return Err(io::Error::new(io::ErrorKind::InvalidData,
+        format!("Bad RTPS TCP frame length {}", length)))
+    }
+    if buffer.len() < length {
+      break // wait for the rest
+    }
+    let frame = buffer.split_to(length);
+    let message = &frame[TCP_HEADER_SIZE..];
+    if tcp_checksum(message) != crc {
+      warn!("decode_frames: checksum mismatch. Discarding message.");
+      continue
+    }
+    messages.push(Bytes::copy_from_slice(message));
+  }
+  Ok(messages)
+}
+
+struct TCPConnection {
+  stream: TcpStream,
+  peer_address: SocketAddr,
+  // true, if we opened this connection, i.e. we should reconnect
+  outbound: bool,
+  // Learned from the RTPS headers received from this connection
+  remote_guid_prefix: Option<GuidPrefix>,
+  receive_buffer: BytesMut,
+  send_buffer: BytesMut,
+}
+
+impl TCPConnection {
+  fn new(stream: TcpStream, peer_address: SocketAddr, outbound: bool) -> TCPConnection {
+    TCPConnection {
+      stream,
+      peer_address,
+      outbound,
+      remote_guid_prefix: None,
+      receive_buffer: BytesMut::with_capacity(READ_CHUNK_SIZE),
+      send_buffer: BytesMut::new(),
+    }
+  }
+
+  // Queue a frame and send as much as the socket accepts.
+  fn send(&mut self, frame: &[u8]) {
+    if self.send_buffer.len() + frame.len() > MAX_SEND_QUEUE_SIZE {
+      warn!("TCP send queue to {:?} is full. Dropping message.", self.peer_address);
+      return
+    }
+    self.send_buffer.extend_from_slice(frame);
+    if let Err(e) = self.flush() {
+      // The error is reported again by poll, and handled there.
+      debug!("TCP send to {:?} failed: {:?}", self.peer_address, e);
+    }
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    while !self.send_buffer.is_empty() {
+      match self.stream.write(&self.send_buffer) {
+        Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "TCP write returned zero")),
+        Ok(n) => {
+          let _ = self.send_buffer.split_to(n);
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()), // continue when writable
+        Err(ref e) if e.kind() == io::ErrorKind::NotConnected => return Ok(()), // still connecting
+        Err(e) => return Err(e),
+      }
+    }
+    Ok(())
+  }
+
+  // Returns received messages, or error if the connection should be closed.
+  fn receive(&mut self) -> io::Result<Vec<Bytes>> {
+    let mut chunk = [0u8; READ_CHUNK_SIZE];
+    loop {
+      match self.stream.read(&mut chunk) {
+        Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "TCP connection closed")),
+        Ok(n) => self.receive_buffer.extend_from_slice(&chunk[..n]),
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+        Err(e) => return Err(e),
+      }
+    }
+    let messages = decode_frames(&mut self.receive_buffer)?;
+    // Learn who is at the other end, so that we can send to them by GuidPrefix.
+    if let Some(m) = messages.last() {
+      if m.len() >= 20 && &m[0..4] == b"RTPS" {
+        self.remote_guid_prefix = Some(GuidPrefix::new(&m[8..20]));
+      }
+    }
+    Ok(messages)
+  }
+}
+
+struct TCPConnections {
+  connections: HashMap<Token, TCPConnection>,
+}
+
+/// Handle for sending RTPS messages over the established TCP connections.
+/// Writers and Readers hold a clone of this.
+#[derive(Clone)]
+pub(crate) struct TCPSender {
+  connections: Arc<Mutex<TCPConnections>>,
+}
+
+impl TCPSender {
+  /// Sends to the connection where the given participant has been heard from.
+  /// Returns false, if there is no such connection.
+  pub fn send_to_participant(&self, guid_prefix: GuidPrefix, buffer: &[u8]) -> bool {
+    let mut conns = self.connections.lock().unwrap();
+    match conns.connections.values_mut()
+            .find(|c| c.remote_guid_prefix == Some(guid_prefix)) {
+      Some(conn) => {
+        conn.send(&encode_frame(buffer));
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Sends to a connection whose peer address matches the locator.
+  pub fn send_to_locator(&self, buffer: &[u8], locator: &Locator) {
+    if !locator.isTCP() || locator.port == 0 {
+      return // not reachable by address
+    }
+    let address = locator.to_socket_address();
+    let mut conns = self.connections.lock().unwrap();
+    match conns.connections.values_mut().find(|c| c.peer_address == address) {
+      Some(conn) => conn.send(&encode_frame(buffer)),
+      None => trace!("send_to_locator: no TCP connection to {:?}", address),
+    }
+  }
+
+  pub fn has_connection_to(&self, guid_prefix: GuidPrefix) -> bool {
+    self.connections.lock().unwrap().connections.values()
+      .any(|c| c.remote_guid_prefix == Some(guid_prefix))
+  }
+}
+
+/// TCPv4 transport. This is owned by the DPEventLoop, which calls
+/// `handle_event` for all events with TCP tokens.
+pub(crate) struct TCPTransport {
+  config: TCPTransportConfig,
+  listener: Option<TcpListener>,
+  connections: Arc<Mutex<TCPConnections>>,
+  reconnect_timer: Timer<SocketAddr>,
+  next_connection_token: usize,
+}
+
+impl TCPTransport {
+  pub fn new(config: TCPTransportConfig) -> io::Result<TCPTransport> {
+    let listener = match &config.role {
+      TCPRole::Server { listen_address } => {
+        let l = TcpListener::bind(listen_address)?;
+        info!("TCP transport listening at {:?}", l.local_addr());
+        Some(l)
+      }
+      TCPRole::Client { .. } => None,
+    };
+    Ok(TCPTransport {
+      config,
+      listener,
+      connections: Arc::new(Mutex::new(TCPConnections {
+        connections: HashMap::new(),
+      })),
+      reconnect_timer: Timer::default(),
+      next_connection_token: TCP_CONNECTION_TOKEN_BASE,
+    })
+  }
+
+  pub fn sender(&self) -> TCPSender {
+    TCPSender {
+      connections: self.connections.clone(),
+    }
+  }
+
+  pub fn config(&self) -> &TCPTransportConfig {
+    &self.config
+  }
+
+  pub fn is_tcp_token(token: Token) -> bool {
+    token == TCP_LISTENER_TOKEN
+      || token == TCP_RECONNECT_TIMER_TOKEN
+      || (token.0 >= TCP_CONNECTION_TOKEN_BASE && token.0 < TCP_CONNECTION_TOKEN_LIMIT)
+  }
+
+  /// Register to poll and open client connections.
+  pub fn register(&mut self, poll: &Poll) -> io::Result<()> {
+    if let Some(listener) = &self.listener {
+      poll.register(listener, TCP_LISTENER_TOKEN, Ready::readable(), PollOpt::edge())?;
+    }
+    poll.register(&self.reconnect_timer, TCP_RECONNECT_TIMER_TOKEN,
+      Ready::readable(), PollOpt::edge())?;
+    if let TCPRole::Client { server_addresses } = self.config.role.clone() {
+      for address in server_addresses {
+        self.connect(poll, address);
+      }
+    }
+    Ok(())
+  }
+
+  fn allocate_token(&mut self) -> Token {
+    let conns = self.connections.lock().unwrap();
+    loop {
+      let t = Token(self.next_connection_token);
+      self.next_connection_token += 1;
+      if self.next_connection_token >= TCP_CONNECTION_TOKEN_LIMIT {
+        self.next_connection_token = TCP_CONNECTION_TOKEN_BASE;
+      }
+      if !conns.connections.contains_key(&t) {
+        return t
+      }
+    }
+  }
+
+  fn add_connection(&mut self, poll: &Poll, stream: TcpStream, peer_address: SocketAddr,
+    outbound: bool)
+  {
+    stream.set_nodelay(true).unwrap_or_else(|e| warn!("Cannot set TCP_NODELAY: {:?}", e));
+    let token = self.allocate_token();
+    match poll.register(&stream, token, Ready::readable() | Ready::writable(), PollOpt::edge()) {
+      Ok(()) => {
+        self.connections.lock().unwrap().connections
+          .insert(token, TCPConnection::new(stream, peer_address, outbound));
+      }
+      Err(e) => {
+        error!("Cannot register TCP connection to {:?}: {:?}", peer_address, e);
+        if outbound {
+          self.schedule_reconnect(peer_address);
+        }
+      }
+    }
+  }
+
+  fn connect(&mut self, poll: &Poll, address: SocketAddr) {
+    debug!("TCP connecting to {:?}", address);
+    match TcpStream::connect(&address) {
+      Ok(stream) => self.add_connection(poll, stream, address, true),
+      Err(e) => {
+        warn!("TCP connect to {:?} failed: {:?}", address, e);
+        self.schedule_reconnect(address);
+      }
+    }
+  }
+
+  fn schedule_reconnect(&mut self, address: SocketAddr) {
+    self.reconnect_timer.set_timeout(self.config.reconnect_period, address);
+  }
+
+  fn close_connection(&mut self, poll: &Poll, token: Token, reason: io::Error) {
+    let removed = self.connections.lock().unwrap().connections.remove(&token);
+    if let Some(conn) = removed {
+      info!("TCP connection to {:?} closed: {:?}", conn.peer_address, reason);
+      poll.deregister(&conn.stream).unwrap_or(());
+      if conn.outbound {
+        self.schedule_reconnect(conn.peer_address);
+      }
+    }
+  }
+
+  /// Handle a poll event with a TCP token. Returns the received RTPS messages.
+  pub fn handle_event(&mut self, poll: &Poll, event: &Event) -> Vec<Bytes> {
+    match event.token() {
+      TCP_LISTENER_TOKEN => {
+        let mut accepted = Vec::new();
+        if let Some(listener) = &self.listener {
+          loop {
+            match listener.accept() {
+              Ok((stream, peer)) => accepted.push((stream, peer)),
+              Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+              Err(e) => {
+                error!("TCP accept failed: {:?}", e);
+                break
+              }
+            }
+          }
+        }
+        for (stream, peer) in accepted {
+          info!("TCP connection accepted from {:?}", peer);
+          self.add_connection(poll, stream, peer, false);
+        }
+        vec![]
+      }
+
+      TCP_RECONNECT_TIMER_TOKEN => {
+        while let Some(address) = self.reconnect_timer.poll() {
+          self.connect(poll, address);
+        }
+        vec![]
+      }
+
+      token => {
+        let result = {
+          let mut conns = self.connections.lock().unwrap();
+          match conns.connections.get_mut(&token) {
+            None => return vec![], // already closed
+            Some(conn) => {
+              let readiness = event.readiness();
+              let mut result = match conn.stream.take_error() {
+                Ok(Some(e)) | Err(e) => Err(e),
+                Ok(None) => Ok(vec![]),
+              };
+              if result.is_ok() && readiness.is_writable() {
+                result = conn.flush().map(|_| vec![]);
+              }
+              if result.is_ok() && readiness.is_readable() {
+                result = conn.receive();
+              }
+              result
+            }
+          }
+        };
+        match result {
+          Ok(messages) => messages,
+          Err(e) => {
+            self.close_connection(poll, token, e);
+            vec![]
+          }
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Instant;
+  use mio::Events;
+
+  #[test]
+  fn tcp_frame_round_trip() {
+    let m1: Vec<u8> = b"RTPS\x02\x03\x01\x0fabcdefghijkl".to_vec();
+    let m2: Vec<u8> = vec![1, 2, 3, 4, 5, 6, 7];
+    let mut stream = BytesMut::new();
+    stream.extend_from_slice(&encode_frame(&m1));
+    stream.extend_from_slice(&encode_frame(&m2));
+
+    // deliver in two pieces to test partial frames
+    let second_half = stream.split_off(TCP_HEADER_SIZE + m1.len() + 3);
+    let mut buffer = stream;
+    let first = decode_frames(&mut buffer).unwrap();
+    assert_eq!(first, vec![Bytes::from(m1)]);
+    buffer.extend_from_slice(&second_half);
+    let second = decode_frames(&mut buffer).unwrap();
+    assert_eq!(second, vec![Bytes::from(m2)]);
+    assert!(buffer.is_empty());
+  }
+
+  #[test]
+  fn tcp_frame_bad_magic() {
+    let mut buffer = BytesMut::from(&b"HTTP/1.1 200 OK\r\n"[..]);
+    assert!(decode_frames(&mut buffer).is_err());
+  }
+
+  // A message with an RTPS header, so that the receiver learns the GuidPrefix
+  fn rtps_message(prefix: u8, body: &[u8]) -> Vec<u8> {
+    let mut message = b"RTPS\x02\x03\x01\x0f".to_vec();
+    message.extend_from_slice(&[prefix; 12]);
+    message.extend_from_slice(body);
+    message
+  }
+
+  struct Endpoint {
+    transport: TCPTransport,
+    poll: Poll,
+    events: Events,
+  }
+
+  impl Endpoint {
+    fn new(config: TCPTransportConfig) -> Endpoint {
+      let mut transport = TCPTransport::new(config).unwrap();
+      let poll = Poll::new().unwrap();
+      transport.register(&poll).unwrap();
+      Endpoint {
+        transport,
+        poll,
+        events: Events::with_capacity(16),
+      }
+    }
+
+    fn listen_address(&self) -> SocketAddr {
+      self.transport.listener.as_ref().unwrap().local_addr().unwrap()
+    }
+
+    fn connection_count(&self) -> usize {
+      self.transport.connections.lock().unwrap().connections.len()
+    }
+
+    fn poll_once(&mut self) -> Vec<Bytes> {
+      self.poll.poll(&mut self.events, Some(Duration::from_millis(10))).unwrap();
+      let mut messages = Vec::new();
+      for event in self.events.iter() {
+        assert!(TCPTransport::is_tcp_token(event.token()));
+        messages.extend(self.transport.handle_event(&self.poll, &event));
+      }
+      messages
+    }
+  }
+
+  // Polls both endpoints until `done` holds, or panics after a while
+  fn run_until(
+    a: &mut Endpoint,
+    b: &mut Endpoint,
+    what: &str,
+    mut done: impl FnMut(&mut Endpoint, &mut Endpoint, Vec<Bytes>, Vec<Bytes>) -> bool,
+  ) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+      let from_a = a.poll_once();
+      let from_b = b.poll_once();
+      if done(a, b, from_a, from_b) {
+        return;
+      }
+    }
+    panic!("Timed out waiting for {}", what);
+  }
+
+  fn loopback_server(address: &str) -> Endpoint {
+    Endpoint::new(TCPTransportConfig::server(address.parse().unwrap()))
+  }
+
+  fn client_of(server_address: SocketAddr) -> Endpoint {
+    Endpoint::new(
+      TCPTransportConfig::client(vec![server_address])
+        .reconnect_period(Duration::from_millis(50)),
+    )
+  }
+
+  #[test]
+  fn tcp_connect_accept_and_send() {
+    let mut server = loopback_server("127.0.0.1:0");
+    let server_address = server.listen_address();
+    let mut client = client_of(server_address);
+    run_until(&mut server, &mut client, "accept", |server, _, _, _| {
+      server.connection_count() == 1
+    });
+
+    // client to server by locator, which also tells the server who we are
+    let request = rtps_message(7, b"hello");
+    client
+      .transport
+      .sender()
+      .send_to_locator(&request, &Locator::from_tcp_socket_address(server_address));
+    let mut received = Vec::new();
+    run_until(&mut server, &mut client, "request", |_, _, messages, _| {
+      received.extend(messages);
+      !received.is_empty()
+    });
+    assert_eq!(received, vec![Bytes::from(request)]);
+
+    // and back over the same connection
+    let server_sender = server.transport.sender();
+    let client_prefix = GuidPrefix::new(&[7; 12]);
+    assert!(server_sender.has_connection_to(client_prefix));
+    let reply = rtps_message(9, b"world");
+    assert!(server_sender.send_to_participant(client_prefix, &reply));
+    let mut received = Vec::new();
+    run_until(&mut server, &mut client, "reply", |_, _, _, messages| {
+      received.extend(messages);
+      !received.is_empty()
+    });
+    assert_eq!(received, vec![Bytes::from(reply)]);
+  }
+
+  #[test]
+  fn tcp_client_reconnects() {
+    // A free port, with nobody listening yet
+    let server_address = std::net::TcpListener::bind("127.0.0.1:0")
+      .unwrap()
+      .local_addr()
+      .unwrap();
+    let mut client = client_of(server_address);
+    let mut idle = Endpoint::new(TCPTransportConfig::client(vec![]));
+    run_until(&mut client, &mut idle, "failed connect", |client, _, _, _| {
+      client.connection_count() == 0
+    });
+
+    // The server comes up later, and the client retries.
+    let locator = Locator::from_tcp_socket_address(server_address);
+    let mut server = loopback_server(&server_address.to_string());
+    run_until(&mut server, &mut client, "connect", |server, _, _, _| {
+      server.connection_count() == 1
+    });
+    let mut received = Vec::new();
+    run_until(&mut server, &mut client, "first message", |_, client, messages, _| {
+      client.transport.sender().send_to_locator(b"first", &locator);
+      received.extend(messages);
+      !received.is_empty()
+    });
+    assert_eq!(received[0], Bytes::from(&b"first"[..]));
+
+    // The server goes away, and comes back at the same address.
+    drop(server);
+    run_until(&mut client, &mut idle, "connection loss", |client, _, _, _| {
+      client.connection_count() == 0
+    });
+    let mut server = loopback_server(&server_address.to_string());
+    let mut received = Vec::new();
+    run_until(&mut server, &mut client, "reconnect", |_, client, messages, _| {
+      client.transport.sender().send_to_locator(b"again", &locator);
+      received.extend(messages);
+      !received.is_empty()
+    });
+    assert_eq!(received[0], Bytes::from(&b"again"[..]));
+  }
+}
@@ -0,0 +1,44 @@
+use crate::network::tcp_transport::TCPTransportConfig;
+use crate::network::flow_controller::FlowControllerConfig;
+
+/// Network transport settings of a DomainParticipant.
+///
+/// The default is UDP only, with operating system default socket buffers.
+///
+/// # Examples
+/// ```
+/// # use rustdds::dds::{DomainParticipant, TransportConfig};
+/// let config = TransportConfig::new().udp_receive_buffer_size(4 * 1024 * 1024);
+/// let domain_participant = DomainParticipant::new_with_transport(0, config);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransportConfig {
+  pub(crate) tcp: Option<TCPTransportConfig>,
+  pub(crate) udp_receive_buffer_size: Option<usize>,
+  pub(crate) flow_controllers: Vec<FlowControllerConfig>,
+}
+
+impl TransportConfig {
+  pub fn new() -> TransportConfig {
+    TransportConfig::default()
+  }
+
+  /// Use also RTPS over TCPv4.
+  pub fn tcp(mut self, tcp_config: TCPTransportConfig) -> TransportConfig {
+    self.tcp = Some(tcp_config);
+    self
+  }
+
+  /// Kernel receive buffer size (SO_RCVBUF) in bytes for the UDP sockets.
+  /// Increase this, if bursty high-rate traffic is dropped before it is read.
+  pub fn udp_receive_buffer_size(mut self, bytes: usize) -> TransportConfig {
+    self.udp_receive_buffer_size = Some(bytes);
+    self
+  }
+
+  /// Defines a named flow controller, which DataWriters can then attach to.
+  pub fn flow_controller(mut self, config: FlowControllerConfig) -> TransportConfig {
+    self.flow_controllers.push(config);
+    self
+  }
+}
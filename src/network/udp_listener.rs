@@ -11,7 +11,14 @@ use socket2::{Socket,Domain, Type, SockAddr, Protocol, };
 use bytes::{Bytes,BytesMut};
 
 const MAX_MESSAGE_SIZE : usize = 64 * 1024; // This is max we can get from UDP.
-const MESSAGE_BUFFER_ALLOCATION_CHUNK : usize = 256 * 1024; // must be >= MAX_MESSAGE_SIZE
+const MESSAGE_BUFFER_ALLOCATION_CHUNK : usize = 128 * 1024; // must be >= MAX_MESSAGE_SIZE
+
+// How many datagrams are received with one recvmmsg call. Each of these
+// has its own buffer in the pool.
+#[cfg(target_os = "linux")]
+const RECEIVE_BATCH_SIZE : usize = 16;
+#[cfg(not(target_os = "linux"))]
+const RECEIVE_BATCH_SIZE : usize = 1;
 
 /// Listens to messages coming to specified host port combination.
 /// Only messages from added listen addressed are read when get_all_messages is called.
@@ -19,7 +26,9 @@ const MESSAGE_BUFFER_ALLOCATION_CHUNK : usize = 256 * 1024; // must be >= MAX_ME
 pub struct UDPListener {
   socket: UdpSocket,
   token: Token,
-  receive_buffer: BytesMut,
+  // Pool of receive buffers. Received messages are split off the front of these
+  // and frozen, so the allocations are shared with the returned Bytes.
+  receive_buffers: Vec<BytesMut>,
 }
 
 // TODO: Remove panics from this function. Convert return value to Result.
@@ -53,7 +62,7 @@ impl UDPListener {
     debug!("UDPListener::new with address {:?}", mio_socket.local_addr());
 
     UDPListener { socket: mio_socket, token,
-      receive_buffer: BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK),
+      receive_buffers: UDPListener::new_receive_buffers(),
     }
   }
 
@@ -102,7 +111,7 @@ impl UDPListener {
     debug!("UDPListener::try_bind with address {:?}", socket.local_addr());
 
     Some(UDPListener { socket, token ,
-      receive_buffer: BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK)
+      receive_buffers: UDPListener::new_receive_buffers(),
     })
   }
 
@@ -141,39 +150,141 @@ impl UDPListener {
     message
   }
 
-  fn ensure_receive_buffer_capacity(&mut self) {
-    if self.receive_buffer.capacity() < MAX_MESSAGE_SIZE {
-      self.receive_buffer = BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK);
+  fn new_receive_buffers() -> Vec<BytesMut> {
+    (0..RECEIVE_BATCH_SIZE)
+      .map(|_| BytesMut::with_capacity(MESSAGE_BUFFER_ALLOCATION_CHUNK))
+      .collect()
+  }
+
+  /// Sets the kernel receive buffer size (SO_RCVBUF) of the socket. A larger buffer
+  /// avoids dropping datagrams in the kernel under bursty traffic.
+  /// The operating system may adjust the value, e.g. Linux doubles it and caps
+  /// it at net.core.rmem_max.
+  #[cfg(unix)]
+  pub fn set_receive_buffer_size(&self, size: usize) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let size = size as libc::c_int;
+    let res = unsafe {
+      libc::setsockopt(self.socket.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF,
+        &size as *const libc::c_int as *const libc::c_void,
+        std::mem::size_of::<libc::c_int>() as libc::socklen_t)
+    };
+    if res < 0 { Err(io::Error::last_os_error()) } else { Ok(()) }
+  }
+
+  #[cfg(unix)]
+  pub fn receive_buffer_size(&self) -> io::Result<usize> {
+    use std::os::unix::io::AsRawFd;
+    let mut size: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let res = unsafe {
+      libc::getsockopt(self.socket.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF,
+        &mut size as *mut libc::c_int as *mut libc::c_void, &mut len)
+    };
+    if res < 0 { Err(io::Error::last_os_error()) } else { Ok(size as usize) }
+  }
+
+  #[cfg(not(unix))]
+  pub fn set_receive_buffer_size(&self, _size: usize) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "Setting receive buffer size is not supported"))
+  }
+
+  #[cfg(not(unix))]
+  pub fn receive_buffer_size(&self) -> io::Result<usize> {
+    Err(io::Error::new(io::ErrorKind::Other, "Getting receive buffer size is not supported"))
+  }
+
+  fn ensure_receive_buffer_capacity(buffer: &mut BytesMut) {
+    if buffer.capacity() < MAX_MESSAGE_SIZE {
+      // If all the messages split off from this buffer have been dropped already,
+      // reserve reclaims the old allocation. Otherwise a new chunk is allocated.
+      buffer.clear();
+      buffer.reserve(MESSAGE_BUFFER_ALLOCATION_CHUNK);
       debug!("ensure_receive_buffer_capacity - reallocated receive_buffer");
     }
     unsafe {
       // This is safe, because we just checked that there is enough capacity.
       // We do not read undefined data, because next the recv call in get_messages() 
       // will overwrite this space and truncate the rest away.
-      self.receive_buffer.set_len(MAX_MESSAGE_SIZE)
+      buffer.set_len(MAX_MESSAGE_SIZE)
     }
-    trace!("ensure_receive_buffer_capacity - {} bytes left",self.receive_buffer.capacity());
+    trace!("ensure_receive_buffer_capacity - {} bytes left", buffer.capacity());
   }
 
-  /// Get all messages waiting in the socket.
-  pub fn get_messages(&mut self) -> Vec<Bytes> {
+  // Splits a received message of nbytes off the front of the buffer.
+  fn take_message(buffer: &mut BytesMut, nbytes: usize) -> Bytes {
     // This code may seem slighlty non-sensical, if you do not know
     // how BytesMut works.
+    buffer.truncate(nbytes);
+    // Now append some extra data to align the buffer end, so the next piece will
+    // be aligned also. This assumes that the initial buffer was aligned to begin with.
+    while buffer.len() % 4 != 0 {
+      buffer.extend_from_slice(&[0xCC]); // add some funny padding bytes
+      // Funny value encourages fast crash in case these bytes are ever accessed,
+      // as they should not.
+    }
+    let mut message = buffer.split_to(buffer.len());
+    message.truncate(nbytes); // discard (hide) padding
+    Bytes::from(message) // freeze
+  }
+
+  /// Get all messages waiting in the socket.
+  #[cfg(not(target_os = "linux"))]
+  pub fn get_messages(&mut self) -> Vec<Bytes> {
     let mut messages = Vec::with_capacity(4); // just a guess, should cover most cases
-    self.ensure_receive_buffer_capacity();
-    while let Ok(nbytes) = self.socket.recv(&mut self.receive_buffer) {
-      self.receive_buffer.truncate(nbytes);
-      // Now append some extra data to align the buffer end, so the next piece will
-      // be aligned also. This assumes that the initial buffer was aligned to begin with.
-      while self.receive_buffer.len() % 4 != 0 {
-        self.receive_buffer.extend_from_slice(&[0xCC]); // add some funny padding bytes
-        // Funny value encourages fast crash in case these bytes are ever accessed,
-        // as they should not.
+    let buffer = &mut self.receive_buffers[0];
+    UDPListener::ensure_receive_buffer_capacity(buffer);
+    while let Ok(nbytes) = self.socket.recv(buffer) {
+      messages.push( UDPListener::take_message(buffer, nbytes) );
+      UDPListener::ensure_receive_buffer_capacity(buffer);
+    }
+    messages
+  }
+
+  /// Get all messages waiting in the socket.
+  /// Linux version: receives up to RECEIVE_BATCH_SIZE datagrams per system call.
+  #[cfg(target_os = "linux")]
+  pub fn get_messages(&mut self) -> Vec<Bytes> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut messages = Vec::with_capacity(4); // just a guess, should cover most cases
+    loop {
+      let mut iovecs: Vec<libc::iovec> = self.receive_buffers.iter_mut()
+        .map(|buffer| {
+          UDPListener::ensure_receive_buffer_capacity(buffer);
+          libc::iovec { 
+            iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+            iov_len: MAX_MESSAGE_SIZE,
+          }
+        })
+        .collect();
+      let mut headers: Vec<libc::mmsghdr> = iovecs.iter_mut()
+        .map(|iov| {
+          // mmsghdr has private padding fields on some platforms, so start from zeroes.
+          let mut header: libc::mmsghdr = unsafe { std::mem::zeroed() };
+          header.msg_hdr.msg_iov = iov as *mut libc::iovec;
+          header.msg_hdr.msg_iovlen = 1;
+          header
+        })
+        .collect();
+
+      // Safety: every header points to its own iovec, which points to MAX_MESSAGE_SIZE
+      // bytes of buffer space. All of these outlive the call.
+      let received = unsafe {
+        libc::recvmmsg(self.socket.as_raw_fd(), headers.as_mut_ptr(), headers.len() as _,
+          libc::MSG_DONTWAIT, std::ptr::null_mut())
+      };
+      if received < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::WouldBlock {
+          error!("UDPListener::get_messages - recvmmsg failed: {:?}", err);
+        }
+        break // socket is drained, as required by edge-triggered poll
+      }
+      for (buffer, header) in self.receive_buffers.iter_mut().zip(headers.iter())
+          .take(received as usize) {
+        messages.push( UDPListener::take_message(buffer, header.msg_len as usize) );
       }
-      let mut message = self.receive_buffer.split_to(self.receive_buffer.len());
-      self.ensure_receive_buffer_capacity();
-      message.truncate(nbytes); // discard (hide) padding
-      messages.push( Bytes::from(message) ); // freeze and push
     }
     messages
   }
@@ -259,4 +370,34 @@ mod tests {
     assert_eq!(rec_data.len(), 3);
     assert_eq!(rec_data, data);
   }
+
+  #[test]
+  fn udpl_batch_receive() {
+    let mut listener = UDPListener::new(Token(0), "127.0.0.1", 10003);
+    listener.set_receive_buffer_size(1024 * 1024).unwrap();
+    let sender = UDPSender::new_with_random_port();
+    let addrs = vec![SocketAddr::new("127.0.0.1".parse().unwrap(), 10003)];
+
+    // more than one batch
+    let count = 3 * RECEIVE_BATCH_SIZE + 1;
+    for i in 0..count {
+      sender.send_to_all(&vec![i as u8; 1 + i], &addrs);
+    }
+    thread::sleep(time::Duration::from_millis(100));
+
+    let messages = listener.get_messages();
+    assert_eq!(messages.len(), count);
+    for (i, m) in messages.iter().enumerate() {
+      assert_eq!(m[..], vec![i as u8; 1 + i][..]);
+    }
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn udpl_receive_buffer_size() {
+    let listener = UDPListener::new(Token(0), "127.0.0.1", 10004);
+    listener.set_receive_buffer_size(64 * 1024).unwrap();
+    // Linux reports double the requested size, other systems the requested size.
+    assert!(listener.receive_buffer_size().unwrap() >= 64 * 1024);
+  }
 }
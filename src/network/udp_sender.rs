@@ -5,6 +5,7 @@ use mio::net::UdpSocket;
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::io;
+use bytes::Bytes;
 use crate::structure::locator::{LocatorKind, LocatorList, Locator};
 
 #[derive(Debug)]
@@ -52,16 +53,30 @@ impl UDPSender {
   }
 
   pub fn send_to_locator(&self, buffer: &[u8], l: &Locator) {
+    self.send_to_locator_with(l, buffer.len(), |a| self.socket.send_to(buffer, a))
+  }
+
+  /// Sends a single datagram gathered from several buffers. The buffers are
+  /// not concatenated in user space, so e.g. DATA payloads are sent
+  /// directly from DDSCache.
+  pub fn send_segments_to_locator(&self, segments: &[Bytes], l: &Locator) {
+    let total_len = segments.iter().map(|s| s.len()).sum();
+    self.send_to_locator_with(l, total_len, |a| send_vectored_to(&self.socket, segments, a))
+  }
+
+  fn send_to_locator_with<F>(&self, l: &Locator, len: usize, send: F) 
+  where F: FnOnce(&SocketAddr) -> io::Result<usize>
+  {
       match l.kind {
         LocatorKind::LOCATOR_KIND_UDPv4 |
         LocatorKind::LOCATOR_KIND_UDPv6 => {
           let a = SocketAddr::from(l.to_socket_address());
-          match self.socket.send_to(buffer, &a) {
+          match send(&a) {
             Ok(bytes_sent) =>
-              if bytes_sent == buffer.len() { () // ok
+              if bytes_sent == len { () // ok
               } else {
                 error!("send_to_locator - send_to tried {} bytes, sent only {}",
-                    buffer.len(), bytes_sent);
+                    len, bytes_sent);
               }
             Err(e) => {
               warn!("send_to_locator - send_to {} : {:?}", a, e);
@@ -99,6 +114,53 @@ impl UDPSender {
   }
 }
 
+// POSIX only guarantees this many iovecs per sendmsg call (_XOPEN_IOV_MAX).
+#[cfg(unix)]
+const MIN_IOV_MAX: usize = 16;
+
+// How many iovecs one sendmsg call accepts on this system
+#[cfg(unix)]
+fn iov_max() -> usize {
+  // Safety: sysconf has no preconditions.
+  match unsafe { libc::sysconf(libc::_SC_IOV_MAX) } {
+    n if n > 0 => n as usize,
+    _ => MIN_IOV_MAX, // the limit is not known
+  }
+}
+
+#[cfg(unix)]
+fn send_vectored_to(socket: &UdpSocket, segments: &[Bytes], addr: &SocketAddr) -> io::Result<usize> {
+  use std::os::unix::io::AsRawFd;
+
+  if segments.len() > iov_max() {
+    return socket.send_to(&segments.concat(), addr)
+  }
+  let iovecs: Vec<libc::iovec> = segments.iter()
+    .map(|s| libc::iovec { iov_base: s.as_ptr() as *mut libc::c_void, iov_len: s.len() })
+    .collect();
+  let sockaddr = socket2::SockAddr::from(*addr);
+
+  // msghdr has private padding fields on some platforms, so start from zeroes.
+  let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+  msg.msg_name = sockaddr.as_ptr() as *mut libc::c_void;
+  msg.msg_namelen = sockaddr.len();
+  msg.msg_iov = iovecs.as_ptr() as *mut libc::iovec;
+  msg.msg_iovlen = iovecs.len() as _;
+
+  // Safety: msg points to iovecs and sockaddr, which both outlive the call.
+  let sent = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) };
+  if sent < 0 {
+    Err(io::Error::last_os_error())
+  } else {
+    Ok(sent as usize)
+  }
+}
+
+#[cfg(not(unix))]
+fn send_vectored_to(socket: &UdpSocket, segments: &[Bytes], addr: &SocketAddr) -> io::Result<usize> {
+  socket.send_to(&segments.concat(), addr)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -143,4 +205,32 @@ mod tests {
     assert_eq!(rec_data_2.len(), 6);
     assert_eq!(rec_data_2, data);
   }
+
+  #[test]
+  fn udps_segments_send() {
+    let listener = UDPListener::new(Token(0), "127.0.0.1", 10401);
+    let sender = UDPSender::new(11401);
+
+    let segments = vec![Bytes::from_static(&[1, 2, 3]), Bytes::from_static(&[]),
+                        Bytes::from(vec![4, 5, 6, 7])];
+    let locator = Locator::from(SocketAddr::new("127.0.0.1".parse().unwrap(), 10401));
+    sender.send_segments_to_locator(&segments, &locator);
+
+    let rec_data = listener.get_message();
+    assert_eq!(rec_data, vec![1, 2, 3, 4, 5, 6, 7]);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn udps_more_segments_than_iov_max() {
+    let listener = UDPListener::new(Token(0), "127.0.0.1", 10402);
+    let sender = UDPSender::new(11402);
+
+    let data: Vec<u8> = (0..=iov_max()).map(|i| i as u8).collect();
+    let segments: Vec<Bytes> = data.iter().map(|b| Bytes::copy_from_slice(&[*b])).collect();
+    let locator = Locator::from(SocketAddr::new("127.0.0.1".parse().unwrap(), 10402));
+    sender.send_segments_to_locator(&segments, &locator);
+
+    assert_eq!(listener.get_message(), data);
+  }
 }
@@ -0,0 +1,940 @@
+//! ROS 2 actions
+//!
+//! An action `/name` consists of three services and two topics:
+//! * `/name/_action/send_goal` starts a goal,
+//! * `/name/_action/cancel_goal` requests cancelling goals,
+//! * `/name/_action/get_result` waits for the result of a goal,
+//! * `rt/name/_action/feedback` carries feedback of goals in progress and
+//! * `rt/name/_action/status` carries the status of all goals of the server.
+//!
+//! Goals are identified by UUIDs chosen by the client. The server tracks the
+//! state of each goal with the state machine of `action_msgs/GoalStatus`.
+
+use std::{
+  collections::BTreeMap,
+  fmt,
+  marker::PhantomData,
+  time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use uuid::Uuid;
+
+use crate::{
+  dds::{
+    qos::{
+      policy::{Durability, History, Reliability},
+      QosPolicies,
+    },
+    topic::TopicKind,
+    values::result::Error,
+  },
+  serialization::{CDRDeserializerAdapter, CDRSerializerAdapter},
+  structure::duration::Duration as DDSDuration,
+};
+
+use super::{
+  builtin_datatypes::Time,
+  qos_profiles,
+  service::{Client, ResponseFuture, RmwRequestId, Server, Service, ServiceMapping},
+  RosNode, RosPublisher, RosSubscriber,
+};
+
+/// Goal, result and feedback types of a ROS 2 action
+pub trait Action {
+  type Goal: Serialize + DeserializeOwned + Send + 'static;
+  /// The default value is sent as the result of unknown goals.
+  type Result: Serialize + DeserializeOwned + Clone + Default + Send + 'static;
+  type Feedback: Serialize + DeserializeOwned + Send + 'static;
+
+  /// DDS type name prefix of the action, e.g.
+  /// `example_interfaces::action::dds_::Fibonacci_`
+  fn type_name() -> String;
+}
+
+/// `unique_identifier_msgs/UUID` of a goal
+#[derive(
+  Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct GoalId {
+  uuid: [u8; 16],
+}
+
+impl GoalId {
+  /// The all-zero id, which matches all goals in cancel requests
+  pub const ZERO: GoalId = GoalId { uuid: [0; 16] };
+
+  pub fn new_random() -> GoalId {
+    GoalId {
+      uuid: *Uuid::new_v4().as_bytes(),
+    }
+  }
+
+  pub fn from_bytes(uuid: [u8; 16]) -> GoalId {
+    GoalId { uuid }
+  }
+
+  pub fn as_bytes(&self) -> &[u8; 16] {
+    &self.uuid
+  }
+
+  pub fn is_zero(&self) -> bool {
+    *self == GoalId::ZERO
+  }
+}
+
+impl fmt::Display for GoalId {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fmt::Display::fmt(&Uuid::from_bytes(self.uuid), f)
+  }
+}
+
+/// Status of a goal, as in `action_msgs/GoalStatus`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize_repr, Deserialize_repr)]
+#[repr(i8)]
+pub enum GoalStatusEnum {
+  Unknown = 0,
+  /// Accepted and waiting for execution
+  Accepted = 1,
+  Executing = 2,
+  /// Cancelling has been accepted, but the goal has not finished yet
+  Canceling = 3,
+  Succeeded = 4,
+  Canceled = 5,
+  Aborted = 6,
+}
+
+impl GoalStatusEnum {
+  /// Terminal goals have a result, and their status no longer changes.
+  pub fn is_terminal(self) -> bool {
+    matches!(
+      self,
+      GoalStatusEnum::Succeeded | GoalStatusEnum::Canceled | GoalStatusEnum::Aborted
+    )
+  }
+
+  // Transitions of the goal state machine of rcl_action
+  fn can_become(self, next: GoalStatusEnum) -> bool {
+    use GoalStatusEnum::*;
+    matches!(
+      (self, next),
+      (Accepted, Executing)
+        | (Accepted, Canceling)
+        | (Executing, Canceling)
+        | (Executing, Succeeded)
+        | (Executing, Aborted)
+        | (Canceling, Succeeded)
+        | (Canceling, Aborted)
+        | (Canceling, Canceled)
+    )
+  }
+}
+
+/// `action_msgs/GoalInfo`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoalInfo {
+  pub goal_id: GoalId,
+  /// Time when the goal was accepted
+  pub stamp: Time,
+}
+
+/// `action_msgs/GoalStatus`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoalStatus {
+  pub goal_info: GoalInfo,
+  pub status: GoalStatusEnum,
+}
+
+/// `action_msgs/GoalStatusArray`, published by the server on every change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoalStatusArray {
+  pub status_list: Vec<GoalStatus>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SendGoalRequest<G> {
+  goal_id: GoalId,
+  goal: G,
+}
+
+/// Response to a new goal
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SendGoalResponse {
+  pub accepted: bool,
+  pub stamp: Time,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetResultRequest {
+  goal_id: GoalId,
+}
+
+/// Result of a goal. The status is `Unknown` if the server does not know the
+/// goal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetResultResponse<R> {
+  pub status: GoalStatusEnum,
+  pub result: R,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CancelGoalRequest {
+  goal_info: GoalInfo,
+}
+
+/// Return code of `action_msgs/CancelGoal`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(i8)]
+pub enum CancelGoalResponseCode {
+  ErrorNone = 0,
+  ErrorRejected = 1,
+  ErrorUnknownGoalId = 2,
+  ErrorGoalTerminated = 3,
+}
+
+/// Response of `action_msgs/CancelGoal`: the goals that are being cancelled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CancelGoalResponse {
+  pub return_code: CancelGoalResponseCode,
+  pub goals_canceling: Vec<GoalInfo>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FeedbackMessage<F> {
+  goal_id: GoalId,
+  feedback: F,
+}
+
+struct SendGoalService<A>(PhantomData<A>);
+
+impl<A: Action> Service for SendGoalService<A> {
+  type Request = SendGoalRequest<A::Goal>;
+  type Response = SendGoalResponse;
+  fn type_name() -> String {
+    A::type_name() + "SendGoal_"
+  }
+}
+
+struct GetResultService<A>(PhantomData<A>);
+
+impl<A: Action> Service for GetResultService<A> {
+  type Request = GetResultRequest;
+  type Response = GetResultResponse<A::Result>;
+  fn type_name() -> String {
+    A::type_name() + "GetResult_"
+  }
+}
+
+struct CancelGoalService;
+
+impl Service for CancelGoalService {
+  type Request = CancelGoalRequest;
+  type Response = CancelGoalResponse;
+  fn type_name() -> String {
+    "action_msgs::srv::dds_::CancelGoal_".to_string()
+  }
+}
+
+const GOAL_STATUS_ARRAY_TYPE_NAME: &str = "action_msgs::msg::dds_::GoalStatusArray_";
+
+// rcl_action_qos_profile_status_default: late joiners get the latest status
+fn status_qos() -> QosPolicies {
+  QosPolicies::builder()
+    .durability(Durability::TransientLocal)
+    .reliability(Reliability::Reliable {
+      max_blocking_time: DDSDuration::DURATION_ZERO,
+    })
+    .history(History::KeepLast { depth: 1 })
+    .build()
+}
+
+struct ActionNames {
+  send_goal: String,
+  cancel_goal: String,
+  get_result: String,
+  feedback: String,
+  status: String,
+}
+
+impl ActionNames {
+  // `name` is the fully qualified action name, resolved like a topic name.
+  fn new(name: &str) -> ActionNames {
+    ActionNames {
+      send_goal: format!("{}/_action/send_goal", name),
+      cancel_goal: format!("{}/_action/cancel_goal", name),
+      get_result: format!("{}/_action/get_result", name),
+      feedback: format!("{}/_action/feedback", name),
+      status: format!("{}/_action/status", name),
+    }
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+/// Client of a ROS 2 action. Created with
+/// [`RosNode::create_action_client`](struct.RosNode.html#method.create_action_client).
+pub struct ActionClient<A: Action> {
+  send_goal_client: Client<SendGoalService<A>>,
+  cancel_goal_client: Client<CancelGoalService>,
+  get_result_client: Client<GetResultService<A>>,
+  feedback_subscriber: RosSubscriber<
+    FeedbackMessage<A::Feedback>,
+    CDRDeserializerAdapter<FeedbackMessage<A::Feedback>>,
+  >,
+  status_subscriber: RosSubscriber<GoalStatusArray, CDRDeserializerAdapter<GoalStatusArray>>,
+}
+
+impl<A: Action> ActionClient<A> {
+  pub(crate) fn new(
+    node: &mut RosNode,
+    action_name: &str,
+    mapping: ServiceMapping,
+  ) -> Result<ActionClient<A>, Error> {
+    let names = ActionNames::new(&node.resolve_topic_name(action_name)?);
+    let send_goal_client =
+      node.create_client(&names.send_goal, mapping, qos_profiles::services_default())?;
+    let cancel_goal_client = node.create_client(
+      &names.cancel_goal,
+      mapping,
+      qos_profiles::services_default(),
+    )?;
+    let get_result_client =
+      node.create_client(&names.get_result, mapping, qos_profiles::services_default())?;
+
+    let feedback_topic = node.create_ros_topic(
+      &names.feedback,
+      &(A::type_name() + "FeedbackMessage_"),
+      qos_profiles::services_default(),
+      TopicKind::NoKey,
+    )?;
+    let feedback_subscriber = node.create_ros_nokey_subscriber(feedback_topic, None)?;
+    let status_topic = node.create_ros_topic(
+      &names.status,
+      GOAL_STATUS_ARRAY_TYPE_NAME,
+      status_qos(),
+      TopicKind::NoKey,
+    )?;
+    let status_subscriber = node.create_ros_nokey_subscriber(status_topic, None)?;
+
+    Ok(ActionClient {
+      send_goal_client,
+      cancel_goal_client,
+      get_result_client,
+      feedback_subscriber,
+      status_subscriber,
+    })
+  }
+
+  /// Sends a new goal with a random id and waits until the server accepts
+  /// or rejects it.
+  pub fn send_goal(&self, goal: A::Goal) -> Result<(GoalId, SendGoalResponse), Error> {
+    let goal_id = GoalId::new_random();
+    let response = self
+      .send_goal_client
+      .call(SendGoalRequest { goal_id, goal })?;
+    Ok((goal_id, response))
+  }
+
+  /// Sends a new goal with a random id
+  pub fn send_goal_async(&self, goal: A::Goal) -> (GoalId, ResponseFuture<SendGoalResponse>) {
+    let goal_id = GoalId::new_random();
+    let response = self
+      .send_goal_client
+      .call_async(SendGoalRequest { goal_id, goal });
+    (goal_id, response)
+  }
+
+  /// Requests cancelling a goal, and waits for the answer
+  pub fn cancel_goal(&self, goal_id: GoalId) -> Result<CancelGoalResponse, Error> {
+    self.cancel_goal_client.call(Self::cancel_request(goal_id))
+  }
+
+  pub fn cancel_goal_async(&self, goal_id: GoalId) -> ResponseFuture<CancelGoalResponse> {
+    self
+      .cancel_goal_client
+      .call_async(Self::cancel_request(goal_id))
+  }
+
+  /// Requests cancelling all goals of the server, also those of other
+  /// clients.
+  pub fn cancel_all_goals(&self) -> Result<CancelGoalResponse, Error> {
+    self.cancel_goal(GoalId::ZERO)
+  }
+
+  fn cancel_request(goal_id: GoalId) -> CancelGoalRequest {
+    CancelGoalRequest {
+      goal_info: GoalInfo {
+        goal_id,
+        stamp: Time::ZERO,
+      },
+    }
+  }
+
+  /// Waits until the goal has finished, and returns its result
+  pub fn get_result(&self, goal_id: GoalId) -> Result<GetResultResponse<A::Result>, Error> {
+    self.get_result_client.call(GetResultRequest { goal_id })
+  }
+
+  pub fn get_result_async(&self, goal_id: GoalId) -> ResponseFuture<GetResultResponse<A::Result>> {
+    self
+      .get_result_client
+      .call_async(GetResultRequest { goal_id })
+  }
+
+  /// Takes the next received feedback of any goal of the server
+  pub fn receive_feedback(&mut self) -> Result<Option<(GoalId, A::Feedback)>, Error> {
+    Ok(
+      self
+        .feedback_subscriber
+        .take_next_sample()?
+        .map(|sample| sample.into_value())
+        .map(|message| (message.goal_id, message.feedback)),
+    )
+  }
+
+  /// Takes the next received status of the goals of the server
+  pub fn receive_status(&mut self) -> Result<Option<GoalStatusArray>, Error> {
+    Ok(
+      self
+        .status_subscriber
+        .take_next_sample()?
+        .map(|sample| sample.into_value()),
+    )
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+/// Goal received by an [`ActionServer`](struct.ActionServer.html), which
+/// must be either accepted or rejected
+pub struct NewGoal<G> {
+  request_id: RmwRequestId,
+  goal_id: GoalId,
+  goal: G,
+}
+
+impl<G> NewGoal<G> {
+  pub fn goal_id(&self) -> GoalId {
+    self.goal_id
+  }
+
+  pub fn goal(&self) -> &G {
+    &self.goal
+  }
+}
+
+/// Cancel request received by an [`ActionServer`](struct.ActionServer.html),
+/// which must be answered with
+/// [`respond_to_cancel`](struct.ActionServer.html#method.respond_to_cancel)
+pub struct CancelRequest {
+  request_id: RmwRequestId,
+  goals: Vec<GoalInfo>,
+  failure_code: CancelGoalResponseCode,
+}
+
+impl CancelRequest {
+  /// Goals that the request selects and that can still be cancelled
+  pub fn goals(&self) -> &[GoalInfo] {
+    &self.goals
+  }
+}
+
+struct GoalRecord<R> {
+  stamp: Time,
+  status: GoalStatusEnum,
+  result: Option<R>,
+  waiting_for_result: Vec<RmwRequestId>,
+  finished: Option<Instant>,
+}
+
+/// Default of how long results of finished goals are kept
+pub const DEFAULT_RESULT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Server of a ROS 2 action. Created with
+/// [`RosNode::create_action_server`](struct.RosNode.html#method.create_action_server).
+///
+/// The server does not execute goals by itself. The application receives
+/// new goals and cancel requests, and drives each goal through its states:
+/// accepted, executing, possibly canceling, and finally succeeded, canceled
+/// or aborted. The result is sent when the goal finishes, to the clients
+/// that have requested it by then. Later requests for results are answered
+/// whenever the server receives new goals or cancel requests, so those
+/// should be called regularly.
+pub struct ActionServer<A: Action> {
+  send_goal_server: Server<SendGoalService<A>>,
+  cancel_goal_server: Server<CancelGoalService>,
+  get_result_server: Server<GetResultService<A>>,
+  feedback_publisher:
+    RosPublisher<FeedbackMessage<A::Feedback>, CDRSerializerAdapter<FeedbackMessage<A::Feedback>>>,
+  status_publisher: RosPublisher<GoalStatusArray, CDRSerializerAdapter<GoalStatusArray>>,
+  goals: BTreeMap<GoalId, GoalRecord<A::Result>>,
+  result_timeout: Duration,
+}
+
+impl<A: Action> ActionServer<A> {
+  pub(crate) fn new(
+    node: &mut RosNode,
+    action_name: &str,
+    mapping: ServiceMapping,
+  ) -> Result<ActionServer<A>, Error> {
+    let names = ActionNames::new(&node.resolve_topic_name(action_name)?);
+    let send_goal_server =
+      node.create_service(&names.send_goal, mapping, qos_profiles::services_default())?;
+    let cancel_goal_server = node.create_service(
+      &names.cancel_goal,
+      mapping,
+      qos_profiles::services_default(),
+    )?;
+    let get_result_server =
+      node.create_service(&names.get_result, mapping, qos_profiles::services_default())?;
+
+    let feedback_topic = node.create_ros_topic(
+      &names.feedback,
+      &(A::type_name() + "FeedbackMessage_"),
+      qos_profiles::services_default(),
+      TopicKind::NoKey,
+    )?;
+    let feedback_publisher = node.create_ros_nokey_publisher(feedback_topic, None)?;
+    let status_topic = node.create_ros_topic(
+      &names.status,
+      GOAL_STATUS_ARRAY_TYPE_NAME,
+      status_qos(),
+      TopicKind::NoKey,
+    )?;
+    let status_publisher = node.create_ros_nokey_publisher(status_topic, None)?;
+
+    Ok(ActionServer {
+      send_goal_server,
+      cancel_goal_server,
+      get_result_server,
+      feedback_publisher,
+      status_publisher,
+      goals: BTreeMap::new(),
+      result_timeout: DEFAULT_RESULT_TIMEOUT,
+    })
+  }
+
+  /// Sets how long results of finished goals are kept for clients.
+  /// The default is [`DEFAULT_RESULT_TIMEOUT`](constant.DEFAULT_RESULT_TIMEOUT.html).
+  pub fn set_result_timeout(&mut self, timeout: Duration) {
+    self.result_timeout = timeout;
+  }
+
+  /// Takes the next new goal. Goals with the id of an existing goal are
+  /// rejected without asking.
+  pub fn receive_new_goal(&mut self) -> Result<Option<NewGoal<A::Goal>>, Error> {
+    self.handle_result_requests()?;
+    while let Some((request_id, request)) = self.send_goal_server.receive_request()? {
+      if self.goals.contains_key(&request.goal_id) {
+        warn!("Rejecting goal {} with a duplicate id", request.goal_id);
+        self.send_goal_response(request_id, false, Time::ZERO)?;
+        continue;
+      }
+      return Ok(Some(NewGoal {
+        request_id,
+        goal_id: request.goal_id,
+        goal: request.goal,
+      }));
+    }
+    Ok(None)
+  }
+
+  /// Accepts the goal, which is then in state `Accepted`
+  pub fn accept_goal(&mut self, new_goal: NewGoal<A::Goal>) -> Result<(GoalId, A::Goal), Error> {
+    let stamp = Time::now();
+    self.goals.insert(
+      new_goal.goal_id,
+      GoalRecord {
+        stamp,
+        status: GoalStatusEnum::Accepted,
+        result: None,
+        waiting_for_result: Vec::new(),
+        finished: None,
+      },
+    );
+    self.send_goal_response(new_goal.request_id, true, stamp)?;
+    self.publish_status()?;
+    Ok((new_goal.goal_id, new_goal.goal))
+  }
+
+  pub fn reject_goal(&mut self, new_goal: NewGoal<A::Goal>) -> Result<(), Error> {
+    self.send_goal_response(new_goal.request_id, false, Time::now())
+  }
+
+  fn send_goal_response(
+    &self,
+    request_id: RmwRequestId,
+    accepted: bool,
+    stamp: Time,
+  ) -> Result<(), Error> {
+    self
+      .send_goal_server
+      .send_response(request_id, SendGoalResponse { accepted, stamp })
+  }
+
+  /// Moves an accepted goal to state `Executing`
+  pub fn execute_goal(&mut self, goal_id: GoalId) -> Result<(), Error> {
+    self.change_status(goal_id, GoalStatusEnum::Executing)?;
+    self.publish_status()
+  }
+
+  /// Publishes feedback of a goal that has not finished
+  pub fn publish_feedback(&self, goal_id: GoalId, feedback: A::Feedback) -> Result<(), Error> {
+    match self.goals.get(&goal_id) {
+      Some(goal) if !goal.status.is_terminal() => self
+        .feedback_publisher
+        .write(FeedbackMessage { goal_id, feedback }, None),
+      _ => Error::precondition_not_met("Feedback is only published for unfinished goals."),
+    }
+  }
+
+  /// Takes the next cancel request
+  pub fn receive_cancel_request(&mut self) -> Result<Option<CancelRequest>, Error> {
+    self.handle_result_requests()?;
+    let (request_id, request) = match self.cancel_goal_server.receive_request()? {
+      Some(request) => request,
+      None => return Ok(None),
+    };
+    let GoalInfo { goal_id, stamp } = request.goal_info;
+    let goals = self
+      .goals
+      .iter()
+      .filter(|(_, goal)| !goal.status.is_terminal())
+      .filter(|(id, goal)| {
+        // The rules of action_msgs/CancelGoal
+        let by_id = goal_id.is_zero() && stamp.is_zero() || **id == goal_id;
+        let by_stamp = !stamp.is_zero() && goal.stamp <= stamp;
+        by_id || by_stamp
+      })
+      .map(|(id, goal)| GoalInfo {
+        goal_id: *id,
+        stamp: goal.stamp,
+      })
+      .collect();
+    let failure_code = match self.goals.get(&goal_id) {
+      _ if goal_id.is_zero() => CancelGoalResponseCode::ErrorRejected,
+      None => CancelGoalResponseCode::ErrorUnknownGoalId,
+      Some(goal) if goal.status.is_terminal() => CancelGoalResponseCode::ErrorGoalTerminated,
+      Some(_) => CancelGoalResponseCode::ErrorRejected,
+    };
+    Ok(Some(CancelRequest {
+      request_id,
+      goals,
+      failure_code,
+    }))
+  }
+
+  /// Answers a cancel request. The `accepted` goals, which must be among the
+  /// [`goals`](struct.CancelRequest.html#method.goals) of the request, move
+  /// to state `Canceling`. The request is rejected if none are accepted.
+  pub fn respond_to_cancel(
+    &mut self,
+    cancel_request: CancelRequest,
+    accepted: &[GoalId],
+  ) -> Result<(), Error> {
+    let goals_canceling: Vec<GoalInfo> = cancel_request
+      .goals
+      .into_iter()
+      .filter(|info| accepted.contains(&info.goal_id))
+      .collect();
+    for info in &goals_canceling {
+      self.change_status(info.goal_id, GoalStatusEnum::Canceling)?;
+    }
+    let return_code = if goals_canceling.is_empty() {
+      cancel_request.failure_code
+    } else {
+      CancelGoalResponseCode::ErrorNone
+    };
+    self.cancel_goal_server.send_response(
+      cancel_request.request_id,
+      CancelGoalResponse {
+        return_code,
+        goals_canceling,
+      },
+    )?;
+    self.publish_status()
+  }
+
+  /// Finishes a goal. `status` must be `Succeeded`, `Aborted` or, if the
+  /// goal is being cancelled, `Canceled`. The result is sent to clients
+  /// waiting for it, including those whose requests have not been handled
+  /// yet.
+  pub fn send_result(
+    &mut self,
+    goal_id: GoalId,
+    status: GoalStatusEnum,
+    result: A::Result,
+  ) -> Result<(), Error> {
+    if !status.is_terminal() {
+      return Error::bad_parameter("The final status of a goal must be terminal.");
+    }
+    self.change_status(goal_id, status)?;
+    let goal = match self.goals.get_mut(&goal_id) {
+      Some(goal) => goal,
+      None => return Error::precondition_not_met("Unknown goal"),
+    };
+    goal.finished = Some(Instant::now());
+    let waiting = std::mem::take(&mut goal.waiting_for_result);
+    for request_id in waiting {
+      self.get_result_server.send_response(
+        request_id,
+        GetResultResponse {
+          status,
+          result: result.clone(),
+        },
+      )?;
+    }
+    if let Some(goal) = self.goals.get_mut(&goal_id) {
+      goal.result = Some(result);
+    }
+    // Requests that arrived before the goal finished, but have not been
+    // taken yet, are answered now that the result is known.
+    self.handle_result_requests()?;
+    self.publish_status()
+  }
+
+  /// Current status of a goal, `None` if the goal is unknown or its result
+  /// has expired
+  pub fn goal_status(&self, goal_id: GoalId) -> Option<GoalStatusEnum> {
+    self.goals.get(&goal_id).map(|goal| goal.status)
+  }
+
+  fn change_status(&mut self, goal_id: GoalId, status: GoalStatusEnum) -> Result<(), Error> {
+    match self.goals.get_mut(&goal_id) {
+      Some(goal) if goal.status.can_become(status) => {
+        debug!("Goal {}: {:?} -> {:?}", goal_id, goal.status, status);
+        goal.status = status;
+        Ok(())
+      }
+      Some(goal) => Error::precondition_not_met(&format!(
+        "Goal {} cannot change from {:?} to {:?}",
+        goal_id, goal.status, status
+      )),
+      None => Error::precondition_not_met(&format!("Unknown goal {}", goal_id)),
+    }
+  }
+
+  /// Answers the received requests for results, and forgets finished goals
+  /// whose result has expired. Receiving new goals and cancel requests does
+  /// this too.
+  pub fn handle_result_requests(&mut self) -> Result<(), Error> {
+    let timeout = self.result_timeout;
+    let before = self.goals.len();
+    self
+      .goals
+      .retain(|_, goal| goal.finished.is_none_or(|t| t.elapsed() < timeout));
+    if self.goals.len() != before {
+      self.publish_status()?;
+    }
+
+    while let Some((request_id, request)) = self.get_result_server.receive_request()? {
+      match self.goals.get_mut(&request.goal_id) {
+        Some(goal) => match &goal.result {
+          Some(result) => {
+            let response = GetResultResponse {
+              status: goal.status,
+              result: result.clone(),
+            };
+            self.get_result_server.send_response(request_id, response)?;
+          }
+          None => goal.waiting_for_result.push(request_id),
+        },
+        None => self.get_result_server.send_response(
+          request_id,
+          GetResultResponse {
+            status: GoalStatusEnum::Unknown,
+            result: A::Result::default(),
+          },
+        )?,
+      }
+    }
+    Ok(())
+  }
+
+  fn publish_status(&self) -> Result<(), Error> {
+    let mut status_list: Vec<GoalStatus> = self
+      .goals
+      .iter()
+      .map(|(goal_id, goal)| GoalStatus {
+        goal_info: GoalInfo {
+          goal_id: *goal_id,
+          stamp: goal.stamp,
+        },
+        status: goal.status,
+      })
+      .collect();
+    status_list.sort_by_key(|status| status.goal_info.stamp);
+    self
+      .status_publisher
+      .write(GoalStatusArray { status_list }, None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::thread;
+
+  use super::*;
+
+  use crate::{
+    ros2::{service::block_on_until, NodeOptions, RosParticipant},
+    serialization::cdr_serializer::to_little_endian_binary,
+  };
+
+  struct Fibonacci;
+
+  impl Action for Fibonacci {
+    type Goal = i32;
+    type Result = Vec<i32>;
+    type Feedback = Vec<i32>;
+    fn type_name() -> String {
+      "example_interfaces::action::dds_::Fibonacci_".to_string()
+    }
+  }
+
+  // Repeats `f` until it returns something, or panics after a few seconds.
+  fn wait_for<T>(what: &str, mut f: impl FnMut() -> Option<T>) -> T {
+    for _ in 0..100 {
+      if let Some(t) = f() {
+        return t;
+      }
+      thread::sleep(Duration::from_millis(50));
+    }
+    panic!("Timed out waiting for {}", what)
+  }
+
+  fn wait_response<R>(response: ResponseFuture<R>) -> R {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    block_on_until(response, deadline)
+      .expect("no response")
+      .unwrap()
+  }
+
+  fn server_and_client(
+    node_name: &str,
+  ) -> (RosNode, ActionServer<Fibonacci>, ActionClient<Fibonacci>) {
+    let ros_participant = RosParticipant::new().unwrap();
+    let mut node = ros_participant
+      .new_RosNode(node_name, "/", NodeOptions::new(false))
+      .unwrap();
+    let server = node
+      .create_action_server::<Fibonacci>("/fibonacci", ServiceMapping::default())
+      .unwrap();
+    let client = node
+      .create_action_client::<Fibonacci>("/fibonacci", ServiceMapping::default())
+      .unwrap();
+    (node, server, client)
+  }
+
+  // Sends a goal and lets the server accept it
+  fn start_goal(server: &mut ActionServer<Fibonacci>, client: &ActionClient<Fibonacci>) -> GoalId {
+    let (goal_id, response) = client.send_goal_async(5);
+    let new_goal = wait_for("goal", || server.receive_new_goal().unwrap());
+    assert_eq!(new_goal.goal_id(), goal_id);
+    assert_eq!(*new_goal.goal(), 5);
+    server.accept_goal(new_goal).unwrap();
+    assert!(wait_response(response).accepted);
+    goal_id
+  }
+
+  #[test]
+  fn goal_state_machine() {
+    use GoalStatusEnum::*;
+    assert!(Accepted.can_become(Executing));
+    assert!(Accepted.can_become(Canceling));
+    assert!(Canceling.can_become(Canceled));
+    assert!(Executing.can_become(Succeeded));
+    assert!(!Executing.can_become(Canceled));
+    assert!(!Accepted.can_become(Succeeded));
+    assert!(!Succeeded.can_become(Aborted));
+    assert!(Aborted.is_terminal() && !Canceling.is_terminal());
+  }
+
+  #[test]
+  fn action_messages() {
+    let status = GoalStatusArray {
+      status_list: vec![GoalStatus {
+        goal_info: GoalInfo {
+          goal_id: GoalId::from_bytes([7; 16]),
+          stamp: Time { sec: 1, nanosec: 2 },
+        },
+        status: GoalStatusEnum::Executing,
+      }],
+    };
+    let bytes = to_little_endian_binary(&status).unwrap();
+    assert_eq!(bytes.len(), 4 + 16 + 8 + 1);
+    assert_eq!(&bytes[..4], &[1, 0, 0, 0]);
+    assert_eq!(&bytes[20..], &[1, 0, 0, 0, 2, 0, 0, 0, 2]);
+
+    let names = ActionNames::new("/fibonacci");
+    assert_eq!(names.send_goal, "/fibonacci/_action/send_goal");
+    assert_eq!(names.status, "/fibonacci/_action/status");
+  }
+
+  #[test]
+  fn goal_feedback_result() {
+    let (_node, mut server, mut client) = server_and_client("action_goal");
+    let goal_id = start_goal(&mut server, &client);
+    server.execute_goal(goal_id).unwrap();
+
+    // Requested before the goal finishes, and not polled by the server
+    // afterwards
+    let result = client.get_result_async(goal_id);
+    thread::sleep(Duration::from_millis(200));
+
+    server.publish_feedback(goal_id, vec![0, 1]).unwrap();
+    let (feedback_goal, feedback) = wait_for("feedback", || client.receive_feedback().unwrap());
+    assert_eq!((feedback_goal, feedback), (goal_id, vec![0, 1]));
+
+    server
+      .send_result(goal_id, GoalStatusEnum::Succeeded, vec![0, 1, 1, 2, 3])
+      .unwrap();
+    let result = wait_response(result);
+    assert_eq!(result.status, GoalStatusEnum::Succeeded);
+    assert_eq!(result.result, vec![0, 1, 1, 2, 3]);
+    assert!(server.publish_feedback(goal_id, vec![]).is_err());
+  }
+
+  #[test]
+  fn cancel_goal() {
+    let (_node, mut server, client) = server_and_client("action_cancel");
+    let goal_id = start_goal(&mut server, &client);
+
+    let response = client.cancel_goal_async(goal_id);
+    let request = wait_for("cancel request", || {
+      server.receive_cancel_request().unwrap()
+    });
+    assert_eq!(request.goals().len(), 1);
+    assert_eq!(request.goals()[0].goal_id, goal_id);
+    server.respond_to_cancel(request, &[goal_id]).unwrap();
+    let response = wait_response(response);
+    assert_eq!(response.return_code, CancelGoalResponseCode::ErrorNone);
+    assert_eq!(response.goals_canceling.len(), 1);
+    assert_eq!(server.goal_status(goal_id), Some(GoalStatusEnum::Canceling));
+
+    server
+      .send_result(goal_id, GoalStatusEnum::Canceled, Vec::new())
+      .unwrap();
+    // Requested after the goal finished, so answered while polling
+    let result = client.get_result_async(goal_id);
+    thread::sleep(Duration::from_millis(200));
+    server.handle_result_requests().unwrap();
+    assert_eq!(wait_response(result).status, GoalStatusEnum::Canceled);
+
+    // A finished goal can no longer be cancelled.
+    let response = client.cancel_goal_async(goal_id);
+    let request = wait_for("cancel request", || {
+      server.receive_cancel_request().unwrap()
+    });
+    assert!(request.goals().is_empty());
+    server.respond_to_cancel(request, &[]).unwrap();
+    assert_eq!(
+      wait_response(response).return_code,
+      CancelGoalResponseCode::ErrorGoalTerminated
+    );
+  }
+}
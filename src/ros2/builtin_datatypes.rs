@@ -1,10 +1,17 @@
+use std::{
+  ops::{Add, Sub},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
 use serde::{Serialize, Deserialize};
 
 use crate::{
   dds::traits::key::Key,
-  structure::{guid::GUID, time::Timestamp},
+  structure::{duration::Duration as DDSDuration, guid::GUID, time::Timestamp},
 };
 
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
 /// Analog of DDS GUID in ROS2 builtin datastructures
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Gid {
@@ -21,7 +28,148 @@ impl Gid {
   }
 }
 
-impl Key for Gid {}
+impl Key for Gid {
+  fn is_fixed_size() -> bool {
+    true
+  }
+}
+
+/// ROS2 `builtin_interfaces/Time`. Unlike the RTPS
+/// [DDSTimestamp](../../dds/data_types/struct.DDSTimestamp.html), the fraction
+/// is in nanoseconds.
+#[derive(
+  Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Time {
+  pub sec: i32,
+  pub nanosec: u32,
+}
+
+impl Time {
+  pub const ZERO: Time = Time { sec: 0, nanosec: 0 };
+
+  /// Current system time
+  pub fn now() -> Time {
+    let since_epoch = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default();
+    Time {
+      sec: since_epoch.as_secs() as i32,
+      nanosec: since_epoch.subsec_nanos(),
+    }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    *self == Time::ZERO
+  }
+
+  pub fn from_nanos(nanos: i64) -> Time {
+    Time {
+      sec: nanos.div_euclid(NANOS_PER_SEC) as i32,
+      nanosec: nanos.rem_euclid(NANOS_PER_SEC) as u32,
+    }
+  }
+
+  /// Nanoseconds since the epoch of the clock
+  pub fn to_nanos(&self) -> i64 {
+    self.sec as i64 * NANOS_PER_SEC + self.nanosec as i64
+  }
+}
+
+impl From<Timestamp> for Time {
+  fn from(timestamp: Timestamp) -> Time {
+    Time::from_nanos(timestamp.to_nanos() as i64)
+  }
+}
+
+/// Times before the epoch map to `TIME_ZERO`.
+impl From<Time> for Timestamp {
+  fn from(time: Time) -> Timestamp {
+    Timestamp::from_nanos(time.to_nanos().max(0) as u64)
+  }
+}
+
+impl Add<Duration> for Time {
+  type Output = Time;
+
+  fn add(self, duration: Duration) -> Time {
+    Time::from_nanos(self.to_nanos() + duration.to_nanos())
+  }
+}
+
+impl Sub<Duration> for Time {
+  type Output = Time;
+
+  fn sub(self, duration: Duration) -> Time {
+    Time::from_nanos(self.to_nanos() - duration.to_nanos())
+  }
+}
+
+impl Sub for Time {
+  type Output = Duration;
+
+  fn sub(self, other: Time) -> Duration {
+    Duration::from_nanos(self.to_nanos() - other.to_nanos())
+  }
+}
+
+/// ROS2 `builtin_interfaces/Duration`. The duration is negative when `sec` is
+/// negative, and `nanosec` is always less than a second.
+#[derive(
+  Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct Duration {
+  pub sec: i32,
+  pub nanosec: u32,
+}
+
+impl Duration {
+  pub const ZERO: Duration = Duration { sec: 0, nanosec: 0 };
+
+  pub fn from_nanos(nanos: i64) -> Duration {
+    Duration {
+      sec: nanos.div_euclid(NANOS_PER_SEC) as i32,
+      nanosec: nanos.rem_euclid(NANOS_PER_SEC) as u32,
+    }
+  }
+
+  pub fn to_nanos(&self) -> i64 {
+    self.sec as i64 * NANOS_PER_SEC + self.nanosec as i64
+  }
+}
+
+impl From<DDSDuration> for Duration {
+  fn from(duration: DDSDuration) -> Duration {
+    // rounded, so that conversion back and forth keeps the value
+    let nanos = ((duration.to_ticks() as i128 * NANOS_PER_SEC as i128 + (1 << 31)) >> 32) as i64;
+    Duration::from_nanos(nanos)
+  }
+}
+
+impl From<Duration> for DDSDuration {
+  fn from(duration: Duration) -> DDSDuration {
+    DDSDuration::from_ticks((((duration.to_nanos() as i128) << 32) / NANOS_PER_SEC as i128) as i64)
+  }
+}
+
+impl From<std::time::Duration> for Duration {
+  fn from(duration: std::time::Duration) -> Duration {
+    Duration::from_nanos(duration.as_nanos() as i64)
+  }
+}
+
+/// Negative durations saturate to zero.
+impl From<Duration> for std::time::Duration {
+  fn from(duration: Duration) -> std::time::Duration {
+    std::time::Duration::from_nanos(duration.to_nanos().max(0) as u64)
+  }
+}
+
+/// `rosgraph_msgs/Clock`, published on `/clock` by simulators
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockMessage {
+  pub clock: Time,
+}
 
 /// Information about the node in ROS2 network
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -57,6 +205,14 @@ impl NodeInfo {
     name
   }
 
+  pub fn get_reader_gids(&self) -> &[Gid] {
+    &self.reader_guid
+  }
+
+  pub fn get_writer_gids(&self) -> &[Gid] {
+    &self.writer_guid
+  }
+
   pub fn add_writer(&mut self, gid: Gid) {
     if !self.writer_guid.contains(&gid) {
       self.writer_guid.push(gid);
@@ -105,46 +261,100 @@ impl ROSParticipantInfo {
   }
 }
 
+/// `rcl_interfaces/ParameterEvent`, published when parameters of a node
+/// are declared, changed or undeclared
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParameterEvents {
-  timestamp: Timestamp,
+  pub(crate) timestamp: Time,
   // fully qualified path
-  node: String,
-  new_parameters: Vec<Parameter>,
-  changed_parameters: Vec<Parameter>,
-  deleted_parameters: Vec<Parameter>,
+  pub(crate) node: String,
+  pub(crate) new_parameters: Vec<Parameter>,
+  pub(crate) changed_parameters: Vec<Parameter>,
+  pub(crate) deleted_parameters: Vec<Parameter>,
+}
+
+impl ParameterEvents {
+  pub fn get_timestamp(&self) -> &Time {
+    &self.timestamp
+  }
+
+  /// Fully qualified name of the node
+  pub fn get_node(&self) -> &str {
+    &self.node
+  }
+
+  pub fn get_new_parameters(&self) -> &[Parameter] {
+    &self.new_parameters
+  }
+
+  pub fn get_changed_parameters(&self) -> &[Parameter] {
+    &self.changed_parameters
+  }
+
+  pub fn get_deleted_parameters(&self) -> &[Parameter] {
+    &self.deleted_parameters
+  }
 }
 
+/// `rcl_interfaces/Parameter`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Parameter {
-  name: String,
-  value: ParameterValue,
+  pub(crate) name: String,
+  pub(crate) value: ParameterValue,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Parameter {
+  pub fn get_name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn get_value(&self) -> &ParameterValue {
+    &self.value
+  }
+}
+
+/// `rcl_interfaces/ParameterValue` as it is sent. Convert to
+/// [ros2::ParameterValue](../enum.ParameterValue.html) to access the value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ParameterValue {
-  ptype: u8,
-  boolean_value: bool,
-  int_value: i64,
-  double_value: f64,
-  string_value: String,
-  byte_array: Vec<u8>,
-  bool_array: Vec<bool>,
-  int_array: Vec<i64>,
-  double_array: Vec<f64>,
-  string_array: Vec<String>,
+  pub(crate) ptype: u8,
+  pub(crate) boolean_value: bool,
+  pub(crate) int_value: i64,
+  pub(crate) double_value: f64,
+  pub(crate) string_value: String,
+  pub(crate) byte_array: Vec<u8>,
+  pub(crate) bool_array: Vec<bool>,
+  pub(crate) int_array: Vec<i64>,
+  pub(crate) double_array: Vec<f64>,
+  pub(crate) string_array: Vec<String>,
+}
+
+// Serializes a Timestamp in the layout of builtin_interfaces/Time
+mod timestamp_as_time {
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+  use super::{Time, Timestamp};
+
+  pub fn serialize<S: Serializer>(timestamp: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+    Time::from(*timestamp).serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+    Time::deserialize(deserializer).map(Timestamp::from)
+  }
 }
 
 /// Rosout message structure, received from RosParticipant rosout reader
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Log {
-  timestamp: Timestamp,
-  level: u8,
-  name: String,
-  msg: String,
-  file: String,
-  function: String,
-  line: u32,
+  #[serde(with = "timestamp_as_time")]
+  pub(crate) timestamp: Timestamp,
+  pub(crate) level: u8,
+  pub(crate) name: String,
+  pub(crate) msg: String,
+  pub(crate) file: String,
+  pub(crate) function: String,
+  pub(crate) line: u32,
 }
 
 impl Log {
@@ -153,6 +363,11 @@ impl Log {
     &self.timestamp
   }
 
+  /// Same as [`get_timestamp`](#method.get_timestamp), as ROS time
+  pub fn get_time(&self) -> Time {
+    Time::from(self.timestamp)
+  }
+
   /// Rosout level
   pub fn get_level(&self) -> u8 {
     self.level
@@ -180,3 +395,25 @@ impl Log {
     self.line
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::serialization::cdr_serializer::to_little_endian_binary;
+
+  #[test]
+  fn log_timestamp_is_builtin_time() {
+    let log = Log {
+      timestamp: Timestamp::from(Time { sec: 1, nanosec: 2 }),
+      level: 20,
+      name: String::new(),
+      msg: String::new(),
+      file: String::new(),
+      function: String::new(),
+      line: 0,
+    };
+    assert_eq!(log.get_time(), Time { sec: 1, nanosec: 2 });
+    let bytes = to_little_endian_binary(&log).unwrap();
+    assert_eq!(&bytes[..8], &[1, 0, 0, 0, 2, 0, 0, 0]);
+  }
+}
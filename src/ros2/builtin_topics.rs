@@ -31,6 +31,8 @@ impl ROSDiscoveryTopic {
     lifespan: Some(Lifespan {
       duration: Duration::DURATION_INFINITE,
     }),
+    data_representation: None,
+    type_consistency: None,
   };
 
   const TOPIC_NAME: &'static str = "ros_discovery_info";
@@ -67,6 +69,8 @@ impl ParameterEventsTopic {
     history: Some(History::KeepLast { depth: 1 }),
     resource_limits: None,
     lifespan: None,
+    data_representation: None,
+    type_consistency: None,
   };
 
   const TOPIC_NAME: &'static str = "rt/parameter_events";
@@ -104,11 +108,13 @@ impl RosOutTopic {
       max_blocking_time: Duration::DURATION_ZERO,
     }),
     destination_order: Some(DestinationOrder::ByReceptionTimestamp),
-    history: Some(History::KeepLast { depth: 1 }),
+    history: Some(History::KeepLast { depth: 1000 }),
     resource_limits: None,
     lifespan: Some(Lifespan {
       duration: Duration::from_secs(10),
     }),
+    data_representation: None,
+    type_consistency: None,
   };
 
   const TOPIC_NAME: &'static str = "rt/rosout";
@@ -0,0 +1,470 @@
+//! Clocks of ROS 2 nodes, and timers driven by them
+//!
+//! While the `use_sim_time` parameter of a node is true, e.g. from
+//! `--ros-args -p use_sim_time:=true`, the node subscribes to `/clock`, which
+//! simulators such as Gazebo publish. Its ROS time then follows the received
+//! time instead of the system time, and stays at zero until the first
+//! `/clock` message arrives. When the parameter is set back to false, the
+//! subscription is dropped and the ROS time returns to system time.
+//!
+//! # Example
+//!
+//! ```
+//! use rustdds::ros2::{builtin_datatypes::Duration, NodeOptions, RosParticipant};
+//!
+//! let ros_participant = RosParticipant::new().unwrap();
+//! let node = ros_participant
+//!   .new_RosNode("planner", "/", NodeOptions::new(false).use_sim_time(true))
+//!   .unwrap();
+//! let mut timer = node
+//!   .get_clock()
+//!   .create_timer(Duration::from_nanos(100_000_000))
+//!   .unwrap();
+//! // Fires every 100 ms of simulated time, once /clock is published
+//! if timer.is_ready() {
+//!   println!("plan at {:?}", node.now());
+//! }
+//! ```
+
+use std::{
+  sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+  thread,
+  time::{Duration as StdDuration, Instant},
+};
+
+use log::error;
+use mio::{Events, PollOpt, Ready, Token};
+use mio_extras::channel as mio_channel;
+
+use crate::{
+  dds::{
+    pubsub::Subscriber,
+    topic::{Topic, TopicKind},
+    values::result::Error,
+  },
+  serialization::CDRDeserializerAdapter,
+};
+
+use super::{
+  builtin_datatypes::{ClockMessage, Duration, Time},
+  parameters::{ParameterStore, ParameterValue},
+  qos_profiles,
+  ros_node::NodeThread,
+  RosNode,
+};
+
+/// Parameter that switches the ROS time of a node to `/clock`
+pub(crate) const USE_SIM_TIME: &str = "use_sim_time";
+
+const CLOCK_TOPIC_NAME: &str = "/clock";
+const CLOCK_TYPE_NAME: &str = "rosgraph_msgs::msg::dds_::Clock_";
+
+// Longest time a sleeping thread waits before checking the clock again, so
+// that switching between simulated and system time is noticed.
+const CLOCK_WAIT_INTERVAL: StdDuration = StdDuration::from_millis(100);
+
+/// Source of the time of a [Clock](struct.Clock.html)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClockType {
+  /// System time, or simulated time while it is active
+  RosTime,
+  /// Wall clock time since the Unix epoch
+  SystemTime,
+  /// Monotonic time since the clock was created
+  SteadyTime,
+}
+
+struct ClockState {
+  ros_time_override: Mutex<Option<Time>>,
+  changed: Condvar,
+}
+
+/// Clock of a node. Clones share the same time source.
+#[derive(Clone)]
+pub struct Clock {
+  clock_type: ClockType,
+  steady_origin: Instant,
+  state: Arc<ClockState>,
+}
+
+impl Clock {
+  pub fn new(clock_type: ClockType) -> Clock {
+    Clock {
+      clock_type,
+      steady_origin: Instant::now(),
+      state: Arc::new(ClockState {
+        ros_time_override: Mutex::new(None),
+        changed: Condvar::new(),
+      }),
+    }
+  }
+
+  pub fn clock_type(&self) -> ClockType {
+    self.clock_type
+  }
+
+  /// Current time of the clock
+  pub fn now(&self) -> Time {
+    self.now_with(&self.lock_override())
+  }
+
+  /// Whether a `RosTime` clock follows simulated time
+  pub fn is_ros_time_active(&self) -> bool {
+    self.clock_type == ClockType::RosTime && self.lock_override().is_some()
+  }
+
+  /// Sets the simulated time of a `RosTime` clock, or returns it to system
+  /// time with `None`. This is done by the node when `/clock` is received,
+  /// but can also be used to drive the clock from another source. Other
+  /// clock types ignore the override.
+  pub fn set_ros_time_override(&self, time: Option<Time>) {
+    *self.lock_override() = time;
+    self.state.changed.notify_all();
+  }
+
+  /// Blocks the thread until the clock reaches `until`
+  pub fn sleep_until(&self, until: Time) {
+    while self.now() < until {
+      self.wait_step(until);
+    }
+  }
+
+  /// Blocks the thread for the duration on this clock
+  pub fn sleep_for(&self, duration: Duration) {
+    self.sleep_until(self.now() + duration)
+  }
+
+  /// Creates a timer that fires periodically on this clock
+  pub fn create_timer(&self, period: Duration) -> Result<Timer, Error> {
+    if period.to_nanos() <= 0 {
+      return Error::bad_parameter("Timer period must be positive.");
+    }
+    Ok(Timer {
+      clock: self.clone(),
+      period,
+      next: self.now() + period,
+    })
+  }
+
+  fn lock_override(&self) -> MutexGuard<'_, Option<Time>> {
+    // The lock only guards a value, so a poisoned one is still usable.
+    self
+      .state
+      .ros_time_override
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+  }
+
+  fn now_with(&self, ros_time_override: &Option<Time>) -> Time {
+    match (self.clock_type, ros_time_override) {
+      (ClockType::RosTime, Some(time)) => *time,
+      (ClockType::RosTime, None) | (ClockType::SystemTime, _) => Time::now(),
+      (ClockType::SteadyTime, _) => {
+        Time::from_nanos(self.steady_origin.elapsed().as_nanos() as i64)
+      }
+    }
+  }
+
+  // Waits until the clock may have reached `until`: simulated time is
+  // checked when it changes, other time after sleeping until `until`.
+  fn wait_step(&self, until: Time) {
+    let ros_time_override = self.lock_override();
+    let now = self.now_with(&ros_time_override);
+    if now >= until {
+      return;
+    }
+    if self.clock_type == ClockType::RosTime && ros_time_override.is_some() {
+      let _ = self
+        .state
+        .changed
+        .wait_timeout(ros_time_override, CLOCK_WAIT_INTERVAL);
+    } else {
+      drop(ros_time_override);
+      thread::sleep(StdDuration::from(until - now).min(CLOCK_WAIT_INTERVAL));
+    }
+  }
+}
+
+/// Periodic timer on a [Clock](struct.Clock.html). The timer does not run a
+/// callback, but is polled with [is_ready](#method.is_ready) or waited on
+/// with [wait](#method.wait).
+///
+/// If the timer is not polled for several periods, the missed periods are
+/// skipped. If the clock jumps backwards by more than a period, e.g. when a
+/// simulation is restarted, the timer starts over from the current time.
+pub struct Timer {
+  clock: Clock,
+  period: Duration,
+  next: Time,
+}
+
+impl Timer {
+  pub fn period(&self) -> Duration {
+    self.period
+  }
+
+  pub fn get_clock(&self) -> &Clock {
+    &self.clock
+  }
+
+  /// Time left until the timer fires. Negative if it is overdue.
+  pub fn time_until_trigger(&self) -> Duration {
+    self.next - self.clock.now()
+  }
+
+  /// Checks if the timer has fired since it last fired, and if so,
+  /// schedules the next firing.
+  pub fn is_ready(&mut self) -> bool {
+    let now = self.clock.now();
+    if self.next - now > self.period {
+      self.next = now + self.period;
+    }
+    if now < self.next {
+      return false;
+    }
+    let periods = (now - self.next).to_nanos() / self.period.to_nanos() + 1;
+    self.next = Time::from_nanos(self.next.to_nanos() + periods * self.period.to_nanos());
+    true
+  }
+
+  /// Blocks the thread until the timer fires
+  pub fn wait(&mut self) {
+    while !self.is_ready() {
+      self.clock.wait_step(self.next);
+    }
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+// How often the time source thread checks the use_sim_time parameter
+const TIME_SOURCE_POLL_INTERVAL: StdDuration = StdDuration::from_millis(200);
+
+const CLOCK_TOKEN: Token = Token(0);
+const STOP_TOKEN: Token = Token(1);
+
+fn use_sim_time(store: &Mutex<ParameterStore>) -> Result<bool, Error> {
+  Ok(store.lock()?.get(USE_SIM_TIME) == Some(ParameterValue::Bool(true)))
+}
+
+type ClockReader = super::RosSubscriber<ClockMessage, CDRDeserializerAdapter<ClockMessage>>;
+
+fn create_clock_reader(
+  subscriber: &Subscriber,
+  topic: &Topic,
+  poll: &mio::Poll,
+) -> Result<ClockReader, Error> {
+  let clock_reader = subscriber
+    .create_datareader_no_key::<ClockMessage, CDRDeserializerAdapter<ClockMessage>>(
+      topic.clone(),
+      None,
+      None,
+    )?;
+  poll.register(&clock_reader, CLOCK_TOKEN, Ready::readable(), PollOpt::edge())?;
+  Ok(clock_reader)
+}
+
+fn time_source_thread(
+  store: Weak<Mutex<ParameterStore>>,
+  subscriber: Subscriber,
+  topic: Topic,
+  mut clock_reader: Option<ClockReader>,
+  clock: Clock,
+  poll: mio::Poll,
+  _stop: mio_channel::Receiver<()>,
+) {
+  let mut events = Events::with_capacity(2);
+  let mut latest = Time::ZERO;
+  loop {
+    if let Err(e) = poll.poll(&mut events, Some(TIME_SOURCE_POLL_INTERVAL)) {
+      error!("Time source thread poll failed: {:?}", e);
+      return;
+    }
+    if events.iter().any(|event| event.token() == STOP_TOKEN) {
+      return; // node is being dropped
+    }
+    let use_sim_time = match store.upgrade().map(|store| use_sim_time(&store)) {
+      Some(Ok(use_sim_time)) => use_sim_time,
+      _ => return,
+    };
+    if use_sim_time && clock_reader.is_none() {
+      match create_clock_reader(&subscriber, &topic, &poll) {
+        Ok(reader) => {
+          clock_reader = Some(reader);
+          latest = Time::ZERO;
+        }
+        // Tried again on the next round
+        Err(e) => error!("Subscribing to /clock failed: {:?}", e),
+      }
+    } else if !use_sim_time {
+      // Only the switch back to system time touches the override, which may
+      // otherwise be driven by another source.
+      if let Some(reader) = clock_reader.take() {
+        let _ = poll.deregister(&reader);
+        clock.set_ros_time_override(None);
+      }
+    }
+    if let Some(reader) = clock_reader.as_mut() {
+      loop {
+        match reader.take_next_sample() {
+          Ok(Some(sample)) => latest = sample.into_value().clock,
+          Ok(None) => break,
+          Err(e) => {
+            error!("Reading /clock failed: {:?}", e);
+            break;
+          }
+        }
+      }
+      clock.set_ros_time_override(Some(latest));
+    }
+  }
+}
+
+/// Starts a thread that drives the ROS time of the node by `/clock` while
+/// the `use_sim_time` parameter is true. The thread subscribes to `/clock`
+/// only for that time.
+pub(crate) fn start_time_source(
+  node: &RosNode,
+  store: &Arc<Mutex<ParameterStore>>,
+) -> Result<NodeThread, Error> {
+  let topic = node.create_ros_topic(
+    CLOCK_TOPIC_NAME,
+    CLOCK_TYPE_NAME,
+    qos_profiles::clock(),
+    TopicKind::NoKey,
+  )?;
+  let subscriber = node.ros_subscriber();
+  let clock = node.get_clock();
+  let poll = mio::Poll::new()?;
+  let clock_reader = if use_sim_time(store)? {
+    clock.set_ros_time_override(Some(Time::ZERO));
+    Some(create_clock_reader(&subscriber, &topic, &poll)?)
+  } else {
+    None
+  };
+
+  let weak_store = Arc::downgrade(store);
+  NodeThread::spawn("RustDDS time source", poll, STOP_TOKEN, move |poll, stop| {
+    time_source_thread(weak_store, subscriber, topic, clock_reader, clock, poll, stop)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    ros2::{NodeOptions, RosParticipant},
+    structure::{duration::Duration as DDSDuration, time::Timestamp},
+  };
+
+  #[test]
+  fn time_conversion() {
+    let time = Time {
+      sec: 1_600_000_000,
+      nanosec: 123_456_789,
+    };
+    assert_eq!(Time::from(Timestamp::from(time)), time);
+    assert_eq!(Time::from_nanos(-1), Time { sec: -1, nanosec: 999_999_999 });
+    assert_eq!(Timestamp::from(Time::from_nanos(-1)), Timestamp::TIME_ZERO);
+
+    let duration = Duration::from_nanos(-1_500_000_001);
+    assert_eq!(duration, Duration { sec: -2, nanosec: 499_999_999 });
+    assert_eq!(Duration::from(DDSDuration::from(duration)), duration);
+    assert_eq!(StdDuration::from(duration), StdDuration::from_secs(0));
+    assert_eq!(
+      Duration::from(StdDuration::from_millis(2500)),
+      Duration { sec: 2, nanosec: 500_000_000 }
+    );
+    assert_eq!(time + duration - duration, time);
+    assert_eq!((time + duration) - time, duration);
+  }
+
+  #[test]
+  fn ros_time_override() {
+    let clock = Clock::new(ClockType::RosTime);
+    assert!(!clock.is_ros_time_active());
+    let sim_time = Time { sec: 5, nanosec: 0 };
+    clock.set_ros_time_override(Some(sim_time));
+    assert!(clock.is_ros_time_active());
+    assert_eq!(clock.now(), sim_time);
+    clock.set_ros_time_override(None);
+    assert!(clock.now() > sim_time);
+
+    let steady = Clock::new(ClockType::SteadyTime);
+    steady.set_ros_time_override(Some(sim_time));
+    assert!(!steady.is_ros_time_active());
+    assert!(steady.now() < sim_time);
+  }
+
+  #[test]
+  fn timer_follows_sim_time() {
+    let clock = Clock::new(ClockType::RosTime);
+    clock.set_ros_time_override(Some(Time::ZERO));
+    assert!(clock.create_timer(Duration::ZERO).is_err());
+    let second = Duration::from_nanos(1_000_000_000);
+    let mut timer = clock.create_timer(second).unwrap();
+    assert!(!timer.is_ready());
+    assert_eq!(timer.time_until_trigger(), second);
+
+    clock.set_ros_time_override(Some(Time { sec: 1, nanosec: 0 }));
+    assert!(timer.is_ready());
+    assert!(!timer.is_ready());
+
+    // missed periods are skipped
+    clock.set_ros_time_override(Some(Time { sec: 4, nanosec: 500 }));
+    assert!(timer.is_ready());
+    assert!(!timer.is_ready());
+    assert_eq!(timer.time_until_trigger(), Duration::from_nanos(999_999_500));
+
+    // jump backwards restarts the timer
+    clock.set_ros_time_override(Some(Time { sec: 1, nanosec: 0 }));
+    assert!(!timer.is_ready());
+    assert_eq!(timer.time_until_trigger(), second);
+
+    let sim_clock = clock.clone();
+    let driver = thread::spawn(move || {
+      for sec in 2..4 {
+        thread::sleep(StdDuration::from_millis(10));
+        sim_clock.set_ros_time_override(Some(Time { sec, nanosec: 0 }));
+      }
+    });
+    timer.wait();
+    assert!(clock.now() >= Time { sec: 2, nanosec: 0 });
+    clock.sleep_until(Time { sec: 3, nanosec: 0 });
+    driver.join().unwrap();
+  }
+
+  #[test]
+  fn use_sim_time_parameter() {
+    let ros_participant = RosParticipant::new().unwrap();
+    let from_arguments = ros_participant
+      .new_RosNode(
+        "sim_time_argument",
+        "/",
+        NodeOptions::new(false)
+          .arguments(vec!["--ros-args", "-p", "use_sim_time:=true"])
+          .use_global_arguments(false),
+      )
+      .unwrap();
+    assert!(from_arguments.get_clock().is_ros_time_active());
+    assert_eq!(from_arguments.now(), Time::ZERO);
+
+    let mut later = ros_participant
+      .new_RosNode("sim_time_later", "/", NodeOptions::new(false))
+      .unwrap();
+    let clock = later.get_clock();
+    assert!(!clock.is_ros_time_active());
+    // The /clock reader is created and dropped as the parameter changes.
+    for use_sim_time in [true, false, true, false] {
+      later
+        .set_parameter(USE_SIM_TIME, ParameterValue::Bool(use_sim_time))
+        .unwrap();
+      let deadline = Instant::now() + StdDuration::from_secs(5);
+      while clock.is_ros_time_active() != use_sim_time && Instant::now() < deadline {
+        thread::sleep(StdDuration::from_millis(10));
+      }
+      assert_eq!(clock.is_ros_time_active(), use_sim_time);
+    }
+  }
+}
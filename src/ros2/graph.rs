@@ -0,0 +1,476 @@
+//! View of the ROS 2 graph: nodes, and the topics and services of their
+//! publishers, subscriptions, servers and clients.
+//!
+//! Nodes are announced on `ros_discovery_info`, with the GIDs of their DDS
+//! readers and writers. The readers and writers themselves, with their topics,
+//! types and QoS, are known from DDS discovery (SEDP). The graph combines the
+//! two, and demangles the DDS names to ROS 2 names, e.g. the topic
+//! `rt/turtle1/cmd_vel` of type `geometry_msgs::msg::dds_::Twist_` is
+//! `/turtle1/cmd_vel` of type `geometry_msgs/msg/Twist`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::structure::{entity::RTPSEntity, guid::GUID};
+
+use super::{
+  builtin_datatypes::Gid, qos_profiles::RmwQosProfile, ros_node::RosParticipant,
+};
+
+/// Node name used by rmw for endpoints that no node has announced
+pub const NODE_NAME_UNKNOWN: &str = "_NODE_NAME_UNKNOWN_";
+/// Node namespace used by rmw for endpoints that no node has announced
+pub const NODE_NAMESPACE_UNKNOWN: &str = "_NODE_NAMESPACE_UNKNOWN_";
+
+/// Name and namespace of a node in the graph
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeName {
+  pub namespace: String,
+  pub name: String,
+}
+
+impl NodeName {
+  /// Namespace and name, e.g. `/ros2_demo/turtle_sender`
+  pub fn fully_qualified_name(&self) -> String {
+    format!("{}/{}", self.namespace.trim_end_matches('/'), self.name)
+  }
+
+  fn unknown() -> NodeName {
+    NodeName {
+      namespace: NODE_NAMESPACE_UNKNOWN.to_string(),
+      name: NODE_NAME_UNKNOWN.to_string(),
+    }
+  }
+}
+
+/// Whether an endpoint writes or reads its topic
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EndpointKind {
+  Publisher,
+  Subscription,
+}
+
+/// What the DDS topic of an endpoint is in ROS 2
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EndpointRole {
+  /// Publisher or subscription of a topic
+  Topic,
+  /// Reads requests and writes responses of a service
+  ServiceServer,
+  /// Writes requests and reads responses of a service
+  ServiceClient,
+}
+
+/// DDS reader or writer in the graph, as `rmw_topic_endpoint_info_t`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointInfo {
+  /// Node that announced the endpoint, or `NODE_NAME_UNKNOWN`
+  pub node: NodeName,
+  /// Demangled name of the topic or service
+  pub name: String,
+  /// Demangled type, e.g. `std_msgs/msg/String`
+  pub type_name: String,
+  pub kind: EndpointKind,
+  pub role: EndpointRole,
+  pub gid: Gid,
+  pub qos_profile: RmwQosProfile,
+}
+
+/// Change in the graph, reported by [`RosGraph::refresh`](struct.RosGraph.html#method.refresh)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphEvent {
+  NodeAdded(NodeName),
+  NodeRemoved(NodeName),
+  EndpointAdded(EndpointInfo),
+  EndpointRemoved(EndpointInfo),
+}
+
+// Demangles a DDS topic name. Names without a ROS 2 prefix, such as
+// `ros_discovery_info`, are not part of the graph.
+fn demangle_topic_name(dds_name: &str) -> Option<(EndpointRole, String)> {
+  if let Some(name) = dds_name.strip_prefix("rt/") {
+    return Some((EndpointRole::Topic, format!("/{}", name)));
+  }
+  if let Some(name) = dds_name
+    .strip_prefix("rq/")
+    .and_then(|name| name.strip_suffix("Request"))
+  {
+    return Some((EndpointRole::ServiceServer, format!("/{}", name)));
+  }
+  if let Some(name) = dds_name
+    .strip_prefix("rr/")
+    .and_then(|name| name.strip_suffix("Reply"))
+  {
+    return Some((EndpointRole::ServiceClient, format!("/{}", name)));
+  }
+  None
+}
+
+/// Converts a DDS type name, e.g. `std_msgs::msg::dds_::String_`, to the ROS 2
+/// form `std_msgs/msg/String`. Requests and responses of services convert to
+/// the type of the service. Other names are returned unchanged.
+pub fn demangle_type_name(dds_type_name: &str) -> String {
+  let parts: Vec<&str> = dds_type_name.split("::").collect();
+  match parts.as_slice() {
+    [package, kind, "dds_", type_name] => {
+      let type_name = type_name.strip_suffix('_').unwrap_or(type_name);
+      let type_name = if *kind == "srv" {
+        type_name
+          .strip_suffix("_Request")
+          .or_else(|| type_name.strip_suffix("_Response"))
+          .unwrap_or(type_name)
+      } else {
+        type_name
+      };
+      format!("{}/{}/{}", package, kind, type_name)
+    }
+    _ => dds_type_name.to_string(),
+  }
+}
+
+// State of the graph at one point in time
+#[derive(Default)]
+struct Snapshot {
+  nodes: BTreeSet<NodeName>,
+  endpoints: BTreeMap<Gid, EndpointInfo>,
+}
+
+impl Snapshot {
+  fn take(ros_participant: &RosParticipant) -> Snapshot {
+    let mut snapshot = Snapshot::default();
+    let mut owners: HashMap<Gid, NodeName> = HashMap::new();
+    for node_info in ros_participant.discovered_node_infos() {
+      let node = NodeName {
+        namespace: node_info.get_namespace().to_string(),
+        name: node_info.get_name().to_string(),
+      };
+      for gid in node_info
+        .get_reader_gids()
+        .iter()
+        .chain(node_info.get_writer_gids())
+      {
+        owners.insert(*gid, node.clone());
+      }
+      snapshot.nodes.insert(node);
+    }
+
+    let mut add_endpoint = |kind: EndpointKind,
+                            guid: GUID,
+                            dds_topic_name: &str,
+                            dds_type_name: &str,
+                            qos_profile: RmwQosProfile| {
+      if let Some((role, name)) = demangle_topic_name(dds_topic_name) {
+        // Servers write replies and clients write requests
+        let role = match (role, kind) {
+          (EndpointRole::ServiceServer, EndpointKind::Publisher) => EndpointRole::ServiceClient,
+          (EndpointRole::ServiceClient, EndpointKind::Publisher) => EndpointRole::ServiceServer,
+          (role, _) => role,
+        };
+        let gid = Gid::from_guid(guid);
+        snapshot.endpoints.insert(
+          gid,
+          EndpointInfo {
+            node: owners.get(&gid).cloned().unwrap_or_else(NodeName::unknown),
+            name,
+            type_name: demangle_type_name(dds_type_name),
+            kind,
+            role,
+            gid,
+            qos_profile,
+          },
+        );
+      }
+    };
+
+    let discovery_db = ros_participant.domain_participant().discovery_db();
+    if let Ok(db) = discovery_db.read() {
+      for writer in db
+        .get_all_local_topic_writers()
+        .chain(db.get_external_writer_proxies())
+      {
+        let data = &writer.publication_topic_data;
+        add_endpoint(
+          EndpointKind::Publisher,
+          writer.writer_proxy.remote_writer_guid,
+          &data.topic_name,
+          &data.type_name,
+          RmwQosProfile::from(&data.qos()),
+        );
+      }
+      for reader in db
+        .get_all_local_topic_readers()
+        .chain(db.get_external_reader_proxies())
+      {
+        let data = &reader.subscription_topic_data;
+        add_endpoint(
+          EndpointKind::Subscription,
+          reader.reader_proxy.remote_reader_guid,
+          data.topic_name(),
+          data.type_name(),
+          RmwQosProfile::from(&data.generate_qos()),
+        );
+      }
+    }
+    snapshot
+  }
+
+  fn changes_to(&self, newer: &Snapshot) -> Vec<GraphEvent> {
+    let mut events: Vec<GraphEvent> = self
+      .nodes
+      .difference(&newer.nodes)
+      .map(|node| GraphEvent::NodeRemoved(node.clone()))
+      .chain(
+        newer
+          .nodes
+          .difference(&self.nodes)
+          .map(|node| GraphEvent::NodeAdded(node.clone())),
+      )
+      .collect();
+    for (gid, endpoint) in self.endpoints.iter() {
+      match newer.endpoints.get(gid) {
+        Some(newer_endpoint) if newer_endpoint == endpoint => (),
+        // e.g. the node announcing the endpoint was discovered only now
+        Some(newer_endpoint) => {
+          events.push(GraphEvent::EndpointRemoved(endpoint.clone()));
+          events.push(GraphEvent::EndpointAdded(newer_endpoint.clone()));
+        }
+        None => events.push(GraphEvent::EndpointRemoved(endpoint.clone())),
+      }
+    }
+    for (gid, endpoint) in newer.endpoints.iter() {
+      if !self.endpoints.contains_key(gid) {
+        events.push(GraphEvent::EndpointAdded(endpoint.clone()));
+      }
+    }
+    events
+  }
+}
+
+/// ROS 2 graph as seen by a [RosParticipant](struct.RosParticipant.html).
+/// Created with [`RosParticipant::graph`](struct.RosParticipant.html#method.graph).
+///
+/// The graph is a snapshot. [`refresh`](#method.refresh) updates it and
+/// reports what changed, e.g. when the participant becomes readable or
+/// periodically.
+///
+/// # Example
+///
+/// ```
+/// use rustdds::ros2::RosParticipant;
+///
+/// let ros_participant = RosParticipant::new().unwrap();
+/// let mut graph = ros_participant.graph();
+/// # std::thread::sleep(std::time::Duration::from_millis(100));
+/// for event in graph.refresh() {
+///   println!("{:?}", event);
+/// }
+/// for node in graph.node_names() {
+///   println!("{}", node.fully_qualified_name());
+///   for (topic, types) in graph.publisher_names_and_types_by_node(&node) {
+///     println!("  publishes {}: {}", topic, types.join(", "));
+///   }
+/// }
+/// ```
+pub struct RosGraph {
+  ros_participant: RosParticipant,
+  snapshot: Snapshot,
+}
+
+impl RosGraph {
+  pub(crate) fn new(ros_participant: RosParticipant) -> RosGraph {
+    let snapshot = Snapshot::take(&ros_participant);
+    RosGraph {
+      ros_participant,
+      snapshot,
+    }
+  }
+
+  /// Updates the graph from discovery, and returns the changes since the
+  /// previous update. Node announcements that are read here are no longer
+  /// returned by
+  /// [`RosParticipant::handle_node_read`](struct.RosParticipant.html#method.handle_node_read).
+  pub fn refresh(&mut self) -> Vec<GraphEvent> {
+    let snapshot = Snapshot::take(&self.ros_participant);
+    let events = self.snapshot.changes_to(&snapshot);
+    self.snapshot = snapshot;
+    events
+  }
+
+  /// GUID of the participant whose view this is
+  pub fn participant_guid(&self) -> GUID {
+    self.ros_participant.domain_participant().get_guid()
+  }
+
+  /// Nodes of the graph, including those of our own participant
+  pub fn node_names(&self) -> Vec<NodeName> {
+    self.snapshot.nodes.iter().cloned().collect()
+  }
+
+  /// All readers and writers of topics and services
+  pub fn endpoints(&self) -> impl Iterator<Item = &EndpointInfo> {
+    self.snapshot.endpoints.values()
+  }
+
+  fn names_and_types<F>(&self, filter: F) -> BTreeMap<String, Vec<String>>
+  where
+    F: Fn(&EndpointInfo) -> bool,
+  {
+    let mut names_and_types: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for endpoint in self.endpoints().filter(|endpoint| filter(endpoint)) {
+      let types = names_and_types.entry(endpoint.name.clone()).or_default();
+      if !types.contains(&endpoint.type_name) {
+        types.push(endpoint.type_name.clone());
+      }
+    }
+    names_and_types
+  }
+
+  /// Topics with their types. A topic has more than one type if the
+  /// endpoints disagree.
+  pub fn topic_names_and_types(&self) -> BTreeMap<String, Vec<String>> {
+    self.names_and_types(|endpoint| endpoint.role == EndpointRole::Topic)
+  }
+
+  /// Services with their types
+  pub fn service_names_and_types(&self) -> BTreeMap<String, Vec<String>> {
+    self.names_and_types(|endpoint| endpoint.role != EndpointRole::Topic)
+  }
+
+  /// Publishers of a topic, by its fully qualified ROS 2 name
+  pub fn publishers_info_by_topic(&self, topic_name: &str) -> Vec<EndpointInfo> {
+    self.topic_endpoints(topic_name, EndpointKind::Publisher)
+  }
+
+  /// Subscriptions of a topic, by its fully qualified ROS 2 name
+  pub fn subscriptions_info_by_topic(&self, topic_name: &str) -> Vec<EndpointInfo> {
+    self.topic_endpoints(topic_name, EndpointKind::Subscription)
+  }
+
+  fn topic_endpoints(&self, topic_name: &str, kind: EndpointKind) -> Vec<EndpointInfo> {
+    self
+      .endpoints()
+      .filter(|endpoint| {
+        endpoint.role == EndpointRole::Topic && endpoint.kind == kind && endpoint.name == topic_name
+      })
+      .cloned()
+      .collect()
+  }
+
+  /// Topics that the node publishes, with their types
+  pub fn publisher_names_and_types_by_node(&self, node: &NodeName) -> BTreeMap<String, Vec<String>> {
+    self.names_and_types(|endpoint| {
+      &endpoint.node == node
+        && endpoint.role == EndpointRole::Topic
+        && endpoint.kind == EndpointKind::Publisher
+    })
+  }
+
+  /// Topics that the node subscribes to, with their types
+  pub fn subscriber_names_and_types_by_node(
+    &self,
+    node: &NodeName,
+  ) -> BTreeMap<String, Vec<String>> {
+    self.names_and_types(|endpoint| {
+      &endpoint.node == node
+        && endpoint.role == EndpointRole::Topic
+        && endpoint.kind == EndpointKind::Subscription
+    })
+  }
+
+  /// Services that the node serves, with their types
+  pub fn service_names_and_types_by_node(&self, node: &NodeName) -> BTreeMap<String, Vec<String>> {
+    self.names_and_types(|endpoint| {
+      &endpoint.node == node && endpoint.role == EndpointRole::ServiceServer
+    })
+  }
+
+  /// Services that the node is a client of, with their types
+  pub fn client_names_and_types_by_node(&self, node: &NodeName) -> BTreeMap<String, Vec<String>> {
+    self.names_and_types(|endpoint| {
+      &endpoint.node == node && endpoint.role == EndpointRole::ServiceClient
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn demangling() {
+    assert_eq!(
+      demangle_topic_name("rt/turtle1/cmd_vel"),
+      Some((EndpointRole::Topic, "/turtle1/cmd_vel".to_string()))
+    );
+    assert_eq!(
+      demangle_topic_name("rq/add_two_intsRequest"),
+      Some((EndpointRole::ServiceServer, "/add_two_ints".to_string()))
+    );
+    assert_eq!(
+      demangle_topic_name("rr/add_two_intsReply"),
+      Some((EndpointRole::ServiceClient, "/add_two_ints".to_string()))
+    );
+    assert_eq!(demangle_topic_name("ros_discovery_info"), None);
+    assert_eq!(demangle_topic_name("rq/no_suffix"), None);
+
+    assert_eq!(
+      demangle_type_name("geometry_msgs::msg::dds_::Twist_"),
+      "geometry_msgs/msg/Twist"
+    );
+    assert_eq!(
+      demangle_type_name("example_interfaces::srv::dds_::AddTwoInts_Request_"),
+      "example_interfaces/srv/AddTwoInts"
+    );
+    assert_eq!(
+      demangle_type_name("example_interfaces::srv::dds_::AddTwoInts_Response_"),
+      "example_interfaces/srv/AddTwoInts"
+    );
+    assert_eq!(demangle_type_name("NodeInfo"), "NodeInfo");
+  }
+
+  #[test]
+  fn snapshot_changes() {
+    let node = NodeName {
+      namespace: "/".to_string(),
+      name: "talker".to_string(),
+    };
+    let endpoint = EndpointInfo {
+      node: NodeName::unknown(),
+      name: "/chatter".to_string(),
+      type_name: "std_msgs/msg/String".to_string(),
+      kind: EndpointKind::Publisher,
+      role: EndpointRole::Topic,
+      gid: Gid::from_guid(GUID::GUID_UNKNOWN),
+      qos_profile: RmwQosProfile::from(&super::super::qos_profiles::default()),
+    };
+    let empty = Snapshot::default();
+    let mut first = Snapshot::default();
+    first.endpoints.insert(endpoint.gid, endpoint.clone());
+    assert_eq!(
+      empty.changes_to(&first),
+      vec![GraphEvent::EndpointAdded(endpoint.clone())]
+    );
+
+    let mut second = Snapshot::default();
+    second.nodes.insert(node.clone());
+    let owned = EndpointInfo {
+      node: node.clone(),
+      ..endpoint.clone()
+    };
+    second.endpoints.insert(owned.gid, owned.clone());
+    assert_eq!(
+      first.changes_to(&second),
+      vec![
+        GraphEvent::NodeAdded(node.clone()),
+        GraphEvent::EndpointRemoved(endpoint),
+        GraphEvent::EndpointAdded(owned.clone()),
+      ]
+    );
+    assert_eq!(
+      second.changes_to(&empty),
+      vec![
+        GraphEvent::NodeRemoved(node),
+        GraphEvent::EndpointRemoved(owned),
+      ]
+    );
+  }
+}
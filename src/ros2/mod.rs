@@ -50,10 +50,33 @@
 pub mod builtin_datatypes;
 /// Some convenience topic infos for ROS2 communication
 pub mod builtin_topics;
+pub mod qos_profiles;
 
+pub(crate) mod action;
+pub(crate) mod clock;
+pub(crate) mod graph;
+pub(crate) mod names;
+pub(crate) mod parameters;
 pub(crate) mod ros_node;
+pub(crate) mod rosout;
+pub(crate) mod service;
 
+pub use action::{
+  Action, ActionClient, ActionServer, CancelGoalResponse, CancelGoalResponseCode, CancelRequest,
+  GetResultResponse, GoalId, GoalInfo, GoalStatus, GoalStatusArray, GoalStatusEnum, NewGoal,
+  SendGoalResponse, DEFAULT_RESULT_TIMEOUT,
+};
+pub use clock::{Clock, ClockType, Timer};
+pub use graph::{
+  demangle_type_name, EndpointInfo, EndpointKind, EndpointRole, GraphEvent, NodeName, RosGraph,
+  NODE_NAMESPACE_UNKNOWN, NODE_NAME_UNKNOWN,
+};
+pub use parameters::{
+  FloatingPointRange, IntegerRange, ParameterDescriptor, ParameterType, ParameterValue,
+};
 pub use ros_node::*;
+pub use rosout::{log_level, RosoutLogger};
+pub use service::{Client, ResponseFuture, RmwRequestId, Server, Service, ServiceMapping};
 
 pub type RosSubscriber<D, DA> = crate::dds::no_key::datareader::DataReader<D, DA>;
 
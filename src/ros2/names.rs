@@ -0,0 +1,450 @@
+//! Validation, expansion and remapping of ROS 2 node, namespace, topic and
+//! service names, following the
+//! [ROS 2 design article](https://design.ros2.org/articles/topic_and_service_names.html)
+//! and the remapping rules of `--ros-args -r from:=to`.
+
+use log::warn;
+
+use crate::dds::values::result::Error;
+
+use super::parameters::ParameterOverride;
+
+fn is_name_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Checks that `name` is a valid base name of a node, e.g. `turtle_sender`.
+pub(crate) fn validate_node_name(name: &str) -> Result<(), Error> {
+  if name.is_empty() {
+    return Error::bad_parameter("Node name must not be empty.");
+  }
+  if !name.chars().all(is_name_char) {
+    return Error::bad_parameter(&format!(
+      "Node name {:?} may contain only alphanumeric characters and underscores.",
+      name
+    ));
+  }
+  if name.starts_with(|c: char| c.is_ascii_digit()) {
+    return Error::bad_parameter(&format!(
+      "Node name {:?} must not start with a number.",
+      name
+    ));
+  }
+  Ok(())
+}
+
+/// Validates a node namespace and returns it in absolute form. An empty
+/// namespace is the root namespace `/`, and a relative one is made absolute.
+pub(crate) fn normalize_namespace(namespace: &str) -> Result<String, Error> {
+  let namespace = if namespace.starts_with('/') {
+    namespace.to_string()
+  } else {
+    format!("/{}", namespace)
+  };
+  if namespace == "/" {
+    return Ok(namespace);
+  }
+  validate_fully_qualified_name(&namespace)
+    .map_err(|_| Error::BadParameter {
+      reason: format!("Invalid node namespace {:?}.", namespace),
+    })?;
+  Ok(namespace)
+}
+
+/// Checks a topic or service name as given by the user, before expansion.
+pub(crate) fn validate_topic_name(name: &str) -> Result<(), Error> {
+  let invalid = |reason: &str| Error::bad_parameter(&format!("Invalid name {:?}: {}", name, reason));
+
+  if name.is_empty() {
+    return invalid("must not be empty");
+  }
+  if name.starts_with(|c: char| c.is_ascii_digit()) {
+    return invalid("must not start with a number");
+  }
+  if name.ends_with('/') {
+    return invalid("must not end with a forward slash");
+  }
+  if name.contains("//") {
+    return invalid("must not contain repeated forward slashes");
+  }
+  if name.contains("__") {
+    return invalid("must not contain repeated underscores");
+  }
+  if let Some(rest) = name.strip_prefix('~') {
+    if !rest.is_empty() && !rest.starts_with('/') {
+      return invalid("tilde must be followed by a forward slash");
+    }
+  }
+
+  let mut in_substitution = false;
+  for (i, c) in name.char_indices() {
+    match c {
+      '~' if i == 0 => (),
+      '{' if !in_substitution => in_substitution = true,
+      '}' if in_substitution => {
+        if name[..i].ends_with('{') {
+          return invalid("substitution must not be empty");
+        }
+        in_substitution = false
+      }
+      '/' if !in_substitution => (),
+      c if is_name_char(c) => (),
+      _ => return invalid("contains an invalid character or unbalanced braces"),
+    }
+  }
+  if in_substitution {
+    return invalid("has unbalanced curly braces");
+  }
+  Ok(())
+}
+
+// A fully qualified name is absolute and consists of non-empty tokens of
+// alphanumeric characters and underscores that do not start with a number.
+fn validate_fully_qualified_name(name: &str) -> Result<(), Error> {
+  let valid = match name.strip_prefix('/') {
+    Some(tokens) => {
+      !name.contains("__")
+        && tokens.split('/').all(|token| {
+          !token.is_empty()
+            && token.chars().all(is_name_char)
+            && !token.starts_with(|c: char| c.is_ascii_digit())
+        })
+    }
+    None => false,
+  };
+  if valid {
+    Ok(())
+  } else {
+    Error::bad_parameter(&format!("Invalid fully qualified name {:?}.", name))
+  }
+}
+
+/// Expands a topic or service name of a node to a fully qualified name.
+///
+/// A leading `~` is the private namespace of the node, i.e. its namespace and
+/// name. The substitutions `{node}`, `{ns}` and `{namespace}` are replaced
+/// with the node name and namespace. A relative name is put under the
+/// namespace of the node.
+pub(crate) fn expand_topic_name(
+  name: &str,
+  node_name: &str,
+  namespace: &str,
+) -> Result<String, Error> {
+  validate_topic_name(name)?;
+  let namespace = namespace.trim_end_matches('/');
+
+  let (mut expanded, mut rest) = match name.strip_prefix('~') {
+    Some(rest) => (format!("{}/{}", namespace, node_name), rest),
+    None => (String::new(), name),
+  };
+  while let Some(start) = rest.find('{') {
+    // Braces are balanced after validation.
+    let end = start + rest[start..].find('}').unwrap_or(0);
+    expanded.push_str(&rest[..start]);
+    match &rest[start + 1..end] {
+      "node" => expanded.push_str(node_name),
+      "ns" | "namespace" => expanded.push_str(namespace),
+      other => {
+        return Error::bad_parameter(&format!(
+          "Unknown substitution {{{}}} in name {:?}.",
+          other, name
+        ))
+      }
+    }
+    rest = &rest[end + 1..];
+  }
+  expanded.push_str(rest);
+
+  // The root namespace substitutes to an empty string, which may leave a
+  // slash at the start or a double slash in between.
+  while expanded.contains("//") {
+    expanded = expanded.replace("//", "/");
+  }
+  if !expanded.starts_with('/') {
+    expanded = format!("{}/{}", namespace, expanded);
+  }
+  validate_fully_qualified_name(&expanded)?;
+  Ok(expanded)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RemapKind {
+  NodeName,
+  Namespace,
+  Name,
+}
+
+/// Remapping rule `[node:]from:=to`, as given with `--ros-args -r`. The
+/// special names `__node` (or `__name`) and `__ns` on the left side rename
+/// the node and change its namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RemapRule {
+  node: Option<String>,
+  kind: RemapKind,
+  from: String,
+  to: String,
+}
+
+impl RemapRule {
+  pub(crate) fn parse(rule: &str) -> Result<RemapRule, Error> {
+    let invalid = || Error::BadParameter {
+      reason: format!("Invalid remapping rule {:?}.", rule),
+    };
+    let (lhs, to) = match rule.find(":=") {
+      Some(i) => (&rule[..i], &rule[i + 2..]),
+      None => return Err(invalid()),
+    };
+    let (node, from) = match lhs.find(':') {
+      Some(i) => (Some(&lhs[..i]), &lhs[i + 1..]),
+      None => (None, lhs),
+    };
+    if let Some(node) = node {
+      validate_node_name(node).map_err(|_| invalid())?;
+    }
+    let kind = match from {
+      "__node" | "__name" => {
+        validate_node_name(to).map_err(|_| invalid())?;
+        RemapKind::NodeName
+      }
+      "__ns" => {
+        if !to.starts_with('/') {
+          return Err(invalid());
+        }
+        normalize_namespace(to).map_err(|_| invalid())?;
+        RemapKind::Namespace
+      }
+      _ => {
+        validate_topic_name(from).map_err(|_| invalid())?;
+        validate_topic_name(to).map_err(|_| invalid())?;
+        RemapKind::Name
+      }
+    };
+    Ok(RemapRule {
+      node: node.map(String::from),
+      kind,
+      from: from.to_string(),
+      to: to.to_string(),
+    })
+  }
+
+  fn applies_to(&self, kind: RemapKind, node_name: &str) -> bool {
+    self.kind == kind && self.node.as_deref().is_none_or(|node| node == node_name)
+  }
+}
+
+/// Rules collected from command line style arguments
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RosArguments {
+  pub(crate) remappings: Vec<RemapRule>,
+  pub(crate) parameters: Vec<ParameterOverride>,
+}
+
+impl RosArguments {
+  /// Adds the rules of `other` after these, so that these take precedence.
+  pub(crate) fn extend(&mut self, other: RosArguments) {
+    self.remappings.extend(other.remappings);
+    self.parameters.extend(other.parameters);
+  }
+}
+
+/// Collects the remapping rules and parameter overrides from command line
+/// style arguments. They are given as `-r rule` or `--remap rule`, and as
+/// `-p override` or `--param override`, between `--ros-args` and an optional
+/// `--`. Other ROS arguments are ignored.
+pub(crate) fn parse_ros_arguments<S: AsRef<str>>(arguments: &[S]) -> Result<RosArguments, Error> {
+  let (rules, errors) = collect_ros_arguments(arguments);
+  match errors.into_iter().next() {
+    Some(e) => Err(e),
+    None => Ok(rules),
+  }
+}
+
+/// The remapping rules and parameter overrides in the arguments of the
+/// process. Malformed rules are skipped with a warning, so that they do not
+/// make every node of the process fail. So are arguments that are not valid
+/// Unicode.
+pub(crate) fn process_ros_arguments() -> RosArguments {
+  let arguments: Vec<String> = std::env::args_os()
+    .map(|argument| {
+      argument.into_string().unwrap_or_else(|argument| {
+        warn!(
+          "Skipping argument {:?}, which is not valid Unicode.",
+          argument
+        );
+        // Keeps the place of the argument, e.g. as the rule after -r, which
+        // is then malformed.
+        String::new()
+      })
+    })
+    .collect();
+  let (rules, errors) = collect_ros_arguments(&arguments);
+  for e in errors {
+    warn!("Skipping ROS argument of the process: {:?}", e);
+  }
+  rules
+}
+
+// Parses all arguments, and collects the errors of malformed rules instead of
+// stopping at the first.
+fn collect_ros_arguments<S: AsRef<str>>(arguments: &[S]) -> (RosArguments, Vec<Error>) {
+  let mut rules = RosArguments::default();
+  let mut errors = Vec::new();
+  let mut in_ros_args = false;
+  let mut arguments = arguments.iter().map(AsRef::as_ref);
+  while let Some(argument) = arguments.next() {
+    match argument {
+      "--ros-args" => in_ros_args = true,
+      "--" => in_ros_args = false,
+      "-r" | "--remap" if in_ros_args => match arguments.next().map(RemapRule::parse) {
+        Some(Ok(rule)) => rules.remappings.push(rule),
+        Some(Err(e)) => errors.push(e),
+        None => errors.push(Error::BadParameter {
+          reason: "Missing remapping rule after -r.".to_string(),
+        }),
+      },
+      "-p" | "--param" if in_ros_args => match arguments.next().map(ParameterOverride::parse) {
+        Some(Ok(rule)) => rules.parameters.push(rule),
+        Some(Err(e)) => errors.push(e),
+        None => errors.push(Error::BadParameter {
+          reason: "Missing parameter override after -p.".to_string(),
+        }),
+      },
+      _ => (),
+    }
+  }
+  (rules, errors)
+}
+
+/// The node name after remapping. The first matching rule applies.
+pub(crate) fn remap_node_name(rules: &[RemapRule], node_name: &str) -> String {
+  rules
+    .iter()
+    .find(|rule| rule.applies_to(RemapKind::NodeName, node_name))
+    .map_or(node_name, |rule| rule.to.as_str())
+    .to_string()
+}
+
+/// The namespace after remapping. `node_name` is the name before remapping.
+pub(crate) fn remap_namespace(rules: &[RemapRule], node_name: &str, namespace: &str) -> String {
+  rules
+    .iter()
+    .find(|rule| rule.applies_to(RemapKind::Namespace, node_name))
+    .map_or(namespace, |rule| rule.to.as_str())
+    .to_string()
+}
+
+/// Expands a topic or service name and applies the first rule whose
+/// expanded left side equals it.
+pub(crate) fn resolve_topic_name(
+  rules: &[RemapRule],
+  name: &str,
+  node_name: &str,
+  namespace: &str,
+) -> Result<String, Error> {
+  let expanded = expand_topic_name(name, node_name, namespace)?;
+  for rule in rules.iter().filter(|rule| rule.applies_to(RemapKind::Name, node_name)) {
+    if expand_topic_name(&rule.from, node_name, namespace)? == expanded {
+      return expand_topic_name(&rule.to, node_name, namespace);
+    }
+  }
+  Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn validation() {
+    for valid in &["foo", "/foo/bar", "~", "~/foo", "{node}/foo", "foo_1"] {
+      assert!(validate_topic_name(valid).is_ok(), "{}", valid);
+    }
+    for invalid in &[
+      "", "1foo", "foo/", "/", "foo//bar", "foo__bar", "~foo", "foo~", "{node/foo", "foo}",
+      "{}", "foo bar", "{{node}}",
+    ] {
+      assert!(validate_topic_name(invalid).is_err(), "{}", invalid);
+    }
+    assert!(validate_node_name("turtle_1").is_ok());
+    assert!(validate_node_name("1turtle").is_err());
+    assert!(validate_node_name("turtle/1").is_err());
+    assert_eq!(normalize_namespace("").unwrap(), "/");
+    assert_eq!(normalize_namespace("ns/sub").unwrap(), "/ns/sub");
+    assert!(normalize_namespace("/ns/").is_err());
+  }
+
+  #[test]
+  fn expansion() {
+    let expand = |name| expand_topic_name(name, "my_node", "/my_ns").unwrap();
+    assert_eq!(expand("ping"), "/my_ns/ping");
+    assert_eq!(expand("/ping"), "/ping");
+    assert_eq!(expand("~"), "/my_ns/my_node");
+    assert_eq!(expand("~/ping"), "/my_ns/my_node/ping");
+    assert_eq!(expand("{node}/ping"), "/my_ns/my_node/ping");
+    assert_eq!(expand("/{ns}/{node}"), "/my_ns/my_node");
+    assert_eq!(expand("~/{node}"), "/my_ns/my_node/my_node");
+    assert_eq!(expand_topic_name("{ns}/ping", "my_node", "/").unwrap(), "/ping");
+    assert_eq!(expand_topic_name("~/ping", "my_node", "/").unwrap(), "/my_node/ping");
+    assert!(expand_topic_name("{unknown}", "my_node", "/").is_err());
+    assert!(expand_topic_name("{node}", "my_node", "/1ns").is_err());
+  }
+
+  #[test]
+  fn remapping() {
+    let arguments = parse_ros_arguments(&[
+      "program", "--ros-args", "-r", "other_node:chatter:=ignored", "--remap", "chatter:=/talk",
+      "-r", "__node:=renamed", "-p", "x:=1", "--", "-r", "not_a_rule",
+    ])
+    .unwrap();
+    assert_eq!(arguments.parameters.len(), 1);
+    let rules = arguments.remappings;
+    assert_eq!(rules.len(), 3);
+    assert_eq!(remap_node_name(&rules, "talker"), "renamed");
+    assert_eq!(remap_namespace(&rules, "talker", "/ns"), "/ns");
+    assert_eq!(
+      resolve_topic_name(&rules, "chatter", "talker", "/ns").unwrap(),
+      "/talk"
+    );
+    assert_eq!(
+      resolve_topic_name(&rules, "/ns/chatter", "talker", "/ns").unwrap(),
+      "/talk"
+    );
+    assert_eq!(
+      resolve_topic_name(&rules, "chatter", "other_node", "/").unwrap(),
+      "/ignored"
+    );
+    assert_eq!(
+      resolve_topic_name(&rules, "other", "talker", "/ns").unwrap(),
+      "/ns/other"
+    );
+
+    let rules = parse_ros_arguments(&["--ros-args", "-r", "talker:__ns:=/remapped"])
+      .unwrap()
+      .remappings;
+    assert_eq!(remap_namespace(&rules, "talker", "/ns"), "/remapped");
+    assert_eq!(remap_namespace(&rules, "listener", "/ns"), "/ns");
+
+    assert!(parse_ros_arguments(&["--ros-args", "-r"]).is_err());
+    assert!(parse_ros_arguments(&["--ros-args", "-r", "no_separator"]).is_err());
+    assert!(parse_ros_arguments(&["--ros-args", "-r", "__ns:=relative"]).is_err());
+    assert!(parse_ros_arguments(&["--ros-args", "-p"]).is_err());
+    assert!(parse_ros_arguments(&["--ros-args", "-p", "no_separator"]).is_err());
+
+    // The arguments of the process are parsed past malformed rules.
+    let (rules, errors) = collect_ros_arguments(&[
+      "--ros-args",
+      "-r",
+      "",
+      "-r",
+      "chatter:=talk",
+      "-p",
+      "no_separator",
+      "-p",
+      "x:=1",
+      "-r",
+    ]);
+    assert_eq!(errors.len(), 3);
+    assert_eq!(rules.remappings.len(), 1);
+    assert_eq!(rules.parameters.len(), 1);
+  }
+}
@@ -0,0 +1,1125 @@
+//! ROS 2 parameters of a node
+//!
+//! Parameters are declared with a default value and a descriptor. The
+//! descriptor fixes the type of the parameter, unless dynamic typing is
+//! allowed, and can limit the values. Other nodes and `ros2 param` access
+//! the parameters through the standard services of the node, e.g.
+//! `/namespace/node/get_parameters`. All changes are published on
+//! `/parameter_events`.
+
+use std::{
+  collections::BTreeMap,
+  sync::{Arc, Mutex, Weak},
+};
+
+use log::{error, warn};
+use mio::{Events, PollOpt, Ready, Token};
+use mio_extras::channel as mio_channel;
+use serde::{Deserialize, Serialize};
+
+use crate::dds::{no_key::datawriter::DataWriter as NoKeyDataWriter, values::result::Error};
+
+use super::{
+  builtin_datatypes::{self, ParameterEvents, Time},
+  qos_profiles,
+  ros_node::NodeThread,
+  service::{Server, Service, ServiceMapping},
+  RosNode,
+};
+
+/// Type of a parameter, as in `rcl_interfaces/ParameterType`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ParameterType {
+  NotSet = 0,
+  Bool = 1,
+  Integer = 2,
+  Double = 3,
+  String = 4,
+  ByteArray = 5,
+  BoolArray = 6,
+  IntegerArray = 7,
+  DoubleArray = 8,
+  StringArray = 9,
+}
+
+/// Value of a parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterValue {
+  /// Parameter without a value. Setting this value to a parameter with
+  /// dynamic typing undeclares it.
+  NotSet,
+  Bool(bool),
+  Integer(i64),
+  Double(f64),
+  String(String),
+  ByteArray(Vec<u8>),
+  BoolArray(Vec<bool>),
+  IntegerArray(Vec<i64>),
+  DoubleArray(Vec<f64>),
+  StringArray(Vec<String>),
+}
+
+impl ParameterValue {
+  pub fn parameter_type(&self) -> ParameterType {
+    match self {
+      ParameterValue::NotSet => ParameterType::NotSet,
+      ParameterValue::Bool(_) => ParameterType::Bool,
+      ParameterValue::Integer(_) => ParameterType::Integer,
+      ParameterValue::Double(_) => ParameterType::Double,
+      ParameterValue::String(_) => ParameterType::String,
+      ParameterValue::ByteArray(_) => ParameterType::ByteArray,
+      ParameterValue::BoolArray(_) => ParameterType::BoolArray,
+      ParameterValue::IntegerArray(_) => ParameterType::IntegerArray,
+      ParameterValue::DoubleArray(_) => ParameterType::DoubleArray,
+      ParameterValue::StringArray(_) => ParameterType::StringArray,
+    }
+  }
+}
+
+impl From<builtin_datatypes::ParameterValue> for ParameterValue {
+  fn from(raw: builtin_datatypes::ParameterValue) -> ParameterValue {
+    match raw.ptype {
+      1 => ParameterValue::Bool(raw.boolean_value),
+      2 => ParameterValue::Integer(raw.int_value),
+      3 => ParameterValue::Double(raw.double_value),
+      4 => ParameterValue::String(raw.string_value),
+      5 => ParameterValue::ByteArray(raw.byte_array),
+      6 => ParameterValue::BoolArray(raw.bool_array),
+      7 => ParameterValue::IntegerArray(raw.int_array),
+      8 => ParameterValue::DoubleArray(raw.double_array),
+      9 => ParameterValue::StringArray(raw.string_array),
+      _ => ParameterValue::NotSet,
+    }
+  }
+}
+
+impl From<ParameterValue> for builtin_datatypes::ParameterValue {
+  fn from(value: ParameterValue) -> builtin_datatypes::ParameterValue {
+    let mut raw = builtin_datatypes::ParameterValue {
+      ptype: value.parameter_type() as u8,
+      ..Default::default()
+    };
+    match value {
+      ParameterValue::NotSet => (),
+      ParameterValue::Bool(b) => raw.boolean_value = b,
+      ParameterValue::Integer(i) => raw.int_value = i,
+      ParameterValue::Double(d) => raw.double_value = d,
+      ParameterValue::String(s) => raw.string_value = s,
+      ParameterValue::ByteArray(a) => raw.byte_array = a,
+      ParameterValue::BoolArray(a) => raw.bool_array = a,
+      ParameterValue::IntegerArray(a) => raw.int_array = a,
+      ParameterValue::DoubleArray(a) => raw.double_array = a,
+      ParameterValue::StringArray(a) => raw.string_array = a,
+    }
+    raw
+  }
+}
+
+/// `rcl_interfaces/FloatingPointRange`. A zero step allows any value in the
+/// range.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FloatingPointRange {
+  pub from_value: f64,
+  pub to_value: f64,
+  pub step: f64,
+}
+
+/// `rcl_interfaces/IntegerRange`. A zero step allows any value in the range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegerRange {
+  pub from_value: i64,
+  pub to_value: i64,
+  pub step: u64,
+}
+
+/// Describes and restricts a parameter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterDescriptor {
+  pub description: String,
+  /// Constraints in plain text, for users
+  pub additional_constraints: String,
+  /// The parameter cannot be set after it has been declared.
+  pub read_only: bool,
+  /// The type of the parameter may change.
+  pub dynamic_typing: bool,
+  pub floating_point_range: Option<FloatingPointRange>,
+  pub integer_range: Option<IntegerRange>,
+}
+
+fn doubles_equal(a: f64, b: f64) -> bool {
+  (a - b).abs() <= f64::EPSILON * 100.0 * a.abs().max(b.abs()).max(1.0)
+}
+
+impl ParameterDescriptor {
+  // Checks the type and the range of a new value. Returns the reason of
+  // rejection.
+  fn check(&self, declared_type: ParameterType, value: &ParameterValue) -> Result<(), String> {
+    if !self.dynamic_typing && value.parameter_type() != declared_type {
+      return Err(format!(
+        "Wrong parameter type, expected {:?}, got {:?}",
+        declared_type,
+        value.parameter_type()
+      ));
+    }
+    match (value, &self.integer_range, &self.floating_point_range) {
+      (ParameterValue::Integer(v), Some(range), _) => {
+        let v = *v;
+        if v == range.from_value || v == range.to_value {
+          return Ok(());
+        }
+        if v < range.from_value || v > range.to_value {
+          return Err(format!(
+            "Value {} is not in range [{}, {}]",
+            v, range.from_value, range.to_value
+          ));
+        }
+        // v >= from_value here, and the distance always fits in u64.
+        if range.step != 0 && v.abs_diff(range.from_value) % range.step != 0 {
+          return Err(format!(
+            "Value {} is not a multiple of step {} from {}",
+            v, range.step, range.from_value
+          ));
+        }
+        Ok(())
+      }
+      (ParameterValue::Double(v), _, Some(range)) => {
+        let v = *v;
+        if doubles_equal(v, range.from_value) || doubles_equal(v, range.to_value) {
+          return Ok(());
+        }
+        if v < range.from_value || v > range.to_value {
+          return Err(format!(
+            "Value {} is not in range [{}, {}]",
+            v, range.from_value, range.to_value
+          ));
+        }
+        if range.step != 0.0 {
+          let steps = ((v - range.from_value) / range.step).round();
+          if !doubles_equal(v, range.from_value + steps * range.step) {
+            return Err(format!(
+              "Value {} is not a multiple of step {} from {}",
+              v, range.step, range.from_value
+            ));
+          }
+        }
+        Ok(())
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Messages of the parameter services
+
+/// `rcl_interfaces/SetParametersResult`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SetParametersResult {
+  successful: bool,
+  reason: String,
+}
+
+impl From<Result<(), String>> for SetParametersResult {
+  fn from(result: Result<(), String>) -> SetParametersResult {
+    match result {
+      Ok(()) => SetParametersResult {
+        successful: true,
+        reason: String::new(),
+      },
+      Err(reason) => SetParametersResult {
+        successful: false,
+        reason,
+      },
+    }
+  }
+}
+
+// rcl_interfaces/ParameterDescriptor as it is sent. The ranges are
+// sequences of at most one element.
+#[derive(Serialize, Deserialize)]
+struct RawParameterDescriptor {
+  name: String,
+  ptype: u8,
+  description: String,
+  additional_constraints: String,
+  read_only: bool,
+  dynamic_typing: bool,
+  floating_point_range: Vec<FloatingPointRange>,
+  integer_range: Vec<IntegerRange>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NamesRequest {
+  names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetParametersResponse {
+  values: Vec<builtin_datatypes::ParameterValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetParameterTypesResponse {
+  types: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SetParametersRequest {
+  parameters: Vec<builtin_datatypes::Parameter>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SetParametersResponse {
+  results: Vec<SetParametersResult>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SetParametersAtomicallyResponse {
+  result: SetParametersResult,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ListParametersRequest {
+  prefixes: Vec<String>,
+  depth: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ListParametersResponse {
+  names: Vec<String>,
+  prefixes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DescribeParametersResponse {
+  descriptors: Vec<RawParameterDescriptor>,
+}
+
+macro_rules! parameter_service {
+  ($service:ident, $type_name:literal, $request:ty, $response:ty) => {
+    struct $service;
+
+    impl Service for $service {
+      type Request = $request;
+      type Response = $response;
+      fn type_name() -> String {
+        concat!("rcl_interfaces::srv::dds_::", $type_name).to_string()
+      }
+    }
+  };
+}
+
+parameter_service!(
+  DescribeParametersService,
+  "DescribeParameters_",
+  NamesRequest,
+  DescribeParametersResponse
+);
+parameter_service!(
+  GetParametersService,
+  "GetParameters_",
+  NamesRequest,
+  GetParametersResponse
+);
+parameter_service!(
+  GetParameterTypesService,
+  "GetParameterTypes_",
+  NamesRequest,
+  GetParameterTypesResponse
+);
+parameter_service!(
+  ListParametersService,
+  "ListParameters_",
+  ListParametersRequest,
+  ListParametersResponse
+);
+parameter_service!(
+  SetParametersService,
+  "SetParameters_",
+  SetParametersRequest,
+  SetParametersResponse
+);
+parameter_service!(
+  SetParametersAtomicallyService,
+  "SetParametersAtomically_",
+  SetParametersRequest,
+  SetParametersAtomicallyResponse
+);
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+/// Parameter value `[node:]name:=value`, as given with `--ros-args -p`. The
+/// value is read like a YAML scalar or flow sequence, e.g. `true`, `3`,
+/// `0.5`, `"text"` or `[1, 2]`. It replaces the default value when the
+/// parameter is declared.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ParameterOverride {
+  node: Option<String>,
+  name: String,
+  value: ParameterValue,
+}
+
+impl ParameterOverride {
+  pub(crate) fn parse(rule: &str) -> Result<ParameterOverride, Error> {
+    let invalid = || Error::BadParameter {
+      reason: format!("Invalid parameter override {:?}.", rule),
+    };
+    let (lhs, value) = match rule.find(":=") {
+      Some(i) => (&rule[..i], &rule[i + 2..]),
+      None => return Err(invalid()),
+    };
+    let (node, name) = match lhs.find(':') {
+      Some(i) => (Some(&lhs[..i]), &lhs[i + 1..]),
+      None => (None, lhs),
+    };
+    if name.is_empty() || node.is_some_and(str::is_empty) {
+      return Err(invalid());
+    }
+    Ok(ParameterOverride {
+      node: node.map(String::from),
+      name: name.to_string(),
+      value: parse_parameter_value(value).ok_or_else(invalid)?,
+    })
+  }
+
+  // As in rcl, the node is given by its fully qualified name, where the
+  // leading slash may be left out.
+  fn applies_to(&self, fully_qualified_node_name: &str) -> bool {
+    self.node.as_deref().is_none_or(|node| {
+      node.trim_start_matches('/') == fully_qualified_node_name.trim_start_matches('/')
+    })
+  }
+}
+
+fn parse_scalar(text: &str) -> ParameterValue {
+  let text = text.trim();
+  let quoted = |quote| text.len() >= 2 && text.starts_with(quote) && text.ends_with(quote);
+  if quoted('"') || quoted('\'') {
+    return ParameterValue::String(text[1..text.len() - 1].to_string());
+  }
+  match text {
+    "true" | "True" | "TRUE" => ParameterValue::Bool(true),
+    "false" | "False" | "FALSE" => ParameterValue::Bool(false),
+    _ => match (text.parse::<i64>(), text.parse::<f64>()) {
+      (Ok(i), _) => ParameterValue::Integer(i),
+      (_, Ok(d)) => ParameterValue::Double(d),
+      _ => ParameterValue::String(text.to_string()),
+    },
+  }
+}
+
+// None if the elements of a sequence have different types
+fn parse_parameter_value(text: &str) -> Option<ParameterValue> {
+  let trimmed = text.trim();
+  if !(trimmed.starts_with('[') && trimmed.ends_with(']')) {
+    return Some(parse_scalar(text));
+  }
+  let inner = trimmed[1..trimmed.len() - 1].trim();
+  let elements: Vec<ParameterValue> = if inner.is_empty() {
+    Vec::new()
+  } else {
+    inner.split(',').map(parse_scalar).collect()
+  };
+  let all = |ptype| elements.iter().all(|e| e.parameter_type() == ptype);
+  if all(ParameterType::Bool) {
+    Some(ParameterValue::BoolArray(
+      elements
+        .into_iter()
+        .map(|e| e == ParameterValue::Bool(true))
+        .collect(),
+    ))
+  } else if all(ParameterType::Integer) {
+    let integers = elements.into_iter().filter_map(|e| match e {
+      ParameterValue::Integer(i) => Some(i),
+      _ => None,
+    });
+    Some(ParameterValue::IntegerArray(integers.collect()))
+  } else if all(ParameterType::Double) {
+    let doubles = elements.into_iter().filter_map(|e| match e {
+      ParameterValue::Double(d) => Some(d),
+      _ => None,
+    });
+    Some(ParameterValue::DoubleArray(doubles.collect()))
+  } else if all(ParameterType::String) {
+    let strings = elements.into_iter().filter_map(|e| match e {
+      ParameterValue::String(s) => Some(s),
+      _ => None,
+    });
+    Some(ParameterValue::StringArray(strings.collect()))
+  } else {
+    None
+  }
+}
+
+struct DeclaredParameter {
+  value: ParameterValue,
+  // The type the parameter was declared with
+  ptype: ParameterType,
+  descriptor: ParameterDescriptor,
+}
+
+// Parameters of a node, shared by the node and the thread that serves the
+// parameter services
+pub(crate) struct ParameterStore {
+  node_name: String,
+  allow_undeclared: bool,
+  parameters: BTreeMap<String, DeclaredParameter>,
+  // Values from the arguments of the node, used instead of the defaults
+  overrides: BTreeMap<String, ParameterValue>,
+  events_writer: NoKeyDataWriter<ParameterEvents>,
+}
+
+#[derive(Default)]
+struct ParameterChanges {
+  new: Vec<builtin_datatypes::Parameter>,
+  changed: Vec<builtin_datatypes::Parameter>,
+  deleted: Vec<builtin_datatypes::Parameter>,
+}
+
+fn raw_parameter(name: &str, value: &ParameterValue) -> builtin_datatypes::Parameter {
+  builtin_datatypes::Parameter {
+    name: name.to_string(),
+    value: value.clone().into(),
+  }
+}
+
+impl ParameterStore {
+  // The first of the overrides for the node applies to a parameter
+  pub(crate) fn new(
+    node_name: String,
+    allow_undeclared: bool,
+    overrides: &[ParameterOverride],
+    events_writer: NoKeyDataWriter<ParameterEvents>,
+  ) -> ParameterStore {
+    let mut override_values = BTreeMap::new();
+    for o in overrides.iter().filter(|o| o.applies_to(&node_name)) {
+      override_values
+        .entry(o.name.clone())
+        .or_insert_with(|| o.value.clone());
+    }
+    ParameterStore {
+      node_name,
+      allow_undeclared,
+      parameters: BTreeMap::new(),
+      overrides: override_values,
+      events_writer,
+    }
+  }
+
+  pub(crate) fn declare(
+    &mut self,
+    name: &str,
+    value: ParameterValue,
+    descriptor: ParameterDescriptor,
+  ) -> Result<(), Error> {
+    if name.is_empty() {
+      return Error::bad_parameter("Parameter name must not be empty.");
+    }
+    if self.parameters.contains_key(name) {
+      return Error::precondition_not_met("Parameter is already declared.");
+    }
+    let ptype = value.parameter_type();
+    let value = self.overrides.get(name).cloned().unwrap_or(value);
+    if let Err(reason) = descriptor.check(ptype, &value) {
+      return Err(Error::BadParameter { reason });
+    }
+    let mut changes = ParameterChanges::default();
+    changes.new.push(raw_parameter(name, &value));
+    self.parameters.insert(
+      name.to_string(),
+      DeclaredParameter {
+        value,
+        ptype,
+        descriptor,
+      },
+    );
+    self.publish(changes);
+    Ok(())
+  }
+
+  pub(crate) fn undeclare(&mut self, name: &str) -> Result<(), Error> {
+    match self.parameters.get(name) {
+      None => Error::precondition_not_met("Parameter is not declared."),
+      Some(p) if p.descriptor.read_only => {
+        Error::precondition_not_met("Read-only parameter cannot be undeclared.")
+      }
+      Some(_) => {
+        let mut changes = ParameterChanges::default();
+        if let Some(p) = self.parameters.remove(name) {
+          changes.deleted.push(raw_parameter(name, &p.value));
+        }
+        self.publish(changes);
+        Ok(())
+      }
+    }
+  }
+
+  pub(crate) fn get(&self, name: &str) -> Option<ParameterValue> {
+    self.parameters.get(name).map(|p| p.value.clone())
+  }
+
+  pub(crate) fn names(&self) -> Vec<String> {
+    self.parameters.keys().cloned().collect()
+  }
+
+  // Set from the node itself
+  pub(crate) fn set(&mut self, name: &str, value: ParameterValue) -> Result<(), Error> {
+    let mut changes = ParameterChanges::default();
+    let result = self.set_one(name, value, false, &mut changes);
+    self.publish(changes);
+    result.map_err(|reason| Error::BadParameter { reason })
+  }
+
+  fn check_set(&self, name: &str, value: &ParameterValue, remote: bool) -> Result<(), String> {
+    match self.parameters.get(name) {
+      Some(p) if p.descriptor.read_only => Err("Parameter is read-only".to_string()),
+      Some(p) => p.descriptor.check(p.ptype, value),
+      None if remote && !self.allow_undeclared => Err("Parameter is not declared".to_string()),
+      None if *value == ParameterValue::NotSet => Err("Parameter is not declared".to_string()),
+      None if name.is_empty() => Err("Parameter name must not be empty".to_string()),
+      None => Ok(()),
+    }
+  }
+
+  fn set_one(
+    &mut self,
+    name: &str,
+    value: ParameterValue,
+    remote: bool,
+    changes: &mut ParameterChanges,
+  ) -> Result<(), String> {
+    self.check_set(name, &value, remote)?;
+    if value == ParameterValue::NotSet {
+      // only parameters with dynamic typing get here
+      if let Some(p) = self.parameters.remove(name) {
+        changes.deleted.push(raw_parameter(name, &p.value));
+      }
+      return Ok(());
+    }
+    match self.parameters.get_mut(name) {
+      Some(p) => {
+        changes.changed.push(raw_parameter(name, &value));
+        p.value = value;
+      }
+      None => {
+        // Implicitly declared parameters can change their type, as in rclcpp.
+        changes.new.push(raw_parameter(name, &value));
+        self.parameters.insert(
+          name.to_string(),
+          DeclaredParameter {
+            ptype: value.parameter_type(),
+            value,
+            descriptor: ParameterDescriptor {
+              dynamic_typing: true,
+              ..ParameterDescriptor::default()
+            },
+          },
+        );
+      }
+    }
+    Ok(())
+  }
+
+  fn publish(&self, changes: ParameterChanges) {
+    if changes.new.is_empty() && changes.changed.is_empty() && changes.deleted.is_empty() {
+      return;
+    }
+    let event = ParameterEvents {
+      timestamp: Time::now(),
+      node: self.node_name.clone(),
+      new_parameters: changes.new,
+      changed_parameters: changes.changed,
+      deleted_parameters: changes.deleted,
+    };
+    if let Err(e) = self.events_writer.write(event, None) {
+      error!("Failed to publish parameter event: {:?}", e);
+    }
+  }
+
+  fn get_parameters(&self, request: NamesRequest) -> GetParametersResponse {
+    GetParametersResponse {
+      values: request
+        .names
+        .iter()
+        .map(|name| self.get(name).unwrap_or(ParameterValue::NotSet).into())
+        .collect(),
+    }
+  }
+
+  fn get_parameter_types(&self, request: NamesRequest) -> GetParameterTypesResponse {
+    GetParameterTypesResponse {
+      types: request
+        .names
+        .iter()
+        .map(|name| {
+          self
+            .parameters
+            .get(name)
+            .map_or(ParameterType::NotSet, |p| p.value.parameter_type()) as u8
+        })
+        .collect(),
+    }
+  }
+
+  fn describe_parameters(&self, request: NamesRequest) -> DescribeParametersResponse {
+    let default_descriptor = ParameterDescriptor::default();
+    let mut descriptors = Vec::with_capacity(request.names.len());
+    for name in request.names {
+      let (ptype, descriptor) = match self.parameters.get(&name) {
+        Some(p) => (p.value.parameter_type(), &p.descriptor),
+        None if self.allow_undeclared => (ParameterType::NotSet, &default_descriptor),
+        // rclcpp fails the whole request
+        None => {
+          return DescribeParametersResponse {
+            descriptors: Vec::new(),
+          }
+        }
+      };
+      descriptors.push(RawParameterDescriptor {
+        name,
+        ptype: ptype as u8,
+        description: descriptor.description.clone(),
+        additional_constraints: descriptor.additional_constraints.clone(),
+        read_only: descriptor.read_only,
+        dynamic_typing: descriptor.dynamic_typing,
+        floating_point_range: descriptor.floating_point_range.into_iter().collect(),
+        integer_range: descriptor.integer_range.into_iter().collect(),
+      });
+    }
+    DescribeParametersResponse { descriptors }
+  }
+
+  // Lists the parameters as rclcpp does: names are separated to levels by
+  // dots, and depth 0 means all levels.
+  fn list_parameters(&self, request: ListParametersRequest) -> ListParametersResponse {
+    let depth = request.depth as usize;
+    let within_depth = |rest: &str| depth == 0 || rest.matches('.').count() < depth;
+    let mut response = ListParametersResponse {
+      names: Vec::new(),
+      prefixes: Vec::new(),
+    };
+    for name in self.parameters.keys() {
+      let get_all = request.prefixes.is_empty() && within_depth(name);
+      let prefix_matches = request.prefixes.iter().any(|prefix| {
+        name == prefix
+          || name
+            .strip_prefix(prefix.as_str())
+            .and_then(|rest| rest.strip_prefix('.'))
+            .is_some_and(within_depth)
+      });
+      if get_all || prefix_matches {
+        response.names.push(name.clone());
+        if let Some(dot) = name.rfind('.') {
+          let prefix = name[..dot].to_string();
+          if !response.prefixes.contains(&prefix) {
+            response.prefixes.push(prefix);
+          }
+        }
+      }
+    }
+    response
+  }
+
+  fn set_parameters(&mut self, request: SetParametersRequest) -> SetParametersResponse {
+    let mut changes = ParameterChanges::default();
+    let results = request
+      .parameters
+      .into_iter()
+      .map(|p| {
+        self
+          .set_one(&p.name, p.value.into(), true, &mut changes)
+          .into()
+      })
+      .collect();
+    self.publish(changes);
+    SetParametersResponse { results }
+  }
+
+  fn set_parameters_atomically(
+    &mut self,
+    request: SetParametersRequest,
+  ) -> SetParametersAtomicallyResponse {
+    let parameters: Vec<(String, ParameterValue)> = request
+      .parameters
+      .into_iter()
+      .map(|p| (p.name, p.value.into()))
+      .collect();
+    let checked = parameters
+      .iter()
+      .try_for_each(|(name, value)| self.check_set(name, value, true));
+    if checked.is_ok() {
+      let mut changes = ParameterChanges::default();
+      for (name, value) in parameters {
+        // Checked already, but an earlier parameter of the same request may
+        // have undeclared a later one.
+        if let Err(reason) = self.set_one(&name, value, true, &mut changes) {
+          warn!("Setting parameter {} failed: {}", name, reason);
+        }
+      }
+      self.publish(changes);
+    }
+    SetParametersAtomicallyResponse {
+      result: checked.into(),
+    }
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+struct ParameterServices {
+  describe: Server<DescribeParametersService>,
+  get: Server<GetParametersService>,
+  get_types: Server<GetParameterTypesService>,
+  list: Server<ListParametersService>,
+  set: Server<SetParametersService>,
+  set_atomically: Server<SetParametersAtomicallyService>,
+}
+
+impl ParameterServices {
+  fn handle_requests(&mut self, store: &mut ParameterStore) -> Result<(), Error> {
+    while let Some((id, request)) = self.describe.receive_request()? {
+      self
+        .describe
+        .send_response(id, store.describe_parameters(request))?;
+    }
+    while let Some((id, request)) = self.get.receive_request()? {
+      self.get.send_response(id, store.get_parameters(request))?;
+    }
+    while let Some((id, request)) = self.get_types.receive_request()? {
+      self
+        .get_types
+        .send_response(id, store.get_parameter_types(request))?;
+    }
+    while let Some((id, request)) = self.list.receive_request()? {
+      self
+        .list
+        .send_response(id, store.list_parameters(request))?;
+    }
+    while let Some((id, request)) = self.set.receive_request()? {
+      self.set.send_response(id, store.set_parameters(request))?;
+    }
+    while let Some((id, request)) = self.set_atomically.receive_request()? {
+      self
+        .set_atomically
+        .send_response(id, store.set_parameters_atomically(request))?;
+    }
+    Ok(())
+  }
+}
+
+const STOP_TOKEN: Token = Token(6);
+
+fn parameter_service_thread(
+  store: Weak<Mutex<ParameterStore>>,
+  mut services: ParameterServices,
+  poll: mio::Poll,
+  _stop: mio_channel::Receiver<()>,
+) {
+  let mut events = Events::with_capacity(8);
+  loop {
+    if let Err(e) = poll.poll(&mut events, None) {
+      error!("Parameter service thread poll failed: {:?}", e);
+      return;
+    }
+    if events.iter().any(|event| event.token() == STOP_TOKEN) {
+      return; // node is being dropped
+    }
+    let store = match store.upgrade() {
+      Some(store) => store,
+      None => return,
+    };
+    let mut store = match store.lock() {
+      Ok(store) => store,
+      Err(_) => return,
+    };
+    if let Err(e) = services.handle_requests(&mut store) {
+      error!("Serving parameters failed: {:?}", e);
+    }
+  }
+}
+
+/// Creates the parameter services of the node under its fully qualified
+/// name, and starts a thread that answers them.
+pub(crate) fn start_parameter_services(
+  node: &mut RosNode,
+  store: &Arc<Mutex<ParameterStore>>,
+  mapping: ServiceMapping,
+) -> Result<NodeThread, Error> {
+  let prefix = node.get_fully_qualified_name();
+  let qos = qos_profiles::parameters();
+  let services = ParameterServices {
+    describe: node.create_service(
+      &format!("{}/describe_parameters", prefix),
+      mapping,
+      qos.clone(),
+    )?,
+    get: node.create_service(&format!("{}/get_parameters", prefix), mapping, qos.clone())?,
+    get_types: node.create_service(
+      &format!("{}/get_parameter_types", prefix),
+      mapping,
+      qos.clone(),
+    )?,
+    list: node.create_service(&format!("{}/list_parameters", prefix), mapping, qos.clone())?,
+    set: node.create_service(&format!("{}/set_parameters", prefix), mapping, qos.clone())?,
+    set_atomically: node.create_service(
+      &format!("{}/set_parameters_atomically", prefix),
+      mapping,
+      qos,
+    )?,
+  };
+
+  let poll = mio::Poll::new()?;
+  let interest = Ready::readable();
+  poll.register(&services.describe, Token(0), interest, PollOpt::edge())?;
+  poll.register(&services.get, Token(1), interest, PollOpt::edge())?;
+  poll.register(&services.get_types, Token(2), interest, PollOpt::edge())?;
+  poll.register(&services.list, Token(3), interest, PollOpt::edge())?;
+  poll.register(&services.set, Token(4), interest, PollOpt::edge())?;
+  poll.register(
+    &services.set_atomically,
+    Token(5),
+    interest,
+    PollOpt::edge(),
+  )?;
+
+  let weak_store = Arc::downgrade(store);
+  NodeThread::spawn(
+    "RustDDS parameter services",
+    poll,
+    STOP_TOKEN,
+    move |poll, stop| parameter_service_thread(weak_store, services, poll, stop),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+  use crate::ros2::{service::Client, NodeOptions, RosNode, RosParticipant};
+
+  #[test]
+  fn value_conversion() {
+    for value in &[
+      ParameterValue::NotSet,
+      ParameterValue::Bool(true),
+      ParameterValue::Integer(-3),
+      ParameterValue::Double(0.5),
+      ParameterValue::String("a".to_string()),
+      ParameterValue::ByteArray(vec![1, 2]),
+      ParameterValue::StringArray(vec!["b".to_string()]),
+    ] {
+      let raw = builtin_datatypes::ParameterValue::from(value.clone());
+      assert_eq!(raw.ptype, value.parameter_type() as u8);
+      assert_eq!(ParameterValue::from(raw), *value);
+    }
+  }
+
+  #[test]
+  fn descriptor_checks() {
+    let descriptor = ParameterDescriptor {
+      integer_range: Some(IntegerRange {
+        from_value: -10,
+        to_value: 10,
+        step: 5,
+      }),
+      floating_point_range: Some(FloatingPointRange {
+        from_value: 0.0,
+        to_value: 1.0,
+        step: 0.1,
+      }),
+      ..ParameterDescriptor::default()
+    };
+    let int = ParameterType::Integer;
+    assert!(descriptor.check(int, &ParameterValue::Integer(5)).is_ok());
+    assert!(descriptor.check(int, &ParameterValue::Integer(10)).is_ok());
+    assert!(descriptor.check(int, &ParameterValue::Integer(3)).is_err());
+    assert!(descriptor.check(int, &ParameterValue::Integer(15)).is_err());
+    let full_range = ParameterDescriptor {
+      integer_range: Some(IntegerRange {
+        from_value: i64::MIN,
+        to_value: i64::MAX,
+        step: 2,
+      }),
+      ..ParameterDescriptor::default()
+    };
+    assert!(full_range.check(int, &ParameterValue::Integer(0)).is_ok());
+    assert!(full_range.check(int, &ParameterValue::Integer(i64::MAX - 2)).is_err());
+    assert!(descriptor.check(int, &ParameterValue::Double(0.5)).is_err());
+    let double = ParameterType::Double;
+    assert!(descriptor
+      .check(double, &ParameterValue::Double(0.3))
+      .is_ok());
+    assert!(descriptor
+      .check(double, &ParameterValue::Double(0.35))
+      .is_err());
+    assert!(descriptor
+      .check(double, &ParameterValue::Double(1.5))
+      .is_err());
+
+    let dynamic = ParameterDescriptor {
+      dynamic_typing: true,
+      ..ParameterDescriptor::default()
+    };
+    assert!(dynamic
+      .check(int, &ParameterValue::String("x".to_string()))
+      .is_ok());
+  }
+
+  #[test]
+  fn parameter_overrides() {
+    let parse = |rule| ParameterOverride::parse(rule).unwrap().value;
+    assert_eq!(parse("a:=true"), ParameterValue::Bool(true));
+    assert_eq!(parse("a:=-3"), ParameterValue::Integer(-3));
+    assert_eq!(parse("a:=0.5"), ParameterValue::Double(0.5));
+    assert_eq!(parse("a:=fast"), ParameterValue::String("fast".to_string()));
+    assert_eq!(parse("a:='3'"), ParameterValue::String("3".to_string()));
+    assert_eq!(parse("a:=[1, 2]"), ParameterValue::IntegerArray(vec![1, 2]));
+    assert_eq!(
+      parse("a:=[x, \"y\"]"),
+      ParameterValue::StringArray(vec!["x".to_string(), "y".to_string()])
+    );
+    assert!(ParameterOverride::parse("a:=[1, x]").is_err());
+    assert!(ParameterOverride::parse(":=1").is_err());
+    assert!(ParameterOverride::parse("a=1").is_err());
+
+    let node_only = ParameterOverride::parse("talker:a:=1").unwrap();
+    assert!(node_only.applies_to("/talker"));
+    assert!(!node_only.applies_to("/ns/talker"));
+    assert!(ParameterOverride::parse("/ns/talker:a:=1")
+      .unwrap()
+      .applies_to("/ns/talker"));
+
+    let ros_participant = RosParticipant::new().unwrap();
+    let mut node = ros_participant
+      .new_RosNode(
+        "overridden",
+        "/",
+        NodeOptions::new(false)
+          .arguments(vec![
+            "--ros-args", "-p", "other:speed:=1.0", "-p", "speed:=2.5", "-p", "speed:=3.5",
+            "-p", "count:=fast",
+          ])
+          .use_global_arguments(false),
+      )
+      .unwrap();
+    node
+      .declare_parameter(
+        "speed",
+        ParameterValue::Double(1.0),
+        ParameterDescriptor::default(),
+      )
+      .unwrap();
+    assert_eq!(node.get_parameter("speed"), Some(ParameterValue::Double(2.5)));
+    // an override of the wrong type fails the declaration
+    assert!(node
+      .declare_parameter(
+        "count",
+        ParameterValue::Integer(1),
+        ParameterDescriptor::default(),
+      )
+      .is_err());
+  }
+
+  // A client of another node, calling the services of `node_name`
+  fn parameter_clients(
+    node: &mut RosNode,
+    node_name: &str,
+  ) -> (Client<GetParametersService>, Client<SetParametersService>) {
+    let qos = qos_profiles::parameters();
+    let get = node
+      .create_client(
+        &format!("{}/get_parameters", node_name),
+        ServiceMapping::default(),
+        qos.clone(),
+      )
+      .unwrap();
+    let set = node
+      .create_client(
+        &format!("{}/set_parameters", node_name),
+        ServiceMapping::default(),
+        qos,
+      )
+      .unwrap();
+    (get, set)
+  }
+
+  #[test]
+  fn parameter_services() {
+    let ros_participant = RosParticipant::new().unwrap();
+    let mut node = ros_participant
+      .new_RosNode("parameter_server", "/", NodeOptions::new(false))
+      .unwrap();
+    // The name keeps the ParameterValue in the request 4-aligned. The CDR
+    // serializer pads every struct to 4 bytes, but the deserializer does not.
+    node
+      .declare_parameter(
+        "max",
+        ParameterValue::Integer(1),
+        ParameterDescriptor::default(),
+      )
+      .unwrap();
+    let mut other = ros_participant
+      .new_RosNode("parameter_client", "/", NodeOptions::new(false))
+      .unwrap();
+    let (get, set) = parameter_clients(&mut other, "/parameter_server");
+    let timeout = Duration::from_secs(5);
+
+    let response = set
+      .call_timeout(
+        SetParametersRequest {
+          parameters: vec![
+            builtin_datatypes::Parameter {
+              name: "max".to_string(),
+              value: ParameterValue::Integer(3).into(),
+            },
+            builtin_datatypes::Parameter {
+              name: "max".to_string(),
+              value: ParameterValue::String("fast".to_string()).into(),
+            },
+          ],
+        },
+        timeout,
+      )
+      .unwrap();
+    assert!(response.results[0].successful);
+    assert!(!response.results[1].successful);
+    assert_eq!(node.get_parameter("max"), Some(ParameterValue::Integer(3)));
+
+    let response = get
+      .call_timeout(
+        NamesRequest {
+          names: vec!["max".to_string(), "missing".to_string()],
+        },
+        timeout,
+      )
+      .unwrap();
+    let values: Vec<ParameterValue> = response.values.into_iter().map(Into::into).collect();
+    assert_eq!(values, vec![ParameterValue::Integer(3), ParameterValue::NotSet]);
+  }
+
+  #[test]
+  fn parameter_services_can_be_disabled() {
+    let ros_participant = RosParticipant::new().unwrap();
+    let _node = ros_participant
+      .new_RosNode(
+        "parameter_silent",
+        "/",
+        NodeOptions::new(false).start_parameter_services(false),
+      )
+      .unwrap();
+    let mut other = ros_participant
+      .new_RosNode("parameter_asker", "/", NodeOptions::new(false))
+      .unwrap();
+    let (get, _) = parameter_clients(&mut other, "/parameter_silent");
+    let request = NamesRequest {
+      names: vec!["use_sim_time".to_string()],
+    };
+    assert!(get
+      .call_timeout(request, Duration::from_millis(500))
+      .is_err());
+  }
+}
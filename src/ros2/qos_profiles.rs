@@ -0,0 +1,387 @@
+//! QoS profiles of ROS 2, and their mapping to and from the QoS profile of
+//! the ROS middleware interface (rmw).
+//!
+//! The presets are those of `rmw/qos_profiles.h`. Policies that rmw leaves
+//! to the system default are not set, so that the DDS defaults apply.
+//!
+//! # Example
+//!
+//! ```
+//! use rustdds::dds::data_types::TopicKind;
+//! use rustdds::ros2::{qos_profiles, NodeOptions, RosParticipant};
+//!
+//! let ros_participant = RosParticipant::new().unwrap();
+//! let ros_node = ros_participant
+//!   .new_RosNode("camera", "/", NodeOptions::new(false))
+//!   .unwrap();
+//! let image_topic = ros_node
+//!   .create_ros_topic(
+//!     "image",
+//!     "sensor_msgs::msg::dds_::Image_",
+//!     qos_profiles::sensor_data(),
+//!     TopicKind::NoKey,
+//!   )
+//!   .unwrap();
+//! ```
+
+use std::fmt;
+
+use crate::{
+  dds::qos::{
+    policy::{Deadline, Durability, History, Lifespan, Liveliness, Reliability},
+    QosPolicies,
+  },
+  structure::duration::Duration,
+};
+
+use super::builtin_topics::RosOutTopic;
+
+fn profile(history: History, reliability: Reliability, durability: Durability) -> QosPolicies {
+  QosPolicies::builder()
+    .history(history)
+    .reliability(reliability)
+    .durability(durability)
+    .build()
+}
+
+const RELIABLE: Reliability = Reliability::Reliable {
+  max_blocking_time: Duration::DURATION_ZERO,
+};
+
+/// `rmw_qos_profile_default`: reliable, volatile and keep last 10. The
+/// default of publishers and subscriptions.
+pub fn default() -> QosPolicies {
+  profile(History::KeepLast { depth: 10 }, RELIABLE, Durability::Volatile)
+}
+
+/// `rmw_qos_profile_sensor_data`: best effort, volatile and keep last 5.
+/// Timely delivery matters more than receiving every sample.
+pub fn sensor_data() -> QosPolicies {
+  profile(
+    History::KeepLast { depth: 5 },
+    Reliability::BestEffort,
+    Durability::Volatile,
+  )
+}
+
+/// `rmw_qos_profile_services_default`: reliable, volatile and keep last 10.
+pub fn services_default() -> QosPolicies {
+  profile(History::KeepLast { depth: 10 }, RELIABLE, Durability::Volatile)
+}
+
+/// `rmw_qos_profile_parameters`: reliable, volatile and keep last 1000. The
+/// profile of the parameter services.
+pub fn parameters() -> QosPolicies {
+  profile(History::KeepLast { depth: 1000 }, RELIABLE, Durability::Volatile)
+}
+
+/// `rmw_qos_profile_parameter_events`: reliable, volatile and keep last 1000.
+pub fn parameter_events() -> QosPolicies {
+  profile(History::KeepLast { depth: 1000 }, RELIABLE, Durability::Volatile)
+}
+
+/// `rmw_qos_profile_system_default`: every policy is left to the DDS
+/// defaults.
+pub fn system_default() -> QosPolicies {
+  QosPolicies::builder().build()
+}
+
+/// Profile of `/clock` in rclcpp (`ClockQoS`): best effort, volatile and
+/// keep last 1. Only the latest simulated time matters.
+pub fn clock() -> QosPolicies {
+  profile(
+    History::KeepLast { depth: 1 },
+    Reliability::BestEffort,
+    Durability::Volatile,
+  )
+}
+
+/// `rcl_qos_profile_rosout_default`: reliable, transient local, keep last
+/// 1000 and a lifespan of 10 seconds.
+pub fn rosout() -> QosPolicies {
+  RosOutTopic::get_qos()
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+/// `rmw_qos_history_policy_t`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RmwHistoryPolicy {
+  SystemDefault,
+  KeepLast,
+  KeepAll,
+  Unknown,
+}
+
+/// `rmw_qos_reliability_policy_t`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RmwReliabilityPolicy {
+  SystemDefault,
+  Reliable,
+  BestEffort,
+  Unknown,
+}
+
+/// `rmw_qos_durability_policy_t`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RmwDurabilityPolicy {
+  SystemDefault,
+  TransientLocal,
+  Volatile,
+  Unknown,
+}
+
+/// `rmw_qos_liveliness_policy_t`. `ManualByNode` is manual by participant in
+/// DDS.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RmwLivelinessPolicy {
+  SystemDefault,
+  Automatic,
+  ManualByNode,
+  ManualByTopic,
+  Unknown,
+}
+
+/// QoS profile of rmw (`rmw_qos_profile_t`), as shown e.g. by
+/// `ros2 topic info -v`.
+///
+/// A zero duration is `RMW_DURATION_UNSPECIFIED`, i.e. the system default.
+/// Converting to [`QosPolicies`](../dds/qos/struct.QosPolicies.html) leaves
+/// system defaults and unknown policies unset.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RmwQosProfile {
+  pub history: RmwHistoryPolicy,
+  pub depth: usize,
+  pub reliability: RmwReliabilityPolicy,
+  pub durability: RmwDurabilityPolicy,
+  pub deadline: Duration,
+  pub lifespan: Duration,
+  pub liveliness: RmwLivelinessPolicy,
+  pub liveliness_lease_duration: Duration,
+  pub avoid_ros_namespace_conventions: bool,
+}
+
+fn specified(duration: Duration) -> Option<Duration> {
+  if duration == Duration::DURATION_ZERO {
+    None
+  } else {
+    Some(duration)
+  }
+}
+
+impl From<&QosPolicies> for RmwQosProfile {
+  fn from(qos: &QosPolicies) -> RmwQosProfile {
+    let (history, depth) = match qos.history() {
+      None => (RmwHistoryPolicy::SystemDefault, 0),
+      Some(History::KeepLast { depth }) => (RmwHistoryPolicy::KeepLast, depth.max(0) as usize),
+      Some(History::KeepAll) => (RmwHistoryPolicy::KeepAll, 0),
+    };
+    let reliability = match qos.reliability() {
+      None => RmwReliabilityPolicy::SystemDefault,
+      Some(Reliability::Reliable { .. }) => RmwReliabilityPolicy::Reliable,
+      Some(Reliability::BestEffort) => RmwReliabilityPolicy::BestEffort,
+    };
+    let durability = match qos.durability() {
+      None => RmwDurabilityPolicy::SystemDefault,
+      Some(Durability::Volatile) => RmwDurabilityPolicy::Volatile,
+      Some(Durability::TransientLocal) => RmwDurabilityPolicy::TransientLocal,
+      // not representable in rmw
+      Some(Durability::Transient) | Some(Durability::Persistent) => RmwDurabilityPolicy::Unknown,
+    };
+    let (liveliness, liveliness_lease_duration) = match qos.liveliness() {
+      None => (RmwLivelinessPolicy::SystemDefault, Duration::DURATION_ZERO),
+      Some(Liveliness::Automatic { lease_duration }) => {
+        (RmwLivelinessPolicy::Automatic, lease_duration)
+      }
+      Some(Liveliness::ManualByParticipant { lease_duration }) => {
+        (RmwLivelinessPolicy::ManualByNode, lease_duration)
+      }
+      Some(Liveliness::ManualByTopic { lease_duration }) => {
+        (RmwLivelinessPolicy::ManualByTopic, lease_duration)
+      }
+    };
+    RmwQosProfile {
+      history,
+      depth,
+      reliability,
+      durability,
+      deadline: qos
+        .deadline()
+        .map_or(Duration::DURATION_ZERO, |Deadline(period)| period),
+      lifespan: qos
+        .lifespan()
+        .map_or(Duration::DURATION_ZERO, |lifespan| lifespan.duration),
+      liveliness,
+      liveliness_lease_duration,
+      avoid_ros_namespace_conventions: false,
+    }
+  }
+}
+
+impl From<RmwQosProfile> for QosPolicies {
+  fn from(profile: RmwQosProfile) -> QosPolicies {
+    let mut builder = QosPolicies::builder();
+    match profile.history {
+      // DDS does not allow a depth of zero
+      RmwHistoryPolicy::KeepLast => {
+        builder = builder.history(History::KeepLast {
+          depth: profile.depth.clamp(1, i32::MAX as usize) as i32,
+        })
+      }
+      RmwHistoryPolicy::KeepAll => builder = builder.history(History::KeepAll),
+      RmwHistoryPolicy::SystemDefault | RmwHistoryPolicy::Unknown => (),
+    }
+    match profile.reliability {
+      RmwReliabilityPolicy::Reliable => builder = builder.reliability(RELIABLE),
+      RmwReliabilityPolicy::BestEffort => builder = builder.reliability(Reliability::BestEffort),
+      RmwReliabilityPolicy::SystemDefault | RmwReliabilityPolicy::Unknown => (),
+    }
+    match profile.durability {
+      RmwDurabilityPolicy::Volatile => builder = builder.durability(Durability::Volatile),
+      RmwDurabilityPolicy::TransientLocal => {
+        builder = builder.durability(Durability::TransientLocal)
+      }
+      RmwDurabilityPolicy::SystemDefault | RmwDurabilityPolicy::Unknown => (),
+    }
+    if let Some(period) = specified(profile.deadline) {
+      builder = builder.deadline(Deadline(period));
+    }
+    if let Some(duration) = specified(profile.lifespan) {
+      builder = builder.lifespan(Lifespan { duration });
+    }
+    let lease_duration =
+      specified(profile.liveliness_lease_duration).unwrap_or(Duration::DURATION_INFINITE);
+    match profile.liveliness {
+      RmwLivelinessPolicy::Automatic => {
+        builder = builder.liveliness(Liveliness::Automatic { lease_duration })
+      }
+      RmwLivelinessPolicy::ManualByNode => {
+        builder = builder.liveliness(Liveliness::ManualByParticipant { lease_duration })
+      }
+      RmwLivelinessPolicy::ManualByTopic => {
+        builder = builder.liveliness(Liveliness::ManualByTopic { lease_duration })
+      }
+      RmwLivelinessPolicy::SystemDefault | RmwLivelinessPolicy::Unknown => (),
+    }
+    builder.build()
+  }
+}
+
+struct DisplayDuration(Duration);
+
+impl fmt::Display for DisplayDuration {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.0 == Duration::DURATION_INFINITE || self.0 == Duration::DURATION_ZERO {
+      write!(f, "Infinite")
+    } else {
+      write!(f, "{} nanoseconds", self.0.to_nanoseconds())
+    }
+  }
+}
+
+/// Formats the profile like `ros2 topic info -v`.
+impl fmt::Display for RmwQosProfile {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let history = match self.history {
+      RmwHistoryPolicy::SystemDefault => "SYSTEM_DEFAULT".to_string(),
+      RmwHistoryPolicy::KeepLast => format!("KEEP_LAST ({})", self.depth),
+      RmwHistoryPolicy::KeepAll => "KEEP_ALL".to_string(),
+      RmwHistoryPolicy::Unknown => "UNKNOWN".to_string(),
+    };
+    let reliability = match self.reliability {
+      RmwReliabilityPolicy::SystemDefault => "SYSTEM_DEFAULT",
+      RmwReliabilityPolicy::Reliable => "RELIABLE",
+      RmwReliabilityPolicy::BestEffort => "BEST_EFFORT",
+      RmwReliabilityPolicy::Unknown => "UNKNOWN",
+    };
+    let durability = match self.durability {
+      RmwDurabilityPolicy::SystemDefault => "SYSTEM_DEFAULT",
+      RmwDurabilityPolicy::TransientLocal => "TRANSIENT_LOCAL",
+      RmwDurabilityPolicy::Volatile => "VOLATILE",
+      RmwDurabilityPolicy::Unknown => "UNKNOWN",
+    };
+    let liveliness = match self.liveliness {
+      RmwLivelinessPolicy::SystemDefault => "SYSTEM_DEFAULT",
+      RmwLivelinessPolicy::Automatic => "AUTOMATIC",
+      RmwLivelinessPolicy::ManualByNode => "MANUAL_BY_NODE",
+      RmwLivelinessPolicy::ManualByTopic => "MANUAL_BY_TOPIC",
+      RmwLivelinessPolicy::Unknown => "UNKNOWN",
+    };
+    writeln!(f, "QoS profile:")?;
+    writeln!(f, "  Reliability: {}", reliability)?;
+    writeln!(f, "  History (Depth): {}", history)?;
+    writeln!(f, "  Durability: {}", durability)?;
+    writeln!(f, "  Lifespan: {}", DisplayDuration(self.lifespan))?;
+    writeln!(f, "  Deadline: {}", DisplayDuration(self.deadline))?;
+    writeln!(f, "  Liveliness: {}", liveliness)?;
+    write!(
+      f,
+      "  Liveliness lease duration: {}",
+      DisplayDuration(self.liveliness_lease_duration)
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn presets_round_trip() {
+    for qos in &[
+      default(),
+      sensor_data(),
+      services_default(),
+      parameters(),
+      parameter_events(),
+      system_default(),
+      clock(),
+    ] {
+      assert_eq!(QosPolicies::from(RmwQosProfile::from(qos)), *qos);
+    }
+
+    let rmw = RmwQosProfile::from(&sensor_data());
+    assert_eq!(rmw.history, RmwHistoryPolicy::KeepLast);
+    assert_eq!(rmw.depth, 5);
+    assert_eq!(rmw.reliability, RmwReliabilityPolicy::BestEffort);
+    assert_eq!(rmw.liveliness, RmwLivelinessPolicy::SystemDefault);
+
+    let rmw = RmwQosProfile::from(&rosout());
+    assert_eq!(rmw.durability, RmwDurabilityPolicy::TransientLocal);
+    assert_eq!(rmw.depth, 1000);
+    assert_eq!(rmw.lifespan, Duration::from_secs(10));
+  }
+
+  #[test]
+  fn rmw_profile_mapping() {
+    let rmw = RmwQosProfile {
+      history: RmwHistoryPolicy::KeepLast,
+      depth: 0,
+      reliability: RmwReliabilityPolicy::Unknown,
+      durability: RmwDurabilityPolicy::SystemDefault,
+      deadline: Duration::from_millis(100),
+      lifespan: Duration::DURATION_ZERO,
+      liveliness: RmwLivelinessPolicy::ManualByTopic,
+      liveliness_lease_duration: Duration::DURATION_ZERO,
+      avoid_ros_namespace_conventions: false,
+    };
+    let qos = QosPolicies::from(rmw);
+    assert_eq!(qos.history(), Some(History::KeepLast { depth: 1 }));
+    assert_eq!(qos.reliability(), None);
+    assert_eq!(qos.durability(), None);
+    assert_eq!(qos.deadline(), Some(Deadline(Duration::from_millis(100))));
+    assert_eq!(qos.lifespan(), None);
+    assert_eq!(
+      qos.liveliness(),
+      Some(Liveliness::ManualByTopic {
+        lease_duration: Duration::DURATION_INFINITE
+      })
+    );
+
+    let text = RmwQosProfile::from(&default()).to_string();
+    assert!(text.contains("  Reliability: RELIABLE\n"));
+    assert!(text.contains("  History (Depth): KEEP_LAST (10)\n"));
+    assert!(text.ends_with("  Liveliness lease duration: Infinite"));
+  }
+}
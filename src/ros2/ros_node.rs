@@ -1,7 +1,9 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc,Mutex};
+use std::thread::{self, JoinHandle};
 use log::{error,info};
-use mio::Evented;
+use mio::{Evented, Poll, PollOpt, Ready, Token};
+use mio_extras::channel as mio_channel;
 use serde::{Serialize, de::DeserializeOwned};
 
 use crate::{
@@ -25,9 +27,19 @@ use crate::{
 };
 
 use super::{
+  graph::RosGraph,
   KeyedRosPublisher, KeyedRosSubscriber, RosPublisher, RosSubscriber,
+  action::{Action, ActionClient, ActionServer},
+  clock::{start_time_source, Clock, ClockType, USE_SIM_TIME},
+  names::{
+    normalize_namespace, parse_ros_arguments, process_ros_arguments, remap_namespace,
+    remap_node_name, resolve_topic_name, validate_node_name, RemapRule,
+  },
+  parameters::{start_parameter_services, ParameterDescriptor, ParameterStore, ParameterValue},
+  rosout::{logger_name, RosoutLogger},
+  service::{service_topic_names, Client, Server, Service, ServiceMapping},
   builtin_datatypes::NodeInfo,
-  builtin_datatypes::{Gid, Log, ParameterEvents, ROSParticipantInfo},
+  builtin_datatypes::{Gid, Log, ParameterEvents, ROSParticipantInfo, Time},
   builtin_topics::ParameterEventsTopic,
   builtin_topics::{ROSDiscoveryTopic, RosOutTopic},
 };
@@ -77,6 +89,16 @@ impl RosParticipant {
     self.domain_participant().get_discovered_topics()
   }
 
+  /// View of the ROS 2 graph, i.e. nodes with their topics and services
+  pub fn graph(&self) -> RosGraph {
+    RosGraph::new(self.clone())
+  }
+
+  // Our own nodes and those of the other participants that are alive
+  pub(crate) fn discovered_node_infos(&self) -> Vec<NodeInfo> {
+    self.inner.lock().unwrap().discovered_node_infos()
+  }
+
   pub fn add_node_info(&mut self, node_info: NodeInfo) {
     self.inner.lock().unwrap().add_node_info(node_info)
   }
@@ -101,7 +123,7 @@ impl RosParticipant {
     self.inner.lock().unwrap().ros_discovery_subscriber.clone()
   }
 
-  fn domain_participant(&self) -> DomainParticipant {
+  pub(crate) fn domain_participant(&self) -> DomainParticipant {
     self.inner.lock().unwrap().domain_participant.clone()
   }
 
@@ -235,6 +257,31 @@ impl RosParticipantInner {
     pts
   }
 
+  fn discovered_node_infos(&mut self) -> Vec<NodeInfo> {
+    self.handle_node_read();
+    let own_gid = Gid::from_guid(self.domain_participant.get_guid());
+    // Participants that have left may not have cleared their nodes
+    let alive: HashSet<Gid> = match self.domain_participant.discovery_db().read() {
+      Ok(db) => db
+        .get_participants()
+        .map(|participant| Gid::from_guid(participant.participant_guid))
+        .collect(),
+      Err(e) => {
+        error!("DiscoveryDB is poisoned. {:?}", e);
+        HashSet::new()
+      }
+    };
+    let mut node_infos: Vec<NodeInfo> = self.nodes.values().cloned().collect();
+    node_infos.extend(
+      self
+        .external_nodes
+        .iter()
+        .filter(|(gid, _)| **gid != own_gid && alive.contains(gid))
+        .flat_map(|(_, nodes)| nodes.iter().cloned()),
+    );
+    node_infos
+  }
+
 }
 
 impl Evented for RosParticipant {
@@ -258,9 +305,63 @@ impl Evented for RosParticipant {
 // ----------------------------------------------------------------------------------------------------
 // ----------------------------------------------------------------------------------------------------
 
+fn fully_qualified_name(namespace: &str, name: &str) -> String {
+  format!("{}/{}", namespace.trim_end_matches('/'), name)
+}
+
+/// Helper thread of a node, e.g. the one serving its parameters. The thread is
+/// told to stop, and joined, when this is dropped.
+pub(crate) struct NodeThread {
+  stop_sender: mio_channel::Sender<()>,
+  join_handle: Option<JoinHandle<()>>,
+}
+
+impl NodeThread {
+  /// Registers the stop signal in `poll` with `stop_token`, and runs `body`
+  /// with both in a new thread. `body` must keep the receiver, and return
+  /// once it becomes readable.
+  pub(crate) fn spawn<F>(
+    name: &str,
+    poll: Poll,
+    stop_token: Token,
+    body: F,
+  ) -> Result<NodeThread, Error>
+  where
+    F: FnOnce(Poll, mio_channel::Receiver<()>) + Send + 'static,
+  {
+    let (stop_sender, stop_receiver) = mio_channel::channel();
+    poll.register(&stop_receiver, stop_token, Ready::readable(), PollOpt::edge())?;
+    let join_handle = thread::Builder::new()
+      .name(name.to_string())
+      .spawn(move || body(poll, stop_receiver))?;
+    Ok(NodeThread {
+      stop_sender,
+      join_handle: Some(join_handle),
+    })
+  }
+}
+
+impl Drop for NodeThread {
+  fn drop(&mut self) {
+    // Fails if the thread has already returned, which is fine.
+    let _ = self.stop_sender.send(());
+    if let Some(handle) = self.join_handle.take() {
+      if handle.join().is_err() {
+        error!("Node thread panicked");
+      }
+    }
+  }
+}
+
 /// Configuration of [RosNode](struct.RosNode.html)
 pub struct NodeOptions {
   enable_rosout: bool,
+  start_parameter_services: bool,
+  allow_undeclared_parameters: bool,
+  parameter_service_mapping: ServiceMapping,
+  arguments: Vec<String>,
+  use_global_arguments: bool,
+  use_sim_time: bool,
 }
 
 impl NodeOptions {
@@ -270,8 +371,64 @@ impl NodeOptions {
   pub fn new(/*domain_id: u16, */enable_rosout: bool) -> NodeOptions {
     NodeOptions {
       enable_rosout,
+      start_parameter_services: true,
+      allow_undeclared_parameters: false,
+      parameter_service_mapping: ServiceMapping::default(),
+      arguments: Vec::new(),
+      use_global_arguments: true,
+      use_sim_time: false,
     }
   }
+
+  /// Command line style arguments of the node. Remapping rules are given
+  /// after `--ros-args` as `-r [node:]from:=to`, e.g.
+  /// `--ros-args -r chatter:=/talk -r __node:=talker -r __ns:=/demo`.
+  /// Parameter values are given as `-p [node:]name:=value`, e.g.
+  /// `-p use_sim_time:=true`, and replace the default when the parameter is
+  /// declared. These rules take precedence over those of the process
+  /// arguments.
+  pub fn arguments<S: Into<String>>(mut self, arguments: Vec<S>) -> NodeOptions {
+    self.arguments = arguments.into_iter().map(Into::into).collect();
+    self
+  }
+
+  /// Whether the remapping rules and parameter values in the arguments of
+  /// the process apply to the node. Default is true. Malformed rules in the
+  /// arguments of the process are skipped with a warning.
+  pub fn use_global_arguments(mut self, use_global: bool) -> NodeOptions {
+    self.use_global_arguments = use_global;
+    self
+  }
+
+  /// Default value of the `use_sim_time` parameter. While the parameter is
+  /// true, the node subscribes to `/clock`, and its ROS time follows the
+  /// received time. Default is false.
+  pub fn use_sim_time(mut self, use_sim_time: bool) -> NodeOptions {
+    self.use_sim_time = use_sim_time;
+    self
+  }
+
+  /// Whether the node serves its parameters to other nodes. Default is true.
+  /// If false, the node creates neither the parameter services nor their
+  /// thread. Its parameters are still available through the node itself.
+  pub fn start_parameter_services(mut self, start: bool) -> NodeOptions {
+    self.start_parameter_services = start;
+    self
+  }
+
+  /// Whether other nodes may set parameters that the node has not declared.
+  /// Default is false.
+  pub fn allow_undeclared_parameters(mut self, allow: bool) -> NodeOptions {
+    self.allow_undeclared_parameters = allow;
+    self
+  }
+
+  /// Service mapping of the parameter services. Default is
+  /// [`ServiceMapping::Cyclone`](enum.ServiceMapping.html).
+  pub fn parameter_service_mapping(mut self, mapping: ServiceMapping) -> NodeOptions {
+    self.parameter_service_mapping = mapping;
+    self
+  }
 }
 
 // ----------------------------------------------------------------------------------------------------
@@ -294,9 +451,21 @@ pub struct RosNode {
   writers: HashSet<GUID>,
 
   // builtin writers and readers
-  rosout_writer: Option<NoKeyDataWriter<Log>>,
+  rosout_writer: Option<Arc<Mutex<NoKeyDataWriter<Log>>>>,
   rosout_reader: Option<NoKeyDataReader<Log>>,
-  parameter_events_writer: NoKeyDataWriter<ParameterEvents>,
+  parameter_events_writer_guid: GUID,
+  parameters: Arc<Mutex<ParameterStore>>,
+  remappings: Vec<RemapRule>,
+  clock: Clock,
+  threads: Vec<NodeThread>,
+}
+
+impl Drop for RosNode {
+  fn drop(&mut self) {
+    // The threads own endpoints of the participant. Stop them before the
+    // participant can go away.
+    self.threads.clear();
+  }
 }
 
 impl RosNode {
@@ -306,24 +475,45 @@ impl RosNode {
     options: NodeOptions,
     ros_participant: RosParticipant,
   ) -> Result<RosNode, Error> {
+    validate_node_name(name)?;
+    let mut arguments = parse_ros_arguments(&options.arguments)?;
+    if options.use_global_arguments {
+      arguments.extend(process_ros_arguments());
+    }
+    let remappings = arguments.remappings;
+    let namespace = &normalize_namespace(&remap_namespace(&remappings, name, namespace))?;
+    let name = &remap_node_name(&remappings, name);
+
     let paramtopic = ros_participant.get_parameter_events_topic();
     let rosout_topic = ros_participant.get_rosout_topic();
 
     let rosout_writer = if options.enable_rosout {
-      Some(
+      Some(Arc::new(Mutex::new(
         ros_participant
           .get_ros_discovery_publisher()
-          .create_datawriter_no_key(None, rosout_topic.clone(), None)?,
-      )
+          .create_datawriter_no_key(None, rosout_topic.clone(), Some(RosOutTopic::get_qos()))?,
+      )))
     } else {
       None
     };
 
-    let parameter_events_writer = ros_participant
+    let parameter_events_writer: NoKeyDataWriter<ParameterEvents> = ros_participant
       .get_ros_discovery_publisher()
       .create_datawriter_no_key(None, paramtopic.clone(), None)?;
+    let parameter_events_writer_guid = parameter_events_writer.get_guid();
+    let mut parameters = ParameterStore::new(
+      fully_qualified_name(namespace, name),
+      options.allow_undeclared_parameters,
+      &arguments.parameters,
+      parameter_events_writer,
+    );
+    parameters.declare(
+      USE_SIM_TIME,
+      ParameterValue::Bool(options.use_sim_time),
+      ParameterDescriptor::default(),
+    )?;
 
-    Ok(RosNode {
+    let mut node = RosNode {
       name: String::from(name),
       namespace: String::from(namespace),
       options,
@@ -332,17 +522,33 @@ impl RosNode {
       writers: HashSet::new(),
       rosout_writer,
       rosout_reader: None,
-      parameter_events_writer,
-    })
+      parameter_events_writer_guid,
+      parameters: Arc::new(Mutex::new(parameters)),
+      remappings,
+      clock: Clock::new(ClockType::RosTime),
+      threads: Vec::new(),
+    };
+    // The parameter may become true later, so the time source always runs.
+    let thread = start_time_source(&node, &node.parameters)?;
+    node.threads.push(thread);
+    if node.options.start_parameter_services {
+      let parameters = node.parameters.clone();
+      let mapping = node.options.parameter_service_mapping;
+      let thread = start_parameter_services(&mut node, &parameters, mapping)?;
+      node.threads.push(thread);
+    }
+    Ok(node)
   }
 
   // Generates ROS2 node info from added readers and writers.
   fn generate_node_info(&self) -> NodeInfo {
     let mut node_info = NodeInfo::new(self.name.to_owned(), self.namespace.to_owned());
 
-    node_info.add_writer( Gid::from_guid(self.parameter_events_writer.get_guid()) );
+    node_info.add_writer( Gid::from_guid(self.parameter_events_writer_guid) );
     if let Some(row) = &self.rosout_writer {
-      node_info.add_writer( Gid::from_guid(row.get_guid()) )
+      if let Ok(row) = row.lock() {
+        node_info.add_writer( Gid::from_guid(row.get_guid()) )
+      }
     }
 
     for reader in self.readers.iter() {
@@ -391,10 +597,14 @@ impl RosNode {
     &self.namespace
   }
 
+  /// Namespace and name of the node, e.g. `/some_namespace/some_node_name`
   pub fn get_fully_qualified_name(&self) -> String {
-    let mut nn = self.name.clone();
-    nn.push_str(&self.namespace);
-    nn
+    fully_qualified_name(&self.namespace, &self.name)
+  }
+
+  // Subscriber for the internal readers of the node
+  pub(crate) fn ros_subscriber(&self) -> Subscriber {
+    self.ros_participant.get_ros_discovery_subscriber()
   }
 
   pub fn get_options(&self) -> &NodeOptions {
@@ -405,12 +615,126 @@ impl RosNode {
     self.ros_participant.domain_id()
   }
 
+  /// ROS time clock of the node, which follows `/clock` if the node uses
+  /// simulated time
+  pub fn get_clock(&self) -> Clock {
+    self.clock.clone()
+  }
+
+  /// Current ROS time of the node
+  pub fn now(&self) -> Time {
+    self.clock.now()
+  }
+
+  /// Logger for the `log` crate that publishes the records of the application
+  /// to `/rosout` as this node. Requires `enable_rosout` in the node options.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use rustdds::ros2::{NodeOptions, RosParticipant};
+  ///
+  /// let ros_participant = RosParticipant::new().unwrap();
+  /// let node = ros_participant
+  ///   .new_RosNode("turtle", "/", NodeOptions::new(true))
+  ///   .unwrap();
+  /// node
+  ///   .rosout_logger()
+  ///   .unwrap()
+  ///   .level(log::LevelFilter::Debug)
+  ///   .init()
+  ///   .unwrap();
+  /// log::info!("published to /rosout");
+  /// ```
+  pub fn rosout_logger(&self) -> Result<RosoutLogger, Error> {
+    match &self.rosout_writer {
+      Some(writer) => Ok(RosoutLogger::new(
+        logger_name(&self.namespace, &self.name),
+        writer.clone(),
+      )),
+      None => Error::precondition_not_met("rosout is not enabled in node options"),
+    }
+  }
+
+  /// Declares a parameter with its default value. The type of the value is
+  /// the type of the parameter, unless the descriptor allows dynamic typing.
+  /// The declaration is published on `/parameter_events`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use rustdds::ros2::{NodeOptions, ParameterDescriptor, ParameterValue, RosParticipant};
+  ///
+  /// let ros_participant = RosParticipant::new().unwrap();
+  /// let mut node = ros_participant
+  ///   .new_RosNode("turtle", "/", NodeOptions::new(false))
+  ///   .unwrap();
+  /// node
+  ///   .declare_parameter("speed", ParameterValue::Double(1.0), ParameterDescriptor::default())
+  ///   .unwrap();
+  /// node.set_parameter("speed", ParameterValue::Double(2.5)).unwrap();
+  /// // wrong type
+  /// assert!(node.set_parameter("speed", ParameterValue::Integer(3)).is_err());
+  /// assert_eq!(node.get_parameter("speed"), Some(ParameterValue::Double(2.5)));
+  /// ```
+  pub fn declare_parameter(
+    &mut self,
+    name: &str,
+    default_value: ParameterValue,
+    descriptor: ParameterDescriptor,
+  ) -> Result<(), Error> {
+    self.parameters.lock()?.declare(name, default_value, descriptor)
+  }
+
+  /// Removes a parameter that is not read-only
+  pub fn undeclare_parameter(&mut self, name: &str) -> Result<(), Error> {
+    self.parameters.lock()?.undeclare(name)
+  }
+
+  pub fn has_parameter(&self, name: &str) -> bool {
+    self.get_parameter(name).is_some()
+  }
+
+  /// Current value of a parameter, which other nodes may have changed
+  pub fn get_parameter(&self, name: &str) -> Option<ParameterValue> {
+    self.parameters.lock().ok()?.get(name)
+  }
+
+  /// Sets a parameter, checking it against the descriptor of the parameter.
+  /// Undeclared parameters are declared with dynamic typing.
+  pub fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), Error> {
+    self.parameters.lock()?.set(name, value)
+  }
+
+  /// Names of all parameters
+  pub fn list_parameters(&self) -> Vec<String> {
+    self
+      .parameters
+      .lock()
+      .map(|parameters| parameters.names())
+      .unwrap_or_default()
+  }
+
+  /// Expands a topic or service name to a fully qualified name and applies
+  /// the remapping rules of the node. Names are validated with the rules of
+  /// [`create_ros_topic`](#method.create_ros_topic).
+  ///
+  /// * a relative name `foo` is resolved in the namespace of the node
+  /// * a private name `~/foo` is resolved in the namespace of the node and
+  ///   under its name
+  /// * `{node}`, `{ns}` and `{namespace}` are replaced with the node name and
+  ///   namespace
+  pub fn resolve_topic_name(&self, name: &str) -> Result<String, Error> {
+    resolve_topic_name(&self.remappings, name, &self.name, &self.namespace)
+  }
+
   /// Creates ROS2 topic and handles necessary conversions from DDS to ROS2
   ///
   /// # Arguments
   ///
   /// * `domain_participant` - [DomainParticipant](../dds/struct.DomainParticipant.html)
-  /// * `name` - Name of the topic
+  /// * `name` - Name of the topic, resolved with
+  ///   [`resolve_topic_name`](#method.resolve_topic_name)
   /// * `type_name` - What type the topic holds in string form
   /// * `qos` - Quality of Service parameters for the topic (not restricted only to ROS2)
   /// * `topic_kind` - Does the topic have a key (multiple DDS instances)? NoKey or WithKey
@@ -435,12 +759,8 @@ impl RosNode {
     qos: QosPolicies,
     topic_kind: TopicKind,
   ) -> Result<Topic, Error> {
-    if name.len() == 0 { return Error::bad_parameter("Topic name must not be empty.") }
-    // TODO: Implement the rest of the rules.
-
-    let mut oname = "rt/".to_owned();
-    let name_stripped = name.strip_prefix("/").unwrap_or(name); // avoid double slash in name
-    oname.push_str(name_stripped);
+    let mut oname = "rt".to_owned();
+    oname.push_str(&self.resolve_topic_name(name)?);
     info!("Creating topic, DDS name: {}",oname);
     let topic = self.ros_participant.domain_participant()
       .create_topic(&oname, type_name, &qos, topic_kind)?;
@@ -532,4 +852,147 @@ impl RosNode {
     self.add_writer( p.get_guid() );
     Ok(p)
   }
+
+  /// Creates the server end of a ROS 2 service.
+  ///
+  /// # Arguments
+  ///
+  /// * `service_name` - Name of the service, resolved like topic names.
+  ///   Requests are received from the DDS topic `rq/<service_name>Request`
+  ///   and responses are sent to `rr/<service_name>Reply`.
+  /// * `mapping` - How the request header is encoded. Must match the clients.
+  /// * `qos` - Quality of Service parameters of both topics. ROS 2 uses
+  ///   reliable, volatile and keep last 10 by default.
+  pub fn create_service<S: Service>(
+    &mut self,
+    service_name: &str,
+    mapping: ServiceMapping,
+    qos: QosPolicies,
+  ) -> Result<Server<S>, Error> {
+    let (request_topic_name, reply_topic_name) =
+      service_topic_names(&self.resolve_topic_name(service_name)?);
+    let domain_participant = self.ros_participant.domain_participant();
+    let request_topic = domain_participant
+      .create_topic(&request_topic_name, &S::request_type_name(), &qos, TopicKind::NoKey)?;
+    let reply_topic = domain_participant
+      .create_topic(&reply_topic_name, &S::response_type_name(), &qos, TopicKind::NoKey)?;
+
+    let request_reader = self
+      .ros_participant
+      .get_ros_discovery_subscriber()
+      .create_raw_datareader(request_topic, Some(qos.clone()))?;
+    let response_writer = self
+      .ros_participant
+      .get_ros_discovery_publisher()
+      .create_raw_datawriter(reply_topic, Some(qos))?;
+    let server = Server::new(mapping, request_reader, response_writer);
+    self.add_reader( server.request_reader_guid() );
+    self.add_writer( server.response_writer_guid() );
+    Ok(server)
+  }
+
+  /// Creates the client end of a ROS 2 service. Arguments are as in
+  /// [`create_service`](#method.create_service).
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use serde::{Deserialize, Serialize};
+  /// use rustdds::dds::qos::{QosPolicies, policy::{History, Reliability}};
+  /// use rustdds::dds::data_types::DDSDuration;
+  /// use rustdds::ros2::{NodeOptions, RosParticipant, Service, ServiceMapping};
+  ///
+  /// # #[derive(Serialize, Deserialize)]
+  /// # pub struct AddTwoIntsRequest { pub a: i64, pub b: i64 }
+  /// # #[derive(Serialize, Deserialize)]
+  /// # pub struct AddTwoIntsResponse { pub sum: i64 }
+  /// pub struct AddTwoInts;
+  ///
+  /// impl Service for AddTwoInts {
+  ///   type Request = AddTwoIntsRequest;
+  ///   type Response = AddTwoIntsResponse;
+  ///   fn type_name() -> String {
+  ///     "example_interfaces::srv::dds_::AddTwoInts_".to_string()
+  ///   }
+  /// }
+  ///
+  /// let ros_participant = RosParticipant::new().unwrap();
+  /// let mut node = ros_participant
+  ///   .new_RosNode("adder", "/", NodeOptions::new(false))
+  ///   .unwrap();
+  /// let qos = QosPolicies::builder()
+  ///   .reliability(Reliability::Reliable { max_blocking_time: DDSDuration::DURATION_ZERO })
+  ///   .history(History::KeepLast { depth: 10 })
+  ///   .build();
+  ///
+  /// let mut server = node
+  ///   .create_service::<AddTwoInts>("/add_two_ints", ServiceMapping::Cyclone, qos.clone())
+  ///   .unwrap();
+  /// let client = node
+  ///   .create_client::<AddTwoInts>("/add_two_ints", ServiceMapping::Cyclone, qos)
+  ///   .unwrap();
+  ///
+  /// let response = client.call_async(AddTwoIntsRequest { a: 1, b: 2 });
+  /// // In the server, usually polled with mio:
+  /// # std::thread::sleep(std::time::Duration::from_millis(100));
+  /// while let Ok(Some((id, request))) = server.receive_request() {
+  ///   server.send_response(id, AddTwoIntsResponse { sum: request.a + request.b }).unwrap();
+  /// }
+  /// // `response` is a future. client.call(..) would block instead.
+  /// # drop(response);
+  /// ```
+  pub fn create_client<S: Service>(
+    &mut self,
+    service_name: &str,
+    mapping: ServiceMapping,
+    qos: QosPolicies,
+  ) -> Result<Client<S>, Error>
+  where
+    S::Response: Send,
+  {
+    let (request_topic_name, reply_topic_name) =
+      service_topic_names(&self.resolve_topic_name(service_name)?);
+    let domain_participant = self.ros_participant.domain_participant();
+    let request_topic = domain_participant
+      .create_topic(&request_topic_name, &S::request_type_name(), &qos, TopicKind::NoKey)?;
+    let reply_topic = domain_participant
+      .create_topic(&reply_topic_name, &S::response_type_name(), &qos, TopicKind::NoKey)?;
+
+    let request_writer = self
+      .ros_participant
+      .get_ros_discovery_publisher()
+      .create_raw_datawriter(request_topic, Some(qos.clone()))?;
+    let reply_reader = self
+      .ros_participant
+      .get_ros_discovery_subscriber()
+      .create_raw_datareader(reply_topic, Some(qos))?;
+    let client = Client::new(mapping, request_writer, reply_reader)?;
+    self.add_writer( client.request_writer_guid() );
+    self.add_reader( client.reply_reader_guid() );
+    Ok(client)
+  }
+
+  /// Creates a client of a ROS 2 action. The services and topics of the
+  /// action are named under `<action_name>/_action/`, and use the QoS
+  /// defaults of ROS 2.
+  ///
+  /// * `mapping` - How the request header of the services is encoded. Must
+  ///   match the server.
+  pub fn create_action_client<A: Action>(
+    &mut self,
+    action_name: &str,
+    mapping: ServiceMapping,
+  ) -> Result<ActionClient<A>, Error> {
+    ActionClient::new(self, action_name, mapping)
+  }
+
+  /// Creates a server of a ROS 2 action. Arguments are as in
+  /// [`create_action_client`](#method.create_action_client).
+  pub fn create_action_server<A: Action>(
+    &mut self,
+    action_name: &str,
+    mapping: ServiceMapping,
+  ) -> Result<ActionServer<A>, Error> {
+    ActionServer::new(self, action_name, mapping)
+  }
 }
@@ -0,0 +1,162 @@
+//! Forwarding of the `log` crate to the ROS 2 `/rosout` topic
+
+use std::{
+  cell::Cell,
+  sync::{Arc, Mutex},
+};
+
+use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+
+use crate::{dds::no_key::datawriter::DataWriter as NoKeyDataWriter, structure::time::Timestamp};
+
+use super::builtin_datatypes::Log;
+
+/// Severity levels of `rcl_interfaces/Log`
+pub mod log_level {
+  pub const DEBUG: u8 = 10;
+  pub const INFO: u8 = 20;
+  pub const WARN: u8 = 30;
+  pub const ERROR: u8 = 40;
+  pub const FATAL: u8 = 50;
+}
+
+fn ros_level(level: Level) -> u8 {
+  match level {
+    Level::Error => log_level::ERROR,
+    Level::Warn => log_level::WARN,
+    Level::Info => log_level::INFO,
+    Level::Debug | Level::Trace => log_level::DEBUG,
+  }
+}
+
+// Logger name of a node as in rcl: the namespace and the name separated by
+// dots, e.g. "ns.turtle" for "/ns/turtle"
+pub(crate) fn logger_name(namespace: &str, name: &str) -> String {
+  namespace
+    .split('/')
+    .filter(|part| !part.is_empty())
+    .chain(std::iter::once(name))
+    .collect::<Vec<&str>>()
+    .join(".")
+}
+
+thread_local! {
+  // Set while a record is being published, so that log records from the
+  // publishing itself are not published again.
+  static PUBLISHING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// `log` crate logger that publishes the records to `/rosout` on behalf of a
+/// node, so that they appear in e.g. `rqt_console`. Created with
+/// [`RosNode::rosout_logger`](struct.RosNode.html#method.rosout_logger).
+///
+/// Records of RustDDS itself are not published. Records can also be passed
+/// to another logger, e.g. to print them.
+///
+/// The `function` field of the published messages is left empty. `log`
+/// records do not tell the function, only the module path, and tools such as
+/// `rqt_console` would show a module path there as if it were a function.
+pub struct RosoutLogger {
+  name: String,
+  writer: Arc<Mutex<NoKeyDataWriter<Log>>>,
+  level: LevelFilter,
+  chained: Option<Box<dyn log::Log>>,
+}
+
+impl RosoutLogger {
+  pub(crate) fn new(name: String, writer: Arc<Mutex<NoKeyDataWriter<Log>>>) -> RosoutLogger {
+    RosoutLogger {
+      name,
+      writer,
+      level: LevelFilter::Info,
+      chained: None,
+    }
+  }
+
+  /// Sets the most verbose level that is published. Default is `Info`.
+  pub fn level(mut self, level: LevelFilter) -> RosoutLogger {
+    self.level = level;
+    self
+  }
+
+  /// Passes all records also to `logger`, including those of RustDDS.
+  pub fn chain(mut self, logger: Box<dyn log::Log>) -> RosoutLogger {
+    self.chained = Some(logger);
+    self
+  }
+
+  /// Installs this as the global logger and sets the maximum log level of
+  /// the `log` crate to the level of this logger, or to `Trace` if another
+  /// logger is chained. Fails if a global logger is already installed.
+  pub fn init(self) -> Result<(), SetLoggerError> {
+    let level = if self.chained.is_some() {
+      LevelFilter::Trace
+    } else {
+      self.level
+    };
+    log::set_logger(Box::leak(Box::new(self)))?;
+    log::set_max_level(level);
+    Ok(())
+  }
+
+  fn publish(&self, record: &Record) {
+    let log = Log {
+      timestamp: Timestamp::now(),
+      level: ros_level(record.level()),
+      name: self.name.clone(),
+      msg: record.args().to_string(),
+      file: record.file().unwrap_or_default().to_string(),
+      // Not known, see the RosoutLogger documentation
+      function: String::new(),
+      line: record.line().unwrap_or(0),
+    };
+    // Nowhere to report a failure to log
+    if let Ok(writer) = self.writer.lock() {
+      let _ = writer.write(log, None);
+    }
+  }
+}
+
+impl log::Log for RosoutLogger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    metadata.level() <= self.level
+      || self
+        .chained
+        .as_ref()
+        .is_some_and(|chained| chained.enabled(metadata))
+  }
+
+  fn log(&self, record: &Record) {
+    if let Some(chained) = &self.chained {
+      chained.log(record);
+    }
+    if record.level() > self.level || record.target().starts_with("rustdds") {
+      return;
+    }
+    PUBLISHING.with(|publishing| {
+      if !publishing.replace(true) {
+        self.publish(record);
+        publishing.set(false);
+      }
+    });
+  }
+
+  fn flush(&self) {
+    if let Some(chained) = &self.chained {
+      chained.flush();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn names_and_levels() {
+    assert_eq!(logger_name("/", "turtle"), "turtle");
+    assert_eq!(logger_name("/ns/sub/", "turtle"), "ns.sub.turtle");
+    assert_eq!(ros_level(Level::Trace), log_level::DEBUG);
+    assert_eq!(ros_level(Level::Warn), log_level::WARN);
+  }
+}
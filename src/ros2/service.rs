@@ -0,0 +1,901 @@
+//! ROS 2 services: request/reply over a pair of topics
+//!
+//! A service `/name` uses the request topic `rq/nameRequest` and the reply
+//! topic `rr/nameReply`. Each request carries an id of the client and the
+//! request. The server copies it to the reply, so that the client can pick
+//! its own replies from the shared reply topic. How the id is sent depends on
+//! the RMW implementation, see [`ServiceMapping`].
+//!
+//! [`ServiceMapping`]: enum.ServiceMapping.html
+
+use std::{
+  collections::HashMap,
+  future::Future,
+  marker::PhantomData,
+  pin::Pin,
+  sync::{Arc, Mutex, Weak},
+  task::{Context, Poll, Wake, Waker},
+  thread,
+  time::{Duration, Instant},
+};
+
+use log::{debug, error, warn};
+use mio::{Evented, Events, PollOpt, Ready, Token};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+  dds::{
+    data_types::{SampleIdentity, SampleInfo},
+    traits::serde_adapters::DeserializerAdapter,
+    values::result::Error,
+    RawDataReader, RawDataWriter,
+  },
+  messages::submessages::submessage_elements::serialized_payload::{
+    RepresentationIdentifier, SerializedPayload,
+  },
+  serialization::{cdr_serializer::to_little_endian_binary, CDRDeserializerAdapter},
+  structure::{
+    entity::RTPSEntity,
+    guid::{GuidPrefix, GUID},
+    sequence_number::SequenceNumber,
+  },
+};
+
+/// How the request id is sent with the samples. Both ends of a service must
+/// use the same mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ServiceMapping {
+  /// The request header is the sample identity of the request in the
+  /// payload: the GUID of the client's request writer and a sequence number,
+  /// 24 bytes. rmw_connextdds uses this layout only with
+  /// `RMW_CONNEXT_REQUEST_REPLY_MAPPING=basic`.
+  Basic,
+  /// The 16-byte request header of rmw_cyclonedds: a 64-bit client id and a
+  /// sequence number. This is the default.
+  #[default]
+  Cyclone,
+  /// No header: the payload is the request or response only. A request is
+  /// identified by its sample identity, and the reply relates to it in
+  /// inline QoS parameter PID_RELATED_SAMPLE_IDENTITY. The client sends the
+  /// GUID of its reply reader as the related identity of the request, and
+  /// the server puts it in the request id in place of the request writer.
+  /// This is the default of rmw_fastrtps and rmw_connextdds.
+  FastDds,
+}
+
+/// Request and response types of a ROS 2 service
+///
+/// # Example
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use rustdds::ros2::Service;
+///
+/// #[derive(Serialize, Deserialize)]
+/// pub struct AddTwoIntsRequest { pub a: i64, pub b: i64 }
+/// #[derive(Serialize, Deserialize)]
+/// pub struct AddTwoIntsResponse { pub sum: i64 }
+///
+/// pub struct AddTwoInts;
+///
+/// impl Service for AddTwoInts {
+///   type Request = AddTwoIntsRequest;
+///   type Response = AddTwoIntsResponse;
+///   fn type_name() -> String {
+///     "example_interfaces::srv::dds_::AddTwoInts_".to_string()
+///   }
+/// }
+/// ```
+pub trait Service {
+  type Request: Serialize + DeserializeOwned + 'static;
+  type Response: Serialize + DeserializeOwned + 'static;
+
+  /// DDS type name of the service without the `Request_` or `Response_`
+  /// suffix, e.g. `example_interfaces::srv::dds_::AddTwoInts_`
+  fn type_name() -> String;
+
+  fn request_type_name() -> String {
+    Self::type_name() + "Request_"
+  }
+
+  fn response_type_name() -> String {
+    Self::type_name() + "Response_"
+  }
+}
+
+/// Identifies a request at the server, so that the response reaches the
+/// right client.
+///
+/// With [`ServiceMapping::Cyclone`](enum.ServiceMapping.html) the client is
+/// identified by a 64-bit id only. It is stored in the first eight bytes of
+/// `writer_guid`. With [`ServiceMapping::FastDds`](enum.ServiceMapping.html)
+/// `writer_guid` is usually the GUID of the client's reply reader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RmwRequestId {
+  pub writer_guid: GUID,
+  pub sequence_number: i64,
+}
+
+// Sample identity as in the Connext request header
+#[derive(Serialize, Deserialize)]
+struct RequestHeader {
+  writer_guid: GUID,
+  sequence_number_high: i32,
+  sequence_number_low: u32,
+}
+
+// cdds_request_header_t of rmw_cyclonedds
+#[derive(Serialize, Deserialize)]
+struct CycloneHeader {
+  client_id: u64,
+  sequence_number: i64,
+}
+
+impl From<SampleIdentity> for RmwRequestId {
+  fn from(identity: SampleIdentity) -> RmwRequestId {
+    RmwRequestId {
+      writer_guid: identity.writer_guid,
+      sequence_number: identity.sequence_number.into(),
+    }
+  }
+}
+
+impl From<RmwRequestId> for SampleIdentity {
+  fn from(id: RmwRequestId) -> SampleIdentity {
+    SampleIdentity {
+      writer_guid: id.writer_guid,
+      sequence_number: SequenceNumber::from(id.sequence_number),
+    }
+  }
+}
+
+impl RmwRequestId {
+  fn from_cyclone_client_id(client_id: u64, sequence_number: i64) -> RmwRequestId {
+    RmwRequestId {
+      writer_guid: GUID {
+        guidPrefix: GuidPrefix::new(&client_id.to_be_bytes()),
+        ..GUID::GUID_UNKNOWN
+      },
+      sequence_number,
+    }
+  }
+
+  fn cyclone_client_id(&self) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&self.writer_guid.guidPrefix.entityKey[..8]);
+    u64::from_be_bytes(bytes)
+  }
+}
+
+// The id by which a Cyclone client recognizes its replies. It only has to
+// be unique, so both the prefix and the entity id are folded into it.
+fn cyclone_client_id(guid: GUID) -> u64 {
+  let mut bytes = [0; 16];
+  bytes[..12].copy_from_slice(&guid.guidPrefix.entityKey);
+  bytes[12..15].copy_from_slice(&guid.entityId.entityKey);
+  bytes[15] = u8::from(guid.entityId.entityKind);
+  let (high, low) = bytes.split_at(8);
+  let mut folded = [0; 8];
+  for (f, (h, l)) in folded.iter_mut().zip(high.iter().zip(low)) {
+    *f = h ^ l;
+  }
+  u64::from_be_bytes(folded)
+}
+
+fn encode<T: Serialize>(
+  mapping: ServiceMapping,
+  id: RmwRequestId,
+  body: &T,
+) -> Result<SerializedPayload, Error> {
+  // The header and the body form a single CDR stream, so the body is
+  // aligned as if it were a member of the same structure.
+  let bytes = match mapping {
+    ServiceMapping::Basic => {
+      let header = RequestHeader {
+        writer_guid: id.writer_guid,
+        sequence_number_high: (id.sequence_number >> 32) as i32,
+        sequence_number_low: id.sequence_number as u32,
+      };
+      to_little_endian_binary(&(header, body))?
+    }
+    ServiceMapping::Cyclone => {
+      let header = CycloneHeader {
+        client_id: id.cyclone_client_id(),
+        sequence_number: id.sequence_number,
+      };
+      to_little_endian_binary(&(header, body))?
+    }
+    // The id is sent in inline QoS.
+    ServiceMapping::FastDds => to_little_endian_binary(body)?,
+  };
+  Ok(SerializedPayload::new(
+    RepresentationIdentifier::CDR_LE,
+    bytes,
+  ))
+}
+
+fn decode<T: DeserializeOwned>(
+  mapping: ServiceMapping,
+  sample_info: &SampleInfo,
+  payload: &SerializedPayload,
+) -> Result<(RmwRequestId, T), Error> {
+  let encoding = payload.representation_identifier();
+  match mapping {
+    ServiceMapping::Basic => {
+      let (header, body): (RequestHeader, T) =
+        CDRDeserializerAdapter::from_bytes(&payload.value, encoding)?;
+      let sequence_number =
+        ((header.sequence_number_high as i64) << 32) + header.sequence_number_low as i64;
+      Ok((
+        RmwRequestId {
+          writer_guid: header.writer_guid,
+          sequence_number,
+        },
+        body,
+      ))
+    }
+    ServiceMapping::Cyclone => {
+      let (header, body): (CycloneHeader, T) =
+        CDRDeserializerAdapter::from_bytes(&payload.value, encoding)?;
+      Ok((
+        RmwRequestId::from_cyclone_client_id(header.client_id, header.sequence_number),
+        body,
+      ))
+    }
+    // A request relates to the reply reader of the client, with an unknown
+    // sequence number. A reply relates to the request id. So the id is the
+    // related identity, with its unknown parts taken from the sample itself.
+    ServiceMapping::FastDds => {
+      let body = CDRDeserializerAdapter::from_bytes(&payload.value, encoding)?;
+      let mut id = sample_info.sample_identity;
+      if let Some(related) = sample_info.related_sample_identity {
+        if related.writer_guid != GUID::GUID_UNKNOWN {
+          id.writer_guid = related.writer_guid;
+        }
+        if related.sequence_number != SequenceNumber::SEQUENCENUMBER_UNKNOWN {
+          id.sequence_number = related.sequence_number;
+        }
+      }
+      Ok((RmwRequestId::from(id), body))
+    }
+  }
+}
+
+/// DDS names of the request and reply topics of a service
+pub(crate) fn service_topic_names(service_name: &str) -> (String, String) {
+  let name = service_name.strip_prefix('/').unwrap_or(service_name);
+  (format!("rq/{}Request", name), format!("rr/{}Reply", name))
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+/// Server end of a ROS 2 service. Created with
+/// [`RosNode::create_service`](struct.RosNode.html#method.create_service).
+///
+/// The server implements mio `Evented`, and becomes readable when requests
+/// arrive.
+pub struct Server<S: Service> {
+  mapping: ServiceMapping,
+  request_reader: RawDataReader,
+  response_writer: RawDataWriter,
+  phantom: PhantomData<S>,
+}
+
+impl<S: Service> Server<S> {
+  pub(crate) fn new(
+    mapping: ServiceMapping,
+    request_reader: RawDataReader,
+    response_writer: RawDataWriter,
+  ) -> Server<S> {
+    Server {
+      mapping,
+      request_reader,
+      response_writer,
+      phantom: PhantomData,
+    }
+  }
+
+  /// Takes the next received request, if any. Requests that cannot be
+  /// decoded are skipped with a warning.
+  pub fn receive_request(&mut self) -> Result<Option<(RmwRequestId, S::Request)>, Error> {
+    while let Some(sample) = self.request_reader.take_next_sample()? {
+      if let Some(payload) = sample.payload() {
+        match decode(self.mapping, sample.sample_info(), payload) {
+          Ok(request) => return Ok(Some(request)),
+          Err(e) => warn!("Cannot decode service request: {:?}", e),
+        }
+      }
+    }
+    Ok(None)
+  }
+
+  /// Sends the response to the request identified by `request_id`
+  pub fn send_response(
+    &self,
+    request_id: RmwRequestId,
+    response: S::Response,
+  ) -> Result<(), Error> {
+    let payload = encode(self.mapping, request_id, &response)?;
+    match self.mapping {
+      ServiceMapping::FastDds => {
+        self
+          .response_writer
+          .write_related(payload, 0, SampleIdentity::from(request_id), None)
+      }
+      _ => self.response_writer.write(payload, 0, None),
+    }
+  }
+
+  pub fn mapping(&self) -> ServiceMapping {
+    self.mapping
+  }
+
+  pub(crate) fn request_reader_guid(&self) -> GUID {
+    self.request_reader.get_guid()
+  }
+
+  pub(crate) fn response_writer_guid(&self) -> GUID {
+    self.response_writer.get_guid()
+  }
+}
+
+impl<S: Service> Evented for Server<S> {
+  fn register(
+    &self,
+    poll: &mio::Poll,
+    token: Token,
+    interest: Ready,
+    opts: PollOpt,
+  ) -> std::io::Result<()> {
+    self.request_reader.register(poll, token, interest, opts)
+  }
+
+  fn reregister(
+    &self,
+    poll: &mio::Poll,
+    token: Token,
+    interest: Ready,
+    opts: PollOpt,
+  ) -> std::io::Result<()> {
+    self.request_reader.reregister(poll, token, interest, opts)
+  }
+
+  fn deregister(&self, poll: &mio::Poll) -> std::io::Result<()> {
+    self.request_reader.deregister(poll)
+  }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+
+enum PendingCall<R> {
+  Waiting(Option<Waker>),
+  Done(Result<R, Error>),
+}
+
+// Replies are taken from the reader by a background thread, which completes
+// the pending calls of the client.
+struct ReplyState<R> {
+  mapping: ServiceMapping,
+  client: RmwRequestId,
+  reply_reader: RawDataReader,
+  pending: HashMap<i64, PendingCall<R>>,
+}
+
+impl<R: DeserializeOwned> ReplyState<R> {
+  fn is_own(&self, id: &RmwRequestId) -> bool {
+    match self.mapping {
+      ServiceMapping::Basic => id.writer_guid == self.client.writer_guid,
+      ServiceMapping::Cyclone => id.cyclone_client_id() == self.client.cyclone_client_id(),
+      ServiceMapping::FastDds => {
+        id.writer_guid == self.client.writer_guid || id.writer_guid == self.reply_reader.get_guid()
+      }
+    }
+  }
+
+  fn receive_replies(&mut self) {
+    loop {
+      let sample = match self.reply_reader.take_next_sample() {
+        Ok(Some(sample)) => sample,
+        Ok(None) => return,
+        Err(e) => {
+          error!("Reading service replies failed: {:?}", e);
+          return;
+        }
+      };
+      let payload = match sample.payload() {
+        Some(payload) => payload,
+        None => continue,
+      };
+      // The header is decoded separately, so that a reply of another client
+      // is not reported even if its body is garbage to us.
+      let (id, ()) = match decode::<()>(self.mapping, sample.sample_info(), payload) {
+        Ok(header) => header,
+        Err(e) => {
+          warn!("Cannot decode service reply header: {:?}", e);
+          continue;
+        }
+      };
+      if !self.is_own(&id) {
+        continue;
+      }
+      match self.pending.remove(&id.sequence_number) {
+        Some(PendingCall::Waiting(waker)) => {
+          let result =
+            decode::<R>(self.mapping, sample.sample_info(), payload).map(|(_, response)| response);
+          self
+            .pending
+            .insert(id.sequence_number, PendingCall::Done(result));
+          if let Some(waker) = waker {
+            waker.wake();
+          }
+        }
+        Some(done) => {
+          debug!("Duplicate reply to request {}", id.sequence_number);
+          self.pending.insert(id.sequence_number, done);
+        }
+        None => debug!("Reply to unknown request {}", id.sequence_number),
+      }
+    }
+  }
+}
+
+// How often the reply thread checks if the client still exists
+const REPLY_THREAD_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+fn reply_thread<R: DeserializeOwned>(state: Weak<Mutex<ReplyState<R>>>, poll: mio::Poll) {
+  let mut events = Events::with_capacity(4);
+  loop {
+    if let Err(e) = poll.poll(&mut events, Some(REPLY_THREAD_POLL_INTERVAL)) {
+      error!("Service reply thread poll failed: {:?}", e);
+      return;
+    }
+    match state.upgrade() {
+      Some(state) => match state.lock() {
+        Ok(mut state) => state.receive_replies(),
+        Err(_) => return,
+      },
+      None => return, // client was dropped
+    }
+  }
+}
+
+/// Client end of a ROS 2 service. Created with
+/// [`RosNode::create_client`](struct.RosNode.html#method.create_client).
+///
+/// Calls can be made concurrently from several threads or tasks. Each
+/// client has a background thread that receives the replies.
+pub struct Client<S: Service> {
+  mapping: ServiceMapping,
+  request_writer: RawDataWriter,
+  reply_reader_guid: GUID,
+  state: Arc<Mutex<ReplyState<S::Response>>>,
+  // Held while a request is written, so that the requests are numbered in
+  // the order the request writer gives them sequence numbers.
+  next_sequence_number: Mutex<i64>,
+}
+
+impl<S: Service> Client<S>
+where
+  S::Response: Send,
+{
+  pub(crate) fn new(
+    mapping: ServiceMapping,
+    request_writer: RawDataWriter,
+    reply_reader: RawDataReader,
+  ) -> Result<Client<S>, Error> {
+    let writer_guid = request_writer.get_guid();
+    let client = match mapping {
+      ServiceMapping::Basic | ServiceMapping::FastDds => RmwRequestId {
+        writer_guid,
+        sequence_number: 0,
+      },
+      ServiceMapping::Cyclone => {
+        RmwRequestId::from_cyclone_client_id(cyclone_client_id(writer_guid), 0)
+      }
+    };
+    let reply_reader_guid = reply_reader.get_guid();
+    let poll = mio::Poll::new()?;
+    poll.register(&reply_reader, Token(0), Ready::readable(), PollOpt::edge())?;
+    let state = Arc::new(Mutex::new(ReplyState {
+      mapping,
+      client,
+      reply_reader,
+      pending: HashMap::new(),
+    }));
+    let weak_state = Arc::downgrade(&state);
+    thread::Builder::new()
+      .name("RustDDS service client".to_string())
+      .spawn(move || reply_thread(weak_state, poll))?;
+    Ok(Client {
+      mapping,
+      request_writer,
+      reply_reader_guid,
+      state,
+      next_sequence_number: Mutex::new(1),
+    })
+  }
+
+  /// Sends a request and blocks until the response arrives. If the server
+  /// never responds, this blocks forever, see
+  /// [`call_timeout`](#method.call_timeout).
+  pub fn call(&self, request: S::Request) -> Result<S::Response, Error> {
+    block_on(self.call_async(request))
+  }
+
+  /// Sends a request and blocks until the response arrives, or fails with an
+  /// `Io` error of kind `TimedOut` after `timeout`. A late response is
+  /// discarded.
+  pub fn call_timeout(
+    &self,
+    request: S::Request,
+    timeout: Duration,
+  ) -> Result<S::Response, Error> {
+    let deadline = Instant::now() + timeout;
+    match block_on_until(self.call_async(request), deadline) {
+      Some(result) => result,
+      None => Err(Error::Io {
+        inner: std::io::Error::new(
+          std::io::ErrorKind::TimedOut,
+          "No response to service request",
+        ),
+      }),
+    }
+  }
+
+  /// Sends a request. The returned future completes when the response
+  /// arrives. It does not need any particular async runtime.
+  pub fn call_async(&self, request: S::Request) -> ResponseFuture<S::Response> {
+    match self.send_request(request) {
+      Ok(sequence_number) => ResponseFuture {
+        state: Some(self.state.clone()),
+        sequence_number,
+        error: None,
+      },
+      Err(e) => ResponseFuture {
+        state: None,
+        sequence_number: 0,
+        error: Some(e),
+      },
+    }
+  }
+
+  // With ServiceMapping::FastDds the request id is the sample identity of the
+  // request. The sequence number is not returned by the writer, but the
+  // writer numbers the samples 1, 2, 3, ... and only this client writes.
+  fn send_request(&self, request: S::Request) -> Result<i64, Error> {
+    let mut next_sequence_number = self.next_sequence_number.lock()?;
+    let sequence_number = *next_sequence_number;
+    let id = {
+      let mut state = self.state.lock()?;
+      state
+        .pending
+        .insert(sequence_number, PendingCall::Waiting(None));
+      RmwRequestId {
+        sequence_number,
+        ..state.client
+      }
+    };
+    let sent = encode(self.mapping, id, &request).and_then(|payload| match self.mapping {
+      ServiceMapping::FastDds => {
+        let reply_reader = SampleIdentity {
+          writer_guid: self.reply_reader_guid,
+          sequence_number: SequenceNumber::SEQUENCENUMBER_UNKNOWN,
+        };
+        self
+          .request_writer
+          .write_related(payload, 0, reply_reader, None)
+      }
+      _ => self.request_writer.write(payload, 0, None),
+    });
+    match sent {
+      Ok(()) => {
+        *next_sequence_number += 1;
+        Ok(sequence_number)
+      }
+      Err(e) => {
+        self.state.lock()?.pending.remove(&sequence_number);
+        Err(e)
+      }
+    }
+  }
+
+  pub fn mapping(&self) -> ServiceMapping {
+    self.mapping
+  }
+
+  pub(crate) fn request_writer_guid(&self) -> GUID {
+    self.request_writer.get_guid()
+  }
+
+  pub(crate) fn reply_reader_guid(&self) -> GUID {
+    self.reply_reader_guid
+  }
+}
+
+/// Response of a [`Client::call_async`](struct.Client.html#method.call_async)
+pub struct ResponseFuture<R> {
+  state: Option<Arc<Mutex<ReplyState<R>>>>,
+  sequence_number: i64,
+  error: Option<Error>,
+}
+
+impl<R> Future for ResponseFuture<R> {
+  type Output = Result<R, Error>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    if let Some(e) = self.error.take() {
+      return Poll::Ready(Err(e));
+    }
+    let sequence_number = self.sequence_number;
+    let state = match &self.state {
+      Some(state) => state.clone(),
+      None => {
+        return Poll::Ready(Error::precondition_not_met(
+          "Response was already received.",
+        ))
+      }
+    };
+    let mut state = state.lock()?;
+    match state.pending.remove(&sequence_number) {
+      Some(PendingCall::Done(result)) => {
+        drop(state);
+        self.state = None;
+        Poll::Ready(result)
+      }
+      Some(PendingCall::Waiting(_)) | None => {
+        state.pending.insert(
+          sequence_number,
+          PendingCall::Waiting(Some(cx.waker().clone())),
+        );
+        Poll::Pending
+      }
+    }
+  }
+}
+
+impl<R> Drop for ResponseFuture<R> {
+  // An abandoned call must not leave its entry behind.
+  fn drop(&mut self) {
+    if let Some(state) = &self.state {
+      if let Ok(mut state) = state.lock() {
+        state.pending.remove(&self.sequence_number);
+      }
+    }
+  }
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+  fn wake(self: Arc<Self>) {
+    self.0.unpark()
+  }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+  let mut future = Box::pin(future);
+  let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+  let mut cx = Context::from_waker(&waker);
+  loop {
+    match future.as_mut().poll(&mut cx) {
+      Poll::Ready(output) => return output,
+      Poll::Pending => thread::park(),
+    }
+  }
+}
+
+// Like block_on, but gives up at the deadline. The future is dropped then.
+pub(crate) fn block_on_until<F: Future>(future: F, deadline: Instant) -> Option<F::Output> {
+  let mut future = Box::pin(future);
+  let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+  let mut cx = Context::from_waker(&waker);
+  loop {
+    match future.as_mut().poll(&mut cx) {
+      Poll::Ready(output) => return Some(output),
+      Poll::Pending => {
+        let now = Instant::now();
+        if now >= deadline {
+          return None;
+        }
+        thread::park_timeout(deadline - now);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::{
+    ros2::{qos_profiles, NodeOptions, RosParticipant},
+    structure::guid::{EntityId, EntityKind},
+  };
+
+  fn guid() -> GUID {
+    GUID::new(
+      GuidPrefix::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]),
+      EntityId::createCustomEntityID([0, 0, 1], EntityKind::WRITER_NO_KEY_USER_DEFINED),
+    )
+  }
+
+  #[test]
+  fn topic_names() {
+    assert_eq!(
+      service_topic_names("/add_two_ints"),
+      (
+        "rq/add_two_intsRequest".to_string(),
+        "rr/add_two_intsReply".to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn basic_header() {
+    let id = RmwRequestId {
+      writer_guid: guid(),
+      sequence_number: (1 << 32) + 2,
+    };
+    let payload = encode(ServiceMapping::Basic, id, &7i64).unwrap();
+    assert_eq!(payload.value.len(), 32);
+    assert_eq!(
+      &payload.value[..12],
+      &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+    );
+    assert_eq!(&payload.value[16..24], &[1, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(&payload.value[24..], &[7, 0, 0, 0, 0, 0, 0, 0]);
+    let (decoded_id, value): (RmwRequestId, i64) = decode(
+      ServiceMapping::Basic,
+      &SampleInfo::new_deprecated(),
+      &payload,
+    )
+    .unwrap();
+    assert_eq!(decoded_id, id);
+    assert_eq!(value, 7);
+  }
+
+  #[test]
+  fn cyclone_header() {
+    let client_id = cyclone_client_id(guid());
+    let id = RmwRequestId::from_cyclone_client_id(client_id, 3);
+    let payload = encode(ServiceMapping::Cyclone, id, &1u8).unwrap();
+    assert_eq!(&payload.value[..8], &client_id.to_le_bytes());
+    assert_eq!(&payload.value[8..], &[3, 0, 0, 0, 0, 0, 0, 0, 1]);
+    let (decoded_id, _): (RmwRequestId, u8) = decode(
+      ServiceMapping::Cyclone,
+      &SampleInfo::new_deprecated(),
+      &payload,
+    )
+    .unwrap();
+    assert_eq!(decoded_id, id);
+    assert_eq!(decoded_id.cyclone_client_id(), client_id);
+  }
+
+  #[test]
+  fn fastdds_request_id() {
+    let id = RmwRequestId {
+      writer_guid: guid(),
+      sequence_number: 5,
+    };
+    let payload = encode(ServiceMapping::FastDds, id, &7i64).unwrap();
+    assert_eq!(&payload.value[..], &[7, 0, 0, 0, 0, 0, 0, 0]);
+
+    let request_writer = GUID {
+      entityId: EntityId::createCustomEntityID([0, 0, 2], EntityKind::WRITER_NO_KEY_USER_DEFINED),
+      ..guid()
+    };
+    let request_info = SampleInfo {
+      sample_identity: SampleIdentity {
+        writer_guid: request_writer,
+        sequence_number: SequenceNumber::from(5),
+      },
+      // The GUID of the client's reply reader
+      related_sample_identity: Some(SampleIdentity {
+        writer_guid: guid(),
+        sequence_number: SequenceNumber::SEQUENCENUMBER_UNKNOWN,
+      }),
+      ..SampleInfo::new_deprecated()
+    };
+    let (request_id, value): (RmwRequestId, i64) =
+      decode(ServiceMapping::FastDds, &request_info, &payload).unwrap();
+    assert_eq!(request_id, id);
+    assert_eq!(value, 7);
+
+    // Without a related identity the request writer identifies the client.
+    let request_info = SampleInfo {
+      related_sample_identity: None,
+      ..request_info
+    };
+    let (request_id, _): (RmwRequestId, i64) =
+      decode(ServiceMapping::FastDds, &request_info, &payload).unwrap();
+    assert_eq!(request_id.writer_guid, request_writer);
+    assert_eq!(request_id.sequence_number, 5);
+
+    let reply_info = SampleInfo {
+      sample_identity: SampleIdentity {
+        writer_guid: GUID::GUID_UNKNOWN,
+        sequence_number: SequenceNumber::from(1),
+      },
+      related_sample_identity: Some(SampleIdentity::from(id)),
+      ..SampleInfo::new_deprecated()
+    };
+    let (reply_id, _): (RmwRequestId, i64) =
+      decode(ServiceMapping::FastDds, &reply_info, &payload).unwrap();
+    assert_eq!(reply_id, id);
+  }
+
+  struct AddTwoInts;
+
+  impl Service for AddTwoInts {
+    type Request = (i64, i64);
+    type Response = i64;
+    fn type_name() -> String {
+      "example_interfaces::srv::dds_::AddTwoInts_".to_string()
+    }
+  }
+
+  #[test]
+  fn client_server_round_trip() {
+    let ros_participant = RosParticipant::new().unwrap();
+    let mut node = ros_participant
+      .new_RosNode("service_round_trip", "/", NodeOptions::new(false))
+      .unwrap();
+    for (name, mapping) in &[
+      ("/add_basic", ServiceMapping::Basic),
+      ("/add_cyclone", ServiceMapping::Cyclone),
+      ("/add_fastdds", ServiceMapping::FastDds),
+    ] {
+      let qos = qos_profiles::services_default();
+      let mut server = node
+        .create_service::<AddTwoInts>(name, *mapping, qos.clone())
+        .unwrap();
+      let client = node.create_client::<AddTwoInts>(name, *mapping, qos).unwrap();
+
+      // Two calls, so that the replies must be matched to the right requests
+      let first = client.call_async((1, 2));
+      let second = client.call_async((3, 4));
+      let mut served = 0;
+      for _ in 0..100 {
+        while let Some((id, (a, b))) = server.receive_request().unwrap() {
+          server.send_response(id, a + b).unwrap();
+          served += 1;
+        }
+        if served == 2 {
+          break;
+        }
+        thread::sleep(Duration::from_millis(50));
+      }
+      assert_eq!(
+        served, 2,
+        "{:?} server did not receive the requests",
+        mapping
+      );
+      let deadline = Instant::now() + Duration::from_secs(5);
+      assert_eq!(block_on_until(second, deadline).unwrap().unwrap(), 7);
+      assert_eq!(block_on_until(first, deadline).unwrap().unwrap(), 3);
+    }
+  }
+
+  #[test]
+  fn call_timeout_without_server() {
+    let ros_participant = RosParticipant::new().unwrap();
+    let mut node = ros_participant
+      .new_RosNode("service_timeout", "/", NodeOptions::new(false))
+      .unwrap();
+    let client = node
+      .create_client::<AddTwoInts>(
+        "/nobody_adds",
+        ServiceMapping::default(),
+        qos_profiles::services_default(),
+      )
+      .unwrap();
+    match client.call_timeout((1, 2), Duration::from_millis(200)) {
+      Err(Error::Io { inner }) => assert_eq!(inner.kind(), std::io::ErrorKind::TimedOut),
+      other => panic!("Expected a timeout, got {:?}", other.map(|_| ())),
+    }
+    // The abandoned call left nothing behind.
+    assert!(client.state.lock().unwrap().pending.is_empty());
+  }
+}
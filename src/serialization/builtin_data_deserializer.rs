@@ -1,6 +1,7 @@
 use std::time::Instant;
+use std::marker::PhantomData;
 
-use serde::Deserialize;
+use serde::{Deserialize, de::DeserializeOwned};
 
 use chrono::Utc;
 
@@ -25,14 +26,41 @@ use crate::messages::{
 };
 
 use crate::serialization::error::Error;
+use crate::serialization::pl_cdr_deserializer;
+
+/// [`DeserializerAdapter`] for the builtin discovery types. They are not
+/// [`PlCdrType`]s: their `Deserialize` implementations take the whole
+/// parameter list from `deserialize_any`, and parse it with
+/// `BuiltinDataDeserializer`.
+///
+/// [`DeserializerAdapter`]: ../../dds/traits/serde_adapters/trait.DeserializerAdapter.html
+/// [`PlCdrType`]: ../trait.PlCdrType.html
+pub(crate) struct BuiltinPlCdrDeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+impl<D> DeserializerAdapter<D> for BuiltinPlCdrDeserializerAdapter<D>
+where
+  D: DeserializeOwned,
+{
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &pl_cdr_deserializer::repr_ids
+  }
+
+  fn from_bytes<'de>(input_bytes: &'de [u8], encoding: RepresentationIdentifier) -> Result<D, Error> {
+    pl_cdr_deserializer::from_bytes_with_ids(input_bytes, encoding, |_| None)
+  }
+}
 
 use crate::{
   dds::{
     qos::policy::{
       Deadline, Durability, LatencyBudget, Liveliness, Reliability, Ownership, DestinationOrder,
-      TimeBasedFilter, Presentation, Lifespan, History, ResourceLimits,
+      TimeBasedFilter, Presentation, Lifespan, History, ResourceLimits, DataRepresentation,
+      DataRepresentationId, TypeConsistencyEnforcement, TypeConsistencyKind,
     },
     traits::serde_adapters::DeserializerAdapter,
+    xtypes::TypeInformation,
   },
   discovery::{
     content_filter_property::ContentFilterProperty,
@@ -90,6 +118,12 @@ pub struct BuiltinDataDeserializer {
   pub lifespan: Option<Lifespan>,
   pub history: Option<History>,
   pub resource_limits: Option<ResourceLimits>,
+  pub data_representation: Option<DataRepresentation>,
+  pub type_consistency: Option<TypeConsistencyEnforcement>,
+  // PID_TYPE_INFORMATION and PID_RUSTDDS_TYPE_OBJECTS are decoded together,
+  // once all parameters have been read.
+  type_information_data: Option<(Vec<u8>, RepresentationIdentifier)>,
+  type_objects_data: Option<Vec<u8>>,
 
   pub content_filter_property: Option<ContentFilterProperty>,
 }
@@ -132,6 +166,10 @@ impl BuiltinDataDeserializer {
       lifespan: None,
       history: None,
       resource_limits: None,
+      data_representation: None,
+      type_consistency: None,
+      type_information_data: None,
+      type_objects_data: None,
 
       content_filter_property: None,
     }
@@ -246,6 +284,16 @@ impl BuiltinDataDeserializer {
       None => qos,
     };
 
+    let qos = match self.data_representation {
+      Some(dr) => qos.data_representation(dr),
+      None => qos,
+    };
+
+    let qos = match self.type_consistency {
+      Some(tc) => qos.type_consistency(tc),
+      None => qos,
+    };
+
     let qos = qos.build();
 
     let key = match self.endpoint_guid {
@@ -269,6 +317,7 @@ impl BuiltinDataDeserializer {
       Some(g) => sbtd.set_participant_key(g),
       None => (),
     };
+    sbtd.set_type_information(self.type_information());
 
     Ok(sbtd)
   }
@@ -291,9 +340,24 @@ impl BuiltinDataDeserializer {
       ownership: self.ownership,
       destination_order: self.destination_order,
       presentation: self.presentation,
+      data_representation: self.data_representation,
+      type_information: self.type_information(),
     })
   }
 
+  fn type_information(&self) -> Option<TypeInformation> {
+    let (data, rep) = self.type_information_data.as_ref()?;
+    let big_endian = *rep == RepresentationIdentifier::CDR_BE || *rep == RepresentationIdentifier::PL_CDR_BE;
+    match TypeInformation::from_pid_bytes(data, self.type_objects_data.as_deref(), big_endian) {
+      Ok(ti) => Some(ti),
+      Err(e) => {
+        // Possibly a type we cannot represent. Matching falls back to names.
+        warn!("Cannot decode type information of {:?}: {:?}", self.type_name, e);
+        None
+      }
+    }
+  }
+
   pub fn generate_topic_data(self) -> Result<TopicBuiltinTopicData,Error> {
     Ok(TopicBuiltinTopicData {
       key: self.endpoint_guid,
@@ -792,6 +856,63 @@ impl BuiltinDataDeserializer {
           _ => (),
         }
       }
+      ParameterId::PID_DATA_REPRESENTATION => {
+        let ids: Result<Vec<i16>, Error> =
+          CDRDeserializerAdapter::from_bytes(&buffer[4..4 + parameter_length], rep);
+        match ids {
+          Ok(ids) => {
+            // Unknown representations cannot be used by us anyway, so they are dropped.
+            let ids: Vec<DataRepresentationId> =
+              ids.into_iter().filter_map(DataRepresentationId::from_i16).collect();
+            self.data_representation = Some(DataRepresentation::new(&ids));
+            buffer.drain(..4 + parameter_length);
+            return self;
+          }
+          _ => (),
+        }
+      }
+      ParameterId::PID_TYPE_CONSISTENCY_ENFORCEMENT => {
+        #[derive(Deserialize)]
+        struct TypeConsistencyData {
+          kind: u16,
+          ignore_sequence_bounds: bool,
+          ignore_string_bounds: bool,
+          ignore_member_names: bool,
+          prevent_type_widening: bool,
+          force_type_validation: bool,
+        }
+        let tc: Result<TypeConsistencyData, Error> =
+          CDRDeserializerAdapter::from_bytes(&buffer[4..4 + parameter_length], rep);
+        match tc {
+          Ok(tc) => {
+            self.type_consistency = Some(TypeConsistencyEnforcement {
+              kind: if tc.kind == 0 {
+                TypeConsistencyKind::DisallowTypeCoercion
+              } else {
+                TypeConsistencyKind::AllowTypeCoercion
+              },
+              ignore_sequence_bounds: tc.ignore_sequence_bounds,
+              ignore_string_bounds: tc.ignore_string_bounds,
+              ignore_member_names: tc.ignore_member_names,
+              prevent_type_widening: tc.prevent_type_widening,
+              force_type_validation: tc.force_type_validation,
+            });
+            buffer.drain(..4 + parameter_length);
+            return self;
+          }
+          _ => (),
+        }
+      }
+      ParameterId::PID_TYPE_INFORMATION => {
+        self.type_information_data = Some((buffer[4..4 + parameter_length].to_vec(), rep));
+        buffer.drain(..4 + parameter_length);
+        return self;
+      }
+      ParameterId::PID_RUSTDDS_TYPE_OBJECTS => {
+        self.type_objects_data = Some(buffer[4..4 + parameter_length].to_vec());
+        buffer.drain(..4 + parameter_length);
+        return self;
+      }
       ParameterId::PID_TYPE_MAX_SIZE_SERIALIZED => {
         let max_size: Result<u32, Error> =
           CDRDeserializerAdapter::from_bytes(&buffer[4..4 + parameter_length], rep);
@@ -25,10 +25,13 @@ use crate::{
   },
   dds::qos::policy::{
     Deadline, Durability, LatencyBudget, Liveliness, Reliability, Ownership, DestinationOrder,
-    TimeBasedFilter, Presentation, Lifespan, History, ResourceLimits, QosData,
+    TimeBasedFilter, Presentation, Lifespan, History, ResourceLimits, QosData, DataRepresentation,
+    TypeConsistencyEnforcement, TypeConsistencyKind,
   },
+  dds::xtypes::TypeInformation,
 };
 use serde::{Serialize, Serializer, ser::SerializeStruct, Deserialize};
+use byteorder::LittleEndian;
 use std::time::Duration as StdDuration;
 
 #[derive(Serialize, Deserialize)]
@@ -145,6 +148,9 @@ pub struct BuiltinDataSerializer<'a> {
   pub lifespan: Option<Lifespan>,
   pub history: Option<History>,
   pub resource_limits: Option<ResourceLimits>,
+  pub data_representation: Option<DataRepresentation>,
+  pub type_consistency: Option<TypeConsistencyEnforcement>,
+  pub type_information: Option<&'a TypeInformation>,
 
   pub content_filter_property: Option<&'a ContentFilterProperty>,
 }
@@ -275,6 +281,18 @@ impl<'a> BuiltinDataSerializer<'a> {
       Some(v) => Some(v),
       None => self.resource_limits,
     };
+    self.data_representation = match other.data_representation {
+      Some(v) => Some(v),
+      None => self.data_representation,
+    };
+    self.type_consistency = match other.type_consistency {
+      Some(v) => Some(v),
+      None => self.type_consistency,
+    };
+    self.type_information = match other.type_information {
+      Some(v) => Some(v),
+      None => self.type_information,
+    };
     self.content_filter_property = match other.content_filter_property {
       Some(v) => Some(v),
       None => self.content_filter_property,
@@ -318,6 +336,9 @@ impl<'a> BuiltinDataSerializer<'a> {
       lifespan: None,
       history: None,
       resource_limits: None,
+      data_representation: None,
+      type_consistency: None,
+      type_information: None,
       content_filter_property: None,
     }
   }
@@ -355,6 +376,9 @@ impl<'a> BuiltinDataSerializer<'a> {
       lifespan: None,
       history: None,
       resource_limits: None,
+      data_representation: None,
+      type_consistency: None,
+      type_information: None,
       content_filter_property: None,
     }
   }
@@ -392,6 +416,9 @@ impl<'a> BuiltinDataSerializer<'a> {
       lifespan: None,
       history: None,
       resource_limits: None,
+      data_representation: None,
+      type_consistency: None,
+      type_information: None,
       content_filter_property: None,
     }
   }
@@ -431,6 +458,9 @@ impl<'a> BuiltinDataSerializer<'a> {
       lifespan: subscription_topic_data.lifespan().clone(),
       history: None,
       resource_limits: None,
+      data_representation: subscription_topic_data.data_representation().clone(),
+      type_consistency: subscription_topic_data.type_consistency().clone(),
+      type_information: subscription_topic_data.type_information().as_ref(),
       content_filter_property: None,
     }
   }
@@ -470,6 +500,9 @@ impl<'a> BuiltinDataSerializer<'a> {
       lifespan: publication_topic_data.lifespan,
       history: None,
       resource_limits: None,
+      data_representation: publication_topic_data.data_representation,
+      type_consistency: None,
+      type_information: publication_topic_data.type_information.as_ref(),
       content_filter_property: None,
     }
   }
@@ -507,6 +540,9 @@ impl<'a> BuiltinDataSerializer<'a> {
       lifespan: topic_data.lifespan,
       history: topic_data.history,
       resource_limits: topic_data.resource_limits,
+      data_representation: None,
+      type_consistency: None,
+      type_information: None,
       content_filter_property: None,
     }
   }
@@ -577,6 +613,9 @@ impl<'a> BuiltinDataSerializer<'a> {
     self.add_lifespan::<S>(&mut s);
     self.add_history::<S>(&mut s);
     self.add_resource_limits::<S>(&mut s);
+    self.add_data_representation::<S>(&mut s);
+    self.add_type_consistency::<S>(&mut s);
+    self.add_type_information::<S>(&mut s);
 
     self.add_content_filter_property::<S>(&mut s);
 
@@ -629,6 +668,10 @@ impl<'a> BuiltinDataSerializer<'a> {
     count = count + self.lifespan.is_some() as usize;
     count = count + self.history.is_some() as usize;
     count = count + self.resource_limits.is_some() as usize;
+    count = count + self.data_representation.is_some() as usize;
+    count = count + self.type_consistency.is_some() as usize;
+    // type information and type objects are separate parameters
+    count = count + 2 * self.type_information.is_some() as usize;
 
     count = count + self.content_filter_property.is_some() as usize;
 
@@ -1146,6 +1189,122 @@ impl<'a> BuiltinDataSerializer<'a> {
     }
   }
 
+  fn add_data_representation<S: Serializer>(&self, s: &mut S::SerializeStruct) {
+    // sequence<DataRepresentationId_t>, where the id is a short
+    #[derive(Serialize)]
+    struct DataRepresentationData {
+      parameter_id: ParameterId,
+      parameter_length: u16,
+      ids: Vec<i16>,
+    }
+
+    match self.data_representation {
+      Some(dr) => {
+        let ids: Vec<i16> = dr.ids().iter().map(|id| id.to_i16()).collect();
+        let parameter_length = 4 + 2 * ids.len() as u16;
+        s.serialize_field(
+          "data_representation",
+          &DataRepresentationData {
+            parameter_id: ParameterId::PID_DATA_REPRESENTATION,
+            parameter_length: parameter_length + (4 - parameter_length % 4) % 4,
+            ids,
+          },
+        )
+        .unwrap();
+      }
+      None => (),
+    }
+  }
+
+  fn add_type_consistency<S: Serializer>(&self, s: &mut S::SerializeStruct) {
+    #[derive(Serialize)]
+    struct TypeConsistencyData {
+      parameter_id: ParameterId,
+      parameter_length: u16,
+      kind: u16,
+      ignore_sequence_bounds: bool,
+      ignore_string_bounds: bool,
+      ignore_member_names: bool,
+      prevent_type_widening: bool,
+      force_type_validation: bool,
+      padding: u8,
+    }
+
+    match self.type_consistency {
+      Some(tc) => {
+        s.serialize_field(
+          "type_consistency",
+          &TypeConsistencyData {
+            parameter_id: ParameterId::PID_TYPE_CONSISTENCY_ENFORCEMENT,
+            parameter_length: 8,
+            kind: match tc.kind {
+              TypeConsistencyKind::DisallowTypeCoercion => 0,
+              TypeConsistencyKind::AllowTypeCoercion => 1,
+            },
+            ignore_sequence_bounds: tc.ignore_sequence_bounds,
+            ignore_string_bounds: tc.ignore_string_bounds,
+            ignore_member_names: tc.ignore_member_names,
+            prevent_type_widening: tc.prevent_type_widening,
+            force_type_validation: tc.force_type_validation,
+            padding: 0,
+          },
+        )
+        .unwrap();
+      }
+      None => (),
+    }
+  }
+
+  fn add_type_information<S: Serializer>(&self, s: &mut S::SerializeStruct) {
+    // Parameter whose value is already XCDR2 encoded. Discovery data is written little-endian.
+    struct RawParameterData {
+      parameter_id: ParameterId,
+      data: Vec<u8>,
+    }
+
+    impl Serialize for RawParameterData {
+      fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct RawBytes<'b>(&'b [u8]);
+        impl<'b> Serialize for RawBytes<'b> {
+          fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+          }
+        }
+
+        let padding = [0; 3];
+        let padding = &padding[..(4 - self.data.len() % 4) % 4];
+        let mut s = serializer.serialize_struct("RawParameterData", 4)?;
+        s.serialize_field("parameter_id", &self.parameter_id)?;
+        s.serialize_field("parameter_length", &((self.data.len() + padding.len()) as u16))?;
+        s.serialize_field("data", &RawBytes(&self.data))?;
+        s.serialize_field("padding", &RawBytes(padding))?;
+        s.end()
+      }
+    }
+
+    match self.type_information {
+      Some(ti) => {
+        s.serialize_field(
+          "type_information",
+          &RawParameterData {
+            parameter_id: ParameterId::PID_TYPE_INFORMATION,
+            data: ti.to_pid_bytes::<LittleEndian>(),
+          },
+        )
+        .unwrap();
+        s.serialize_field(
+          "type_objects",
+          &RawParameterData {
+            parameter_id: ParameterId::PID_RUSTDDS_TYPE_OBJECTS,
+            data: ti.type_objects_to_pid_bytes::<LittleEndian>(),
+          },
+        )
+        .unwrap();
+      }
+      None => (),
+    }
+  }
+
   fn add_content_filter_property<S: Serializer>(&self, s: &mut S::SerializeStruct) {
     match self.content_filter_property {
       Some(cfp) => {
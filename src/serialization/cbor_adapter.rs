@@ -0,0 +1,125 @@
+use std::{io, marker::PhantomData};
+
+use serde::{
+  de::{Deserialize, DeserializeOwned},
+  ser::Serialize,
+};
+
+use crate::{
+  dds::traits::serde_adapters::{
+    BorrowingDeserializerAdapter, DeserializerAdapter, SerializerAdapter,
+  },
+  messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier,
+  serialization::error::{Error, Result},
+};
+
+/// [`SerializerAdapter`] for CBOR (RFC 7049), using `serde_cbor`. The samples have the
+/// vendor-specific representation identifier `CBOR`, so only RustDDS readers
+/// with [`CBORDeserializerAdapter`] understand them.
+///
+/// Requires the `cbor` feature.
+///
+/// [`SerializerAdapter`]: ../dds/traits/serde_adapters/trait.SerializerAdapter.html
+/// [`CBORDeserializerAdapter`]: struct.CBORDeserializerAdapter.html
+pub struct CBORSerializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+impl<D> SerializerAdapter<D> for CBORSerializerAdapter<D>
+where
+  D: Serialize,
+{
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::CBOR
+  }
+
+  fn to_writer<W: io::Write>(writer: W, value: &D) -> Result<()> {
+    serde_cbor::to_writer(writer, value).map_err(cbor_error)
+  }
+}
+
+/// [`DeserializerAdapter`] for CBOR (RFC 7049), using `serde_cbor`.
+///
+/// Requires the `cbor` feature.
+///
+/// [`DeserializerAdapter`]: ../dds/traits/serde_adapters/trait.DeserializerAdapter.html
+pub struct CBORDeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+const repr_ids: [RepresentationIdentifier; 1] = [RepresentationIdentifier::CBOR];
+
+impl<D> DeserializerAdapter<D> for CBORDeserializerAdapter<D>
+where
+  D: DeserializeOwned,
+{
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &repr_ids
+  }
+
+  fn from_bytes<'de>(input_bytes: &'de [u8], encoding: RepresentationIdentifier) -> Result<D> {
+    Self::from_bytes_borrowed(input_bytes, encoding)
+  }
+}
+
+impl<D> BorrowingDeserializerAdapter for CBORDeserializerAdapter<D> {
+  fn from_bytes_borrowed<'de, T>(
+    input_bytes: &'de [u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<T>
+  where
+    T: Deserialize<'de>,
+  {
+    match encoding {
+      RepresentationIdentifier::CBOR => serde_cbor::from_slice(input_bytes).map_err(cbor_error),
+      repr_id => Err(Error::Message(format!(
+        "Unknown representation identifier {:?}.",
+        repr_id
+      ))),
+    }
+  }
+}
+
+fn cbor_error(e: serde_cbor::Error) -> Error {
+  Error::Message(format!("CBOR: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde::{Deserialize, Serialize};
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Shape {
+    color: String,
+    x: i32,
+    y: i32,
+    size: Option<u16>,
+  }
+
+  #[test]
+  fn cbor_roundtrip() {
+    let shape = Shape {
+      color: "BLUE".to_string(),
+      x: 10,
+      y: -20,
+      size: None,
+    };
+    let mut bytes = Vec::new();
+    CBORSerializerAdapter::to_writer(&mut bytes, &shape).unwrap();
+    // map(4), text(5) "color", text(4) "BLUE", ...
+    assert_eq!(&bytes[..12], b"\xa4\x65color\x64BLUE");
+    assert_eq!(
+      CBORSerializerAdapter::<Shape>::output_encoding(),
+      RepresentationIdentifier::CBOR
+    );
+
+    let result: Shape =
+      CBORDeserializerAdapter::from_bytes(&bytes, RepresentationIdentifier::CBOR).unwrap();
+    assert_eq!(result, shape);
+    assert!(
+      CBORDeserializerAdapter::<Shape>::from_bytes(&bytes, RepresentationIdentifier::JSON).is_err()
+    );
+  }
+}
@@ -0,0 +1,896 @@
+use byteorder::{ByteOrder, LittleEndian, BigEndian, ReadBytesExt};
+use std::marker::PhantomData;
+use serde::de::{
+  self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+  Visitor, Deserialize, DeserializeOwned,
+};
+
+use paste::paste;
+
+use crate::serialization::error::Error;
+use crate::serialization::error::Result;
+use crate::serialization::idl_types::{self, IdlNewtype};
+use crate::serialization::cdr2_serializer::{
+  Extensibility, EMHEADER_LC_SHIFT, EMHEADER_MEMBER_ID_MASK, XCDR2_MAX_ALIGNMENT,
+};
+use crate::dds::traits::serde_adapters::{DeserializerAdapter, BorrowingDeserializerAdapter};
+
+use crate::messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier;
+
+/// [`DeserializerAdapter`] for all XCDR2 encodings: plain (`CDR2_*`), delimited (`D_CDR_*`)
+/// and parameterized (`PL_CDR2_*`). The representation identifier of each sample selects
+/// the extensibility, which is then applied to all structs in the sample.
+///
+/// [`DeserializerAdapter`]: ../dds/traits/serde_adapters/trait.DeserializerAdapter.html
+pub struct CDR2DeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+const repr_ids: [RepresentationIdentifier; 6] = [
+  RepresentationIdentifier::CDR2_BE,
+  RepresentationIdentifier::CDR2_LE,
+  RepresentationIdentifier::D_CDR_BE,
+  RepresentationIdentifier::D_CDR_LE,
+  RepresentationIdentifier::PL_CDR2_BE,
+  RepresentationIdentifier::PL_CDR2_LE,
+];
+
+impl<D> DeserializerAdapter<D> for CDR2DeserializerAdapter<D>
+where
+  D: DeserializeOwned,
+{
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &repr_ids
+  }
+
+  fn from_bytes<'de>(input_bytes: &'de [u8], encoding: RepresentationIdentifier) -> Result<D> {
+    use Extensibility::*;
+    match encoding {
+      RepresentationIdentifier::CDR2_LE => from_bytes::<D, LittleEndian>(input_bytes, Final),
+      RepresentationIdentifier::CDR2_BE => from_bytes::<D, BigEndian>(input_bytes, Final),
+      RepresentationIdentifier::D_CDR_LE => from_bytes::<D, LittleEndian>(input_bytes, Appendable),
+      RepresentationIdentifier::D_CDR_BE => from_bytes::<D, BigEndian>(input_bytes, Appendable),
+      RepresentationIdentifier::PL_CDR2_LE => from_bytes::<D, LittleEndian>(input_bytes, Mutable),
+      RepresentationIdentifier::PL_CDR2_BE => from_bytes::<D, BigEndian>(input_bytes, Mutable),
+      repr_id => Err(Error::Message(format!(
+        "Unknown representation identifier {:?}.", repr_id ))),
+    }
+  }
+}
+
+impl<D> BorrowingDeserializerAdapter for CDR2DeserializerAdapter<D> {
+  fn from_bytes_borrowed<'de, T>(
+    input_bytes: &'de [u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<T>
+  where
+    T: Deserialize<'de>,
+  {
+    use Extensibility::*;
+    match encoding {
+      RepresentationIdentifier::CDR2_LE => from_bytes::<T, LittleEndian>(input_bytes, Final),
+      RepresentationIdentifier::CDR2_BE => from_bytes::<T, BigEndian>(input_bytes, Final),
+      RepresentationIdentifier::D_CDR_LE => from_bytes::<T, LittleEndian>(input_bytes, Appendable),
+      RepresentationIdentifier::D_CDR_BE => from_bytes::<T, BigEndian>(input_bytes, Appendable),
+      RepresentationIdentifier::PL_CDR2_LE => from_bytes::<T, LittleEndian>(input_bytes, Mutable),
+      RepresentationIdentifier::PL_CDR2_BE => from_bytes::<T, BigEndian>(input_bytes, Mutable),
+      repr_id => Err(Error::Message(format!(
+        "Unknown representation identifier {:?}.", repr_id ))),
+    }
+  }
+}
+
+pub fn from_bytes<'a, T, BO>(s: &'a [u8], extensibility: Extensibility) -> Result<T>
+where
+  T: Deserialize<'a>,
+  BO: ByteOrder,
+{
+  let mut deserializer = CDR2_deserializer::<BO>::new(s, extensibility);
+  T::deserialize(&mut deserializer)
+}
+
+// Layout of a sequence or an array, which is known when its first element begins
+#[derive(Clone, Copy)]
+enum CollectionLayout {
+  // The first word of a sequence is read, but it is not known if it is a
+  // DHEADER or the element count.
+  Undecided { first_word: u32, is_sequence: bool },
+  Decided { count: Option<usize>, end: Option<usize> },
+  // A DHEADER followed by a zero element count
+  Empty,
+}
+
+/// XCDR2 deserializer. See [CDR2_serializer](../cdr2_serializer/struct.CDR2_serializer.html)
+/// for the supported subset of XCDR2.
+pub struct CDR2_deserializer<'de, BO> {
+  phantom: PhantomData<BO>,
+  input: &'de [u8],
+  position: usize, // offset from start of serialized data, for alignment
+  extensibility: Extensibility,
+  // The next value is a member of a mutable struct. See CDR2_serializer.
+  member_option: bool,
+  // The next value is the first element of a collection.
+  first_element: Option<CollectionLayout>,
+}
+
+impl<'de, BO> CDR2_deserializer<'de, BO>
+where
+  BO: ByteOrder,
+{
+  pub fn new(input: &'de [u8], extensibility: Extensibility) -> CDR2_deserializer<'de, BO> {
+    CDR2_deserializer::<BO>::new_at(input, 0, extensibility)
+  }
+
+  fn new_at(input: &'de [u8], position: usize, extensibility: Extensibility)
+    -> CDR2_deserializer<'de, BO>
+  {
+    CDR2_deserializer::<BO> {
+      phantom: PhantomData,
+      input,
+      position,
+      extensibility,
+      member_option: false,
+      first_element: None,
+    }
+  }
+
+  fn next_bytes(&mut self, count: usize) -> Result<&'de [u8]> {
+    if count <= self.input.len() {
+      let (head, tail) = self.input.split_at(count);
+      self.input = tail;
+      self.position += count;
+      Ok(head)
+    } else {
+      Err(Error::Eof)
+    }
+  }
+
+  fn align(&mut self, type_octet_alignment: usize) -> Result<()> {
+    let alignment = type_octet_alignment.min(XCDR2_MAX_ALIGNMENT);
+    let modulo = self.position % alignment;
+    if modulo != 0 {
+      self.next_bytes(alignment - modulo)?;
+    }
+    Ok(())
+  }
+
+  fn read_u32(&mut self) -> Result<u32> {
+    self.align(4)?;
+    Ok(BO::read_u32(self.next_bytes(4)?))
+  }
+
+  fn take_member_option(&mut self) -> bool {
+    std::mem::replace(&mut self.member_option, false)
+  }
+
+  // Called when a value begins. If it is the first element of a collection, this
+  // decides if the collection has a DHEADER, as in CDR2_serializer.
+  fn begin_value(&mut self, primitive: bool) -> Result<()> {
+    let (first_word, is_sequence) = match self.first_element {
+      Some(CollectionLayout::Undecided { first_word, is_sequence }) => (first_word, is_sequence),
+      _ => return Ok(()),
+    };
+    let layout = match (primitive, is_sequence) {
+      (true, true) => CollectionLayout::Decided { count: Some(first_word as usize), end: None },
+      (true, false) => CollectionLayout::Decided { count: None, end: None },
+      (false, true) => {
+        // The first word was a DHEADER, and the element count follows it.
+        let end = self.position + first_word as usize;
+        match self.read_u32()? {
+          0 => CollectionLayout::Empty,
+          count => CollectionLayout::Decided { count: Some(count as usize), end: Some(end) },
+        }
+      }
+      (false, false) => {
+        let length = self.read_u32()? as usize;
+        CollectionLayout::Decided { count: None, end: Some(self.position + length) }
+      }
+    };
+    self.first_element = Some(layout);
+    match layout {
+      // There is no element to deserialize. SequenceHelper sees this.
+      CollectionLayout::Empty => Err(Error::Message("Empty collection".to_string())),
+      _ => Ok(()),
+    }
+  }
+
+  // Reads a DHEADER and splits off the delimited contents into a new deserializer.
+  fn delimited(&mut self) -> Result<CDR2_deserializer<'de, BO>> {
+    let length = self.read_u32()? as usize;
+    let start = self.position;
+    let contents = self.next_bytes(length)?;
+    Ok(CDR2_deserializer::<BO>::new_at(contents, start, self.extensibility))
+  }
+}
+
+macro_rules! deserialize_multibyte_number {
+  ($num_type:ident) => {
+    paste! {
+      fn [<deserialize_ $num_type>]<V>(self, visitor: V) -> Result<V::Value>
+      where
+        V: Visitor<'de>,
+      {
+        const size :usize = std::mem::size_of::<$num_type>();
+        self.begin_value(true)?;
+        self.align(size)?;
+        visitor.[<visit_ $num_type>](
+          self.next_bytes(size)?.[<read_ $num_type>]::<BO>().unwrap() )
+      }
+    }
+  };
+}
+
+impl<'de, 'a, BO> de::Deserializer<'de> for &'a mut CDR2_deserializer<'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
+
+  /// XCDR2 is not a self-describing data format.
+  fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    Err(Error::Message("XCDR2 cannot deserialize_any.".to_string()))
+  }
+
+  fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.begin_value(true)?;
+    match self.next_bytes(1)?[0] {
+      0 => visitor.visit_bool(false),
+      1 => visitor.visit_bool(true),
+      x => Err(Error::BadBoolean(x)),
+    }
+  }
+
+  deserialize_multibyte_number!(i16);
+  deserialize_multibyte_number!(i32);
+  deserialize_multibyte_number!(i64);
+
+  deserialize_multibyte_number!(u16);
+  deserialize_multibyte_number!(u32);
+  deserialize_multibyte_number!(u64);
+
+  deserialize_multibyte_number!(f32);
+  deserialize_multibyte_number!(f64);
+
+  fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.begin_value(true)?;
+    visitor.visit_i8(self.next_bytes(1)?[0] as i8)
+  }
+
+  fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.begin_value(true)?;
+    visitor.visit_u8(self.next_bytes(1)?[0])
+  }
+
+  fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.begin_value(true)?;
+    let codepoint = self.read_u32()?;
+    match std::char::from_u32(codepoint) {
+      Some(c) => visitor.visit_char(c),
+      None => Err(Error::BadChar(codepoint)),
+    }
+  }
+
+  fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.begin_value(false)?;
+    let bytes_len = self.read_u32()? as usize;
+    let bytes = self.next_bytes(bytes_len)?; // length includes null terminator
+    let bytes_without_null = match bytes.split_last() {
+      Some((0, rest)) => rest,
+      _ => bytes,
+    };
+    match std::str::from_utf8(bytes_without_null) {
+      Ok(s) => visitor.visit_borrowed_str(s),
+      Err(utf8_err) => Err(Error::BadString(utf8_err)),
+    }
+  }
+
+  fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_str(visitor)
+  }
+
+  // sequence<octet> can be borrowed, e.g. to &'de [u8]
+  fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.take_member_option();
+    self.begin_value(false)?;
+    let bytes_len = self.read_u32()? as usize;
+    visitor.visit_borrowed_bytes(self.next_bytes(bytes_len)?)
+  }
+
+  fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_seq(visitor)
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    if self.take_member_option() {
+      // Members that are present are not flagged. Absent ones never get here.
+      return visitor.visit_some(self)
+    }
+    self.begin_value(false)?;
+    match self.next_bytes(1)?[0] {
+      0 => visitor.visit_none(),
+      1 => visitor.visit_some(self),
+      wtf => Err(Error::BadOption(wtf as u32)),
+    }
+  }
+
+  fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.begin_value(true)?;
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_unit(visitor)
+  }
+
+  fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    // See CDR2_serializer about these.
+    match IdlNewtype::from_name(name) {
+      Some(IdlNewtype::WChar) => {
+        self.begin_value(true)?;
+        self.align(2)?;
+        let c: char = idl_types::decode_wchar(BO::read_u16(self.next_bytes(2)?))?;
+        visitor.visit_newtype_struct(c.into_deserializer())
+      }
+      Some(IdlNewtype::WString) => {
+        self.begin_value(false)?;
+        let bytes_len = self.read_u32()? as usize;
+        let s: String = idl_types::decode_wstring::<BO>(self.next_bytes(bytes_len)?)?;
+        visitor.visit_newtype_struct(s.into_deserializer())
+      }
+      Some(IdlNewtype::LongDouble) => {
+        self.begin_value(true)?;
+        self.align(16)?;
+        let bits: u128 = BO::read_u128(self.next_bytes(16)?);
+        visitor.visit_newtype_struct(bits.into_deserializer())
+      }
+      None => visitor.visit_newtype_struct(self),
+    }
+  }
+
+  fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.take_member_option();
+    self.begin_value(false)?;
+    // Either a DHEADER or the element count. Neither can be zero for a
+    // non-empty sequence.
+    match self.read_u32()? {
+      0 => visitor.visit_seq(SequenceHelper::new(self, 0)),
+      first_word => {
+        let layout = CollectionLayout::Undecided { first_word, is_sequence: true };
+        visitor.visit_seq(SequenceHelper::collection(self, layout, first_word as usize))
+      }
+    }
+  }
+
+  // Arrays are tuples in serde.
+  fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.take_member_option();
+    self.begin_value(false)?;
+    if len == 0 {
+      return visitor.visit_seq(SequenceHelper::new(self, 0))
+    }
+    let layout = CollectionLayout::Undecided { first_word: 0, is_sequence: false };
+    visitor.visit_seq(SequenceHelper::collection(self, layout, len))
+  }
+
+  fn deserialize_tuple_struct<V>(
+    self,
+    _name: &'static str,
+    len: usize,
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.take_member_option();
+    self.begin_value(false)?;
+    visitor.visit_seq(SequenceHelper::new(self, len))
+  }
+
+  fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.take_member_option();
+    self.begin_value(false)?;
+    let element_count = self.read_u32()? as usize;
+    visitor.visit_map(SequenceHelper::new(self, element_count))
+  }
+
+  fn deserialize_struct<V>(
+    self,
+    _name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.take_member_option();
+    self.begin_value(false)?;
+    match self.extensibility {
+      Extensibility::Final => visitor.visit_seq(SequenceHelper::new(self, fields.len())),
+      // Appendable structs may have more members (ignored) or fewer members (error,
+      // unless missing fields have defaults) than we expect.
+      Extensibility::Appendable => {
+        let mut contents = self.delimited()?;
+        visitor.visit_seq(SequenceHelper::new(&mut contents, fields.len()))
+      }
+      Extensibility::Mutable => {
+        let mut contents = self.delimited()?;
+        visitor.visit_map(MemberHelper::new(&mut contents))
+      }
+    }
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.take_member_option();
+    self.begin_value(true)?;
+    self.align(4)?;
+    visitor.visit_enum(EnumerationHelper::<BO>::new(self))
+  }
+
+  // Struct fields are identified by index or member id.
+  fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    self.deserialize_u32(visitor)
+  }
+
+  // Only used for unknown members of mutable structs. MemberHelper has
+  // already split them off, so there is nothing to skip.
+  fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+}
+
+// ----------------------------------------------------------
+
+struct EnumerationHelper<'a, 'de: 'a, BO> {
+  de: &'a mut CDR2_deserializer<'de, BO>,
+}
+
+impl<'a, 'de, BO> EnumerationHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  fn new(de: &'a mut CDR2_deserializer<'de, BO>) -> Self {
+    EnumerationHelper::<BO> { de }
+  }
+}
+
+impl<'de, 'a, BO> EnumAccess<'de> for EnumerationHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
+  type Variant = Self;
+
+  fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    let enum_tag = self.de.read_u32()?;
+    let val: Result<_> = seed.deserialize(enum_tag.into_deserializer());
+    Ok((val?, self))
+  }
+}
+
+impl<'de, 'a, BO> VariantAccess<'de> for EnumerationHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
+
+  fn unit_variant(self) -> Result<()> {
+    Ok(())
+  }
+
+  fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    seed.deserialize(self.de)
+  }
+
+  fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(SequenceHelper::new(self.de, len))
+  }
+
+  fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+  where
+    V: Visitor<'de>,
+  {
+    visitor.visit_seq(SequenceHelper::new(self.de, fields.len()))
+  }
+}
+
+// ----------------------------------------------------------
+
+struct SequenceHelper<'a, 'de: 'a, BO> {
+  de: &'a mut CDR2_deserializer<'de, BO>,
+  element_counter: usize,
+  expected_count: usize,
+  layout: Option<CollectionLayout>, // of a sequence or array, until its first element
+  end: Option<usize>,               // of the contents after a DHEADER
+}
+
+impl<'a, 'de, BO> SequenceHelper<'a, 'de, BO> {
+  fn new(de: &'a mut CDR2_deserializer<'de, BO>, expected_count: usize) -> Self {
+    SequenceHelper { de, element_counter: 0, expected_count, layout: None, end: None }
+  }
+
+  fn collection(
+    de: &'a mut CDR2_deserializer<'de, BO>,
+    layout: CollectionLayout,
+    expected_count: usize,
+  ) -> Self {
+    SequenceHelper { de, element_counter: 0, expected_count, layout: Some(layout), end: None }
+  }
+}
+
+impl<'a, 'de, BO> SequenceHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  // The first element decides the layout of the collection.
+  fn first_element_seed<T>(&mut self, seed: T, layout: CollectionLayout)
+    -> Result<Option<T::Value>>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    // The first element of an enclosing collection may still be undecided.
+    let outer = self.de.first_element.replace(layout);
+    let result = seed.deserialize(&mut *self.de);
+    match std::mem::replace(&mut self.de.first_element, outer) {
+      Some(CollectionLayout::Empty) => return Ok(None),
+      Some(CollectionLayout::Decided { count, end }) => {
+        self.expected_count = count.unwrap_or(self.expected_count);
+        self.end = end;
+      }
+      _ => (),
+    }
+    let value = result?;
+    self.element_counter += 1;
+    Ok(Some(value))
+  }
+
+  // Skips what the sender had after the elements, if the collection was delimited.
+  fn skip_to_end(&mut self) -> Result<()> {
+    match self.end {
+      Some(end) if end < self.de.position => {
+        Err(Error::Message("Collection overruns its DHEADER.".to_string()))
+      }
+      Some(end) => self.de.next_bytes(end - self.de.position).map(|_| ()),
+      None => Ok(()),
+    }
+  }
+}
+
+impl<'a, 'de, BO> SeqAccess<'de> for SequenceHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
+
+  fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    let value = if let Some(layout) = self.layout.take() {
+      self.first_element_seed(seed, layout)?
+    } else if self.element_counter == self.expected_count {
+      None
+    } else {
+      self.element_counter += 1;
+      Some(seed.deserialize(&mut *self.de)?)
+    };
+    if value.is_some() && self.element_counter == self.expected_count {
+      self.skip_to_end()?;
+    }
+    Ok(value)
+  }
+}
+
+impl<'de, 'a, BO> MapAccess<'de> for SequenceHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    if self.element_counter == self.expected_count {
+      Ok(None)
+    } else {
+      self.element_counter += 1;
+      seed.deserialize(&mut *self.de).map(Some)
+    }
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    seed.deserialize(&mut *self.de)
+  }
+}
+
+// ----------------------------------------------------------
+
+// Walks through the EMHEADER-prefixed members of a mutable struct.
+// Keys are member ids, which serde maps to field indices.
+struct MemberHelper<'a, 'de: 'a, BO> {
+  de: &'a mut CDR2_deserializer<'de, BO>,
+  member: Option<CDR2_deserializer<'de, BO>>,
+}
+
+impl<'a, 'de, BO> MemberHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  fn new(de: &'a mut CDR2_deserializer<'de, BO>) -> Self {
+    MemberHelper { de, member: None }
+  }
+
+  // XTypes 7.4.3.4.7: The length code tells how to find the member length.
+  fn member_length(&mut self, length_code: u32) -> Result<usize> {
+    let peek_nextint = |de: &CDR2_deserializer<'de, BO>| {
+      if de.input.len() < 4 {
+        Err(Error::Eof)
+      } else {
+        Ok(BO::read_u32(&de.input[..4]) as usize)
+      }
+    };
+    match length_code {
+      0..=3 => Ok(1 << length_code),
+      4 => Ok(self.de.read_u32()? as usize),
+      // LC 5..7: NEXTINT is the first word of the member itself
+      5 => Ok(4 + peek_nextint(self.de)?),
+      6 => Ok(4 + 4 * peek_nextint(self.de)?),
+      _ => Ok(4 + 8 * peek_nextint(self.de)?),
+    }
+  }
+}
+
+impl<'de, 'a, BO> MapAccess<'de> for MemberHelper<'a, 'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    if self.de.input.is_empty() {
+      return Ok(None)
+    }
+    let emheader = self.de.read_u32()?;
+    let member_id = emheader & EMHEADER_MEMBER_ID_MASK;
+    let length_code = (emheader >> EMHEADER_LC_SHIFT) & 0x7;
+    let length = self.member_length(length_code)?;
+    let start = self.de.position;
+    let contents = self.de.next_bytes(length)?;
+    let mut member = CDR2_deserializer::<BO>::new_at(contents, start, self.de.extensibility);
+    member.member_option = true;
+    self.member = Some(member);
+    seed.deserialize(member_id.into_deserializer()).map(Some)
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    match self.member.as_mut() {
+      Some(member) => seed.deserialize(member),
+      None => Err(Error::Message("Member value without EMHEADER.".to_string())),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::serialization::cdr2_serializer::to_bytes;
+  use serde::{Serialize, Deserialize};
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+  enum Color {
+    Red,
+    Green,
+  }
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+  struct Inner {
+    a: u8,
+    b: f64,
+    c: Vec<i16>,
+  }
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+  struct Outer {
+    x: u16,
+    inner: Inner,
+    name: String,
+    opt: Option<u32>,
+    color: Color,
+    list: Vec<Inner>,
+  }
+
+  fn sample(opt: Option<u32>) -> Outer {
+    let inner = Inner { a: 1, b: 2.5, c: vec![-1, 2, 3] };
+    Outer {
+      x: 7,
+      inner: inner.clone(),
+      name: "hello".to_string(),
+      opt,
+      color: Color::Green,
+      list: vec![inner.clone(), Inner { c: vec![], ..inner }],
+    }
+  }
+
+  #[test]
+  fn cdr2_roundtrip_all_encodings() {
+    use Extensibility::*;
+    for extensibility in &[Final, Appendable, Mutable] {
+      for opt in &[None, Some(42)] {
+        let value = sample(*opt);
+        let le = to_bytes::<_, LittleEndian>(&value, *extensibility).unwrap();
+        let be = to_bytes::<_, BigEndian>(&value, *extensibility).unwrap();
+        assert_eq!(from_bytes::<Outer, LittleEndian>(&le, *extensibility).unwrap(), value);
+        assert_eq!(from_bytes::<Outer, BigEndian>(&be, *extensibility).unwrap(), value);
+      }
+    }
+  }
+
+  #[test]
+  fn cdr2_adapter_encodings() {
+    use crate::serialization::cdr2_serializer::ParameterizedCDR2SerializerAdapter;
+    use crate::dds::traits::serde_adapters::SerializerAdapter;
+
+    let value = sample(Some(1));
+    let mut bytes = Vec::new();
+    ParameterizedCDR2SerializerAdapter::<Outer, BigEndian>::to_writer(&mut bytes, &value)
+      .unwrap();
+    let encoding = ParameterizedCDR2SerializerAdapter::<Outer, BigEndian>::output_encoding();
+    assert_eq!(encoding, RepresentationIdentifier::PL_CDR2_BE);
+    let result: Outer = CDR2DeserializerAdapter::from_bytes(&bytes, encoding).unwrap();
+    assert_eq!(result, value);
+    assert!(CDR2DeserializerAdapter::<Outer>::from_bytes(&bytes, RepresentationIdentifier::CDR_BE)
+      .is_err());
+  }
+
+  #[test]
+  fn cdr2_mutable_type_evolution() {
+    // Sender has a member that we do not know, and lacks an optional one we know.
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct V1 {
+      id: u32,
+      extra: String,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct V2 {
+      id: u32,
+      renamed_extra: Option<i64>, // member id 1, but different type
+      added: Option<u8>,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct V3 {
+      id: u32,
+    }
+
+    let bytes =
+      to_bytes::<_, LittleEndian>(&V3 { id: 5 }, Extensibility::Mutable).unwrap();
+    let v2: V2 = from_bytes::<_, LittleEndian>(&bytes, Extensibility::Mutable).unwrap();
+    assert_eq!(v2, V2 { id: 5, renamed_extra: None, added: None });
+
+    let bytes = to_bytes::<_, LittleEndian>(&V1 { id: 6, extra: "x".to_string() },
+      Extensibility::Mutable).unwrap();
+    let v3: V3 = from_bytes::<_, LittleEndian>(&bytes, Extensibility::Mutable).unwrap();
+    assert_eq!(v3, V3 { id: 6 });
+  }
+
+  #[test]
+  fn cdr2_appendable_extra_members() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Long {
+      a: u32,
+      b: u32,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Short {
+      a: u32,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Pair<T> {
+      first: T,
+      second: u16,
+    }
+    let bytes = to_bytes::<_, LittleEndian>(
+      &Pair { first: Long { a: 1, b: 2 }, second: 3 }, Extensibility::Appendable).unwrap();
+    let short: Pair<Short> =
+      from_bytes::<_, LittleEndian>(&bytes, Extensibility::Appendable).unwrap();
+    assert_eq!(short, Pair { first: Short { a: 1 }, second: 3 });
+  }
+
+  #[test]
+  fn cdr2_length_codes() {
+    // Hand-written member with LC=5: NEXTINT is the string length, part of the member.
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct S {
+      s: String,
+      v: u8,
+    }
+    let bytes: Vec<u8> = vec![
+      0x11, 0x00, 0x00, 0x00, // DHEADER
+      0x00, 0x00, 0x00, 0x50, // EMHEADER: LC=5, id=0
+      0x03, 0x00, 0x00, 0x00, b'a', b'b', 0x00, 0x00, // string + padding
+      0x01, 0x00, 0x00, 0x00, // EMHEADER: LC=0, id=1
+      0x09,
+    ];
+    let s: S = from_bytes::<_, LittleEndian>(&bytes, Extensibility::Mutable).unwrap();
+    assert_eq!(s, S { s: "ab".to_string(), v: 9 });
+  }
+}
@@ -0,0 +1,815 @@
+use serde::{ser, Serialize};
+use std::marker::PhantomData;
+use std::io;
+
+use byteorder::{BigEndian, LittleEndian, ByteOrder, WriteBytesExt};
+
+use crate::serialization::error::Error;
+use crate::serialization::error::Result;
+use crate::serialization::idl_types::{self, IdlNewtype};
+
+use crate::messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier;
+use crate::dds::traits::serde_adapters::SerializerAdapter;
+
+/// Extensibility kind of structured types, as in XTypes 7.2.2.4.4.
+/// It determines which of the XCDR2 encodings is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Extensibility {
+  /// Members are encoded back-to-back, as in plain CDR2.
+  Final,
+  /// Each struct is preceded by a DHEADER, which is its length in bytes.
+  Appendable,
+  /// Each struct has a DHEADER and each member is preceded by an EMHEADER
+  /// carrying the member id and length.
+  Mutable,
+}
+
+// EMHEADER1 layout (XTypes 7.4.3.4.7): M_FLAG | LC (3 bits) | member id (28 bits)
+pub(crate) const EMHEADER_MEMBER_ID_MASK: u32 = 0x0FFF_FFFF;
+pub(crate) const EMHEADER_LC_SHIFT: u32 = 28;
+pub(crate) const LC_NEXTINT: u32 = 4;
+
+// XCDR2 aligns primitives to their size, but at most to 4 bytes.
+pub(crate) const XCDR2_MAX_ALIGNMENT: usize = 4;
+
+// ---------------------------------------------------------------------------------
+// ---------------------------------------------------------------------------------
+
+/// [`SerializerAdapter`] for plain XCDR2 encoding (`CDR2_LE` / `CDR2_BE`).
+/// All structs are treated as FINAL.
+///
+/// [`SerializerAdapter`]: ../dds/traits/serde_adapters/trait.SerializerAdapter.html
+pub struct CDR2SerializerAdapter<D, BO = LittleEndian>
+where
+  BO: ByteOrder,
+{
+  phantom: PhantomData<D>,
+  ghost: PhantomData<BO>,
+}
+
+/// [`SerializerAdapter`] for delimited XCDR2 encoding (`D_CDR_LE` / `D_CDR_BE`).
+/// All structs are treated as APPENDABLE, i.e. they are preceded by a DHEADER.
+///
+/// [`SerializerAdapter`]: ../dds/traits/serde_adapters/trait.SerializerAdapter.html
+pub struct DelimitedCDR2SerializerAdapter<D, BO = LittleEndian>
+where
+  BO: ByteOrder,
+{
+  phantom: PhantomData<D>,
+  ghost: PhantomData<BO>,
+}
+
+/// [`SerializerAdapter`] for parameterized XCDR2 encoding (`PL_CDR2_LE` / `PL_CDR2_BE`).
+/// All structs are treated as MUTABLE. Member ids are assigned in declaration order,
+/// starting from zero. Struct members of type `Option` are left out when they are `None`.
+///
+/// [`SerializerAdapter`]: ../dds/traits/serde_adapters/trait.SerializerAdapter.html
+pub struct ParameterizedCDR2SerializerAdapter<D, BO = LittleEndian>
+where
+  BO: ByteOrder,
+{
+  phantom: PhantomData<D>,
+  ghost: PhantomData<BO>,
+}
+
+macro_rules! cdr2_serializer_adapter {
+  ($adapter:ident, $bo:ty, $repr_id:ident, $extensibility:expr) => {
+    impl<D> SerializerAdapter<D> for $adapter<D, $bo>
+    where
+      D: Serialize,
+    {
+      fn output_encoding() -> RepresentationIdentifier {
+        RepresentationIdentifier::$repr_id
+      }
+
+      fn to_writer<W: io::Write>(writer: W, value: &D) -> Result<()> {
+        to_writer::<D, $bo, W>(writer, value, $extensibility)
+      }
+    }
+  };
+}
+
+cdr2_serializer_adapter!(CDR2SerializerAdapter, LittleEndian, CDR2_LE, Extensibility::Final);
+cdr2_serializer_adapter!(CDR2SerializerAdapter, BigEndian, CDR2_BE, Extensibility::Final);
+cdr2_serializer_adapter!(
+  DelimitedCDR2SerializerAdapter, LittleEndian, D_CDR_LE, Extensibility::Appendable);
+cdr2_serializer_adapter!(
+  DelimitedCDR2SerializerAdapter, BigEndian, D_CDR_BE, Extensibility::Appendable);
+cdr2_serializer_adapter!(
+  ParameterizedCDR2SerializerAdapter, LittleEndian, PL_CDR2_LE, Extensibility::Mutable);
+cdr2_serializer_adapter!(
+  ParameterizedCDR2SerializerAdapter, BigEndian, PL_CDR2_BE, Extensibility::Mutable);
+
+// ---------------------------------------------------------------------------------
+// ---------------------------------------------------------------------------------
+
+// Bookkeeping for a struct that is being serialized.
+struct OpenStruct {
+  dheader_pos: Option<usize>, // where to write the length, once it is known
+  next_member_id: u32,
+}
+
+// Bookkeeping for a sequence or array that is being serialized.
+struct OpenCollection {
+  length_pos: Option<usize>, // element count of a sequence
+  dheader_pos: Option<usize>,
+  empty: bool,
+}
+
+// The value whose kind, primitive or not, is to be recorded when it begins
+#[derive(Clone, Copy)]
+enum FirstValue {
+  Member,
+  Element,
+}
+
+/// XCDR2 serializer.
+///
+/// DHEADERs and EMHEADERs contain lengths that are known only after the contents are
+/// serialized, so the serializer builds the whole output in a buffer, and patches the
+/// lengths in afterwards.
+///
+/// Serde does not tell the element type of a sequence or an array, so it is known
+/// only when the first element begins. A DHEADER is inserted at that point, if
+/// the element is not primitive, i.e. not a number, bool, char or enum.
+///
+/// Limitations compared to full XTypes:
+/// * Extensibility is the same for all structs in the serialized value.
+/// * Enums and unions are always encoded as FINAL, and collections of unions
+///   do not get a DHEADER.
+/// * An empty sequence has no elements to tell its type, so it never gets a
+///   DHEADER. The XCDR2 deserializers read a zero DHEADER as an empty sequence.
+/// * Maps do not get a DHEADER.
+pub struct CDR2_serializer<BO> {
+  buffer: Vec<u8>,
+  extensibility: Extensibility,
+  open_structs: Vec<OpenStruct>,
+  open_collections: Vec<OpenCollection>,
+  first_value: Option<FirstValue>,
+  member_primitive: bool, // kind of the member being serialized
+  // The next value is a member of a mutable struct, so an Option is encoded by
+  // presence of the member instead of a flag.
+  member_option: bool,
+  omit_member: bool,
+  idl_newtype: Option<IdlNewtype>, // WChar or WString, see CDR_serializer
+  phantom: PhantomData<BO>,
+}
+
+impl<BO> CDR2_serializer<BO>
+where
+  BO: ByteOrder,
+{
+  pub fn new(extensibility: Extensibility) -> CDR2_serializer<BO> {
+    CDR2_serializer::<BO> {
+      buffer: Vec::with_capacity(64),
+      extensibility,
+      open_structs: Vec::new(),
+      open_collections: Vec::new(),
+      first_value: None,
+      member_primitive: false,
+      member_option: false,
+      omit_member: false,
+      idl_newtype: None,
+      phantom: PhantomData,
+    }
+  }
+
+  pub fn into_bytes(self) -> Vec<u8> {
+    self.buffer
+  }
+
+  fn align(&mut self, type_octet_alignment: usize) {
+    let alignment = type_octet_alignment.min(XCDR2_MAX_ALIGNMENT);
+    while !self.buffer.len().is_multiple_of(alignment) {
+      self.buffer.push(0);
+    }
+  }
+
+  fn take_member_option(&mut self) -> bool {
+    std::mem::replace(&mut self.member_option, false)
+  }
+
+  fn write_u32_at(&mut self, pos: usize, value: u32) {
+    BO::write_u32(&mut self.buffer[pos..pos + 4], value);
+  }
+
+  // Called when a value begins, before anything of it is written.
+  fn begin_value(&mut self, primitive: bool) {
+    match self.first_value.take() {
+      Some(FirstValue::Member) => self.member_primitive = primitive,
+      Some(FirstValue::Element) if !primitive => self.add_collection_dheader(),
+      Some(FirstValue::Element) | None => (),
+    }
+  }
+
+  // XTypes 7.4.3.5.3: Collections of non-primitive elements have a DHEADER.
+  fn add_collection_dheader(&mut self) {
+    let pos = match self.open_collections.last() {
+      // Only the element count is written so far. It is aligned to four, so
+      // the DHEADER can go in front of it.
+      Some(OpenCollection { length_pos: Some(pos), .. }) => {
+        let pos = *pos;
+        self.buffer.splice(pos..pos, [0; 4].iter().cloned());
+        pos
+      }
+      Some(OpenCollection { length_pos: None, .. }) => {
+        self.align(4);
+        self.buffer.extend_from_slice(&[0; 4]);
+        self.buffer.len() - 4
+      }
+      None => return,
+    };
+    if let Some(collection) = self.open_collections.last_mut() {
+      collection.dheader_pos = Some(pos);
+    }
+  }
+
+  // A sequence begins with its length, an array does not.
+  fn begin_collection(&mut self, length: Option<usize>) -> Result<()> {
+    let length_pos = match length {
+      Some(length) => {
+        self.align(4);
+        let pos = self.buffer.len();
+        self.buffer.write_u32::<BO>(length as u32)?;
+        Some(pos)
+      }
+      None => None,
+    };
+    self.open_collections.push(OpenCollection { length_pos, dheader_pos: None, empty: true });
+    Ok(())
+  }
+
+  fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    if let Some(collection) = self.open_collections.last_mut() {
+      if collection.empty {
+        collection.empty = false;
+        self.first_value = Some(FirstValue::Element);
+      }
+    }
+    value.serialize(&mut *self)?;
+    self.first_value = None;
+    Ok(())
+  }
+
+  fn end_collection(&mut self) -> Result<()> {
+    match self.open_collections.pop() {
+      Some(OpenCollection { dheader_pos: Some(pos), .. }) => {
+        let length = self.buffer.len() - pos - 4;
+        self.write_u32_at(pos, length as u32);
+        Ok(())
+      }
+      Some(OpenCollection { dheader_pos: None, .. }) => Ok(()),
+      None => Err(Error::Message("Sequence end without beginning.".to_string())),
+    }
+  }
+
+  fn serialize_member<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    let member_id = match self.open_structs.last_mut() {
+      Some(s) => {
+        let id = s.next_member_id;
+        s.next_member_id += 1;
+        id
+      }
+      None => 0,
+    };
+    // Reserve room for EMHEADER and NEXTINT
+    let member_start = self.buffer.len();
+    self.align(4);
+    let header_pos = self.buffer.len();
+    self.buffer.extend_from_slice(&[0; 8]);
+    self.member_option = true;
+    self.omit_member = false;
+    // Members of a nested struct must not change the kind of this member.
+    let outer_member_primitive = self.member_primitive;
+    self.member_primitive = false;
+    self.first_value = Some(FirstValue::Member);
+    value.serialize(&mut *self)?;
+    self.first_value = None;
+    let primitive = std::mem::replace(&mut self.member_primitive, outer_member_primitive);
+    self.member_option = false;
+    if self.omit_member {
+      // Option::None is encoded by leaving the member out.
+      self.omit_member = false;
+      self.buffer.truncate(member_start);
+      return Ok(())
+    }
+
+    // XTypes 7.4.3.4.7: Length codes 0 to 3 are only for primitive members.
+    // Others have their length in NEXTINT.
+    let member_len = self.buffer.len() - header_pos - 8;
+    let length_code: u32 = match (primitive, member_len) {
+      (true, 1) => 0,
+      (true, 2) => 1,
+      (true, 4) => 2,
+      (true, 8) => 3,
+      _ => LC_NEXTINT,
+    };
+    if length_code == LC_NEXTINT {
+      self.write_u32_at(header_pos + 4, member_len as u32);
+    } else {
+      // No NEXTINT needed. Moving the member by four bytes does not change its internal
+      // padding, because XCDR2 alignment is at most four.
+      self.buffer.drain(header_pos + 4..header_pos + 8);
+    }
+    let emheader = (length_code << EMHEADER_LC_SHIFT) | (member_id & EMHEADER_MEMBER_ID_MASK);
+    self.write_u32_at(header_pos, emheader);
+    Ok(())
+  }
+}
+
+pub fn to_writer<T, BO, W>(mut writer: W, value: &T, extensibility: Extensibility) -> Result<()>
+where
+  T: Serialize,
+  BO: ByteOrder,
+  W: io::Write,
+{
+  writer.write_all(&to_bytes::<T, BO>(value, extensibility)?)?;
+  Ok(())
+}
+
+pub fn to_bytes<T, BO>(value: &T, extensibility: Extensibility) -> Result<Vec<u8>>
+where
+  T: Serialize,
+  BO: ByteOrder,
+{
+  let mut serializer = CDR2_serializer::<BO>::new(extensibility);
+  value.serialize(&mut serializer)?;
+  Ok(serializer.into_bytes())
+}
+
+macro_rules! serialize_multibyte_number {
+  ($fn_name:ident, $num_type:ty, $write_fn:ident) => {
+    fn $fn_name(self, v: $num_type) -> Result<()> {
+      self.begin_value(true);
+      self.align(std::mem::size_of::<$num_type>());
+      self.buffer.$write_fn::<BO>(v)?;
+      Ok(())
+    }
+  };
+}
+
+impl<BO> ser::Serializer for &mut CDR2_serializer<BO>
+where
+  BO: ByteOrder,
+{
+  type Ok = ();
+  type Error = Error;
+
+  type SerializeSeq = Self;
+  type SerializeTuple = Self;
+  type SerializeTupleStruct = Self;
+  type SerializeTupleVariant = Self;
+  type SerializeMap = Self;
+  type SerializeStruct = Self;
+  type SerializeStructVariant = Self;
+
+  fn serialize_bool(self, v: bool) -> Result<()> {
+    self.begin_value(true);
+    self.buffer.push(v as u8);
+    Ok(())
+  }
+
+  fn serialize_u8(self, v: u8) -> Result<()> {
+    self.begin_value(true);
+    self.buffer.push(v);
+    Ok(())
+  }
+
+  fn serialize_i8(self, v: i8) -> Result<()> {
+    self.begin_value(true);
+    self.buffer.push(v as u8);
+    Ok(())
+  }
+
+  serialize_multibyte_number!(serialize_u16, u16, write_u16);
+  serialize_multibyte_number!(serialize_u32, u32, write_u32);
+  serialize_multibyte_number!(serialize_u64, u64, write_u64);
+  // Also IDL long double, which needs no special handling, as alignment is at most 4.
+  serialize_multibyte_number!(serialize_u128, u128, write_u128);
+  serialize_multibyte_number!(serialize_i16, i16, write_i16);
+  serialize_multibyte_number!(serialize_i32, i32, write_i32);
+  serialize_multibyte_number!(serialize_i64, i64, write_i64);
+  serialize_multibyte_number!(serialize_f32, f32, write_f32);
+  serialize_multibyte_number!(serialize_f64, f64, write_f64);
+
+  // Rust char is a 32-bit Unicode code point. See CDR_serializer.
+  fn serialize_char(self, v: char) -> Result<()> {
+    self.begin_value(true);
+    if self.idl_newtype.take() == Some(IdlNewtype::WChar) {
+      return self.serialize_u16(idl_types::encode_wchar(v)?)
+    }
+    self.serialize_u32(v as u32)
+  }
+
+  // Same as in CDR: length including null terminator, then the characters.
+  // A wstring has its length in bytes, then UTF-16 code units.
+  fn serialize_str(self, v: &str) -> Result<()> {
+    self.begin_value(false);
+    if self.idl_newtype.take() == Some(IdlNewtype::WString) {
+      let units: Vec<u16> = v.encode_utf16().collect();
+      self.serialize_u32(units.len() as u32 * 2)?;
+      for unit in units {
+        self.buffer.write_u16::<BO>(unit)?;
+      }
+      return Ok(())
+    }
+    self.serialize_u32(v.len() as u32 + 1)?;
+    self.buffer.extend_from_slice(v.as_bytes());
+    self.buffer.push(0);
+    Ok(())
+  }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+    self.begin_value(false);
+    self.buffer.extend_from_slice(v);
+    Ok(())
+  }
+
+  // XTypes 7.4.3.5.3: Optional members of final and appendable types are preceded
+  // by a boolean presence flag.
+  fn serialize_none(self) -> Result<()> {
+    if self.take_member_option() {
+      self.omit_member = true;
+      Ok(())
+    } else {
+      self.begin_value(false);
+      self.serialize_bool(false)
+    }
+  }
+
+  fn serialize_some<T>(self, t: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    if !self.take_member_option() {
+      self.begin_value(false);
+      self.serialize_bool(true)?;
+    }
+    t.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<()> {
+    self.begin_value(true);
+    Ok(())
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+    self.serialize_unit()
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+  ) -> Result<()> {
+    self.serialize_u32(variant_index)
+  }
+
+  fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    self.idl_newtype = IdlNewtype::from_name(name);
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T>(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    value: &T,
+  ) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    self.take_member_option();
+    self.begin_value(true);
+    self.serialize_u32(variant_index)?;
+    value.serialize(self)
+  }
+
+  fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+    self.take_member_option();
+    self.begin_value(false);
+    match len {
+      None => Err(Error::SequenceLengthUnknown),
+      Some(elem_count) => {
+        self.begin_collection(Some(elem_count))?;
+        Ok(self)
+      }
+    }
+  }
+
+  // Arrays are tuples in serde.
+  fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+    self.take_member_option();
+    self.begin_value(false);
+    self.begin_collection(None)?;
+    Ok(self)
+  }
+
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleStruct> {
+    self.take_member_option();
+    self.begin_value(false);
+    Ok(self)
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleVariant> {
+    self.take_member_option();
+    self.begin_value(true);
+    self.serialize_u32(variant_index)?;
+    Ok(self)
+  }
+
+  fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+    self.take_member_option();
+    self.begin_value(false);
+    match len {
+      None => Err(Error::SequenceLengthUnknown),
+      Some(elem_count) => {
+        self.serialize_u32(elem_count as u32)?;
+        Ok(self)
+      }
+    }
+  }
+
+  fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+    self.take_member_option();
+    self.begin_value(false);
+    let dheader_pos = match self.extensibility {
+      Extensibility::Final => None,
+      Extensibility::Appendable | Extensibility::Mutable => {
+        self.align(4);
+        let pos = self.buffer.len();
+        self.buffer.extend_from_slice(&[0; 4]);
+        Some(pos)
+      }
+    };
+    self.open_structs.push(OpenStruct { dheader_pos, next_member_id: 0 });
+    Ok(self)
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeStructVariant> {
+    self.take_member_option();
+    self.begin_value(true);
+    self.serialize_u32(variant_index)?;
+    Ok(self)
+  }
+}
+
+impl<BO: ByteOrder> ser::SerializeSeq for &mut CDR2_serializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    CDR2_serializer::serialize_element(&mut **self, value)
+  }
+
+  fn end(self) -> Result<()> {
+    self.end_collection()
+  }
+}
+
+impl<BO: ByteOrder> ser::SerializeTuple for &mut CDR2_serializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    CDR2_serializer::serialize_element(&mut **self, value)
+  }
+
+  fn end(self) -> Result<()> {
+    self.end_collection()
+  }
+}
+
+impl<BO: ByteOrder> ser::SerializeTupleStruct for &mut CDR2_serializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<BO: ByteOrder> ser::SerializeTupleVariant for &mut CDR2_serializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<BO: ByteOrder> ser::SerializeMap for &mut CDR2_serializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    key.serialize(&mut **self)
+  }
+
+  fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<BO: ByteOrder> ser::SerializeStruct for &mut CDR2_serializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    match self.extensibility {
+      Extensibility::Mutable => self.serialize_member(value),
+      Extensibility::Final | Extensibility::Appendable => value.serialize(&mut **self),
+    }
+  }
+
+  fn end(self) -> Result<()> {
+    match self.open_structs.pop() {
+      Some(OpenStruct { dheader_pos: Some(pos), .. }) => {
+        let length = self.buffer.len() - pos - 4;
+        self.write_u32_at(pos, length as u32);
+        Ok(())
+      }
+      Some(OpenStruct { dheader_pos: None, .. }) => Ok(()),
+      None => Err(Error::Message("Struct end without beginning.".to_string())),
+    }
+  }
+}
+
+impl<BO: ByteOrder> ser::SerializeStructVariant for &mut CDR2_serializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde::{Serialize, Deserialize};
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Inner {
+    a: u8,
+    b: u64,
+  }
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Outer {
+    x: u16,
+    inner: Inner,
+    name: String,
+    opt: Option<u32>,
+  }
+
+  fn sample(opt: Option<u32>) -> Outer {
+    Outer { x: 7, inner: Inner { a: 1, b: 2 }, name: "ab".to_string(), opt }
+  }
+
+  #[test]
+  fn cdr2_final_aligns_to_four() {
+    let bytes = to_bytes::<_, LittleEndian>(&sample(Some(9)), Extensibility::Final).unwrap();
+    let expected: Vec<u8> = vec![
+      0x07, 0x00, // x
+      0x01, 0x00, // inner.a + padding to 4, not 8
+      0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // inner.b
+      0x03, 0x00, 0x00, 0x00, b'a', b'b', 0x00, // name
+      0x01, // opt is present
+      0x09, 0x00, 0x00, 0x00, // opt value
+    ];
+    assert_eq!(bytes, expected);
+  }
+
+  #[test]
+  fn cdr2_appendable_has_dheaders() {
+    let bytes = to_bytes::<_, BigEndian>(&sample(None), Extensibility::Appendable).unwrap();
+    let expected: Vec<u8> = vec![
+      0x00, 0x00, 0x00, 0x1c, // DHEADER of Outer
+      0x00, 0x07, 0x00, 0x00, // x + padding
+      0x00, 0x00, 0x00, 0x0c, // DHEADER of Inner
+      0x01, 0x00, 0x00, 0x00, // inner.a + padding
+      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // inner.b
+      0x00, 0x00, 0x00, 0x03, b'a', b'b', 0x00, // name
+      0x00, // opt is absent
+    ];
+    assert_eq!(bytes, expected);
+  }
+
+  #[test]
+  fn cdr2_mutable_has_emheaders() {
+    let bytes = to_bytes::<_, LittleEndian>(&sample(None), Extensibility::Mutable).unwrap();
+    let expected: Vec<u8> = vec![
+      0x37, 0x00, 0x00, 0x00, // DHEADER of Outer
+      0x00, 0x00, 0x00, 0x10, // EMHEADER: LC=1 (2 bytes), id=0
+      0x07, 0x00, 0x00, 0x00, // x + padding
+      0x01, 0x00, 0x00, 0x40, // EMHEADER: LC=4 (NEXTINT), id=1
+      0x18, 0x00, 0x00, 0x00, // NEXTINT
+      0x14, 0x00, 0x00, 0x00, // DHEADER of Inner
+      0x00, 0x00, 0x00, 0x00, // EMHEADER: LC=0 (1 byte), id=0
+      0x01, 0x00, 0x00, 0x00, // inner.a + padding
+      0x01, 0x00, 0x00, 0x30, // EMHEADER: LC=3 (8 bytes), id=1
+      0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // inner.b
+      0x02, 0x00, 0x00, 0x40, // EMHEADER: LC=4, id=2
+      0x07, 0x00, 0x00, 0x00, // NEXTINT
+      0x03, 0x00, 0x00, 0x00, b'a', b'b', 0x00, // name
+      // opt is None, so it is left out
+    ];
+    assert_eq!(bytes, expected);
+  }
+
+  #[test]
+  fn cdr2_non_primitive_collections_have_dheaders() {
+    #[derive(Serialize)]
+    struct Collections {
+      shorts: Vec<u16>,
+      strings: Vec<String>,
+      inners: [Inner; 1],
+    }
+    let value = Collections {
+      shorts: vec![1],
+      strings: vec!["a".to_string()],
+      inners: [Inner { a: 1, b: 2 }],
+    };
+    let bytes = to_bytes::<_, LittleEndian>(&value, Extensibility::Final).unwrap();
+    let expected: Vec<u8> = vec![
+      0x01, 0x00, 0x00, 0x00, 0x01, 0x00, // sequence<unsigned short>: no DHEADER
+      0x00, 0x00, // padding
+      0x0a, 0x00, 0x00, 0x00, // DHEADER of sequence<string>
+      0x01, 0x00, 0x00, 0x00, // length
+      0x02, 0x00, 0x00, 0x00, b'a', 0x00, // "a"
+      0x00, 0x00, // padding
+      0x0c, 0x00, 0x00, 0x00, // DHEADER of Inner[1]
+      0x01, 0x00, 0x00, 0x00, // inner.a + padding
+      0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // inner.b
+    ];
+    assert_eq!(bytes, expected);
+  }
+}
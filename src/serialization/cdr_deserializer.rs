@@ -5,7 +5,7 @@ use std::marker::PhantomData;
 use serde::{
   de::{
     self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
-    Visitor, DeserializeOwned,
+    Visitor, Deserialize, DeserializeOwned,
   },
 };
 
@@ -13,7 +13,8 @@ use paste::paste;
 
 use crate::serialization::error::Error;
 use crate::serialization::error::Result;
-use crate::dds::traits::serde_adapters::DeserializerAdapter;
+use crate::serialization::idl_types::{self, IdlNewtype};
+use crate::dds::traits::serde_adapters::{DeserializerAdapter, BorrowingDeserializerAdapter};
 
 use crate::messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier;
 
@@ -53,6 +54,25 @@ where
   }
 }
 
+impl<D> BorrowingDeserializerAdapter for CDRDeserializerAdapter<D> {
+  fn from_bytes_borrowed<'de, T>(
+    input_bytes: &'de [u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<T>
+  where
+    T: Deserialize<'de>,
+  {
+    match encoding {
+      RepresentationIdentifier::CDR_LE | RepresentationIdentifier::PL_CDR_LE => {
+        deserialize_from_little_endian(input_bytes)
+      }
+      RepresentationIdentifier::CDR_BE => deserialize_from_big_endian(input_bytes),
+      repr_id => Err(Error::Message(format!(
+        "Unknown representaiton identifier {:?}.", repr_id ))),
+    }
+  }
+}
+
 /// CDR deserializer.
 /// Input is from &[u8], since we expect to have the data in contiguous memory buffers.
 pub struct CDR_deserializer<'de, BO> {
@@ -82,7 +102,7 @@ where
   }
 
   /// Read the first bytes in the input.
-  fn next_bytes(&mut self, count: usize) -> Result<&[u8]> {
+  fn next_bytes(&mut self, count: usize) -> Result<&'de [u8]> {
     if count <= self.input.len() {
       let (head, tail) = self.input.split_at(count);
       self.input = tail;
@@ -124,7 +144,7 @@ where
 
 pub fn deserialize_from_little_endian<'a, T>(s: &'a [u8]) -> Result<T>
 where
-  T: DeserializeOwned,
+  T: Deserialize<'a>,
 {
   let mut deserializer = CDR_deserializer::<LittleEndian>::new(s);
   T::deserialize(&mut deserializer)
@@ -137,7 +157,7 @@ where
 
 pub fn deserialize_from_big_endian<'a, T>(s: &'a [u8]) -> Result<T>
 where
-  T: DeserializeOwned,
+  T: Deserialize<'a>,
 {
   let mut deserializer = CDR_deserializer::<BigEndian>::new(s);
   T::deserialize(&mut deserializer)
@@ -227,9 +247,7 @@ where
   {
     self.calculate_padding_count_from_written_bytes_and_remove(4)?;
     let codepoint = self.next_bytes(4)?.read_u32::<BO>().unwrap();
-    // TODO: Temporary workaround until std::char::from_u32() makes it into stable
-    // matched value should be char::from_u32( codepoint )
-    match Some(codepoint as u8 as char) {
+    match std::char::from_u32(codepoint) {
       Some(c) => visitor.visit_char(c),
       None => Err(Error::BadChar(codepoint)),
     }
@@ -245,10 +263,13 @@ where
 
     let bytes = self.next_bytes(bytes_len)?; // length includes null terminator
 
-    let bytes_without_null = &bytes[0..bytes.len() - 1];
+    let bytes_without_null = match bytes.split_last() {
+      Some((0, rest)) => rest,
+      _ => bytes, // tolerate a missing terminator
+    };
 
     match std::str::from_utf8(bytes_without_null) {
-      Ok(s) => visitor.visit_str(s),
+      Ok(s) => visitor.visit_borrowed_str(s),
       Err(utf8_err) => Err(Error::BadString(utf8_err)),
     }
   }
@@ -262,11 +283,14 @@ where
 
   // Byte strings
 
+  // sequence<octet> can be borrowed, e.g. to &'de [u8]
   fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
   where
     V: Visitor<'de>,
   {
-    self.deserialize_seq(visitor)
+    self.calculate_padding_count_from_written_bytes_and_remove(4)?;
+    let bytes_len = self.next_bytes(4)?.read_u32::<BO>().unwrap() as usize;
+    visitor.visit_borrowed_bytes(self.next_bytes(bytes_len)?)
   }
 
   fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
@@ -304,11 +328,31 @@ where
     self.deserialize_unit(visitor) // This means a named type, which has no data.
   }
 
-  fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
   where
     V: Visitor<'de>,
   {
-    visitor.visit_newtype_struct(self)
+    // See CDR_serializer about these.
+    match IdlNewtype::from_name(name) {
+      Some(IdlNewtype::WChar) => {
+        self.calculate_padding_count_from_written_bytes_and_remove(2)?;
+        let unit = self.next_bytes(2)?.read_u16::<BO>().unwrap();
+        let c: char = idl_types::decode_wchar(unit)?;
+        visitor.visit_newtype_struct(c.into_deserializer())
+      }
+      Some(IdlNewtype::WString) => {
+        self.calculate_padding_count_from_written_bytes_and_remove(4)?;
+        let bytes_len = self.next_bytes(4)?.read_u32::<BO>().unwrap() as usize;
+        let s: String = idl_types::decode_wstring::<BO>(self.next_bytes(bytes_len)?)?;
+        visitor.visit_newtype_struct(s.into_deserializer())
+      }
+      Some(IdlNewtype::LongDouble) => {
+        self.calculate_padding_count_from_written_bytes_and_remove(8)?;
+        let bits: u128 = self.next_bytes(16)?.read_u128::<BO>().unwrap();
+        visitor.visit_newtype_struct(bits.into_deserializer())
+      }
+      None => visitor.visit_newtype_struct(self),
+    }
   }
 
   ///Sequences are encoded as an unsigned long value, followed by the elements of the
@@ -9,6 +9,7 @@ use byteorder::{BigEndian, LittleEndian, ByteOrder};
 
 use crate::serialization::error::Error;
 use crate::serialization::error::Result;
+use crate::serialization::idl_types::{self, IdlNewtype};
 
 use crate::messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier;
 use crate::dds::traits::serde_adapters::SerializerAdapter;
@@ -108,6 +109,7 @@ where
   W: io::Write,
 {
   writer: CountingWrite<W>, // serialization destination
+  idl_newtype: Option<IdlNewtype>, // the next value is wrapped in WChar, WString or LongDouble
   phantom: PhantomData<BO>, // This field exists only to provide use for BO. See PhantomData docs.
 }
 
@@ -119,6 +121,7 @@ where
   pub fn new(w: W) -> CDR_serializer<W, BO> {
     CDR_serializer::<W, BO> {
       writer: CountingWrite::<W>::new(w),
+      idl_newtype: None,
       phantom: PhantomData,
     }
   }
@@ -239,6 +242,12 @@ where
   }
 
   fn serialize_u128(self, v: u128) -> Result<()> {
+    if self.idl_newtype.take() == Some(IdlNewtype::LongDouble) {
+      // IDL long double is 16 bytes, but aligned to 8 like double.
+      self.calculate_padding_need_and_write_padding(8)?;
+      self.writer.write_u128::<BO>(v)?;
+      return Ok(())
+    }
     self.calculate_padding_need_and_write_padding(16)?;
     self.writer.write_u128::<BO>(v)?;
     Ok(())
@@ -286,6 +295,10 @@ where
     // IDL & CDR "char" means an octet.
     // We are here actually serializing the 32-bit quantity.
     // If we want to serialize CDR "char", then the corresponding Rust type is "u8".
+    // IDL "wchar" is WChar, a UTF-16 code unit.
+    if self.idl_newtype.take() == Some(IdlNewtype::WChar) {
+      return self.serialize_u16(idl_types::encode_wchar(v)?)
+    }
     self.serialize_u32(v as u32)?;
     Ok(())
   }
@@ -295,6 +308,15 @@ where
   //octets. The string contents include a single terminating null character. The string
   //length includes the null character, so an empty string has a length of 1.
   fn serialize_str(self, v: &str) -> Result<()> {
+    if self.idl_newtype.take() == Some(IdlNewtype::WString) {
+      // A wstring has its length in bytes, then UTF-16 code units without a terminator.
+      let units: Vec<u16> = v.encode_utf16().collect();
+      self.serialize_u32(units.len() as u32 * 2)?;
+      for unit in units {
+        self.writer.write_u16::<BO>(unit)?;
+      }
+      return Ok(())
+    }
     let byte_count: u32 = v.as_bytes().len() as u32 + 1;
     self.serialize_u32(byte_count)?; // +1 for terminator
     self.writer.write(v.as_bytes())?;
@@ -347,10 +369,11 @@ where
   }
 
   // In CDR, this would be a special case of struct.
-  fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+  fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
   where
     T: ?Sized + Serialize,
   {
+    self.idl_newtype = IdlNewtype::from_name(name);
     value.serialize(self)
   }
 
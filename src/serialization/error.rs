@@ -25,6 +25,7 @@ pub enum Error {
   BadBoolean(u8),
   BadString(std::str::Utf8Error), // was not valid UTF-8
   BadChar(u32),                   // invalid Unicode codepoint
+  BadWString(u16),                // unpaired UTF-16 surrogate
   BadOption(u32),                 // Option variant tag (discriminant) is not 0 or 1
   TrailingCharacters(Vec<u8>),
 }
@@ -50,6 +51,7 @@ impl Display for Error {
       Error::SequenceLengthUnknown => formatter
         .write_str("CDR serialization requires sequence length to be specified at the start."),
       Error::BadChar(e) => formatter.write_fmt(format_args!("Bad Unicode character code: {:?}", e)),
+      Error::BadWString(e) => formatter.write_fmt(format_args!("UTF-16 error: unpaired surrogate {:x}", e)),
       Error::BadBoolean(e) => {
         formatter.write_fmt(format_args!("Expected 0 or 1 as Boolean, got: {:?}", e))
       }
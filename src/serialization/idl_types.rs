@@ -0,0 +1,606 @@
+//! Rust types for IDL types that serde does not have
+//!
+//! * [`WChar`] and [`WString`] are IDL `wchar` and `wstring`. CDR encodes them
+//!   as UTF-16, as specified in XTypes 7.4.3.5: a `wchar` is one 2-byte code
+//!   unit, and a `wstring` is its length in bytes followed by the code units,
+//!   without a terminator.
+//! * [`LongDouble`] is IDL `long double`, an IEEE 754 quadruple precision
+//!   number.
+//! * [`Bound`] checks the bound of a `string<N>` or `sequence<T, N>` member,
+//!   when used with `#[serde(with = "...")]`.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use rustdds::serialization::{LongDouble, WString};
+//!
+//! // struct Measurement { string<16> name; sequence<long, 4> samples; wstring note; long double value; };
+//! #[derive(Serialize, Deserialize)]
+//! struct Measurement {
+//!   #[serde(with = "rustdds::serialization::Bound::<16>")]
+//!   name: String,
+//!   #[serde(with = "rustdds::serialization::Bound::<4>")]
+//!   samples: Vec<i32>,
+//!   note: WString,
+//!   value: LongDouble,
+//! }
+//! ```
+//!
+//! The CDR serializers recognize these types by their serde newtype names.
+//! Other serde data formats see them as newtypes of `char`, `String` and
+//! `u128` (the bits of the number).
+//!
+//! [`WChar`]: struct.WChar.html
+//! [`WString`]: struct.WString.html
+//! [`LongDouble`]: struct.LongDouble.html
+//! [`Bound`]: struct.Bound.html
+
+use std::{fmt, marker::PhantomData};
+
+use byteorder::ByteOrder;
+use serde::{
+  de::{self, Visitor},
+  ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::serialization::error::{Error, Result as SerResult};
+
+// Newtype names that the CDR serializers handle specially
+const WCHAR_NAME: &str = "$rustdds::WChar";
+const WSTRING_NAME: &str = "$rustdds::WString";
+const LONG_DOUBLE_NAME: &str = "$rustdds::LongDouble";
+
+// A CDR serializer remembers which of these it is inside of, and encodes the
+// wrapped char, str or u128 accordingly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IdlNewtype {
+  WChar,
+  WString,
+  LongDouble,
+}
+
+impl IdlNewtype {
+  pub(crate) fn from_name(name: &str) -> Option<IdlNewtype> {
+    match name {
+      WCHAR_NAME => Some(IdlNewtype::WChar),
+      WSTRING_NAME => Some(IdlNewtype::WString),
+      LONG_DOUBLE_NAME => Some(IdlNewtype::LongDouble),
+      _ => None,
+    }
+  }
+}
+
+pub(crate) fn encode_wchar(c: char) -> SerResult<u16> {
+  let mut units = [0u16; 2];
+  match c.encode_utf16(&mut units) {
+    [unit] => Ok(*unit),
+    _ => Err(Error::BadChar(c as u32)),
+  }
+}
+
+pub(crate) fn decode_wchar(unit: u16) -> SerResult<char> {
+  std::char::from_u32(unit as u32).ok_or(Error::BadChar(unit as u32))
+}
+
+// Contents of a wstring: UTF-16 code units in byte order BO
+pub(crate) fn decode_wstring<BO: ByteOrder>(bytes: &[u8]) -> SerResult<String> {
+  if bytes.len() % 2 != 0 {
+    return Err(Error::Message(format!(
+      "wstring length {} is not a multiple of 2",
+      bytes.len()
+    )));
+  }
+  std::char::decode_utf16(bytes.chunks(2).map(BO::read_u16))
+    .collect::<std::result::Result<String, _>>()
+    .map_err(|e| Error::BadWString(e.unpaired_surrogate()))
+}
+
+/// IDL `wchar`. Characters outside the Basic Multilingual Plane do not fit
+/// in one UTF-16 code unit, so CDR serialization fails for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct WChar(pub char);
+
+impl From<char> for WChar {
+  fn from(c: char) -> WChar {
+    WChar(c)
+  }
+}
+
+impl Serialize for WChar {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(WCHAR_NAME, &self.0)
+  }
+}
+
+impl<'de> Deserialize<'de> for WChar {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<WChar, D::Error> {
+    struct WCharVisitor;
+    impl<'de> Visitor<'de> for WCharVisitor {
+      type Value = WChar;
+      fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a wchar")
+      }
+      fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<WChar, D::Error> {
+        char::deserialize(d).map(WChar)
+      }
+    }
+    deserializer.deserialize_newtype_struct(WCHAR_NAME, WCharVisitor)
+  }
+}
+
+/// IDL `wstring`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct WString(pub String);
+
+impl From<String> for WString {
+  fn from(s: String) -> WString {
+    WString(s)
+  }
+}
+
+impl From<&str> for WString {
+  fn from(s: &str) -> WString {
+    WString(s.to_string())
+  }
+}
+
+impl fmt::Display for WString {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+impl Serialize for WString {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(WSTRING_NAME, &self.0)
+  }
+}
+
+impl<'de> Deserialize<'de> for WString {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<WString, D::Error> {
+    struct WStringVisitor;
+    impl<'de> Visitor<'de> for WStringVisitor {
+      type Value = WString;
+      fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a wstring")
+      }
+      fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<WString, D::Error> {
+        String::deserialize(d).map(WString)
+      }
+    }
+    deserializer.deserialize_newtype_struct(WSTRING_NAME, WStringVisitor)
+  }
+}
+
+/// IDL `long double`, i.e. IEEE 754 binary128
+///
+/// Rust has no quadruple precision type, so the value is kept as bits.
+/// Conversion from `f64` is exact, and conversion to `f64` rounds to nearest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LongDouble {
+  bits: u128,
+}
+
+const F128_MANTISSA_BITS: u32 = 112;
+const F128_EXPONENT_BIAS: i32 = 16383;
+const F128_EXPONENT_MAX: u128 = 0x7FFF;
+const F64_MANTISSA_BITS: u32 = 52;
+const F64_EXPONENT_BIAS: i32 = 1023;
+
+impl LongDouble {
+  pub fn from_bits(bits: u128) -> LongDouble {
+    LongDouble { bits }
+  }
+
+  pub fn to_bits(self) -> u128 {
+    self.bits
+  }
+
+  pub fn to_f64(self) -> f64 {
+    let sign = ((self.bits >> 127) as u64) << 63;
+    let exponent = (self.bits >> F128_MANTISSA_BITS) & F128_EXPONENT_MAX;
+    let mantissa = self.bits & ((1 << F128_MANTISSA_BITS) - 1);
+    let shift = F128_MANTISSA_BITS - F64_MANTISSA_BITS;
+
+    if exponent == F128_EXPONENT_MAX {
+      // Infinity, or NaN with its payload truncated but kept non-zero
+      let nan_bits = if mantissa == 0 {
+        0
+      } else {
+        (mantissa >> shift) as u64 | 1 << 51
+      };
+      return f64::from_bits(sign | 0x7FF << 52 | nan_bits);
+    }
+    if exponent == 0 {
+      // Zero, or a binary128 subnormal, which is far below f64 range
+      return f64::from_bits(sign);
+    }
+    let unbiased = exponent as i32 - F128_EXPONENT_BIAS;
+    if unbiased > F64_EXPONENT_BIAS {
+      return f64::from_bits(sign | 0x7FF << 52);
+    }
+    // Significand with the implicit bit, and how much of it is dropped. Below
+    // the normal f64 range more is dropped, giving a subnormal.
+    let significand = 1 << F128_MANTISSA_BITS | mantissa;
+    let (biased, drop) = if unbiased > -F64_EXPONENT_BIAS {
+      ((unbiased + F64_EXPONENT_BIAS) as u64, shift)
+    } else {
+      (0, (shift as i32 + 1 - F64_EXPONENT_BIAS - unbiased) as u32)
+    };
+    if drop > F128_MANTISSA_BITS + 1 {
+      return f64::from_bits(sign);
+    }
+    // Round to nearest, ties to even
+    let mut kept = (significand >> drop) as u64;
+    let rest = significand & ((1 << drop) - 1);
+    let half = 1 << (drop - 1);
+    if rest > half || (rest == half && kept & 1 == 1) {
+      kept += 1;
+    }
+    // Without the implicit bit. A carry from rounding moves to the exponent,
+    // which also turns the largest subnormal into the smallest normal.
+    let bits = if biased == 0 {
+      kept
+    } else {
+      (biased << 52) + kept - (1 << 52)
+    };
+    f64::from_bits(sign | bits)
+  }
+}
+
+impl From<f64> for LongDouble {
+  fn from(value: f64) -> LongDouble {
+    let bits = value.to_bits();
+    let sign = ((bits >> 63) as u128) << 127;
+    let exponent = (bits >> F64_MANTISSA_BITS) & 0x7FF;
+    let mantissa = (bits & ((1 << F64_MANTISSA_BITS) - 1)) as u128;
+    let shift = F128_MANTISSA_BITS - F64_MANTISSA_BITS;
+
+    let (exponent, mantissa) = if exponent == 0x7FF {
+      (F128_EXPONENT_MAX, mantissa << shift)
+    } else if exponent == 0 {
+      if mantissa == 0 {
+        (0, 0)
+      } else {
+        // f64 subnormals are normal in binary128
+        let top = 127 - mantissa.leading_zeros();
+        let unbiased = top as i32 - F64_MANTISSA_BITS as i32 - F64_EXPONENT_BIAS + 1;
+        (
+          (unbiased + F128_EXPONENT_BIAS) as u128,
+          (mantissa << (F128_MANTISSA_BITS - top)) & ((1 << F128_MANTISSA_BITS) - 1),
+        )
+      }
+    } else {
+      (
+        (exponent as i32 - F64_EXPONENT_BIAS + F128_EXPONENT_BIAS) as u128,
+        mantissa << shift,
+      )
+    };
+    LongDouble {
+      bits: sign | exponent << F128_MANTISSA_BITS | mantissa,
+    }
+  }
+}
+
+impl From<LongDouble> for f64 {
+  fn from(value: LongDouble) -> f64 {
+    value.to_f64()
+  }
+}
+
+impl Serialize for LongDouble {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(LONG_DOUBLE_NAME, &self.bits)
+  }
+}
+
+impl<'de> Deserialize<'de> for LongDouble {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LongDouble, D::Error> {
+    struct LongDoubleVisitor;
+    impl<'de> Visitor<'de> for LongDoubleVisitor {
+      type Value = LongDouble;
+      fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a long double")
+      }
+      fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<LongDouble, D::Error> {
+        u128::deserialize(d).map(LongDouble::from_bits)
+      }
+    }
+    deserializer.deserialize_newtype_struct(LONG_DOUBLE_NAME, LongDoubleVisitor)
+  }
+}
+
+/// Values that can have an IDL bound: strings, wide strings and sequences
+pub trait Bounded {
+  /// Length that is compared to the bound: bytes of a string, characters of
+  /// a wide string, or elements of a sequence.
+  fn bounded_len(&self) -> usize;
+}
+
+impl Bounded for String {
+  fn bounded_len(&self) -> usize {
+    self.len()
+  }
+}
+
+impl Bounded for WString {
+  fn bounded_len(&self) -> usize {
+    self.0.encode_utf16().count()
+  }
+}
+
+impl<T> Bounded for Vec<T> {
+  fn bounded_len(&self) -> usize {
+    self.len()
+  }
+}
+
+/// Serde adapter for IDL bounds, e.g. `string<16>` is a `String` member with
+/// `#[serde(with = "rustdds::serialization::Bound::<16>")]`.
+///
+/// Serialization and deserialization fail if the value is longer than `N`.
+/// The encoding is the same as without the bound.
+pub struct Bound<const N: usize> {
+  phantom: PhantomData<()>,
+}
+
+impl<const N: usize> Bound<N> {
+  pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    T: Bounded + Serialize,
+    S: Serializer,
+  {
+    match value.bounded_len() {
+      len if len > N => Err(ser::Error::custom(format_args!(
+        "length {} exceeds bound {}",
+        len, N
+      ))),
+      _ => value.serialize(serializer),
+    }
+  }
+
+  pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+  where
+    T: Bounded + Deserialize<'de>,
+    D: Deserializer<'de>,
+  {
+    let value = T::deserialize(deserializer)?;
+    match value.bounded_len() {
+      len if len > N => Err(de::Error::custom(format_args!(
+        "length {} exceeds bound {}",
+        len, N
+      ))),
+      _ => Ok(value),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use byteorder::{BigEndian, LittleEndian};
+
+  use crate::serialization::{
+    cdr2_deserializer, cdr2_serializer, cdr_deserializer::deserialize_from_little_endian,
+    cdr_serializer::to_little_endian_binary, Extensibility, IdlUnion,
+  };
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Wide {
+    flag: u8,
+    c: WChar,
+    s: WString,
+    value: LongDouble,
+    plain: char,
+  }
+
+  fn wide() -> Wide {
+    Wide {
+      flag: 1,
+      c: WChar('ä'),
+      s: WString::from("aä€"),
+      value: LongDouble::from(1.0),
+      plain: '€',
+    }
+  }
+
+  #[test]
+  fn wide_types_cdr() {
+    let bytes = to_little_endian_binary(&wide()).unwrap();
+    #[rustfmt::skip]
+    let expected: Vec<u8> = vec![
+      1, 0, 0xe4, 0,
+      6, 0, 0, 0, b'a', 0, 0xe4, 0, 0xac, 0x20, // wstring: length in bytes, UTF-16
+      0, 0, // padding to 8
+      0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0x3f, // 1.0 as binary128
+      0xac, 0x20, 0, 0, // Rust char as u32, not truncated
+    ];
+    assert_eq!(bytes, expected);
+    let decoded: Wide = deserialize_from_little_endian(&bytes).unwrap();
+    assert_eq!(decoded, wide());
+
+    // characters outside the BMP are not wchars
+    let mut too_wide = wide();
+    too_wide.c = WChar('😀');
+    assert!(to_little_endian_binary(&too_wide).is_err());
+  }
+
+  #[test]
+  fn wide_types_cdr2() {
+    let bytes = cdr2_serializer::to_bytes::<_, BigEndian>(&wide(), Extensibility::Final).unwrap();
+    assert_eq!(&bytes[2..4], &[0, 0xe4]);
+    assert_eq!(&bytes[16..18], &[0x3f, 0xff]); // aligned to 4 only
+    let decoded: Wide =
+      cdr2_deserializer::from_bytes::<_, BigEndian>(&bytes, Extensibility::Final).unwrap();
+    assert_eq!(decoded, wide());
+  }
+
+  #[test]
+  fn long_double_conversions() {
+    assert_eq!(
+      LongDouble::from(1.0).to_bits(),
+      0x3fff_0000_0000_0000_0000_0000_0000_0000
+    );
+    assert_eq!(
+      LongDouble::from(-2.5).to_bits(),
+      0xc000_4000_0000_0000_0000_0000_0000_0000
+    );
+    for v in &[
+      0.0,
+      -0.0,
+      1.0 / 3.0,
+      std::f64::consts::PI,
+      f64::MAX,
+      f64::MIN_POSITIVE,
+      f64::MIN_POSITIVE / 1024.0, // subnormal
+      5e-324,
+      f64::INFINITY,
+      f64::NEG_INFINITY,
+    ] {
+      assert_eq!(
+        LongDouble::from(*v).to_f64().to_bits(),
+        v.to_bits(),
+        "{}",
+        v
+      );
+    }
+    assert!(LongDouble::from(f64::NAN).to_f64().is_nan());
+
+    // Rounding to nearest: one and a half ulp of 1.0 rounds up to even.
+    let one = LongDouble::from(1.0).to_bits();
+    let ulp = 1u128 << 60;
+    assert_eq!(LongDouble::from_bits(one + ulp / 2).to_f64(), 1.0);
+    assert_eq!(
+      LongDouble::from_bits(one + ulp + ulp / 2).to_f64(),
+      1.0 + 2.0 * f64::EPSILON
+    );
+    // Out of f64 range
+    assert_eq!(LongDouble::from_bits(0x7ffe << 112).to_f64(), f64::INFINITY);
+    assert_eq!(LongDouble::from_bits(0x0001 << 112).to_f64(), 0.0);
+  }
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Limited {
+    #[serde(with = "Bound::<4>")]
+    name: String,
+    #[serde(with = "Bound::<2>")]
+    values: Vec<u16>,
+    #[serde(with = "Bound::<1>")]
+    wide: WString,
+  }
+
+  #[test]
+  fn bounds() {
+    let ok = Limited {
+      name: "BLUE".to_string(),
+      values: vec![1, 2],
+      wide: WString::from("€"),
+    };
+    let bytes = to_little_endian_binary(&ok).unwrap();
+    assert_eq!(
+      deserialize_from_little_endian::<Limited>(&bytes).unwrap(),
+      ok
+    );
+
+    let long_name = Limited {
+      name: "GREEN".to_string(),
+      ..ok
+    };
+    assert!(to_little_endian_binary(&long_name).is_err());
+
+    // The bound is also checked on the receiving side.
+    #[derive(Serialize)]
+    struct Unlimited {
+      name: String,
+      values: Vec<u16>,
+      wide: WString,
+    }
+    let bytes = to_little_endian_binary(&Unlimited {
+      name: "RED".to_string(),
+      values: vec![1, 2, 3],
+      wide: WString::from("€"),
+    })
+    .unwrap();
+    assert!(deserialize_from_little_endian::<Limited>(&bytes).is_err());
+  }
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+  enum Kind {
+    Number,
+    Text,
+    Other,
+  }
+
+  #[derive(IdlUnion, Debug, PartialEq)]
+  #[discriminator(i16)]
+  enum Value {
+    #[case(1, 2)]
+    Int(i32),
+    #[case(-3)]
+    Nothing,
+    #[case(default)]
+    Other(i16, f64),
+  }
+
+  #[derive(IdlUnion, Debug, PartialEq)]
+  #[discriminator(Kind)]
+  enum Named {
+    #[case(Kind::Number)]
+    Number(u8),
+    #[case(Kind::Text)]
+    Text(String),
+  }
+
+  #[test]
+  fn unions() {
+    let bytes = to_little_endian_binary(&Value::Int(7)).unwrap();
+    assert_eq!(bytes, vec![1, 0, 0, 0, 7, 0, 0, 0]);
+    assert_eq!(
+      deserialize_from_little_endian::<Value>(&bytes).unwrap(),
+      Value::Int(7)
+    );
+    // Any label of the case selects it.
+    assert_eq!(
+      deserialize_from_little_endian::<Value>(&[2, 0, 0, 0, 8, 0, 0, 0]).unwrap(),
+      Value::Int(8)
+    );
+
+    let bytes = to_little_endian_binary(&Value::Nothing).unwrap();
+    assert_eq!(bytes, vec![0xfd, 0xff]);
+    assert_eq!(
+      deserialize_from_little_endian::<Value>(&bytes).unwrap(),
+      Value::Nothing
+    );
+
+    let other = Value::Other(9, 0.5);
+    let bytes = to_little_endian_binary(&other).unwrap();
+    assert_eq!(
+      bytes,
+      vec![9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xe0, 0x3f]
+    );
+    assert_eq!(
+      deserialize_from_little_endian::<Value>(&bytes).unwrap(),
+      other
+    );
+
+    let text = Named::Text("x".to_string());
+    let bytes = to_little_endian_binary(&text).unwrap();
+    assert_eq!(bytes, vec![1, 0, 0, 0, 2, 0, 0, 0, b'x', 0]);
+    assert_eq!(
+      deserialize_from_little_endian::<Named>(&bytes).unwrap(),
+      text
+    );
+    // Kind::Other is not a case, and there is no default.
+    assert!(deserialize_from_little_endian::<Named>(&[2, 0, 0, 0]).is_err());
+  }
+
+  #[test]
+  fn unions_cdr2() {
+    let other = Value::Other(9, 0.5);
+    let bytes = cdr2_serializer::to_bytes::<_, LittleEndian>(&other, Extensibility::Final).unwrap();
+    assert_eq!(bytes.len(), 12);
+    let decoded: Value =
+      cdr2_deserializer::from_bytes::<_, LittleEndian>(&bytes, Extensibility::Final).unwrap();
+    assert_eq!(decoded, other);
+  }
+}
@@ -0,0 +1,128 @@
+use std::{io, marker::PhantomData};
+
+use serde::{
+  de::{Deserialize, DeserializeOwned},
+  ser::Serialize,
+};
+
+use crate::{
+  dds::traits::serde_adapters::{
+    BorrowingDeserializerAdapter, DeserializerAdapter, SerializerAdapter,
+  },
+  messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier,
+  serialization::error::{Error, Result},
+};
+
+/// [`SerializerAdapter`] for JSON, using `serde_json`. The samples have the
+/// vendor-specific representation identifier `JSON`, so only RustDDS readers
+/// with [`JSONDeserializerAdapter`] understand them.
+///
+/// Requires the `json` feature.
+///
+/// [`SerializerAdapter`]: ../dds/traits/serde_adapters/trait.SerializerAdapter.html
+/// [`JSONDeserializerAdapter`]: struct.JSONDeserializerAdapter.html
+pub struct JSONSerializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+impl<D> SerializerAdapter<D> for JSONSerializerAdapter<D>
+where
+  D: Serialize,
+{
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::JSON
+  }
+
+  fn to_writer<W: io::Write>(writer: W, value: &D) -> Result<()> {
+    serde_json::to_writer(writer, value).map_err(json_error)
+  }
+}
+
+/// [`DeserializerAdapter`] for JSON, using `serde_json`.
+///
+/// Requires the `json` feature.
+///
+/// [`DeserializerAdapter`]: ../dds/traits/serde_adapters/trait.DeserializerAdapter.html
+pub struct JSONDeserializerAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+const repr_ids: [RepresentationIdentifier; 1] = [RepresentationIdentifier::JSON];
+
+impl<D> DeserializerAdapter<D> for JSONDeserializerAdapter<D>
+where
+  D: DeserializeOwned,
+{
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &repr_ids
+  }
+
+  fn from_bytes<'de>(input_bytes: &'de [u8], encoding: RepresentationIdentifier) -> Result<D> {
+    Self::from_bytes_borrowed(input_bytes, encoding)
+  }
+}
+
+impl<D> BorrowingDeserializerAdapter for JSONDeserializerAdapter<D> {
+  fn from_bytes_borrowed<'de, T>(
+    input_bytes: &'de [u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<T>
+  where
+    T: Deserialize<'de>,
+  {
+    match encoding {
+      RepresentationIdentifier::JSON => serde_json::from_slice(input_bytes).map_err(json_error),
+      repr_id => Err(Error::Message(format!(
+        "Unknown representation identifier {:?}.",
+        repr_id
+      ))),
+    }
+  }
+}
+
+fn json_error(e: serde_json::Error) -> Error {
+  Error::Message(format!("JSON: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use serde::{Deserialize, Serialize};
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Shape {
+    color: String,
+    x: i32,
+    y: i32,
+    size: Option<u16>,
+  }
+
+  #[test]
+  fn json_roundtrip() {
+    let shape = Shape {
+      color: "BLUE".to_string(),
+      x: 10,
+      y: -20,
+      size: Some(30),
+    };
+    let mut bytes = Vec::new();
+    JSONSerializerAdapter::to_writer(&mut bytes, &shape).unwrap();
+    assert_eq!(
+      std::str::from_utf8(&bytes).unwrap(),
+      r#"{"color":"BLUE","x":10,"y":-20,"size":30}"#
+    );
+    assert_eq!(
+      JSONSerializerAdapter::<Shape>::output_encoding(),
+      RepresentationIdentifier::JSON
+    );
+
+    let result: Shape =
+      JSONDeserializerAdapter::from_bytes(&bytes, RepresentationIdentifier::JSON).unwrap();
+    assert_eq!(result, shape);
+    assert!(
+      JSONDeserializerAdapter::<Shape>::from_bytes(&bytes, RepresentationIdentifier::CDR_LE)
+        .is_err()
+    );
+  }
+}
@@ -0,0 +1,104 @@
+//! Serde support for arrays longer than 32 elements
+//!
+//! Serde implements its traits only for arrays of up to 32 elements. Longer
+//! arrays can use this module with `#[serde(with = "...")]`. The array is
+//! serialized as a tuple, which is the same as a CDR array.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Image {
+//!   #[serde(with = "rustdds::serialization::large_array")]
+//!   pixels: [u8; 64],
+//! }
+//! ```
+
+use std::{convert::TryInto, fmt, marker::PhantomData};
+
+use serde::{
+  de::{self, SeqAccess, Visitor},
+  ser::SerializeTuple,
+  Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub fn serialize<S, T, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+  T: Serialize,
+{
+  let mut tuple = serializer.serialize_tuple(N)?;
+  for element in array {
+    tuple.serialize_element(element)?;
+  }
+  tuple.end()
+}
+
+pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+where
+  D: Deserializer<'de>,
+  T: Deserialize<'de>,
+{
+  deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+}
+
+struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+impl<'de, T, const N: usize> Visitor<'de> for ArrayVisitor<T, N>
+where
+  T: Deserialize<'de>,
+{
+  type Value = [T; N];
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "an array of {} elements", N)
+  }
+
+  fn visit_seq<A>(self, mut seq: A) -> Result<[T; N], A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let mut elements = Vec::with_capacity(N);
+    for i in 0..N {
+      match seq.next_element()? {
+        Some(element) => elements.push(element),
+        None => return Err(de::Error::invalid_length(i, &self)),
+      }
+    }
+    elements
+      .try_into()
+      .map_err(|_| de::Error::invalid_length(N, &self))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde::{Deserialize, Serialize};
+
+  use crate::serialization::{
+    cdr_deserializer::deserialize_from_little_endian, cdr_serializer::to_little_endian_binary,
+  };
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Large {
+    #[serde(with = "super")]
+    values: [u16; 40],
+    last: u8,
+  }
+
+  #[test]
+  fn large_array_roundtrip() {
+    let mut value = Large {
+      values: [0; 40],
+      last: 7,
+    };
+    for (i, v) in value.values.iter_mut().enumerate() {
+      *v = i as u16;
+    }
+    let bytes = to_little_endian_binary(&value).unwrap();
+    assert_eq!(bytes.len(), 81);
+    assert_eq!(&bytes[2..4], &[1, 0]);
+    let decoded: Large = deserialize_from_little_endian(&bytes).unwrap();
+    assert_eq!(decoded, value);
+  }
+}
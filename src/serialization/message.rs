@@ -60,6 +60,31 @@ impl<'a> Message {
     self.submessages
   }
 
+  /// Serializes the message as a list of buffers to be sent with vectored I/O.
+  /// DATA payloads are not copied: the returned list refers to the same `Bytes` as
+  /// the CacheChange in DDSCache. Concatenating the segments gives the same result
+  /// as `write_to_vec_with_ctx`.
+  pub fn write_to_segments(&self, endianness: Endianness) -> Result<Vec<Bytes>, speedy::Error> {
+    let mut segments = Vec::new();
+    let mut scratch = self.header.write_to_vec_with_ctx(endianness)?;
+    for submessage in &self.submessages {
+      match &submessage.body {
+        SubmessageBody::Entity(EntitySubmessage::Data(data, _flags)) 
+          if data.serialized_payload.is_some() => {
+          scratch.extend(submessage.header.write_to_vec_with_ctx(endianness)?);
+          scratch.extend(data.headers_to_vec(endianness)?);
+          segments.push(Bytes::from(std::mem::take(&mut scratch)));
+          segments.extend(data.serialized_payload.as_ref().map(|p| p.value.clone()));
+        }
+        _ => scratch.extend(submessage.write_to_vec_with_ctx(endianness)?),
+      }
+    }
+    if !scratch.is_empty() {
+      segments.push(Bytes::from(scratch));
+    }
+    Ok(segments)
+  }
+
   pub fn set_header(&mut self, header: Header) {
     self.header = header;
   }
@@ -336,19 +361,47 @@ impl MessageBuilder {
 
   pub fn data_msg(
     mut self,
-    cache_change: CacheChange,
+    cache_change: &CacheChange,
     reader_entity_id: EntityId,
     writer_entity_id: EntityId,
     endianness: Endianness,
   ) -> MessageBuilder {
+    let key_hash = Parameter {
+      parameter_id: ParameterId::PID_KEY_HASH,
+      value: cache_change.key.to_le_bytes().to_vec(),
+    };
     let inline_qos = match cache_change.kind {
-      ChangeKind::ALIVE => None,
+      // Readers that do not deserialize the data, e.g. RawDataReader, need the
+      // key hash to tell the instances apart.
+      ChangeKind::ALIVE => {
+        let mut param_list = ParameterList::new();
+        match writer_entity_id.get_kind() {
+          EntityKind::WRITER_WITH_KEY_USER_DEFINED | EntityKind::WRITER_WITH_KEY_BUILT_IN => {
+            param_list.parameters.push(key_hash)
+          }
+          _ => (),
+        }
+        // Older Fast DDS versions know only the vendor-specific parameter id.
+        if let Some(identity) = cache_change.related_sample_identity {
+          let value = identity.write_to_vec_with_ctx(endianness).unwrap();
+          for parameter_id in &[
+            ParameterId::PID_RELATED_SAMPLE_IDENTITY,
+            ParameterId::PID_CUSTOM_RELATED_SAMPLE_IDENTITY,
+          ] {
+            param_list.parameters.push(Parameter {
+              parameter_id: *parameter_id,
+              value: value.clone(),
+            });
+          }
+        }
+        if param_list.parameters.is_empty() {
+          None
+        } else {
+          Some(param_list)
+        }
+      }
       _ => {
         let mut param_list = ParameterList::new();
-        let key_hash = Parameter {
-          parameter_id: ParameterId::PID_KEY_HASH,
-          value: cache_change.key.to_le_bytes().to_vec(),
-        };
         param_list.parameters.push(key_hash);
         let status_info = Parameter::create_pid_status_info_parameter(true, true, false);
         param_list.parameters.push(status_info);
@@ -361,7 +414,8 @@ impl MessageBuilder {
       writer_id: writer_entity_id, 
       writer_sn: cache_change.sequence_number,
       inline_qos,
-      serialized_payload: cache_change.data_value,
+      // Cloning the payload only increments the reference count of the shared Bytes.
+      serialized_payload: cache_change.data_value.clone(),
     };
     
     // TODO: please explain this logic here:
@@ -372,7 +426,7 @@ impl MessageBuilder {
       }
     }
 
-    let flags: BitFlags<DATA_Flags> = 
+    let mut flags: BitFlags<DATA_Flags> = 
       BitFlags::<DATA_Flags>::from_endianness(endianness)
       | ( if cache_change.kind == ChangeKind::NOT_ALIVE_DISPOSED {
             // No data, we send key instead
@@ -381,12 +435,12 @@ impl MessageBuilder {
             BitFlags::<DATA_Flags>::from_flag(DATA_Flags::Data)
           }
         );
-    // TODO: This is stupid. There should be an easier way to get the submessage length
-    // than serializing it!
+    if data_message.inline_qos.is_some() {
+      flags |= DATA_Flags::InlineQos;
+    }
     let size = data_message
-      .write_to_vec_with_ctx(endianness)
-      .unwrap()
-      .len() as u16;
+      .len_serialized(endianness)
+      .unwrap() as u16;
 
     self.submessages
       .push( SubMessage {
@@ -680,8 +734,125 @@ mod tests {
       .write_to_vec_with_ctx(Endianness::LittleEndian)
       .unwrap();
     assert_eq!(bits1, serialized);
+
+    // Vectored form must produce the same bytes, and share the payload buffer.
+    let segments = rtps.write_to_segments(Endianness::LittleEndian).unwrap();
+    assert_eq!(bits1, segments.concat());
+    assert!(segments.iter().any(|s| s.as_ptr() == serializedPayload.as_ptr()));
   }
 
   // removed case test_RTPS_submessage_flags_helper , as it was cut-and-paste from
   // submessage_flag module - and obsoleted there.
+
+  #[test]
+  fn data_msg_sends_key_hash_of_keyed_writers() {
+    use crate::dds::{ddsdata::DDSData, qos::InlineQos};
+    use crate::messages::submessages::submessage_elements::serialized_payload::SerializedPayload;
+
+    let mut ddsdata = DDSData::from_serialized(
+      SerializedPayload::new(RepresentationIdentifier::CDR2_LE, vec![1, 2, 3, 4]),
+      None,
+    );
+    ddsdata.value_key_hash = 0x0102_0304;
+    let change = CacheChange::new(
+      ChangeKind::ALIVE,
+      GUID::default(),
+      SequenceNumber::from(1),
+      Some(ddsdata),
+    );
+
+    let send_and_receive = |writer_kind: EntityKind| {
+      let message = MessageBuilder::new()
+        .data_msg(
+          &change,
+          EntityId::ENTITYID_UNKNOWN,
+          EntityId::createCustomEntityID([0, 0, 1], writer_kind),
+          Endianness::LittleEndian,
+        )
+        .add_header_and_build(GuidPrefix::default());
+      let bytes = message
+        .write_to_vec_with_ctx(Endianness::LittleEndian)
+        .unwrap();
+      match Message::read_from_buffer(Bytes::from(bytes)).unwrap().submessages.pop() {
+        Some(SubMessage {
+          body: SubmessageBody::Entity(EntitySubmessage::Data(data, flags)),
+          ..
+        }) => (data, flags),
+        other => panic!("Expected DATA, got {:?}", other),
+      }
+    };
+
+    let (data, flags) = send_and_receive(EntityKind::WRITER_WITH_KEY_USER_DEFINED);
+    assert!(flags.contains(DATA_Flags::InlineQos) && flags.contains(DATA_Flags::Data));
+    let key_hash =
+      InlineQos::key_hash(data.inline_qos.as_ref().unwrap(), RepresentationIdentifier::CDR_LE);
+    assert_eq!(key_hash.unwrap().value(), 0x0102_0304);
+    assert!(data.serialized_payload.is_some());
+
+    let (data, flags) = send_and_receive(EntityKind::WRITER_NO_KEY_USER_DEFINED);
+    assert!(!flags.contains(DATA_Flags::InlineQos));
+    assert!(data.inline_qos.is_none());
+  }
+
+  #[test]
+  fn data_msg_sends_related_sample_identity() {
+    use crate::dds::{ddsdata::DDSData, qos::InlineQos};
+    use crate::messages::submessages::submessage_elements::serialized_payload::SerializedPayload;
+    use crate::structure::inline_qos::SampleIdentity;
+
+    let identity = SampleIdentity {
+      writer_guid: GUID::dummy_test_guid(EntityKind::READER_NO_KEY_USER_DEFINED),
+      sequence_number: SequenceNumber::from((1i64 << 32) + 2),
+    };
+    let mut ddsdata = DDSData::from_serialized(
+      SerializedPayload::new(RepresentationIdentifier::CDR_LE, vec![1, 2, 3, 4]),
+      None,
+    );
+    ddsdata.related_sample_identity = Some(identity);
+    let change = CacheChange::new(
+      ChangeKind::ALIVE,
+      GUID::default(),
+      SequenceNumber::from(1),
+      Some(ddsdata),
+    );
+
+    for &endianness in &[Endianness::LittleEndian, Endianness::BigEndian] {
+      let message = MessageBuilder::new()
+        .data_msg(
+          &change,
+          EntityId::ENTITYID_UNKNOWN,
+          EntityId::createCustomEntityID([0, 0, 1], EntityKind::WRITER_NO_KEY_USER_DEFINED),
+          endianness,
+        )
+        .add_header_and_build(GuidPrefix::default());
+      let bytes = message.write_to_vec_with_ctx(endianness).unwrap();
+      let data = match Message::read_from_buffer(Bytes::from(bytes)).unwrap().submessages.pop() {
+        Some(SubMessage {
+          body: SubmessageBody::Entity(EntitySubmessage::Data(data, _)),
+          ..
+        }) => data,
+        other => panic!("Expected DATA, got {:?}", other),
+      };
+      let inline_qos = data.inline_qos.unwrap();
+      // Both the standard and the Fast DDS parameter id, 24 bytes each
+      let ids: Vec<ParameterId> = inline_qos.parameters.iter().map(|p| p.parameter_id).collect();
+      assert_eq!(
+        ids,
+        vec![
+          ParameterId::PID_RELATED_SAMPLE_IDENTITY,
+          ParameterId::PID_CUSTOM_RELATED_SAMPLE_IDENTITY
+        ]
+      );
+      assert!(inline_qos.parameters.iter().all(|p| p.value.len() == 24));
+      let sn_bytes = match endianness {
+        Endianness::LittleEndian => [1, 0, 0, 0, 2, 0, 0, 0],
+        Endianness::BigEndian => [0, 0, 0, 1, 0, 0, 0, 2],
+      };
+      assert_eq!(&inline_qos.parameters[0].value[16..], &sn_bytes);
+      assert_eq!(
+        InlineQos::related_sample_identity(&inline_qos, endianness).unwrap(),
+        Some(identity)
+      );
+    }
+  }
 }
@@ -2,9 +2,20 @@ pub(crate) mod builtin_data_deserializer;
 pub(crate) mod builtin_data_serializer;
 pub(crate) mod cdr_deserializer;
 pub(crate) mod cdr_serializer;
+pub(crate) mod cdr2_deserializer;
+pub(crate) mod cdr2_serializer;
 pub(crate) mod error;
+pub(crate) mod idl_types;
+pub mod large_array;
 pub(crate) mod pl_cdr_deserializer;
+pub(crate) mod pl_cdr_serializer;
 pub(crate) mod visitors;
+#[cfg(feature = "cbor")]
+pub(crate) mod cbor_adapter;
+#[cfg(feature = "json")]
+pub(crate) mod json_adapter;
+#[cfg(feature = "protobuf")]
+pub(crate) mod protobuf_adapter;
 
 pub(crate) mod message;
 pub(crate) mod submessage;
@@ -16,4 +27,49 @@ pub(crate) use submessage::*;
 // public exports
 pub use cdr_serializer::{CDRSerializerAdapter};
 pub use cdr_deserializer::{CDRDeserializerAdapter};
-pub use crate::dds::traits::serde_adapters::{SerializerAdapter, DeserializerAdapter};
+pub use cdr2_serializer::{
+  CDR2SerializerAdapter, DelimitedCDR2SerializerAdapter, ParameterizedCDR2SerializerAdapter,
+  Extensibility,
+};
+pub use cdr2_deserializer::{CDR2DeserializerAdapter};
+pub use pl_cdr_serializer::{PlCdrSerializerAdapter, PlCdrType};
+pub use pl_cdr_deserializer::{PlCdrDeserializerAdapter};
+pub use rustdds_derive::PlCdrType;
+pub use idl_types::{Bound, Bounded, LongDouble, WChar, WString};
+/// IDL unions are enums with a variant for each case. The enum has
+/// `#[discriminator(T)]` with the discriminator type, and each variant has
+/// `#[case(..)]` with its labels, which are literals or paths to constants or
+/// enum values. A variant has the value of its case as the only field, or no
+/// fields if the case has no member. The `default` case has the discriminator
+/// as its first field.
+///
+/// Serialized as the discriminator followed by the value, which is the CDR
+/// encoding of unions.
+///
+/// ```
+/// use rustdds::serialization::IdlUnion;
+///
+/// // union Value switch (short) { case 1: case 2: long i; case 3: string s; case 4: ; default: double d; };
+/// #[derive(IdlUnion, Debug, PartialEq)]
+/// #[discriminator(i16)]
+/// enum Value {
+///   #[case(1, 2)]
+///   I(i32),
+///   #[case(3)]
+///   S(String),
+///   #[case(4)]
+///   Nothing,
+///   #[case(default)]
+///   D(i16, f64),
+/// }
+/// ```
+pub use rustdds_derive::IdlUnion;
+#[cfg(feature = "cbor")]
+pub use cbor_adapter::{CBORSerializerAdapter, CBORDeserializerAdapter};
+#[cfg(feature = "json")]
+pub use json_adapter::{JSONSerializerAdapter, JSONDeserializerAdapter};
+#[cfg(feature = "protobuf")]
+pub use protobuf_adapter::{ProtobufSerializerAdapter, ProtobufDeserializerAdapter};
+pub use crate::dds::traits::serde_adapters::{
+  SerializerAdapter, DeserializerAdapter, BorrowingDeserializerAdapter,
+};
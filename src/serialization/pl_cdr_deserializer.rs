@@ -1,19 +1,35 @@
-use serde::{Deserializer, de::DeserializeOwned};
+use serde::{
+  Deserializer,
+  de::{DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor},
+};
 use std::marker::PhantomData;
 
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
 use crate::serialization::error::Error;
 use crate::{
   messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier,
   serialization::error::Result,
 };
+use crate::serialization::cdr_deserializer::CDR_deserializer;
+use crate::serialization::pl_cdr_serializer::{
+  PlCdrType, PID_MUST_UNDERSTAND_FLAG, PID_PAD, PID_SENTINEL, PID_VENDOR_SPECIFIC_FLAG,
+};
 
 use crate::dds::traits::serde_adapters::DeserializerAdapter;
 
+/// [`DeserializerAdapter`] for parameter list encoding (`PL_CDR_LE` /
+/// `PL_CDR_BE`). See [`PlCdrType`] for how struct fields are found in the
+/// parameter list. Unknown parameters are skipped, unless they have the
+/// must-understand flag and are not vendor-specific.
+///
+/// [`DeserializerAdapter`]: ../dds/traits/serde_adapters/trait.DeserializerAdapter.html
+/// [`PlCdrType`]: trait.PlCdrType.html
 pub struct PlCdrDeserializerAdapter<D> {
   phantom: PhantomData<D>,
 }
 
-const repr_ids: [RepresentationIdentifier; 4] = [
+pub(crate) const repr_ids: [RepresentationIdentifier; 4] = [
   // CDR_* are only added for random interoperability
   RepresentationIdentifier::CDR_BE,
   RepresentationIdentifier::CDR_LE,
@@ -24,58 +40,80 @@ const repr_ids: [RepresentationIdentifier; 4] = [
 
 impl<D> DeserializerAdapter<D> for PlCdrDeserializerAdapter<D>
 where
-  D: DeserializeOwned,
+  D: DeserializeOwned + PlCdrType,
 {
   fn supported_encodings() -> &'static [RepresentationIdentifier] {
     &repr_ids
   }
 
   fn from_bytes<'de>(input_bytes: &'de [u8], encoding: RepresentationIdentifier) -> Result<D> {
-    match encoding {
-      RepresentationIdentifier::PL_CDR_LE | RepresentationIdentifier::CDR_LE => {
-        PlCdrDeserializer::from_little_endian_bytes::<D>(input_bytes)
-      }
-      RepresentationIdentifier::PL_CDR_BE | RepresentationIdentifier::CDR_BE => {
-        PlCdrDeserializer::from_big_endian_bytes::<D>(input_bytes)
-      }
-      repr_id => Err(Error::Message(format!("Unknown representation identifier {:?}",repr_id
-      ))),
-    }
+    from_bytes_with_ids(input_bytes, encoding, D::parameter_id)
   }
 }
 
+// Deserializes from any of the supported encodings, with the given ParameterIds
+pub(crate) fn from_bytes_with_ids<D: DeserializeOwned>(
+  input_bytes: &[u8],
+  encoding: RepresentationIdentifier,
+  parameter_id: fn(&str) -> Option<u16>,
+) -> Result<D> {
+  let endianness = match encoding {
+    RepresentationIdentifier::PL_CDR_LE | RepresentationIdentifier::CDR_LE => {
+      RepresentationIdentifier::PL_CDR_LE
+    }
+    RepresentationIdentifier::PL_CDR_BE | RepresentationIdentifier::CDR_BE => {
+      RepresentationIdentifier::PL_CDR_BE
+    }
+    repr_id => {
+      return Err(Error::Message(format!("Unknown representation identifier {:?}", repr_id)))
+    }
+  };
+  D::deserialize(PlCdrDeserializer::new(input_bytes, endianness, parameter_id))
+}
+
+/// Parameter list deserializer.
+///
+/// A struct is deserialized from the parameters, with the ParameterIds given
+/// by [`PlCdrType`](trait.PlCdrType.html). `deserialize_any` gives the
+/// representation identifier and the whole parameter list as bytes to the
+/// visitor instead. The builtin discovery types are deserialized that way.
 pub struct PlCdrDeserializer<'de> {
   endianness: RepresentationIdentifier,
   input: &'de [u8],
+  parameter_id: fn(&str) -> Option<u16>,
 }
 
 impl<'de> PlCdrDeserializer<'de> {
-  pub fn new(s: &'de [u8], endianness: RepresentationIdentifier) -> PlCdrDeserializer {
+  pub fn new(
+    s: &'de [u8],
+    endianness: RepresentationIdentifier,
+    parameter_id: fn(&str) -> Option<u16>,
+  ) -> PlCdrDeserializer {
     PlCdrDeserializer {
       endianness,
       input: s,
+      parameter_id,
     }
   }
 
-  pub fn from_little_endian_bytes<'a, T: DeserializeOwned>(s: &'a [u8]) -> Result<T> {
-    let deserializer = PlCdrDeserializer::new(s, RepresentationIdentifier::PL_CDR_LE);
+  pub fn from_little_endian_bytes<'a, T: DeserializeOwned + PlCdrType>(s: &'a [u8]) -> Result<T> {
+    let deserializer =
+      PlCdrDeserializer::new(s, RepresentationIdentifier::PL_CDR_LE, T::parameter_id);
     T::deserialize(deserializer)
   }
 
-  pub fn from_big_endian_bytes<'a, T: DeserializeOwned>(s: &'a [u8]) -> Result<T> {
-    let deserializer = PlCdrDeserializer::new(s, RepresentationIdentifier::PL_CDR_BE);
+  pub fn from_big_endian_bytes<'a, T: DeserializeOwned + PlCdrType>(s: &'a [u8]) -> Result<T> {
+    let deserializer =
+      PlCdrDeserializer::new(s, RepresentationIdentifier::PL_CDR_BE, T::parameter_id);
     T::deserialize(deserializer)
   }
 
   fn custom_deserialize_any<V>(self, visitor: V) -> Result<V::Value>
   where
     V: serde::de::Visitor<'de>,
-    //BuiltinDataDeserializer<LittleEndian, V::Value>: DataGeneration<V::Value>,
-    //BuiltinDataDeserializer<BigEndian, V::Value>: DataGeneration<V::Value>,
   {
     match self.endianness {
       RepresentationIdentifier::PL_CDR_LE | RepresentationIdentifier::PL_CDR_BE => {
-        //let rep: Result<Vec<u8>> = to_bytes::<u16, LittleEndian>(&u16::from(self.endianness));
         visitor.visit_bytes(&[self.endianness.to_bytes(), self.input].concat())
       }
       e => Err(Error::Message(format!("Unsupported endianness {:?}", e))),
@@ -83,6 +121,23 @@ impl<'de> PlCdrDeserializer<'de> {
   }
 }
 
+fn not_a_struct<T>() -> Result<T> {
+  Err(Error::Message("Only structs can be deserialized from a parameter list.".to_string()))
+}
+
+macro_rules! deserialize_not_a_struct {
+  ($($method:ident ( $($arg:ident : $arg_type:ty),* );)*) => {
+    $(
+      fn $method<V>(self, $($arg: $arg_type,)* _visitor: V) -> Result<V::Value>
+      where
+        V: Visitor<'de>,
+      {
+        not_a_struct()
+      }
+    )*
+  };
+}
+
 impl<'de> Deserializer<'de> for PlCdrDeserializer<'de> {
   type Error = Error;
 
@@ -93,214 +148,238 @@ impl<'de> Deserializer<'de> for PlCdrDeserializer<'de> {
     self.custom_deserialize_any(visitor)
   }
 
-  fn deserialize_bool<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_i8<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_i16<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_i32<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_i64<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_u8<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_u16<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_u32<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_u64<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
+  deserialize_not_a_struct! {
+    deserialize_bool();
+    deserialize_i8();
+    deserialize_i16();
+    deserialize_i32();
+    deserialize_i64();
+    deserialize_u8();
+    deserialize_u16();
+    deserialize_u32();
+    deserialize_u64();
+    deserialize_f32();
+    deserialize_f64();
+    deserialize_char();
+    deserialize_str();
+    deserialize_string();
+    deserialize_option();
+    deserialize_unit();
+    deserialize_unit_struct(_name: &'static str);
+    deserialize_seq();
+    deserialize_tuple(_len: usize);
+    deserialize_tuple_struct(_name: &'static str, _len: usize);
+    deserialize_map();
+    deserialize_enum(_name: &'static str, _variants: &'static [&'static str]);
+    deserialize_identifier();
   }
 
-  fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
-
-  fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+  fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
   where
     V: serde::de::Visitor<'de>,
   {
-    unimplemented!()
+    visitor.visit_bytes(self.input)
   }
 
-  fn deserialize_str<V>(self, _visitor: V) -> Result<V::Value>
+  fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
   where
     V: serde::de::Visitor<'de>,
   {
-    unimplemented!()
+    self.deserialize_bytes(visitor)
   }
 
-  fn deserialize_string<V>(self, _visitor: V) -> Result<V::Value>
+  fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
   where
     V: serde::de::Visitor<'de>,
   {
-    unimplemented!()
+    visitor.visit_newtype_struct(self)
   }
 
-  fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+  fn deserialize_struct<V>(
+    self,
+    _name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
   where
     V: serde::de::Visitor<'de>,
   {
-    visitor.visit_bytes(self.input)
+    match self.endianness {
+      RepresentationIdentifier::PL_CDR_LE => visitor.visit_map(
+        ParameterHelper::<LittleEndian>::new(self.input, fields, self.parameter_id)),
+      RepresentationIdentifier::PL_CDR_BE => visitor.visit_map(
+        ParameterHelper::<BigEndian>::new(self.input, fields, self.parameter_id)),
+      e => Err(Error::Message(format!("Unsupported endianness {:?}", e))),
+    }
   }
 
-  fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+  fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
   where
     V: serde::de::Visitor<'de>,
   {
-    unimplemented!()
+    visitor.visit_unit()
   }
+}
 
-  fn deserialize_option<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
+// ----------------------------------------------------------
 
-  fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
+// Walks through the parameters. Keys are field names, values are the
+// parameter values.
+struct ParameterHelper<'de, BO> {
+  input: &'de [u8],
+  fields: &'static [&'static str],
+  parameter_id: fn(&str) -> Option<u16>,
+  value: Option<&'de [u8]>,
+  phantom: PhantomData<BO>,
+}
 
-  fn deserialize_unit_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
+impl<'de, BO> ParameterHelper<'de, BO>
+where
+  BO: ByteOrder,
+{
+  fn new(
+    input: &'de [u8],
+    fields: &'static [&'static str],
+    parameter_id: fn(&str) -> Option<u16>,
+  ) -> Self {
+    ParameterHelper {
+      input,
+      fields,
+      parameter_id,
+      value: None,
+      phantom: PhantomData,
+    }
   }
 
-  fn deserialize_newtype_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
+  fn next_bytes(&mut self, count: usize) -> Result<&'de [u8]> {
+    if count <= self.input.len() {
+      let (head, tail) = self.input.split_at(count);
+      self.input = tail;
+      Ok(head)
+    } else {
+      Err(Error::Eof)
+    }
   }
+}
 
-  fn deserialize_seq<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
+impl<'de, BO> MapAccess<'de> for ParameterHelper<'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
 
-  fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
   where
-    V: serde::de::Visitor<'de>,
+    K: DeserializeSeed<'de>,
   {
-    unimplemented!()
+    // A missing PID_SENTINEL is tolerated.
+    while !self.input.is_empty() {
+      let header = self.next_bytes(4)?;
+      let parameter_id = BO::read_u16(&header[0..2]);
+      let length = BO::read_u16(&header[2..4]) as usize;
+      let value = self.next_bytes(length)?;
+      if parameter_id == PID_SENTINEL {
+        break
+      }
+      let field = self
+        .fields
+        .iter()
+        .find(|f| (self.parameter_id)(f) == Some(parameter_id));
+      match field {
+        Some(field) => {
+          self.value = Some(value);
+          return seed.deserialize((*field).into_deserializer()).map(Some)
+        }
+        None if parameter_id == PID_PAD => (),
+        // RTPS spec 9.6.2.2.1: Unknown vendor-specific parameters are
+        // ignored, other unknown parameters only if they need not be
+        // understood.
+        None if parameter_id & PID_VENDOR_SPECIFIC_FLAG == 0
+          && parameter_id & PID_MUST_UNDERSTAND_FLAG != 0 => {
+          return Err(Error::Message(format!(
+            "Unknown ParameterId {:#06x} must be understood", parameter_id)))
+        }
+        None => (),
+      }
+    }
+    Ok(None)
   }
 
-  fn deserialize_tuple_struct<V>(
-    self,
-    _name: &'static str,
-    _len: usize,
-    _visitor: V,
-  ) -> Result<V::Value>
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
   where
-    V: serde::de::Visitor<'de>,
+    V: DeserializeSeed<'de>,
   {
-    unimplemented!()
+    match self.value.take() {
+      Some(value) => seed.deserialize(ParameterValueDeserializer {
+        cdr: CDR_deserializer::<BO>::new(value),
+      }),
+      None => Err(Error::Message("Parameter value without ParameterId.".to_string())),
+    }
   }
+}
 
-  fn deserialize_map<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
+// ----------------------------------------------------------
 
-  fn deserialize_struct<V>(
-    self,
-    _name: &'static str,
-    _fields: &'static [&'static str],
-    visitor: V,
-  ) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    self.deserialize_any(visitor)
-  }
+// Deserializes a parameter value as CDR. A present parameter is Some, see
+// PlCdrSerializer.
+struct ParameterValueDeserializer<'de, BO> {
+  cdr: CDR_deserializer<'de, BO>,
+}
 
-  fn deserialize_enum<V>(
-    self,
-    _name: &'static str,
-    _variants: &'static [&'static str],
-    _visitor: V,
-  ) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
+macro_rules! forward_to_cdr {
+  ($($method:ident ( $($arg:ident : $arg_type:ty),* );)*) => {
+    $(
+      fn $method<V>(mut self, $($arg: $arg_type,)* visitor: V) -> Result<V::Value>
+      where
+        V: Visitor<'de>,
+      {
+        (&mut self.cdr).$method($($arg,)* visitor)
+      }
+    )*
+  };
+}
 
-  fn deserialize_identifier<V>(self, _visitor: V) -> Result<V::Value>
-  where
-    V: serde::de::Visitor<'de>,
-  {
-    unimplemented!()
-  }
+impl<'de, BO> Deserializer<'de> for ParameterValueDeserializer<'de, BO>
+where
+  BO: ByteOrder,
+{
+  type Error = Error;
 
-  fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+  forward_to_cdr! {
+    deserialize_any();
+    deserialize_bool();
+    deserialize_i8();
+    deserialize_i16();
+    deserialize_i32();
+    deserialize_i64();
+    deserialize_u8();
+    deserialize_u16();
+    deserialize_u32();
+    deserialize_u64();
+    deserialize_f32();
+    deserialize_f64();
+    deserialize_char();
+    deserialize_str();
+    deserialize_string();
+    deserialize_bytes();
+    deserialize_byte_buf();
+    deserialize_unit();
+    deserialize_unit_struct(name: &'static str);
+    deserialize_newtype_struct(name: &'static str);
+    deserialize_seq();
+    deserialize_tuple(len: usize);
+    deserialize_tuple_struct(name: &'static str, len: usize);
+    deserialize_map();
+    deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+    deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+    deserialize_identifier();
+    deserialize_ignored_any();
+  }
+
+  fn deserialize_option<V>(mut self, visitor: V) -> Result<V::Value>
   where
-    V: serde::de::Visitor<'de>,
+    V: Visitor<'de>,
   {
-    unimplemented!()
+    visitor.visit_some(&mut self.cdr)
   }
 }
@@ -0,0 +1,497 @@
+use serde::{ser, Serialize};
+use std::marker::PhantomData;
+use std::io;
+
+use byteorder::{BigEndian, LittleEndian, ByteOrder, WriteBytesExt};
+
+use crate::serialization::error::Error;
+use crate::serialization::error::Result;
+use crate::serialization::cdr_serializer::CDR_serializer;
+
+use crate::messages::submessages::submessage_elements::serialized_payload::RepresentationIdentifier;
+use crate::dds::traits::serde_adapters::SerializerAdapter;
+
+// RTPS spec Table 9.12
+pub(crate) const PID_PAD: u16 = 0x0000;
+pub(crate) const PID_SENTINEL: u16 = 0x0001;
+// RTPS spec 9.6.2.2.1: Flag bits of ParameterId
+pub(crate) const PID_MUST_UNDERSTAND_FLAG: u16 = 0x4000;
+pub(crate) const PID_VENDOR_SPECIFIC_FLAG: u16 = 0x8000;
+
+/// Maps the fields of a struct to RTPS ParameterIds, for encoding the struct
+/// as a parameter list (`PL_CDR_LE` / `PL_CDR_BE`), like the builtin
+/// discovery topics are.
+///
+/// Usually derived, with an id for each field:
+///
+/// ```
+/// use serde::{Serialize, Deserialize};
+/// use rustdds::serialization::PlCdrType;
+///
+/// #[derive(Serialize, Deserialize, PlCdrType)]
+/// struct Sensor {
+///   #[parameter_id = 0x0005]
+///   name: String,
+///   #[parameter_id = 0x8001] // vendor-specific
+///   reading: Option<f64>,
+/// }
+/// ```
+///
+/// Fields of type `Option` are left out of the parameter list when they are
+/// `None`, and are `None` when their parameter is missing. Other missing
+/// parameters are an error, unless the field has `#[serde(default)]`.
+///
+/// Types whose `Deserialize` implementation calls `deserialize_any` get the
+/// whole parameter list as bytes instead, and need no parameter ids.
+pub trait PlCdrType {
+  /// ParameterId of a field, given by its serde name.
+  fn parameter_id(field: &str) -> Option<u16>;
+}
+
+/// [`SerializerAdapter`] for parameter list encoding (`PL_CDR_LE` /
+/// `PL_CDR_BE`). The top-level value must be a struct implementing
+/// [`PlCdrType`]. Each field is a parameter, whose value is encoded as plain
+/// CDR, and the list ends with `PID_SENTINEL`.
+///
+/// [`SerializerAdapter`]: ../dds/traits/serde_adapters/trait.SerializerAdapter.html
+/// [`PlCdrType`]: trait.PlCdrType.html
+pub struct PlCdrSerializerAdapter<D, BO = LittleEndian>
+where
+  BO: ByteOrder,
+{
+  phantom: PhantomData<D>,
+  ghost: PhantomData<BO>,
+}
+
+impl<D> SerializerAdapter<D> for PlCdrSerializerAdapter<D, LittleEndian>
+where
+  D: Serialize + PlCdrType,
+{
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::PL_CDR_LE
+  }
+
+  fn to_writer<W: io::Write>(writer: W, value: &D) -> Result<()> {
+    to_writer::<D, LittleEndian, W>(writer, value)
+  }
+}
+
+impl<D> SerializerAdapter<D> for PlCdrSerializerAdapter<D, BigEndian>
+where
+  D: Serialize + PlCdrType,
+{
+  fn output_encoding() -> RepresentationIdentifier {
+    RepresentationIdentifier::PL_CDR_BE
+  }
+
+  fn to_writer<W: io::Write>(writer: W, value: &D) -> Result<()> {
+    to_writer::<D, BigEndian, W>(writer, value)
+  }
+}
+
+pub fn to_writer<T, BO, W>(mut writer: W, value: &T) -> Result<()>
+where
+  T: Serialize + PlCdrType,
+  BO: ByteOrder,
+  W: io::Write,
+{
+  writer.write_all(&to_bytes::<T, BO>(value)?)?;
+  Ok(())
+}
+
+pub fn to_bytes<T, BO>(value: &T) -> Result<Vec<u8>>
+where
+  T: Serialize + PlCdrType,
+  BO: ByteOrder,
+{
+  let mut serializer = PlCdrSerializer::<BO>::new(T::parameter_id);
+  value.serialize(&mut serializer)?;
+  Ok(serializer.buffer)
+}
+
+// ---------------------------------------------------------------------------------
+// ---------------------------------------------------------------------------------
+
+/// Parameter list serializer. Only a struct (possibly inside newtype structs)
+/// can be serialized.
+pub struct PlCdrSerializer<BO> {
+  buffer: Vec<u8>,
+  parameter_id: fn(&str) -> Option<u16>,
+  phantom: PhantomData<BO>,
+}
+
+impl<BO> PlCdrSerializer<BO>
+where
+  BO: ByteOrder,
+{
+  pub fn new(parameter_id: fn(&str) -> Option<u16>) -> PlCdrSerializer<BO> {
+    PlCdrSerializer::<BO> {
+      buffer: Vec::with_capacity(64),
+      parameter_id,
+      phantom: PhantomData,
+    }
+  }
+
+  fn write_parameter_header(&mut self, parameter_id: u16, length: usize) -> Result<()> {
+    if length > u16::MAX as usize {
+      return Err(Error::Message(format!(
+        "Parameter {:#06x} is too long: {} bytes", parameter_id, length)))
+    }
+    self.buffer.write_u16::<BO>(parameter_id)?;
+    self.buffer.write_u16::<BO>(length as u16)?;
+    Ok(())
+  }
+}
+
+fn not_a_struct<T>() -> Result<T> {
+  Err(Error::Message("Only structs can be serialized as a parameter list.".to_string()))
+}
+
+impl<'a, BO> ser::Serializer for &'a mut PlCdrSerializer<BO>
+where
+  BO: ByteOrder,
+{
+  type Ok = ();
+  type Error = Error;
+
+  type SerializeSeq = ser::Impossible<(), Error>;
+  type SerializeTuple = ser::Impossible<(), Error>;
+  type SerializeTupleStruct = ser::Impossible<(), Error>;
+  type SerializeTupleVariant = ser::Impossible<(), Error>;
+  type SerializeMap = ser::Impossible<(), Error>;
+  type SerializeStruct = Self;
+  type SerializeStructVariant = ser::Impossible<(), Error>;
+
+  fn serialize_bool(self, _v: bool) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_i8(self, _v: i8) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_i16(self, _v: i16) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_i32(self, _v: i32) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_i64(self, _v: i64) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_u8(self, _v: u8) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_u16(self, _v: u16) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_u32(self, _v: u32) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_u64(self, _v: u64) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_f32(self, _v: f32) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_f64(self, _v: f64) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_char(self, _v: char) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_str(self, _v: &str) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_none(self) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_some<T>(self, _value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    not_a_struct()
+  }
+  fn serialize_unit(self) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+    not_a_struct()
+  }
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+  ) -> Result<()> {
+    not_a_struct()
+  }
+
+  fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T>(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    _value: &T,
+  ) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    not_a_struct()
+  }
+  fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+    not_a_struct()
+  }
+  fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+    not_a_struct()
+  }
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleStruct> {
+    not_a_struct()
+  }
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleVariant> {
+    not_a_struct()
+  }
+  fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+    not_a_struct()
+  }
+
+  fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+    Ok(self)
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeStructVariant> {
+    not_a_struct()
+  }
+}
+
+impl<'a, BO: ByteOrder> ser::SerializeStruct for &'a mut PlCdrSerializer<BO> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    let parameter_id = (self.parameter_id)(key)
+      .ok_or_else(|| Error::Message(format!("No ParameterId for field {}", key)))?;
+    // Each parameter value is CDR with alignment relative to its own start.
+    let mut value_bytes = Vec::new();
+    let mut omitted = false;
+    value.serialize(ParameterValueSerializer {
+      cdr: &mut CDR_serializer::<_, BO>::new(&mut value_bytes),
+      omitted: &mut omitted,
+    })?;
+    if omitted {
+      return Ok(())
+    }
+    // RTPS spec 9.4.2.11: Parameters start at 4-byte boundaries.
+    while value_bytes.len() % 4 != 0 {
+      value_bytes.push(0);
+    }
+    self.write_parameter_header(parameter_id, value_bytes.len())?;
+    self.buffer.extend_from_slice(&value_bytes);
+    Ok(())
+  }
+
+  fn end(self) -> Result<()> {
+    self.write_parameter_header(PID_SENTINEL, 0)
+  }
+}
+
+// ---------------------------------------------------------------------------------
+
+// Serializes a parameter value as CDR, except that an Option is encoded by
+// presence of the parameter instead of a discriminant.
+struct ParameterValueSerializer<'a, W: io::Write, BO> {
+  cdr: &'a mut CDR_serializer<W, BO>,
+  omitted: &'a mut bool,
+}
+
+macro_rules! forward_to_cdr {
+  ($($method:ident ( $($arg:ident : $arg_type:ty),* ) -> $ret:ty ;)*) => {
+    $(
+      fn $method(self, $($arg: $arg_type),*) -> Result<$ret> {
+        self.cdr.$method($($arg),*)
+      }
+    )*
+  };
+}
+
+impl<'a, W, BO> ser::Serializer for ParameterValueSerializer<'a, W, BO>
+where
+  W: io::Write,
+  BO: ByteOrder,
+{
+  type Ok = ();
+  type Error = Error;
+
+  type SerializeSeq = &'a mut CDR_serializer<W, BO>;
+  type SerializeTuple = &'a mut CDR_serializer<W, BO>;
+  type SerializeTupleStruct = &'a mut CDR_serializer<W, BO>;
+  type SerializeTupleVariant = &'a mut CDR_serializer<W, BO>;
+  type SerializeMap = &'a mut CDR_serializer<W, BO>;
+  type SerializeStruct = &'a mut CDR_serializer<W, BO>;
+  type SerializeStructVariant = &'a mut CDR_serializer<W, BO>;
+
+  forward_to_cdr! {
+    serialize_bool(v: bool) -> ();
+    serialize_i8(v: i8) -> ();
+    serialize_i16(v: i16) -> ();
+    serialize_i32(v: i32) -> ();
+    serialize_i64(v: i64) -> ();
+    serialize_u8(v: u8) -> ();
+    serialize_u16(v: u16) -> ();
+    serialize_u32(v: u32) -> ();
+    serialize_u64(v: u64) -> ();
+    serialize_f32(v: f32) -> ();
+    serialize_f64(v: f64) -> ();
+    serialize_char(v: char) -> ();
+    serialize_str(v: &str) -> ();
+    serialize_bytes(v: &[u8]) -> ();
+    serialize_unit() -> ();
+    serialize_unit_struct(name: &'static str) -> ();
+    serialize_unit_variant(name: &'static str, variant_index: u32, variant: &'static str) -> ();
+    serialize_seq(len: Option<usize>) -> Self::SerializeSeq;
+    serialize_tuple(len: usize) -> Self::SerializeTuple;
+    serialize_tuple_struct(name: &'static str, len: usize) -> Self::SerializeTupleStruct;
+    serialize_tuple_variant(name: &'static str, variant_index: u32, variant: &'static str,
+      len: usize) -> Self::SerializeTupleVariant;
+    serialize_map(len: Option<usize>) -> Self::SerializeMap;
+    serialize_struct(name: &'static str, len: usize) -> Self::SerializeStruct;
+    serialize_struct_variant(name: &'static str, variant_index: u32, variant: &'static str,
+      len: usize) -> Self::SerializeStructVariant;
+  }
+
+  fn serialize_none(self) -> Result<()> {
+    *self.omitted = true;
+    Ok(())
+  }
+
+  fn serialize_some<T>(self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(self.cdr)
+  }
+
+  fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    self.cdr.serialize_newtype_struct(name, value)
+  }
+
+  fn serialize_newtype_variant<T>(
+    self,
+    name: &'static str,
+    variant_index: u32,
+    variant: &'static str,
+    value: &T,
+  ) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    self.cdr.serialize_newtype_variant(name, variant_index, variant, value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::serialization::{pl_cdr_deserializer::PlCdrDeserializerAdapter, PlCdrType};
+  use crate::dds::traits::serde_adapters::DeserializerAdapter;
+  use serde::Deserialize;
+
+  #[derive(Serialize, Deserialize, PlCdrType, Debug, PartialEq, Clone)]
+  struct Sensor {
+    #[parameter_id = 0x0005]
+    name: String,
+    #[parameter_id = 0x0060]
+    reading: f64,
+    #[parameter_id = 0x8001]
+    #[serde(rename = "unit")]
+    unit_name: Option<String>,
+    #[parameter_id = 0x0062]
+    #[serde(default)]
+    flags: Vec<u8>,
+  }
+
+  fn sensor(unit_name: Option<String>) -> Sensor {
+    Sensor { name: "t".to_string(), reading: 0.5, unit_name, flags: vec![1] }
+  }
+
+  #[test]
+  fn pl_cdr_parameters() {
+    let bytes = to_bytes::<_, LittleEndian>(&sensor(None)).unwrap();
+    let expected: Vec<u8> = vec![
+      0x05, 0x00, 0x08, 0x00, // PID 0x0005, length
+      0x02, 0x00, 0x00, 0x00, b't', 0x00, 0x00, 0x00, // name + padding
+      0x60, 0x00, 0x08, 0x00, // PID 0x0060, length
+      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xe0, 0x3f, // reading, aligned to parameter start
+      0x62, 0x00, 0x08, 0x00, // PID 0x0062, length. No parameter for unit_name.
+      0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // flags + padding
+      0x01, 0x00, 0x00, 0x00, // PID_SENTINEL
+    ];
+    assert_eq!(bytes, expected);
+  }
+
+  #[test]
+  fn pl_cdr_roundtrip() {
+    for value in &[sensor(None), sensor(Some("K".to_string()))] {
+      let mut bytes = Vec::new();
+      PlCdrSerializerAdapter::<Sensor, BigEndian>::to_writer(&mut bytes, value).unwrap();
+      let encoding = PlCdrSerializerAdapter::<Sensor, BigEndian>::output_encoding();
+      assert_eq!(encoding, RepresentationIdentifier::PL_CDR_BE);
+      let result: Sensor = PlCdrDeserializerAdapter::from_bytes(&bytes, encoding).unwrap();
+      assert_eq!(&result, value);
+    }
+  }
+
+  #[test]
+  fn pl_cdr_unknown_parameters() {
+    let mut bytes: Vec<u8> = vec![
+      0x00, 0x00, 0x04, 0x00, 0, 0, 0, 0, // PID_PAD
+      0x02, 0x80, 0x04, 0x00, 1, 2, 3, 4, // unknown vendor-specific PID
+      0x05, 0x00, 0x08, 0x00, 0x02, 0x00, 0x00, 0x00, b't', 0x00, 0x00, 0x00, // name
+      0x60, 0x00, 0x08, 0x00, 0, 0, 0, 0, 0, 0, 0xe0, 0x3f, // reading
+      0x33, 0x00, 0x04, 0x00, 1, 2, 3, 4, // unknown PID
+    ];
+    let sentinel: &[u8] = &[0x01, 0x00, 0x00, 0x00];
+    bytes.extend_from_slice(sentinel);
+    let result: Sensor =
+      PlCdrDeserializerAdapter::from_bytes(&bytes, RepresentationIdentifier::PL_CDR_LE).unwrap();
+    assert_eq!(result, Sensor { name: "t".to_string(), reading: 0.5, unit_name: None,
+      flags: vec![] });
+
+    // Unknown parameters that must be understood cannot be skipped.
+    bytes.truncate(bytes.len() - sentinel.len());
+    bytes.extend_from_slice(&[0x33, 0x40, 0x00, 0x00]);
+    bytes.extend_from_slice(sentinel);
+    assert!(PlCdrDeserializerAdapter::<Sensor>::from_bytes(
+      &bytes, RepresentationIdentifier::PL_CDR_LE).is_err());
+  }
+}
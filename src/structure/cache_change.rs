@@ -1,5 +1,6 @@
 use crate::structure::guid::GUID;
 use crate::structure::sequence_number::SequenceNumber;
+use crate::structure::inline_qos::SampleIdentity;
 use crate::messages::submessages::submessage_elements::serialized_payload::SerializedPayload;
 use crate::dds::ddsdata::DDSData;
 
@@ -17,6 +18,7 @@ pub struct CacheChange {
   pub sequence_number: SequenceNumber,
   pub data_value: Option<SerializedPayload>,
   pub key: u128,
+  pub related_sample_identity: Option<SampleIdentity>,
   //pub inline_qos: ParameterList,
 
   //stps_chage_for_reader : RTPSChangeForReader
@@ -46,12 +48,12 @@ impl CacheChange {
     sequence_number: SequenceNumber,
     data_value: Option<DDSData>,  //TODO: Why is this an Option? It seems that all callers pass Some.
   ) -> CacheChange {
-    let (key, data_value) = match data_value {
-      Some(d) => (d.value_key_hash, d.value()),
-      None => (0, None),
+    let (key, data_value, related_sample_identity) = match data_value {
+      Some(d) => (d.value_key_hash, d.value(), d.related_sample_identity),
+      None => (0, None, None),
     };
 
-    CacheChange { kind, writer_guid, sequence_number, data_value, key }
+    CacheChange { kind, writer_guid, sequence_number, data_value, key, related_sample_identity }
   }
 }
 
@@ -13,6 +13,12 @@ pub struct GuidPrefix {
   pub entityKey: [u8; 12],
 }
 
+impl Key for GuidPrefix {
+  fn is_fixed_size() -> bool {
+    true
+  }
+}
+
 impl GuidPrefix {
   pub const GUIDPREFIX_UNKNOWN: GuidPrefix = GuidPrefix {
     entityKey: [0x00; 12],
@@ -337,7 +343,13 @@ impl GUID {
 
 }
 
-impl Key for GUID {}
+// 16 bytes, so the key hash is the GUID itself, as RTPS 9.6.3.8 requires for
+// the builtin topics.
+impl Key for GUID {
+  fn is_fixed_size() -> bool {
+    true
+  }
+}
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct GUIDData {
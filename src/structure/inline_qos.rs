@@ -11,6 +11,9 @@ use crate::{
   },
 };
 use serde::{Serialize, Deserialize};
+use speedy::{Readable, Writable};
+
+use super::{guid::GUID, sequence_number::SequenceNumber};
 
 #[derive(Debug, BitFlags, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
@@ -105,6 +108,25 @@ impl KeyHash {
   }
 }
 
+/// Identity of a sample: the GUID of the DataWriter that wrote it and the
+/// sequence number that the DataWriter gave to it. DDS-RPC 7.8.2
+///
+/// A DataWriter can relate a sample to another one, e.g. a service reply to
+/// its request, by sending the identity of the other sample in inline QoS
+/// parameter PID_RELATED_SAMPLE_IDENTITY.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Readable, Writable)]
+pub struct SampleIdentity {
+  pub writer_guid: GUID,
+  pub sequence_number: SequenceNumber,
+}
+
+impl SampleIdentity {
+  pub const UNKNOWN: SampleIdentity = SampleIdentity {
+    writer_guid: GUID::GUID_UNKNOWN,
+    sequence_number: SequenceNumber::SEQUENCENUMBER_UNKNOWN,
+  };
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -15,6 +15,9 @@ impl LocatorKind {
   pub const LOCATOR_KIND_RESERVED: LocatorKind = LocatorKind { value: 0 };
   pub const LOCATOR_KIND_UDPv4: LocatorKind = LocatorKind { value: 1 };
   pub const LOCATOR_KIND_UDPv6: LocatorKind = LocatorKind { value: 2 };
+  // Locator kinds from the DDS-TCP PSM
+  pub const LOCATOR_KIND_TCPv4: LocatorKind = LocatorKind { value: 4 };
+  pub const LOCATOR_KIND_TCPv6: LocatorKind = LocatorKind { value: 8 };
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize, PartialOrd, Ord)]
@@ -44,6 +47,24 @@ impl Locator {
     self.kind == LocatorKind::LOCATOR_KIND_UDPv4 
     || self.kind == LocatorKind::LOCATOR_KIND_UDPv6
   }
+
+  pub fn isTCP(&self) -> bool {
+    self.kind == LocatorKind::LOCATOR_KIND_TCPv4 
+    || self.kind == LocatorKind::LOCATOR_KIND_TCPv6
+  }
+
+  /// Same as `Locator::from(SocketAddr)`, but produces a TCP locator kind.
+  pub fn from_tcp_socket_address(socket_address: SocketAddr) -> Locator {
+    let udp = Locator::from(socket_address);
+    Locator {
+      kind: match udp.kind {
+        LocatorKind::LOCATOR_KIND_UDPv4 => LocatorKind::LOCATOR_KIND_TCPv4,
+        LocatorKind::LOCATOR_KIND_UDPv6 => LocatorKind::LOCATOR_KIND_TCPv6,
+        other => other,
+      },
+      ..udp
+    }
+  }
 }
 
 impl Default for Locator {
@@ -74,7 +95,7 @@ impl From<SocketAddr> for Locator {
 impl From<Locator> for SocketAddr {
   fn from(locator: Locator) -> Self {
     match locator.kind {
-      LocatorKind::LOCATOR_KIND_UDPv4 => SocketAddr::new(
+      LocatorKind::LOCATOR_KIND_UDPv4 | LocatorKind::LOCATOR_KIND_TCPv4 => SocketAddr::new(
         IpAddr::V4(Ipv4Addr::new(
           locator.address[12],
           locator.address[13],
@@ -83,7 +104,7 @@ impl From<Locator> for SocketAddr {
         )),
         locator.port as u16,
       ),
-      LocatorKind::LOCATOR_KIND_UDPv6 => SocketAddr::new(
+      LocatorKind::LOCATOR_KIND_UDPv6 | LocatorKind::LOCATOR_KIND_TCPv6 => SocketAddr::new(
         IpAddr::V6(Ipv6Addr::from(locator.address)),
         locator.port as u16,
       ),
@@ -162,9 +183,24 @@ mod tests {
         LocatorKind::LOCATOR_KIND_UDPv6,
         le = [0x02, 0x00, 0x00, 0x00],
         be = [0x00, 0x00, 0x00, 0x02]
+    },
+    {
+        locator_kind_tcpv4,
+        LocatorKind::LOCATOR_KIND_TCPv4,
+        le = [0x04, 0x00, 0x00, 0x00],
+        be = [0x00, 0x00, 0x00, 0x04]
     }
   );
 
+  #[test]
+  fn tcp_locator_round_trip() {
+    let sa = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 7)), 7650);
+    let loc = Locator::from_tcp_socket_address(sa);
+    assert_eq!(loc.kind, LocatorKind::LOCATOR_KIND_TCPv4);
+    assert!(loc.isTCP() && !loc.isUDP());
+    assert_eq!(SocketAddr::from(loc), sa);
+  }
+
   #[test]
   fn verify_locator_address_invalid() {
     assert_eq!([0x00; 16], Locator::LOCATOR_ADDRESS_INVALID);
@@ -61,6 +61,17 @@ impl ParameterId {
   pub const PID_ENTITY_NAME: ParameterId = ParameterId { value: 0x0062 };
   pub const PID_KEY_HASH: ParameterId = ParameterId { value: 0x0070 };
   pub const PID_STATUS_INFO: ParameterId = ParameterId { value: 0x0071 };
+  // XTypes 7.6.3.1.1
+  pub const PID_DATA_REPRESENTATION: ParameterId = ParameterId { value: 0x0073 };
+  pub const PID_TYPE_CONSISTENCY_ENFORCEMENT: ParameterId = ParameterId { value: 0x0074 };
+  pub const PID_TYPE_INFORMATION: ParameterId = ParameterId { value: 0x0075 };
+  // DDS-RPC 7.8.2: the sample that this sample relates to, e.g. the request
+  // of a reply. Fast DDS also uses its older vendor-specific id.
+  pub const PID_RELATED_SAMPLE_IDENTITY: ParameterId = ParameterId { value: 0x0083 };
+  pub const PID_CUSTOM_RELATED_SAMPLE_IDENTITY: ParameterId = ParameterId { value: 0x800f };
+  // Vendor specific: the TypeObjects referred to by PID_TYPE_INFORMATION,
+  // so that types can be checked without the TypeLookup service.
+  pub const PID_RUSTDDS_TYPE_OBJECTS: ParameterId = ParameterId { value: 0x8075 };
 }
 
 #[cfg(test)]
@@ -60,13 +60,18 @@ impl Timestamp {
     }
   }
 
-  fn from_nanos(nanos_since_unix_epoch: u64) -> Timestamp {
+  pub(crate) fn from_nanos(nanos_since_unix_epoch: u64) -> Timestamp {
     Timestamp {
       seconds: (nanos_since_unix_epoch / 1_000_000_000) as u32,
       fraction: (((nanos_since_unix_epoch % 1_000_000_000) << 32) / 1_000_000_000) as u32,
     }
   }
 
+  // rounded, so that conversion back and forth keeps the value
+  pub(crate) fn to_nanos(&self) -> u64 {
+    ((self.to_ticks() as u128 * 1_000_000_000 + (1 << 31)) >> 32) as u64
+  }
+
   pub fn duration_since(&self, since: Timestamp) -> Duration {
     *self - since
   }
@@ -99,7 +99,7 @@ use crate::{
     },
   },
   serialization::{
-    Message, cdr_serializer::to_bytes, pl_cdr_deserializer::PlCdrDeserializerAdapter, SubMessage,
+    Message, cdr_serializer::to_bytes, builtin_data_deserializer::BuiltinPlCdrDeserializerAdapter, SubMessage,
     SubmessageBody,
   },
   structure::{
@@ -146,7 +146,7 @@ pub(crate) fn spdp_participant_msg_mod(port: u16) -> Message {
       SubmessageBody::Entity(v) => match v {
         EntitySubmessage::Data(d, _) => {
           let mut participant_data: SPDPDiscoveredParticipantData =
-            PlCdrDeserializerAdapter::<SPDPDiscoveredParticipantData>::from_bytes(
+            BuiltinPlCdrDeserializerAdapter::<SPDPDiscoveredParticipantData>::from_bytes(
               &d.serialized_payload.as_ref().unwrap().value,
               RepresentationIdentifier::PL_CDR_LE,
             )
@@ -186,7 +186,7 @@ pub(crate) fn spdp_participant_data() -> Option<SPDPDiscoveredParticipantData> {
       SubmessageBody::Entity(v) => match v {
         EntitySubmessage::Data(d, _) => {
           let particiapant_data: SPDPDiscoveredParticipantData =
-            PlCdrDeserializerAdapter::from_bytes(
+            BuiltinPlCdrDeserializerAdapter::from_bytes(
               &d.serialized_payload.as_ref().unwrap().value,
               RepresentationIdentifier::PL_CDR_LE,
             )
@@ -298,6 +298,8 @@ pub(crate) fn publication_builtin_topic_data() -> Option<PublicationBuiltinTopic
       coherent_access: true,
       ordered_access: false,
     }),
+    data_representation: None,
+    type_information: None,
   };
 
   Some(pub_topic_data)